@@ -0,0 +1,960 @@
+// Benchmarks for the IPFS pallet.
+//
+// Extrinsics that queue a command are benchmarked against a queue already holding `q` commands,
+// since the connection, DHT and pubsub queues are decoded and re-encoded as a whole; those that
+// carry data are benchmarked over its length `l`.
+
+use super::*;
+
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::traits::EnsureOrigin;
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+use sp_std::vec;
+
+const MAX_BYTES: u32 = 1_000_000;
+const MAX_ITEMS: u32 = 1_000;
+
+// the whitelisted caller, with enough free balance for any deposit
+fn funded_caller<T: Config>() -> T::AccountId {
+	let caller: T::AccountId = whitelisted_caller();
+	T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
+	caller
+}
+
+// the whitelisted caller, allowed to report offchain results
+fn reporter<T: Config>() -> T::AccountId {
+	let reporter = funded_caller::<T>();
+	<Reporters<T>>::put(vec![reporter.clone()]);
+	reporter
+}
+
+// the multiaddr of the `i`th peer
+fn peer(i: u32) -> OpaqueMultiaddr {
+	let mut addr = b"/ip4/127.0.0.1/tcp/4001/p2p/".to_vec();
+	addr.extend_from_slice(&i.to_be_bytes());
+	OpaqueMultiaddr(addr)
+}
+
+// register content owned by `owner`, reserving `deposit` from it
+fn register_content<T: Config>(
+	owner: &T::AccountId,
+	cid: &[u8],
+	deposit: BalanceOf<T>,
+) -> Result<(), &'static str> {
+	T::Currency::reserve(owner, deposit)?;
+	<ContentRegistry<T>>::insert(
+		cid,
+		ContentInfo {
+			owner: owner.clone(),
+			added_at: Zero::zero(),
+			pin_status: PinStatus::Pinned,
+			pin_mode: PinMode::Direct,
+			size: Some(MAX_BYTES as u64),
+			expires_at: None,
+			schema: None,
+			deposit,
+		},
+	);
+	Ok(())
+}
+
+// the funded caller, owning service 0 and so administering its namespace
+fn service_owner<T: Config>() -> Result<T::AccountId, &'static str> {
+	let caller = funded_caller::<T>();
+	Pallet::<T>::register_service(
+		RawOrigin::Signed(caller.clone()).into(),
+		b"service".to_vec(),
+		cid::raw_v1(b"metadata"),
+		MAX_BYTES as u64,
+	)?;
+	Ok(caller)
+}
+
+// bind a name in the namespace of service 0 to content `owner` registered, returning the name
+fn named_content<T: Config>(owner: &T::AccountId) -> Result<Vec<u8>, &'static str> {
+	<MemberQuotas<T>>::insert(0, owner, MemberQuota { granted: MAX_BYTES as u64, used: 0 });
+	let cid = cid::raw_v1(b"named");
+	register_content::<T>(owner, &cid, Zero::zero())?;
+	let name = b"name".to_vec();
+	Pallet::<T>::bind_name(RawOrigin::Signed(owner.clone()).into(), 0, name.clone(), cid, vec![])?;
+	Ok(name)
+}
+
+// start an upload of `chunks` chunks owned by `owner`, with every chunk received
+fn complete_upload<T: Config>(owner: &T::AccountId, chunks: u32) -> Result<UploadId, &'static str> {
+	let id = <NextUploadId<T>>::mutate(|id| {
+		let current = *id;
+		*id += 1;
+		current
+	});
+	T::Currency::reserve(owner, Pallet::<T>::deposit_for(chunks as u64))?;
+	for index in 0..chunks {
+		<UploadChunks<T>>::insert(id, index, true);
+	}
+	<Uploads<T>>::insert(
+		id,
+		UploadInfo {
+			owner: owner.clone(),
+			total_chunks: chunks,
+			content_hash: T::Hashing::hash(b"upload"),
+			received_chunks: chunks,
+			size: chunks as u64,
+			class: Some(ContentClass::Binary),
+		},
+	);
+	Ok(id)
+}
+
+fn fill_connection_queue<T: Config>(q: u32) {
+	<ConnectionQueue<T>>::put(
+		(0..q).map(|i| ConnectionCommand::DisconnectFrom(peer(i))).collect::<Vec<_>>(),
+	);
+}
+
+fn fill_dht_queue<T: Config>(q: u32) {
+	<DhtQueue<T>>::put((0..q).map(|i| DhtCommand::FindPeer(i.encode())).collect::<Vec<_>>());
+}
+
+fn fill_pubsub_queue<T: Config>(q: u32) {
+	<PubsubQueue<T>>::put((0..q).map(|i| PubsubCommand::Subscribe(i.encode())).collect::<Vec<_>>());
+}
+
+benchmarks! {
+	ipfs_connect {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_connection_queue::<T>(q);
+		let addr = peer(q).0;
+	}: _(RawOrigin::Signed(caller), addr.clone())
+	verify {
+		assert!(Pallet::<T>::is_desired_peer(&addr));
+	}
+
+	ipfs_disconnect {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_connection_queue::<T>(q);
+		let addr = peer(q).0;
+		<DesiredPeers<T>>::insert(&addr, true);
+	}: _(RawOrigin::Signed(caller), addr.clone())
+	verify {
+		assert!(!Pallet::<T>::is_desired_peer(&addr));
+	}
+
+	ipfs_add_bytes {
+		let l in 1 .. MAX_BYTES;
+		let caller = funded_caller::<T>();
+	}: _(RawOrigin::Signed(caller.clone()), vec![0u8; l as usize], None, None, false)
+	verify {
+		assert_eq!(T::Currency::reserved_balance(&caller), Pallet::<T>::deposit_for(l as u64));
+	}
+
+	ipfs_add_bytes_with_lease {
+		let l in 1 .. MAX_BYTES;
+		let caller = funded_caller::<T>();
+	}: _(RawOrigin::Signed(caller.clone()), vec![0u8; l as usize], 100)
+	verify {
+		assert_eq!(T::Currency::reserved_balance(&caller), Pallet::<T>::deposit_for(l as u64));
+	}
+
+	ipfs_begin_upload {
+		let caller = funded_caller::<T>();
+	}: _(RawOrigin::Signed(caller), MAX_ITEMS, T::Hashing::hash(b"upload"))
+	verify {
+		assert!(Pallet::<T>::upload(0).is_some());
+	}
+
+	ipfs_upload_chunk {
+		let l in 1 .. MAX_BYTES;
+		let caller = funded_caller::<T>();
+		Pallet::<T>::ipfs_begin_upload(
+			RawOrigin::Signed(caller.clone()).into(),
+			1,
+			T::Hashing::hash(b"upload"),
+		)?;
+	}: _(RawOrigin::Signed(caller), 0, 0, vec![0u8; l as usize])
+	verify {
+		assert!(Pallet::<T>::has_chunk(0, 0));
+	}
+
+	ipfs_finalize_upload {
+		let c in 1 .. MAX_ITEMS;
+		let caller = funded_caller::<T>();
+		let id = complete_upload::<T>(&caller, c)?;
+	}: _(RawOrigin::Signed(caller), id)
+	verify {
+		assert!(Pallet::<T>::upload(id).is_none());
+	}
+
+	ipfs_cancel_upload {
+		let c in 1 .. MAX_ITEMS;
+		let caller = funded_caller::<T>();
+		let id = complete_upload::<T>(&caller, c)?;
+	}: _(RawOrigin::Signed(caller.clone()), id)
+	verify {
+		assert!(T::Currency::reserved_balance(&caller).is_zero());
+	}
+
+	extend_lease {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"leased");
+		let expires_at: T::BlockNumber = 10u32.into();
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		<ContentRegistry<T>>::mutate(&cid, |content| {
+			if let Some(content) = content {
+				content.expires_at = Some(expires_at);
+			}
+		});
+		<LeaseExpiry<T>>::append(expires_at, &cid);
+	}: _(RawOrigin::Signed(caller), cid.clone(), 100)
+	verify {
+		assert_eq!(Pallet::<T>::content(&cid).and_then(|c| c.expires_at), Some(110u32.into()));
+	}
+
+	ipfs_cat_bytes {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"cat");
+	}: _(RawOrigin::Signed(caller), cid, None, None)
+	verify {
+		assert_eq!(Pallet::<T>::data_queue_tail(), 1);
+	}
+
+	ipfs_remove_block {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"block");
+		register_content::<T>(&caller, &cid, Pallet::<T>::deposit_for(MAX_BYTES as u64))?;
+	}: _(RawOrigin::Signed(caller.clone()), cid.clone())
+	verify {
+		assert!(Pallet::<T>::content(&cid).is_none());
+		assert!(T::Currency::reserved_balance(&caller).is_zero());
+	}
+
+	ipfs_insert_pin {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"pin");
+	}: _(RawOrigin::Signed(caller), cid.clone(), true)
+	verify {
+		assert!(Pallet::<T>::content(&cid).is_some());
+	}
+
+	ipfs_remove_pin {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"pin");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+	}: _(RawOrigin::Signed(caller), cid.clone(), true)
+	verify {
+		assert_eq!(Pallet::<T>::content(&cid).map(|c| c.pin_status), Some(PinStatus::Unpinned));
+	}
+
+	ipfs_dht_find_peer {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_dht_queue::<T>(q);
+	}: _(RawOrigin::Signed(caller), b"12D3KooWPeer".to_vec())
+	verify {
+		assert_eq!(<DhtQueue<T>>::get().len() as u32, q + 1);
+	}
+
+	ipfs_dht_find_providers {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_dht_queue::<T>(q);
+	}: _(RawOrigin::Signed(caller), cid::raw_v1(b"providers"))
+	verify {
+		assert_eq!(<DhtQueue<T>>::get().len() as u32, q + 1);
+	}
+
+	ipfs_dht_provide {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_dht_queue::<T>(q);
+	}: _(RawOrigin::Signed(caller), cid::raw_v1(b"provide"))
+	verify {
+		assert_eq!(<DhtQueue<T>>::get().len() as u32, q + 1);
+	}
+
+	ipfs_pubsub_subscribe {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_pubsub_queue::<T>(q);
+		let topic = b"topic".to_vec();
+	}: _(RawOrigin::Signed(caller.clone()), topic.clone())
+	verify {
+		assert_eq!(Pallet::<T>::pubsub_subscribers(&topic), vec![caller]);
+	}
+
+	ipfs_pubsub_publish {
+		let l in 1 .. MAX_BYTES;
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_pubsub_queue::<T>(q);
+	}: _(RawOrigin::Signed(caller), b"topic".to_vec(), vec![0u8; l as usize])
+
+	ipfs_pubsub_unsubscribe {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		let caller = funded_caller::<T>();
+		fill_pubsub_queue::<T>(q);
+		let topic = b"topic".to_vec();
+		<PubsubTopics<T>>::put(vec![topic.clone()]);
+		<PubsubSubscribers<T>>::insert(&topic, vec![caller.clone()]);
+	}: _(RawOrigin::Signed(caller), topic.clone())
+	verify {
+		assert!(Pallet::<T>::pubsub_subscribers(&topic).is_empty());
+	}
+
+	register_schema {
+		let caller = funded_caller::<T>();
+	}: _(RawOrigin::Signed(caller), cid::raw_v1(b"schema"))
+	verify {
+		assert!(Pallet::<T>::schema(0).is_some());
+	}
+
+	set_content_schema {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"content");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		let schema = cid::raw_v1(b"schema");
+		Pallet::<T>::register_schema(RawOrigin::Signed(caller.clone()).into(), schema)?;
+	}: _(RawOrigin::Signed(caller), cid.clone(), Some(0))
+	verify {
+		assert_eq!(Pallet::<T>::content(&cid).and_then(|c| c.schema), Some(0));
+	}
+
+	register_service {
+		let caller = funded_caller::<T>();
+	}: _(RawOrigin::Signed(caller), b"service".to_vec(), cid::raw_v1(b"metadata"), MAX_BYTES as u64)
+	verify {
+		assert!(Pallet::<T>::service(0).is_some());
+	}
+
+	update_service {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::register_service(
+			RawOrigin::Signed(caller.clone()).into(),
+			b"service".to_vec(),
+			cid::raw_v1(b"metadata"),
+			MAX_BYTES as u64,
+		)?;
+		let metadata_cid = cid::raw_v1(b"updated");
+	}: _(RawOrigin::Signed(caller), 0, metadata_cid.clone())
+	verify {
+		assert_eq!(Pallet::<T>::service(0).map(|s| s.metadata_cid), Some(metadata_cid));
+	}
+
+	deregister_service {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::register_service(
+			RawOrigin::Signed(caller.clone()).into(),
+			b"service".to_vec(),
+			cid::raw_v1(b"metadata"),
+			MAX_BYTES as u64,
+		)?;
+	}: _(RawOrigin::Signed(caller), 0)
+	verify {
+		assert!(Pallet::<T>::service(0).is_none());
+	}
+
+	grant_service_quota {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::register_service(
+			RawOrigin::Signed(caller).into(),
+			b"service".to_vec(),
+			cid::raw_v1(b"metadata"),
+			MAX_BYTES as u64,
+		)?;
+	}: _(RawOrigin::Root, 0, MAX_BYTES as u64)
+	verify {
+		assert_eq!(Pallet::<T>::service(0).map(|s| s.granted_quota), Some(MAX_BYTES as u64));
+	}
+
+	service_add_bytes {
+		let l in 1 .. MAX_BYTES;
+		let caller = funded_caller::<T>();
+		Pallet::<T>::register_service(
+			RawOrigin::Signed(caller.clone()).into(),
+			b"service".to_vec(),
+			cid::raw_v1(b"metadata"),
+			MAX_BYTES as u64,
+		)?;
+		Pallet::<T>::grant_service_quota(RawOrigin::Root.into(), 0, MAX_BYTES as u64)?;
+	}: _(RawOrigin::Signed(caller), 0, vec![0u8; l as usize])
+	verify {
+		assert_eq!(Pallet::<T>::service(0).map(|s| s.used_bytes), Some(l as u64));
+	}
+
+	pause {
+	}: _(RawOrigin::Root, CommandKind::Add)
+	verify {
+		assert!(Pallet::<T>::is_paused(CommandKind::Add));
+	}
+
+	resume {
+		<Paused<T>>::insert(CommandKind::Add, true);
+	}: _(RawOrigin::Root, CommandKind::Add)
+	verify {
+		assert!(!Pallet::<T>::is_paused(CommandKind::Add));
+	}
+
+	set_pin_policy {
+	}: _(RawOrigin::Root, PinMode::Direct)
+	verify {
+		assert_eq!(Pallet::<T>::pin_policy(), PinMode::Direct);
+	}
+
+	set_feature_flags {
+	}: _(RawOrigin::Root, u32::MAX)
+
+	set_ingress_policy {
+		let policy = IngressPolicy {
+			max_sizes: vec![(ContentClass::Binary, MAX_BYTES as u64)],
+			denied_mime_types: vec![b"application/x-msdownload".to_vec()],
+			max_entropy: Some(7_900),
+		};
+	}: _(RawOrigin::Root, policy.clone())
+	verify {
+		assert_eq!(Pallet::<T>::ingress_policy(), policy);
+	}
+
+	add_reporter {
+		let r in 0 .. MAX_ITEMS;
+		let reporters: Vec<T::AccountId> = (0..r).map(|i| account("reporter", i, 0)).collect();
+		<Reporters<T>>::put(reporters);
+		let who: T::AccountId = account("reporter", r, 0);
+	}: _(RawOrigin::Root, who.clone())
+	verify {
+		assert!(Pallet::<T>::reporters().contains(&who));
+	}
+
+	remove_reporter {
+		let r in 1 .. MAX_ITEMS;
+		let reporters: Vec<T::AccountId> = (0..r).map(|i| account("reporter", i, 0)).collect();
+		<Reporters<T>>::put(reporters);
+		let who: T::AccountId = account("reporter", r - 1, 0);
+	}: _(RawOrigin::Root, who.clone())
+	verify {
+		assert!(!Pallet::<T>::reporters().contains(&who));
+	}
+
+	force_connect {
+		let q in 0 .. T::MaxQueueLen::get() - 1;
+		fill_connection_queue::<T>(q);
+		let origin = T::AdminOrigin::successful_origin();
+		let addr = peer(q);
+	}: _<T::Origin>(origin, addr.clone())
+	verify {
+		assert!(Pallet::<T>::is_desired_peer(&addr.0));
+	}
+
+	force_disconnect {
+		let p in 1 .. MAX_ITEMS;
+		<BootstrapPeers<T>>::put((0..p).map(peer).collect::<Vec<_>>());
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::Origin>(origin, peer(0))
+	verify {
+		assert_eq!(Pallet::<T>::bootstrap_peers().len() as u32, p - 1);
+	}
+
+	set_bootstrap_peers {
+		let p in 0 .. MAX_ITEMS;
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::Origin>(origin, (0..p).map(peer).collect())
+	verify {
+		assert_eq!(Pallet::<T>::bootstrap_peers().len() as u32, p);
+	}
+
+	start_recovery {
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::Origin>(origin)
+	verify {
+		assert_eq!(Pallet::<T>::recovery_round(), 1);
+	}
+
+	submit_cid {
+		let reporter = reporter::<T>();
+		let owner: T::AccountId = account("owner", 0, 0);
+		let cid = cid::raw_v1(b"added");
+	}: _(RawOrigin::Signed(reporter), owner, cid.clone(), MAX_BYTES as u64, Some(100), true)
+	verify {
+		assert!(Pallet::<T>::content(&cid).is_some());
+		assert!(Pallet::<T>::is_encrypted(&cid));
+	}
+
+	import_pinset {
+		let n in 1 .. MAX_ITEMS;
+		let owner: T::AccountId = account("owner", 0, 0);
+		let cids = (0..n).map(|i| cid::raw_v1(&i.to_le_bytes())).collect::<Vec<_>>();
+	}: _(RawOrigin::Root, owner, cids)
+	verify {
+		assert_eq!(<ImportedPinCount<T>>::get(), n);
+	}
+
+	report_abandoned {
+		let l in 1 .. MAX_BYTES;
+		let reporter = reporter::<T>();
+		let cmd = DataCommand::AddBytes(reporter.clone(), vec![0u8; l as usize], None, None);
+		let error = Error::<T>::RequestTimeout.into();
+	}: _(RawOrigin::Signed(reporter), cmd, Some(0), T::MaxRetries::get(), error)
+	verify {
+		assert_eq!(<DeadLetterCount<T>>::get(), 1);
+	}
+
+	replay_dead_letter {
+		let caller = funded_caller::<T>();
+		let reporter = reporter::<T>();
+		let cmd = DataCommand::CatBytes(cid::raw_v1(b"lost"), None);
+		Pallet::<T>::report_abandoned(
+			RawOrigin::Signed(reporter).into(),
+			cmd,
+			Some(0),
+			T::MaxRetries::get(),
+			Error::<T>::RequestTimeout.into(),
+		)?;
+	}: _(RawOrigin::Root, 0)
+	verify {
+		assert_eq!(<DeadLetterCount<T>>::get(), 0);
+	}
+
+	purge_dead_letters {
+		let n in 0 .. MAX_ITEMS;
+		let reporter = reporter::<T>();
+		for request in 0..n {
+			let cmd = DataCommand::CatBytes(cid::raw_v1(&request.to_le_bytes()), None);
+			let origin = RawOrigin::Signed(reporter.clone()).into();
+			let reason = Error::<T>::RequestTimeout.into();
+			Pallet::<T>::report_abandoned(origin, cmd, Some(request as RequestId), 1, reason)?;
+		}
+	}: _(RawOrigin::Root)
+	verify {
+		assert_eq!(<DeadLetterCount<T>>::get(), 0);
+	}
+
+	ack_data_commands {
+		let reporter = reporter::<T>();
+		<DataQueueTail<T>>::put(MAX_ITEMS as u64);
+	}: _(RawOrigin::Signed(reporter), MAX_ITEMS as u64)
+	verify {
+		assert_eq!(<DataQueueAcked<T>>::get(), MAX_ITEMS as u64);
+	}
+
+	update_request_statuses {
+		let n in 1 .. MAX_STATUS_UPDATES;
+		let reporter = reporter::<T>();
+		for request in 0..n {
+			<Requests<T>>::insert(request as RequestId, RequestStatus::Processing);
+		}
+		let statuses = (0..n)
+			.map(|request| (request as RequestId, RequestStatus::Done(T::Hash::default())))
+			.collect();
+	}: _(RawOrigin::Signed(reporter), statuses)
+	verify {
+		assert_eq!(Pallet::<T>::request_status(0), Some(RequestStatus::Done(T::Hash::default())));
+	}
+
+	submit_pubsub_message {
+		let l in 1 .. MAX_BYTES;
+		let reporter = reporter::<T>();
+	}: _(RawOrigin::Signed(reporter), b"topic".to_vec(), vec![0u8; l as usize])
+
+	submit_dht_result {
+		let l in 1 .. MAX_BYTES;
+		let query = DhtCommand::GetProviders(cid::raw_v1(b"providers"));
+		let request = T::Hashing::hash_of(&query);
+		Pallet::<T>::expect_result(request);
+	}: _(RawOrigin::None, query, vec![vec![0u8; l as usize]])
+	verify {
+		assert!(Pallet::<T>::dht_result(request).is_some());
+	}
+
+	submit_node_stats {
+		let stats = NodeHealth {
+			reported_at: <frame_system::Pallet<T>>::block_number(),
+			peers: MAX_ITEMS,
+			repo_size: Some(MAX_BYTES as u64),
+			pins: Some(MAX_ITEMS as u64),
+			last_success: Some(<frame_system::Pallet<T>>::block_number()),
+		};
+	}: _(RawOrigin::None, stats.clone())
+	verify {
+		assert_eq!(Pallet::<T>::node_stats(), Some(stats));
+	}
+
+	submit_cat_result {
+		let l in 1 .. MAX_BYTES;
+		let request = T::Hashing::hash(b"request");
+		Pallet::<T>::expect_result(request);
+	}: _(RawOrigin::None, request, vec![0u8; l as usize])
+	verify {
+		assert!(Pallet::<T>::cat_result(request).is_some());
+	}
+
+	report_schema_mismatch {
+		let reporter = reporter::<T>();
+	}: _(RawOrigin::Signed(reporter), cid::raw_v1(b"content"), 0)
+
+	report_recovery {
+		let reporter = reporter::<T>();
+		<RecoveryRound<T>>::put(1);
+		let progress = RecoveryProgress { round: 1, checked: MAX_ITEMS, ..Default::default() };
+	}: _(RawOrigin::Signed(reporter.clone()), progress.clone())
+	verify {
+		assert_eq!(Pallet::<T>::recovery_of(&reporter), Some(progress));
+	}
+
+	report_incidents {
+		let n in 1 .. incidents::MAX_PER_REPORT;
+		let reporter = reporter::<T>();
+		let incident = OffchainIncident {
+			subsystem: Subsystem::Ipfs,
+			code: Error::<T>::RequestTimeout.into(),
+			correlation_id: T::Hash::default(),
+			block: 1u32.into(),
+		};
+		// the recent incidents are full, so the oldest ones have to make room
+		let recent = vec![(reporter.clone(), incident.clone()); incidents::MAX_RECENT];
+		<RecentIncidents<T>>::put(recent);
+		// and the pruned ones fill a batch to archive
+		let pruned = vec![(reporter.clone(), incident.clone()); incidents::ARCHIVE_BATCH - 1];
+		<PrunedIncidents<T>>::put(pruned);
+	}: _(RawOrigin::Signed(reporter), vec![incident; n as usize])
+	verify {
+		assert_eq!(Pallet::<T>::recent_incidents().len(), incidents::MAX_RECENT);
+		assert_eq!(Pallet::<T>::incident_archive_count(), 1);
+	}
+
+	attest_pins {
+		let n in 1 .. MAX_ATTESTATIONS;
+		let reporter = reporter::<T>();
+		let cids = (0..n).map(|i| cid::raw_v1(&i.to_be_bytes())).collect::<Vec<_>>();
+		for cid in cids.iter() {
+			register_content::<T>(&reporter, cid, Zero::zero())?;
+		}
+	}: _(RawOrigin::Signed(reporter.clone()), cids.clone())
+	verify {
+		assert!(cids.iter().all(|cid| Pallet::<T>::attested_at(cid, &reporter).is_some()));
+	}
+
+	bond_storage {
+		let reporter = reporter::<T>();
+	}: _(RawOrigin::Signed(reporter.clone()))
+	verify {
+		assert_eq!(Pallet::<T>::storage_bond(&reporter), Some(T::StorageBond::get()));
+	}
+
+	unbond_storage {
+		let reporter = reporter::<T>();
+		Pallet::<T>::bond_storage(RawOrigin::Signed(reporter.clone()).into())?;
+	}: _(RawOrigin::Signed(reporter.clone()))
+	verify {
+		assert_eq!(Pallet::<T>::storage_bond(&reporter), None);
+	}
+
+	respond_challenge {
+		let reporter = reporter::<T>();
+		let challenge = StorageChallenge {
+			cid: cid::raw_v1(b"challenged"),
+			offset: 0,
+			len: CHALLENGE_LEN,
+			seed: T::Hash::default(),
+			deadline: 1u32.into(),
+			pinners: vec![reporter.clone()],
+		};
+		<Challenges<T>>::insert(0, challenge);
+	}: _(RawOrigin::Signed(reporter.clone()), 0, T::Hash::default())
+	verify {
+		assert_eq!(Pallet::<T>::challenge_response(0, &reporter), Some(T::Hash::default()));
+	}
+
+	grant_access {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"private");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		let grantee: T::AccountId = account("grantee", 0, 0);
+	}: _(RawOrigin::Signed(caller), cid.clone(), grantee.clone(), T::MaxAccessTtl::get())
+	verify {
+		assert!(Pallet::<T>::has_access(&cid, &grantee));
+	}
+
+	revoke_access {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"private");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		let grantee: T::AccountId = account("grantee", 0, 0);
+		<AccessGrants<T>>::insert(&cid, &grantee, T::MaxAccessTtl::get());
+	}: _(RawOrigin::Signed(caller), cid.clone(), grantee.clone())
+	verify {
+		assert!(!Pallet::<T>::has_access(&cid, &grantee));
+	}
+
+	ipfs_run_gc {
+	}: _(RawOrigin::Root)
+	verify {
+		let pending = Pallet::<T>::pending_commands();
+		assert!(pending.data.iter().any(|(_, _, cmd)| *cmd == DataCommand::CollectGarbage));
+	}
+
+	evict_content {
+		let n in 1 .. MAX_EVICTIONS;
+		let reporter = reporter::<T>();
+		let cids = (0..n).map(|i| cid::raw_v1(&i.to_be_bytes())).collect::<Vec<_>>();
+		for cid in cids.iter() {
+			register_content::<T>(&reporter, cid, 1u32.into())?;
+		}
+	}: _(RawOrigin::Signed(reporter), cids.clone())
+	verify {
+		assert!(cids.iter().all(|cid| Pallet::<T>::content(cid).is_none()));
+	}
+
+	export_registry {
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::Origin>(origin)
+	verify {
+		assert!(Pallet::<T>::pending_export().is_some());
+	}
+
+	submit_registry_export {
+		let reporter = reporter::<T>();
+		<PendingExport<T>>::put(T::BlockNumber::zero());
+		let export = RegistryExport {
+			cid: cid::raw_v1(b"snapshot"),
+			entries: MAX_ITEMS,
+			exported_at: Zero::zero(),
+		};
+	}: _(RawOrigin::Signed(reporter), Zero::zero(), export)
+	verify {
+		assert!(Pallet::<T>::last_export().is_some());
+	}
+
+	import_registry {
+		let origin = T::AdminOrigin::successful_origin();
+		let export = RegistryExport {
+			cid: cid::raw_v1(b"snapshot"),
+			entries: MAX_ITEMS,
+			exported_at: Zero::zero(),
+		};
+	}: _<T::Origin>(origin, export)
+	verify {
+		assert!(Pallet::<T>::pending_import().is_some());
+	}
+
+	restore_registry {
+		let n in 1 .. snapshot::MAX_RESTORE_PAGE;
+		let reporter = reporter::<T>();
+		let export =
+			RegistryExport { cid: cid::raw_v1(b"snapshot"), entries: n, exported_at: Zero::zero() };
+		<PendingImport<T>>::put(RegistryImport { export, restored: 0 });
+		let entries = (0..n)
+			.map(|i| RegistryEntry::Content(cid::raw_v1(&i.to_be_bytes()), ContentInfo {
+				owner: reporter.clone(),
+				added_at: Zero::zero(),
+				pin_status: PinStatus::Pinned,
+				pin_mode: PinMode::Direct,
+				size: Some(MAX_BYTES as u64),
+				expires_at: Some(One::one()),
+				schema: None,
+				deposit: Zero::zero(),
+			}))
+			.collect::<Vec<_>>();
+	}: _(RawOrigin::Signed(reporter), 0, entries)
+	verify {
+		assert!(Pallet::<T>::pending_import().is_none());
+		assert_eq!(<ImportedPinCount<T>>::get(), n);
+	}
+
+	create_bucket {
+		let caller = funded_caller::<T>();
+		let name = vec![b'b'; MAX_BUCKET_NAME_LEN];
+	}: _(RawOrigin::Signed(caller.clone()), name.clone())
+	verify {
+		assert_eq!(Pallet::<T>::bucket_by_name(&caller, &name), Some(0));
+	}
+
+	grant_bucket_role {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::create_bucket(RawOrigin::Signed(caller.clone()).into(), b"bucket".to_vec())?;
+		let who: T::AccountId = account("member", 0, 0);
+	}: _(RawOrigin::Signed(caller), 0, who.clone(), BucketRole::Writer)
+	verify {
+		assert_eq!(Pallet::<T>::role_in_bucket(0, &who), Some(BucketRole::Writer));
+	}
+
+	revoke_bucket_role {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::create_bucket(RawOrigin::Signed(caller.clone()).into(), b"bucket".to_vec())?;
+		let who: T::AccountId = account("member", 0, 0);
+		<BucketRoles<T>>::insert(0, &who, BucketRole::Writer);
+	}: _(RawOrigin::Signed(caller), 0, who.clone())
+	verify {
+		assert_eq!(Pallet::<T>::role_in_bucket(0, &who), None);
+	}
+
+	add_to_bucket {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::create_bucket(RawOrigin::Signed(caller.clone()).into(), b"bucket".to_vec())?;
+		let cid = cid::raw_v1(b"shared");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+	}: _(RawOrigin::Signed(caller), 0, cid.clone())
+	verify {
+		assert_eq!(Pallet::<T>::content_bucket(&cid), Some(0));
+	}
+
+	schedule_task {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"scheduled");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+	}: _(RawOrigin::Signed(caller.clone()), cid, ScheduledCommand::Pin(true), One::one())
+	verify {
+		assert_eq!(Pallet::<T>::schedule_count(&caller), 1);
+	}
+
+	cancel_schedule {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"scheduled");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		Pallet::<T>::schedule_task(
+			RawOrigin::Signed(caller.clone()).into(),
+			cid,
+			ScheduledCommand::Cat,
+			One::one(),
+		)?;
+	}: _(RawOrigin::Signed(caller.clone()), 0)
+	verify {
+		assert_eq!(Pallet::<T>::schedule_count(&caller), 0);
+	}
+
+	ipfs_ipns_publish {
+		let caller = funded_caller::<T>();
+		let cid = cid::raw_v1(b"published");
+	}: _(RawOrigin::Signed(caller), cid)
+	verify {
+		assert_eq!(Pallet::<T>::data_queue_tail(), 1);
+	}
+
+	ipfs_ipns_resolve {
+		let caller = funded_caller::<T>();
+		let name = vec![b'k'; MAX_IPNS_NAME_LEN];
+	}: _(RawOrigin::Signed(caller), name)
+	verify {
+		assert_eq!(Pallet::<T>::data_queue_tail(), 1);
+	}
+
+	deny_cid {
+		let owner = funded_caller::<T>();
+		let cid = cid::raw_v1(b"denied");
+		register_content::<T>(&owner, &cid, Pallet::<T>::deposit_for(MAX_BYTES as u64))?;
+		let origin = T::ModerationOrigin::successful_origin();
+	}: _<T::Origin>(origin, cid.clone())
+	verify {
+		assert!(Pallet::<T>::is_denied(&cid));
+		assert!(Pallet::<T>::content(&cid).is_none());
+		assert!(T::Currency::reserved_balance(&owner).is_zero());
+	}
+
+	allow_cid {
+		let cid = cid::raw_v1(b"denied");
+		<Denylist<T>>::insert(&cid, true);
+		let origin = T::ModerationOrigin::successful_origin();
+	}: _<T::Origin>(origin, cid.clone())
+	verify {
+		assert!(!Pallet::<T>::is_denied(&cid));
+	}
+
+	bump_request {
+		let caller = funded_caller::<T>();
+		Pallet::<T>::ipfs_cat_bytes(
+			RawOrigin::Signed(caller.clone()).into(),
+			cid::raw_v1(b"bumped"),
+			None,
+			Some(Priority::Low),
+		)?;
+	}: _(RawOrigin::Signed(caller), 0)
+	verify {
+		assert!(Pallet::<T>::bumped_at(0).is_some());
+	}
+
+	appeal_denial {
+		let e in 1 .. MAX_APPEAL_EVIDENCE;
+		let owner = funded_caller::<T>();
+		let cid = cid::raw_v1(b"denied");
+		<Denylist<T>>::insert(&cid, true);
+		<DeniedOwners<T>>::insert(&cid, &owner);
+		let evidence = (0..e).map(|i| cid::raw_v1(&i.to_le_bytes())).collect::<Vec<_>>();
+	}: _(RawOrigin::Signed(owner), cid.clone(), evidence)
+	verify {
+		assert!(Pallet::<T>::appeal(&cid).is_some());
+	}
+
+	rule_appeal {
+		let owner = funded_caller::<T>();
+		let cid = cid::raw_v1(b"denied");
+		<Denylist<T>>::insert(&cid, true);
+		<DeniedOwners<T>>::insert(&cid, &owner);
+		Pallet::<T>::appeal_denial(
+			RawOrigin::Signed(owner).into(),
+			cid.clone(),
+			vec![cid::raw_v1(b"evidence")],
+		)?;
+		let origin = T::AppealOrigin::successful_origin();
+	}: _<T::Origin>(origin, cid.clone(), true)
+	verify {
+		assert!(!Pallet::<T>::is_denied(&cid));
+	}
+
+	grant_namespace_role {
+		let caller = service_owner::<T>()?;
+		let who: T::AccountId = account("member", 0, 0);
+	}: _(RawOrigin::Signed(caller), 0, who.clone(), NamespaceRole::Writer)
+	verify {
+		assert_eq!(Pallet::<T>::role_in_namespace(0, &who), Some(NamespaceRole::Writer));
+	}
+
+	revoke_namespace_role {
+		let caller = service_owner::<T>()?;
+		let who: T::AccountId = account("member", 0, 0);
+		<NamespaceRoles<T>>::insert(0, &who, NamespaceRole::Writer);
+	}: _(RawOrigin::Signed(caller), 0, who.clone())
+	verify {
+		assert_eq!(Pallet::<T>::role_in_namespace(0, &who), None);
+	}
+
+	set_member_quota {
+		let caller = service_owner::<T>()?;
+		let who: T::AccountId = account("member", 0, 0);
+		<NamespaceRoles<T>>::insert(0, &who, NamespaceRole::Admin);
+	}: _(RawOrigin::Signed(caller), 0, who.clone(), MAX_BYTES as u64)
+	verify {
+		assert_eq!(Pallet::<T>::member_quota(0, &who).granted, MAX_BYTES as u64);
+	}
+
+	bind_name {
+		let caller = service_owner::<T>()?;
+		<MemberQuotas<T>>::insert(0, &caller, MemberQuota { granted: MAX_BYTES as u64, used: 0 });
+		let cid = cid::raw_v1(b"named");
+		register_content::<T>(&caller, &cid, Zero::zero())?;
+		let name = vec![b'n'; MAX_NAMESPACE_NAME_LEN];
+		let tags = vec![vec![b't'; MAX_TAG_LEN]; MAX_TAGS];
+	}: _(RawOrigin::Signed(caller), 0, name.clone(), cid, tags)
+	verify {
+		assert!(Pallet::<T>::namespace_entry(0, &name).is_some());
+	}
+
+	unbind_name {
+		let caller = service_owner::<T>()?;
+		let name = named_content::<T>(&caller)?;
+	}: _(RawOrigin::Signed(caller.clone()), 0, name.clone())
+	verify {
+		assert!(Pallet::<T>::namespace_entry(0, &name).is_none());
+		assert_eq!(Pallet::<T>::member_quota(0, &caller).used, 0);
+	}
+
+	tag_name {
+		let caller = service_owner::<T>()?;
+		let name = named_content::<T>(&caller)?;
+		let tags = vec![vec![b't'; MAX_TAG_LEN]; MAX_TAGS];
+	}: _(RawOrigin::Signed(caller), 0, name.clone(), tags.clone())
+	verify {
+		assert_eq!(Pallet::<T>::namespace_entry(0, &name).map(|entry| entry.tags), Some(tags));
+	}
+
+	impl_benchmark_test_suite!(Pallet, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -0,0 +1,118 @@
+// A separate block-length quota for data-carrying extrinsics.
+//
+// Adds, chunk uploads and pubsub publications carry their payload in the extrinsic, so a burst of
+// them can fill whole blocks and delay ordinary transfers and M-Pesa settlements. `CheckBlockData`
+// caps the bytes such extrinsics may take up in a block at `MaxBlockDataLen`; further ones stay in
+// the transaction pool until a later block has room, while other transactions keep the rest of
+// the block.
+
+use codec::{Decode, Encode};
+use frame_support::traits::{Get, IsSubType};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{DispatchInfoOf, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt, marker::PhantomData};
+
+use crate::{BlockDataLen, Call, Config};
+
+/// The number of payload bytes `call` carries, if it counts against the block's data quota.
+///
+/// The offchain workers' result callbacks are not counted: they are operational and use the block
+/// space reserved for operational extrinsics.
+pub fn data_len_of<T: Config>(call: &Call<T>) -> Option<u32> {
+	let len = match call {
+		Call::ipfs_add_bytes { data, .. }
+		| Call::ipfs_add_bytes_with_lease { data, .. }
+		| Call::service_add_bytes { data, .. }
+		| Call::ipfs_pubsub_publish { data, .. } => data.len(),
+		Call::ipfs_upload_chunk { bytes, .. } => bytes.len(),
+		Call::ipfs_batch { commands } => {
+			match commands.iter().map(|command| command.data_len()).sum() {
+				0 => return None,
+				len => len,
+			}
+		},
+		_ => return None,
+	};
+	Some(len as u32)
+}
+
+/// Keeps the data-carrying extrinsics of a block within `MaxBlockDataLen` bytes.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckBlockData<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckBlockData<T> {
+	/// Create a new `CheckBlockData` extension.
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckBlockData<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckBlockData<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckBlockData")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckBlockData<T>
+where
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckBlockData";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	// the pool only rejects extrinsics that could never fit; whether there is room left is up to
+	// the block they end up in
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		if let Some(len) = call.is_sub_type().and_then(data_len_of::<T>) {
+			if len > T::MaxBlockDataLen::get() {
+				return InvalidTransaction::ExhaustsResources.into();
+			}
+		}
+		Ok(ValidTransaction::default())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<(), TransactionValidityError> {
+		self.validate(who, call, info, len)?;
+
+		if let Some(len) = call.is_sub_type().and_then(data_len_of::<T>) {
+			// `ExhaustsResources` makes the block author skip the extrinsic without dropping it
+			let used = <BlockDataLen<T>>::get().saturating_add(len);
+			if used > T::MaxBlockDataLen::get() {
+				return Err(InvalidTransaction::ExhaustsResources.into());
+			}
+			<BlockDataLen<T>>::put(used);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,155 @@
+// CID helpers.
+//
+// Commands carrying a malformed CID would only fail in the offchain worker, where the caller never
+// sees the error, so extrinsics check the structure up front: CIDv0 (base58btc sha2-256 multihash)
+// or CIDv1 (multibase prefix, version, codec and multihash).
+
+use sp_std::{vec, vec::Vec};
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// The multihash code and digest length of sha2-256.
+const SHA2_256: u64 = 0x12;
+const SHA2_256_LEN: u64 = 32;
+
+/// The size of the chunks IPFS splits added data into: data up to this size is stored as a single
+/// block, under `raw_v1(data)`.
+pub const MAX_BLOCK_LEN: usize = 256 * 1024;
+
+/// Whether `cid` is a well-formed CIDv0 or CIDv1 string.
+pub fn is_valid(cid: &[u8]) -> bool {
+	match cid {
+		// CIDv0: a bare base58btc sha2-256 multihash, always 46 characters starting with "Qm"
+		[b'Q', b'm', ..] if cid.len() == 46 => decode_base58(cid).map_or(false, |bytes| {
+			bytes.len() == 34 && bytes[0] == SHA2_256 as u8 && bytes[1] == SHA2_256_LEN as u8
+		}),
+		[prefix, rest @ ..] => {
+			let bytes = match prefix {
+				b'b' => decode_base32(rest, false),
+				b'B' => decode_base32(rest, true),
+				b'z' => decode_base58(rest),
+				b'f' | b'F' => decode_base16(rest),
+				_ => None,
+			};
+			bytes.map_or(false, |bytes| is_valid_v1(&bytes))
+		},
+		[] => false,
+	}
+}
+
+/// The CIDv1 (raw codec, sha2-256, base32 multibase) IPFS would assign to `data` as a single
+/// block.
+pub fn raw_v1(data: &[u8]) -> Vec<u8> {
+	let bytes = raw_v1_binary(data);
+
+	let mut cid = vec![b'b'];
+	let (mut buffer, mut bits) = (0u16, 0u8);
+	for byte in bytes {
+		buffer = (buffer << 8) | byte as u16;
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			cid.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize]);
+		}
+	}
+	if bits > 0 {
+		cid.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize]);
+	}
+
+	cid
+}
+
+/// The binary form of `raw_v1(data)`, as CAR files and other IPLD encodings carry it.
+pub fn raw_v1_binary(data: &[u8]) -> Vec<u8> {
+	let mut bytes = vec![0x01, 0x55, 0x12, 0x20];
+	bytes.extend_from_slice(&sp_io::hashing::sha2_256(data));
+	bytes
+}
+
+// version 1, a codec, then a multihash whose digest length matches the rest of the bytes
+fn is_valid_v1(bytes: &[u8]) -> bool {
+	let mut rest = bytes;
+	let mut next = || {
+		let (value, len) = read_varint(rest)?;
+		rest = &rest[len..];
+		Some(value)
+	};
+
+	match (next(), next(), next(), next()) {
+		(Some(1), Some(_codec), Some(_hash), Some(digest_len)) => rest.len() as u64 == digest_len,
+		_ => false,
+	}
+}
+
+/// Read an unsigned LEB128 varint of at most 9 bytes, as used by multiformats; returns the value
+/// and the number of bytes read.
+pub fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+	let mut value = 0u64;
+	for (i, byte) in bytes.iter().enumerate().take(9) {
+		value |= ((byte & 0x7f) as u64) << (7 * i);
+		if byte & 0x80 == 0 {
+			return Some((value, i + 1));
+		}
+	}
+	None
+}
+
+/// Append `value` to `bytes` as an unsigned LEB128 varint.
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+	while value >= 0x80 {
+		bytes.push((value as u8 & 0x7f) | 0x80);
+		value >>= 7;
+	}
+	bytes.push(value as u8);
+}
+
+fn decode_base32(input: &[u8], upper: bool) -> Option<Vec<u8>> {
+	let mut bytes = Vec::with_capacity(input.len() * 5 / 8);
+	let (mut buffer, mut bits) = (0u16, 0u8);
+	for c in input {
+		let c = if upper { c.to_ascii_lowercase() } else { *c };
+		let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u16;
+		buffer = (buffer << 5) | value;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			bytes.push((buffer >> bits) as u8);
+		}
+	}
+	Some(bytes)
+}
+
+fn decode_base58(input: &[u8]) -> Option<Vec<u8>> {
+	let mut bytes: Vec<u8> = Vec::with_capacity(input.len());
+	for c in input {
+		let mut carry = BASE58_ALPHABET.iter().position(|a| a == c)? as u32;
+		for byte in bytes.iter_mut().rev() {
+			carry += (*byte as u32) * 58;
+			*byte = carry as u8;
+			carry >>= 8;
+		}
+		while carry > 0 {
+			bytes.insert(0, carry as u8);
+			carry >>= 8;
+		}
+	}
+	// leading '1's encode leading zero bytes
+	let zeros = input.iter().take_while(|c| **c == b'1').count();
+	let mut decoded = vec![0u8; zeros];
+	decoded.extend(bytes);
+	Some(decoded)
+}
+
+fn decode_base16(input: &[u8]) -> Option<Vec<u8>> {
+	if input.len() % 2 != 0 {
+		return None;
+	}
+	input
+		.chunks(2)
+		.map(|pair| {
+			let digit = |c: u8| (c as char).to_digit(16);
+			Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8)
+		})
+		.collect()
+}
@@ -0,0 +1,72 @@
+// Encryption of private content at rest.
+//
+// Content added with `encrypt` set is encrypted by the offchain worker before it is added to
+// IPFS, with a symmetric key the node operators configure in the persistent off-chain storage of
+// every node under `ipfs::encryption_key` (e.g. through the `offchain_localStorageSet` RPC). The
+// scheme is deterministic: the nonce is a MAC of the plaintext, as in SIV modes, so all nodes
+// arrive at the same Cid for the same content, at the price of revealing when two encrypted adds
+// carry the same data.
+//
+// The data still reaches the chain in clear as part of the `ipfs_add_bytes` extrinsic; callers
+// that need to keep it from the chain's history have to encrypt it themselves.
+
+use codec::Encode;
+use sp_io::hashing::blake2_256;
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_std::vec::Vec;
+
+/// The persistent off-chain storage key of the node's 32-byte encryption key.
+pub const KEY: &[u8] = b"ipfs::encryption_key";
+// The prefix of the keys decrypted cat results are kept under, followed by the request hash.
+const DECRYPTED_PREFIX: &[u8] = b"ipfs::decrypted::";
+const NONCE_LEN: usize = 32;
+
+/// The encryption key configured on this node, if any.
+pub fn key() -> Option<[u8; 32]> {
+	StorageValueRef::persistent(KEY).get::<[u8; 32]>().ok().flatten()
+}
+
+/// Encrypt `data`: the nonce followed by the data XORed with the keystream.
+pub fn encrypt(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+	let nonce = mac(key, data);
+	let mut ciphertext = Vec::with_capacity(NONCE_LEN + data.len());
+	ciphertext.extend_from_slice(&nonce);
+	ciphertext.extend(apply_keystream(key, &nonce, data));
+	ciphertext
+}
+
+/// Decrypt the output of `encrypt`; `None` if it wasn't encrypted with `key` or was tampered with.
+pub fn decrypt(key: &[u8; 32], ciphertext: &[u8]) -> Option<Vec<u8>> {
+	if ciphertext.len() < NONCE_LEN {
+		return None;
+	}
+	let (nonce, body) = ciphertext.split_at(NONCE_LEN);
+	let data = apply_keystream(key, nonce, body);
+	if mac(key, &data)[..] != *nonce {
+		return None;
+	}
+	Some(data)
+}
+
+/// Keep the decrypted result of a cat request in the node's persistent off-chain storage, where
+/// the operator can read it; it is never submitted to the chain.
+pub fn store_decrypted(request: &[u8], data: &[u8]) {
+	let mut key = DECRYPTED_PREFIX.to_vec();
+	key.extend_from_slice(request);
+	StorageValueRef::persistent(&key).set(&data);
+}
+
+fn mac(key: &[u8; 32], data: &[u8]) -> [u8; 32] {
+	blake2_256(&(b"ipfs::mac", key, data).encode())
+}
+
+// XOR `data` with the keystream of blake2 hashes of the key, the nonce and a block counter
+fn apply_keystream(key: &[u8; 32], nonce: &[u8], data: &[u8]) -> Vec<u8> {
+	data.chunks(32)
+		.enumerate()
+		.flat_map(|(counter, chunk)| {
+			let block = blake2_256(&(b"ipfs::stream", key, nonce, counter as u64).encode());
+			chunk.iter().zip(block).map(|(byte, pad)| byte ^ pad).collect::<Vec<_>>()
+		})
+		.collect()
+}
@@ -0,0 +1,98 @@
+// Runtime feature flags for experimental call groups.
+//
+// Experimental subsystems ship dark: their calls are rejected by `CheckFeatureFlags` until
+// governance sets the corresponding bit in `FeatureFlags`.
+
+use codec::{Decode, Encode};
+use frame_support::traits::IsSubType;
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{DispatchInfoOf, SignedExtension},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+};
+use sp_std::{fmt, marker::PhantomData};
+
+use crate::{Call, Config, Pallet};
+
+/// Pubsub subscriptions and publications.
+pub const PUBSUB: u32 = 1 << 0;
+/// Chunked uploads.
+pub const CHUNKED_UPLOADS: u32 = 1 << 1;
+/// Leased content.
+pub const LEASES: u32 = 1 << 2;
+/// Content encrypted at rest by the offchain workers.
+pub const ENCRYPTION: u32 = 1 << 3;
+
+/// The `InvalidTransaction::Custom` code of calls to disabled features.
+pub const FEATURE_DISABLED: u8 = 1;
+
+/// The feature flag gating `call`, if it belongs to an experimental call group.
+pub fn feature_of<T: Config>(call: &Call<T>) -> Option<u32> {
+	match call {
+		Call::ipfs_pubsub_subscribe { .. }
+		| Call::ipfs_pubsub_publish { .. }
+		| Call::ipfs_pubsub_unsubscribe { .. } => Some(PUBSUB),
+		Call::ipfs_begin_upload { .. }
+		| Call::ipfs_upload_chunk { .. }
+		| Call::ipfs_finalize_upload { .. } => Some(CHUNKED_UPLOADS),
+		Call::ipfs_add_bytes_with_lease { .. } | Call::extend_lease { .. } => Some(LEASES),
+		Call::ipfs_add_bytes { encrypt: true, .. } => Some(ENCRYPTION),
+		_ => None,
+	}
+}
+
+/// Rejects signed calls to experimental call groups whose feature flag is not set.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckFeatureFlags<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckFeatureFlags<T> {
+	/// Create a new `CheckFeatureFlags` extension.
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckFeatureFlags<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckFeatureFlags<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckFeatureFlags")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckFeatureFlags<T>
+where
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckFeatureFlags";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		_who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		if let Some(feature) = call.is_sub_type().and_then(feature_of::<T>) {
+			if !Pallet::<T>::is_enabled(feature) {
+				return InvalidTransaction::Custom(FEATURE_DISABLED).into();
+			}
+		}
+		Ok(ValidTransaction::default())
+	}
+}
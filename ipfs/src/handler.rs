@@ -0,0 +1,44 @@
+// Programmatic access to the pallet's queues for other pallets.
+//
+// Only extrinsics used to be able to queue IPFS work. A pallet that stores or retrieves content
+// on its own, such as mpesa-user's receipts, takes an `IpfsHandler` in its config, which the
+// runtime points at this pallet, and the runtime routes the offchain workers' results back to it
+// through the pallet's `OnResult`.
+
+use sp_runtime::{DispatchError, DispatchResult};
+use sp_std::vec::Vec;
+
+/// Queues IPFS work on behalf of an account, with the same checks, deposits and events as the
+/// corresponding extrinsics.
+pub trait IpfsHandler<AccountId> {
+	/// The identifier of a queued request, which its result refers to.
+	type RequestId;
+
+	/// Add `data` to IPFS on behalf of `owner`, reserving the storage deposit from it. The `Cid`
+	/// is reported through `OnIpfsResult::on_added`.
+	fn add_bytes(owner: &AccountId, data: Vec<u8>) -> Result<Self::RequestId, DispatchError>;
+
+	/// Fetch the content of `cid` for `who`; the data is reported through
+	/// `OnIpfsResult::on_cat_result`.
+	fn cat_bytes(who: &AccountId, cid: Vec<u8>) -> Result<Self::RequestId, DispatchError>;
+
+	/// Pin `cid` with the pallet's pin policy, registering it to `owner` if it isn't registered.
+	fn pin(owner: &AccountId, cid: Vec<u8>) -> DispatchResult;
+
+	/// Unpin the registered `cid`.
+	fn unpin(cid: Vec<u8>) -> DispatchResult;
+}
+
+/// Receives the results the offchain workers report, whoever queued the work.
+pub trait OnIpfsResult<AccountId, RequestId> {
+	/// Data added on behalf of `owner` was registered as `cid`.
+	fn on_added(_owner: &AccountId, _cid: &[u8]) {}
+
+	/// The cat request `request` returned `data`.
+	fn on_cat_result(_request: &RequestId, _data: &[u8]) {}
+
+	/// The offchain workers gave up on `request`.
+	fn on_abandoned(_request: &RequestId) {}
+}
+
+impl<AccountId, RequestId> OnIpfsResult<AccountId, RequestId> for () {}
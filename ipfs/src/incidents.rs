@@ -0,0 +1,96 @@
+// A uniform record of offchain worker failures.
+//
+// Offchain workers fail in ways the chain doesn't see: an IPFS daemon that is down, an HTTP API
+// that times out, an oracle that answers garbage. Instead of each worker logging failures in its
+// own words, every failure becomes an `OffchainIncident` naming the subsystem it happened in, the
+// pallet error describing it and an id correlating it with the request or worker run it belongs
+// to. Incidents are buffered in the node's persistent off-chain storage and submitted in batches,
+// so monitoring can follow the health of all workers through the same events and storage.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	offchain::storage::{StorageRetrievalError, StorageValueRef},
+	DispatchError, RuntimeDebug,
+};
+use sp_std::vec::Vec;
+
+const PENDING: &[u8] = b"ipfs::incidents";
+// The most incidents a node buffers between reports; the oldest ones are dropped first.
+const MAX_PENDING: usize = 64;
+/// The most incidents a single report may carry.
+pub const MAX_PER_REPORT: u32 = 16;
+/// The number of most recent incidents kept on-chain.
+pub const MAX_RECENT: usize = 128;
+/// The number of incidents dropped from the most recent ones that are archived to IPFS together.
+pub const ARCHIVE_BATCH: usize = 32;
+
+/// The part of the offchain machinery an incident happened in.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum Subsystem {
+	/// Requests to the IPFS node.
+	Ipfs,
+	/// HTTP requests to external services.
+	Http,
+	/// Price feeds and other oracles.
+	Oracle,
+	/// Notifications sent to users.
+	Notification,
+}
+
+/// A failure of an offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct OffchainIncident<Hash, BlockNumber> {
+	/// The subsystem the failure happened in.
+	pub subsystem: Subsystem,
+	/// The error, as the pallet that raised it reports it on-chain.
+	pub code: DispatchError,
+	/// The hash of the failed request, or the parent hash of the worker run for failures that
+	/// aren't tied to a request.
+	pub correlation_id: Hash,
+	/// The block of the worker run that hit the failure.
+	pub block: BlockNumber,
+}
+
+/// Buffer an incident until the next report.
+pub fn record<Hash: Encode + Decode, BlockNumber: Encode + Decode>(
+	incident: OffchainIncident<Hash, BlockNumber>,
+) {
+	let _ = StorageValueRef::persistent(PENDING).mutate(
+		|pending: Result<
+			Option<Vec<OffchainIncident<Hash, BlockNumber>>>,
+			StorageRetrievalError,
+		>|
+		 -> Result<_, ()> {
+			let mut pending = pending.ok().flatten().unwrap_or_default();
+			if pending.len() >= MAX_PENDING {
+				pending.remove(0);
+			}
+			pending.push(incident);
+			Ok(pending)
+		},
+	);
+}
+
+/// Take up to `max` of the buffered incidents, oldest first.
+pub fn take<Hash: Encode + Decode, BlockNumber: Encode + Decode>(
+	max: usize,
+) -> Vec<OffchainIncident<Hash, BlockNumber>> {
+	let mut taken = Vec::new();
+	let result = StorageValueRef::persistent(PENDING).mutate(
+		|pending: Result<
+			Option<Vec<OffchainIncident<Hash, BlockNumber>>>,
+			StorageRetrievalError,
+		>|
+		 -> Result<_, ()> {
+			let mut pending = pending.ok().flatten().unwrap_or_default();
+			taken = pending.drain(..pending.len().min(max)).collect();
+			Ok(pending)
+		},
+	);
+	// a concurrent worker run changed the buffer; leave the incidents to the next report
+	match result {
+		Ok(_) => taken,
+		Err(_) => Vec::new(),
+	}
+}
@@ -0,0 +1,152 @@
+// Content ingress filters.
+//
+// Governance sets an `IngressPolicy` that data entering through the add paths has to pass: a size
+// limit per content class, MIME types that are refused outright, and optionally an entropy ceiling
+// for deployments that don't accept encrypted blobs. Callers don't declare a MIME type, so it is
+// sniffed from the data's leading bytes.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// The broad class of a piece of content, as sniffed from its leading bytes.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum ContentClass {
+	Text,
+	Image,
+	Audio,
+	Video,
+	Document,
+	Archive,
+	Binary,
+}
+
+/// The filters data added to IPFS has to pass. The default policy admits everything.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct IngressPolicy {
+	/// The maximum size in bytes of content of each class; classes not listed are unbounded.
+	pub max_sizes: Vec<(ContentClass, u64)>,
+	/// The MIME types refused outright, e.g. `b"application/x-msdownload"`.
+	pub denied_mime_types: Vec<Vec<u8>>,
+	/// The highest Shannon entropy, in millibits per byte, data may have. Encrypted and well
+	/// compressed data comes close to 8000; data shorter than 256 bytes can't reach it.
+	pub max_entropy: Option<u32>,
+}
+
+/// The filter data was refused by.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Rejection {
+	TooLarge,
+	MimeTypeDenied,
+	EntropyTooHigh,
+}
+
+impl IngressPolicy {
+	/// Check complete content against every filter, returning its class.
+	pub fn check(&self, data: &[u8]) -> Result<ContentClass, Rejection> {
+		let class = self.check_leading(data)?;
+		self.check_size(class, data.len() as u64)?;
+		self.check_entropy(data)?;
+		Ok(class)
+	}
+
+	/// Check the leading bytes of content against the MIME filter, returning its class.
+	pub fn check_leading(&self, data: &[u8]) -> Result<ContentClass, Rejection> {
+		let (mime, class) = sniff(data);
+		if self.denied_mime_types.iter().any(|denied| denied.as_slice() == mime) {
+			return Err(Rejection::MimeTypeDenied);
+		}
+		Ok(class)
+	}
+
+	/// Check that `size` bytes of content of the given class are within its size limit.
+	pub fn check_size(&self, class: ContentClass, size: u64) -> Result<(), Rejection> {
+		match self.max_sizes.iter().find(|(c, _)| *c == class) {
+			Some((_, max)) if size > *max => Err(Rejection::TooLarge),
+			_ => Ok(()),
+		}
+	}
+
+	/// Check data, or a chunk of it, against the entropy ceiling.
+	pub fn check_entropy(&self, data: &[u8]) -> Result<(), Rejection> {
+		match self.max_entropy {
+			Some(max) if entropy(data) > max => Err(Rejection::EntropyTooHigh),
+			_ => Ok(()),
+		}
+	}
+}
+
+// (offset, magic bytes, MIME type, class) of the formats recognised by their signature
+const SIGNATURES: &[(usize, &[u8], &[u8], ContentClass)] = &[
+	(0, b"\x89PNG\r\n\x1a\n", b"image/png", ContentClass::Image),
+	(0, b"\xff\xd8\xff", b"image/jpeg", ContentClass::Image),
+	(0, b"GIF87a", b"image/gif", ContentClass::Image),
+	(0, b"GIF89a", b"image/gif", ContentClass::Image),
+	(8, b"WEBP", b"image/webp", ContentClass::Image),
+	(0, b"ID3", b"audio/mpeg", ContentClass::Audio),
+	(0, b"OggS", b"audio/ogg", ContentClass::Audio),
+	(0, b"fLaC", b"audio/flac", ContentClass::Audio),
+	(8, b"WAVE", b"audio/wav", ContentClass::Audio),
+	(4, b"ftyp", b"video/mp4", ContentClass::Video),
+	(0, b"\x1a\x45\xdf\xa3", b"video/webm", ContentClass::Video),
+	(8, b"AVI ", b"video/x-msvideo", ContentClass::Video),
+	(0, b"%PDF-", b"application/pdf", ContentClass::Document),
+	(0, b"PK\x03\x04", b"application/zip", ContentClass::Archive),
+	(0, b"\x1f\x8b", b"application/gzip", ContentClass::Archive),
+	(0, b"7z\xbc\xaf\x27\x1c", b"application/x-7z-compressed", ContentClass::Archive),
+	(0, b"MZ", b"application/x-msdownload", ContentClass::Binary),
+	(0, b"\x7fELF", b"application/x-executable", ContentClass::Binary),
+];
+
+/// The MIME type and class of `data`, sniffed from its leading bytes. Data without a known
+/// signature is `text/plain` if it is printable UTF-8, `application/octet-stream` otherwise.
+pub fn sniff(data: &[u8]) -> (&'static [u8], ContentClass) {
+	for (offset, magic, mime, class) in SIGNATURES {
+		if data.get(*offset..offset + magic.len()) == Some(*magic) {
+			return (*mime, *class);
+		}
+	}
+
+	let printable = sp_std::str::from_utf8(data)
+		.map_or(false, |text| text.chars().all(|c| !c.is_control() || c.is_ascii_whitespace()));
+	if printable {
+		(b"text/plain", ContentClass::Text)
+	} else {
+		(b"application/octet-stream", ContentClass::Binary)
+	}
+}
+
+/// The Shannon entropy of the byte distribution of `data`, in millibits per byte (0 to 8000).
+pub fn entropy(data: &[u8]) -> u32 {
+	if data.is_empty() {
+		return 0;
+	}
+
+	let mut counts = [0u64; 256];
+	for byte in data {
+		counts[*byte as usize] += 1;
+	}
+
+	// H = log2(n) - sum(c * log2(c)) / n, in 16.16 fixed point
+	let n = data.len() as u64;
+	let sum = counts.iter().filter(|c| **c > 0).map(|c| c * log2(*c)).sum::<u64>();
+	let bits = (n * log2(n) - sum) / n;
+	((bits * 1000) >> 16) as u32
+}
+
+// log2 of a non-zero `x` in 16.16 fixed point, by repeated squaring of the normalised mantissa
+fn log2(x: u64) -> u64 {
+	let int = 63 - x.leading_zeros() as u64;
+	let one = 1u128 << 32;
+	let mut mantissa = ((x as u128) << 32) >> int;
+	let mut fraction = 0;
+	for bit in (0..16).rev() {
+		mantissa = (mantissa * mantissa) >> 32;
+		if mantissa >= 2 * one {
+			mantissa >>= 1;
+			fraction |= 1 << bit;
+		}
+	}
+	(int << 16) | fraction
+}
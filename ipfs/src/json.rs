@@ -0,0 +1,69 @@
+// Just enough JSON scanning to pick string and integer values out of Kubo's responses and content
+// schemas without pulling a JSON parser into the runtime.
+
+use sp_std::vec::Vec;
+
+/// Collect every string value of `key` in `body`, including the elements of string arrays.
+pub fn string_values(body: &[u8], key: &[u8]) -> Vec<Vec<u8>> {
+	let mut needle = Vec::with_capacity(key.len() + 3);
+	needle.push(b'"');
+	needle.extend_from_slice(key);
+	needle.extend_from_slice(b"\":");
+
+	let mut values = Vec::new();
+	let mut pos = 0;
+	while let Some(offset) = find(&body[pos..], &needle) {
+		pos += offset + needle.len();
+		let in_array = body.get(pos) == Some(&b'[');
+		if in_array {
+			pos += 1;
+		}
+		loop {
+			if body.get(pos) != Some(&b'"') {
+				break;
+			}
+			let end = match body[pos + 1..].iter().position(|b| *b == b'"') {
+				Some(end) => pos + 1 + end,
+				None => return values,
+			};
+			values.push(body[pos + 1..end].to_vec());
+			pos = end + 1;
+			if !in_array || body.get(pos) != Some(&b',') {
+				break;
+			}
+			pos += 1;
+		}
+	}
+
+	values
+}
+
+/// The first unsigned integer value of `key` in `body`.
+pub fn u64_value(body: &[u8], key: &[u8]) -> Option<u64> {
+	let mut needle = Vec::with_capacity(key.len() + 3);
+	needle.push(b'"');
+	needle.extend_from_slice(key);
+	needle.extend_from_slice(b"\":");
+
+	let start = find(body, &needle)? + needle.len();
+	let digits = body[start..].iter().take_while(|b| b.is_ascii_digit()).collect::<Vec<_>>();
+	if digits.is_empty() {
+		return None;
+	}
+	digits
+		.into_iter()
+		.try_fold(0u64, |value, digit| value.checked_mul(10)?.checked_add((digit - b'0') as u64))
+}
+
+/// Whether `body` has a member named `key`.
+pub fn has_key(body: &[u8], key: &[u8]) -> bool {
+	let mut needle = Vec::with_capacity(key.len() + 3);
+	needle.push(b'"');
+	needle.extend_from_slice(key);
+	needle.extend_from_slice(b"\":");
+	find(body, &needle).is_some()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack.windows(needle.len()).position(|w| w == needle)
+}
@@ -0,0 +1,109 @@
+// Latency sampling for IPFS requests.
+//
+// Every request issued by the offchain worker is timed and the most recent samples per request
+// kind are kept in the node's persistent off-chain storage. The summaries printed from them are
+// what `FastDeadline`/`BackgroundDeadline` and the OCW budget should be sized from on reference
+// hardware, rather than guessing.
+
+use codec::{Decode, Encode};
+use sp_runtime::offchain::storage::StorageValueRef;
+use sp_std::vec::Vec;
+
+use crate::IpfsRequest;
+
+const PREFIX: &[u8] = b"ipfs::latency::";
+// The number of most recent samples kept per request kind.
+const MAX_SAMPLES: usize = 256;
+
+/// The request kinds latency is tracked for.
+pub const KINDS: [&str; 14] = [
+	"connect",
+	"disconnect",
+	"add",
+	"cat",
+	"insert_pin",
+	"remove_pin",
+	"remove_block",
+	"dht",
+	"peers",
+	"pubsub",
+	"repo",
+	"refs",
+	"gc",
+	"ipns",
+];
+
+/// A single timed request.
+#[derive(Encode, Decode, Clone, Copy)]
+struct Sample {
+	millis: u64,
+	ok: bool,
+}
+
+/// Percentiles over the retained samples of one request kind.
+pub struct Summary {
+	pub count: usize,
+	pub failures: usize,
+	pub p50: u64,
+	pub p95: u64,
+	pub p99: u64,
+	pub max: u64,
+}
+
+/// The label latency of `req` is tracked under.
+pub fn kind(req: &IpfsRequest) -> &'static str {
+	match req {
+		IpfsRequest::Connect(_) => "connect",
+		IpfsRequest::Disconnect(_) => "disconnect",
+		IpfsRequest::AddBytes(_) => "add",
+		IpfsRequest::CatBytes(_) => "cat",
+		IpfsRequest::InsertPin(..) => "insert_pin",
+		IpfsRequest::RemovePin(..) => "remove_pin",
+		IpfsRequest::RemoveBlock(_) => "remove_block",
+		IpfsRequest::FindPeer(_) | IpfsRequest::GetProviders(_) => "dht",
+		IpfsRequest::Peers => "peers",
+		IpfsRequest::LocalRefs => "refs",
+		IpfsRequest::Subscribe(_)
+		| IpfsRequest::Unsubscribe(_)
+		| IpfsRequest::Publish { .. }
+		| IpfsRequest::SubscriptionNext(_) => "pubsub",
+	}
+}
+
+fn key(kind: &str) -> Vec<u8> {
+	let mut key = Vec::from(PREFIX);
+	key.extend_from_slice(kind.as_bytes());
+	key
+}
+
+/// Record how long a request of the given kind took and whether it succeeded.
+pub fn record(kind: &str, millis: u64, ok: bool) {
+	let storage = StorageValueRef::persistent(&key(kind));
+	let mut samples = storage.get::<Vec<Sample>>().ok().flatten().unwrap_or_default();
+	if samples.len() >= MAX_SAMPLES {
+		samples.remove(0);
+	}
+	samples.push(Sample { millis, ok });
+	storage.set(&samples);
+}
+
+/// Summarize the retained samples of the given kind, if there are any.
+pub fn summary(kind: &str) -> Option<Summary> {
+	let samples = StorageValueRef::persistent(&key(kind)).get::<Vec<Sample>>().ok().flatten()?;
+	if samples.is_empty() {
+		return None;
+	}
+
+	let mut millis = samples.iter().map(|s| s.millis).collect::<Vec<_>>();
+	millis.sort_unstable();
+	let percentile = |p: usize| millis[(millis.len() - 1) * p / 100];
+
+	Some(Summary {
+		count: samples.len(),
+		failures: samples.iter().filter(|s| !s.ok).count(),
+		p50: percentile(50),
+		p95: percentile(95),
+		p99: percentile(99),
+		max: millis[millis.len() - 1],
+	})
+}
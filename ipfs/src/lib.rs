@@ -1,411 +1,6442 @@
 // IPFS implementation
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use codec::{Encode, Decode};
-use frame_support::{debug, decl_module, decl_storage, decl_event, decl_error, weights::Weight};
-use frame_system::{self as system, ensure_signed};
-use sp_core::offchain::{Duration, IpfsRequest, IpfsResponse, OpaqueMultiaddr, Timestamp};
+use codec::{Decode, Encode};
+use frame_support::{
+	ensure,
+	storage::StoragePrefixedMap,
+	traits::{
+		Contains, Currency, EnsureOriginWithArg, ExistenceRequirement, Get, Randomness,
+		ReservableCurrency, WithdrawReasons,
+	},
+	weights::Weight,
+};
+use frame_system::{
+	ensure_signed,
+	offchain::{
+		AppCrypto, SendSignedTransaction, SendUnsignedTransaction, SignedPayload, Signer,
+		SigningTypes, SubmitTransaction,
+	},
+	pallet_prelude::OriginFor,
+};
+use scale_info::TypeInfo;
+use sp_core::{
+	crypto::KeyTypeId,
+	offchain::{Duration, OpaqueMultiaddr, StorageKind, Timestamp},
+};
 use sp_io::offchain::timestamp;
-use sp_runtime::offchain::ipfs;
-use sp_std::{str, vec::Vec};
-
-/// The pallet's configuration trait.
-pub trait Trait: system::Trait {
-    /// The overarching event type.
-    type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
-}
-
-#[derive(Encode, Decode, PartialEq)]
-enum ConnectionCommand {
-    ConnectTo(OpaqueMultiaddr),
-    DisconnectFrom(OpaqueMultiaddr),
-}
-
-#[derive(Encode, Decode, PartialEq)]
-enum DataCommand {
-    AddBytes(Vec<u8>),
-    CatBytes(Vec<u8>),
-    InsertPin(Vec<u8>),
-    RemoveBlock(Vec<u8>),
-    RemovePin(Vec<u8>),
-}
-
-#[derive(Encode, Decode, PartialEq)]
-enum DhtCommand {
-    FindPeer(Vec<u8>),
-    GetProviders(Vec<u8>),
-}
-
-// This pallet's storage items.
-decl_storage! {
-    trait Store for Module<T: Trait> as TemplateModule {
-        // A list of addresses to connect to and disconnect from.
-        pub ConnectionQueue: Vec<ConnectionCommand>;
-        // A queue of data to publish or obtain on IPFS.
-        pub DataQueue: Vec<DataCommand>;
-        // A list of requests to the DHT.
-        pub DhtQueue: Vec<DhtCommand>;
-    }
-}
-
-// The pallet's events
-decl_event!(
-    pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-        ConnectionRequested(AccountId),
-        DisconnectRequested(AccountId),
-        QueuedDataToAdd(AccountId),
-        QueuedDataToCat(AccountId),
-        QueuedDataToPin(AccountId),
-        QueuedDataToRemove(AccountId),
-        QueuedDataToUnpin(AccountId),
-        FindPeerIssued(AccountId),
-        FindProvidersIssued(AccountId),
-    }
-);
-
-// The pallet's errors
-decl_error! {
-    pub enum Error for Module<T: Trait> {
-        CantCreateRequest,
-        RequestTimeout,
-        RequestFailed,
-    }
-}
-
-// The pallet's dispatchable functions.
-decl_module! {
-    /// The module declaration.
-    pub struct Module<T: Trait> for enum Call where origin: T::Origin {
-        // Initializing errors
-        type Error = Error<T>;
-
-        // Initializing events
-        fn deposit_event() = default;
-
-        // needs to be synchronized with offchain_worker actitivies
-        fn on_initialize(block_number: T::BlockNumber) -> Weight {
-            ConnectionQueue::kill();
-            DhtQueue::kill();
-
-            if block_number % 2.into() == 1.into() {
-                DataQueue::kill();
-            }
-
-            0
-        }
-
-        /// Mark a `Multiaddr` as a desired connection target. The connection will be established
-        /// during the next run of the off-chain `connection_housekeeping` process.
-        #[weight = 100_000]
-        pub fn ipfs_connect(origin, addr: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-            let cmd = ConnectionCommand::ConnectTo(OpaqueMultiaddr(addr));
-
-            ConnectionQueue::mutate(|cmds| if !cmds.contains(&cmd) { cmds.push(cmd) });
-            Self::deposit_event(RawEvent::ConnectionRequested(who));
-        }
-
-        /// Queues a `Multiaddr` to be disconnected. The connection will be severed during the next
-        /// run of the off-chain `connection_housekeeping` process.
-        #[weight = 500_000]
-        pub fn ipfs_disconnect(origin, addr: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-            let cmd = ConnectionCommand::DisconnectFrom(OpaqueMultiaddr(addr));
-
-            ConnectionQueue::mutate(|cmds| if !cmds.contains(&cmd) { cmds.push(cmd) });
-            Self::deposit_event(RawEvent::DisconnectRequested(who));
-        }
-
-        /// Add arbitrary bytes to the IPFS repository. The registered `Cid` is printed out in the
-        /// logs.
-        #[weight = 200_000]
-        pub fn ipfs_add_bytes(origin, data: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DataQueue::mutate(|queue| queue.push(DataCommand::AddBytes(data)));
-            Self::deposit_event(RawEvent::QueuedDataToAdd(who));
-        }
-
-        /// Find IPFS data pointed to by the given `Cid`; if it is valid UTF-8, it is printed in the
-        /// logs verbatim; otherwise, the decimal representation of the bytes is displayed instead.
-        #[weight = 100_000]
-        pub fn ipfs_cat_bytes(origin, cid: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DataQueue::mutate(|queue| queue.push(DataCommand::CatBytes(cid)));
-            Self::deposit_event(RawEvent::QueuedDataToCat(who));
-        }
-
-        /// Add arbitrary bytes to the IPFS repository. The registered `Cid` is printed out in the
-        /// logs.
-        #[weight = 300_000]
-        pub fn ipfs_remove_block(origin, cid: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DataQueue::mutate(|queue| queue.push(DataCommand::RemoveBlock(cid)));
-            Self::deposit_event(RawEvent::QueuedDataToRemove(who));
-        }
-
-        /// Pins a given `Cid` non-recursively.
-        #[weight = 100_000]
-        pub fn ipfs_insert_pin(origin, cid: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DataQueue::mutate(|queue| queue.push(DataCommand::InsertPin(cid)));
-            Self::deposit_event(RawEvent::QueuedDataToPin(who));
-        }
-
-        /// Unpins a given `Cid` non-recursively.
-        #[weight = 100_000]
-        pub fn ipfs_remove_pin(origin, cid: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DataQueue::mutate(|queue| queue.push(DataCommand::RemovePin(cid)));
-            Self::deposit_event(RawEvent::QueuedDataToUnpin(who));
-        }
-
-        /// Find addresses associated with the given `PeerId`.
-        #[weight = 100_000]
-        pub fn ipfs_dht_find_peer(origin, peer_id: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DhtQueue::mutate(|queue| queue.push(DhtCommand::FindPeer(peer_id)));
-            Self::deposit_event(RawEvent::FindPeerIssued(who));
-        }
-
-        /// Find the list of `PeerId`s known to be hosting the given `Cid`.
-        #[weight = 100_000]
-        pub fn ipfs_dht_find_providers(origin, cid: Vec<u8>) {
-            let who = ensure_signed(origin)?;
-
-            DhtQueue::mutate(|queue| queue.push(DhtCommand::GetProviders(cid)));
-            Self::deposit_event(RawEvent::FindProvidersIssued(who));
-        }
-
-        fn offchain_worker(block_number: T::BlockNumber) {
-            // process connect/disconnect commands
-            if let Err(e) = Self::connection_housekeeping() {
-                debug::error!("IPFS: Encountered an error during connection housekeeping: {:?}", e);
-            }
-
-            // process requests to the DHT
-            if let Err(e) = Self::handle_dht_requests() {
-                debug::error!("IPFS: Encountered an error while processing DHT requests: {:?}", e);
-            }
-
-            // process Ipfs::{add, get} queues every other block
-            if block_number % 2.into() == 1.into() {
-                if let Err(e) = Self::handle_data_requests() {
-                    debug::error!("IPFS: Encountered an error while processing data requests: {:?}", e);
-                }
-            }
-
-            // display some stats every 5 blocks
-            if block_number % 5.into() == 0.into() {
-                if let Err(e) = Self::print_metadata() {
-                    debug::error!("IPFS: Encountered an error while obtaining metadata: {:?}", e);
-                }
-            }
-        }
-    }
-}
-
-impl<T: Trait> Module<T> {
-    // send a request to the local IPFS node; can only be called be an off-chain worker
-    fn ipfs_request(req: IpfsRequest, deadline: impl Into<Option<Timestamp>>) -> Result<IpfsResponse, Error<T>> {
-        let ipfs_request = ipfs::PendingRequest::new(req).map_err(|_| Error::<T>::CantCreateRequest)?;
-        ipfs_request.try_wait(deadline)
-            .map_err(|_| Error::<T>::RequestTimeout)?
-            .map(|r| r.response)
-            .map_err(|e| {
-                if let ipfs::Error::IoError(err) = e {
-                    debug::error!("IPFS: request failed: {}", str::from_utf8(&err).unwrap());
-                } else {
-                    debug::error!("IPFS: request failed: {:?}", e);
-                }
-                Error::<T>::RequestFailed
-            })
-    }
-
-    fn connection_housekeeping() -> Result<(), Error<T>> {
-        let mut deadline;
-
-        for cmd in ConnectionQueue::get() {
-            deadline = Some(timestamp().add(Duration::from_millis(1_000)));
-
-            match cmd {
-                // connect to the desired peers if not yet connected
-                ConnectionCommand::ConnectTo(addr) => {
-                    match Self::ipfs_request(IpfsRequest::Connect(addr.clone()), deadline) {
-                        Ok(IpfsResponse::Success) => {
-                            debug::info!(
-                                "IPFS: connected to {}",
-                                str::from_utf8(&addr.0).expect("our own calls can be trusted to be UTF-8; qed")
-                            );
-                        }
-                        Ok(_) => unreachable!("only Success can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: connect error: {:?}", e),
-                    }
-                }
-                // disconnect from peers that are no longer desired
-                ConnectionCommand::DisconnectFrom(addr) => {
-                    match Self::ipfs_request(IpfsRequest::Disconnect(addr.clone()), deadline) {
-                        Ok(IpfsResponse::Success) => {
-                            debug::info!(
-                                "IPFS: disconnected from {}",
-                                str::from_utf8(&addr.0).expect("our own calls can be trusted to be UTF-8; qed")
-                            );
-                        }
-                        Ok(_) => unreachable!("only Success can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: disconnect error: {:?}", e),
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn handle_dht_requests() -> Result<(), Error<T>> {
-        let mut deadline;
-
-        for cmd in DhtQueue::get() {
-            deadline = Some(timestamp().add(Duration::from_millis(1_000)));
-
-            match cmd {
-                // find the known addresses of the given peer
-                DhtCommand::FindPeer(peer_id) => {
-                    match Self::ipfs_request(IpfsRequest::FindPeer(peer_id.clone()), deadline) {
-                        Ok(IpfsResponse::FindPeer(addrs)) => {
-                            debug::info!(
-                                "IPFS: found the following addresses of {}: {:?}",
-                                str::from_utf8(&peer_id).expect("our own calls can be trusted to be UTF-8; qed"),
-                                addrs.iter()
-                                    .map(|addr| str::from_utf8(&addr.0)
-                                        .expect("our node's results can be trusted to be UTF-8; qed"))
-                                    .collect::<Vec<_>>()
-                            );
-                        }
-                        Ok(_) => unreachable!("only FindPeer can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: find peer error: {:?}", e),
-                    }
-                }
-                // disconnect from peers that are no longer desired
-                DhtCommand::GetProviders(cid) => {
-                    match Self::ipfs_request(IpfsRequest::GetProviders(cid.clone()), deadline) {
-                        Ok(IpfsResponse::GetProviders(peer_ids)) => {
-                            debug::info!(
-                                "IPFS: found the following providers of {}: {:?}",
-                                str::from_utf8(&cid).expect("our own calls can be trusted to be UTF-8; qed"),
-                                peer_ids.iter()
-                                    .map(|peer_id| str::from_utf8(&peer_id)
-                                        .expect("our node's results can be trusted to be UTF-8; qed"))
-                                    .collect::<Vec<_>>()
-                            );
-                        }
-                        Ok(_) => unreachable!("only GetProviders can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: find providers error: {:?}", e),
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn handle_data_requests() -> Result<(), Error<T>> {
-        let data_queue = DataQueue::get();
-        let len = data_queue.len();
-        if len != 0 {
-            debug::info!("IPFS: {} entr{} in the data queue", len, if len == 1 { "y" } else { "ies" });
-        }
-
-        let deadline = Some(timestamp().add(Duration::from_millis(1_000)));
-        for cmd in data_queue.into_iter() {
-            match cmd {
-                DataCommand::AddBytes(data) => {
-                    match Self::ipfs_request(IpfsRequest::AddBytes(data.clone()), deadline) {
-                        Ok(IpfsResponse::AddBytes(cid)) => {
-                            debug::info!(
-                                "IPFS: added data with Cid {}",
-                                str::from_utf8(&cid).expect("our own IPFS node can be trusted here; qed")
-                            );
-                        },
-                        Ok(_) => unreachable!("only AddBytes can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: add error: {:?}", e),
-                    }
-                }
-                DataCommand::CatBytes(data) => {
-                    match Self::ipfs_request(IpfsRequest::CatBytes(data.clone()), deadline) {
-                        Ok(IpfsResponse::CatBytes(data)) => {
-                            if let Ok(str) = str::from_utf8(&data) {
-                                debug::info!("IPFS: got data: {:?}", str);
-                            } else {
-                                debug::info!("IPFS: got data: {:x?}", data);
-                            };
-                        },
-                        Ok(_) => unreachable!("only CatBytes can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: error: {:?}", e),
-                    }
-                }
-                DataCommand::RemoveBlock(cid) => {
-                    match Self::ipfs_request(IpfsRequest::RemoveBlock(cid), deadline) {
-                        Ok(IpfsResponse::RemoveBlock(cid)) => {
-                            debug::info!(
-                                "IPFS: removed a block with Cid {}",
-                                str::from_utf8(&cid).expect("our own IPFS node can be trusted here; qed")
-                            );
-                        },
-                        Ok(_) => unreachable!("only RemoveBlock can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: remove block error: {:?}", e),
-                    }
-                }
-                DataCommand::InsertPin(cid) => {
-                    match Self::ipfs_request(IpfsRequest::InsertPin(cid.clone(), false), deadline) {
-                        Ok(IpfsResponse::Success) => {
-                            debug::info!(
-                                "IPFS: pinned data with Cid {}",
-                                str::from_utf8(&cid).expect("our own request can be trusted to be UTF-8; qed")
-                            );
-                        },
-                        Ok(_) => unreachable!("only Success can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: insert pin error: {:?}", e),
-                    }
-                }
-                DataCommand::RemovePin(cid) => {
-                    match Self::ipfs_request(IpfsRequest::RemovePin(cid.clone(), false), deadline) {
-                        Ok(IpfsResponse::Success) => {
-                            debug::info!(
-                                "IPFS: unpinned data with Cid {}",
-                                str::from_utf8(&cid).expect("our own request can be trusted to be UTF-8; qed")
-                            );
-                        },
-                        Ok(_) => unreachable!("only Success can be a response for that request type; qed"),
-                        Err(e) => debug::error!("IPFS: remove pin error: {:?}", e),
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn print_metadata() -> Result<(), Error<T>> {
-        let deadline = Some(timestamp().add(Duration::from_millis(200)));
-
-        let peers = if let IpfsResponse::Peers(peers) = Self::ipfs_request(IpfsRequest::Peers, deadline)? {
-            peers
-        } else {
-            unreachable!("only Peers can be a response for that request type; qed");
-        };
-        let peer_count = peers.len();
-
-        debug::info!(
-            "IPFS: currently connected to {} peer{}",
-            peer_count,
-            if peer_count == 1 { "" } else { "s" },
-        );
-
-        Ok(())
-    }
-}
\ No newline at end of file
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	traits::{CheckedSub, Hash, IdentifyAccount, One, SaturatedConversion, Saturating, Zero},
+	transaction_validity::{InvalidTransaction, TransactionValidity, ValidTransaction},
+	DispatchError, RuntimeAppPublic, RuntimeDebug,
+};
+use sp_std::{collections::btree_set::BTreeSet, marker::PhantomData, str, vec::Vec};
+
+use mpesa_primitives::{
+	collect_page, AsyncOp, IndexFilter, Page, PageRequest, PromiseRegistry, WebhookNotifier,
+};
+use pallet_ocw_jobs::{JobHandler, Jobs, Outcome};
+
+pub use pallet::*;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+pub mod blockspace;
+pub mod cid;
+mod encryption;
+pub mod features;
+pub mod handler;
+pub mod incidents;
+pub mod ingress;
+mod json;
+mod latency;
+pub mod migrations;
+#[cfg(test)]
+mod mock;
+pub mod runtime_api;
+pub mod snapshot;
+mod store;
+#[cfg(test)]
+mod tests;
+pub mod weights;
+
+pub use blockspace::CheckBlockData;
+pub use features::CheckFeatureFlags;
+pub use handler::{IpfsHandler, OnIpfsResult};
+pub use incidents::{OffchainIncident, Subsystem};
+pub use ingress::{ContentClass, IngressPolicy};
+pub use runtime_api::PendingCommands;
+pub use snapshot::{RegistryEntry, RegistryExport, RegistryImport, RegistrySnapshot};
+#[cfg(feature = "embedded-ipfs")]
+pub use store::EmbeddedIpfs;
+pub use store::{ContentStore, IpfsRequest, IpfsResponse, Kubo, LocalStore, RepoStats, StoreError};
+pub use weights::WeightInfo;
+
+/// The key type of the keys the offchain worker signs its result transactions with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"ipfs");
+
+/// The crypto used by the offchain worker to sign result transactions.
+pub mod crypto {
+	use super::KEY_TYPE;
+	use sp_core::sr25519::Signature as Sr25519Signature;
+	use sp_runtime::{
+		app_crypto::{app_crypto, sr25519},
+		traits::Verify,
+		MultiSignature, MultiSigner,
+	};
+
+	app_crypto!(sr25519, KEY_TYPE);
+
+	/// The identifier used to look up the worker's signing key in the keystore.
+	pub struct OcwAuthId;
+
+	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for OcwAuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+
+	impl frame_system::offchain::AppCrypto<<Sr25519Signature as Verify>::Signer, Sr25519Signature>
+		for OcwAuthId
+	{
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+}
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type RegistryEntryOf<T> = RegistryEntry<
+	<T as frame_system::Config>::AccountId,
+	<T as frame_system::Config>::BlockNumber,
+	BalanceOf<T>,
+>;
+type ReportPayloadOf<T> = ReportPayload<
+	<T as SigningTypes>::Public,
+	<T as frame_system::Config>::AccountId,
+	<T as frame_system::Config>::BlockNumber,
+>;
+
+// Node-local queue of data requests that failed and are waiting to be retried.
+const RETRY_QUEUE: &[u8] = b"ipfs::retry_queue";
+// Node-local list of the desired peers this node connected to; only these are disconnected once
+// they are no longer desired, never peers the IPFS node found on its own.
+const MANAGED_PEERS: &[u8] = b"ipfs::managed_peers";
+// How far this node has got processing `DataQueue`: every command below the cursor is done.
+const DATA_CURSOR: &[u8] = b"ipfs::data_cursor";
+// The commands at or above the cursor this node has already processed, out of order.
+const DATA_DONE: &[u8] = b"ipfs::data_done";
+// Prefix of the off-chain index keys upload chunks are staged under.
+const UPLOAD_INDEX_PREFIX: &[u8] = b"ipfs::upload::";
+// Prefix of the off-chain index keys received pubsub messages are stored under.
+const PUBSUB_INDEX_PREFIX: &[u8] = b"ipfs::pubsub::";
+// The largest cat result submitted back to the chain, in bytes.
+const MAX_CAT_RESULT_LEN: usize = 64 * 1024;
+/// The prefix of the persistent off-chain storage keys cat results are cached under, followed by
+/// the `Cid`; the values are the raw content.
+pub const CAT_CACHE_PREFIX: &[u8] = b"ipfs::cat::";
+// How many messages are fetched per subscribed topic and block.
+const PUBSUB_BATCH: u32 = 5;
+// The last block in which one of this node's IPFS requests succeeded.
+const LAST_SUCCESS: &[u8] = b"ipfs::last_success";
+// How far this node has got pinning `ImportedPins`.
+const IMPORT_CURSOR: &[u8] = b"ipfs::import_cursor";
+// How many imported CIDs are pinned per block.
+const IMPORT_BATCH: u32 = 20;
+// The last block the offchain worker ran in; a gap means the node was down or is new.
+const LAST_RUN: &[u8] = b"ipfs::last_run";
+// The `RecoveryScan` in progress, or the last one completed.
+const RECOVERY_SCAN: &[u8] = b"ipfs::recovery_scan";
+// How many registry entries are checked per block during a recovery scan.
+const RECOVERY_BATCH: usize = 20;
+// How many due retries are processed per block.
+const RETRY_BATCH: usize = 2;
+// The number of registry entries checked for under-replication per block.
+const REPLICATION_SWEEP: u32 = 10;
+// The number of missing assigned `Cid`s the offchain worker pins per attestation round.
+const REPLICATION_PIN_BATCH: usize = 20;
+// The most `Cid`s a single attestation may cover.
+const MAX_ATTESTATIONS: u32 = 50;
+// The most bytes of content a storage challenge covers.
+const CHALLENGE_LEN: u32 = 256;
+// Node-local record of the storage challenges the offchain worker has responded to.
+const CHALLENGES_ANSWERED: &[u8] = b"ipfs::challenges_answered";
+// The most `Cid`s evicted from a full repository per stats report.
+const MAX_EVICTIONS: u32 = 20;
+// The most request statuses a single report may carry.
+const MAX_STATUS_UPDATES: u32 = 32;
+// the most evidence `Cid`s an appeal against a denial can cite
+const MAX_APPEAL_EVIDENCE: u32 = 8;
+// The longest a bucket name may be.
+const MAX_BUCKET_NAME_LEN: usize = 64;
+// The longest a name in a namespace may be.
+const MAX_NAMESPACE_NAME_LEN: usize = 128;
+// The most tags a name in a namespace may carry.
+const MAX_TAGS: usize = 8;
+// The longest a tag may be.
+const MAX_TAG_LEN: usize = 32;
+// Node-local record of the block of the last registry export request the offchain worker served.
+const REGISTRY_EXPORTED: &[u8] = b"ipfs::registry_exported";
+// Node-local cache of the `Cid` and entries of the registry snapshot being imported.
+const REGISTRY_SNAPSHOT: &[u8] = b"ipfs::registry_snapshot";
+// The longest IPNS name that may be resolved.
+const MAX_IPNS_NAME_LEN: usize = 128;
+// The prefix of the names of the IPFS node keys accounts publish their IPNS names with.
+const IPNS_KEY_PREFIX: &[u8] = b"substrate-ipns-";
+// The number of blocks between the stats reports of the offchain workers.
+const STATS_INTERVAL: u32 = 5;
+// The number of blocks a reporter's offchain worker may go without a report before it counts as
+// unreported: four stats reports.
+const REPORTING_WINDOW: u32 = 4 * STATS_INTERVAL;
+// Node-local flag set while the keystore holds no signing key, so that the offchain worker warns
+// once rather than on every block.
+const SIGNING_KEY_MISSING: &[u8] = b"ipfs::signing_key_missing";
+
+/// A connection change queued for the offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum ConnectionCommand {
+	ConnectTo(OpaqueMultiaddr),
+	DisconnectFrom(OpaqueMultiaddr),
+}
+
+/// A request to add, fetch, pin or remove data, queued for the offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum DataCommand<AccountId> {
+	/// The owner, the data, the number of blocks the content is leased for, if it is, and the
+	/// caller's deadline override in ms.
+	AddBytes(AccountId, Vec<u8>, Option<u32>, Option<u64>),
+	/// The owner, the upload, its number of chunks, and the hash the assembled data must have.
+	AddUpload(AccountId, UploadId, u32, Vec<u8>),
+	/// The Cid and the caller's deadline override in ms.
+	CatBytes(Vec<u8>, Option<u64>),
+	InsertPin(Vec<u8>, bool),
+	RemoveBlock(Vec<u8>),
+	RemovePin(Vec<u8>, bool),
+	/// The owner, the data to encrypt before adding it, and the caller's deadline override in ms.
+	AddEncrypted(AccountId, Vec<u8>, Option<u64>),
+	/// Remove unpinned blocks from the repository.
+	CollectGarbage,
+	/// The account whose IPNS name to point at the Cid.
+	IpnsPublish(AccountId, Vec<u8>),
+	/// The IPNS name to resolve.
+	IpnsResolve(Vec<u8>),
+}
+
+/// The identifier of a queued data command: its sequence number in the data queue.
+pub type RequestId = u64;
+
+/// How far the offchain workers have got with a queued data command.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum RequestStatus<Hash> {
+	/// Waiting in the data queue.
+	Queued,
+	/// Picked up by an offchain worker, which retries it after a failed attempt.
+	Processing,
+	/// Completed, with the hash of the result: the `Cid` added, pinned, unpinned or removed, or
+	/// the data fetched.
+	Done(Hash),
+	/// Given up on after `MaxRetries` retries, with the error of the last attempt.
+	Failed(DispatchError),
+}
+
+impl<Hash> RequestStatus<Hash> {
+	/// Whether the command completed or was given up on.
+	pub fn is_final(&self) -> bool {
+		matches!(self, RequestStatus::Done(_) | RequestStatus::Failed(_))
+	}
+
+	// whether a worker's report of `next` replaces the status; every node processes the queue, so
+	// a command one of them completed stays completed and a failed one can only still complete
+	fn may_become(&self, next: &Self) -> bool {
+		match (self, next) {
+			(_, RequestStatus::Queued) | (RequestStatus::Done(_), _) => false,
+			(RequestStatus::Failed(_), next) => matches!(next, RequestStatus::Done(_)),
+			_ => true,
+		}
+	}
+}
+
+/// A data command the offchain workers gave up on, kept for operators to replay or purge.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct DeadLetter<AccountId, Hash, BlockNumber> {
+	/// The account the command was queued for, if any.
+	pub caller: Option<AccountId>,
+	/// The command itself.
+	pub cmd: DataCommand<AccountId>,
+	/// The hash of the encoded command, which its results and incidents refer to.
+	pub payload_hash: Hash,
+	/// The error of the last attempt.
+	pub reason: DispatchError,
+	/// The block the command was dead-lettered in.
+	pub block: BlockNumber,
+}
+
+/// A command of an `ipfs_batch`, queued as if issued through the call of the same name.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum IpfsCommand {
+	/// The `Multiaddr` to connect to.
+	Connect(Vec<u8>),
+	/// The data to add.
+	AddBytes(Vec<u8>),
+	/// The `Cid` to cat.
+	CatBytes(Vec<u8>),
+	/// The `Cid` to pin, recursively or not.
+	InsertPin(Vec<u8>, bool),
+	/// The `Cid` to unpin, recursively or not.
+	RemovePin(Vec<u8>, bool),
+	/// The `Cid` of the block to remove.
+	RemoveBlock(Vec<u8>),
+}
+
+impl IpfsCommand {
+	// the number of payload bytes the command carries
+	fn data_len(&self) -> usize {
+		match self {
+			IpfsCommand::AddBytes(data) => data.len(),
+			_ => 0,
+		}
+	}
+}
+
+/// A pubsub subscription change or publication queued for the offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum PubsubCommand {
+	Subscribe(Vec<u8>),
+	Publish(Vec<u8>, Vec<u8>),
+	Unsubscribe(Vec<u8>),
+}
+
+impl<AccountId> DataCommand<AccountId> {
+	// the deadline (in ms) the caller asked for, if any
+	fn timeout(&self) -> Option<u64> {
+		match self {
+			DataCommand::AddBytes(_, _, _, timeout)
+			| DataCommand::AddEncrypted(_, _, timeout)
+			| DataCommand::CatBytes(_, timeout) => *timeout,
+			_ => None,
+		}
+	}
+}
+
+// A data request to the IPFS node, run through `DataJobs`.
+#[derive(Encode, Decode, Clone)]
+struct DataJob<AccountId> {
+	cmd: DataCommand<AccountId>,
+	// the queued command the request belongs to, whose status is reported; `None` for requests
+	// the worker made on its own
+	request: Option<RequestId>,
+	// whether retries always get `BackgroundDeadline` rather than `FastDeadline`, as for content
+	// that has to be fetched from other peers
+	background: bool,
+}
+
+/// A DHT query or announcement queued for the offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum DhtCommand {
+	FindPeer(Vec<u8>),
+	GetProviders(Vec<u8>),
+	Provide(Vec<u8>),
+}
+
+/// The kinds of data commands governance can pause independently.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum CommandKind {
+	/// Adding data, directly or on behalf of a service.
+	Add,
+	/// Retrieving data.
+	Cat,
+	/// Pinning and unpinning.
+	Pin,
+}
+
+/// Whether registered content is meant to be pinned by the IPFS node.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum PinStatus {
+	Pinned,
+	Unpinned,
+}
+
+/// The priority class of a queued data command; higher classes are processed first.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug, TypeInfo)]
+pub enum Priority {
+	/// Pin maintenance: pins, unpins and removals, and the commands their submitters paid to
+	/// bump up to it.
+	High,
+	/// The default for adds and cats.
+	Normal,
+	/// Bulk work that may wait, such as chunked uploads.
+	Low,
+}
+
+/// How content is pinned by the IPFS node.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum PinMode {
+	/// Only the root block is pinned.
+	Direct,
+	/// The root block and everything it links to are pinned.
+	Recursive,
+}
+
+impl PinMode {
+	/// The `recursive` flag of the corresponding IPFS pin request.
+	pub fn is_recursive(self) -> bool {
+		self == PinMode::Recursive
+	}
+}
+
+impl From<bool> for PinMode {
+	fn from(recursive: bool) -> Self {
+		if recursive {
+			PinMode::Recursive
+		} else {
+			PinMode::Direct
+		}
+	}
+}
+
+/// Ownership and lifecycle information about a piece of content stored through the pallet.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ContentInfo<AccountId, BlockNumber, Balance> {
+	/// The account that added (or first pinned) the content; only it or root may unpin or remove
+	/// it.
+	pub owner: AccountId,
+	/// The block in which the content was registered.
+	pub added_at: BlockNumber,
+	/// The most recently requested pin status.
+	pub pin_status: PinStatus,
+	/// The mode of the most recent pin request.
+	pub pin_mode: PinMode,
+	/// The size of the content in bytes, if it was added through the pallet.
+	pub size: Option<u64>,
+	/// The block the content's lease expires in, after which it is unpinned and removed.
+	pub expires_at: Option<BlockNumber>,
+	/// The schema the content declares to follow, if any.
+	pub schema: Option<SchemaId>,
+	/// The storage deposit reserved from the owner, released when the block is removed.
+	pub deposit: Balance,
+}
+
+/// An appeal of a content owner against the denial of its content's `Cid`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Appeal<AccountId, Balance, BlockNumber> {
+	/// The owner of the removed content, who filed the appeal.
+	pub appellant: AccountId,
+	/// The deposit reserved from the appellant, slashed if the appeal is dismissed.
+	pub deposit: Balance,
+	/// The `Cid`s of the documents supporting the appeal.
+	pub evidence: Vec<Vec<u8>>,
+	/// The block the `Cid` is allowed again in unless the appeal was ruled on before.
+	pub deadline: BlockNumber,
+}
+
+/// What an account has used of its quotas.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct AccountUsage<BlockNumber> {
+	/// The quota era `bytes` were counted in.
+	pub era: u32,
+	/// The number of bytes added or published in `era`.
+	pub bytes: u64,
+	/// The block `commands` were counted in.
+	pub block: BlockNumber,
+	/// The number of commands issued in `block`.
+	pub commands: u32,
+}
+
+/// The identifier of a chunked upload.
+pub type UploadId = u32;
+
+/// A chunked upload in progress.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct UploadInfo<AccountId, Hash> {
+	/// The account uploading the data.
+	pub owner: AccountId,
+	/// The number of chunks the data is split into.
+	pub total_chunks: u32,
+	/// The hash the assembled data has to have.
+	pub content_hash: Hash,
+	/// The number of chunks received so far.
+	pub received_chunks: u32,
+	/// The number of bytes received so far.
+	pub size: u64,
+	/// The class sniffed from the first chunk, once it was received.
+	pub class: Option<ContentClass>,
+}
+
+/// The identifier of a schema in the schema registry.
+pub type SchemaId = u32;
+
+/// A schema structured content can declare to follow.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct SchemaInfo<AccountId> {
+	/// The account that registered the schema.
+	pub owner: AccountId,
+	/// CID of the JSON-schema or IPLD schema document.
+	pub cid: Vec<u8>,
+}
+
+/// The identifier of a dApp registered in the service registry.
+pub type ServiceId = u32;
+
+/// A dApp publisher consuming the storage layer; quotas and usage are tracked per service rather
+/// than per end-user account.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ServiceInfo<AccountId> {
+	/// The account that registered, and administers, the service.
+	pub owner: AccountId,
+	/// Human-readable app name.
+	pub name: Vec<u8>,
+	/// CID of the app's metadata document.
+	pub metadata_cid: Vec<u8>,
+	/// The number of bytes the publisher asked to be able to store.
+	pub requested_quota: u64,
+	/// The number of bytes granted by governance; nothing can be stored until this is set.
+	pub granted_quota: u64,
+	/// The number of bytes queued for storage on behalf of the service so far.
+	pub used_bytes: u64,
+}
+
+/// IPFS health figures reported by an offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct NodeHealth<BlockNumber> {
+	/// The block the figures were collected in.
+	pub reported_at: BlockNumber,
+	/// The number of connected peers.
+	pub peers: u32,
+	/// The size of the IPFS repository in bytes, if the content store reports it.
+	pub repo_size: Option<u64>,
+	/// The number of recursive pins, if the content store reports it.
+	pub pins: Option<u64>,
+	/// The last block in which one of the node's IPFS requests succeeded.
+	pub last_success: Option<BlockNumber>,
+}
+
+/// Feedback of an offchain worker submitted through `submit_report`, without a transaction fee.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum WorkerReport<AccountId, BlockNumber> {
+	/// The `Cid` of `size` bytes of data added on behalf of `owner`; see `submit_cid`.
+	Cid { owner: AccountId, cid: Vec<u8>, size: u64, lease: Option<u32>, encrypted: bool },
+	/// The node's health figures; see `submit_node_stats`.
+	Stats(NodeHealth<BlockNumber>),
+	/// The `Cid`s the node keeps pinned; see `attest_pins`.
+	Attestations(Vec<Vec<u8>>),
+	/// The IPNS `name` of `owner`, published to point at `cid`; see `ipfs_ipns_publish`.
+	IpnsPublished { owner: AccountId, name: Vec<u8>, cid: Vec<u8> },
+	/// The `Cid` the IPNS `name` resolved to; see `ipfs_ipns_resolve`.
+	IpnsResolved { name: Vec<u8>, cid: Vec<u8> },
+}
+
+/// A `WorkerReport` signed with the offchain worker key of a reporter.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ReportPayload<Public, AccountId, BlockNumber> {
+	/// The key the report is signed with; its account has to be a reporter.
+	pub public: Public,
+	/// The block the report was made in. The report is accepted at most once, within
+	/// `UnsignedLongevity` blocks of it.
+	pub block_number: BlockNumber,
+	/// The report itself.
+	pub report: WorkerReport<AccountId, BlockNumber>,
+}
+
+impl<T: SigningTypes> SignedPayload<T> for ReportPayload<T::Public, T::AccountId, T::BlockNumber> {
+	fn public(&self) -> T::Public {
+		self.public.clone()
+	}
+}
+
+/// How far an offchain worker has got re-pinning registered content missing from its repo.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct RecoveryProgress {
+	/// The recovery round the scan was started in.
+	pub round: u32,
+	/// The number of pinned registry entries checked so far.
+	pub checked: u32,
+	/// The number of checked entries that were missing from the repo.
+	pub missing: u32,
+	/// The number of missing entries pinned again; the rest are retried in the background.
+	pub repinned: u32,
+	/// Whether the whole registry has been checked.
+	pub done: bool,
+}
+
+/// The identifier of a storage challenge.
+pub type ChallengeId = u64;
+
+/// A proof-of-storage challenge: the assigned pinners of a `Cid` have to prove they hold it by
+/// hashing a byte range of it together with a random seed.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct StorageChallenge<AccountId, Hash, BlockNumber> {
+	/// The challenged content.
+	pub cid: Vec<u8>,
+	/// The first byte of the range to hash.
+	pub offset: u64,
+	/// The length of the range to hash.
+	pub len: u32,
+	/// The seed the range is hashed with, so responses can't be computed in advance.
+	pub seed: Hash,
+	/// The block in which the challenge is resolved; pinners that haven't responded by then fail.
+	pub deadline: BlockNumber,
+	/// The bonded pinners that have to respond.
+	pub pinners: Vec<AccountId>,
+}
+
+/// The identifier of a content bucket.
+pub type BucketId = u32;
+
+/// A role an account holds in a content bucket.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum BucketRole {
+	/// May retrieve the bucket's content from gateways.
+	Reader,
+	/// May also add content to the bucket, and pin, unpin and remove the content in it.
+	Writer,
+}
+
+/// The identifier of a scheduled task.
+pub type ScheduleId = u32;
+
+/// What a scheduled task does with its `Cid`.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum ScheduledCommand {
+	/// Pin the content again, recursively or not.
+	Pin(bool),
+	/// Cat the content, checking that it can still be retrieved; the result is reported like
+	/// that of `ipfs_cat_bytes`.
+	Cat,
+}
+
+/// The IPNS name an account publishes its mutable pointer under.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct IpnsBinding<AccountId> {
+	/// The IPNS name.
+	pub name: Vec<u8>,
+	/// The `Cid` the name was last published to point at.
+	pub cid: Vec<u8>,
+	/// The reporter whose IPFS node holds the name's key, and so publishes its updates.
+	pub publisher: AccountId,
+}
+
+/// A data command queued every `period` blocks.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct ScheduleInfo<AccountId, BlockNumber> {
+	/// The account that scheduled the task.
+	pub owner: AccountId,
+	/// The content the command is issued for.
+	pub cid: Vec<u8>,
+	/// The command to issue.
+	pub command: ScheduledCommand,
+	/// The number of blocks between two runs.
+	pub period: BlockNumber,
+}
+
+/// A named collection of content shared between accounts, such as a shared document store.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct BucketInfo<AccountId> {
+	/// The account that created the bucket; it is a `Writer` and grants the roles of others.
+	pub owner: AccountId,
+	/// The name of the bucket, unique among its owner's buckets.
+	pub name: Vec<u8>,
+}
+
+/// A role an account holds in the namespace of a service, in which the service's clients bind
+/// names to content without colliding with those of other services.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum NamespaceRole {
+	/// May bind names to content it owns, within its member quota, and retag and unbind the
+	/// names it bound.
+	Writer,
+	/// May also grant and revoke roles, set member quotas, and retag and unbind any name.
+	Admin,
+}
+
+/// A name bound in the namespace of a service.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct NamespaceEntry<AccountId> {
+	/// The `Cid` the name points at.
+	pub cid: Vec<u8>,
+	/// The account that bound the name.
+	pub bound_by: AccountId,
+	/// The size of the content, counted against the member quota of `bound_by` until the name
+	/// is unbound.
+	pub size: u64,
+	/// The labels the service's clients find and group content by.
+	pub tags: Vec<Vec<u8>>,
+}
+
+/// The bytes a member of a namespace may bind names to, and has bound names to.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct MemberQuota {
+	/// The number of bytes granted by a namespace admin.
+	pub granted: u64,
+	/// The number of bytes of the content the member's names point at.
+	pub used: u64,
+}
+
+// A node-local scan of the content registry for pinned content missing from the repo.
+#[derive(Encode, Decode)]
+struct RecoveryScan {
+	// the raw storage key of the last registry entry checked
+	cursor: Option<Vec<u8>>,
+	// the CIDs of the blocks in the repo when the scan started
+	local: BTreeSet<Vec<u8>>,
+	progress: RecoveryProgress,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{pallet_prelude::*, traits::EnsureOrigin, weights::DispatchClass};
+	use frame_system::{
+		ensure_none, ensure_root,
+		offchain::{AppCrypto, CreateSignedTransaction},
+		pallet_prelude::*,
+	};
+
+	/// The storage layout version; see the `migrations` module.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// The pallet's configuration trait.
+	#[pallet::config]
+	pub trait Config: CreateSignedTransaction<Call<Self>> + frame_system::Config {
+		/// The identifier type for the offchain worker's signing key.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+		/// The overarching event type.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The currency storage deposits are reserved in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// The deposit reserved per byte of data added to IPFS, refunded when the block is removed.
+		type ByteDeposit: Get<BalanceOf<Self>>;
+		/// The accounts other pallets publish protocol documents from, such as mpesa-user's
+		/// receipts; their adds reserve no deposit and count against no quota.
+		type ProtocolAccounts: Contains<Self::AccountId>;
+		/// The protocol account the archives of pruned logs are published under.
+		type ArchiveAccount: Get<Self::AccountId>;
+		/// The backend serving the offchain worker's IPFS requests: `Kubo`, or `EmbeddedIpfs` when
+		/// built with the `embedded-ipfs` feature against the IPFS-enabled Substrate fork.
+		type ContentStore: ContentStore;
+		/// The maximum number of commands each of the connection, data and DHT queues may hold.
+		#[pallet::constant]
+		type MaxQueueLen: Get<u32>;
+		/// The maximum number of commands an `ipfs_batch` may carry.
+		#[pallet::constant]
+		type MaxBatchLen: Get<u32>;
+		/// The deadline (in ms) of connection, DHT, pubsub and metadata requests.
+		#[pallet::constant]
+		type RequestTimeout: Get<u64>;
+		/// The longest deadline (in ms) callers may ask for when adding or catting data.
+		#[pallet::constant]
+		type MaxRequestTimeout: Get<u64>;
+		/// The deadline (in ms) data requests get on their first attempt. No reference-hardware
+		/// measurements back a default yet: size it from the `latency` summaries the offchain
+		/// worker logs.
+		#[pallet::constant]
+		type FastDeadline: Get<u64>;
+		/// The deadline (in ms) adds and cats that timed out get when retried in the background;
+		/// like `FastDeadline`, it has no measured default yet.
+		#[pallet::constant]
+		type BackgroundDeadline: Get<u64>;
+		/// How many times a failed data request is retried before it is abandoned.
+		#[pallet::constant]
+		type MaxRetries: Get<u32>;
+		/// How often (in blocks) the offchain worker re-announces all pinned content to the DHT;
+		/// zero disables re-announcing.
+		#[pallet::constant]
+		type ReprovideInterval: Get<Self::BlockNumber>;
+		/// The size (in bytes) an IPFS node's repository may grow to before the offchain worker
+		/// evicts the oldest content without a lease.
+		#[pallet::constant]
+		type MaxRepoSize: Get<u64>;
+		/// The number of reporters that should keep each pinned `Cid` pinned.
+		#[pallet::constant]
+		type ReplicationFactor: Get<u32>;
+		/// The longest an access grant may last, in blocks.
+		#[pallet::constant]
+		type MaxAccessTtl: Get<Self::BlockNumber>;
+		/// How often (in blocks) the offchain workers attest the pins they are assigned; pinned
+		/// content fewer than `ReplicationFactor` assigned reporters attested within the last two
+		/// intervals is under-replicated.
+		#[pallet::constant]
+		type AttestationInterval: Get<Self::BlockNumber>;
+		/// The pin mode used for pins the caller didn't choose a mode for, until governance sets
+		/// one.
+		type DefaultPinPolicy: Get<PinMode>;
+		/// How many blocks DHT and cat results are kept after the request was made.
+		type ResultTtl: Get<Self::BlockNumber>;
+		/// The priority of the unsigned transactions carrying request results; should be high
+		/// enough for the chain to learn the outcome of its offchain work under congestion.
+		type UnsignedPriority: Get<TransactionPriority>;
+		/// How many blocks after it was made a report submitted through `submit_report` is valid.
+		#[pallet::constant]
+		type UnsignedLongevity: Get<Self::BlockNumber>;
+		/// Whether the offchain worker checks fetched content against its declared schema.
+		type ValidateSchemas: Get<bool>;
+		/// The length of a quota era in blocks; with zero, the first era never ends.
+		#[pallet::constant]
+		type EraLength: Get<Self::BlockNumber>;
+		/// The number of bytes an account may add or publish per era.
+		#[pallet::constant]
+		type MaxBytesPerAccountPerEra: Get<u64>;
+		/// The number of IPFS commands an account may issue per block.
+		#[pallet::constant]
+		type MaxCommandsPerBlock: Get<u32>;
+		/// The number of scheduled tasks an account may have.
+		#[pallet::constant]
+		type MaxSchedulesPerAccount: Get<u32>;
+		/// The number of bytes the data-carrying extrinsics of a block may carry in total; see
+		/// `CheckBlockData`.
+		#[pallet::constant]
+		type MaxBlockDataLen: Get<u32>;
+		/// The deposit reserved per block a lease is extended by, released with the content.
+		type LeaseDepositPerBlock: Get<BalanceOf<Self>>;
+		/// The fee burned from the submitter of a data command to bump it; see `bump_request`.
+		#[pallet::constant]
+		type BumpFee: Get<BalanceOf<Self>>;
+		/// The number of data commands the offchain worker processes per block, highest priority
+		/// first.
+		type DataBudget: Get<u32>;
+		/// The number of reporters assigned to each data command; the others leave it to them.
+		#[pallet::constant]
+		type WorkersPerCommand: Get<u32>;
+		/// How many blocks a data command no assigned reporter picked up waits before any node
+		/// may process it.
+		#[pallet::constant]
+		type ClaimGracePeriod: Get<Self::BlockNumber>;
+		/// The origin allowed to administer the peer list without a signed user account.
+		type AdminOrigin: EnsureOrigin<Self::Origin>;
+		/// The origin allowed to deny `Cid`s the IPFS nodes refuse to host, and to allow them
+		/// again.
+		type ModerationOrigin: EnsureOrigin<Self::Origin>;
+		/// The origin ruling on appeals against denials, e.g. a council.
+		type AppealOrigin: EnsureOrigin<Self::Origin>;
+		/// The origin allowed to administer the namespace of the given service besides the
+		/// namespace's admins, e.g. governance stepping in for a dApp.
+		type NamespaceAdminOrigin: EnsureOriginWithArg<Self::Origin, ServiceId>;
+		/// The deposit reserved from the owner of denied content to appeal the denial.
+		#[pallet::constant]
+		type AppealDeposit: Get<BalanceOf<Self>>;
+		/// The number of blocks `AppealOrigin` has to rule on an appeal; a denial that wasn't
+		/// ruled on by then is lifted.
+		#[pallet::constant]
+		type AppealPeriod: Get<Self::BlockNumber>;
+		/// The source of the seeds storage challenges are picked with.
+		type Randomness: Randomness<Self::Hash, Self::BlockNumber>;
+		/// The bond a reporter reserves to be assigned storage challenges.
+		#[pallet::constant]
+		type StorageBond: Get<BalanceOf<Self>>;
+		/// The amount slashed from the bond of a pinner that misses or fails a storage challenge.
+		#[pallet::constant]
+		type ChallengeSlash: Get<BalanceOf<Self>>;
+		/// The number of storage challenges issued at the start of every quota era.
+		#[pallet::constant]
+		type ChallengesPerEra: Get<u32>;
+		/// The number of blocks pinners have to respond to a storage challenge.
+		#[pallet::constant]
+		type ChallengePeriod: Get<Self::BlockNumber>;
+		/// Weight information for the pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+		/// Receives the offchain workers' results, e.g. for the pallets queueing work through
+		/// `IpfsHandler`; requests are identified by the hash of their encoded command.
+		type OnResult: OnIpfsResult<Self::AccountId, Self::Hash>;
+		/// Keeps promises of the data commands' and DHT queries' outcomes, under their request
+		/// ids and request hashes respectively; a data command's promise settles with its first
+		/// final status.
+		type Promises: PromiseRegistry<Self::Hash>;
+		/// Pushes the final statuses of the data commands queued for an account to the account's
+		/// webhook.
+		type Webhooks: WebhookNotifier<Self::AccountId, Self::Hash>;
+	}
+
+	// A list of addresses to connect to and disconnect from.
+	#[pallet::storage]
+	pub(super) type ConnectionQueue<T> = StorageValue<_, Vec<ConnectionCommand>, ValueQuery>;
+
+	// The addresses the IPFS nodes should stay connected to; missing connections are
+	// re-established by the offchain workers.
+	#[pallet::storage]
+	#[pallet::getter(fn is_desired_peer)]
+	pub type DesiredPeers<T> = StorageMap<_, Blake2_128Concat, Vec<u8>, bool, ValueQuery>;
+
+	// Peers the IPFS nodes dial on startup and stay connected to, set by `AdminOrigin`.
+	#[pallet::storage]
+	#[pallet::getter(fn bootstrap_peers)]
+	pub type BootstrapPeers<T> = StorageValue<_, Vec<OpaqueMultiaddr>, ValueQuery>;
+
+	// A queue of data to publish or obtain on IPFS, keyed by sequence number.
+	#[pallet::storage]
+	pub(super) type DataQueue<T: Config> =
+		StorageMap<_, Twox64Concat, u64, (Priority, DataCommand<T::AccountId>)>;
+
+	// The block each command in `DataQueue` was queued in.
+	#[pallet::storage]
+	#[pallet::getter(fn data_queued_at)]
+	pub type DataQueuedAt<T: Config> = StorageMap<_, Twox64Concat, u64, T::BlockNumber>;
+
+	// The sequence number of the oldest command still in `DataQueue`.
+	#[pallet::storage]
+	#[pallet::getter(fn data_queue_head)]
+	pub type DataQueueHead<T> = StorageValue<_, u64, ValueQuery>;
+
+	// The sequence number the next queued data command will receive.
+	#[pallet::storage]
+	#[pallet::getter(fn data_queue_tail)]
+	pub type DataQueueTail<T> = StorageValue<_, u64, ValueQuery>;
+
+	// Commands with a lower sequence number were acknowledged as processed by an offchain
+	// worker and are pruned in the next block.
+	#[pallet::storage]
+	#[pallet::getter(fn data_queue_acked)]
+	pub type DataQueueAcked<T> = StorageValue<_, u64, ValueQuery>;
+
+	// The status of each queued data command, as reported by the offchain workers; kept for
+	// `ResultTtl` blocks once the command completed or was given up on.
+	#[pallet::storage]
+	#[pallet::getter(fn request_status)]
+	pub type Requests<T: Config> = StorageMap<_, Twox64Concat, RequestId, RequestStatus<T::Hash>>;
+
+	// The requests whose final status expires at the given block.
+	#[pallet::storage]
+	pub type RequestExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<RequestId>, ValueQuery>;
+
+	// The account each data command was queued for; commands the pallet queues on its own have
+	// none. Dropped along with the request's status.
+	#[pallet::storage]
+	#[pallet::getter(fn request_caller)]
+	pub type RequestCallers<T: Config> = StorageMap<_, Twox64Concat, RequestId, T::AccountId>;
+
+	// The block each bumped data command was last bumped in; its attempts get `MaxRequestTimeout`.
+	// Dropped along with the request's status.
+	#[pallet::storage]
+	#[pallet::getter(fn bumped_at)]
+	pub type BumpedAt<T: Config> = StorageMap<_, Twox64Concat, RequestId, T::BlockNumber>;
+
+	// Data commands the offchain workers gave up on, keyed by their request, until an operator
+	// replays or purges them.
+	#[pallet::storage]
+	#[pallet::getter(fn dead_letter)]
+	pub type DeadLetters<T: Config> =
+		StorageMap<_, Twox64Concat, RequestId, DeadLetter<T::AccountId, T::Hash, T::BlockNumber>>;
+
+	// The number of entries in `DeadLetters`.
+	#[pallet::storage]
+	#[pallet::getter(fn dead_letter_count)]
+	pub type DeadLetterCount<T> = StorageValue<_, u32, ValueQuery>;
+
+	// The IPNS name each account published, as reported by the offchain workers.
+	#[pallet::storage]
+	#[pallet::getter(fn ipns_name)]
+	pub type IpnsNames<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, IpnsBinding<T::AccountId>>;
+
+	// A list of requests to the DHT.
+	#[pallet::storage]
+	pub type DhtQueue<T> = StorageValue<_, Vec<DhtCommand>, ValueQuery>;
+
+	// A list of pubsub subscriptions, publications and unsubscriptions.
+	#[pallet::storage]
+	pub(super) type PubsubQueue<T> = StorageValue<_, Vec<PubsubCommand>, ValueQuery>;
+
+	// The pubsub topics the IPFS nodes are subscribed to.
+	#[pallet::storage]
+	#[pallet::getter(fn pubsub_topics)]
+	pub type PubsubTopics<T> = StorageValue<_, Vec<Vec<u8>>, ValueQuery>;
+
+	// The accounts that subscribed to each pubsub topic; the nodes unsubscribe once the last
+	// one leaves.
+	#[pallet::storage]
+	#[pallet::getter(fn pubsub_subscribers)]
+	pub type PubsubSubscribers<T: Config> =
+		StorageMap<_, Blake2_128Concat, Vec<u8>, Vec<T::AccountId>, ValueQuery>;
+
+	// dApps registered as consumers of the storage layer.
+	#[pallet::storage]
+	#[pallet::getter(fn service)]
+	pub type Services<T: Config> =
+		StorageMap<_, Twox64Concat, ServiceId, ServiceInfo<T::AccountId>>;
+
+	// The identifier the next registered service will receive.
+	#[pallet::storage]
+	#[pallet::getter(fn next_service_id)]
+	pub type NextServiceId<T> = StorageValue<_, ServiceId, ValueQuery>;
+
+	// Chunked uploads that haven't been finalized yet.
+	#[pallet::storage]
+	#[pallet::getter(fn upload)]
+	pub type Uploads<T: Config> =
+		StorageMap<_, Twox64Concat, UploadId, UploadInfo<T::AccountId, T::Hash>>;
+
+	// The chunks received for each upload; their data is staged in the off-chain index.
+	#[pallet::storage]
+	#[pallet::getter(fn has_chunk)]
+	pub type UploadChunks<T> =
+		StorageDoubleMap<_, Twox64Concat, UploadId, Twox64Concat, u32, bool, ValueQuery>;
+
+	// The identifier the next upload will receive.
+	#[pallet::storage]
+	#[pallet::getter(fn next_upload_id)]
+	pub type NextUploadId<T> = StorageValue<_, UploadId, ValueQuery>;
+
+	// Schemas registry entries can declare to follow.
+	#[pallet::storage]
+	#[pallet::getter(fn schema)]
+	pub type Schemas<T: Config> = StorageMap<_, Twox64Concat, SchemaId, SchemaInfo<T::AccountId>>;
+
+	// The identifier the next registered schema will receive.
+	#[pallet::storage]
+	#[pallet::getter(fn next_schema_id)]
+	pub type NextSchemaId<T> = StorageValue<_, SchemaId, ValueQuery>;
+
+	// The CIDs of the data each account added, as reported by the offchain worker.
+	#[pallet::storage]
+	#[pallet::getter(fn cids_of)]
+	pub type CidByAccount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, Vec<Vec<u8>>, ValueQuery>;
+
+	// Ownership and lifecycle of the content stored through the pallet, keyed by `Cid`.
+	#[pallet::storage]
+	#[pallet::getter(fn content)]
+	pub type ContentRegistry<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		Vec<u8>,
+		ContentInfo<T::AccountId, T::BlockNumber, BalanceOf<T>>,
+	>;
+
+	// The registered content the offchain workers encrypted before adding it.
+	#[pallet::storage]
+	#[pallet::getter(fn is_encrypted)]
+	pub type EncryptedContent<T> = StorageMap<_, Blake2_128Concat, Vec<u8>, bool, ValueQuery>;
+
+	// The `Cid`s the IPFS nodes refuse to host, set by `ModerationOrigin`.
+	#[pallet::storage]
+	#[pallet::getter(fn is_denied)]
+	pub type Denylist<T> = StorageMap<_, Blake2_128Concat, Vec<u8>, bool, ValueQuery>;
+
+	// The owner of the content removed under each denied `Cid`, who may appeal the denial.
+	#[pallet::storage]
+	#[pallet::getter(fn denied_owner)]
+	pub type DeniedOwners<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, T::AccountId>;
+
+	// The appeals awaiting a ruling, by denied `Cid`.
+	#[pallet::storage]
+	#[pallet::getter(fn appeal)]
+	pub type Appeals<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		Vec<u8>,
+		Appeal<T::AccountId, BalanceOf<T>, T::BlockNumber>,
+	>;
+
+	// The appeals whose deadline falls in each block.
+	#[pallet::storage]
+	pub(super) type AppealExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<Vec<u8>>, ValueQuery>;
+
+	// CIDs imported from an existing pinset, in import order; pinned by the offchain workers in
+	// batches.
+	#[pallet::storage]
+	#[pallet::getter(fn imported_pin)]
+	pub type ImportedPins<T> = StorageMap<_, Twox64Concat, u32, Vec<u8>>;
+
+	// The number of entries in `ImportedPins`.
+	#[pallet::storage]
+	#[pallet::getter(fn imported_pin_count)]
+	pub type ImportedPinCount<T> = StorageValue<_, u32, ValueQuery>;
+
+	// Accounts whose offchain workers may report request results.
+	#[pallet::storage]
+	#[pallet::getter(fn reporters)]
+	pub type Reporters<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+	// The last block each reporter's offchain worker submitted a report in, or the reporter was
+	// added in.
+	#[pallet::storage]
+	#[pallet::getter(fn last_reported)]
+	pub type LastReported<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber>;
+
+	// The reporters whose offchain workers submitted no report in the last `REPORTING_WINDOW`
+	// blocks as of the last check, e.g. because their node is down or has no signing key.
+	#[pallet::storage]
+	#[pallet::getter(fn unreported_workers)]
+	pub type UnreportedWorkers<T: Config> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
+
+	// DHT and cat requests whose result hasn't been submitted yet, keyed by the hash of the
+	// encoded command.
+	#[pallet::storage]
+	#[pallet::getter(fn is_result_pending)]
+	pub type PendingResults<T: Config> = StorageMap<_, Identity, T::Hash, bool, ValueQuery>;
+
+	// Addresses found by `FindPeer` and peer IDs found by `GetProviders` requests, keyed by
+	// the hash of the encoded `DhtCommand`, along with the block they were submitted in.
+	#[pallet::storage]
+	#[pallet::getter(fn dht_result)]
+	pub type DhtResults<T: Config> =
+		StorageMap<_, Identity, T::Hash, (T::BlockNumber, Vec<Vec<u8>>)>;
+
+	// Data obtained by cat requests, keyed by the hash of the encoded `DataCommand`, along with
+	// the block it was submitted in.
+	#[pallet::storage]
+	#[pallet::getter(fn cat_result)]
+	pub type CatResults<T: Config> = StorageMap<_, Identity, T::Hash, (T::BlockNumber, Vec<u8>)>;
+
+	// The requests whose pending status and results expire at the given block.
+	#[pallet::storage]
+	pub type ResultExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<T::Hash>, ValueQuery>;
+
+	// The block in which an account's access to a `Cid` granted by its owner expires.
+	#[pallet::storage]
+	#[pallet::getter(fn access_grant)]
+	pub type AccessGrants<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		Vec<u8>,
+		Blake2_128Concat,
+		T::AccountId,
+		T::BlockNumber,
+	>;
+
+	// The access grants that expire in the given block.
+	#[pallet::storage]
+	pub type AccessExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<(Vec<u8>, T::AccountId)>, ValueQuery>;
+
+	// The CIDs whose lease expires in the given block.
+	#[pallet::storage]
+	pub type LeaseExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<Vec<u8>>, ValueQuery>;
+
+	// The first block whose lease, access, result and request expiries `on_idle` hasn't swept yet.
+	#[pallet::storage]
+	#[pallet::getter(fn maintenance_cursor)]
+	pub type MaintenanceCursor<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+	// The current quota era; counters of earlier eras are treated as zero.
+	#[pallet::storage]
+	#[pallet::getter(fn current_era)]
+	pub type CurrentEra<T> = StorageValue<_, u32, ValueQuery>;
+
+	// Per-account usage of the `MaxBytesPerAccountPerEra` and `MaxCommandsPerBlock` quotas.
+	#[pallet::storage]
+	#[pallet::getter(fn usage)]
+	pub type Usage<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, AccountUsage<T::BlockNumber>, ValueQuery>;
+
+	// The number of bytes carried by the data-carrying extrinsics of the current block.
+	#[pallet::storage]
+	#[pallet::getter(fn block_data_len)]
+	pub type BlockDataLen<T> = StorageValue<_, u32, ValueQuery>;
+
+	#[pallet::type_value]
+	pub fn PinPolicyOnEmpty<T: Config>() -> PinMode {
+		T::DefaultPinPolicy::get()
+	}
+
+	// The pin mode used for pins the caller didn't choose a mode for.
+	#[pallet::storage]
+	#[pallet::getter(fn pin_policy)]
+	pub type PinPolicy<T> = StorageValue<_, PinMode, ValueQuery, PinPolicyOnEmpty<T>>;
+
+	// Bitmap of the experimental call groups in `features` that are enabled.
+	#[pallet::storage]
+	#[pallet::getter(fn feature_flags)]
+	pub type FeatureFlags<T> = StorageValue<_, u32, ValueQuery>;
+
+	// The filters data added through the add paths has to pass.
+	#[pallet::storage]
+	#[pallet::getter(fn ingress_policy)]
+	pub type IngressFilters<T> = StorageValue<_, IngressPolicy, ValueQuery>;
+
+	// The latest health figures reported by an offchain worker.
+	#[pallet::storage]
+	#[pallet::getter(fn node_stats)]
+	pub type NodeStats<T: Config> = StorageValue<_, NodeHealth<T::BlockNumber>>;
+
+	// The kinds of data commands that are currently not accepted.
+	#[pallet::storage]
+	#[pallet::getter(fn is_paused)]
+	pub type Paused<T> = StorageMap<_, Twox64Concat, CommandKind, bool, ValueQuery>;
+
+	// The current recovery round; offchain workers that haven't scanned their repo in it do so.
+	#[pallet::storage]
+	#[pallet::getter(fn recovery_round)]
+	pub type RecoveryRound<T> = StorageValue<_, u32, ValueQuery>;
+
+	// The latest recovery progress reported by each reporter.
+	#[pallet::storage]
+	#[pallet::getter(fn recovery_of)]
+	pub type Recoveries<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, RecoveryProgress>;
+
+	// The block in which each assigned reporter last attested that it keeps a `Cid` pinned.
+	#[pallet::storage]
+	#[pallet::getter(fn attested_at)]
+	pub type Attestations<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		Vec<u8>,
+		Blake2_128Concat,
+		T::AccountId,
+		T::BlockNumber,
+	>;
+
+	// Pinned content that too few of its assigned reporters attested recently.
+	#[pallet::storage]
+	#[pallet::getter(fn is_under_replicated)]
+	pub type UnderReplicatedContent<T> = StorageMap<_, Blake2_128Concat, Vec<u8>, bool, ValueQuery>;
+
+	// The raw registry key the under-replication check continues after.
+	#[pallet::storage]
+	pub(super) type ReplicationCursor<T> = StorageValue<_, Vec<u8>>;
+
+	// The storage bonds of reporters; only bonded reporters are challenged.
+	#[pallet::storage]
+	#[pallet::getter(fn storage_bond)]
+	pub type StorageBonds<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>>;
+
+	// The number of unresolved storage challenges each bonded reporter is a party to.
+	#[pallet::storage]
+	#[pallet::getter(fn open_challenges)]
+	pub type OpenChallenges<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	// The identifier of the next storage challenge.
+	#[pallet::storage]
+	pub(super) type NextChallengeId<T> = StorageValue<_, ChallengeId, ValueQuery>;
+
+	// Unresolved storage challenges.
+	#[pallet::storage]
+	#[pallet::getter(fn challenge)]
+	pub type Challenges<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		ChallengeId,
+		StorageChallenge<T::AccountId, T::Hash, T::BlockNumber>,
+	>;
+
+	// The responses pinners submitted to unresolved storage challenges.
+	#[pallet::storage]
+	#[pallet::getter(fn challenge_response)]
+	pub type ChallengeResponses<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, ChallengeId, Blake2_128Concat, T::AccountId, T::Hash>;
+
+	// The storage challenges resolved in the given block.
+	#[pallet::storage]
+	pub type ChallengeDeadlines<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<ChallengeId>, ValueQuery>;
+
+	// Named collections of content shared between accounts.
+	#[pallet::storage]
+	#[pallet::getter(fn bucket)]
+	pub type Buckets<T: Config> = StorageMap<_, Twox64Concat, BucketId, BucketInfo<T::AccountId>>;
+
+	// The identifier the next bucket will receive.
+	#[pallet::storage]
+	pub(super) type NextBucketId<T> = StorageValue<_, BucketId, ValueQuery>;
+
+	// The buckets of each account by name.
+	#[pallet::storage]
+	#[pallet::getter(fn bucket_by_name)]
+	pub type BucketNames<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Blake2_128Concat, Vec<u8>, BucketId>;
+
+	// The roles granted in each bucket; its owner's isn't stored.
+	#[pallet::storage]
+	#[pallet::getter(fn bucket_role)]
+	pub type BucketRoles<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, BucketId, Blake2_128Concat, T::AccountId, BucketRole>;
+
+	// The bucket each `Cid` in one was added to.
+	#[pallet::storage]
+	#[pallet::getter(fn content_bucket)]
+	pub type ContentBuckets<T> = StorageMap<_, Blake2_128Concat, Vec<u8>, BucketId>;
+
+	// The roles granted in the namespace of each service; its owner's isn't stored.
+	#[pallet::storage]
+	#[pallet::getter(fn namespace_role)]
+	pub type NamespaceRoles<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, ServiceId, Blake2_128Concat, T::AccountId, NamespaceRole>;
+
+	// The names bound in the namespace of each service.
+	#[pallet::storage]
+	#[pallet::getter(fn namespace_entry)]
+	pub type NamespaceEntries<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		ServiceId,
+		Blake2_128Concat,
+		Vec<u8>,
+		NamespaceEntry<T::AccountId>,
+	>;
+
+	// The quotas of the members of each service's namespace; members without one may bind no
+	// names to content of any size.
+	#[pallet::storage]
+	#[pallet::getter(fn member_quota)]
+	pub type MemberQuotas<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		ServiceId,
+		Blake2_128Concat,
+		T::AccountId,
+		MemberQuota,
+		ValueQuery,
+	>;
+
+	// Data commands queued on a schedule.
+	#[pallet::storage]
+	#[pallet::getter(fn schedule)]
+	pub type Schedules<T: Config> =
+		StorageMap<_, Twox64Concat, ScheduleId, ScheduleInfo<T::AccountId, T::BlockNumber>>;
+
+	// The identifier the next scheduled task will receive.
+	#[pallet::storage]
+	pub(super) type NextScheduleId<T> = StorageValue<_, ScheduleId, ValueQuery>;
+
+	// The number of scheduled tasks of each account.
+	#[pallet::storage]
+	#[pallet::getter(fn schedule_count)]
+	pub type ScheduleCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	// The scheduled tasks that run in the given block.
+	#[pallet::storage]
+	pub type ScheduledRuns<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<ScheduleId>, ValueQuery>;
+
+	// The block in which governance asked for a registry export that wasn't submitted yet.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_export)]
+	pub type PendingExport<T: Config> = StorageValue<_, T::BlockNumber>;
+
+	// The most recent registry snapshot exported to IPFS.
+	#[pallet::storage]
+	#[pallet::getter(fn last_export)]
+	pub type LastExport<T: Config> = StorageValue<_, RegistryExport<T::BlockNumber>>;
+
+	// The registry snapshot being restored.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_import)]
+	pub type PendingImport<T: Config> = StorageValue<_, RegistryImport<T::BlockNumber>>;
+
+	// The most recent offchain incidents, oldest first, along with the reporter that submitted
+	// them.
+	#[pallet::storage]
+	#[pallet::getter(fn recent_incidents)]
+	pub type RecentIncidents<T: Config> =
+		StorageValue<_, Vec<(T::AccountId, OffchainIncident<T::Hash, T::BlockNumber>)>, ValueQuery>;
+
+	// The incidents dropped from `RecentIncidents`, oldest first, until `incidents::ARCHIVE_BATCH`
+	// of them are archived to IPFS together.
+	#[pallet::storage]
+	#[pallet::getter(fn pruned_incidents)]
+	pub type PrunedIncidents<T: Config> =
+		StorageValue<_, Vec<(T::AccountId, OffchainIncident<T::Hash, T::BlockNumber>)>, ValueQuery>;
+
+	// The CIDs of the archives of pruned incidents, in archive order; each holds the SCALE encoding
+	// of a batch of `(reporter, incident)` pairs, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn incident_archive)]
+	pub type IncidentArchives<T> = StorageMap<_, Twox64Concat, u32, Vec<u8>>;
+
+	// The number of entries in `IncidentArchives`.
+	#[pallet::storage]
+	#[pallet::getter(fn incident_archive_count)]
+	pub type IncidentArchiveCount<T> = StorageValue<_, u32, ValueQuery>;
+
+	// The hashes of the reports accepted through `submit_report`, by the block they were made in;
+	// dropped once the reports can no longer be valid.
+	#[pallet::storage]
+	pub(super) type AcceptedReports<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::BlockNumber, Identity, T::Hash, (), OptionQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// The multiaddrs the IPFS nodes dial on startup and stay connected to.
+		pub bootstrap_peers: Vec<Vec<u8>>,
+		/// Content registered from the first block, each CID with its owner, for the offchain
+		/// workers to pin like an imported pinset.
+		pub pins: Vec<(T::AccountId, Vec<u8>)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { bootstrap_peers: Vec::new(), pins: Vec::new() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			let peers: Vec<_> = self.bootstrap_peers.iter().cloned().map(OpaqueMultiaddr).collect();
+			<BootstrapPeers<T>>::put(peers);
+			for (owner, cid) in &self.pins {
+				assert!(cid::is_valid(cid), "invalid genesis CID {:?}", str::from_utf8(cid));
+				Pallet::<T>::import_pins(owner, vec![cid.clone()]);
+			}
+		}
+	}
+
+	// The pallet's events
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		ConnectionRequested(T::AccountId, OpaqueMultiaddr),
+		DisconnectRequested(T::AccountId, OpaqueMultiaddr),
+		QueuedDataToAdd(T::AccountId, T::Hash, RequestId),
+		QueuedDataToCat(T::AccountId, Vec<u8>, T::Hash, RequestId),
+		QueuedDataToPin(T::AccountId, Vec<u8>, RequestId),
+		QueuedDataToRemove(T::AccountId, Vec<u8>, RequestId),
+		QueuedDataToUnpin(T::AccountId, Vec<u8>, RequestId),
+		FindPeerIssued(T::AccountId, Vec<u8>, T::Hash),
+		FindProvidersIssued(T::AccountId, Vec<u8>, T::Hash),
+		ProvideIssued(T::AccountId, Vec<u8>),
+		PeerFound(Vec<u8>, Vec<Vec<u8>>),
+		ProvidersFound(Vec<u8>, Vec<Vec<u8>>),
+		ServiceRegistered(T::AccountId, ServiceId),
+		ServiceUpdated(ServiceId),
+		ServiceQuotaGranted(ServiceId, u64),
+		ServiceDeregistered(ServiceId),
+		QueuedServiceDataToAdd(ServiceId, u64, RequestId),
+		AddedCid(T::AccountId, Vec<u8>),
+		ReporterAdded(T::AccountId),
+		ReporterRemoved(T::AccountId),
+		DepositReserved(T::AccountId, BalanceOf<T>),
+		DepositReleased(T::AccountId, BalanceOf<T>),
+		PinsetImported(T::AccountId, u32),
+		RequestAbandoned(T::Hash, u32),
+		DataCommandsAcknowledged(u64),
+		RequestStatusChanged(RequestId, RequestStatus<T::Hash>),
+		OperationDeadLettered(RequestId, T::Hash, DispatchError),
+		DeadLetterReplayed(RequestId, RequestId),
+		DeadLettersPurged(u32),
+		DhtResultStored(T::Hash),
+		CatResultStored(T::Hash),
+		PubsubSubscribed(T::AccountId, Vec<u8>),
+		PubsubUnsubscribed(T::AccountId, Vec<u8>),
+		PubsubPublished(T::AccountId, Vec<u8>),
+		PubsubMessageReceived(Vec<u8>, T::Hash),
+		CommandPaused(CommandKind),
+		CommandResumed(CommandKind),
+		PinPolicySet(PinMode),
+		SchemaRegistered(T::AccountId, SchemaId),
+		ContentSchemaSet(Vec<u8>, Option<SchemaId>),
+		SchemaMismatch(Vec<u8>, SchemaId),
+		NewQuotaEra(u32),
+		LeaseExpired(Vec<u8>),
+		LeaseExtended(Vec<u8>, T::BlockNumber),
+		UploadStarted(T::AccountId, UploadId),
+		ChunkUploaded(UploadId, u32),
+		UploadFinalized(UploadId, RequestId),
+		UploadCancelled(UploadId),
+		FeatureFlagsSet(u32),
+		IngressPolicySet(IngressPolicy),
+		StatsUpdated(NodeHealth<T::BlockNumber>),
+		ForceConnected(OpaqueMultiaddr),
+		ForceDisconnected(OpaqueMultiaddr),
+		BootstrapPeersSet(Vec<OpaqueMultiaddr>),
+		RecoveryStarted(u32),
+		RecoveryProgressed(T::AccountId, RecoveryProgress),
+		OffchainIncidentReported(T::AccountId, OffchainIncident<T::Hash, T::BlockNumber>),
+		PinsAttested(T::AccountId, u32),
+		UnderReplicated(Vec<u8>),
+		ReplicationRestored(Vec<u8>),
+		StorageBonded(T::AccountId, BalanceOf<T>),
+		StorageUnbonded(T::AccountId, BalanceOf<T>),
+		ChallengeIssued(ChallengeId, Vec<u8>),
+		ChallengeResponded(ChallengeId, T::AccountId),
+		ChallengePassed(ChallengeId, T::AccountId),
+		ChallengeFailed(ChallengeId, T::AccountId, BalanceOf<T>),
+		AccessGranted(Vec<u8>, T::AccountId, T::BlockNumber),
+		AccessRevoked(Vec<u8>, T::AccountId),
+		QueuedGarbageCollection(RequestId),
+		EvictedContent(Vec<u8>),
+		RegistryExportRequested(T::BlockNumber),
+		RegistryExported(Vec<u8>, u32),
+		RegistryImportRequested(Vec<u8>),
+		RegistryImported(Vec<u8>, u32),
+		BucketCreated(T::AccountId, BucketId),
+		BucketRoleGranted(BucketId, T::AccountId, BucketRole),
+		BucketRoleRevoked(BucketId, T::AccountId),
+		AddedToBucket(BucketId, Vec<u8>),
+		TaskScheduled(T::AccountId, ScheduleId),
+		ScheduleCancelled(ScheduleId),
+		QueuedIpnsPublish(T::AccountId, Vec<u8>, RequestId),
+		QueuedIpnsResolve(T::AccountId, Vec<u8>, RequestId),
+		IpnsPublished(Vec<u8>, Vec<u8>),
+		IpnsResolved(Vec<u8>, Vec<u8>),
+		CidDenied(Vec<u8>),
+		CidAllowed(Vec<u8>),
+		DeniedContentRemoved(Vec<u8>, T::AccountId),
+		WorkersUnreported(Vec<T::AccountId>),
+		RequestBumped(T::AccountId, RequestId, BalanceOf<T>),
+		AppealFiled(Vec<u8>, T::AccountId, T::BlockNumber),
+		AppealUpheld(Vec<u8>),
+		AppealDismissed(Vec<u8>, BalanceOf<T>),
+		AppealLapsed(Vec<u8>),
+		IncidentsArchived(u32, Vec<u8>),
+		NamespaceRoleGranted(ServiceId, T::AccountId, NamespaceRole),
+		NamespaceRoleRevoked(ServiceId, T::AccountId),
+		MemberQuotaSet(ServiceId, T::AccountId, u64),
+		NameBound(ServiceId, Vec<u8>, Vec<u8>),
+		NameUnbound(ServiceId, Vec<u8>),
+		NameTagged(ServiceId, Vec<u8>, Vec<Vec<u8>>),
+	}
+
+	// The pallet's errors
+	#[pallet::error]
+	pub enum Error<T> {
+		CantCreateRequest,
+		RequestTimeout,
+		RequestFailed,
+		UnsupportedRequest,
+		UnknownService,
+		NotServiceOwner,
+		ServiceQuotaExceeded,
+		NotAReporter,
+		NoSigningKey,
+		UnknownContent,
+		NotContentOwner,
+		QueueFull,
+		CommandPaused,
+		NotSubscribed,
+		UnknownSchema,
+		QuotaExceeded,
+		NotLeased,
+		UnknownUpload,
+		NotUploadOwner,
+		InvalidChunkIndex,
+		ChunkAlreadyUploaded,
+		UploadIncomplete,
+		TimeoutTooLong,
+		InvalidCid,
+		ContentTooLarge,
+		MimeTypeDenied,
+		EntropyTooHigh,
+		PriorityReserved,
+		UnknownRecoveryRound,
+		NoEncryptionKey,
+		CantDecrypt,
+		TooManyIncidents,
+		TooManyAttestations,
+		AlreadyBonded,
+		NotBonded,
+		ChallengesOpen,
+		UnknownChallenge,
+		NotChallenged,
+		AlreadyResponded,
+		InvalidAccessTtl,
+		NoAccessGrant,
+		TooManyEvictions,
+		BatchTooLarge,
+		NoPendingExport,
+		NoPendingImport,
+		ImportInProgress,
+		InvalidSnapshot,
+		UnexpectedRestorePage,
+		TooManyEntries,
+		BucketNameTooLong,
+		BucketExists,
+		UnknownBucket,
+		NotBucketOwner,
+		NotBucketWriter,
+		NoBucketRole,
+		AlreadyInBucket,
+		InvalidSchedulePeriod,
+		TooManySchedules,
+		UnknownSchedule,
+		NotScheduleOwner,
+		TooManyStatusUpdates,
+		UnknownDeadLetter,
+		InvalidIpnsName,
+		ContentDenied,
+		NotDenied,
+		UnknownRequest,
+		NotRequestCaller,
+		RequestNotPending,
+		AlreadyBumped,
+		NotDeniedOwner,
+		AlreadyAppealed,
+		InvalidEvidence,
+		UnknownAppeal,
+		NotNamespaceAdmin,
+		NotNamespaceWriter,
+		NoNamespaceRole,
+		NamespaceNameTooLong,
+		NameTaken,
+		UnknownName,
+		MemberQuotaExceeded,
+		InvalidTags,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		// needs to be synchronized with offchain_worker actitivies
+		fn on_initialize(block_number: T::BlockNumber) -> Weight {
+			<ConnectionQueue<T>>::kill();
+			<DhtQueue<T>>::kill();
+			<PubsubQueue<T>>::kill();
+			<BlockDataLen<T>>::kill();
+
+			// start a new quota era; usage counters are reset lazily when accounts next use them
+			let era_length = T::EraLength::get();
+			let new_era = !era_length.is_zero() && (block_number % era_length).is_zero();
+			let mut issued = 0;
+			if new_era {
+				let era = <CurrentEra<T>>::mutate(|era| {
+					*era += 1;
+					*era
+				});
+				Self::deposit_event(Event::<T>::NewQuotaEra(era));
+				issued = Self::issue_challenges(block_number);
+			}
+
+			// slash the pinners that missed or failed the challenges due in this block
+			let due = <ChallengeDeadlines<T>>::take(block_number);
+			for challenge in due.iter() {
+				Self::resolve_challenge(*challenge);
+			}
+
+			// queue the commands of the scheduled tasks due
+			let scheduled = <ScheduledRuns<T>>::take(block_number);
+			for id in scheduled.iter() {
+				Self::run_schedule(*id, block_number);
+			}
+
+			// forget the reports that went stale, their hashes are no longer needed to reject
+			// replays
+			let stale =
+				block_number.checked_sub(&T::UnsignedLongevity::get().saturating_add(One::one()));
+			if let Some(stale) = stale {
+				let _ = <AcceptedReports<T>>::clear_prefix(stale, u32::MAX, None);
+			}
+
+			// look for pinned content its assigned reporters stopped attesting
+			let (checked, changed) = Self::check_replication(block_number);
+
+			// look for reporters whose offchain workers stopped reporting
+			let gauged = (block_number % REPORTING_WINDOW.into()).is_zero();
+			let gauged_reporters = if gauged { Self::gauge_reporters(block_number) } else { 0 };
+
+			let factor = T::ReplicationFactor::get() as Weight;
+			T::DbWeight::get().reads_writes(
+				4 + new_era as Weight * (3 + factor)
+					+ gauged as Weight * (2 + gauged_reporters as Weight)
+					+ 3 * scheduled.len() as Weight
+					+ checked as Weight * (2 + factor)
+					+ issued as Weight * (2 + factor)
+					+ due.len() as Weight * (1 + 3 * factor),
+				7 + new_era as Weight
+					+ gauged as Weight
+					+ 4 * scheduled.len() as Weight
+					+ changed as Weight
+					+ issued as Weight * (3 + factor)
+					+ due.len() as Weight * (1 + 3 * factor),
+			)
+		}
+
+		// housekeeping that doesn't have to happen in a particular block is left to the weight
+		// blocks have to spare; see `run_maintenance`
+		fn on_idle(block_number: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			Self::run_maintenance(block_number, remaining_weight)
+		}
+
+		fn offchain_worker(block_number: T::BlockNumber) {
+			// failures that aren't tied to a request are correlated with the worker run
+			let run = <frame_system::Pallet<T>>::parent_hash();
+			// without a signing key none of the results could be reported back, so the tasks
+			// that report are skipped instead of failing on every block
+			let can_sign = Self::check_signing_key(block_number);
+
+			// process connect/disconnect commands
+			if let Err(e) = Self::connection_housekeeping() {
+				log::error!("IPFS: Encountered an error during connection housekeeping: {:?}", e);
+				Self::record_incident(e, run);
+			}
+
+			// process requests to the DHT
+			if let Err(e) = Self::handle_dht_requests() {
+				log::error!("IPFS: Encountered an error while processing DHT requests: {:?}", e);
+				Self::record_incident(e, run);
+			}
+
+			// re-announce pinned content before the DHT records expire
+			let reprovide_interval = T::ReprovideInterval::get();
+			if !reprovide_interval.is_zero() && (block_number % reprovide_interval).is_zero() {
+				if let Err(e) = Self::reprovide_pinned() {
+					log::error!(
+						"IPFS: Encountered an error while re-providing pinned content: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+			}
+
+			// process pubsub commands and collect messages on the subscribed topics
+			if let Err(e) = Self::handle_pubsub(can_sign) {
+				log::error!("IPFS: Encountered an error while processing pubsub requests: {:?}", e);
+				Self::record_incident(e, run);
+			}
+
+			// process the Ipfs::{add, get} queue, leaving it to the nodes that can report the
+			// results otherwise
+			if can_sign {
+				if let Err(e) = Self::handle_data_requests(block_number) {
+					log::error!(
+						"IPFS: Encountered an error while processing data requests: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+			}
+
+			// pin the next batch of an imported pinset
+			if let Err(e) = Self::handle_imported_pins() {
+				log::error!("IPFS: Encountered an error while pinning imported content: {:?}", e);
+				Self::record_incident(e, run);
+			}
+
+			// the tasks below all report to the chain
+			if can_sign {
+				// re-pin registered content missing from the repo after a restart or on request
+				if let Err(e) = Self::handle_recovery(block_number) {
+					log::error!(
+						"IPFS: Encountered an error while recovering pinned content: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+
+				// export the registry or restore an imported one when governance asked for it
+				if let Err(e) = Self::handle_registry_export(block_number) {
+					log::error!("IPFS: Encountered an error while exporting the registry: {:?}", e);
+					Self::record_incident(e, run);
+				}
+				if let Err(e) = Self::handle_registry_import() {
+					log::error!(
+						"IPFS: Encountered an error while importing a registry snapshot: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+
+				// pin and attest the registered content assigned to this node
+				if let Err(e) = Self::handle_replication(block_number) {
+					log::error!(
+						"IPFS: Encountered an error while attesting assigned pins: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+
+				// answer the storage challenges this node's reporter is a party to
+				if let Err(e) = Self::handle_challenges() {
+					log::error!(
+						"IPFS: Encountered an error while answering storage challenges: {:?}",
+						e
+					);
+					Self::record_incident(e, run);
+				}
+
+				// retry failed data requests whose backoff has elapsed
+				Jobs::<DataJobs<T>>::run_retries(&mut (), block_number, RETRY_BATCH);
+			}
+
+			// report some stats every `STATS_INTERVAL` blocks; nodes without a signing key share
+			// theirs too
+			if (block_number % STATS_INTERVAL.into()).is_zero() {
+				if let Err(e) = Self::report_stats(block_number) {
+					log::error!("IPFS: Encountered an error while obtaining metadata: {:?}", e);
+					Self::record_incident(e, run);
+				}
+			}
+
+			// submit the failures of this and earlier runs, once there is a key to sign them with
+			if can_sign {
+				Self::submit_incidents();
+			}
+		}
+	}
+
+	// The pallet's dispatchable functions.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Mark a `Multiaddr` as a desired connection target. The connection will be established
+		/// during the next run of the off-chain `connection_housekeeping` process, and
+		/// re-established whenever it drops. The weight charged for a full connection queue is
+		/// refunded down to that of the queue's actual length.
+		#[pallet::weight(T::WeightInfo::ipfs_connect(T::MaxQueueLen::get()))]
+		pub fn ipfs_connect(origin: OriginFor<T>, addr: Vec<u8>) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, 0)?;
+			let cmd = ConnectionCommand::ConnectTo(OpaqueMultiaddr(addr.clone()));
+
+			let queued = Self::enqueue_connection(cmd)?;
+			<DesiredPeers<T>>::insert(&addr, true);
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::ConnectionRequested(who, OpaqueMultiaddr(addr)));
+			Ok(Some(T::WeightInfo::ipfs_connect(queued)).into())
+		}
+
+		/// Queues a `Multiaddr` to be disconnected and removes it from the desired peers. The
+		/// connection will be severed during the next run of the off-chain
+		/// `connection_housekeeping` process.
+		#[pallet::weight(T::WeightInfo::ipfs_disconnect(T::MaxQueueLen::get()))]
+		pub fn ipfs_disconnect(origin: OriginFor<T>, addr: Vec<u8>) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, 0)?;
+			let cmd = ConnectionCommand::DisconnectFrom(OpaqueMultiaddr(addr.clone()));
+
+			let queued = Self::enqueue_connection(cmd)?;
+			<DesiredPeers<T>>::remove(&addr);
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::DisconnectRequested(who, OpaqueMultiaddr(addr)));
+			Ok(Some(T::WeightInfo::ipfs_disconnect(queued)).into())
+		}
+
+		/// Add arbitrary bytes to the IPFS repository, reserving `ByteDeposit` per byte from the
+		/// caller. The registered `Cid` is reported back by the offchain worker. `timeout`
+		/// overrides the first attempt's deadline (in ms), up to `MaxRequestTimeout`; `priority`
+		/// may lower the command to `Low` for bulk adds. With `encrypt` set the offchain workers
+		/// encrypt the data with the nodes' key before adding it; see the `encryption` module.
+		#[pallet::weight(T::WeightInfo::ipfs_add_bytes(data.len() as u32))]
+		pub fn ipfs_add_bytes(
+			origin: OriginFor<T>,
+			data: Vec<u8>,
+			timeout: Option<u64>,
+			priority: Option<Priority>,
+			encrypt: bool,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_timeout(timeout)?;
+			let priority = Self::caller_priority(priority)?;
+
+			if encrypt {
+				Self::queue_add(who, data, priority, |who, data| {
+					DataCommand::AddEncrypted(who, data, timeout)
+				})?;
+			} else {
+				Self::queue_add(who, data, priority, |who, data| {
+					DataCommand::AddBytes(who, data, None, timeout)
+				})?;
+			}
+			Ok(())
+		}
+
+		/// Add arbitrary bytes to the IPFS repository like `ipfs_add_bytes`, but only for the
+		/// given number of blocks; once the lease expires the content is unpinned and removed.
+		#[pallet::weight(T::WeightInfo::ipfs_add_bytes_with_lease(data.len() as u32))]
+		pub fn ipfs_add_bytes_with_lease(
+			origin: OriginFor<T>,
+			data: Vec<u8>,
+			blocks: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let to_cmd = |who, data| DataCommand::AddBytes(who, data, Some(blocks), None);
+			Self::queue_add(who, data, Priority::Normal, to_cmd)?;
+			Ok(())
+		}
+
+		/// Start uploading data too large for a single extrinsic in `total_chunks` chunks. The
+		/// assembled data has to hash to `content_hash`.
+		#[pallet::weight(T::WeightInfo::ipfs_begin_upload())]
+		pub fn ipfs_begin_upload(
+			origin: OriginFor<T>,
+			total_chunks: u32,
+			content_hash: T::Hash,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, 0)?;
+			Self::ensure_not_paused(CommandKind::Add)?;
+
+			let id = <NextUploadId<T>>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			<Uploads<T>>::insert(
+				id,
+				UploadInfo {
+					owner: who.clone(),
+					total_chunks,
+					content_hash,
+					received_chunks: 0,
+					size: 0,
+					class: None,
+				},
+			);
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::UploadStarted(who, id));
+			Ok(())
+		}
+
+		/// Upload one chunk of a chunked upload, reserving `ByteDeposit` per byte. The chunk is
+		/// staged in the off-chain index until the upload is finalized.
+		#[pallet::weight(T::WeightInfo::ipfs_upload_chunk(bytes.len() as u32))]
+		pub fn ipfs_upload_chunk(
+			origin: OriginFor<T>,
+			upload_id: UploadId,
+			index: u32,
+			bytes: Vec<u8>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, bytes.len() as u64)?;
+
+			let mut upload = Self::upload(upload_id).ok_or(Error::<T>::UnknownUpload)?;
+			ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+			ensure!(index < upload.total_chunks, Error::<T>::InvalidChunkIndex);
+			ensure!(!Self::has_chunk(upload_id, index), Error::<T>::ChunkAlreadyUploaded);
+			let policy = Self::ingress_policy();
+			policy.check_entropy(&bytes).map_err(Error::<T>::from)?;
+			if index == 0 {
+				upload.class = Some(policy.check_leading(&bytes).map_err(Error::<T>::from)?);
+			}
+
+			Self::reserve_deposit(&who, bytes.len() as u64)?;
+			sp_io::offchain_index::set(&upload_chunk_key(upload_id, index), &bytes);
+			<UploadChunks<T>>::insert(upload_id, index, true);
+			upload.received_chunks += 1;
+			upload.size = upload.size.saturating_add(bytes.len() as u64);
+			<Uploads<T>>::insert(upload_id, upload);
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::ChunkUploaded(upload_id, index));
+			Ok(())
+		}
+
+		/// Queue a complete chunked upload to be assembled and added to IPFS by the offchain
+		/// worker.
+		#[pallet::weight(T::WeightInfo::ipfs_finalize_upload(Pallet::<T>::chunk_count(*upload_id)))]
+		pub fn ipfs_finalize_upload(origin: OriginFor<T>, upload_id: UploadId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let upload = Self::upload(upload_id).ok_or(Error::<T>::UnknownUpload)?;
+			ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+			ensure!(upload.received_chunks == upload.total_chunks, Error::<T>::UploadIncomplete);
+			let class = upload.class.unwrap_or(ContentClass::Binary);
+			Self::ingress_policy()
+				.check_size(class, upload.size)
+				.map_err(Error::<T>::from)?;
+
+			let content_hash = upload.content_hash.as_ref().to_vec();
+			let cmd = DataCommand::AddUpload(who, upload_id, upload.total_chunks, content_hash);
+			let request = Self::enqueue_data_for(&upload.owner, cmd, Priority::Low)?;
+			<Uploads<T>>::remove(upload_id);
+			let _ = <UploadChunks<T>>::clear_prefix(upload_id, upload.total_chunks, None);
+			Self::deposit_event(Event::<T>::UploadFinalized(upload_id, request));
+			Ok(())
+		}
+
+		/// Abandon a chunked upload, releasing the deposit reserved for its chunks.
+		#[pallet::weight(T::WeightInfo::ipfs_cancel_upload(Pallet::<T>::chunk_count(*upload_id)))]
+		pub fn ipfs_cancel_upload(origin: OriginFor<T>, upload_id: UploadId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let upload = Self::upload(upload_id).ok_or(Error::<T>::UnknownUpload)?;
+			ensure!(upload.owner == who, Error::<T>::NotUploadOwner);
+
+			for index in 0..upload.total_chunks {
+				if <UploadChunks<T>>::take(upload_id, index) {
+					sp_io::offchain_index::clear(&upload_chunk_key(upload_id, index));
+				}
+			}
+			<Uploads<T>>::remove(upload_id);
+			Self::release_deposit(&who, Self::deposit_of(&who, upload.size));
+			Self::deposit_event(Event::<T>::UploadCancelled(upload_id));
+			Ok(())
+		}
+
+		/// Extend the lease of content by the given number of blocks, reserving
+		/// `LeaseDepositPerBlock` for each of them from the owner.
+		#[pallet::weight(T::WeightInfo::extend_lease())]
+		pub fn extend_lease(origin: OriginFor<T>, cid: Vec<u8>, blocks: u32) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut content = Self::content(&cid).ok_or(Error::<T>::UnknownContent)?;
+			ensure!(content.owner == who, Error::<T>::NotContentOwner);
+			let expires_at = content.expires_at.ok_or(Error::<T>::NotLeased)?;
+
+			let deposit = T::LeaseDepositPerBlock::get().saturating_mul(blocks.into());
+			if !deposit.is_zero() {
+				T::Currency::reserve(&who, deposit)?;
+				Self::deposit_event(Event::<T>::DepositReserved(who.clone(), deposit));
+			}
+
+			let new_expiry = expires_at.saturating_add(blocks.into());
+			<LeaseExpiry<T>>::mutate(expires_at, |cids| cids.retain(|c| c != &cid));
+			<LeaseExpiry<T>>::append(new_expiry, &cid);
+			content.expires_at = Some(new_expiry);
+			content.deposit = content.deposit.saturating_add(deposit);
+			<ContentRegistry<T>>::insert(&cid, content);
+			Self::deposit_event(Event::<T>::LeaseExtended(cid, new_expiry));
+			Ok(())
+		}
+
+		/// Find IPFS data pointed to by the given `Cid`; if it is valid UTF-8, it is printed in the
+		/// logs verbatim; otherwise, the decimal representation of the bytes is displayed instead.
+		/// `timeout` overrides the first attempt's deadline (in ms), up to `MaxRequestTimeout`.
+		#[pallet::weight(T::WeightInfo::ipfs_cat_bytes())]
+		pub fn ipfs_cat_bytes(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			timeout: Option<u64>,
+			priority: Option<Priority>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_timeout(timeout)?;
+			let priority = Self::caller_priority(priority)?;
+
+			Self::queue_cat(who, cid, timeout, priority)?;
+			Ok(())
+		}
+
+		/// Remove the block with the given `Cid` from the IPFS repository and drop it from the
+		/// content registry, releasing the owner's storage deposit. Only the content owner, the
+		/// writers of its bucket or root may do so; the event names the owner.
+		#[pallet::weight(T::WeightInfo::ipfs_remove_block())]
+		pub fn ipfs_remove_block(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResult {
+			let owner = Self::ensure_content_owner_or_root(origin, &cid)?;
+
+			let request = Self::enqueue_data_for(
+				&owner,
+				DataCommand::RemoveBlock(cid.clone()),
+				Priority::High,
+			)?;
+			if let Some(content) = <ContentRegistry<T>>::take(&cid) {
+				Self::release_deposit(&content.owner, content.deposit);
+			}
+			<EncryptedContent<T>>::remove(&cid);
+			Self::forget_content(&cid);
+			Self::deposit_event(Event::<T>::QueuedDataToRemove(owner, cid, request));
+			Ok(())
+		}
+
+		/// Pins a given `Cid`, recursively or not. Content not yet in the registry is registered
+		/// with the caller as its owner; content in a bucket may only be pinned by its writers.
+		#[pallet::weight(T::WeightInfo::ipfs_insert_pin())]
+		pub fn ipfs_insert_pin(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			recursive: bool,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::queue_pin(who, cid, recursive)
+		}
+
+		/// Unpins a given `Cid`, recursively or not. Only the content owner, the writers of its
+		/// bucket or root may do so; the event names the owner.
+		#[pallet::weight(T::WeightInfo::ipfs_remove_pin())]
+		pub fn ipfs_remove_pin(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			recursive: bool,
+		) -> DispatchResult {
+			let owner = Self::ensure_content_owner_or_root(origin, &cid)?;
+
+			Self::queue_unpin(owner, cid, recursive)
+		}
+
+		/// Find addresses associated with the given `PeerId`.
+		#[pallet::weight(T::WeightInfo::ipfs_dht_find_peer(T::MaxQueueLen::get()))]
+		pub fn ipfs_dht_find_peer(
+			origin: OriginFor<T>,
+			peer_id: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, 0)?;
+
+			let cmd = DhtCommand::FindPeer(peer_id.clone());
+			let request = T::Hashing::hash_of(&cmd);
+			let queued = Self::enqueue_dht(cmd)?;
+			Self::expect_result(request);
+			T::Promises::create(AsyncOp::DhtQuery(request));
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::FindPeerIssued(who, peer_id, request));
+			Ok(Some(T::WeightInfo::ipfs_dht_find_peer(queued)).into())
+		}
+
+		/// Find the list of `PeerId`s known to be hosting the given `Cid`.
+		#[pallet::weight(T::WeightInfo::ipfs_dht_find_providers(T::MaxQueueLen::get()))]
+		pub fn ipfs_dht_find_providers(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_cid(&cid)?;
+			let usage = Self::check_quota(&who, 0)?;
+
+			let cmd = DhtCommand::GetProviders(cid.clone());
+			let request = T::Hashing::hash_of(&cmd);
+			let queued = Self::enqueue_dht(cmd)?;
+			Self::expect_result(request);
+			T::Promises::create(AsyncOp::DhtQuery(request));
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::FindProvidersIssued(who, cid, request));
+			Ok(Some(T::WeightInfo::ipfs_dht_find_providers(queued)).into())
+		}
+
+		/// Announce to the DHT that the IPFS nodes provide the given `Cid`.
+		#[pallet::weight(T::WeightInfo::ipfs_dht_provide(T::MaxQueueLen::get()))]
+		pub fn ipfs_dht_provide(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_cid(&cid)?;
+			let usage = Self::check_quota(&who, 0)?;
+
+			let queued = Self::enqueue_dht(DhtCommand::Provide(cid.clone()))?;
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::ProvideIssued(who, cid));
+			Ok(Some(T::WeightInfo::ipfs_dht_provide(queued)).into())
+		}
+
+		/// Subscribe to a pubsub topic. Messages received on it are stored in the off-chain index
+		/// and announced with `PubsubMessageReceived`.
+		#[pallet::weight(T::WeightInfo::ipfs_pubsub_subscribe(T::MaxQueueLen::get()))]
+		pub fn ipfs_pubsub_subscribe(
+			origin: OriginFor<T>,
+			topic: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, 0)?;
+
+			// the nodes only need to be told about topics nobody was subscribed to yet
+			let mut queued = 0;
+			if !<PubsubTopics<T>>::get().contains(&topic) {
+				queued = Self::enqueue_pubsub(PubsubCommand::Subscribe(topic.clone()))?;
+				<PubsubTopics<T>>::mutate(|topics| topics.push(topic.clone()));
+			}
+			<PubsubSubscribers<T>>::mutate(&topic, |subscribers| {
+				if !subscribers.contains(&who) {
+					subscribers.push(who.clone());
+				}
+			});
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::PubsubSubscribed(who, topic));
+			Ok(Some(T::WeightInfo::ipfs_pubsub_subscribe(queued)).into())
+		}
+
+		/// Publish a message on a pubsub topic.
+		#[pallet::weight(T::WeightInfo::ipfs_pubsub_publish(
+			data.len() as u32,
+			T::MaxQueueLen::get(),
+		))]
+		pub fn ipfs_pubsub_publish(
+			origin: OriginFor<T>,
+			topic: Vec<u8>,
+			data: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let usage = Self::check_quota(&who, data.len() as u64)?;
+			let len = data.len() as u32;
+
+			let queued = Self::enqueue_pubsub(PubsubCommand::Publish(topic.clone(), data))?;
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::PubsubPublished(who, topic));
+			Ok(Some(T::WeightInfo::ipfs_pubsub_publish(len, queued)).into())
+		}
+
+		/// Unsubscribe from a pubsub topic; the nodes stop listening once nobody is subscribed.
+		#[pallet::weight(T::WeightInfo::ipfs_pubsub_unsubscribe(T::MaxQueueLen::get()))]
+		pub fn ipfs_pubsub_unsubscribe(
+			origin: OriginFor<T>,
+			topic: Vec<u8>,
+		) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+
+			let mut subscribers = Self::pubsub_subscribers(&topic);
+			ensure!(subscribers.contains(&who), Error::<T>::NotSubscribed);
+			subscribers.retain(|s| s != &who);
+			let mut queued = 0;
+			if subscribers.is_empty() {
+				queued = Self::enqueue_pubsub(PubsubCommand::Unsubscribe(topic.clone()))?;
+				<PubsubTopics<T>>::mutate(|topics| topics.retain(|t| t != &topic));
+				<PubsubSubscribers<T>>::remove(&topic);
+			} else {
+				<PubsubSubscribers<T>>::insert(&topic, subscribers);
+			}
+			Self::deposit_event(Event::<T>::PubsubUnsubscribed(who, topic));
+			Ok(Some(T::WeightInfo::ipfs_pubsub_unsubscribe(queued)).into())
+		}
+
+		/// Register the schema document with the given `Cid` in the schema registry.
+		#[pallet::weight(T::WeightInfo::register_schema())]
+		pub fn register_schema(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_cid(&cid)?;
+
+			let id = <NextSchemaId<T>>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			<Schemas<T>>::insert(id, SchemaInfo { owner: who.clone(), cid });
+			Self::deposit_event(Event::<T>::SchemaRegistered(who, id));
+			Ok(())
+		}
+
+		/// Declare which schema registered content follows, or clear it with `None`. Only the
+		/// content owner, the writers of its bucket or root may do so.
+		#[pallet::weight(T::WeightInfo::set_content_schema())]
+		pub fn set_content_schema(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			schema: Option<SchemaId>,
+		) -> DispatchResult {
+			Self::ensure_content_owner_or_root(origin, &cid)?;
+			if let Some(id) = schema {
+				ensure!(<Schemas<T>>::contains_key(id), Error::<T>::UnknownSchema);
+			}
+
+			<ContentRegistry<T>>::mutate(&cid, |content| {
+				if let Some(content) = content {
+					content.schema = schema;
+				}
+			});
+			Self::deposit_event(Event::<T>::ContentSchemaSet(cid, schema));
+			Ok(())
+		}
+
+		/// Register a dApp in the service registry. Storage quota has to be granted by governance
+		/// before the service can add data.
+		#[pallet::weight(T::WeightInfo::register_service())]
+		pub fn register_service(
+			origin: OriginFor<T>,
+			name: Vec<u8>,
+			metadata_cid: Vec<u8>,
+			requested_quota: u64,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let id = <NextServiceId<T>>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			<Services<T>>::insert(
+				id,
+				ServiceInfo {
+					owner: who.clone(),
+					name,
+					metadata_cid,
+					requested_quota,
+					granted_quota: 0,
+					used_bytes: 0,
+				},
+			);
+			Self::deposit_event(Event::<T>::ServiceRegistered(who, id));
+			Ok(())
+		}
+
+		/// Point a service at a new metadata document; only callable by the service owner.
+		#[pallet::weight(T::WeightInfo::update_service())]
+		pub fn update_service(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			metadata_cid: Vec<u8>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			<Services<T>>::try_mutate(id, |service| -> Result<(), Error<T>> {
+				let service = service.as_mut().ok_or(Error::<T>::UnknownService)?;
+				ensure!(service.owner == who, Error::<T>::NotServiceOwner);
+				service.metadata_cid = metadata_cid;
+				Ok(())
+			})?;
+			Self::deposit_event(Event::<T>::ServiceUpdated(id));
+			Ok(())
+		}
+
+		/// Remove a service from the registry, together with its namespace; only callable by the
+		/// service owner.
+		#[pallet::weight(T::WeightInfo::deregister_service())]
+		pub fn deregister_service(origin: OriginFor<T>, id: ServiceId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let service = Self::service(id).ok_or(Error::<T>::UnknownService)?;
+			ensure!(service.owner == who, Error::<T>::NotServiceOwner);
+			<Services<T>>::remove(id);
+			let _ = <NamespaceRoles<T>>::clear_prefix(id, u32::MAX, None);
+			let _ = <NamespaceEntries<T>>::clear_prefix(id, u32::MAX, None);
+			let _ = <MemberQuotas<T>>::clear_prefix(id, u32::MAX, None);
+			Self::deposit_event(Event::<T>::ServiceDeregistered(id));
+			Ok(())
+		}
+
+		/// Grant a service the given storage quota in bytes.
+		#[pallet::weight(T::WeightInfo::grant_service_quota())]
+		pub fn grant_service_quota(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			quota: u64,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Services<T>>::try_mutate(id, |service| -> Result<(), Error<T>> {
+				service.as_mut().ok_or(Error::<T>::UnknownService)?.granted_quota = quota;
+				Ok(())
+			})?;
+			Self::deposit_event(Event::<T>::ServiceQuotaGranted(id, quota));
+			Ok(())
+		}
+
+		/// Add bytes to the IPFS repository on behalf of a service, counting them against its
+		/// quota; only callable by the service owner.
+		#[pallet::weight(T::WeightInfo::service_add_bytes(data.len() as u32))]
+		pub fn service_add_bytes(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			data: Vec<u8>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_not_paused(CommandKind::Add)?;
+			let len = data.len() as u64;
+			let usage = Self::check_quota(&who, len)?;
+			Self::ingress_policy().check(&data).map_err(Error::<T>::from)?;
+
+			<Services<T>>::try_mutate(id, |service| -> Result<(), Error<T>> {
+				let service = service.as_mut().ok_or(Error::<T>::UnknownService)?;
+				ensure!(service.owner == who, Error::<T>::NotServiceOwner);
+				let used_bytes = service.used_bytes.saturating_add(len);
+				ensure!(used_bytes <= service.granted_quota, Error::<T>::ServiceQuotaExceeded);
+				service.used_bytes = used_bytes;
+				Ok(())
+			})?;
+			Self::reserve_deposit(&who, len)?;
+			let cmd = DataCommand::AddBytes(who.clone(), data, None, None);
+			let request = Self::enqueue_data_for(&who, cmd, Priority::Normal)?;
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::QueuedServiceDataToAdd(id, len, request));
+			Ok(())
+		}
+
+		/// Stop accepting new data commands of the given kind, e.g. to halt uploads during an
+		/// incident while retrievals keep working. Commands already queued are still processed.
+		#[pallet::weight(T::WeightInfo::pause())]
+		pub fn pause(origin: OriginFor<T>, kind: CommandKind) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Paused<T>>::insert(kind, true);
+			Self::deposit_event(Event::<T>::CommandPaused(kind));
+			Ok(())
+		}
+
+		/// Accept data commands of the given kind again.
+		#[pallet::weight(T::WeightInfo::resume())]
+		pub fn resume(origin: OriginFor<T>, kind: CommandKind) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Paused<T>>::remove(kind);
+			Self::deposit_event(Event::<T>::CommandResumed(kind));
+			Ok(())
+		}
+
+		/// Set the pin mode used for pins the caller didn't choose a mode for, such as imported
+		/// pinsets.
+		#[pallet::weight(T::WeightInfo::set_pin_policy())]
+		pub fn set_pin_policy(origin: OriginFor<T>, policy: PinMode) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<PinPolicy<T>>::put(policy);
+			Self::deposit_event(Event::<T>::PinPolicySet(policy));
+			Ok(())
+		}
+
+		/// Set the bitmap of enabled experimental call groups; see the `features` module.
+		#[pallet::weight(T::WeightInfo::set_feature_flags())]
+		pub fn set_feature_flags(origin: OriginFor<T>, flags: u32) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<FeatureFlags<T>>::put(flags);
+			Self::deposit_event(Event::<T>::FeatureFlagsSet(flags));
+			Ok(())
+		}
+
+		/// Set the filters data added through `ipfs_add_bytes`, `service_add_bytes` and chunked
+		/// uploads has to pass; see the `ingress` module.
+		#[pallet::weight(T::WeightInfo::set_ingress_policy())]
+		pub fn set_ingress_policy(origin: OriginFor<T>, policy: IngressPolicy) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<IngressFilters<T>>::put(policy.clone());
+			Self::deposit_event(Event::<T>::IngressPolicySet(policy));
+			Ok(())
+		}
+
+		/// Allow the offchain worker signing with `who` to report request results.
+		#[pallet::weight(T::WeightInfo::add_reporter(Pallet::<T>::reporter_count()))]
+		pub fn add_reporter(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Reporters<T>>::mutate(|reporters| {
+				if !reporters.contains(&who) {
+					reporters.push(who.clone())
+				}
+			});
+			// new reporters get a full `REPORTING_WINDOW` to start reporting
+			<LastReported<T>>::insert(&who, <frame_system::Pallet<T>>::block_number());
+			Self::deposit_event(Event::<T>::ReporterAdded(who));
+			Ok(())
+		}
+
+		/// Revoke the permission of `who` to report request results.
+		#[pallet::weight(T::WeightInfo::remove_reporter(Pallet::<T>::reporter_count()))]
+		pub fn remove_reporter(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			ensure_root(origin)?;
+
+			<Reporters<T>>::mutate(|reporters| reporters.retain(|r| r != &who));
+			<LastReported<T>>::remove(&who);
+			<UnreportedWorkers<T>>::mutate(|unreported| unreported.retain(|r| r != &who));
+			Self::deposit_event(Event::<T>::ReporterRemoved(who));
+			Ok(())
+		}
+
+		/// Connect to `addr` and keep the connection up, like `ipfs_connect` but without a signed
+		/// account or quota.
+		#[pallet::weight(T::WeightInfo::force_connect(T::MaxQueueLen::get()))]
+		pub fn force_connect(
+			origin: OriginFor<T>,
+			addr: OpaqueMultiaddr,
+		) -> DispatchResultWithPostInfo {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let queued = Self::enqueue_connection(ConnectionCommand::ConnectTo(addr.clone()))?;
+			<DesiredPeers<T>>::insert(&addr.0, true);
+			Self::deposit_event(Event::<T>::ForceConnected(addr));
+			Ok(Some(T::WeightInfo::force_connect(queued)).into())
+		}
+
+		/// Disconnect from `addr` and drop it from the desired and bootstrap peers, e.g. to purge
+		/// a misbehaving peer.
+		#[pallet::weight(T::WeightInfo::force_disconnect(Pallet::<T>::bootstrap_peer_count()))]
+		pub fn force_disconnect(origin: OriginFor<T>, addr: OpaqueMultiaddr) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			Self::enqueue_connection(ConnectionCommand::DisconnectFrom(addr.clone()))?;
+			<DesiredPeers<T>>::remove(&addr.0);
+			<BootstrapPeers<T>>::mutate(|peers| peers.retain(|peer| !same_peer(&peer.0, &addr.0)));
+			Self::deposit_event(Event::<T>::ForceDisconnected(addr));
+			Ok(())
+		}
+
+		/// Replace the bootstrap peers, which the offchain workers dial on startup and reconnect
+		/// whenever the connection drops.
+		#[pallet::weight(T::WeightInfo::set_bootstrap_peers(peers.len() as u32))]
+		pub fn set_bootstrap_peers(
+			origin: OriginFor<T>,
+			peers: Vec<OpaqueMultiaddr>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			<BootstrapPeers<T>>::put(&peers);
+			Self::deposit_event(Event::<T>::BootstrapPeersSet(peers));
+			Ok(())
+		}
+
+		/// Ask every offchain worker to check its repo against the content registry and pin again
+		/// whatever is missing, e.g. after a node's IPFS repo was lost.
+		#[pallet::weight(T::WeightInfo::start_recovery())]
+		pub fn start_recovery(origin: OriginFor<T>) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let round = <RecoveryRound<T>>::mutate(|round| {
+				*round += 1;
+				*round
+			});
+			Self::deposit_event(Event::<T>::RecoveryStarted(round));
+			Ok(())
+		}
+
+		// The callbacks of the offchain workers below are operational, so they are prioritised in
+		// the transaction pool and can use the block space reserved for operational extrinsics:
+		// under congestion the chain still learns the outcome of its own offchain work.
+
+		/// Record the `Cid` the local IPFS node assigned to `size` bytes of data added by `owner`
+		/// and register the content, leased for `lease` blocks if given and marked as encrypted if
+		/// the offchain worker encrypted it; submitted by the offchain worker.
+		#[pallet::weight((T::WeightInfo::submit_cid(), DispatchClass::Operational))]
+		pub fn submit_cid(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			cid: Vec<u8>,
+			size: u64,
+			lease: Option<u32>,
+			encrypted: bool,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+
+			Self::record_cid(owner, cid, size, lease, encrypted);
+			Ok(())
+		}
+
+		/// Import an existing pinset (e.g. a Kubo `pin ls` dump) into the content registry on
+		/// behalf of `owner`, without storage deposits. CIDs already in the registry are left
+		/// untouched; the rest are pinned by the offchain workers in batches.
+		#[pallet::weight(T::WeightInfo::import_pinset(cids.len() as u32))]
+		pub fn import_pinset(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			cids: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			for cid in cids.iter() {
+				Self::ensure_valid_cid(cid)?;
+			}
+
+			let imported = Self::import_pins(&owner, cids);
+			Self::deposit_event(Event::<T>::PinsetImported(owner, imported));
+			Ok(())
+		}
+
+		/// Record that the offchain worker gave up on the data command `cmd` after `attempts`
+		/// failed attempts, the last one failing with `reason`. Queued commands, which `request`
+		/// identifies, are dead-lettered unless another worker completed them.
+		#[pallet::weight((
+			T::WeightInfo::report_abandoned(cmd.encoded_size() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn report_abandoned(
+			origin: OriginFor<T>,
+			cmd: DataCommand<T::AccountId>,
+			request: Option<RequestId>,
+			attempts: u32,
+			reason: DispatchError,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+
+			let payload_hash = T::Hashing::hash_of(&cmd);
+			T::OnResult::on_abandoned(&payload_hash);
+			Self::deposit_event(Event::<T>::RequestAbandoned(payload_hash, attempts));
+			if let Some(request) = request {
+				Self::dead_letter(request, cmd, payload_hash, reason);
+			}
+			Ok(())
+		}
+
+		/// Queue a dead-lettered command again, for the account it was originally queued for. The
+		/// event carries the id of the new request.
+		#[pallet::weight(T::WeightInfo::replay_dead_letter())]
+		pub fn replay_dead_letter(origin: OriginFor<T>, request: RequestId) -> DispatchResult {
+			ensure_root(origin)?;
+
+			let letter = <DeadLetters<T>>::take(request).ok_or(Error::<T>::UnknownDeadLetter)?;
+			<DeadLetterCount<T>>::mutate(|count| *count = count.saturating_sub(1));
+			let is_cat = matches!(letter.cmd, DataCommand::CatBytes(..));
+			let replayed = match letter.caller {
+				Some(caller) => Self::enqueue_data_for(&caller, letter.cmd, Priority::Normal)?,
+				None => Self::enqueue_data(letter.cmd, Priority::Normal)?,
+			};
+			if is_cat {
+				Self::expect_result(letter.payload_hash);
+			}
+			Self::deposit_event(Event::<T>::DeadLetterReplayed(request, replayed));
+			Ok(())
+		}
+
+		/// Drop all dead-lettered commands.
+		#[pallet::weight(T::WeightInfo::purge_dead_letters(Pallet::<T>::dead_letter_count()))]
+		pub fn purge_dead_letters(origin: OriginFor<T>) -> DispatchResult {
+			ensure_root(origin)?;
+
+			let count = <DeadLetterCount<T>>::take();
+			let _ = <DeadLetters<T>>::clear(count, None);
+			Self::deposit_event(Event::<T>::DeadLettersPurged(count));
+			Ok(())
+		}
+
+		/// Acknowledge that the data commands with a sequence number below `up_to` were processed,
+		/// allowing them to be pruned; submitted by the offchain worker.
+		#[pallet::weight((T::WeightInfo::ack_data_commands(), DispatchClass::Operational))]
+		pub fn ack_data_commands(origin: OriginFor<T>, up_to: u64) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+
+			let up_to = up_to.min(<DataQueueTail<T>>::get());
+			if up_to > <DataQueueAcked<T>>::get() {
+				<DataQueueAcked<T>>::put(up_to);
+				Self::deposit_event(Event::<T>::DataCommandsAcknowledged(up_to));
+			}
+			Ok(())
+		}
+
+		/// Record how far the reporter's offchain worker has got with queued data commands, up to
+		/// `MAX_STATUS_UPDATES` at once; submitted by the offchain worker. Commands completed by
+		/// any worker stay completed, and unknown or expired requests are ignored.
+		#[pallet::weight((
+            T::WeightInfo::update_request_statuses(statuses.len() as u32),
+            DispatchClass::Operational,
+        ))]
+		pub fn update_request_statuses(
+			origin: OriginFor<T>,
+			statuses: Vec<(RequestId, RequestStatus<T::Hash>)>,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+			ensure!(statuses.len() as u32 <= MAX_STATUS_UPDATES, Error::<T>::TooManyStatusUpdates);
+
+			let expires_at =
+				<frame_system::Pallet<T>>::block_number().saturating_add(T::ResultTtl::get());
+			for (request, status) in statuses {
+				let updated = <Requests<T>>::mutate(request, |current| match current {
+					Some(current) if current.may_become(&status) => {
+						if !current.is_final() && status.is_final() {
+							<RequestExpiry<T>>::append(expires_at, request);
+						}
+						match &status {
+							RequestStatus::Done(result) => {
+								T::Promises::resolve(&AsyncOp::IpfsRequest(request), *result);
+								Self::notify_caller(request, Ok(*result));
+							},
+							RequestStatus::Failed(error) => {
+								T::Promises::reject(&AsyncOp::IpfsRequest(request), *error);
+								Self::notify_caller(request, Err(*error));
+							},
+							_ => {},
+						}
+						*current = status.clone();
+						true
+					},
+					_ => false,
+				});
+				if updated {
+					Self::deposit_event(Event::<T>::RequestStatusChanged(request, status));
+				}
+			}
+			Ok(())
+		}
+
+		/// Store a message received on a subscribed pubsub topic in the off-chain index, under
+		/// `ipfs::pubsub::` followed by the encoded topic and message hash; submitted by the
+		/// offchain worker.
+		#[pallet::weight((
+			T::WeightInfo::submit_pubsub_message(data.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn submit_pubsub_message(
+			origin: OriginFor<T>,
+			topic: Vec<u8>,
+			data: Vec<u8>,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+
+			let hash = T::Hashing::hash(&data);
+			let mut key = PUBSUB_INDEX_PREFIX.to_vec();
+			key.extend((&topic, hash).encode());
+			sp_io::offchain_index::set(&key, &data);
+			Self::deposit_event(Event::<T>::PubsubMessageReceived(topic, hash));
+			Ok(())
+		}
+
+		/// Store the result of a `FindPeer` or `GetProviders` query and announce the addresses or
+		/// providers found; submitted by the offchain worker as an unsigned transaction.
+		#[pallet::weight((
+            T::WeightInfo::submit_dht_result(result.iter().map(|r| r.len()).sum::<usize>() as u32),
+            DispatchClass::Operational,
+        ))]
+		pub fn submit_dht_result(
+			origin: OriginFor<T>,
+			query: DhtCommand,
+			result: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+
+			let request = T::Hashing::hash_of(&query);
+			<PendingResults<T>>::remove(request);
+			T::Promises::resolve(&AsyncOp::DhtQuery(request), T::Hashing::hash_of(&result));
+			<DhtResults<T>>::insert(
+				request,
+				(<frame_system::Pallet<T>>::block_number(), result.clone()),
+			);
+			match query {
+				DhtCommand::FindPeer(peer_id) => {
+					Self::deposit_event(Event::<T>::PeerFound(peer_id, result))
+				},
+				DhtCommand::GetProviders(cid) => {
+					Self::deposit_event(Event::<T>::ProvidersFound(cid, result))
+				},
+				DhtCommand::Provide(_) => {},
+			}
+			Self::deposit_event(Event::<T>::DhtResultStored(request));
+			Ok(())
+		}
+
+		/// Store the health figures collected by an offchain worker; submitted every 5 blocks as an
+		/// unsigned transaction by nodes without a reporter key, reporters use `submit_report`.
+		#[pallet::weight((T::WeightInfo::submit_node_stats(), DispatchClass::Operational))]
+		pub fn submit_node_stats(
+			origin: OriginFor<T>,
+			stats: NodeHealth<T::BlockNumber>,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+
+			Self::record_stats(stats);
+			Ok(())
+		}
+
+		/// Store the data obtained by a cat request; submitted by the offchain worker as an
+		/// unsigned transaction.
+		#[pallet::weight((
+			T::WeightInfo::submit_cat_result(data.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn submit_cat_result(
+			origin: OriginFor<T>,
+			request: T::Hash,
+			data: Vec<u8>,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+
+			<PendingResults<T>>::remove(request);
+			T::OnResult::on_cat_result(&request, &data);
+			<CatResults<T>>::insert(request, (<frame_system::Pallet<T>>::block_number(), data));
+			Self::deposit_event(Event::<T>::CatResultStored(request));
+			Ok(())
+		}
+
+		/// Flag content whose data doesn't match the schema it declares; submitted by the
+		/// offchain worker.
+		#[pallet::weight((T::WeightInfo::report_schema_mismatch(), DispatchClass::Operational))]
+		pub fn report_schema_mismatch(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			schema: SchemaId,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+
+			Self::deposit_event(Event::<T>::SchemaMismatch(cid, schema));
+			Ok(())
+		}
+
+		/// Record how far the reporter's offchain worker has got re-pinning registered content
+		/// missing from its repo; submitted by the offchain worker.
+		#[pallet::weight((T::WeightInfo::report_recovery(), DispatchClass::Operational))]
+		pub fn report_recovery(origin: OriginFor<T>, progress: RecoveryProgress) -> DispatchResult {
+			let who = Self::ensure_reporter(origin)?;
+			ensure!(progress.round <= Self::recovery_round(), Error::<T>::UnknownRecoveryRound);
+
+			<Recoveries<T>>::insert(&who, &progress);
+			Self::deposit_event(Event::<T>::RecoveryProgressed(who, progress));
+			Ok(())
+		}
+
+		/// Record failures the reporter's offchain workers ran into; submitted by the offchain
+		/// worker. Only the latest `incidents::MAX_RECENT` incidents are kept; older ones are
+		/// archived to IPFS in batches of `incidents::ARCHIVE_BATCH`.
+		#[pallet::weight((
+			T::WeightInfo::report_incidents(incidents.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn report_incidents(
+			origin: OriginFor<T>,
+			incidents: Vec<OffchainIncident<T::Hash, T::BlockNumber>>,
+		) -> DispatchResult {
+			let who = Self::ensure_reporter(origin)?;
+			ensure!(
+				incidents.len() as u32 <= incidents::MAX_PER_REPORT,
+				Error::<T>::TooManyIncidents
+			);
+
+			<RecentIncidents<T>>::mutate(|recent| {
+				for incident in incidents {
+					recent.push((who.clone(), incident.clone()));
+					Self::deposit_event(Event::<T>::OffchainIncidentReported(
+						who.clone(),
+						incident,
+					));
+				}
+				let excess = recent.len().saturating_sub(incidents::MAX_RECENT);
+				<PrunedIncidents<T>>::mutate(|pruned| pruned.extend(recent.drain(..excess)));
+			});
+			Self::archive_incidents();
+			Ok(())
+		}
+
+		/// Attest that the reporter's IPFS node keeps the given `Cid`s pinned; submitted by the
+		/// offchain worker for the content assigned to it. `Cid`s that are no longer registered or
+		/// assigned to the reporter are ignored.
+		#[pallet::weight((
+			T::WeightInfo::attest_pins(cids.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn attest_pins(origin: OriginFor<T>, cids: Vec<Vec<u8>>) -> DispatchResult {
+			let who = Self::ensure_reporter(origin)?;
+			Self::record_attestations(who, cids)
+		}
+
+		/// Reserve `StorageBond` from a reporter so that it is assigned storage challenges; pinners
+		/// that miss or fail a challenge are slashed `ChallengeSlash` of it.
+		#[pallet::weight(T::WeightInfo::bond_storage())]
+		pub fn bond_storage(origin: OriginFor<T>) -> DispatchResult {
+			let who = Self::ensure_reporter(origin)?;
+			ensure!(!<StorageBonds<T>>::contains_key(&who), Error::<T>::AlreadyBonded);
+
+			let bond = T::StorageBond::get();
+			T::Currency::reserve(&who, bond)?;
+			<StorageBonds<T>>::insert(&who, bond);
+			Self::deposit_event(Event::<T>::StorageBonded(who, bond));
+			Ok(())
+		}
+
+		/// Release what is left of the caller's storage bond; only possible while the caller is not
+		/// a party to unresolved challenges.
+		#[pallet::weight(T::WeightInfo::unbond_storage())]
+		pub fn unbond_storage(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::open_challenges(&who) == 0, Error::<T>::ChallengesOpen);
+			let bond = <StorageBonds<T>>::take(&who).ok_or(Error::<T>::NotBonded)?;
+
+			T::Currency::unreserve(&who, bond);
+			Self::deposit_event(Event::<T>::StorageUnbonded(who, bond));
+			Ok(())
+		}
+
+		/// Respond to a storage challenge with the hash of the challenged byte range and the
+		/// challenge seed; submitted by the offchain worker. Responses are checked against each
+		/// other when the challenge is resolved.
+		#[pallet::weight((T::WeightInfo::respond_challenge(), DispatchClass::Operational))]
+		pub fn respond_challenge(
+			origin: OriginFor<T>,
+			challenge: ChallengeId,
+			response: T::Hash,
+		) -> DispatchResult {
+			let who = Self::ensure_reporter(origin)?;
+			let pinners = Self::challenge(challenge).ok_or(Error::<T>::UnknownChallenge)?.pinners;
+			ensure!(pinners.contains(&who), Error::<T>::NotChallenged);
+			ensure!(
+				!<ChallengeResponses<T>>::contains_key(challenge, &who),
+				Error::<T>::AlreadyResponded
+			);
+
+			<ChallengeResponses<T>>::insert(challenge, &who, response);
+			Self::deposit_event(Event::<T>::ChallengeResponded(challenge, who));
+			Ok(())
+		}
+
+		/// Let `grantee` retrieve content the caller owns, or is a writer of the bucket of, from
+		/// gateways for the next `ttl` blocks, up to `MaxAccessTtl`; replaces an earlier grant to
+		/// the same account.
+		#[pallet::weight(T::WeightInfo::grant_access())]
+		pub fn grant_access(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			grantee: T::AccountId,
+			ttl: T::BlockNumber,
+		) -> DispatchResult {
+			Self::ensure_content_owner_or_root(origin, &cid)?;
+			ensure!(!ttl.is_zero() && ttl <= T::MaxAccessTtl::get(), Error::<T>::InvalidAccessTtl);
+
+			let expires_at = <frame_system::Pallet<T>>::block_number().saturating_add(ttl);
+			<AccessGrants<T>>::insert(&cid, &grantee, expires_at);
+			<AccessExpiry<T>>::append(expires_at, (cid.clone(), grantee.clone()));
+			Self::deposit_event(Event::<T>::AccessGranted(cid, grantee, expires_at));
+			Ok(())
+		}
+
+		/// Withdraw an access grant before it expires.
+		#[pallet::weight(T::WeightInfo::revoke_access())]
+		pub fn revoke_access(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			grantee: T::AccountId,
+		) -> DispatchResult {
+			Self::ensure_content_owner_or_root(origin, &cid)?;
+			ensure!(<AccessGrants<T>>::contains_key(&cid, &grantee), Error::<T>::NoAccessGrant);
+
+			<AccessGrants<T>>::remove(&cid, &grantee);
+			Self::deposit_event(Event::<T>::AccessRevoked(cid, grantee));
+			Ok(())
+		}
+
+		/// Have the IPFS nodes remove unpinned blocks from their repositories.
+		#[pallet::weight(T::WeightInfo::ipfs_run_gc())]
+		pub fn ipfs_run_gc(origin: OriginFor<T>) -> DispatchResult {
+			ensure_root(origin)?;
+
+			let request = Self::enqueue_data(DataCommand::CollectGarbage, Priority::Low)?;
+			Self::deposit_event(Event::<T>::QueuedGarbageCollection(request));
+			Ok(())
+		}
+
+		/// Unpin and remove content to make room in a reporter's repository, which grew past
+		/// `MaxRepoSize`; submitted by the offchain worker. Owners get their storage deposits back.
+		/// `Cid`s that are no longer registered or are leased are ignored.
+		#[pallet::weight((
+			T::WeightInfo::evict_content(cids.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn evict_content(origin: OriginFor<T>, cids: Vec<Vec<u8>>) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+			ensure!(cids.len() as u32 <= MAX_EVICTIONS, Error::<T>::TooManyEvictions);
+
+			let mut evicted = false;
+			for cid in cids {
+				let content = match Self::content(&cid) {
+					Some(content) if content.expires_at.is_none() => content,
+					_ => continue,
+				};
+				if content.pin_status == PinStatus::Pinned {
+					let unpin =
+						DataCommand::RemovePin(cid.clone(), content.pin_mode.is_recursive());
+					Self::enqueue_data(unpin, Priority::High)?;
+				}
+				Self::enqueue_data(DataCommand::RemoveBlock(cid.clone()), Priority::High)?;
+
+				<ContentRegistry<T>>::remove(&cid);
+				<EncryptedContent<T>>::remove(&cid);
+				Self::forget_content(&cid);
+				Self::release_deposit(&content.owner, content.deposit);
+				Self::deposit_event(Event::<T>::EvictedContent(cid));
+				evicted = true;
+			}
+			// reclaim the space of the removed blocks
+			if evicted {
+				Self::enqueue_data(DataCommand::CollectGarbage, Priority::Low)?;
+			}
+			Ok(())
+		}
+
+		/// Refuse to host the given `Cid`: adding data with that `Cid` and pinning it are rejected
+		/// from now on, and content already registered under it is unpinned and removed by the
+		/// offchain workers, with its owner's deposit released.
+		#[pallet::weight(T::WeightInfo::deny_cid())]
+		pub fn deny_cid(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResult {
+			T::ModerationOrigin::ensure_origin(origin)?;
+			Self::ensure_valid_cid(&cid)?;
+
+			<Denylist<T>>::insert(&cid, true);
+			if let Some(content) = <ContentRegistry<T>>::get(&cid) {
+				if content.pin_status == PinStatus::Pinned {
+					let unpin =
+						DataCommand::RemovePin(cid.clone(), content.pin_mode.is_recursive());
+					Self::enqueue_data(unpin, Priority::High)?;
+				}
+				Self::enqueue_data(DataCommand::RemoveBlock(cid.clone()), Priority::High)?;
+
+				<ContentRegistry<T>>::remove(&cid);
+				<EncryptedContent<T>>::remove(&cid);
+				Self::forget_content(&cid);
+				Self::release_deposit(&content.owner, content.deposit);
+				<DeniedOwners<T>>::insert(&cid, &content.owner);
+				Self::deposit_event(Event::<T>::DeniedContentRemoved(cid.clone(), content.owner));
+			}
+			Self::deposit_event(Event::<T>::CidDenied(cid));
+			Ok(())
+		}
+
+		/// Take the given `Cid` off the denylist, so it may be added and pinned again. A pending
+		/// appeal against the denial is closed and its deposit released.
+		#[pallet::weight(T::WeightInfo::allow_cid())]
+		pub fn allow_cid(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResult {
+			T::ModerationOrigin::ensure_origin(origin)?;
+			ensure!(Self::is_denied(&cid), Error::<T>::NotDenied);
+
+			if let Some(appeal) = <Appeals<T>>::take(&cid) {
+				Self::release_deposit(&appeal.appellant, appeal.deposit);
+			}
+			Self::lift_denial(cid);
+			Ok(())
+		}
+
+		/// Issue up to `MaxBatchLen` commands at once. Each command is checked and queued as if
+		/// issued through the call of the same name, counts against the caller's quotas and emits
+		/// that call's event, with that call's refund; if any of them fails, none is queued.
+		#[pallet::weight(Pallet::<T>::batch_weight(commands))]
+		pub fn ipfs_batch(
+			origin: OriginFor<T>,
+			commands: Vec<IpfsCommand>,
+		) -> DispatchResultWithPostInfo {
+			ensure!(commands.len() as u32 <= T::MaxBatchLen::get(), Error::<T>::BatchTooLarge);
+
+			let mut weight = Self::batch_weight(&commands);
+			for command in commands {
+				match command {
+					IpfsCommand::Connect(addr) => {
+						let charged = T::WeightInfo::ipfs_connect(T::MaxQueueLen::get());
+						let actual = Self::ipfs_connect(origin.clone(), addr)?
+							.actual_weight
+							.unwrap_or(charged);
+						weight = weight.saturating_sub(charged.saturating_sub(actual));
+					},
+					IpfsCommand::AddBytes(data) => {
+						Self::ipfs_add_bytes(origin.clone(), data, None, None, false)?
+					},
+					IpfsCommand::CatBytes(cid) => {
+						Self::ipfs_cat_bytes(origin.clone(), cid, None, None)?
+					},
+					IpfsCommand::InsertPin(cid, recursive) => {
+						Self::ipfs_insert_pin(origin.clone(), cid, recursive)?
+					},
+					IpfsCommand::RemovePin(cid, recursive) => {
+						Self::ipfs_remove_pin(origin.clone(), cid, recursive)?
+					},
+					IpfsCommand::RemoveBlock(cid) => Self::ipfs_remove_block(origin.clone(), cid)?,
+				}
+			}
+			Ok(Some(weight).into())
+		}
+
+		/// Have the offchain workers export the content registry, the services' quotas and the
+		/// replication assignments to IPFS; see the `snapshot` module. The snapshot becomes the
+		/// `last_export` once a reporter submits it.
+		#[pallet::weight(T::WeightInfo::export_registry())]
+		pub fn export_registry(origin: OriginFor<T>) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			<PendingExport<T>>::put(now);
+			Self::deposit_event(Event::<T>::RegistryExportRequested(now));
+			Ok(())
+		}
+
+		/// Record the registry snapshot exported for the request made in block `requested_at`;
+		/// submitted by the offchain worker.
+		#[pallet::weight((T::WeightInfo::submit_registry_export(), DispatchClass::Operational))]
+		pub fn submit_registry_export(
+			origin: OriginFor<T>,
+			requested_at: T::BlockNumber,
+			export: RegistryExport<T::BlockNumber>,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+			ensure!(Self::pending_export() == Some(requested_at), Error::<T>::NoPendingExport);
+			Self::ensure_valid_cid(&export.cid)?;
+
+			<PendingExport<T>>::kill();
+			Self::deposit_event(Event::<T>::RegistryExported(export.cid.clone(), export.entries));
+			<LastExport<T>>::put(export);
+			Ok(())
+		}
+
+		/// Rebuild the registry from a snapshot another chain exported, given the export as that
+		/// chain recorded it; the offchain workers fetch the snapshot and restore it in pages.
+		#[pallet::weight(T::WeightInfo::import_registry())]
+		pub fn import_registry(
+			origin: OriginFor<T>,
+			export: RegistryExport<T::BlockNumber>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_valid_cid(&export.cid)?;
+			ensure!(export.entries > 0, Error::<T>::InvalidSnapshot);
+			ensure!(Self::pending_import().is_none(), Error::<T>::ImportInProgress);
+
+			Self::deposit_event(Event::<T>::RegistryImportRequested(export.cid.clone()));
+			<PendingImport<T>>::put(RegistryImport { export, restored: 0 });
+			Ok(())
+		}
+
+		/// Restore the entries of the registry snapshot being imported, starting with the one at
+		/// `start`; submitted by the offchain worker. Content already in the registry is left
+		/// untouched; the rest is registered without storage deposits, keeps the number of blocks
+		/// its lease had left and is pinned by the offchain workers like an imported pinset.
+		#[pallet::weight((
+			T::WeightInfo::restore_registry(entries.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn restore_registry(
+			origin: OriginFor<T>,
+			start: u32,
+			entries: Vec<RegistryEntryOf<T>>,
+		) -> DispatchResult {
+			Self::ensure_reporter(origin)?;
+			let mut import = Self::pending_import().ok_or(Error::<T>::NoPendingImport)?;
+			ensure!(entries.len() as u32 <= snapshot::MAX_RESTORE_PAGE, Error::<T>::TooManyEntries);
+			ensure!(start == import.restored, Error::<T>::UnexpectedRestorePage);
+			let restored = import.restored.saturating_add(entries.len() as u32);
+			ensure!(restored <= import.export.entries, Error::<T>::TooManyEntries);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			let mut count = <ImportedPinCount<T>>::get();
+			for entry in entries {
+				match entry {
+					RegistryEntry::Content(cid, content) => {
+						if <ContentRegistry<T>>::contains_key(&cid) {
+							continue;
+						}
+						let expires_at = content.expires_at.map(|expires_at| {
+							let left = expires_at.saturating_sub(import.export.exported_at);
+							now.saturating_add(left.max(One::one()))
+						});
+						if let Some(expires_at) = expires_at {
+							<LeaseExpiry<T>>::append(expires_at, &cid);
+						}
+						if content.pin_status == PinStatus::Pinned {
+							<ImportedPins<T>>::insert(count, &cid);
+							count += 1;
+						}
+						<ContentRegistry<T>>::insert(
+							&cid,
+							ContentInfo {
+								added_at: now,
+								expires_at,
+								deposit: Zero::zero(),
+								..content
+							},
+						);
+					},
+					RegistryEntry::Service(id, service) => {
+						if <Services<T>>::contains_key(id) {
+							continue;
+						}
+						<Services<T>>::insert(id, service);
+						<NextServiceId<T>>::mutate(|next| {
+							*next = (*next).max(id.saturating_add(1))
+						});
+					},
+					// a successor chain assigns the content to its own reporters
+					RegistryEntry::Assignment(..) => {},
+				}
+			}
+			<ImportedPinCount<T>>::put(count);
+
+			if restored == import.export.entries {
+				<PendingImport<T>>::kill();
+				Self::deposit_event(Event::<T>::RegistryImported(import.export.cid, restored));
+			} else {
+				import.restored = restored;
+				<PendingImport<T>>::put(import);
+			}
+			Ok(())
+		}
+
+		/// Create a bucket named `name`, unique among the caller's buckets, to share content with
+		/// other accounts.
+		#[pallet::weight(T::WeightInfo::create_bucket())]
+		pub fn create_bucket(origin: OriginFor<T>, name: Vec<u8>) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+			ensure!(name.len() <= MAX_BUCKET_NAME_LEN, Error::<T>::BucketNameTooLong);
+			ensure!(!<BucketNames<T>>::contains_key(&owner, &name), Error::<T>::BucketExists);
+
+			let id = <NextBucketId<T>>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			<BucketNames<T>>::insert(&owner, &name, id);
+			<Buckets<T>>::insert(id, BucketInfo { owner: owner.clone(), name });
+			Self::deposit_event(Event::<T>::BucketCreated(owner, id));
+			Ok(())
+		}
+
+		/// Grant `who` a role in a bucket the caller owns, replacing the role it held before.
+		#[pallet::weight(T::WeightInfo::grant_bucket_role())]
+		pub fn grant_bucket_role(
+			origin: OriginFor<T>,
+			bucket: BucketId,
+			who: T::AccountId,
+			role: BucketRole,
+		) -> DispatchResult {
+			Self::ensure_bucket_owner(origin, bucket)?;
+
+			<BucketRoles<T>>::insert(bucket, &who, role);
+			Self::deposit_event(Event::<T>::BucketRoleGranted(bucket, who, role));
+			Ok(())
+		}
+
+		/// Take the role `who` holds in a bucket the caller owns.
+		#[pallet::weight(T::WeightInfo::revoke_bucket_role())]
+		pub fn revoke_bucket_role(
+			origin: OriginFor<T>,
+			bucket: BucketId,
+			who: T::AccountId,
+		) -> DispatchResult {
+			Self::ensure_bucket_owner(origin, bucket)?;
+			ensure!(<BucketRoles<T>>::contains_key(bucket, &who), Error::<T>::NoBucketRole);
+
+			<BucketRoles<T>>::remove(bucket, &who);
+			Self::deposit_event(Event::<T>::BucketRoleRevoked(bucket, who));
+			Ok(())
+		}
+
+		/// Add content the caller owns to a bucket it is a writer of. From then on the bucket's
+		/// writers may pin, unpin and remove the content, and its readers retrieve it.
+		#[pallet::weight(T::WeightInfo::add_to_bucket())]
+		pub fn add_to_bucket(
+			origin: OriginFor<T>,
+			bucket: BucketId,
+			cid: Vec<u8>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let content = Self::content(&cid).ok_or(Error::<T>::UnknownContent)?;
+			ensure!(content.owner == who, Error::<T>::NotContentOwner);
+			ensure!(<Buckets<T>>::contains_key(bucket), Error::<T>::UnknownBucket);
+			ensure!(
+				Self::role_in_bucket(bucket, &who) == Some(BucketRole::Writer),
+				Error::<T>::NotBucketWriter
+			);
+			ensure!(!<ContentBuckets<T>>::contains_key(&cid), Error::<T>::AlreadyInBucket);
+
+			<ContentBuckets<T>>::insert(&cid, bucket);
+			Self::deposit_event(Event::<T>::AddedToBucket(bucket, cid));
+			Ok(())
+		}
+
+		/// Issue `command` for registered content every `period` blocks, starting `period` blocks
+		/// from now, until the task is cancelled. Only the content owner or the writers of its
+		/// bucket may schedule tasks for it; each account may have up to `MaxSchedulesPerAccount`.
+		#[pallet::weight(T::WeightInfo::schedule_task())]
+		pub fn schedule_task(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			command: ScheduledCommand,
+			period: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(origin.clone())?;
+			Self::ensure_content_owner_or_root(origin, &cid)?;
+			ensure!(!period.is_zero(), Error::<T>::InvalidSchedulePeriod);
+			let count = Self::schedule_count(&who);
+			ensure!(count < T::MaxSchedulesPerAccount::get(), Error::<T>::TooManySchedules);
+
+			let id = <NextScheduleId<T>>::mutate(|id| {
+				let current = *id;
+				*id += 1;
+				current
+			});
+			let next_run = <frame_system::Pallet<T>>::block_number().saturating_add(period);
+			<ScheduledRuns<T>>::append(next_run, id);
+			<Schedules<T>>::insert(id, ScheduleInfo { owner: who.clone(), cid, command, period });
+			<ScheduleCount<T>>::insert(&who, count + 1);
+			Self::deposit_event(Event::<T>::TaskScheduled(who, id));
+			Ok(())
+		}
+
+		/// Stop a scheduled task; only its owner or root may do so.
+		#[pallet::weight(T::WeightInfo::cancel_schedule())]
+		pub fn cancel_schedule(origin: OriginFor<T>, id: ScheduleId) -> DispatchResult {
+			let schedule = Self::schedule(id).ok_or(Error::<T>::UnknownSchedule)?;
+			match origin.into() {
+				Ok(frame_system::RawOrigin::Root) => {},
+				Ok(frame_system::RawOrigin::Signed(who)) if who == schedule.owner => {},
+				Ok(frame_system::RawOrigin::Signed(_)) => {
+					return Err(Error::<T>::NotScheduleOwner.into())
+				},
+				_ => return Err(DispatchError::BadOrigin),
+			}
+
+			<Schedules<T>>::remove(id);
+			<ScheduleCount<T>>::mutate(&schedule.owner, |count| *count = count.saturating_sub(1));
+			Self::deposit_event(Event::<T>::ScheduleCancelled(id));
+			Ok(())
+		}
+
+		/// Point the caller's IPNS name at the given `Cid`, so the caller can keep a fixed name for
+		/// content it updates over time. The name is created on first publication and announced
+		/// with `IpnsPublished` once an offchain worker has published it.
+		#[pallet::weight(T::WeightInfo::ipfs_ipns_publish())]
+		pub fn ipfs_ipns_publish(origin: OriginFor<T>, cid: Vec<u8>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_valid_cid(&cid)?;
+			ensure!(!Self::is_denied(&cid), Error::<T>::ContentDenied);
+			let usage = Self::check_quota(&who, 0)?;
+			Self::ensure_not_paused(CommandKind::Pin)?;
+
+			let cmd = DataCommand::IpnsPublish(who.clone(), cid.clone());
+			let request = Self::enqueue_data_for(&who, cmd, Priority::Normal)?;
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::QueuedIpnsPublish(who, cid, request));
+			Ok(())
+		}
+
+		/// Find the `Cid` the given IPNS name points at; it is announced with `IpnsResolved`.
+		#[pallet::weight(T::WeightInfo::ipfs_ipns_resolve())]
+		pub fn ipfs_ipns_resolve(origin: OriginFor<T>, name: Vec<u8>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				!name.is_empty() && name.len() <= MAX_IPNS_NAME_LEN,
+				Error::<T>::InvalidIpnsName
+			);
+			let usage = Self::check_quota(&who, 0)?;
+			Self::ensure_not_paused(CommandKind::Cat)?;
+
+			let request = Self::enqueue_data_for(
+				&who,
+				DataCommand::IpnsResolve(name.clone()),
+				Priority::Normal,
+			)?;
+			<Usage<T>>::insert(&who, usage);
+			Self::deposit_event(Event::<T>::QueuedIpnsResolve(who, name, request));
+			Ok(())
+		}
+
+		/// Apply a report signed with a reporter's offchain worker key; submitted by the offchain
+		/// worker as an unsigned transaction, so reporters don't pay fees for their feedback. The
+		/// signature and the age of the report are checked when the transaction is validated.
+		#[pallet::weight((Pallet::<T>::report_weight(&payload.report), DispatchClass::Operational))]
+		pub fn submit_report(
+			origin: OriginFor<T>,
+			payload: ReportPayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let who = payload.public.clone().into_account();
+			ensure!(Self::reporters().contains(&who), Error::<T>::NotAReporter);
+
+			<AcceptedReports<T>>::insert(payload.block_number, T::Hashing::hash_of(&payload), ());
+			<LastReported<T>>::insert(&who, <frame_system::Pallet<T>>::block_number());
+			match payload.report {
+				WorkerReport::Cid { owner, cid, size, lease, encrypted } => {
+					Self::record_cid(owner, cid, size, lease, encrypted)
+				},
+				WorkerReport::Stats(stats) => Self::record_stats(stats),
+				WorkerReport::Attestations(cids) => Self::record_attestations(who, cids)?,
+				WorkerReport::IpnsPublished { owner, name, cid } => {
+					<IpnsNames<T>>::insert(
+						owner,
+						IpnsBinding { name: name.clone(), cid: cid.clone(), publisher: who },
+					);
+					Self::deposit_event(Event::<T>::IpnsPublished(name, cid));
+				},
+				WorkerReport::IpnsResolved { name, cid } => {
+					Self::deposit_event(Event::<T>::IpnsResolved(name, cid))
+				},
+			}
+			Ok(())
+		}
+
+		/// Pay `BumpFee` to hurry a data command the caller queued that hasn't completed yet. A
+		/// queued command moves up a priority class, up to `High`, and all its attempts get
+		/// `MaxRequestTimeout` from then on; offchain workers backing off from a failed attempt
+		/// retry it right away. The fee is burned.
+		#[pallet::weight(T::WeightInfo::bump_request())]
+		pub fn bump_request(origin: OriginFor<T>, request: RequestId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let status = Self::request_status(request).ok_or(Error::<T>::UnknownRequest)?;
+			ensure!(
+				Self::request_caller(request) == Some(who.clone()),
+				Error::<T>::NotRequestCaller
+			);
+			ensure!(!status.is_final(), Error::<T>::RequestNotPending);
+
+			// commands already picked up keep their priority
+			let raised = match <DataQueue<T>>::get(request) {
+				Some((priority, cmd))
+					if status == RequestStatus::Queued && priority != Priority::High =>
+				{
+					let raised =
+						if priority == Priority::Low { Priority::Normal } else { Priority::High };
+					<DataQueue<T>>::insert(request, (raised, cmd));
+					true
+				},
+				_ => false,
+			};
+			// the deadline is only extended once, but a stuck command may be retried again
+			ensure!(
+				raised
+					|| status == RequestStatus::Processing
+					|| !<BumpedAt<T>>::contains_key(request),
+				Error::<T>::AlreadyBumped
+			);
+
+			let fee = T::BumpFee::get();
+			let _ = T::Currency::withdraw(
+				&who,
+				fee,
+				WithdrawReasons::FEE,
+				ExistenceRequirement::KeepAlive,
+			)?;
+			<BumpedAt<T>>::insert(request, <frame_system::Pallet<T>>::block_number());
+			Self::deposit_event(Event::<T>::RequestBumped(who, request, fee));
+			Ok(())
+		}
+
+		/// Appeal the denial of the `Cid` the caller's content was removed under, citing the
+		/// documents with the `evidence` `Cid`s, up to `MAX_APPEAL_EVIDENCE` of them.
+		/// `AppealDeposit` is reserved until `AppealOrigin` rules on the appeal; if it hasn't
+		/// within `AppealPeriod` blocks, the denial is lifted and the deposit released.
+		#[pallet::weight(T::WeightInfo::appeal_denial(evidence.len() as u32))]
+		pub fn appeal_denial(
+			origin: OriginFor<T>,
+			cid: Vec<u8>,
+			evidence: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Self::is_denied(&cid), Error::<T>::NotDenied);
+			ensure!(Self::denied_owner(&cid) == Some(who.clone()), Error::<T>::NotDeniedOwner);
+			ensure!(!<Appeals<T>>::contains_key(&cid), Error::<T>::AlreadyAppealed);
+			ensure!(
+				!evidence.is_empty() && evidence.len() as u32 <= MAX_APPEAL_EVIDENCE,
+				Error::<T>::InvalidEvidence
+			);
+			for document in &evidence {
+				Self::ensure_valid_cid(document).map_err(|_| Error::<T>::InvalidEvidence)?;
+			}
+
+			let deposit = T::AppealDeposit::get();
+			T::Currency::reserve(&who, deposit)?;
+			let deadline =
+				<frame_system::Pallet<T>>::block_number().saturating_add(T::AppealPeriod::get());
+			<AppealExpiry<T>>::append(deadline, &cid);
+			<Appeals<T>>::insert(
+				&cid,
+				Appeal { appellant: who.clone(), deposit, evidence, deadline },
+			);
+			Self::deposit_event(Event::<T>::AppealFiled(cid, who, deadline));
+			Ok(())
+		}
+
+		/// Rule on the appeal against the denial of `cid`. An upheld appeal lifts the denial and
+		/// releases the appellant's deposit; a dismissed one keeps the denial and slashes the
+		/// deposit.
+		#[pallet::weight(T::WeightInfo::rule_appeal())]
+		pub fn rule_appeal(origin: OriginFor<T>, cid: Vec<u8>, upheld: bool) -> DispatchResult {
+			T::AppealOrigin::ensure_origin(origin)?;
+			let appeal = <Appeals<T>>::take(&cid).ok_or(Error::<T>::UnknownAppeal)?;
+
+			if upheld {
+				Self::release_deposit(&appeal.appellant, appeal.deposit);
+				Self::deposit_event(Event::<T>::AppealUpheld(cid.clone()));
+				Self::lift_denial(cid);
+			} else {
+				let (_, unslashed) = T::Currency::slash_reserved(&appeal.appellant, appeal.deposit);
+				let slashed = appeal.deposit.saturating_sub(unslashed);
+				Self::deposit_event(Event::<T>::AppealDismissed(cid, slashed));
+			}
+			Ok(())
+		}
+
+		/// Grant `who` a role in the namespace of service `id`, replacing the role it held
+		/// before; only callable by the namespace's admins and `NamespaceAdminOrigin`.
+		#[pallet::weight(T::WeightInfo::grant_namespace_role())]
+		pub fn grant_namespace_role(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			who: T::AccountId,
+			role: NamespaceRole,
+		) -> DispatchResult {
+			Self::ensure_namespace_admin(origin, id, None)?;
+
+			<NamespaceRoles<T>>::insert(id, &who, role);
+			Self::deposit_event(Event::<T>::NamespaceRoleGranted(id, who, role));
+			Ok(())
+		}
+
+		/// Take the role `who` holds in the namespace of service `id`. The names it bound stay
+		/// bound, and count against its member quota, until they are unbound.
+		#[pallet::weight(T::WeightInfo::revoke_namespace_role())]
+		pub fn revoke_namespace_role(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			who: T::AccountId,
+		) -> DispatchResult {
+			Self::ensure_namespace_admin(origin, id, None)?;
+			ensure!(<NamespaceRoles<T>>::contains_key(id, &who), Error::<T>::NoNamespaceRole);
+
+			<NamespaceRoles<T>>::remove(id, &who);
+			Self::deposit_event(Event::<T>::NamespaceRoleRevoked(id, who));
+			Ok(())
+		}
+
+		/// Let `who` bind names in the namespace of service `id` to `bytes` of content in total.
+		/// A quota below what the member already uses only stops it from binding more.
+		#[pallet::weight(T::WeightInfo::set_member_quota())]
+		pub fn set_member_quota(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			who: T::AccountId,
+			bytes: u64,
+		) -> DispatchResult {
+			Self::ensure_namespace_admin(origin, id, None)?;
+
+			<MemberQuotas<T>>::mutate(id, &who, |quota| quota.granted = bytes);
+			Self::deposit_event(Event::<T>::MemberQuotaSet(id, who, bytes));
+			Ok(())
+		}
+
+		/// Bind `name` in the namespace of service `id` to registered content the caller owns,
+		/// tagged with `tags`, counting the content's size against the caller's member quota.
+		/// Only the namespace's writers and admins may bind names, each name once.
+		#[pallet::weight(T::WeightInfo::bind_name())]
+		pub fn bind_name(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			name: Vec<u8>,
+			cid: Vec<u8>,
+			tags: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(<Services<T>>::contains_key(id), Error::<T>::UnknownService);
+			ensure!(Self::role_in_namespace(id, &who).is_some(), Error::<T>::NotNamespaceWriter);
+			ensure!(name.len() <= MAX_NAMESPACE_NAME_LEN, Error::<T>::NamespaceNameTooLong);
+			Self::ensure_valid_tags(&tags)?;
+			ensure!(!<NamespaceEntries<T>>::contains_key(id, &name), Error::<T>::NameTaken);
+			let content = Self::content(&cid).ok_or(Error::<T>::UnknownContent)?;
+			ensure!(content.owner == who, Error::<T>::NotContentOwner);
+
+			let size = content.size.unwrap_or(0);
+			<MemberQuotas<T>>::try_mutate(id, &who, |quota| -> DispatchResult {
+				let used = quota.used.saturating_add(size);
+				ensure!(used <= quota.granted, Error::<T>::MemberQuotaExceeded);
+				quota.used = used;
+				Ok(())
+			})?;
+			let entry = NamespaceEntry { cid: cid.clone(), bound_by: who, size, tags };
+			<NamespaceEntries<T>>::insert(id, &name, entry);
+			Self::deposit_event(Event::<T>::NameBound(id, name, cid));
+			Ok(())
+		}
+
+		/// Unbind `name` in the namespace of service `id`, releasing the member quota its
+		/// content used; only callable by the account that bound it, the namespace's admins and
+		/// `NamespaceAdminOrigin`.
+		#[pallet::weight(T::WeightInfo::unbind_name())]
+		pub fn unbind_name(origin: OriginFor<T>, id: ServiceId, name: Vec<u8>) -> DispatchResult {
+			let entry = Self::namespace_entry(id, &name).ok_or(Error::<T>::UnknownName)?;
+			Self::ensure_namespace_admin(origin, id, Some(&entry.bound_by))?;
+
+			<NamespaceEntries<T>>::remove(id, &name);
+			<MemberQuotas<T>>::mutate_exists(id, &entry.bound_by, |quota| {
+				if let Some(quota) = quota {
+					quota.used = quota.used.saturating_sub(entry.size);
+				}
+			});
+			Self::deposit_event(Event::<T>::NameUnbound(id, name));
+			Ok(())
+		}
+
+		/// Replace the tags of `name` in the namespace of service `id`; only callable by the
+		/// account that bound it, the namespace's admins and `NamespaceAdminOrigin`.
+		#[pallet::weight(T::WeightInfo::tag_name())]
+		pub fn tag_name(
+			origin: OriginFor<T>,
+			id: ServiceId,
+			name: Vec<u8>,
+			tags: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			let entry = Self::namespace_entry(id, &name).ok_or(Error::<T>::UnknownName)?;
+			Self::ensure_namespace_admin(origin, id, Some(&entry.bound_by))?;
+			Self::ensure_valid_tags(&tags)?;
+
+			let entry = NamespaceEntry { tags: tags.clone(), ..entry };
+			<NamespaceEntries<T>>::insert(id, &name, entry);
+			Self::deposit_event(Event::<T>::NameTagged(id, name, tags));
+			Ok(())
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		// only accept results for requests that are still waiting for one, once per request, and
+		// stats newer than the stored ones, once per block they were collected in; reports are
+		// accepted once each, signed by a reporter and no older than `UnsignedLongevity`
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			if let Call::submit_report { payload, signature } = call {
+				return Self::validate_report(payload, signature);
+			}
+
+			if let Call::submit_node_stats { stats } = call {
+				let newer = Self::node_stats()
+					.map_or(true, |current| stats.reported_at > current.reported_at);
+				if !newer || stats.reported_at > <frame_system::Pallet<T>>::block_number() {
+					return InvalidTransaction::Stale.into();
+				}
+				return ValidTransaction::with_tag_prefix("IpfsStats")
+					.priority(T::UnsignedPriority::get())
+					.and_provides(stats.reported_at)
+					.longevity(5)
+					.propagate(true)
+					.build();
+			}
+
+			let request = match call {
+				Call::submit_dht_result { query, .. } => T::Hashing::hash_of(query),
+				Call::submit_cat_result { request, data } => {
+					if data.len() > MAX_CAT_RESULT_LEN {
+						return InvalidTransaction::ExhaustsResources.into();
+					}
+					*request
+				},
+				_ => return InvalidTransaction::Call.into(),
+			};
+			if !Self::is_result_pending(request) {
+				return InvalidTransaction::Stale.into();
+			}
+
+			ValidTransaction::with_tag_prefix("IpfsResult")
+				.priority(T::UnsignedPriority::get())
+				.and_provides(request)
+				.longevity(5)
+				.propagate(true)
+				.build()
+		}
+	}
+}
+
+impl<T: Config> From<StoreError> for Error<T> {
+	fn from(e: StoreError) -> Self {
+		match e {
+			StoreError::CantCreateRequest => Error::<T>::CantCreateRequest,
+			StoreError::RequestTimeout => Error::<T>::RequestTimeout,
+			StoreError::RequestFailed => Error::<T>::RequestFailed,
+			StoreError::Unsupported => Error::<T>::UnsupportedRequest,
+		}
+	}
+}
+
+impl<T: Config> From<ingress::Rejection> for Error<T> {
+	fn from(rejection: ingress::Rejection) -> Self {
+		match rejection {
+			ingress::Rejection::TooLarge => Error::<T>::ContentTooLarge,
+			ingress::Rejection::MimeTypeDenied => Error::<T>::MimeTypeDenied,
+			ingress::Rejection::EntropyTooHigh => Error::<T>::EntropyTooHigh,
+		}
+	}
+}
+
+// Lets mpesa-user publish its receipt documents. Documents are limited to a single block, which
+// the offchain workers' stores address by its raw CIDv1, so the CID is known before the data is
+// added.
+impl<T: Config> mpesa_primitives::IpfsStore<T::AccountId> for Pallet<T> {
+	fn add_bytes(owner: &T::AccountId, data: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+		ensure!(data.len() <= cid::MAX_BLOCK_LEN, Error::<T>::ContentTooLarge);
+		let cid = cid::raw_v1(&data);
+		<Self as IpfsHandler<T::AccountId>>::add_bytes(owner, data)?;
+		Ok(cid)
+	}
+
+	fn is_pinned(cid: &[u8]) -> bool {
+		Self::content(cid).map_or(false, |content| content.pin_status == PinStatus::Pinned)
+	}
+
+	fn pin(owner: &T::AccountId, cid: Vec<u8>) -> DispatchResult {
+		<Self as IpfsHandler<T::AccountId>>::pin(owner, cid)
+	}
+}
+
+impl<T: Config> IpfsHandler<T::AccountId> for Pallet<T> {
+	type RequestId = T::Hash;
+
+	fn add_bytes(owner: &T::AccountId, data: Vec<u8>) -> Result<T::Hash, DispatchError> {
+		Self::queue_add(owner.clone(), data, Priority::Normal, |who, data| {
+			DataCommand::AddBytes(who, data, None, None)
+		})
+	}
+
+	fn cat_bytes(who: &T::AccountId, cid: Vec<u8>) -> Result<T::Hash, DispatchError> {
+		Self::queue_cat(who.clone(), cid, None, Priority::Normal)
+	}
+
+	fn pin(owner: &T::AccountId, cid: Vec<u8>) -> DispatchResult {
+		Self::queue_pin(owner.clone(), cid, Self::pin_policy().is_recursive())
+	}
+
+	fn unpin(cid: Vec<u8>) -> DispatchResult {
+		let content = Self::content(&cid).ok_or(Error::<T>::UnknownContent)?;
+		Self::queue_unpin(content.owner, cid, content.pin_mode.is_recursive())
+	}
+}
+
+// Runs the offchain workers' data requests: reports the statuses of the queued commands they
+// belong to, retries the failed ones with exponential backoff and reports those it gives up on.
+struct DataJobs<T>(PhantomData<T>);
+
+impl<T: Config> JobHandler for DataJobs<T> {
+	type BlockNumber = T::BlockNumber;
+	type Job = DataJob<T::AccountId>;
+	type Output = T::Hash;
+	type Error = DispatchError;
+	type Context = ();
+
+	const QUEUE: &'static [u8] = RETRY_QUEUE;
+
+	fn max_retries() -> u32 {
+		T::MaxRetries::get()
+	}
+
+	// a bumped command is retried whatever its backoff
+	fn is_urgent(job: &Self::Job, now: T::BlockNumber) -> bool {
+		Pallet::<T>::bumped_in(job.request, now)
+	}
+
+	// every request gets a short deadline first (unless the caller asked for another) so that one
+	// slow request doesn't hold up the rest; background requests, and adds and cats that merely
+	// timed out, get a long one when retried
+	fn process(
+		_: &mut (),
+		job: &Self::Job,
+		retry: Option<&DispatchError>,
+	) -> Result<T::Hash, DispatchError> {
+		let timeout = Pallet::<T>::requested_timeout(job.request, &job.cmd);
+		let slow = retry.map_or(false, |error| {
+			job.background
+				|| (*error == Error::<T>::RequestTimeout.into()
+					&& matches!(
+						job.cmd,
+						DataCommand::AddBytes(..)
+							| DataCommand::AddEncrypted(..)
+							| DataCommand::AddUpload(..)
+							| DataCommand::CatBytes(..)
+					))
+		});
+		let millis = if slow {
+			T::BackgroundDeadline::get().max(timeout.unwrap_or(0))
+		} else {
+			timeout.unwrap_or_else(T::FastDeadline::get)
+		};
+		let deadline = Some(timestamp().add(Duration::from_millis(millis)));
+		Pallet::<T>::process_data_command(job.cmd.clone(), deadline).map_err(Into::into)
+	}
+
+	fn on_complete(_: &mut (), outcomes: Vec<Outcome<Self::Job, T::Hash, DispatchError>>) {
+		let mut statuses = Vec::new();
+		for outcome in outcomes {
+			match outcome {
+				Outcome::Done { job, attempts, output } => {
+					if attempts > 1 {
+						log::info!("IPFS: data request completed after {} attempt(s)", attempts);
+					}
+					statuses
+						.extend(job.request.map(|request| (request, RequestStatus::Done(output))));
+				},
+				Outcome::Failed { job, attempts, error, retrying } => {
+					Pallet::<T>::record_incident(error, T::Hashing::hash_of(&job.cmd));
+					// the command is reported as being processed after its first attempt, and as
+					// failed once it is abandoned
+					let status = match (retrying, attempts) {
+						(true, 1) => RequestStatus::Processing,
+						(true, _) => continue,
+						(false, _) => RequestStatus::Failed(error),
+					};
+					statuses.extend(job.request.map(|request| (request, status)));
+				},
+			}
+		}
+		Pallet::<T>::report_request_statuses(statuses);
+	}
+
+	fn on_dead_letter(job: Self::Job, attempts: u32, error: DispatchError) {
+		log::error!("IPFS: data request abandoned after {} attempt(s)", attempts);
+		Pallet::<T>::report_abandoned_request(job, attempts, error);
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	// the number of chunks of an upload, which the weight of finalizing or cancelling it depends on
+	fn chunk_count(upload_id: UploadId) -> u32 {
+		Self::upload(upload_id).map_or(0, |upload| upload.total_chunks)
+	}
+
+	// the number of reporters, which the weight of adding or removing one depends on
+	fn reporter_count() -> u32 {
+		<Reporters<T>>::decode_len().unwrap_or(0) as u32
+	}
+
+	// the number of bootstrap peers, which the weight of `force_disconnect` depends on
+	fn bootstrap_peer_count() -> u32 {
+		<BootstrapPeers<T>>::decode_len().unwrap_or(0) as u32
+	}
+
+	// mark a DHT or cat request as awaiting its result until `ResultTtl` has passed
+	fn expect_result(request: T::Hash) {
+		if Self::is_result_pending(request) {
+			return;
+		}
+		let expires_at =
+			<frame_system::Pallet<T>>::block_number().saturating_add(T::ResultTtl::get());
+		<PendingResults<T>>::insert(request, true);
+		<ResultExpiry<T>>::append(expires_at, request);
+	}
+
+	// ensure that the origin is a signed account allowed to report offchain results
+	fn ensure_reporter(origin: OriginFor<T>) -> Result<T::AccountId, Error<T>> {
+		let who = ensure_signed(origin).map_err(|_| Error::<T>::NotAReporter)?;
+		ensure!(Self::reporters().contains(&who), Error::<T>::NotAReporter);
+		Ok(who)
+	}
+
+	// register content reported by an offchain worker
+	fn record_cid(
+		owner: T::AccountId,
+		cid: Vec<u8>,
+		size: u64,
+		lease: Option<u32>,
+		encrypted: bool,
+	) {
+		// content denied under the `Cid` the node assigned is removed rather than registered; if
+		// the data queue is full, the unpinned block goes with the next garbage collection instead
+		if Self::is_denied(&cid) {
+			let _ = Self::enqueue_data(DataCommand::RemoveBlock(cid.clone()), Priority::High);
+			Self::release_deposit(&owner, Self::deposit_of(&owner, size));
+			<DeniedOwners<T>>::insert(&cid, &owner);
+			Self::deposit_event(Event::<T>::DeniedContentRemoved(cid, owner));
+			return;
+		}
+		let now = <frame_system::Pallet<T>>::block_number();
+		let expires_at = lease.map(|blocks| now.saturating_add(blocks.into()));
+		if let Some(expires_at) = expires_at {
+			<LeaseExpiry<T>>::append(expires_at, &cid);
+		}
+		<ContentRegistry<T>>::insert(
+			&cid,
+			ContentInfo {
+				owner: owner.clone(),
+				added_at: now,
+				pin_status: PinStatus::Unpinned,
+				pin_mode: Self::pin_policy(),
+				size: Some(size),
+				expires_at,
+				schema: None,
+				deposit: Self::deposit_of(&owner, size),
+			},
+		);
+		if encrypted {
+			<EncryptedContent<T>>::insert(&cid, true);
+		}
+		<CidByAccount<T>>::mutate(&owner, |cids| cids.push(cid.clone()));
+		T::OnResult::on_added(&owner, &cid);
+		Self::deposit_event(Event::<T>::AddedCid(owner, cid));
+	}
+
+	// record the attestations of a reporter for the `Cid`s still registered and assigned to it
+	fn record_attestations(who: T::AccountId, cids: Vec<Vec<u8>>) -> DispatchResult {
+		ensure!(cids.len() as u32 <= MAX_ATTESTATIONS, Error::<T>::TooManyAttestations);
+
+		let reporters = Self::reporters();
+		let now = <frame_system::Pallet<T>>::block_number();
+		let mut attested = 0;
+		for cid in cids {
+			if <ContentRegistry<T>>::contains_key(&cid)
+				&& Self::assigned_pinners(&cid, &reporters).contains(&who)
+			{
+				<Attestations<T>>::insert(&cid, &who, now);
+				attested += 1;
+			}
+		}
+		Self::deposit_event(Event::<T>::PinsAttested(who, attested));
+		Ok(())
+	}
+
+	// record the reporters that have submitted no report in the last `REPORTING_WINDOW` blocks;
+	// returns the number of reporters checked
+	fn gauge_reporters(block_number: T::BlockNumber) -> u32 {
+		let reporters = Self::reporters();
+		let since = block_number.saturating_sub(REPORTING_WINDOW.into());
+		let unreported: Vec<_> = reporters
+			.iter()
+			.filter(|who| Self::last_reported(who).map_or(true, |at| at < since))
+			.cloned()
+			.collect();
+		if unreported != Self::unreported_workers() {
+			<UnreportedWorkers<T>>::put(&unreported);
+			Self::deposit_event(Event::<T>::WorkersUnreported(unreported));
+		}
+		reporters.len() as u32
+	}
+
+	fn record_stats(stats: NodeHealth<T::BlockNumber>) {
+		<NodeStats<T>>::put(&stats);
+		Self::deposit_event(Event::<T>::StatsUpdated(stats));
+	}
+
+	/// The weight of a `submit_report` carrying `report`: the weight of the call the report stands
+	/// for, plus checking the reporter and remembering the report.
+	pub fn report_weight(report: &WorkerReport<T::AccountId, T::BlockNumber>) -> Weight {
+		let weight = match report {
+			// recording an IPNS name or resolution costs no more than registering content
+			WorkerReport::Cid { .. }
+			| WorkerReport::IpnsPublished { .. }
+			| WorkerReport::IpnsResolved { .. } => T::WeightInfo::submit_cid(),
+			WorkerReport::Stats(_) => T::WeightInfo::submit_node_stats(),
+			WorkerReport::Attestations(cids) => T::WeightInfo::attest_pins(cids.len() as u32),
+		};
+		weight.saturating_add(T::DbWeight::get().reads_writes(1, 2))
+	}
+
+	// Reports are tagged with the block they were made in: a report older than `UnsignedLongevity`
+	// is stale, and a younger one is rejected if its hash was accepted before. Reports that are
+	// about to go stale are preferred over fresh ones of the same kind, and the results of commands
+	// users paid for are preferred over attestations, which are preferred over stats, since those
+	// are replaced every few blocks anyway.
+	fn validate_report(
+		payload: &ReportPayloadOf<T>,
+		signature: &T::Signature,
+	) -> TransactionValidity {
+		if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+			return InvalidTransaction::BadProof.into();
+		}
+		if !Self::reporters().contains(&payload.public.clone().into_account()) {
+			return InvalidTransaction::BadSigner.into();
+		}
+
+		let now = <frame_system::Pallet<T>>::block_number();
+		if payload.block_number > now {
+			return InvalidTransaction::Future.into();
+		}
+		let age = now - payload.block_number;
+		let longevity = T::UnsignedLongevity::get();
+		let hash = T::Hashing::hash_of(payload);
+		if age > longevity || <AcceptedReports<T>>::contains_key(payload.block_number, hash) {
+			return InvalidTransaction::Stale.into();
+		}
+		if let WorkerReport::Stats(stats) = &payload.report {
+			if Self::node_stats().map_or(false, |current| stats.reported_at <= current.reported_at)
+			{
+				return InvalidTransaction::Stale.into();
+			}
+		}
+
+		let priority = match payload.report {
+			WorkerReport::Cid { .. }
+			| WorkerReport::IpnsPublished { .. }
+			| WorkerReport::IpnsResolved { .. } => T::UnsignedPriority::get(),
+			WorkerReport::Attestations(_) => T::UnsignedPriority::get() / 2,
+			WorkerReport::Stats(_) => T::UnsignedPriority::get() / 4,
+		};
+		ValidTransaction::with_tag_prefix("IpfsReport")
+			.priority(priority.saturating_add(age.saturated_into()))
+			.and_provides(hash)
+			.longevity((longevity - age).saturated_into::<u64>().max(1))
+			.propagate(true)
+			.build()
+	}
+
+	/// Whether the experimental call group with the given flag is enabled.
+	pub fn is_enabled(feature: u32) -> bool {
+		Self::feature_flags() & feature == feature
+	}
+
+	fn ensure_valid_cid(cid: &[u8]) -> Result<(), Error<T>> {
+		ensure!(cid::is_valid(cid), Error::<T>::InvalidCid);
+		Ok(())
+	}
+
+	// register the valid `cids` neither in the registry already nor denied to `owner`, without
+	// deposits, for the offchain workers to pin in batches; returns how many were registered
+	fn import_pins(owner: &T::AccountId, cids: Vec<Vec<u8>>) -> u32 {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let pin_mode = Self::pin_policy();
+		let mut count = <ImportedPinCount<T>>::get();
+		let mut imported = 0u32;
+		for cid in cids {
+			if <ContentRegistry<T>>::contains_key(&cid) || Self::is_denied(&cid) {
+				continue;
+			}
+			<ContentRegistry<T>>::insert(
+				&cid,
+				ContentInfo {
+					owner: owner.clone(),
+					added_at: now,
+					pin_status: PinStatus::Pinned,
+					pin_mode,
+					size: None,
+					expires_at: None,
+					schema: None,
+					deposit: Zero::zero(),
+				},
+			);
+			<ImportedPins<T>>::insert(count, cid);
+			count += 1;
+			imported += 1;
+		}
+		<ImportedPinCount<T>>::put(count);
+		imported
+	}
+
+	// `High` is reserved for pin maintenance, so that user adds and cats can't starve it
+	fn caller_priority(priority: Option<Priority>) -> Result<Priority, Error<T>> {
+		let priority = priority.unwrap_or(Priority::Normal);
+		ensure!(priority != Priority::High, Error::<T>::PriorityReserved);
+		Ok(priority)
+	}
+
+	fn ensure_valid_timeout(timeout: Option<u64>) -> Result<(), Error<T>> {
+		ensure!(
+			timeout.map_or(true, |t| t <= T::MaxRequestTimeout::get()),
+			Error::<T>::TimeoutTooLong
+		);
+		Ok(())
+	}
+
+	fn ensure_not_paused(kind: CommandKind) -> Result<(), Error<T>> {
+		ensure!(!Self::is_paused(kind), Error::<T>::CommandPaused);
+		Ok(())
+	}
+
+	// queue the command `to_cmd` makes of data to be added on behalf of `who`, returning the hash
+	// of the command
+	fn queue_add(
+		who: T::AccountId,
+		data: Vec<u8>,
+		priority: Priority,
+		to_cmd: impl FnOnce(T::AccountId, Vec<u8>) -> DataCommand<T::AccountId>,
+	) -> Result<T::Hash, DispatchError> {
+		let usage = Self::check_quota(&who, data.len() as u64)?;
+		Self::ensure_not_paused(CommandKind::Add)?;
+		Self::ingress_policy().check(&data).map_err(Error::<T>::from)?;
+		// the `Cid` the IPFS node assigns depends on its chunking, so the data is checked against
+		// the raw `Cid` of its bytes here and against the reported `Cid` once it has been added
+		ensure!(!Self::is_denied(&cid::raw_v1(&data)), Error::<T>::ContentDenied);
+
+		let data_hash = T::Hashing::hash(&data);
+		Self::reserve_deposit(&who, data.len() as u64)?;
+		let cmd = to_cmd(who.clone(), data);
+		let request = T::Hashing::hash_of(&cmd);
+		let id = Self::enqueue_data_for(&who, cmd, priority)?;
+		<Usage<T>>::insert(&who, usage);
+		Self::deposit_event(Event::<T>::QueuedDataToAdd(who, data_hash, id));
+		Ok(request)
+	}
+
+	// queue a pin of `cid` on behalf of `who`, registering the content to it if it isn't registered
+	fn queue_pin(who: T::AccountId, cid: Vec<u8>, recursive: bool) -> DispatchResult {
+		Self::ensure_valid_cid(&cid)?;
+		ensure!(!Self::is_denied(&cid), Error::<T>::ContentDenied);
+		if let Some(bucket) = Self::content_bucket(&cid) {
+			ensure!(
+				Self::role_in_bucket(bucket, &who) == Some(BucketRole::Writer),
+				Error::<T>::NotBucketWriter
+			);
+		}
+		let usage = Self::check_quota(&who, 0)?;
+		Self::ensure_not_paused(CommandKind::Pin)?;
+
+		let request = Self::enqueue_data_for(
+			&who,
+			DataCommand::InsertPin(cid.clone(), recursive),
+			Priority::High,
+		)?;
+		<ContentRegistry<T>>::mutate(&cid, |content| match content {
+			Some(content) => {
+				content.pin_status = PinStatus::Pinned;
+				content.pin_mode = recursive.into();
+			},
+			None => {
+				*content = Some(ContentInfo {
+					owner: who.clone(),
+					added_at: <frame_system::Pallet<T>>::block_number(),
+					pin_status: PinStatus::Pinned,
+					pin_mode: recursive.into(),
+					size: None,
+					expires_at: None,
+					schema: None,
+					deposit: Zero::zero(),
+				})
+			},
+		});
+		<Usage<T>>::insert(&who, usage);
+		Self::deposit_event(Event::<T>::QueuedDataToPin(who, cid, request));
+		Ok(())
+	}
+
+	// queue an unpin of `cid`, whose owner `owner` is named in the event
+	fn queue_unpin(owner: T::AccountId, cid: Vec<u8>, recursive: bool) -> DispatchResult {
+		Self::ensure_not_paused(CommandKind::Pin)?;
+
+		let request = Self::enqueue_data_for(
+			&owner,
+			DataCommand::RemovePin(cid.clone(), recursive),
+			Priority::High,
+		)?;
+		<ContentRegistry<T>>::mutate(&cid, |content| {
+			if let Some(content) = content {
+				content.pin_status = PinStatus::Unpinned;
+				content.pin_mode = recursive.into();
+			}
+		});
+		Self::deposit_event(Event::<T>::QueuedDataToUnpin(owner, cid, request));
+		Ok(())
+	}
+
+	// queue a cat of `cid` on behalf of `who`, returning the hash of the command its result is
+	// stored under
+	fn queue_cat(
+		who: T::AccountId,
+		cid: Vec<u8>,
+		timeout: Option<u64>,
+		priority: Priority,
+	) -> Result<T::Hash, DispatchError> {
+		Self::ensure_valid_cid(&cid)?;
+		let usage = Self::check_quota(&who, 0)?;
+		Self::ensure_not_paused(CommandKind::Cat)?;
+
+		let cmd = DataCommand::CatBytes(cid.clone(), timeout);
+		let request = T::Hashing::hash_of(&cmd);
+		let id = Self::enqueue_data_for(&who, cmd, priority)?;
+		Self::expect_result(request);
+		<Usage<T>>::insert(&who, usage);
+		Self::deposit_event(Event::<T>::QueuedDataToCat(who, cid, request, id));
+		Ok(request)
+	}
+
+	// Drop the data commands the offchain workers acknowledged, then sweep the expiries of the
+	// blocks from `MaintenanceCursor` up to `now`: unpin content whose lease ran out, and drop
+	// expired access grants, request results and request statuses, and lift the denials whose
+	// appeals weren't ruled on in time. Each item is only taken on while its weight fits in
+	// `limit`; the rest is left for the next block with weight to spare. Returns the weight used.
+	fn run_maintenance(now: T::BlockNumber, limit: Weight) -> Weight {
+		let db = T::DbWeight::get();
+		// the queue bounds and the cursor
+		let mut used = db.reads_writes(3, 2);
+		if used > limit {
+			return 0;
+		}
+
+		// data commands are only dropped once an offchain worker acknowledged them, so that a
+		// crashed or timed-out worker doesn't lose user requests
+		let head = <DataQueueHead<T>>::get();
+		let acked = <DataQueueAcked<T>>::get();
+		let mut acknowledged = (head..acked).collect::<Vec<_>>();
+		Self::sweep_items(&mut acknowledged, db.writes(2), &mut used, limit, |index| {
+			<DataQueue<T>>::remove(index);
+			<DataQueuedAt<T>>::remove(index);
+		});
+		let new_head = acknowledged.first().copied().unwrap_or(acked);
+		if new_head > head {
+			<DataQueueHead<T>>::put(new_head);
+		}
+
+		let mut block = Self::maintenance_cursor();
+		while block <= now && Self::sweep_block(block, &mut used, limit) {
+			block = block.saturating_add(One::one());
+		}
+		<MaintenanceCursor<T>>::put(block);
+		used
+	}
+
+	// sweep the expiries due in `block` as far as `limit` allows, putting back what is left;
+	// returns whether all of them were swept
+	fn sweep_block(block: T::BlockNumber, used: &mut Weight, limit: Weight) -> bool {
+		let db = T::DbWeight::get();
+		// the five lists
+		let base = db.reads_writes(5, 5);
+		if used.saturating_add(base) > limit {
+			return false;
+		}
+		*used += base;
+
+		let mut leases = <LeaseExpiry<T>>::take(block);
+		Self::sweep_items(&mut leases, db.reads_writes(3, 9), used, limit, |cid| {
+			Self::expire_lease(&cid, block)
+		});
+		if !leases.is_empty() {
+			<LeaseExpiry<T>>::insert(block, leases);
+			return false;
+		}
+
+		// grants renewed since expire later
+		let mut grants = <AccessExpiry<T>>::take(block);
+		Self::sweep_items(&mut grants, db.reads_writes(1, 1), used, limit, |(cid, grantee)| {
+			if Self::access_grant(&cid, &grantee) == Some(block) {
+				<AccessGrants<T>>::remove(&cid, &grantee);
+			}
+		});
+		if !grants.is_empty() {
+			<AccessExpiry<T>>::insert(block, grants);
+			return false;
+		}
+
+		let mut results = <ResultExpiry<T>>::take(block);
+		// queries still pending by now have timed out
+		let per_result = db.reads_writes(2, 6);
+		Self::sweep_items(&mut results, per_result, used, limit, |request| {
+			if <PendingResults<T>>::take(request) {
+				T::Promises::reject(&AsyncOp::DhtQuery(request), Error::<T>::RequestTimeout.into());
+			}
+			<DhtResults<T>>::remove(request);
+			<CatResults<T>>::remove(request);
+		});
+		if !results.is_empty() {
+			<ResultExpiry<T>>::insert(block, results);
+			return false;
+		}
+
+		let mut requests = <RequestExpiry<T>>::take(block);
+		Self::sweep_items(&mut requests, db.writes(3), used, limit, |request| {
+			<Requests<T>>::remove(request);
+			<RequestCallers<T>>::remove(request);
+			<BumpedAt<T>>::remove(request);
+		});
+		if !requests.is_empty() {
+			<RequestExpiry<T>>::insert(block, requests);
+			return false;
+		}
+
+		// appeals ruled on since are gone
+		let mut appeals = <AppealExpiry<T>>::take(block);
+		Self::sweep_items(&mut appeals, db.reads_writes(2, 4), used, limit, |cid| {
+			if Self::appeal(&cid).map_or(false, |appeal| appeal.deadline == block) {
+				Self::lapse_appeal(cid);
+			}
+		});
+		if !appeals.is_empty() {
+			<AppealExpiry<T>>::insert(block, appeals);
+			return false;
+		}
+		true
+	}
+
+	// publish the oldest `incidents::ARCHIVE_BATCH` pruned incidents to IPFS once there are as
+	// many; they are kept for a later report if the store refuses them, up to `MAX_RECENT`
+	fn archive_incidents() {
+		let mut pruned = Self::pruned_incidents();
+		if pruned.len() < incidents::ARCHIVE_BATCH {
+			return;
+		}
+		let batch: Vec<_> = pruned.drain(..incidents::ARCHIVE_BATCH).collect();
+		let archived = <Self as mpesa_primitives::IpfsStore<T::AccountId>>::add_bytes(
+			&T::ArchiveAccount::get(),
+			batch.encode(),
+		);
+		match archived {
+			Ok(cid) => {
+				let index = Self::incident_archive_count();
+				<IncidentArchives<T>>::insert(index, &cid);
+				<IncidentArchiveCount<T>>::put(index + 1);
+				<PrunedIncidents<T>>::put(pruned);
+				Self::deposit_event(Event::<T>::IncidentsArchived(index, cid));
+			},
+			Err(e) => {
+				log::warn!("IPFS: can't archive {} pruned incidents: {:?}", batch.len(), e);
+				let excess = (batch.len() + pruned.len()).saturating_sub(incidents::MAX_RECENT);
+				<PrunedIncidents<T>>::put(
+					batch.into_iter().chain(pruned).skip(excess).collect::<Vec<_>>(),
+				);
+			},
+		}
+	}
+
+	// lift the denial of `cid` whose appeal wasn't ruled on in time
+	fn lapse_appeal(cid: Vec<u8>) {
+		if let Some(appeal) = <Appeals<T>>::take(&cid) {
+			Self::release_deposit(&appeal.appellant, appeal.deposit);
+		}
+		Self::deposit_event(Event::<T>::AppealLapsed(cid.clone()));
+		Self::lift_denial(cid);
+	}
+
+	// take `cid` off the denylist
+	fn lift_denial(cid: Vec<u8>) {
+		<Denylist<T>>::remove(&cid);
+		<DeniedOwners<T>>::remove(&cid);
+		Self::deposit_event(Event::<T>::CidAllowed(cid));
+	}
+
+	// apply `sweep` to as many of the first `items` as fit in `limit` at `cost` each and remove
+	// them, adding their weight to `used`
+	fn sweep_items<I>(
+		items: &mut Vec<I>,
+		cost: Weight,
+		used: &mut Weight,
+		limit: Weight,
+		sweep: impl FnMut(I),
+	) {
+		let fit = limit.saturating_sub(*used) / cost.max(1);
+		let swept = items.len().min(fit.saturated_into());
+		*used = used.saturating_add(cost.saturating_mul(swept as Weight));
+		items.drain(..swept).for_each(sweep);
+	}
+
+	// unpin and remove leased content that expires in `block_number`, releasing its deposit
+	fn expire_lease(cid: &[u8], block_number: T::BlockNumber) {
+		let content = match Self::content(cid) {
+			Some(content) if content.expires_at == Some(block_number) => content,
+			// removed or extended in the meantime
+			_ => return,
+		};
+
+		let unpin = DataCommand::RemovePin(cid.to_vec(), content.pin_mode.is_recursive());
+		let queued = Self::enqueue_data(unpin, Priority::High).and_then(|_| {
+			Self::enqueue_data(DataCommand::RemoveBlock(cid.to_vec()), Priority::High)
+		});
+		if queued.is_err() {
+			// the data queue is full; try again in the next block
+			let retry_at = block_number.saturating_add(1u32.into());
+			<LeaseExpiry<T>>::append(retry_at, cid);
+			<ContentRegistry<T>>::mutate(cid, |content| {
+				if let Some(content) = content {
+					content.expires_at = Some(retry_at);
+				}
+			});
+			return;
+		}
+
+		<ContentRegistry<T>>::remove(cid);
+		<EncryptedContent<T>>::remove(cid);
+		Self::forget_content(cid);
+		Self::release_deposit(&content.owner, content.deposit);
+		Self::deposit_event(Event::<T>::LeaseExpired(cid.to_vec()));
+	}
+
+	// queue the command of a scheduled task and schedule its next run; cancelled tasks are skipped
+	// and the tasks of content that left the registry end
+	fn run_schedule(id: ScheduleId, block_number: T::BlockNumber) {
+		let schedule = match Self::schedule(id) {
+			Some(schedule) => schedule,
+			None => return,
+		};
+		if !<ContentRegistry<T>>::contains_key(&schedule.cid) {
+			<Schedules<T>>::remove(id);
+			<ScheduleCount<T>>::mutate(&schedule.owner, |count| *count = count.saturating_sub(1));
+			Self::deposit_event(Event::<T>::ScheduleCancelled(id));
+			return;
+		}
+
+		let queued = match schedule.command {
+			ScheduledCommand::Pin(recursive) => Self::enqueue_data(
+				DataCommand::InsertPin(schedule.cid, recursive),
+				Priority::Normal,
+			),
+			ScheduledCommand::Cat => {
+				let cmd = DataCommand::CatBytes(schedule.cid, None);
+				let request = T::Hashing::hash_of(&cmd);
+				Self::enqueue_data(cmd, Priority::Normal).map(|id| {
+					Self::expect_result(request);
+					id
+				})
+			},
+		};
+		// the data queue is full; try again in the next block
+		let next_run = if queued.is_err() { One::one() } else { schedule.period };
+		<ScheduledRuns<T>>::append(block_number.saturating_add(next_run), id);
+	}
+
+	/// The reporters assigned to keep `cid` pinned: the `ReplicationFactor` reporters ranked
+	/// lowest by the hash of the `Cid` and their account, so every node arrives at the same
+	/// assignment and content spreads evenly over the reporters.
+	pub fn assigned_pinners(cid: &[u8], reporters: &[T::AccountId]) -> Vec<T::AccountId> {
+		let mut ranked = reporters
+			.iter()
+			.map(|who| (T::Hashing::hash_of(&(cid, who)), who))
+			.collect::<Vec<_>>();
+		ranked.sort_by(|a, b| a.0.cmp(&b.0));
+		ranked
+			.into_iter()
+			.take(T::ReplicationFactor::get() as usize)
+			.map(|(_, who)| who.clone())
+			.collect()
+	}
+
+	/// The reporters assigned to process the data command with sequence number `index`: the
+	/// `WorkersPerCommand` reporters ranked lowest by the hash of the index and their account, so
+	/// every node arrives at the same assignment and the queue spreads evenly over the reporters.
+	pub fn assigned_workers(index: u64, reporters: &[T::AccountId]) -> Vec<T::AccountId> {
+		let mut ranked = reporters
+			.iter()
+			.map(|who| (T::Hashing::hash_of(&(index, who)), who))
+			.collect::<Vec<_>>();
+		ranked.sort_by(|a, b| a.0.cmp(&b.0));
+		ranked
+			.into_iter()
+			.take(T::WorkersPerCommand::get() as usize)
+			.map(|(_, who)| who.clone())
+			.collect()
+	}
+
+	// check the next few registry entries for pinned content fewer than `ReplicationFactor` of its
+	// assigned reporters attested within the last two attestation intervals; returns the number
+	// of entries checked and of entries whose replication state changed
+	fn check_replication(block_number: T::BlockNumber) -> (u32, u32) {
+		let reporters = Self::reporters();
+		if reporters.is_empty() {
+			return (0, 0);
+		}
+		let required = T::ReplicationFactor::get().min(reporters.len() as u32) as usize;
+		let window = T::AttestationInterval::get().saturating_mul(2u32.into());
+
+		let entries = match <ReplicationCursor<T>>::take() {
+			Some(key) => <ContentRegistry<T>>::iter_from(key),
+			None => <ContentRegistry<T>>::iter(),
+		};
+		let (mut checked, mut changed) = (0, 0);
+		let mut cursor = None;
+		for (cid, content) in entries.take(REPLICATION_SWEEP as usize) {
+			checked += 1;
+			cursor = Some(<ContentRegistry<T>>::hashed_key_for(&cid));
+			// new content gets two intervals to be picked up by its pinners
+			if content.pin_status != PinStatus::Pinned
+				|| block_number < content.added_at.saturating_add(window)
+			{
+				continue;
+			}
+
+			let recent = |at: T::BlockNumber| at.saturating_add(window) >= block_number;
+			let attested = Self::assigned_pinners(&cid, &reporters)
+				.iter()
+				.filter(|who| Self::attested_at(&cid, who).map_or(false, recent))
+				.count();
+			let under = attested < required;
+			if under == Self::is_under_replicated(&cid) {
+				continue;
+			}
+			changed += 1;
+			if under {
+				<UnderReplicatedContent<T>>::insert(&cid, true);
+				Self::deposit_event(Event::<T>::UnderReplicated(cid));
+			} else {
+				<UnderReplicatedContent<T>>::remove(&cid);
+				Self::deposit_event(Event::<T>::ReplicationRestored(cid));
+			}
+		}
+		// start over from the beginning once the whole registry was checked
+		if checked == REPLICATION_SWEEP {
+			if let Some(cursor) = cursor {
+				<ReplicationCursor<T>>::put(cursor);
+			}
+		}
+		(checked, changed)
+	}
+
+	/// The response to a storage challenge over `data` with the given seed.
+	pub fn storage_proof(seed: &T::Hash, data: &[u8]) -> T::Hash {
+		T::Hashing::hash_of(&(seed, data))
+	}
+
+	// challenge the bonded pinners of a few random pieces of registered content; returns the number
+	// of challenges issued
+	fn issue_challenges(block_number: T::BlockNumber) -> u32 {
+		let reporters = Self::reporters();
+		let (seed, _) = T::Randomness::random(b"ipfs::challenge");
+		let prefix = <ContentRegistry<T>>::final_prefix();
+		let mut issued = 0;
+		for i in 0..T::ChallengesPerEra::get() {
+			// registry keys start with a hash of the `Cid`, so the entry following a random key is
+			// a random entry
+			let random = T::Hashing::hash_of(&(seed, i));
+			let mut key = prefix.to_vec();
+			key.extend_from_slice(random.as_ref());
+			let entry = <ContentRegistry<T>>::iter_from(key)
+				.next()
+				.or_else(|| <ContentRegistry<T>>::iter().next());
+			let (cid, content) = match entry {
+				Some(entry) => entry,
+				None => break,
+			};
+			// only content added through the pallet has a known size
+			let size = match content.size {
+				Some(size) if size > 0 && content.pin_status == PinStatus::Pinned => size,
+				_ => continue,
+			};
+			let pinners = Self::assigned_pinners(&cid, &reporters)
+				.into_iter()
+				.filter(|who| <StorageBonds<T>>::contains_key(who))
+				.collect::<Vec<_>>();
+			if pinners.is_empty() {
+				continue;
+			}
+
+			let offset = u64::decode(&mut random.as_ref()).unwrap_or_default() % size;
+			let len = (size - offset).min(CHALLENGE_LEN as u64) as u32;
+			let deadline = block_number.saturating_add(T::ChallengePeriod::get());
+			let id = <NextChallengeId<T>>::mutate(|next| {
+				let id = *next;
+				*next += 1;
+				id
+			});
+			for who in pinners.iter() {
+				<OpenChallenges<T>>::mutate(who, |open| *open += 1);
+			}
+			let challenge =
+				StorageChallenge { cid: cid.clone(), offset, len, seed: random, deadline, pinners };
+			<Challenges<T>>::insert(id, challenge);
+			<ChallengeDeadlines<T>>::append(deadline, id);
+			Self::deposit_event(Event::<T>::ChallengeIssued(id, cid));
+			issued += 1;
+		}
+		issued
+	}
+
+	// slash the pinners of a challenge that didn't respond, or whose response differs from the one
+	// given by most of them; without a single most common response only the silent ones are
+	// slashed, and nobody is if the content was removed in the meantime
+	fn resolve_challenge(id: ChallengeId) {
+		let challenge = match <Challenges<T>>::take(id) {
+			Some(challenge) => challenge,
+			None => return,
+		};
+		let responses = <ChallengeResponses<T>>::drain_prefix(id).collect::<Vec<_>>();
+		let removed = !<ContentRegistry<T>>::contains_key(&challenge.cid);
+
+		let mut counts: Vec<(T::Hash, u32)> = Vec::new();
+		for (_, response) in responses.iter() {
+			match counts.iter_mut().find(|(hash, _)| hash == response) {
+				Some((_, count)) => *count += 1,
+				None => counts.push((*response, 1)),
+			}
+		}
+		counts.sort_by(|a, b| b.1.cmp(&a.1));
+		let accepted = match counts.as_slice() {
+			[(hash, _)] => Some(*hash),
+			[(hash, first), (_, second), ..] if first > second => Some(*hash),
+			_ => None,
+		};
+
+		for who in challenge.pinners {
+			<OpenChallenges<T>>::mutate_exists(&who, |open| {
+				*open = open.map(|open| open.saturating_sub(1)).filter(|open| *open > 0)
+			});
+			if removed {
+				continue;
+			}
+			let response = responses
+				.iter()
+				.find(|(pinner, _)| *pinner == who)
+				.map(|(_, response)| *response);
+			let passed = match (response, accepted) {
+				(None, _) => false,
+				(Some(response), Some(accepted)) => response == accepted,
+				(Some(_), None) => true,
+			};
+			if passed {
+				Self::deposit_event(Event::<T>::ChallengePassed(id, who));
+				continue;
+			}
+
+			let amount =
+				T::ChallengeSlash::get().min(Self::storage_bond(&who).unwrap_or_else(Zero::zero));
+			let (_, unslashed) = T::Currency::slash_reserved(&who, amount);
+			let slashed = amount.saturating_sub(unslashed);
+			<StorageBonds<T>>::mutate_exists(&who, |bond| {
+				*bond = bond.map(|bond| bond.saturating_sub(slashed)).filter(|bond| !bond.is_zero())
+			});
+			Self::deposit_event(Event::<T>::ChallengeFailed(id, who, slashed));
+		}
+	}
+
+	/// The commands waiting in the pallet's queues, data commands in queue order.
+	pub fn pending_commands() -> PendingCommands<T::AccountId> {
+		let data = (<DataQueueHead<T>>::get()..<DataQueueTail<T>>::get())
+			.filter_map(|index| {
+				<DataQueue<T>>::get(index).map(|(priority, cmd)| (index, priority, cmd))
+			})
+			.collect();
+		PendingCommands {
+			connections: <ConnectionQueue<T>>::get(),
+			dht: <DhtQueue<T>>::get(),
+			pubsub: <PubsubQueue<T>>::get(),
+			data,
+		}
+	}
+
+	/// The peers the IPFS nodes are asked to stay connected to: the bootstrap peers followed by
+	/// the peers accounts connected to.
+	pub fn peer_list() -> Vec<OpaqueMultiaddr> {
+		let mut peers = Self::bootstrap_peers();
+		for (addr, _) in <DesiredPeers<T>>::iter() {
+			let addr = OpaqueMultiaddr(addr);
+			if !peers.contains(&addr) {
+				peers.push(addr);
+			}
+		}
+		peers
+	}
+
+	/// A page of the registered content matching `filter`, for indexers.
+	///
+	/// Filtered by `Cid`, the page holds that content at most. Filtered by account, the `Cid`s
+	/// the account added are paged in the order it added them, and the cursor is the SCALE
+	/// encoding of the position of the next one; otherwise the whole registry is paged in
+	/// storage order, and the cursor is the last `Cid` examined.
+	pub fn registry_page(
+		filter: &IndexFilter<T::AccountId, T::BlockNumber>,
+		request: &PageRequest,
+	) -> Page<(Vec<u8>, ContentInfo<T::AccountId, T::BlockNumber, BalanceOf<T>>)> {
+		let matching = |(cid, info): (Vec<u8>, ContentInfo<_, _, _>)| {
+			filter.matches(&info.owner, Some(&cid), &info.added_at).then(|| (cid, info))
+		};
+		if let Some(cid) = &filter.cid {
+			let content = match request.cursor {
+				Some(_) => None,
+				None => Self::content(cid).map(|info| (cid.clone(), info)),
+			};
+			return collect_page(content, request, matching, |(cid, _)| cid.clone());
+		}
+		if let Some(owner) = &filter.account {
+			let start = request.cursor.as_ref().and_then(|c| u32::decode(&mut &c[..]).ok());
+			let cids =
+				Self::cids_of(owner).into_iter().enumerate().skip(start.unwrap_or(0) as usize);
+			return collect_page(
+				cids,
+				request,
+				|(_, cid)| Self::content(&cid).and_then(|info| matching((cid, info))),
+				|(position, _)| (*position as u32 + 1).encode(),
+			);
+		}
+		let content = match &request.cursor {
+			Some(cid) => <ContentRegistry<T>>::iter_from(<ContentRegistry<T>>::hashed_key_for(cid)),
+			None => <ContentRegistry<T>>::iter(),
+		};
+		collect_page(content, request, matching, |(cid, _)| cid.clone())
+	}
+
+	// drop the replication, access and bucket records of content leaving the registry
+	fn forget_content(cid: &[u8]) {
+		let _ = <Attestations<T>>::clear_prefix(cid, u32::MAX, None);
+		<UnderReplicatedContent<T>>::remove(cid);
+		let _ = <AccessGrants<T>>::clear_prefix(cid, u32::MAX, None);
+		<ContentBuckets<T>>::remove(cid);
+	}
+
+	/// Whether `who` may retrieve `cid` from a gateway: the owner always may, others while they
+	/// hold an unexpired access grant or a role in the bucket the content is in.
+	pub fn has_access(cid: &[u8], who: &T::AccountId) -> bool {
+		let now = <frame_system::Pallet<T>>::block_number();
+		match Self::content(cid) {
+			Some(content) if content.owner == *who => true,
+			Some(_) => {
+				Self::access_grant(cid, who).map_or(false, |expires_at| expires_at > now)
+					|| Self::content_bucket(cid)
+						.and_then(|bucket| Self::role_in_bucket(bucket, who))
+						.is_some()
+			},
+			None => false,
+		}
+	}
+
+	/// The role `who` holds in `bucket`; its owner is a `Writer`.
+	pub fn role_in_bucket(bucket: BucketId, who: &T::AccountId) -> Option<BucketRole> {
+		match Self::bucket(bucket) {
+			Some(info) if info.owner == *who => Some(BucketRole::Writer),
+			Some(_) => Self::bucket_role(bucket, who),
+			None => None,
+		}
+	}
+
+	/// The role `who` holds in the namespace of service `id`; the service owner is an `Admin`.
+	pub fn role_in_namespace(id: ServiceId, who: &T::AccountId) -> Option<NamespaceRole> {
+		match Self::service(id) {
+			Some(info) if info.owner == *who => Some(NamespaceRole::Admin),
+			Some(_) => Self::namespace_role(id, who),
+			None => None,
+		}
+	}
+
+	// ensure that the origin is `NamespaceAdminOrigin` for service `id`, or signed by an admin
+	// of its namespace or by `member`
+	fn ensure_namespace_admin(
+		origin: OriginFor<T>,
+		id: ServiceId,
+		member: Option<&T::AccountId>,
+	) -> DispatchResult {
+		ensure!(<Services<T>>::contains_key(id), Error::<T>::UnknownService);
+		let origin = match T::NamespaceAdminOrigin::try_origin(origin, &id) {
+			Ok(_) => return Ok(()),
+			Err(origin) => origin,
+		};
+		let who = ensure_signed(origin)?;
+		ensure!(
+			member == Some(&who) || Self::role_in_namespace(id, &who) == Some(NamespaceRole::Admin),
+			Error::<T>::NotNamespaceAdmin
+		);
+		Ok(())
+	}
+
+	// ensure that `tags` are few and short enough to be stored with a name
+	fn ensure_valid_tags(tags: &[Vec<u8>]) -> DispatchResult {
+		ensure!(
+			tags.len() <= MAX_TAGS && tags.iter().all(|tag| tag.len() <= MAX_TAG_LEN),
+			Error::<T>::InvalidTags
+		);
+		Ok(())
+	}
+
+	// ensure that the origin is signed by the owner of `bucket`
+	fn ensure_bucket_owner(
+		origin: OriginFor<T>,
+		bucket: BucketId,
+	) -> Result<T::AccountId, DispatchError> {
+		let who = ensure_signed(origin)?;
+		let info = Self::bucket(bucket).ok_or(Error::<T>::UnknownBucket)?;
+		ensure!(info.owner == who, Error::<T>::NotBucketOwner);
+		Ok(who)
+	}
+
+	// the usage of `who` after one more command of `bytes` bytes, or `QuotaExceeded`
+	fn check_quota(
+		who: &T::AccountId,
+		bytes: u64,
+	) -> Result<AccountUsage<T::BlockNumber>, Error<T>> {
+		let era = Self::current_era();
+		let now = <frame_system::Pallet<T>>::block_number();
+		let mut usage = Self::usage(who);
+		if usage.era != era {
+			usage.era = era;
+			usage.bytes = 0;
+		}
+		if usage.block != now {
+			usage.block = now;
+			usage.commands = 0;
+		}
+
+		usage.bytes = usage.bytes.saturating_add(bytes);
+		usage.commands = usage.commands.saturating_add(1);
+		if T::ProtocolAccounts::contains(who) {
+			return Ok(usage);
+		}
+		ensure!(usage.bytes <= T::MaxBytesPerAccountPerEra::get(), Error::<T>::QuotaExceeded);
+		ensure!(usage.commands <= T::MaxCommandsPerBlock::get(), Error::<T>::QuotaExceeded);
+		Ok(usage)
+	}
+
+	// queue a connection change, returning the number of commands that were queued before, which
+	// the weight of the calls queueing it depends on
+	fn enqueue_connection(cmd: ConnectionCommand) -> Result<u32, Error<T>> {
+		<ConnectionQueue<T>>::try_mutate(|queue| {
+			let len = queue.len() as u32;
+			if !queue.contains(&cmd) {
+				ensure!(len < T::MaxQueueLen::get(), Error::<T>::QueueFull);
+				queue.push(cmd);
+			}
+			Ok(len)
+		})
+	}
+
+	// queue a data command, returning the identifier its status is tracked under
+	fn enqueue_data(
+		cmd: DataCommand<T::AccountId>,
+		priority: Priority,
+	) -> Result<RequestId, Error<T>> {
+		let tail = <DataQueueTail<T>>::get();
+		ensure!(
+			tail - <DataQueueHead<T>>::get() < T::MaxQueueLen::get() as u64,
+			Error::<T>::QueueFull
+		);
+		<DataQueue<T>>::insert(tail, (priority, cmd));
+		<DataQueuedAt<T>>::insert(tail, <frame_system::Pallet<T>>::block_number());
+		<DataQueueTail<T>>::put(tail + 1);
+		<Requests<T>>::insert(tail, RequestStatus::Queued);
+		T::Promises::create(AsyncOp::IpfsRequest(tail));
+		Ok(tail)
+	}
+
+	// queue a data command on behalf of `who`, who the command is dead-lettered for if it fails
+	fn enqueue_data_for(
+		who: &T::AccountId,
+		cmd: DataCommand<T::AccountId>,
+		priority: Priority,
+	) -> Result<RequestId, Error<T>> {
+		let request = Self::enqueue_data(cmd, priority)?;
+		<RequestCallers<T>>::insert(request, who);
+		Ok(request)
+	}
+
+	// push a data command's final status to the webhook of the account it was queued for, if any
+	fn notify_caller(request: RequestId, outcome: Result<T::Hash, DispatchError>) {
+		if let Some(caller) = Self::request_caller(request) {
+			T::Webhooks::notify(&caller, AsyncOp::IpfsRequest(request), outcome);
+		}
+	}
+
+	// keep an abandoned command for operators, unless another worker completed it or already
+	// dead-lettered it
+	fn dead_letter(
+		request: RequestId,
+		cmd: DataCommand<T::AccountId>,
+		payload_hash: T::Hash,
+		reason: DispatchError,
+	) {
+		if <DeadLetters<T>>::contains_key(request)
+			|| matches!(Self::request_status(request), Some(RequestStatus::Done(_)))
+		{
+			return;
+		}
+
+		<DeadLetters<T>>::insert(
+			request,
+			DeadLetter {
+				caller: Self::request_caller(request),
+				cmd,
+				payload_hash,
+				reason,
+				block: <frame_system::Pallet<T>>::block_number(),
+			},
+		);
+		<DeadLetterCount<T>>::mutate(|count| *count += 1);
+		Self::deposit_event(Event::<T>::OperationDeadLettered(request, payload_hash, reason));
+	}
+
+	// like `enqueue_connection`, for DHT requests
+	fn enqueue_dht(cmd: DhtCommand) -> Result<u32, Error<T>> {
+		<DhtQueue<T>>::try_mutate(|queue| {
+			let len = queue.len() as u32;
+			ensure!(len < T::MaxQueueLen::get(), Error::<T>::QueueFull);
+			queue.push(cmd);
+			Ok(len)
+		})
+	}
+
+	// like `enqueue_connection`, for pubsub commands
+	fn enqueue_pubsub(cmd: PubsubCommand) -> Result<u32, Error<T>> {
+		<PubsubQueue<T>>::try_mutate(|queue| {
+			let len = queue.len() as u32;
+			ensure!(len < T::MaxQueueLen::get(), Error::<T>::QueueFull);
+			queue.push(cmd);
+			Ok(len)
+		})
+	}
+
+	// the storage deposit for `len` bytes of data
+	fn deposit_for(len: u64) -> BalanceOf<T> {
+		T::ByteDeposit::get().saturating_mul(len.saturated_into())
+	}
+
+	// the deposit `who` reserves for `len` bytes of content
+	fn deposit_of(who: &T::AccountId, len: u64) -> BalanceOf<T> {
+		if T::ProtocolAccounts::contains(who) {
+			Zero::zero()
+		} else {
+			Self::deposit_for(len)
+		}
+	}
+
+	fn reserve_deposit(who: &T::AccountId, len: u64) -> Result<(), DispatchError> {
+		let deposit = Self::deposit_of(who, len);
+		if !deposit.is_zero() {
+			T::Currency::reserve(who, deposit)?;
+			Self::deposit_event(Event::<T>::DepositReserved(who.clone(), deposit));
+		}
+		Ok(())
+	}
+
+	fn release_deposit(who: &T::AccountId, deposit: BalanceOf<T>) {
+		if !deposit.is_zero() {
+			T::Currency::unreserve(who, deposit);
+			Self::deposit_event(Event::<T>::DepositReleased(who.clone(), deposit));
+		}
+	}
+
+	// ensure that the origin is root, the owner of registered content or a writer of the bucket
+	// the content is in; returns the owner
+	fn ensure_content_owner_or_root(
+		origin: OriginFor<T>,
+		cid: &[u8],
+	) -> Result<T::AccountId, DispatchError> {
+		let content = Self::content(cid).ok_or(Error::<T>::UnknownContent)?;
+		let is_writer = |who: &T::AccountId| {
+			Self::content_bucket(cid).and_then(|bucket| Self::role_in_bucket(bucket, who))
+				== Some(BucketRole::Writer)
+		};
+		match origin.into() {
+			Ok(frame_system::RawOrigin::Root) => Ok(content.owner),
+			Ok(frame_system::RawOrigin::Signed(who)) if who == content.owner || is_writer(&who) => {
+				Ok(content.owner)
+			},
+			Ok(frame_system::RawOrigin::Signed(_)) => Err(Error::<T>::NotContentOwner.into()),
+			_ => Err(DispatchError::BadOrigin),
+		}
+	}
+
+	// submit a report to the chain as an unsigned transaction signed with the worker's key, or
+	// with `public` if given
+	fn send_report(
+		public: Option<T::Public>,
+		report: WorkerReport<T::AccountId, T::BlockNumber>,
+	) -> Result<(), Error<T>> {
+		let signer = match public {
+			Some(public) => Signer::<T, T::AuthorityId>::any_account().with_filter(vec![public]),
+			None => Signer::<T, T::AuthorityId>::any_account(),
+		};
+		let block_number = <frame_system::Pallet<T>>::block_number();
+		let result = signer.send_unsigned_transaction(
+			|account| ReportPayload {
+				public: account.public.clone(),
+				block_number,
+				report: report.clone(),
+			},
+			|payload, signature| Call::submit_report { payload, signature },
+		);
+		match result {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!("IPFS: failed to submit a report with account {:?}", account.id)
+			},
+			None => return Err(Error::<T>::NoSigningKey),
+		}
+		Ok(())
+	}
+
+	// submit the Cid of added data back to the chain
+	fn report_cid(
+		owner: T::AccountId,
+		cid: Vec<u8>,
+		size: u64,
+		lease: Option<u32>,
+		encrypted: bool,
+	) {
+		if let Err(e) =
+			Self::send_report(None, WorkerReport::Cid { owner, cid, size, lease, encrypted })
+		{
+			log::error!("IPFS: can't report the Cid: {:?}", e);
+		}
+	}
+
+	// acknowledge processed data commands as a signed transaction
+	fn report_data_processed(up_to: u64) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer.send_signed_transaction(|_| Call::ack_data_commands { up_to }) {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to acknowledge data commands with account {:?}",
+					account.id
+				)
+			},
+			None => {
+				log::error!("IPFS: can't acknowledge data commands: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// submit the statuses of the queued commands this node worked on as signed transactions
+	fn report_request_statuses(statuses: Vec<(RequestId, RequestStatus<T::Hash>)>) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		for statuses in statuses.chunks(MAX_STATUS_UPDATES as usize) {
+			match signer.send_signed_transaction(|_| Call::update_request_statuses {
+				statuses: statuses.to_vec(),
+			}) {
+				Some((_, Ok(()))) => {},
+				Some((account, Err(()))) => {
+					log::error!(
+						"IPFS: failed to report request statuses with account {:?}",
+						account.id
+					)
+				},
+				None => log::error!(
+					"IPFS: can't report request statuses: {:?}",
+					Error::<T>::NoSigningKey
+				),
+			}
+		}
+	}
+
+	// submit a received pubsub message to the chain as a signed transaction
+	fn report_pubsub_message(topic: Vec<u8>, data: Vec<u8>) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer.send_signed_transaction(|_| Call::submit_pubsub_message {
+			topic: topic.clone(),
+			data: data.clone(),
+		}) {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!("IPFS: failed to submit a pubsub message with account {:?}", account.id)
+			},
+			None => {
+				log::error!("IPFS: can't submit a pubsub message: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// report recovery progress to the chain as a signed transaction
+	fn report_recovery_progress(progress: RecoveryProgress) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer
+			.send_signed_transaction(|_| Call::report_recovery { progress: progress.clone() })
+		{
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to report recovery progress with account {:?}",
+					account.id
+				)
+			},
+			None => {
+				log::error!("IPFS: can't report recovery progress: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// buffer a failure of this node's IPFS requests until the next incident report
+	fn record_incident(e: impl Into<DispatchError>, correlation_id: T::Hash) {
+		incidents::record(OffchainIncident {
+			subsystem: Subsystem::Ipfs,
+			code: e.into(),
+			correlation_id,
+			block: <frame_system::Pallet<T>>::block_number(),
+		});
+	}
+
+	// submit the buffered incidents to the chain as a signed transaction
+	fn submit_incidents() {
+		let pending =
+			incidents::take::<T::Hash, T::BlockNumber>(incidents::MAX_PER_REPORT as usize);
+		if pending.is_empty() {
+			return;
+		}
+
+		log::info!("IPFS: reporting {} offchain incident(s)", pending.len());
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer
+			.send_signed_transaction(|_| Call::report_incidents { incidents: pending.clone() })
+		{
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to report offchain incidents with account {:?}",
+					account.id
+				)
+			},
+			None => {
+				log::error!("IPFS: can't report offchain incidents: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// respond to a storage challenge as a signed transaction of the given reporter key
+	fn report_challenge_response(public: T::Public, challenge: ChallengeId, response: T::Hash) {
+		let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![public]);
+		match signer.send_signed_transaction(|_| Call::respond_challenge { challenge, response }) {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to respond to a storage challenge with account {:?}",
+					account.id
+				)
+			},
+			None => log::error!(
+				"IPFS: can't respond to a storage challenge: {:?}",
+				Error::<T>::NoSigningKey
+			),
+		}
+	}
+
+	// report an exported registry snapshot as a signed transaction of the given reporter key
+	fn report_registry_export(
+		public: T::Public,
+		requested_at: T::BlockNumber,
+		export: RegistryExport<T::BlockNumber>,
+	) {
+		let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![public]);
+		let result = signer.send_signed_transaction(|_| Call::submit_registry_export {
+			requested_at,
+			export: export.clone(),
+		});
+		match result {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to report a registry export with account {:?}",
+					account.id
+				)
+			},
+			None => {
+				log::error!("IPFS: can't report a registry export: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// submit a page of an imported registry snapshot as a signed transaction of the given reporter
+	// key
+	fn report_registry_page(public: T::Public, start: u32, entries: Vec<RegistryEntryOf<T>>) {
+		let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![public]);
+		match signer
+			.send_signed_transaction(|_| Call::restore_registry { start, entries: entries.clone() })
+		{
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to restore registry entries with account {:?}",
+					account.id
+				)
+			},
+			None => {
+				log::error!("IPFS: can't restore registry entries: {:?}", Error::<T>::NoSigningKey)
+			},
+		}
+	}
+
+	// attest pins to the chain with the given reporter key
+	fn report_attestations(public: T::Public, cids: Vec<Vec<u8>>) {
+		if let Err(e) = Self::send_report(Some(public), WorkerReport::Attestations(cids)) {
+			log::error!("IPFS: can't attest pins: {:?}", e);
+		}
+	}
+
+	/// The weight of an `ipfs_batch` of `commands`: the sum of the weights of the calls the
+	/// commands stand for.
+	pub fn batch_weight(commands: &[IpfsCommand]) -> Weight {
+		commands.iter().fold(0, |weight, command| {
+			weight.saturating_add(match command {
+				IpfsCommand::Connect(_) => T::WeightInfo::ipfs_connect(T::MaxQueueLen::get()),
+				IpfsCommand::AddBytes(data) => T::WeightInfo::ipfs_add_bytes(data.len() as u32),
+				IpfsCommand::CatBytes(_) => T::WeightInfo::ipfs_cat_bytes(),
+				IpfsCommand::InsertPin(..) => T::WeightInfo::ipfs_insert_pin(),
+				IpfsCommand::RemovePin(..) => T::WeightInfo::ipfs_remove_pin(),
+				IpfsCommand::RemoveBlock(_) => T::WeightInfo::ipfs_remove_block(),
+			})
+		})
+	}
+
+	/// A snapshot of the content registry, the services and the replication assignments of the
+	/// pinned content as they are now, taken in block `exported_at`.
+	pub fn registry_snapshot(
+		exported_at: T::BlockNumber,
+	) -> RegistrySnapshot<T::AccountId, T::BlockNumber, BalanceOf<T>> {
+		let mut content = <ContentRegistry<T>>::iter().collect::<Vec<_>>();
+		content.sort_by(|a, b| a.0.cmp(&b.0));
+		let mut services = <Services<T>>::iter().collect::<Vec<_>>();
+		services.sort_by_key(|(id, _)| *id);
+		let reporters = Self::reporters();
+		let assignments = content
+			.iter()
+			.filter(|(_, content)| content.pin_status == PinStatus::Pinned)
+			.map(|(cid, _)| {
+				RegistryEntry::Assignment(cid.clone(), Self::assigned_pinners(cid, &reporters))
+			})
+			.collect::<Vec<_>>();
+
+		let mut entries = content
+			.into_iter()
+			.map(|(cid, content)| RegistryEntry::Content(cid, content))
+			.collect::<Vec<_>>();
+		entries
+			.extend(services.into_iter().map(|(id, service)| RegistryEntry::Service(id, service)));
+		entries.extend(assignments);
+		RegistrySnapshot { exported_at, entries }
+	}
+
+	// the oldest registered content without a lease whose known sizes add up to `excess` bytes,
+	// at most `MAX_EVICTIONS` of it; content of unknown size counts as empty
+	fn eviction_candidates(excess: u64) -> Vec<Vec<u8>> {
+		let mut unleased = <ContentRegistry<T>>::iter()
+			.filter(|(_, content)| content.expires_at.is_none())
+			.map(|(cid, content)| (content.added_at, content.size.unwrap_or_default(), cid))
+			.collect::<Vec<_>>();
+		unleased.sort();
+
+		let mut freed = 0u64;
+		unleased
+			.into_iter()
+			.take(MAX_EVICTIONS as usize)
+			.take_while(|(_, size, _)| {
+				let enough = freed >= excess;
+				freed = freed.saturating_add(*size);
+				!enough
+			})
+			.map(|(_, _, cid)| cid)
+			.collect()
+	}
+
+	// ask the chain to evict content as a signed transaction
+	fn report_evictions(cids: Vec<Vec<u8>>) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer.send_signed_transaction(|_| Call::evict_content { cids: cids.clone() }) {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!("IPFS: failed to evict content with account {:?}", account.id)
+			},
+			None => log::error!("IPFS: can't evict content: {:?}", Error::<T>::NoSigningKey),
+		}
+	}
+
+	// submit a request result back to the chain as an unsigned transaction
+	fn submit_result(call: Call<T>) {
+		if SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).is_err() {
+			log::error!("IPFS: failed to submit a request result");
+		}
+	}
+
+	// report an abandoned request back to the chain as a signed transaction
+	fn report_abandoned_request(job: DataJob<T::AccountId>, attempts: u32, reason: DispatchError) {
+		let signer = Signer::<T, T::AuthorityId>::any_account();
+		match signer.send_signed_transaction(|_| Call::report_abandoned {
+			cmd: job.cmd.clone(),
+			request: job.request,
+			attempts,
+			reason,
+		}) {
+			Some((_, Ok(()))) => {},
+			Some((account, Err(()))) => {
+				log::error!(
+					"IPFS: failed to report an abandoned request with account {:?}",
+					account.id
+				)
+			},
+			None => log::error!(
+				"IPFS: can't report an abandoned request: {:?}",
+				Error::<T>::NoSigningKey
+			),
+		}
+	}
+
+	// send a request to the configured content store; can only be called be an off-chain worker
+	fn ipfs_request(
+		req: IpfsRequest,
+		deadline: impl Into<Option<Timestamp>>,
+	) -> Result<IpfsResponse, Error<T>> {
+		let kind = latency::kind(&req);
+		let started = timestamp();
+		let result = T::ContentStore::request(req, deadline.into());
+		latency::record(
+			kind,
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			result.is_ok(),
+		);
+		if result.is_ok() {
+			StorageValueRef::persistent(LAST_SUCCESS)
+				.set(&<frame_system::Pallet<T>>::block_number());
+		}
+
+		result.map_err(Into::into)
+	}
+
+	// announce `cid` through the configured content store; can only be called by an off-chain
+	// worker
+	fn ipfs_provide(cid: Vec<u8>, deadline: Option<Timestamp>) -> Result<(), Error<T>> {
+		let started = timestamp();
+		let result = T::ContentStore::provide(cid, deadline);
+		latency::record(
+			"dht",
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			result.is_ok(),
+		);
+
+		result.map_err(Into::into)
+	}
+
+	// remove unpinned blocks through the configured content store; can only be called by an
+	// off-chain worker
+	fn ipfs_collect_garbage(deadline: Option<Timestamp>) -> Result<(), Error<T>> {
+		let started = timestamp();
+		let result = T::ContentStore::collect_garbage(deadline);
+		latency::record(
+			"gc",
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			result.is_ok(),
+		);
+
+		result.map_err(Into::into)
+	}
+
+	// point the IPNS name of the node key `key` at `cid` through the configured content store,
+	// returning the name; can only be called by an off-chain worker
+	fn ipfs_name_publish(
+		key: &[u8],
+		cid: &[u8],
+		deadline: Option<Timestamp>,
+	) -> Result<Vec<u8>, Error<T>> {
+		let started = timestamp();
+		let result = T::ContentStore::name_publish(key, cid, deadline);
+		latency::record(
+			"ipns",
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			result.is_ok(),
+		);
+
+		result.map_err(Into::into)
+	}
+
+	// resolve the IPNS `name` through the configured content store; can only be called by an
+	// off-chain worker
+	fn ipfs_name_resolve(name: &[u8], deadline: Option<Timestamp>) -> Result<Vec<u8>, Error<T>> {
+		let started = timestamp();
+		let result = T::ContentStore::name_resolve(name, deadline);
+		latency::record(
+			"ipns",
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			result.is_ok(),
+		);
+
+		result.map_err(Into::into)
+	}
+
+	// the name of the IPFS node key `owner`'s IPNS name is published with: a fixed prefix and the
+	// hex-encoded hash of the account
+	fn ipns_key(owner: &T::AccountId) -> Vec<u8> {
+		const HEX: &[u8; 16] = b"0123456789abcdef";
+		let mut key = IPNS_KEY_PREFIX.to_vec();
+		for byte in T::Hashing::hash_of(owner).as_ref() {
+			key.push(HEX[(byte >> 4) as usize]);
+			key.push(HEX[(byte & 0xf) as usize]);
+		}
+		key
+	}
+
+	fn connection_housekeeping() -> Result<(), Error<T>> {
+		let mut deadline;
+
+		for cmd in <ConnectionQueue<T>>::get() {
+			deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+
+			match cmd {
+				// connect to the desired peers if not yet connected
+				ConnectionCommand::ConnectTo(addr) => {
+					match Self::ipfs_request(IpfsRequest::Connect(addr.clone()), deadline) {
+						Ok(IpfsResponse::Success) => {
+							log::info!(
+								"IPFS: connected to {}",
+								str::from_utf8(&addr.0)
+									.expect("our own calls can be trusted to be UTF-8; qed")
+							);
+						},
+						Ok(_) => unreachable!(
+							"only Success can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: connect error: {:?}", e),
+					}
+				},
+				// disconnect from peers that are no longer desired
+				ConnectionCommand::DisconnectFrom(addr) => {
+					match Self::ipfs_request(IpfsRequest::Disconnect(addr.clone()), deadline) {
+						Ok(IpfsResponse::Success) => {
+							log::info!(
+								"IPFS: disconnected from {}",
+								str::from_utf8(&addr.0)
+									.expect("our own calls can be trusted to be UTF-8; qed")
+							);
+						},
+						Ok(_) => unreachable!(
+							"only Success can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: disconnect error: {:?}", e),
+					}
+				},
+			}
+		}
+
+		Self::reconcile_peers()
+	}
+
+	// diff the desired and bootstrap peers against the live connections: dial the ones that aren't
+	// connected (which is all of them on startup) and disconnect the ones this node connected to
+	// that are no longer desired
+	fn reconcile_peers() -> Result<(), Error<T>> {
+		let deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+		let live = match Self::ipfs_request(IpfsRequest::Peers, deadline)? {
+			IpfsResponse::Peers(peers) => peers,
+			_ => unreachable!("only Peers can be a response for that request type; qed"),
+		};
+		let is_live = |addr: &[u8]| live.iter().any(|peer| same_peer(&peer.0, addr));
+
+		let managed_storage = StorageValueRef::persistent(MANAGED_PEERS);
+		let mut managed = managed_storage.get::<Vec<Vec<u8>>>().ok().flatten().unwrap_or_default();
+
+		let bootstrap = Self::bootstrap_peers().into_iter().map(|addr| addr.0).collect::<Vec<_>>();
+		let mut desired = <DesiredPeers<T>>::iter()
+			.map(|(addr, _)| addr)
+			.chain(bootstrap.iter().cloned())
+			.collect::<Vec<_>>();
+		desired.sort();
+		desired.dedup();
+		for addr in desired {
+			if is_live(&addr) {
+				continue;
+			}
+			let deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+			match Self::ipfs_request(IpfsRequest::Connect(OpaqueMultiaddr(addr.clone())), deadline)
+			{
+				Ok(_) => log::info!(
+					"IPFS: reconnected to {}",
+					str::from_utf8(&addr).unwrap_or("<non-UTF-8 address>")
+				),
+				Err(e) => log::error!("IPFS: reconnect error: {:?}", e),
+			}
+			if !managed.contains(&addr) {
+				managed.push(addr);
+			}
+		}
+
+		let mut kept = Vec::with_capacity(managed.len());
+		for addr in managed {
+			if Self::is_desired_peer(&addr) || bootstrap.contains(&addr) {
+				kept.push(addr);
+				continue;
+			}
+			if is_live(&addr) {
+				let deadline =
+					Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+				if let Err(e) = Self::ipfs_request(
+					IpfsRequest::Disconnect(OpaqueMultiaddr(addr.clone())),
+					deadline,
+				) {
+					log::error!("IPFS: disconnect error: {:?}", e);
+					kept.push(addr);
+				}
+			}
+		}
+		managed_storage.set(&kept);
+
+		Ok(())
+	}
+
+	fn handle_dht_requests() -> Result<(), Error<T>> {
+		let mut deadline;
+
+		for cmd in <DhtQueue<T>>::get() {
+			deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+
+			match cmd {
+				// find the known addresses of the given peer
+				DhtCommand::FindPeer(peer_id) => {
+					match Self::ipfs_request(IpfsRequest::FindPeer(peer_id.clone()), deadline) {
+						Ok(IpfsResponse::FindPeer(addrs)) => {
+							log::info!(
+								"IPFS: found the following addresses of {}: {:?}",
+								str::from_utf8(&peer_id)
+									.expect("our own calls can be trusted to be UTF-8; qed"),
+								addrs
+									.iter()
+									.map(|addr| str::from_utf8(&addr.0).expect(
+										"our node's results can be trusted to be UTF-8; qed"
+									))
+									.collect::<Vec<_>>()
+							);
+							let addrs = addrs.into_iter().map(|addr| addr.0).collect();
+							let query = DhtCommand::FindPeer(peer_id);
+							Self::submit_result(Call::submit_dht_result { query, result: addrs });
+						},
+						Ok(_) => unreachable!(
+							"only FindPeer can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: find peer error: {:?}", e),
+					}
+				},
+				// disconnect from peers that are no longer desired
+				DhtCommand::GetProviders(cid) => {
+					match Self::ipfs_request(IpfsRequest::GetProviders(cid.clone()), deadline) {
+						Ok(IpfsResponse::GetProviders(peer_ids)) => {
+							log::info!(
+								"IPFS: found the following providers of {}: {:?}",
+								str::from_utf8(&cid)
+									.expect("our own calls can be trusted to be UTF-8; qed"),
+								peer_ids
+									.iter()
+									.map(|peer_id| str::from_utf8(&peer_id).expect(
+										"our node's results can be trusted to be UTF-8; qed"
+									))
+									.collect::<Vec<_>>()
+							);
+							let query = DhtCommand::GetProviders(cid);
+							Self::submit_result(Call::submit_dht_result {
+								query,
+								result: peer_ids,
+							});
+						},
+						Ok(_) => unreachable!(
+							"only GetProviders can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: find providers error: {:?}", e),
+					}
+				},
+				// announce that this node provides the given content
+				DhtCommand::Provide(cid) => match Self::ipfs_provide(cid.clone(), deadline) {
+					Ok(()) => log::info!(
+						"IPFS: providing {}",
+						str::from_utf8(&cid)
+							.expect("our own calls can be trusted to be UTF-8; qed")
+					),
+					Err(e) => log::error!("IPFS: provide error: {:?}", e),
+				},
+			}
+		}
+
+		Ok(())
+	}
+
+	fn reprovide_pinned() -> Result<(), Error<T>> {
+		let mut provided = 0u32;
+		for (cid, content) in <ContentRegistry<T>>::iter() {
+			if content.pin_status != PinStatus::Pinned {
+				continue;
+			}
+			let deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+			match Self::ipfs_provide(cid, deadline) {
+				Ok(()) => provided += 1,
+				Err(Error::<T>::UnsupportedRequest) => return Err(Error::<T>::UnsupportedRequest),
+				Err(e) => log::error!("IPFS: re-provide error: {:?}", e),
+			}
+		}
+
+		log::info!(
+			"IPFS: re-provided {} pinned Cid{}",
+			provided,
+			if provided == 1 { "" } else { "s" }
+		);
+
+		Ok(())
+	}
+
+	fn handle_pubsub(report: bool) -> Result<(), Error<T>> {
+		let mut deadline;
+
+		for cmd in <PubsubQueue<T>>::get() {
+			deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+
+			match cmd {
+				PubsubCommand::Subscribe(topic) => {
+					match Self::ipfs_request(IpfsRequest::Subscribe(topic.clone()), deadline) {
+						Ok(IpfsResponse::Success) => log::info!(
+							"IPFS: subscribed to {}",
+							str::from_utf8(&topic).unwrap_or("<non-UTF-8 topic>")
+						),
+						Ok(_) => unreachable!(
+							"only Success can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: subscribe error: {:?}", e),
+					}
+				},
+				PubsubCommand::Publish(topic, message) => {
+					match Self::ipfs_request(
+						IpfsRequest::Publish { topic: topic.clone(), message },
+						deadline,
+					) {
+						Ok(IpfsResponse::Success) => log::info!(
+							"IPFS: published a message on {}",
+							str::from_utf8(&topic).unwrap_or("<non-UTF-8 topic>")
+						),
+						Ok(_) => unreachable!(
+							"only Success can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: publish error: {:?}", e),
+					}
+				},
+				PubsubCommand::Unsubscribe(topic) => {
+					match Self::ipfs_request(IpfsRequest::Unsubscribe(topic.clone()), deadline) {
+						Ok(IpfsResponse::Success) => log::info!(
+							"IPFS: unsubscribed from {}",
+							str::from_utf8(&topic).unwrap_or("<non-UTF-8 topic>")
+						),
+						Ok(_) => unreachable!(
+							"only Success can be a response for that request type; qed"
+						),
+						Err(e) => log::error!("IPFS: unsubscribe error: {:?}", e),
+					}
+				},
+			}
+		}
+
+		// drain a few pending messages per topic and hand them to the chain, unless they can't be
+		// reported, in which case they are left for the IPFS node to buffer
+		if !report {
+			return Ok(());
+		}
+		for topic in <PubsubTopics<T>>::get() {
+			for _ in 0..PUBSUB_BATCH {
+				deadline = Some(timestamp().add(Duration::from_millis(100)));
+				match Self::ipfs_request(IpfsRequest::SubscriptionNext(topic.clone()), deadline) {
+					Ok(IpfsResponse::SubscriptionNext(data)) => {
+						Self::report_pubsub_message(topic.clone(), data)
+					},
+					Ok(_) => unreachable!(
+						"only SubscriptionNext can be a response for that request type; qed"
+					),
+					// no message pending on this topic
+					Err(Error::<T>::RequestTimeout) => break,
+					Err(e) => {
+						log::error!("IPFS: pubsub receive error: {:?}", e);
+						break;
+					},
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn handle_data_requests(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		// commands stay queued until acknowledged, so remember locally which ones this node has
+		// already processed
+		let cursor = StorageValueRef::persistent(DATA_CURSOR);
+		let done_ref = StorageValueRef::persistent(DATA_DONE);
+		let start = cursor.get::<u64>().ok().flatten().unwrap_or(0).max(<DataQueueHead<T>>::get());
+		let end = <DataQueueTail<T>>::get();
+		if start >= end {
+			return Ok(());
+		}
+		let mut done = done_ref.get::<BTreeSet<u64>>().ok().flatten().unwrap_or_default();
+
+		// take up to `DataBudget` of the commands this node may claim, highest priority first and
+		// in queue order within a priority class
+		let reporters = Self::reporters();
+		let local = Self::local_reporter().map(|(_, account)| account);
+		let mut pending = (start..end)
+			.filter(|index| !done.contains(index))
+			.filter_map(|index| {
+				<DataQueue<T>>::get(index).map(|(priority, cmd)| (priority, index, cmd))
+			})
+			.filter(|(_, index, cmd)| {
+				Self::may_claim(*index, cmd, &reporters, local.as_ref(), block_number)
+			})
+			.collect::<Vec<_>>();
+		let len = pending.len();
+		log::info!("IPFS: {} entr{} in the data queue", len, if len == 1 { "y" } else { "ies" });
+		pending.sort_by_key(|(priority, index, _)| (*priority, *index));
+		pending.truncate(T::DataBudget::get() as usize);
+
+		// every request gets a short deadline first (unless the caller asked for another) so that
+		// one slow request doesn't hold up the rest of the queue; failed requests are retried
+		// later with exponential backoff, adds and cats that merely timed out with a long deadline
+		let jobs = pending
+			.into_iter()
+			.map(|(_, index, cmd)| {
+				done.insert(index);
+				DataJob { cmd, request: Some(index), background: false }
+			})
+			.collect();
+		Jobs::<DataJobs<T>>::process(&mut (), jobs, block_number);
+
+		// advance the cursor over the commands processed without a gap, by this node or the
+		// reporters that picked them up
+		let mut processed = start;
+		while processed < end
+			&& (done.contains(&processed)
+				|| !<DataQueue<T>>::contains_key(processed)
+				|| Self::picked_up_elsewhere(processed))
+		{
+			processed += 1;
+		}
+		done = done.split_off(&processed);
+		done_ref.set(&done);
+		if processed > start {
+			cursor.set(&processed);
+			Self::report_data_processed(processed);
+		}
+
+		Ok(())
+	}
+
+	// whether this node may process the data command `index`: commands are left to their assigned
+	// reporters until `ClaimGracePeriod` passed without any of them picking the command up, and
+	// every node processes them while there are no reporters; updates of a published IPNS name are
+	// assigned to the reporter whose node holds its key
+	fn may_claim(
+		index: u64,
+		cmd: &DataCommand<T::AccountId>,
+		reporters: &[T::AccountId],
+		local: Option<&T::AccountId>,
+		block_number: T::BlockNumber,
+	) -> bool {
+		let publisher = match cmd {
+			DataCommand::IpnsPublish(owner, _) => Self::ipns_name(owner)
+				.map(|binding| binding.publisher)
+				.filter(|publisher| reporters.contains(publisher)),
+			_ => None,
+		};
+		let assigned = |local: &T::AccountId| match &publisher {
+			Some(publisher) => publisher == local,
+			None => Self::assigned_workers(index, reporters).contains(local),
+		};
+		if reporters.is_empty() || local.map_or(false, assigned) {
+			return true;
+		}
+		let unclaimed =
+			Self::request_status(index).map_or(true, |status| status == RequestStatus::Queued);
+		let waited = Self::data_queued_at(index).map_or(true, |queued_at| {
+			block_number >= queued_at.saturating_add(T::ClaimGracePeriod::get())
+		});
+		unclaimed && waited
+	}
+
+	// the deadline (in ms) asked for the data command of `request`: the longest allowed once its
+	// submitter bumped it, otherwise the caller's, if any
+	fn requested_timeout(
+		request: Option<RequestId>,
+		cmd: &DataCommand<T::AccountId>,
+	) -> Option<u64> {
+		match request {
+			Some(request) if <BumpedAt<T>>::contains_key(request) => {
+				Some(T::MaxRequestTimeout::get())
+			},
+			_ => cmd.timeout(),
+		}
+	}
+
+	// whether the data command of `request` was bumped in `block_number`, which makes a retry of it
+	// due whatever its backoff
+	fn bumped_in(request: Option<RequestId>, block_number: T::BlockNumber) -> bool {
+		request.and_then(Self::bumped_at) == Some(block_number)
+	}
+
+	// whether another node reported working on the data command `index`
+	fn picked_up_elsewhere(index: u64) -> bool {
+		matches!(Self::request_status(index), Some(status) if status != RequestStatus::Queued)
+	}
+
+	// carry out a data command, returning the hash of its result for the command's status
+	fn process_data_command(
+		cmd: DataCommand<T::AccountId>,
+		deadline: Option<Timestamp>,
+	) -> Result<T::Hash, Error<T>> {
+		match cmd {
+			DataCommand::AddUpload(owner, upload_id, total_chunks, content_hash) => {
+				let data = Self::assemble_upload(upload_id, total_chunks, &content_hash)?;
+				let result = Self::process_data_command(
+					DataCommand::AddBytes(owner, data, None, None),
+					deadline,
+				)?;
+				for index in 0..total_chunks {
+					sp_io::offchain::local_storage_clear(
+						StorageKind::PERSISTENT,
+						&upload_chunk_key(upload_id, index),
+					);
+				}
+				Ok(result)
+			},
+			DataCommand::AddBytes(owner, data, lease, _) => {
+				match Self::ipfs_request(IpfsRequest::AddBytes(data.clone()), deadline) {
+					Ok(IpfsResponse::AddBytes(cid)) => {
+						log::info!(
+							"IPFS: added data with Cid {}",
+							str::from_utf8(&cid)
+								.expect("our own IPFS node can be trusted here; qed")
+						);
+						let result = T::Hashing::hash(&cid);
+						Self::report_cid(owner, cid, data.len() as u64, lease, false);
+						Ok(result)
+					},
+					Ok(_) => {
+						unreachable!("only AddBytes can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: add error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::AddEncrypted(owner, data, _) => {
+				let key = encryption::key().ok_or_else(|| {
+					log::error!(
+						"IPFS: no encryption key is configured under {:?}",
+						encryption::KEY
+					);
+					Error::<T>::NoEncryptionKey
+				})?;
+				match Self::ipfs_request(
+					IpfsRequest::AddBytes(encryption::encrypt(&key, &data)),
+					deadline,
+				) {
+					Ok(IpfsResponse::AddBytes(cid)) => {
+						log::info!(
+							"IPFS: added encrypted data with Cid {}",
+							str::from_utf8(&cid)
+								.expect("our own IPFS node can be trusted here; qed")
+						);
+						let result = T::Hashing::hash(&cid);
+						Self::report_cid(owner, cid, data.len() as u64, None, true);
+						Ok(result)
+					},
+					Ok(_) => {
+						unreachable!("only AddBytes can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: add error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::CatBytes(cid, timeout) => {
+				let request = T::Hashing::hash_of(&DataCommand::<T::AccountId>::CatBytes(
+					cid.clone(),
+					timeout,
+				));
+				match Self::ipfs_request(IpfsRequest::CatBytes(cid.clone()), deadline) {
+					Ok(IpfsResponse::CatBytes(data)) => {
+						// encrypted content is only decrypted into this node's off-chain storage;
+						// the chain gets the ciphertext
+						let decrypted = if Self::is_encrypted(&cid) {
+							let decrypted = match encryption::key()
+								.map(|key| encryption::decrypt(&key, &data))
+							{
+								Some(Some(decrypted)) => decrypted,
+								Some(None) => {
+									log::error!(
+										"IPFS: the data doesn't decrypt with this node's key"
+									);
+									return Err(Error::<T>::CantDecrypt);
+								},
+								None => {
+									log::error!(
+										"IPFS: no encryption key is configured under {:?}",
+										encryption::KEY
+									);
+									return Err(Error::<T>::NoEncryptionKey);
+								},
+							};
+							log::info!("IPFS: got {} bytes of encrypted data", decrypted.len());
+							encryption::store_decrypted(request.as_ref(), &decrypted);
+							Some(decrypted)
+						} else if let Ok(str) = str::from_utf8(&data) {
+							log::info!("IPFS: got data: {:?}", str);
+							None
+						} else {
+							log::info!("IPFS: got data: {:x?}", data);
+							None
+						};
+						if T::ValidateSchemas::get() {
+							Self::check_schema(&cid, decrypted.as_ref().unwrap_or(&data));
+						}
+						Self::cache_content(&cid, &data);
+						let result = T::Hashing::hash(&data);
+						if data.len() <= MAX_CAT_RESULT_LEN {
+							Self::submit_result(Call::submit_cat_result { request, data });
+						} else {
+							log::info!(
+								"IPFS: cat result of {} bytes is only kept off-chain",
+								data.len()
+							);
+						}
+						Ok(result)
+					},
+					Ok(_) => {
+						unreachable!("only CatBytes can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::RemoveBlock(cid) => {
+				match Self::ipfs_request(IpfsRequest::RemoveBlock(cid), deadline) {
+					Ok(IpfsResponse::RemoveBlock(cid)) => {
+						log::info!(
+							"IPFS: removed a block with Cid {}",
+							str::from_utf8(&cid)
+								.expect("our own IPFS node can be trusted here; qed")
+						);
+						Ok(T::Hashing::hash(&cid))
+					},
+					Ok(_) => unreachable!(
+						"only RemoveBlock can be a response for that request type; qed"
+					),
+					Err(e) => {
+						log::error!("IPFS: remove block error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::InsertPin(cid, recursive) => {
+				match Self::ipfs_request(IpfsRequest::InsertPin(cid.clone(), recursive), deadline) {
+					Ok(IpfsResponse::Success) => {
+						log::info!(
+							"IPFS: pinned data with Cid {}",
+							str::from_utf8(&cid)
+								.expect("our own request can be trusted to be UTF-8; qed")
+						);
+						Ok(T::Hashing::hash(&cid))
+					},
+					Ok(_) => {
+						unreachable!("only Success can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: insert pin error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::RemovePin(cid, recursive) => {
+				match Self::ipfs_request(IpfsRequest::RemovePin(cid.clone(), recursive), deadline) {
+					Ok(IpfsResponse::Success) => {
+						log::info!(
+							"IPFS: unpinned data with Cid {}",
+							str::from_utf8(&cid)
+								.expect("our own request can be trusted to be UTF-8; qed")
+						);
+						Ok(T::Hashing::hash(&cid))
+					},
+					Ok(_) => {
+						unreachable!("only Success can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: remove pin error: {:?}", e);
+						Err(e)
+					},
+				}
+			},
+			DataCommand::CollectGarbage => Self::ipfs_collect_garbage(deadline)
+				.map(|_| {
+					log::info!("IPFS: collected garbage");
+					T::Hashing::hash(&[])
+				})
+				.map_err(|e| {
+					log::error!("IPFS: garbage collection error: {:?}", e);
+					e
+				}),
+			DataCommand::IpnsPublish(owner, cid) => {
+				let name = Self::ipfs_name_publish(&Self::ipns_key(&owner), &cid, deadline)
+					.map_err(|e| {
+						log::error!("IPFS: IPNS publish error: {:?}", e);
+						e
+					})?;
+				log::info!(
+					"IPFS: published IPNS name {}",
+					str::from_utf8(&name).expect("our own IPFS node can be trusted here; qed")
+				);
+				let result = T::Hashing::hash(&name);
+				if let Err(e) =
+					Self::send_report(None, WorkerReport::IpnsPublished { owner, name, cid })
+				{
+					log::error!("IPFS: can't report the IPNS name: {:?}", e);
+				}
+				Ok(result)
+			},
+			DataCommand::IpnsResolve(name) => {
+				let cid = Self::ipfs_name_resolve(&name, deadline).map_err(|e| {
+					log::error!("IPFS: IPNS resolve error: {:?}", e);
+					e
+				})?;
+				let result = T::Hashing::hash(&cid);
+				if let Err(e) = Self::send_report(None, WorkerReport::IpnsResolved { name, cid }) {
+					log::error!("IPFS: can't report the resolved IPNS name: {:?}", e);
+				}
+				Ok(result)
+			},
+		}
+	}
+
+	// put the staged chunks of an upload back together and check them against the declared hash
+	fn assemble_upload(
+		upload_id: UploadId,
+		total_chunks: u32,
+		content_hash: &[u8],
+	) -> Result<Vec<u8>, Error<T>> {
+		let mut data = Vec::new();
+		for index in 0..total_chunks {
+			let chunk = sp_io::offchain::local_storage_get(
+				StorageKind::PERSISTENT,
+				&upload_chunk_key(upload_id, index),
+			)
+			.ok_or_else(|| {
+				log::error!("IPFS: chunk {} of upload {} is missing", index, upload_id);
+				Error::<T>::RequestFailed
+			})?;
+			data.extend(chunk);
+		}
+
+		if T::Hashing::hash(&data).as_ref() != content_hash {
+			log::error!("IPFS: upload {} doesn't match its declared hash", upload_id);
+			return Err(Error::<T>::RequestFailed);
+		}
+
+		Ok(data)
+	}
+
+	// check fetched content against the `required` members of its declared schema and flag it
+	// on-chain if any are missing
+	fn check_schema(cid: &[u8], data: &[u8]) {
+		let id = match Self::content(cid).and_then(|content| content.schema) {
+			Some(id) => id,
+			None => return,
+		};
+		let schema = match Self::schema(id) {
+			Some(schema) => schema,
+			None => return,
+		};
+		let deadline = Some(timestamp().add(Duration::from_millis(T::FastDeadline::get())));
+		let document = match Self::ipfs_request(IpfsRequest::CatBytes(schema.cid), deadline) {
+			Ok(IpfsResponse::CatBytes(document)) => document,
+			Ok(_) => unreachable!("only CatBytes can be a response for that request type; qed"),
+			Err(e) => {
+				log::error!("IPFS: can't fetch schema {}: {:?}", id, e);
+				return;
+			},
+		};
+
+		let missing = json::string_values(&document, b"required")
+			.into_iter()
+			.filter(|key| !json::has_key(data, key))
+			.count();
+		if missing != 0 {
+			log::error!("IPFS: content misses {} member(s) required by schema {}", missing, id);
+			let signer = Signer::<T, T::AuthorityId>::any_account();
+			match signer.send_signed_transaction(|_| Call::report_schema_mismatch {
+				cid: cid.to_vec(),
+				schema: id,
+			}) {
+				Some((_, Ok(()))) => {},
+				Some((account, Err(()))) => {
+					log::error!(
+						"IPFS: failed to report a schema mismatch with account {:?}",
+						account.id
+					)
+				},
+				None => log::error!(
+					"IPFS: can't report a schema mismatch: {:?}",
+					Error::<T>::NoSigningKey
+				),
+			}
+		}
+	}
+
+	// pin imported CIDs in batches, remembering locally how far this node has got
+	fn handle_imported_pins() -> Result<(), Error<T>> {
+		let cursor = StorageValueRef::persistent(IMPORT_CURSOR);
+		let start = cursor.get::<u32>().ok().flatten().unwrap_or(0);
+		let end = <ImportedPinCount<T>>::get().min(start.saturating_add(IMPORT_BATCH));
+		if start >= end {
+			return Ok(());
+		}
+
+		let deadline = Some(timestamp().add(Duration::from_millis(T::FastDeadline::get())));
+		for index in start..end {
+			let cid = match <ImportedPins<T>>::get(index) {
+				Some(cid) => cid,
+				None => continue,
+			};
+			let recursive =
+				Self::content(&cid).map_or(false, |content| content.pin_mode.is_recursive());
+			if let Err(e) =
+				Self::ipfs_request(IpfsRequest::InsertPin(cid.clone(), recursive), deadline)
+			{
+				log::error!(
+					"IPFS: failed to pin imported Cid {}: {:?}",
+					str::from_utf8(&cid).unwrap_or("<non-UTF-8 Cid>"),
+					e
+				);
+			}
+		}
+		cursor.set(&end);
+
+		log::info!("IPFS: pinned imported content {}/{}", end, <ImportedPinCount<T>>::get());
+
+		Ok(())
+	}
+
+	// check the pinned registry entries against the repo in batches and pin the missing ones
+	// again; a scan starts on the first run after a gap, e.g. after a restart with a lost repo,
+	// and when a new recovery round is started on-chain
+	fn handle_recovery(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		let last_run = StorageValueRef::persistent(LAST_RUN);
+		let resumed = last_run
+			.get::<T::BlockNumber>()
+			.ok()
+			.flatten()
+			.map_or(true, |last| block_number > last.saturating_add(1u32.into()));
+		last_run.set(&block_number);
+
+		let round = Self::recovery_round();
+		let scan_ref = StorageValueRef::persistent(RECOVERY_SCAN);
+		let mut scan = match scan_ref.get::<RecoveryScan>().ok().flatten() {
+			Some(scan) if !scan.progress.done => scan,
+			last => {
+				let new_round = last.map_or(round > 0, |last| last.progress.round < round);
+				if !resumed && !new_round {
+					return Ok(());
+				}
+				log::info!(
+					"IPFS: checking the repo for missing content (recovery round {})",
+					round
+				);
+				RecoveryScan {
+					cursor: None,
+					local: Self::local_refs(),
+					progress: RecoveryProgress { round, ..Default::default() },
+				}
+			},
+		};
+
+		let entries = match scan.cursor.take() {
+			Some(key) => <ContentRegistry<T>>::iter_from(key),
+			None => <ContentRegistry<T>>::iter(),
+		};
+		let deadline = Some(timestamp().add(Duration::from_millis(T::FastDeadline::get())));
+		let mut failed = Vec::new();
+		let mut remaining = RECOVERY_BATCH;
+		scan.progress.done = true;
+		for (cid, content) in entries {
+			if remaining == 0 {
+				scan.progress.done = false;
+				break;
+			}
+			remaining -= 1;
+			scan.cursor = Some(<ContentRegistry<T>>::hashed_key_for(&cid));
+
+			if content.pin_status != PinStatus::Pinned {
+				continue;
+			}
+			scan.progress.checked += 1;
+			// the repo may list a different CID version of the same content; pinning it again
+			// is harmless
+			if scan.local.contains(&cid) {
+				continue;
+			}
+
+			scan.progress.missing += 1;
+			let recursive = content.pin_mode.is_recursive();
+			match Self::ipfs_request(IpfsRequest::InsertPin(cid.clone(), recursive), deadline) {
+				Ok(_) => scan.progress.repinned += 1,
+				// fetching the content from other peers can take a while, so retry in the
+				// background
+				Err(e) => failed.push((
+					DataJob {
+						cmd: DataCommand::InsertPin(cid, recursive),
+						request: None,
+						background: true,
+					},
+					1,
+					e.into(),
+				)),
+			}
+		}
+		Jobs::<DataJobs<T>>::retry(failed, block_number);
+
+		let progress = scan.progress.clone();
+		if progress.done {
+			scan.local.clear();
+			log::info!(
+				"IPFS: recovery checked {} pinned Cid(s), {} missing, {} pinned again",
+				progress.checked,
+				progress.missing,
+				progress.repinned
+			);
+		}
+		scan_ref.set(&scan);
+		Self::report_recovery_progress(progress);
+
+		Ok(())
+	}
+
+	// the CIDs of the blocks in the repo; empty if the content store can't list them, in which
+	// case every pinned entry is pinned again
+	fn local_refs() -> BTreeSet<Vec<u8>> {
+		let deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+		match Self::ipfs_request(IpfsRequest::LocalRefs, deadline) {
+			Ok(IpfsResponse::LocalRefs(refs)) => refs.into_iter().collect(),
+			Ok(_) => unreachable!("only LocalRefs can be a response for that request type; qed"),
+			Err(e) => {
+				log::error!("IPFS: can't list the local refs: {:?}", e);
+				BTreeSet::new()
+			},
+		}
+	}
+
+	// whether the keystore holds a key to sign reports with; the first run without one warns and
+	// the first run with one again says so
+	fn check_signing_key(block_number: T::BlockNumber) -> bool {
+		let can_sign = Signer::<T, T::AuthorityId>::any_account().can_sign();
+		let missing = StorageValueRef::persistent(SIGNING_KEY_MISSING);
+		let flagged = missing.get::<bool>().ok().flatten().unwrap_or(false);
+		if !can_sign && !flagged {
+			log::warn!(
+				"IPFS: no signing key in the keystore (key_type=ipfs, block={:?}); skipping the \
+				 tasks that report to the chain until one is inserted",
+				block_number
+			);
+			missing.set(&true);
+		} else if can_sign && flagged {
+			log::info!("IPFS: found a signing key, resuming the tasks that report to the chain");
+			missing.clear();
+		}
+		can_sign
+	}
+
+	// the signing key in this node's keystore that belongs to a reporter, with the reporter's
+	// account
+	fn local_reporter() -> Option<(T::Public, T::AccountId)> {
+		let reporters = Self::reporters();
+		<T::AuthorityId as AppCrypto<T::Public, T::Signature>>::RuntimeAppPublic::all()
+			.into_iter()
+			.map(|key| {
+				let generic: <T::AuthorityId as AppCrypto<T::Public, T::Signature>>::GenericPublic =
+					key.into();
+				let public: T::Public = generic.into();
+				(public.clone(), public.into_account())
+			})
+			.find(|(_, account)| reporters.contains(account))
+	}
+
+	// every `AttestationInterval` blocks, pin the registered content assigned to this node that is
+	// missing from its repo and attest the assigned content it keeps pinned
+	fn handle_replication(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		let interval = T::AttestationInterval::get();
+		if interval.is_zero() || !(block_number % interval).is_zero() {
+			return Ok(());
+		}
+		let (public, account) = match Self::local_reporter() {
+			Some(reporter) => reporter,
+			None => return Ok(()),
+		};
+
+		let reporters = Self::reporters();
+		let local = Self::local_refs();
+		let deadline = Some(timestamp().add(Duration::from_millis(T::FastDeadline::get())));
+		let mut attested = Vec::new();
+		let mut pins = REPLICATION_PIN_BATCH;
+		for (cid, content) in <ContentRegistry<T>>::iter() {
+			if content.pin_status != PinStatus::Pinned
+				|| !Self::assigned_pinners(&cid, &reporters).contains(&account)
+			{
+				continue;
+			}
+			if !local.contains(&cid) {
+				// content that can't be pinned right away is tried again in the next round
+				if pins == 0 {
+					continue;
+				}
+				pins -= 1;
+				let recursive = content.pin_mode.is_recursive();
+				if let Err(e) =
+					Self::ipfs_request(IpfsRequest::InsertPin(cid.clone(), recursive), deadline)
+				{
+					log::error!("IPFS: can't pin assigned content: {:?}", e);
+					continue;
+				}
+			}
+			attested.push(cid);
+		}
+
+		log::info!("IPFS: attesting {} assigned pin(s)", attested.len());
+		for cids in attested.chunks(MAX_ATTESTATIONS as usize) {
+			Self::report_attestations(public.clone(), cids.to_vec());
+		}
+		Ok(())
+	}
+
+	// once governance asked for a registry export, take a snapshot of the registry, add it to IPFS
+	// and report its `Cid`
+	fn handle_registry_export(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		let requested_at = match Self::pending_export() {
+			Some(requested_at) => requested_at,
+			None => return Ok(()),
+		};
+		let exported_ref = StorageValueRef::persistent(REGISTRY_EXPORTED);
+		if exported_ref.get::<T::BlockNumber>().ok().flatten() == Some(requested_at) {
+			return Ok(());
+		}
+		let (public, _) = match Self::local_reporter() {
+			Some(reporter) => reporter,
+			None => return Ok(()),
+		};
+
+		let snapshot = Self::registry_snapshot(block_number);
+		let entries = snapshot.entries.len() as u32;
+		let car = snapshot::to_car(&snapshot.encode());
+		let deadline = Some(timestamp().add(Duration::from_millis(T::BackgroundDeadline::get())));
+		let cid = match Self::ipfs_request(IpfsRequest::AddBytes(car), deadline)? {
+			IpfsResponse::AddBytes(cid) => cid,
+			_ => unreachable!("only AddBytes can be a response for that request type; qed"),
+		};
+		log::info!(
+			"IPFS: exported {} registry entries to {}",
+			entries,
+			str::from_utf8(&cid).expect("our own IPFS node can be trusted here; qed")
+		);
+
+		Self::report_registry_export(
+			public,
+			requested_at,
+			RegistryExport { cid, entries, exported_at: block_number },
+		);
+		exported_ref.set(&requested_at);
+		Ok(())
+	}
+
+	// fetch the registry snapshot being imported and submit the next page of entries the chain
+	// hasn't restored yet
+	fn handle_registry_import() -> Result<(), Error<T>> {
+		let import = match Self::pending_import() {
+			Some(import) => import,
+			None => return Ok(()),
+		};
+		let (public, _) = match Self::local_reporter() {
+			Some(reporter) => reporter,
+			None => return Ok(()),
+		};
+
+		let snapshot_ref = StorageValueRef::persistent(REGISTRY_SNAPSHOT);
+		let entries = match snapshot_ref.get::<(Vec<u8>, Vec<RegistryEntryOf<T>>)>().ok().flatten()
+		{
+			Some((cid, entries)) if cid == import.export.cid => entries,
+			_ => {
+				let entries = Self::fetch_snapshot(&import.export)?;
+				snapshot_ref.set(&(&import.export.cid, &entries));
+				entries
+			},
+		};
+
+		let start = import.restored as usize;
+		let end = entries.len().min(start.saturating_add(snapshot::MAX_RESTORE_PAGE as usize));
+		if start < end {
+			Self::report_registry_page(public, import.restored, entries[start..end].to_vec());
+		}
+		// the cache is fetched again should the last page get lost
+		if end == entries.len() {
+			snapshot_ref.clear();
+		}
+		Ok(())
+	}
+
+	// cat a registry snapshot and check it against the export it is imported as
+	fn fetch_snapshot(
+		export: &RegistryExport<T::BlockNumber>,
+	) -> Result<Vec<RegistryEntryOf<T>>, Error<T>> {
+		let deadline = Some(timestamp().add(Duration::from_millis(T::BackgroundDeadline::get())));
+		let car = match Self::ipfs_request(IpfsRequest::CatBytes(export.cid.clone()), deadline)? {
+			IpfsResponse::CatBytes(car) => car,
+			_ => unreachable!("only CatBytes can be a response for that request type; qed"),
+		};
+		let snapshot = snapshot::from_car(&car)
+			.and_then(|data| {
+				RegistrySnapshot::<T::AccountId, T::BlockNumber, BalanceOf<T>>::decode(
+					&mut &data[..],
+				)
+				.ok()
+			})
+			.filter(|snapshot| {
+				snapshot.exported_at == export.exported_at
+					&& snapshot.entries.len() as u32 == export.entries
+			})
+			.ok_or(Error::<T>::InvalidSnapshot)?;
+		Ok(snapshot.entries)
+	}
+
+	// fetch the content of the open storage challenges this node's reporter is a party to and
+	// respond with the hash of the challenged range
+	fn handle_challenges() -> Result<(), Error<T>> {
+		let (public, account) = match Self::local_reporter() {
+			Some(reporter) => reporter,
+			None => return Ok(()),
+		};
+
+		let answered_ref = StorageValueRef::persistent(CHALLENGES_ANSWERED);
+		let answered =
+			answered_ref.get::<BTreeSet<ChallengeId>>().ok().flatten().unwrap_or_default();
+		let open = <Challenges<T>>::iter()
+			.filter(|(id, challenge)| {
+				challenge.pinners.contains(&account) && !answered.contains(id)
+			})
+			.filter(|(id, _)| !<ChallengeResponses<T>>::contains_key(id, &account))
+			.collect::<Vec<_>>();
+		if open.is_empty() {
+			return Ok(());
+		}
+
+		// forget the challenges that were resolved in the meantime
+		let mut answered = answered
+			.into_iter()
+			.filter(|id| <Challenges<T>>::contains_key(id))
+			.collect::<BTreeSet<_>>();
+		let deadline = Some(timestamp().add(Duration::from_millis(T::FastDeadline::get())));
+		for (id, challenge) in open {
+			let data =
+				match Self::ipfs_request(IpfsRequest::CatBytes(challenge.cid.clone()), deadline) {
+					Ok(IpfsResponse::CatBytes(data)) => data,
+					Ok(_) => {
+						unreachable!("only CatBytes can be a response for that request type; qed")
+					},
+					Err(e) => {
+						log::error!("IPFS: can't fetch challenged content: {:?}", e);
+						continue;
+					},
+				};
+			let start = (challenge.offset as usize).min(data.len());
+			let end = start.saturating_add(challenge.len as usize).min(data.len());
+			let response = Self::storage_proof(&challenge.seed, &data[start..end]);
+			Self::report_challenge_response(public.clone(), id, response);
+			answered.insert(id);
+		}
+		answered_ref.set(&answered);
+
+		Ok(())
+	}
+
+	/// The key fetched content is cached under in the node's persistent off-chain storage, where
+	/// the node's `ipfs_getCachedContent` RPC reads it from.
+	pub fn cached_content_key(cid: &[u8]) -> Vec<u8> {
+		let mut key = CAT_CACHE_PREFIX.to_vec();
+		key.extend_from_slice(cid);
+		key
+	}
+
+	// keep the raw bytes of a cat result in the node's persistent off-chain storage, so that
+	// results too large for the chain can still be retrieved from the node
+	fn cache_content(cid: &[u8], data: &[u8]) {
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			&Self::cached_content_key(cid),
+			data,
+		);
+	}
+
+	// log the connected peers and request latencies, and submit them along with the repository
+	// figures as the new `NodeStats`, signed with the worker's key if it has one
+	fn report_stats(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+		let deadline = Some(timestamp().add(Duration::from_millis(T::RequestTimeout::get())));
+
+		let peers =
+			if let IpfsResponse::Peers(peers) = Self::ipfs_request(IpfsRequest::Peers, deadline)? {
+				peers
+			} else {
+				unreachable!("only Peers can be a response for that request type; qed");
+			};
+		let peer_count = peers.len();
+
+		log::info!(
+			"IPFS: currently connected to {} peer{}",
+			peer_count,
+			if peer_count == 1 { "" } else { "s" },
+		);
+
+		for kind in latency::KINDS.iter() {
+			if let Some(s) = latency::summary(kind) {
+				log::info!(
+					"IPFS: {} latency over {} requests ({} failed): p50 {} ms, p95 {} ms, p99 {} \
+					 ms, max {} ms",
+					kind,
+					s.count,
+					s.failures,
+					s.p50,
+					s.p95,
+					s.p99,
+					s.max
+				);
+			}
+		}
+
+		let started = timestamp();
+		let repo = T::ContentStore::repo_stats(deadline);
+		latency::record(
+			"repo",
+			timestamp().unix_millis().saturating_sub(started.unix_millis()),
+			repo.is_ok(),
+		);
+		match repo {
+			Ok(repo) if repo.size > T::MaxRepoSize::get() => {
+				let excess = repo.size - T::MaxRepoSize::get();
+				log::warn!("IPFS: the repo exceeds its size limit by {} bytes", excess);
+				let cids = Self::eviction_candidates(excess);
+				if !cids.is_empty() {
+					Self::report_evictions(cids);
+				}
+			},
+			Ok(_) => {},
+			Err(e) => log::error!("IPFS: repo stats error: {:?}", e),
+		}
+
+		let stats = NodeHealth {
+			reported_at: block_number,
+			peers: peer_count as u32,
+			repo_size: repo.map(|r| r.size).ok(),
+			pins: repo.map(|r| r.pins).ok(),
+			last_success: StorageValueRef::persistent(LAST_SUCCESS)
+				.get::<T::BlockNumber>()
+				.ok()
+				.flatten(),
+		};
+		// nodes without a reporter key still share their figures, as a plain unsigned transaction
+		if Self::send_report(None, WorkerReport::Stats(stats.clone())).is_err() {
+			Self::submit_result(Call::submit_node_stats { stats });
+		}
+
+		Ok(())
+	}
+}
+
+// the off-chain index key a chunk of an upload is staged under
+fn upload_chunk_key(upload_id: UploadId, index: u32) -> Vec<u8> {
+	let mut key = UPLOAD_INDEX_PREFIX.to_vec();
+	key.extend((upload_id, index).encode());
+	key
+}
+
+// whether two multiaddrs point at the same peer: compared by their `/p2p/` (or legacy `/ipfs/`)
+// component if both have one, verbatim otherwise
+fn same_peer(a: &[u8], b: &[u8]) -> bool {
+	match (peer_id(a), peer_id(b)) {
+		(Some(a), Some(b)) => a == b,
+		_ => a == b,
+	}
+}
+
+fn peer_id(addr: &[u8]) -> Option<&[u8]> {
+	let mut parts = addr.split(|b| *b == b'/');
+	while let Some(part) = parts.next() {
+		if part == b"p2p" || part == b"ipfs" {
+			return parts.next();
+		}
+	}
+	None
+}
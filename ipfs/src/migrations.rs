@@ -0,0 +1,122 @@
+// Storage migrations.
+//
+// Before the port to the FRAME v2 pallet macros the pallet declared its storage `as
+// TemplateModule`, with each queue a plain `Vec` of commands that carried nothing but their `Cid`
+// or data. FRAME v2 pallets store their items under the name the runtime gives them in
+// `construct_runtime!`, and the data queue is now keyed by sequence number, with a priority and a
+// request status per command.
+
+/// Migration to storage version 1: re-encode the queues `decl_storage!` kept into the current
+/// layout.
+pub mod v1 {
+	use codec::{Decode, Encode};
+	use frame_support::{
+		storage::migration::take_storage_value,
+		traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+		weights::Weight,
+	};
+	use sp_std::{marker::PhantomData, vec::Vec};
+
+	use crate::{
+		Config, ConnectionCommand, ConnectionQueue, DataCommand, DhtCommand, DhtQueue,
+		MaintenanceCursor, Pallet, Priority,
+	};
+
+	/// The prefix `decl_storage!` stored the items under.
+	pub const OLD_PREFIX: &[u8] = b"TemplateModule";
+
+	/// A data command as queued before version 1.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+	pub enum OldDataCommand {
+		AddBytes(Vec<u8>),
+		CatBytes(Vec<u8>),
+		InsertPin(Vec<u8>),
+		RemoveBlock(Vec<u8>),
+		RemovePin(Vec<u8>),
+	}
+
+	impl OldDataCommand {
+		// the command in the current layout and the priority it is queued with; pins were only
+		// ever direct
+		fn upgrade<AccountId>(self, owner: AccountId) -> (DataCommand<AccountId>, Priority) {
+			match self {
+				OldDataCommand::AddBytes(data) => {
+					(DataCommand::AddBytes(owner, data, None, None), Priority::Normal)
+				},
+				OldDataCommand::CatBytes(cid) => {
+					(DataCommand::CatBytes(cid, None), Priority::Normal)
+				},
+				OldDataCommand::InsertPin(cid) => {
+					(DataCommand::InsertPin(cid, false), Priority::High)
+				},
+				OldDataCommand::RemoveBlock(cid) => (DataCommand::RemoveBlock(cid), Priority::High),
+				OldDataCommand::RemovePin(cid) => {
+					(DataCommand::RemovePin(cid, false), Priority::High)
+				},
+			}
+		}
+	}
+
+	/// Move the connection, data and DHT queues kept under `TemplateModule` into the pallet's
+	/// storage, and start `on_idle`'s sweep of expiries at the current block rather than at
+	/// genesis, which would take a read per empty list of every block since.
+	///
+	/// The old data commands didn't record who queued them, so the content of queued adds is
+	/// registered to `LegacyOwner`. Commands are queued in their old order, as far as
+	/// `MaxQueueLen` allows; the others are dropped. Does nothing on chains already at version 1.
+	pub struct MigrateToV1<T, LegacyOwner>(PhantomData<(T, LegacyOwner)>);
+
+	impl<T, LegacyOwner> OnRuntimeUpgrade for MigrateToV1<T, LegacyOwner>
+	where
+		T: Config,
+		LegacyOwner: Get<T::AccountId>,
+	{
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() >= 1 {
+				return T::DbWeight::get().reads(1);
+			}
+
+			let connections =
+				take_storage_value::<Vec<ConnectionCommand>>(OLD_PREFIX, b"ConnectionQueue", &[])
+					.unwrap_or_default();
+			let dht = take_storage_value::<Vec<DhtCommand>>(OLD_PREFIX, b"DhtQueue", &[])
+				.unwrap_or_default();
+			let data = take_storage_value::<Vec<OldDataCommand>>(OLD_PREFIX, b"DataQueue", &[])
+				.unwrap_or_default();
+			let max = T::MaxQueueLen::get() as usize;
+
+			let (connection_count, dht_count) = (connections.len().min(max), dht.len().min(max));
+			<ConnectionQueue<T>>::mutate(|queue| queue.extend(connections.into_iter().take(max)));
+			<DhtQueue<T>>::mutate(|queue| queue.extend(dht.into_iter().take(max)));
+
+			let owner = LegacyOwner::get();
+			let total = data.len();
+			let mut queued = 0u64;
+			for cmd in data {
+				let (cmd, priority) = cmd.upgrade(owner.clone());
+				if Pallet::<T>::enqueue_data(cmd, priority).is_err() {
+					break;
+				}
+				queued += 1;
+			}
+			if total > 0 || connection_count > 0 || dht_count > 0 {
+				log::info!(
+					"IPFS: migrated {} connection, {} DHT and {} of {} data command(s)",
+					connection_count,
+					dht_count,
+					queued,
+					total
+				);
+			}
+
+			<MaintenanceCursor<T>>::put(<frame_system::Pallet<T>>::block_number());
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			// the version and the three old queues, the two new ones, the cursor and the version;
+			// then the queue bounds and four writes per queued data command
+			T::DbWeight::get()
+				.reads_writes(6, 7)
+				.saturating_add(T::DbWeight::get().reads_writes(2 * queued, 4 * queued))
+		}
+	}
+}
@@ -0,0 +1,333 @@
+use crate as pallet_ipfs;
+use crate::{LocalStore, OnIpfsResult, PinMode};
+use frame_support::{
+	parameter_types,
+	traits::{
+		AsEnsureOriginWithArg, ConstBool, ConstU16, ConstU32, ConstU64, Contains, Randomness,
+	},
+};
+use frame_system::EnsureRoot;
+use mpesa_primitives::{AsyncOp, PromiseId, PromiseRegistry};
+use parking_lot::RwLock;
+use sp_core::{
+	offchain::{testing, OffchainDbExt, OffchainWorkerExt, TransactionPoolExt},
+	sr25519::{self, Signature},
+	H256,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, Hash, IdentifyAccount, IdentityLookup, Verify},
+	DispatchError, RuntimeAppPublic,
+};
+use std::{cell::RefCell, sync::Arc};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+pub type Extrinsic = TestXt<Call, ()>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		Ipfs: pallet_ipfs,
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		_public: <Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+// Randomness that only depends on the subject, so tests know which content gets challenged.
+pub struct TestRandomness;
+
+impl Randomness<H256, u64> for TestRandomness {
+	fn random(subject: &[u8]) -> (H256, u64) {
+		(BlakeTwo256::hash(subject), System::block_number())
+	}
+}
+
+// A result handler that records the results it is notified of, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IpfsResult {
+	Added(AccountId, Vec<u8>),
+	Cat(H256, Vec<u8>),
+	Abandoned(H256),
+}
+
+thread_local! {
+	static RESULTS: RefCell<Vec<IpfsResult>> = RefCell::new(Vec::new());
+}
+
+pub struct RecordResults;
+
+impl RecordResults {
+	// the results notified so far
+	pub fn take() -> Vec<IpfsResult> {
+		RESULTS.with(|results| results.take())
+	}
+}
+
+impl OnIpfsResult<AccountId, H256> for RecordResults {
+	fn on_added(owner: &AccountId, cid: &[u8]) {
+		RESULTS.with(|results| results.borrow_mut().push(IpfsResult::Added(*owner, cid.to_vec())));
+	}
+
+	fn on_cat_result(request: &H256, data: &[u8]) {
+		RESULTS.with(|results| results.borrow_mut().push(IpfsResult::Cat(*request, data.to_vec())));
+	}
+
+	fn on_abandoned(request: &H256) {
+		RESULTS.with(|results| results.borrow_mut().push(IpfsResult::Abandoned(*request)));
+	}
+}
+
+thread_local! {
+	// the operations promised so far, with their outcomes once they settled
+	static PROMISES: RefCell<Vec<(AsyncOp<H256>, Option<Result<H256, DispatchError>>)>> =
+		RefCell::new(Vec::new());
+}
+
+/// Keeps promises in `PROMISES`, their ids being their indices.
+pub struct RecordPromises;
+
+impl RecordPromises {
+	// the operations promised so far, with their outcomes once they settled
+	pub fn promises() -> Vec<(AsyncOp<H256>, Option<Result<H256, DispatchError>>)> {
+		PROMISES.with(|promises| promises.borrow().clone())
+	}
+
+	fn settle(op: &AsyncOp<H256>, outcome: Result<H256, DispatchError>) {
+		PROMISES.with(|promises| {
+			if let Some(promise) = promises
+				.borrow_mut()
+				.iter_mut()
+				.find(|(promised, settled)| promised == op && settled.is_none())
+			{
+				promise.1 = Some(outcome);
+			}
+		})
+	}
+}
+
+impl PromiseRegistry<H256> for RecordPromises {
+	fn create(op: AsyncOp<H256>) -> Option<PromiseId> {
+		PROMISES.with(|promises| {
+			let mut promises = promises.borrow_mut();
+			let pending = promises
+				.iter()
+				.position(|(promised, settled)| *promised == op && settled.is_none());
+			Some(pending.unwrap_or_else(|| {
+				promises.push((op, None));
+				promises.len() - 1
+			}) as PromiseId)
+		})
+	}
+
+	fn resolve(op: &AsyncOp<H256>, result: H256) {
+		Self::settle(op, Ok(result))
+	}
+
+	fn reject(op: &AsyncOp<H256>, error: DispatchError) {
+		Self::settle(op, Err(error))
+	}
+}
+
+// Only `protocol()` publishes protocol documents.
+pub struct ProtocolAccounts;
+
+impl Contains<AccountId> for ProtocolAccounts {
+	fn contains(who: &AccountId) -> bool {
+		*who == protocol()
+	}
+}
+
+parameter_types! {
+	pub const DefaultPinPolicy: PinMode = PinMode::Recursive;
+	pub ArchiveAccount: AccountId = protocol();
+}
+
+impl pallet_ipfs::Config for Test {
+	type AuthorityId = pallet_ipfs::crypto::OcwAuthId;
+	type Event = Event;
+	type Currency = Balances;
+	type ByteDeposit = ConstU64<1>;
+	type ProtocolAccounts = ProtocolAccounts;
+	type ArchiveAccount = ArchiveAccount;
+	type ContentStore = LocalStore;
+	type MaxQueueLen = ConstU32<10>;
+	type MaxBatchLen = ConstU32<8>;
+	type RequestTimeout = ConstU64<1_000>;
+	type MaxRequestTimeout = ConstU64<60_000>;
+	type FastDeadline = ConstU64<1_000>;
+	type BackgroundDeadline = ConstU64<10_000>;
+	type MaxRetries = ConstU32<2>;
+	type ReprovideInterval = ConstU64<100>;
+	type MaxRepoSize = ConstU64<1_000_000>;
+	type MaxAccessTtl = ConstU64<50>;
+	type ReplicationFactor = ConstU32<2>;
+	type AttestationInterval = ConstU64<10>;
+	type DefaultPinPolicy = DefaultPinPolicy;
+	type ResultTtl = ConstU64<10>;
+	type UnsignedPriority = ConstU64<{ 1 << 20 }>;
+	type UnsignedLongevity = ConstU64<5>;
+	type ValidateSchemas = ConstBool<false>;
+	type EraLength = ConstU64<100>;
+	type MaxBytesPerAccountPerEra = ConstU64<10_000_000>;
+	type MaxCommandsPerBlock = ConstU32<5>;
+	type MaxSchedulesPerAccount = ConstU32<2>;
+	type MaxBlockDataLen = ConstU32<1_024>;
+	type LeaseDepositPerBlock = ConstU64<1>;
+	type BumpFee = ConstU64<50>;
+	type DataBudget = ConstU32<10>;
+	type WorkersPerCommand = ConstU32<1>;
+	type ClaimGracePeriod = ConstU64<3>;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type ModerationOrigin = EnsureRoot<AccountId>;
+	type AppealOrigin = EnsureRoot<AccountId>;
+	type NamespaceAdminOrigin = AsEnsureOriginWithArg<EnsureRoot<AccountId>>;
+	type AppealDeposit = ConstU64<1_000>;
+	type AppealPeriod = ConstU64<10>;
+	type Randomness = TestRandomness;
+	type StorageBond = ConstU64<1_000>;
+	type ChallengeSlash = ConstU64<100>;
+	type ChallengesPerEra = ConstU32<1>;
+	type ChallengePeriod = ConstU64<5>;
+	type WeightInfo = ();
+	type OnResult = RecordResults;
+	type Promises = RecordPromises;
+	type Webhooks = ();
+}
+
+pub const INITIAL_BALANCE: u64 = 10_000_000;
+
+pub fn alice() -> AccountId {
+	sr25519::Public::from_raw([1; 32])
+}
+
+pub fn bob() -> AccountId {
+	sr25519::Public::from_raw([2; 32])
+}
+
+// an account without funds that adds content as a protocol account
+pub fn protocol() -> AccountId {
+	sr25519::Public::from_raw([3; 32])
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(alice(), INITIAL_BALANCE), (bob(), INITIAL_BALANCE)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	// events are only deposited from block 1 on
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
+
+// Test externalities with an offchain worker whose signing key belongs to a reporter; returns the
+// account of that key and the transaction pool the worker submits to.
+pub fn new_offchain_ext() -> (sp_io::TestExternalities, AccountId, Arc<RwLock<testing::PoolState>>)
+{
+	let (offchain, _) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	let keystore = KeyStore::new();
+	let reporter =
+		SyncCryptoStore::sr25519_generate_new(&keystore, pallet_ipfs::crypto::Public::ID, None)
+			.unwrap();
+
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	ext.register_extension(KeystoreExt(Arc::new(keystore)));
+	ext.execute_with(|| pallet_ipfs::Reporters::<Test>::put(vec![reporter]));
+	(ext, reporter, pool_state)
+}
+
+// Test externalities with an offchain worker whose keystore holds no signing key; returns the
+// transaction pool the worker submits to.
+pub fn new_keyless_offchain_ext() -> (sp_io::TestExternalities, Arc<RwLock<testing::PoolState>>) {
+	let (offchain, _) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	ext.register_extension(KeystoreExt(Arc::new(KeyStore::new())));
+	(ext, pool_state)
+}
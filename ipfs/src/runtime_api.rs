@@ -0,0 +1,50 @@
+// Runtime API for inspecting the pallet's queues and content registry.
+//
+// Wallets and indexers would otherwise have to decode raw storage keys, including the hashed
+// registry keys, to tell what the offchain workers still have to do. A runtime implements the API
+// by forwarding to `Pallet::pending_commands`, `Pallet::content`, `Pallet::peer_list` and
+// `Pallet::request_status`, and the node exposes it over RPC.
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::offchain::OpaqueMultiaddr;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+use crate::{
+	ConnectionCommand, ContentInfo, DataCommand, DhtCommand, Priority, PubsubCommand, RequestId,
+	RequestStatus,
+};
+
+/// The commands waiting in the pallet's queues.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PendingCommands<AccountId> {
+	/// Connections to establish or drop in the next offchain worker run.
+	pub connections: Vec<ConnectionCommand>,
+	/// DHT queries and announcements for the next offchain worker run.
+	pub dht: Vec<DhtCommand>,
+	/// Pubsub commands for the next offchain worker run.
+	pub pubsub: Vec<PubsubCommand>,
+	/// Data commands not yet acknowledged by an offchain worker, with their sequence number and
+	/// priority.
+	pub data: Vec<(u64, Priority, DataCommand<AccountId>)>,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to inspect the IPFS pallet's queues and content registry.
+	pub trait IpfsApi<AccountId, BlockNumber, Balance, Hash> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+		Balance: Codec,
+		Hash: Codec,
+	{
+		/// The commands waiting in the pallet's queues.
+		fn pending_commands() -> PendingCommands<AccountId>;
+		/// The registry entry of `cid`, if it is registered.
+		fn content_info(cid: Vec<u8>) -> Option<ContentInfo<AccountId, BlockNumber, Balance>>;
+		/// The peers the IPFS nodes are asked to stay connected to.
+		fn peer_list() -> Vec<OpaqueMultiaddr>;
+		/// The status of the queued data command `request`, until it expires.
+		fn request_status(request: RequestId) -> Option<RequestStatus<Hash>>;
+	}
+}
@@ -0,0 +1,112 @@
+// Cold storage snapshots of the content registry.
+//
+// If the chain's state is ever lost, the content pinned through the pallet outlives it on the IPFS
+// nodes, but nobody would know any more who owns what. On request, the offchain workers export the
+// content registry, the services' quotas and the replication assignments as a `RegistrySnapshot`
+// and add it to IPFS wrapped in a CARv1 file. A successor chain can then import the snapshot by
+// the `Cid` recorded on the old chain and rebuild its registry from it.
+//
+// Snapshots are canonical: their entries are sorted, and the CAR file holds a single raw block
+// whose `Cid` is the root, so every node exporting the same state arrives at the same `Cid`.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+use crate::{cid, ContentInfo, ServiceId, ServiceInfo};
+
+/// The most entries a single `restore_registry` call may carry.
+pub const MAX_RESTORE_PAGE: u32 = 50;
+
+// The DAG-CBOR header of a CARv1 file with a single root, up to the root `Cid`: a map of
+// `roots` (an array of one tag-42 byte string, a zero byte followed by the binary `Cid`) ...
+const HEADER_PREFIX: [u8; 12] =
+	[0xa2, 0x65, b'r', b'o', b'o', b't', b's', 0x81, 0xd8, 0x2a, 0x58, 0x25];
+// ... and `version` 1.
+const HEADER_SUFFIX: [u8; 9] = [0x67, b'v', b'e', b'r', b's', b'i', b'o', b'n', 0x01];
+// The length of a binary raw CIDv1 with a sha2-256 multihash.
+const RAW_CID_LEN: usize = 36;
+
+/// An entry of a registry snapshot.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum RegistryEntry<AccountId, BlockNumber, Balance> {
+	/// A registered `Cid` and its registry entry.
+	Content(Vec<u8>, ContentInfo<AccountId, BlockNumber, Balance>),
+	/// A service and its quota.
+	Service(ServiceId, ServiceInfo<AccountId>),
+	/// A pinned `Cid` and the reporters assigned to keep it pinned. Assignments are not restored,
+	/// since a successor chain has reporters of its own, but they tell which nodes to recover the
+	/// content from.
+	Assignment(Vec<u8>, Vec<AccountId>),
+}
+
+/// The registry as it was at the end of a block.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct RegistrySnapshot<AccountId, BlockNumber, Balance> {
+	/// The block whose state the snapshot was taken of.
+	pub exported_at: BlockNumber,
+	/// The content entries sorted by `Cid`, followed by the services sorted by id and the
+	/// assignments sorted by `Cid`.
+	pub entries: Vec<RegistryEntry<AccountId, BlockNumber, Balance>>,
+}
+
+/// A registry snapshot added to IPFS.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct RegistryExport<BlockNumber> {
+	/// The `Cid` of the CAR file holding the snapshot.
+	pub cid: Vec<u8>,
+	/// The number of entries in the snapshot.
+	pub entries: u32,
+	/// The block whose state the snapshot was taken of.
+	pub exported_at: BlockNumber,
+}
+
+/// A registry snapshot being restored.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct RegistryImport<BlockNumber> {
+	/// The snapshot, as recorded by the chain it was exported from.
+	pub export: RegistryExport<BlockNumber>,
+	/// The number of entries restored so far.
+	pub restored: u32,
+}
+
+/// Wrap `data` in a CARv1 file holding it as its single raw block.
+pub fn to_car(data: &[u8]) -> Vec<u8> {
+	let root = cid::raw_v1_binary(data);
+	let header_len = HEADER_PREFIX.len() + 1 + RAW_CID_LEN + HEADER_SUFFIX.len();
+
+	let mut car = Vec::with_capacity(header_len + RAW_CID_LEN + data.len() + 8);
+	cid::write_varint(&mut car, header_len as u64);
+	car.extend_from_slice(&HEADER_PREFIX);
+	car.push(0);
+	car.extend_from_slice(&root);
+	car.extend_from_slice(&HEADER_SUFFIX);
+	cid::write_varint(&mut car, (RAW_CID_LEN + data.len()) as u64);
+	car.extend_from_slice(&root);
+	car.extend_from_slice(data);
+	car
+}
+
+/// The data of a CARv1 file written by `to_car`, if its block matches the `Cid` it is stored
+/// under and that `Cid` is the file's root.
+pub fn from_car(car: &[u8]) -> Option<Vec<u8>> {
+	let (header_len, read) = cid::read_varint(car)?;
+	let header = car.get(read..read.checked_add(header_len as usize)?)?;
+	let rest = &car[read + header.len()..];
+	let root = header
+		.strip_prefix(&HEADER_PREFIX[..])?
+		.strip_prefix(&[0][..])?
+		.strip_suffix(&HEADER_SUFFIX[..])?;
+
+	let (section_len, read) = cid::read_varint(rest)?;
+	let section = rest.get(read..)?;
+	if section.len() as u64 != section_len || section_len < RAW_CID_LEN as u64 {
+		return None;
+	}
+	let (block_cid, data) = section.split_at(RAW_CID_LEN);
+	if block_cid != root || cid::raw_v1_binary(data) != block_cid {
+		return None;
+	}
+	Some(data.to_vec())
+}
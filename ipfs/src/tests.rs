@@ -0,0 +1,2050 @@
+use crate::{
+	blockspace, cid, encryption, incidents, mock::*, snapshot, BucketRole, CheckBlockData,
+	ConnectionCommand, ContentStore, DataCommand, DataJobs, DhtCommand, Error, IpfsCommand,
+	IpfsHandler, IpfsRequest, IpfsResponse, LocalStore, MemberQuota, NamespaceRole,
+	OffchainIncident, PinStatus, Priority, ReportPayloadOf, RequestStatus, ScheduledCommand,
+	StorageChallenge, Subsystem, WeightInfo, WorkerReport,
+};
+use codec::{Decode, Encode};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, GenesisBuild, Hooks, ReservableCurrency},
+	weights::{DispatchInfo, Weight},
+};
+use frame_system::offchain::SignedPayload;
+use mpesa_primitives::{AsyncOp, IndexFilter, PageRequest};
+use pallet_ocw_jobs::Jobs;
+use parking_lot::RwLock;
+use sp_core::{
+	offchain::{testing::PoolState, OpaqueMultiaddr, StorageKind},
+	sr25519, H256,
+};
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	traits::{BlakeTwo256, Hash, SignedExtension, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+	DispatchError,
+};
+use std::sync::Arc;
+
+const ADDR: &[u8] = b"/ip4/127.0.0.1/tcp/4001/p2p/QmPeer";
+
+// the pallet calls submitted by the offchain worker, and whether they were signed
+fn submitted(pool: &Arc<RwLock<PoolState>>) -> Vec<(bool, crate::Call<Test>)> {
+	pool.read()
+		.transactions
+		.iter()
+		.map(|tx| {
+			let tx = Extrinsic::decode(&mut &**tx).unwrap();
+			match tx.call {
+				Call::Ipfs(call) => (tx.signature.is_some(), call),
+				call => panic!("unexpected call submitted: {:?}", call),
+			}
+		})
+		.collect()
+}
+
+// the reports the offchain worker submitted through `submit_report`
+fn reports(pool: &Arc<RwLock<PoolState>>) -> Vec<WorkerReport<AccountId, u64>> {
+	submitted(pool)
+		.into_iter()
+		.filter_map(|(signed, call)| match call {
+			crate::Call::submit_report { payload, .. } if !signed => Some(payload.report),
+			_ => None,
+		})
+		.collect()
+}
+
+fn add_bytes(who: AccountId, data: &[u8]) {
+	assert_ok!(Ipfs::ipfs_add_bytes(Origin::signed(who), data.to_vec(), None, None, false));
+}
+
+// register content added by `who` the way the offchain worker reports it
+fn register(reporter: AccountId, who: AccountId, data: &[u8]) -> Vec<u8> {
+	let cid = cid::raw_v1(data);
+	assert_ok!(Ipfs::submit_cid(
+		Origin::signed(reporter),
+		who,
+		cid.clone(),
+		data.len() as u64,
+		None,
+		false
+	));
+	cid
+}
+
+// start the next block; blocks carry few extrinsics in these tests, so `on_idle` gets all the
+// weight it needs
+fn next_block() {
+	let block_number = System::block_number() + 1;
+	System::set_block_number(block_number);
+	Ipfs::on_initialize(block_number);
+	Ipfs::on_idle(block_number, Weight::MAX);
+}
+
+#[test]
+fn connection_requests_are_deduplicated() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Ipfs::ipfs_connect(Origin::signed(alice()), ADDR.to_vec()));
+		assert_ok!(Ipfs::ipfs_connect(Origin::signed(bob()), ADDR.to_vec()));
+
+		let connect = ConnectionCommand::ConnectTo(OpaqueMultiaddr(ADDR.to_vec()));
+		assert!(crate::ConnectionQueue::<Test>::get() == vec![connect]);
+		assert!(Ipfs::is_desired_peer(ADDR));
+	});
+}
+
+#[test]
+fn full_queues_reject_commands() {
+	new_test_ext().execute_with(|| {
+		let queued = (0..10u8)
+			.map(|i| ConnectionCommand::ConnectTo(OpaqueMultiaddr(vec![i])))
+			.collect();
+		crate::ConnectionQueue::<Test>::put(queued);
+
+		assert_noop!(
+			Ipfs::ipfs_connect(Origin::signed(alice()), ADDR.to_vec()),
+			Error::<Test>::QueueFull
+		);
+	});
+}
+
+#[test]
+fn commands_per_block_are_limited() {
+	new_test_ext().execute_with(|| {
+		for i in 0..5u8 {
+			assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), vec![i]));
+		}
+		assert_noop!(
+			Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), vec![5]),
+			Error::<Test>::QuotaExceeded
+		);
+		assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(bob()), vec![5]));
+
+		next_block();
+		assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), vec![6]));
+	});
+}
+
+#[test]
+fn transient_queues_are_cleared_every_block() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Ipfs::ipfs_connect(Origin::signed(alice()), ADDR.to_vec()));
+		assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), b"QmPeer".to_vec()));
+		assert_ok!(Ipfs::ipfs_pubsub_subscribe(Origin::signed(alice()), b"topic".to_vec()));
+
+		next_block();
+
+		assert!(crate::ConnectionQueue::<Test>::get().is_empty());
+		assert!(crate::DhtQueue::<Test>::get().is_empty());
+		assert!(crate::PubsubQueue::<Test>::get().is_empty());
+		// the desired peers and subscriptions outlive the commands
+		assert!(Ipfs::is_desired_peer(ADDR));
+		assert_eq!(Ipfs::pubsub_subscribers(b"topic".to_vec()), vec![alice()]);
+	});
+}
+
+#[test]
+fn data_commands_are_kept_until_acknowledged() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		add_bytes(alice(), b"world");
+
+		next_block();
+		assert!(crate::DataQueue::<Test>::contains_key(0));
+		assert_eq!(Ipfs::data_queue_head(), 0);
+
+		assert_ok!(Ipfs::ack_data_commands(Origin::signed(reporter), 1));
+		next_block();
+		assert!(!crate::DataQueue::<Test>::contains_key(0));
+		assert!(crate::DataQueue::<Test>::contains_key(1));
+		assert_eq!(Ipfs::data_queue_head(), 1);
+	});
+}
+
+#[test]
+fn acknowledgements_are_capped_at_the_queue_tail() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+
+		assert_ok!(Ipfs::ack_data_commands(Origin::signed(reporter), 10));
+		assert_eq!(crate::DataQueueAcked::<Test>::get(), 1);
+		assert_noop!(
+			Ipfs::ack_data_commands(Origin::signed(alice()), 1),
+			Error::<Test>::NotAReporter
+		);
+	});
+}
+
+#[test]
+fn maintenance_waits_for_spare_block_weight() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		assert_ok!(Ipfs::ack_data_commands(Origin::signed(reporter), 1));
+		let leased = cid::raw_v1(b"leased");
+		assert_ok!(Ipfs::submit_cid(
+			Origin::signed(reporter),
+			alice(),
+			leased.clone(),
+			6,
+			Some(1),
+			false
+		));
+
+		// a block without weight to spare leaves the pruning and the lease to a later one
+		System::set_block_number(2);
+		Ipfs::on_initialize(2);
+		assert_eq!(Ipfs::on_idle(2, 0), 0);
+		assert!(crate::DataQueue::<Test>::contains_key(0));
+		assert!(Ipfs::content(&leased).is_some());
+
+		System::set_block_number(3);
+		Ipfs::on_initialize(3);
+		Ipfs::on_idle(3, Weight::MAX);
+		assert_eq!(Ipfs::data_queue_head(), 1);
+		assert!(Ipfs::content(&leased).is_none());
+		System::assert_has_event(Event::Ipfs(crate::Event::LeaseExpired(leased)));
+		assert_eq!(Ipfs::maintenance_cursor(), 4);
+	});
+}
+
+#[test]
+fn queue_weights_are_refunded_for_short_queues() {
+	new_test_ext().execute_with(|| {
+		let info = Ipfs::ipfs_connect(Origin::signed(alice()), ADDR.to_vec()).unwrap();
+		assert_eq!(info.actual_weight, Some(<() as WeightInfo>::ipfs_connect(0)));
+		assert!(<() as WeightInfo>::ipfs_connect(0) < <() as WeightInfo>::ipfs_connect(10));
+
+		let info = Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), b"QmPeer".to_vec()).unwrap();
+		assert_eq!(info.actual_weight, Some(<() as WeightInfo>::ipfs_dht_find_peer(0)));
+		let info = Ipfs::ipfs_dht_provide(Origin::signed(alice()), cid::raw_v1(b"hello")).unwrap();
+		assert_eq!(info.actual_weight, Some(<() as WeightInfo>::ipfs_dht_provide(1)));
+
+		// a batch is refunded what its commands are
+		let commands = vec![IpfsCommand::Connect(b"/ip4/127.0.0.2/tcp/4001".to_vec())];
+		let info = Ipfs::ipfs_batch(Origin::signed(bob()), commands).unwrap();
+		assert_eq!(info.actual_weight, Some(<() as WeightInfo>::ipfs_connect(1)));
+	});
+}
+
+#[test]
+fn adding_data_reserves_a_deposit_per_byte() {
+	new_test_ext().execute_with(|| {
+		add_bytes(alice(), &[7; 100]);
+
+		assert_eq!(Balances::reserved_balance(alice()), 100);
+		assert_eq!(Balances::free_balance(alice()), INITIAL_BALANCE - 100);
+	});
+}
+
+#[test]
+fn protocol_accounts_add_without_deposits_or_quotas() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		// more adds than `MaxCommandsPerBlock`, from an account that couldn't pay a deposit
+		for i in 0..6u8 {
+			assert_ok!(<Ipfs as IpfsHandler<_>>::add_bytes(&protocol(), vec![i; 100]));
+		}
+		assert_eq!(Balances::reserved_balance(protocol()), 0);
+
+		let cid = register(reporter, protocol(), &[0; 100]);
+		assert_eq!(Ipfs::content(&cid).map(|content| content.deposit), Some(0));
+	});
+}
+
+#[test]
+fn removing_content_releases_its_deposit() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		let cid = register(reporter, alice(), b"hello");
+		assert_eq!(Ipfs::content(&cid).map(|c| c.deposit), Some(5));
+
+		assert_noop!(
+			Ipfs::ipfs_remove_block(Origin::signed(bob()), cid.clone()),
+			Error::<Test>::NotContentOwner
+		);
+		assert_ok!(Ipfs::ipfs_remove_block(Origin::signed(alice()), cid.clone()));
+
+		assert!(Ipfs::content(&cid).is_none());
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+	});
+}
+
+#[test]
+fn indexers_page_through_the_registry() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		add_bytes(alice(), b"a");
+		let a = register(reporter, alice(), b"a");
+		System::set_block_number(2);
+		add_bytes(alice(), b"b");
+		let b = register(reporter, alice(), b"b");
+		add_bytes(bob(), b"c");
+		let c = register(reporter, bob(), b"c");
+
+		let cids = |filter: IndexFilter<AccountId, u64>| {
+			let mut request = PageRequest { cursor: None, limit: 1 };
+			let mut cids = Vec::new();
+			loop {
+				let page = Ipfs::registry_page(&filter, &request);
+				cids.extend(page.items.into_iter().map(|(cid, _)| cid));
+				match page.next {
+					Some(next) => request.cursor = Some(next),
+					None => return cids,
+				}
+			}
+		};
+		let mut all = cids(IndexFilter::default());
+		all.sort();
+		let mut expected = vec![a.clone(), b.clone(), c.clone()];
+		expected.sort();
+		assert_eq!(all, expected);
+
+		assert_eq!(
+			cids(IndexFilter { account: Some(alice()), ..Default::default() }),
+			vec![a.clone(), b.clone()]
+		);
+		let filter = IndexFilter { account: Some(alice()), from: Some(2), ..Default::default() };
+		assert_eq!(cids(filter), vec![b]);
+		let filter = IndexFilter { to: Some(1), ..Default::default() };
+		assert_eq!(cids(filter), vec![a]);
+		assert_eq!(
+			cids(IndexFilter { cid: Some(c.clone()), ..Default::default() }),
+			vec![c.clone()]
+		);
+		let filter = IndexFilter { account: Some(alice()), cid: Some(c), ..Default::default() };
+		assert!(cids(filter).is_empty());
+	});
+}
+
+#[test]
+fn denied_content_is_refused_and_removed() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		let cid = register(reporter, alice(), b"hello");
+		assert_noop!(
+			Ipfs::deny_cid(Origin::signed(alice()), cid.clone()),
+			DispatchError::BadOrigin
+		);
+
+		// registered content is dropped and queued for removal
+		assert_ok!(Ipfs::deny_cid(Origin::root(), cid.clone()));
+		System::assert_has_event(Event::Ipfs(crate::Event::DeniedContentRemoved(
+			cid.clone(),
+			alice(),
+		)));
+		assert!(Ipfs::content(&cid).is_none());
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+		let queued = Ipfs::pending_commands()
+			.data
+			.into_iter()
+			.map(|(_, _, cmd)| cmd)
+			.collect::<Vec<_>>();
+		assert!(queued.contains(&DataCommand::RemoveBlock(cid.clone())));
+
+		next_block();
+		assert_noop!(
+			Ipfs::ipfs_add_bytes(Origin::signed(bob()), b"hello".to_vec(), None, None, false),
+			Error::<Test>::ContentDenied
+		);
+		assert_noop!(
+			Ipfs::ipfs_insert_pin(Origin::signed(bob()), cid.clone(), true),
+			Error::<Test>::ContentDenied
+		);
+
+		// content denied while its add was queued isn't registered once reported
+		add_bytes(bob(), b"late");
+		let late = cid::raw_v1(b"late");
+		assert_ok!(Ipfs::deny_cid(Origin::root(), late.clone()));
+		register(reporter, bob(), b"late");
+		assert!(Ipfs::content(&late).is_none());
+		assert_eq!(Balances::reserved_balance(bob()), 0);
+
+		assert_ok!(Ipfs::allow_cid(Origin::root(), cid.clone()));
+		System::assert_last_event(Event::Ipfs(crate::Event::CidAllowed(cid.clone())));
+		assert_noop!(Ipfs::allow_cid(Origin::root(), cid), Error::<Test>::NotDenied);
+		add_bytes(bob(), b"hello");
+	});
+}
+
+#[test]
+fn cancelling_an_upload_releases_its_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Ipfs::ipfs_begin_upload(
+			Origin::signed(alice()),
+			2,
+			BlakeTwo256::hash(b"helloworld")
+		));
+		assert_ok!(Ipfs::ipfs_upload_chunk(Origin::signed(alice()), 0, 0, b"hello".to_vec()));
+		assert_eq!(Balances::reserved_balance(alice()), 5);
+		assert_noop!(
+			Ipfs::ipfs_finalize_upload(Origin::signed(alice()), 0),
+			Error::<Test>::UploadIncomplete
+		);
+
+		assert_ok!(Ipfs::ipfs_cancel_upload(Origin::signed(alice()), 0));
+		assert!(Ipfs::upload(0).is_none());
+		assert!(!Ipfs::has_chunk(0, 0));
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+	});
+}
+
+#[test]
+fn block_data_quota_is_enforced() {
+	new_test_ext().execute_with(|| {
+		let add = |len: usize| {
+			let data = vec![0; len];
+			Call::Ipfs(crate::Call::ipfs_add_bytes {
+				data,
+				timeout: None,
+				priority: None,
+				encrypt: false,
+			})
+		};
+		let info = DispatchInfo::default();
+
+		assert_ok!(CheckBlockData::<Test>::new().pre_dispatch(&alice(), &add(600), &info, 600));
+		assert_eq!(
+			CheckBlockData::<Test>::new().pre_dispatch(&bob(), &add(600), &info, 600),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+		// transactions that could never fit are rejected by the pool
+		assert_eq!(
+			CheckBlockData::<Test>::new().validate(&bob(), &add(2_000), &info, 2_000),
+			Err(InvalidTransaction::ExhaustsResources.into())
+		);
+
+		next_block();
+		assert_ok!(CheckBlockData::<Test>::new().pre_dispatch(&bob(), &add(600), &info, 600));
+	});
+}
+
+#[test]
+fn offchain_worker_adds_data_and_reports_the_cid() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+
+		Ipfs::offchain_worker(1);
+
+		let cid = cid::raw_v1(b"hello");
+		assert!(matches!(
+			LocalStore::request(IpfsRequest::CatBytes(cid.clone()), None),
+			Ok(IpfsResponse::CatBytes(data)) if data == b"hello"
+		));
+		let report =
+			WorkerReport::Cid { owner: alice(), cid, size: 5, lease: None, encrypted: false };
+		assert!(reports(&pool).contains(&report));
+		assert!(submitted(&pool).contains(&(true, crate::Call::ack_data_commands { up_to: 1 })));
+	});
+}
+
+#[test]
+fn offchain_worker_processes_each_data_command_once() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+
+		Ipfs::offchain_worker(1);
+		System::set_block_number(2);
+		Ipfs::offchain_worker(2);
+
+		let reported = reports(&pool)
+			.into_iter()
+			.filter(|report| matches!(report, WorkerReport::Cid { .. }))
+			.count();
+		assert_eq!(reported, 1);
+	});
+}
+
+#[test]
+fn offchain_workers_share_the_data_queue() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		for i in 0..8u8 {
+			add_bytes(if i < 4 { alice() } else { bob() }, &[i]);
+		}
+		// a second reporter, whose key this node doesn't hold, assigned some of the commands
+		let (other, assigned) = (3..=u8::MAX)
+			.map(|i| {
+				let other = sr25519::Public::from_raw([i; 32]);
+				let reporters = [reporter, other];
+				(
+					other,
+					(0..8u64)
+						.map(|index| Ipfs::assigned_workers(index, &reporters))
+						.collect::<Vec<_>>(),
+				)
+			})
+			.find(|(other, assigned)| {
+				assigned.contains(&vec![*other]) && assigned.contains(&vec![reporter])
+			})
+			.unwrap();
+		crate::Reporters::<Test>::put(vec![reporter, other]);
+		let added = |pool: &Arc<RwLock<PoolState>>| {
+			let cids = reports(pool)
+				.into_iter()
+				.filter_map(|report| match report {
+					WorkerReport::Cid { cid, .. } => Some(cid),
+					_ => None,
+				})
+				.collect::<Vec<_>>();
+			(0..8u8).filter(|i| cids.contains(&cid::raw_v1(&[*i]))).collect::<Vec<_>>()
+		};
+
+		// each node only processes the commands assigned to it
+		Ipfs::offchain_worker(1);
+		let mine = (0..8u8).filter(|i| assigned[*i as usize] == vec![reporter]).collect::<Vec<_>>();
+		assert_eq!(added(&pool), mine);
+		let first_other =
+			assigned.iter().position(|workers| workers == &vec![other]).unwrap() as u64;
+		let cursor = StorageValueRef::persistent(crate::DATA_CURSOR).get::<u64>().ok().flatten();
+		assert_eq!(cursor.unwrap_or(0), first_other);
+
+		// until the grace period passed without the other reporter picking them up
+		System::set_block_number(3);
+		Ipfs::offchain_worker(3);
+		assert_eq!(added(&pool), mine);
+		System::set_block_number(4);
+		Ipfs::offchain_worker(4);
+		assert_eq!(added(&pool), (0..8u8).collect::<Vec<_>>());
+	});
+}
+
+#[test]
+fn offchain_worker_reports_cat_results() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		Ipfs::offchain_worker(1);
+
+		let cid = cid::raw_v1(b"hello");
+		System::set_block_number(2);
+		assert_ok!(Ipfs::ipfs_cat_bytes(Origin::signed(bob()), cid.clone(), None, None));
+		Ipfs::offchain_worker(2);
+
+		// the raw bytes are also cached for the node's RPC
+		let cached = sp_io::offchain::local_storage_get(
+			StorageKind::PERSISTENT,
+			&Ipfs::cached_content_key(&cid),
+		);
+		assert_eq!(cached, Some(b"hello".to_vec()));
+		let request = BlakeTwo256::hash_of(&DataCommand::<AccountId>::CatBytes(cid, None));
+		assert!(Ipfs::is_result_pending(request));
+		let result = crate::Call::submit_cat_result { request, data: b"hello".to_vec() };
+		assert!(submitted(&pool).contains(&(false, result)));
+	});
+}
+
+#[test]
+fn accounts_publish_and_resolve_ipns_names() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		// apply the offchain worker's signed report as the transaction pool would
+		let apply = |report: WorkerReport<AccountId, u64>| {
+			let (payload, signature) = submitted(&pool)
+				.into_iter()
+				.find_map(|(_, call)| match call {
+					crate::Call::submit_report { payload, signature }
+						if payload.report == report =>
+					{
+						Some((payload, signature))
+					},
+					_ => None,
+				})
+				.unwrap();
+			assert_ok!(Ipfs::submit_report(Origin::none(), payload, signature));
+		};
+		let cid = cid::raw_v1(b"v1");
+		assert_ok!(Ipfs::ipfs_ipns_publish(Origin::signed(alice()), cid.clone()));
+		System::assert_last_event(Event::Ipfs(crate::Event::QueuedIpnsPublish(
+			alice(),
+			cid.clone(),
+			0,
+		)));
+		assert_noop!(
+			Ipfs::ipfs_ipns_publish(Origin::signed(alice()), b"not a cid".to_vec()),
+			Error::<Test>::InvalidCid
+		);
+		Ipfs::offchain_worker(1);
+
+		// the name is bound to the account once the report is applied
+		let name = reports(&pool)
+			.into_iter()
+			.find_map(|report| match report {
+				WorkerReport::IpnsPublished { owner, name, cid: published } if owner == alice() => {
+					assert_eq!(published, cid);
+					Some(name)
+				},
+				_ => None,
+			})
+			.unwrap();
+		apply(WorkerReport::IpnsPublished { owner: alice(), name: name.clone(), cid: cid.clone() });
+		System::assert_last_event(Event::Ipfs(crate::Event::IpnsPublished(
+			name.clone(),
+			cid.clone(),
+		)));
+		let binding = Ipfs::ipns_name(alice()).unwrap();
+		assert_eq!((binding.name, binding.cid, binding.publisher), (name.clone(), cid, reporter));
+
+		// updates keep the name
+		next_block();
+		let updated = cid::raw_v1(b"v2");
+		assert_ok!(Ipfs::ipfs_ipns_publish(Origin::signed(alice()), updated.clone()));
+		assert_ok!(Ipfs::ipfs_ipns_resolve(Origin::signed(bob()), name.clone()));
+		System::assert_last_event(Event::Ipfs(crate::Event::QueuedIpnsResolve(
+			bob(),
+			name.clone(),
+			2,
+		)));
+		assert_noop!(
+			Ipfs::ipfs_ipns_resolve(Origin::signed(bob()), vec![]),
+			Error::<Test>::InvalidIpnsName
+		);
+		Ipfs::offchain_worker(2);
+
+		let published = WorkerReport::IpnsPublished {
+			owner: alice(),
+			name: name.clone(),
+			cid: updated.clone(),
+		};
+		let resolved = WorkerReport::IpnsResolved { name: name.clone(), cid: updated.clone() };
+		assert!(reports(&pool).contains(&published));
+		apply(resolved);
+		System::assert_last_event(Event::Ipfs(crate::Event::IpnsResolved(name, updated)));
+	});
+}
+
+#[test]
+fn offchain_worker_removes_blocks() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		Ipfs::offchain_worker(1);
+		let cid = register(reporter, alice(), b"hello");
+
+		System::set_block_number(2);
+		assert_ok!(Ipfs::ipfs_remove_block(Origin::signed(alice()), cid.clone()));
+		Ipfs::offchain_worker(2);
+
+		assert!(LocalStore::request(IpfsRequest::CatBytes(cid), None).is_err());
+	});
+}
+
+#[test]
+fn offchain_worker_answers_dht_queries() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), b"QmPeer".to_vec()));
+
+		Ipfs::offchain_worker(1);
+
+		let query = DhtCommand::FindPeer(b"QmPeer".to_vec());
+		assert!(submitted(&pool)
+			.contains(&(false, crate::Call::submit_dht_result { query, result: vec![] })));
+	});
+}
+
+#[test]
+fn offchain_worker_retries_failed_pins() {
+	let (mut ext, _, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		// the local store can only pin content it holds
+		let cid = cid::raw_v1(b"missing");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), cid.clone(), true));
+
+		Ipfs::offchain_worker(1);
+
+		// both the queued pin and the recovery scan of the first run failed to pin it
+		let retries = Jobs::<DataJobs<Test>>::pending();
+		assert_eq!(retries.len(), 2);
+		for retry in retries {
+			assert!(retry.job.cmd == DataCommand::InsertPin(cid.clone(), true));
+			assert_eq!((retry.attempts, retry.retry_at), (1, 3));
+		}
+	});
+}
+
+#[test]
+fn offchain_workers_report_request_statuses() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		let missing = cid::raw_v1(b"missing");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), missing.clone(), true));
+		System::assert_last_event(Event::Ipfs(crate::Event::QueuedDataToPin(alice(), missing, 1)));
+		assert_eq!(Ipfs::request_status(0), Some(RequestStatus::Queued));
+
+		Ipfs::offchain_worker(1);
+
+		// the add completed, the pin of content the local store doesn't hold is being retried
+		let statuses = submitted(&pool)
+			.into_iter()
+			.flat_map(|(_, call)| match call {
+				crate::Call::update_request_statuses { statuses } => statuses,
+				_ => vec![],
+			})
+			.collect::<Vec<_>>();
+		let added = BlakeTwo256::hash(&cid::raw_v1(b"hello"));
+		assert_eq!(statuses.len(), 2);
+		assert!(statuses.contains(&(0, RequestStatus::Done(added))));
+		assert!(statuses.contains(&(1, RequestStatus::Processing)));
+		assert_ok!(Ipfs::update_request_statuses(Origin::signed(reporter), statuses));
+		assert_eq!(Ipfs::request_status(1), Some(RequestStatus::Processing));
+
+		// completed commands stay completed, failed ones may still complete on another node
+		let failed = RequestStatus::Failed(Error::<Test>::RequestFailed.into());
+		let statuses = vec![
+			(0, RequestStatus::Processing),
+			(1, failed.clone()),
+			(7, RequestStatus::Processing),
+		];
+		assert_ok!(Ipfs::update_request_statuses(Origin::signed(reporter), statuses));
+		assert_eq!(Ipfs::request_status(0), Some(RequestStatus::Done(added)));
+		assert_eq!(Ipfs::request_status(1), Some(failed));
+		assert_eq!(Ipfs::request_status(7), None);
+		let pinned = BlakeTwo256::hash(&cid::raw_v1(b"missing"));
+		assert_ok!(Ipfs::update_request_statuses(
+			Origin::signed(reporter),
+			vec![(1, RequestStatus::Done(pinned))]
+		));
+		System::assert_last_event(Event::Ipfs(crate::Event::RequestStatusChanged(
+			1,
+			RequestStatus::Done(pinned),
+		)));
+
+		assert_noop!(
+			Ipfs::update_request_statuses(
+				Origin::signed(alice()),
+				vec![(1, RequestStatus::Processing)]
+			),
+			Error::<Test>::NotAReporter
+		);
+		let statuses = vec![(0, RequestStatus::Processing); 33];
+		assert_noop!(
+			Ipfs::update_request_statuses(Origin::signed(reporter), statuses),
+			Error::<Test>::TooManyStatusUpdates
+		);
+
+		// final statuses are kept for `ResultTtl` blocks
+		for _ in 0..9 {
+			next_block();
+		}
+		assert!(Ipfs::request_status(1).is_some());
+		next_block();
+		assert_eq!((Ipfs::request_status(0), Ipfs::request_status(1)), (None, None));
+	});
+}
+
+#[test]
+fn owners_appeal_denials_of_their_content() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+		let cid = register(reporter, alice(), b"hello");
+		assert_ok!(Ipfs::deny_cid(Origin::root(), cid.clone()));
+		let evidence = vec![cid::raw_v1(b"evidence")];
+		assert_noop!(
+			Ipfs::appeal_denial(Origin::signed(bob()), cid.clone(), evidence.clone()),
+			Error::<Test>::NotDeniedOwner
+		);
+		assert_noop!(
+			Ipfs::appeal_denial(Origin::signed(alice()), cid.clone(), vec![]),
+			Error::<Test>::InvalidEvidence
+		);
+
+		// a dismissed appeal keeps the denial and costs the deposit
+		let balance = Balances::total_balance(&alice());
+		assert_ok!(Ipfs::appeal_denial(Origin::signed(alice()), cid.clone(), evidence.clone()));
+		System::assert_last_event(Event::Ipfs(crate::Event::AppealFiled(cid.clone(), alice(), 11)));
+		assert_eq!(Balances::reserved_balance(alice()), 1_000);
+		assert_noop!(
+			Ipfs::appeal_denial(Origin::signed(alice()), cid.clone(), evidence.clone()),
+			Error::<Test>::AlreadyAppealed
+		);
+		assert_noop!(
+			Ipfs::rule_appeal(Origin::signed(bob()), cid.clone(), true),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(Ipfs::rule_appeal(Origin::root(), cid.clone(), false));
+		System::assert_last_event(Event::Ipfs(crate::Event::AppealDismissed(cid.clone(), 1_000)));
+		assert!(Ipfs::is_denied(&cid));
+		assert_eq!(Balances::total_balance(&alice()), balance - 1_000);
+
+		// an upheld appeal lifts the denial and releases the deposit
+		assert_ok!(Ipfs::appeal_denial(Origin::signed(alice()), cid.clone(), evidence.clone()));
+		assert_ok!(Ipfs::rule_appeal(Origin::root(), cid.clone(), true));
+		System::assert_has_event(Event::Ipfs(crate::Event::AppealUpheld(cid.clone())));
+		assert!(!Ipfs::is_denied(&cid));
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+		assert_noop!(
+			Ipfs::rule_appeal(Origin::root(), cid.clone(), true),
+			Error::<Test>::UnknownAppeal
+		);
+
+		// without a ruling by the deadline, the denial is lifted
+		add_bytes(alice(), b"other");
+		let other = register(reporter, alice(), b"other");
+		assert_ok!(Ipfs::deny_cid(Origin::root(), other.clone()));
+		assert_ok!(Ipfs::appeal_denial(Origin::signed(alice()), other.clone(), evidence));
+		for _ in 0..9 {
+			next_block();
+		}
+		assert!(Ipfs::is_denied(&other));
+		next_block();
+		System::assert_has_event(Event::Ipfs(crate::Event::AppealLapsed(other.clone())));
+		assert!(!Ipfs::is_denied(&other));
+		assert_eq!(Ipfs::appeal(&other), None);
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+	});
+}
+
+#[test]
+fn data_commands_and_dht_queries_are_promised() {
+	new_test_ext().execute_with(|| {
+		add_bytes(alice(), b"hello");
+		assert_ok!(Ipfs::ipfs_dht_find_peer(Origin::signed(alice()), b"QmPeer".to_vec()));
+		assert_ok!(Ipfs::ipfs_dht_find_providers(Origin::signed(alice()), cid::raw_v1(b"hello")));
+		let find_peer = BlakeTwo256::hash_of(&DhtCommand::FindPeer(b"QmPeer".to_vec()));
+		let find_providers = BlakeTwo256::hash_of(&DhtCommand::GetProviders(cid::raw_v1(b"hello")));
+		assert_eq!(
+			RecordPromises::promises(),
+			vec![
+				(AsyncOp::IpfsRequest(0), None),
+				(AsyncOp::DhtQuery(find_peer), None),
+				(AsyncOp::DhtQuery(find_providers), None),
+			]
+		);
+
+		let added = BlakeTwo256::hash(&cid::raw_v1(b"hello"));
+		assert_ok!(Ipfs::add_reporter(Origin::root(), bob()));
+		assert_ok!(Ipfs::update_request_statuses(
+			Origin::signed(bob()),
+			vec![(0, RequestStatus::Done(added))]
+		));
+		let addresses = vec![ADDR.to_vec()];
+		assert_ok!(Ipfs::submit_dht_result(
+			Origin::none(),
+			DhtCommand::FindPeer(b"QmPeer".to_vec()),
+			addresses.clone()
+		));
+
+		// the query without a result times out after `ResultTtl` blocks
+		for _ in 0..10 {
+			next_block();
+		}
+		assert_eq!(
+			RecordPromises::promises()[..3],
+			[
+				(AsyncOp::IpfsRequest(0), Some(Ok(added))),
+				(AsyncOp::DhtQuery(find_peer), Some(Ok(BlakeTwo256::hash_of(&addresses)))),
+				(
+					AsyncOp::DhtQuery(find_providers),
+					Some(Err(Error::<Test>::RequestTimeout.into()))
+				),
+			]
+		);
+	});
+}
+
+#[test]
+fn abandoned_commands_are_dead_lettered_for_operators() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		let missing = cid::raw_v1(b"missing");
+		assert_ok!(Ipfs::ipfs_cat_bytes(Origin::signed(alice()), missing.clone(), None, None));
+		let cmd = DataCommand::CatBytes(missing, None);
+		let payload_hash = BlakeTwo256::hash_of(&cmd);
+
+		// the worker gives up after `MaxRetries` retries
+		let mut block_number = 1;
+		while !submitted(&pool)
+			.iter()
+			.any(|(_, call)| matches!(call, crate::Call::report_abandoned { .. }))
+		{
+			assert!(block_number < 20);
+			System::set_block_number(block_number);
+			Ipfs::offchain_worker(block_number);
+			block_number += 1;
+		}
+		let reason = Error::<Test>::RequestFailed.into();
+		let abandoned = crate::Call::report_abandoned {
+			cmd: cmd.clone(),
+			request: Some(0),
+			attempts: 3,
+			reason,
+		};
+		assert!(submitted(&pool).contains(&(true, abandoned)));
+
+		let abandon =
+			|| Ipfs::report_abandoned(Origin::signed(reporter), cmd.clone(), Some(0), 3, reason);
+		assert_ok!(abandon());
+		System::assert_last_event(Event::Ipfs(crate::Event::OperationDeadLettered(
+			0,
+			payload_hash,
+			reason,
+		)));
+		let letter = Ipfs::dead_letter(0).unwrap();
+		assert_eq!(
+			(letter.caller, letter.cmd, letter.reason),
+			(Some(alice()), cmd.clone(), reason)
+		);
+		// other workers giving up on it too don't add it again
+		assert_ok!(abandon());
+		assert_eq!(Ipfs::dead_letter_count(), 1);
+
+		assert_noop!(
+			Ipfs::replay_dead_letter(Origin::signed(alice()), 0),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(Ipfs::replay_dead_letter(Origin::root(), 1), Error::<Test>::UnknownDeadLetter);
+		assert_ok!(Ipfs::replay_dead_letter(Origin::root(), 0));
+		System::assert_last_event(Event::Ipfs(crate::Event::DeadLetterReplayed(0, 1)));
+		assert_eq!(Ipfs::request_caller(1), Some(alice()));
+		assert!(Ipfs::is_result_pending(payload_hash));
+		assert_eq!((Ipfs::dead_letter(0), Ipfs::dead_letter_count()), (None, 0));
+
+		// commands another worker completed aren't dead-lettered
+		let done = vec![(1, RequestStatus::Done(payload_hash))];
+		assert_ok!(Ipfs::update_request_statuses(Origin::signed(reporter), done));
+		assert_ok!(Ipfs::report_abandoned(
+			Origin::signed(reporter),
+			cmd.clone(),
+			Some(1),
+			3,
+			reason
+		));
+		assert_eq!(Ipfs::dead_letter(1), None);
+
+		for request in 2..4 {
+			assert_ok!(Ipfs::report_abandoned(
+				Origin::signed(reporter),
+				cmd.clone(),
+				Some(request),
+				3,
+				reason
+			));
+		}
+		assert_noop!(Ipfs::purge_dead_letters(Origin::signed(alice())), DispatchError::BadOrigin);
+		assert_ok!(Ipfs::purge_dead_letters(Origin::root()));
+		System::assert_last_event(Event::Ipfs(crate::Event::DeadLettersPurged(2)));
+		assert_eq!((Ipfs::dead_letter(2), Ipfs::dead_letter_count()), (None, 0));
+	});
+}
+
+#[test]
+fn submitters_bump_their_pending_requests() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let missing = cid::raw_v1(b"missing");
+		let low = Some(Priority::Low);
+		assert_ok!(Ipfs::ipfs_cat_bytes(Origin::signed(alice()), missing.clone(), None, low));
+		let cmd = DataCommand::CatBytes(missing, None);
+
+		assert_noop!(Ipfs::bump_request(Origin::signed(alice()), 1), Error::<Test>::UnknownRequest);
+		assert_noop!(Ipfs::bump_request(Origin::signed(bob()), 0), Error::<Test>::NotRequestCaller);
+
+		// a queued command moves up a class per bump, and gets the longest deadline
+		assert_ok!(Ipfs::bump_request(Origin::signed(alice()), 0));
+		System::assert_last_event(Event::Ipfs(crate::Event::RequestBumped(alice(), 0, 50)));
+		assert_eq!(crate::DataQueue::<Test>::get(0), Some((Priority::Normal, cmd.clone())));
+		assert_eq!(Ipfs::requested_timeout(Some(0), &cmd), Some(60_000));
+		assert_ok!(Ipfs::bump_request(Origin::signed(alice()), 0));
+		assert_eq!(crate::DataQueue::<Test>::get(0), Some((Priority::High, cmd.clone())));
+		assert_noop!(Ipfs::bump_request(Origin::signed(alice()), 0), Error::<Test>::AlreadyBumped);
+		assert_eq!(Balances::free_balance(alice()), INITIAL_BALANCE - 100);
+
+		// the local store doesn't hold the content, so the cat is retried from block 3 on
+		Ipfs::offchain_worker(1);
+		let processing = vec![(0, RequestStatus::Processing)];
+		assert_ok!(Ipfs::update_request_statuses(Origin::signed(reporter), processing));
+
+		// bumping a stuck command has it retried right away
+		System::set_block_number(2);
+		assert_ok!(Ipfs::bump_request(Origin::signed(alice()), 0));
+		Ipfs::offchain_worker(2);
+		let retries = Jobs::<DataJobs<Test>>::pending();
+		let retry = retries.iter().find(|retry| retry.job.request == Some(0)).unwrap();
+		assert_eq!((retry.attempts, retry.retry_at), (2, 6));
+
+		let done = vec![(0, RequestStatus::Done(BlakeTwo256::hash_of(&cmd)))];
+		assert_ok!(Ipfs::update_request_statuses(Origin::signed(reporter), done));
+		assert_noop!(
+			Ipfs::bump_request(Origin::signed(alice()), 0),
+			Error::<Test>::RequestNotPending
+		);
+	});
+}
+
+#[test]
+fn encryption_round_trips_and_detects_tampering() {
+	let key = [9; 32];
+	let data = b"a payload longer than a single keystream block".to_vec();
+
+	let ciphertext = encryption::encrypt(&key, &data);
+	assert_eq!(ciphertext.len(), data.len() + 32);
+	assert_eq!(ciphertext, encryption::encrypt(&key, &data));
+	assert_eq!(encryption::decrypt(&key, &ciphertext), Some(data));
+
+	let mut tampered = ciphertext.clone();
+	tampered[40] ^= 1;
+	assert_eq!(encryption::decrypt(&key, &tampered), None);
+	assert_eq!(encryption::decrypt(&[8; 32], &ciphertext), None);
+}
+
+#[test]
+fn offchain_worker_encrypts_private_data() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		StorageValueRef::persistent(encryption::KEY).set(&[9u8; 32]);
+		assert_ok!(Ipfs::ipfs_add_bytes(
+			Origin::signed(alice()),
+			b"secret".to_vec(),
+			None,
+			None,
+			true
+		));
+
+		Ipfs::offchain_worker(1);
+
+		let ciphertext = encryption::encrypt(&[9; 32], b"secret");
+		let cid = cid::raw_v1(&ciphertext);
+		let owner = alice();
+		let report =
+			WorkerReport::Cid { owner, cid: cid.clone(), size: 6, lease: None, encrypted: true };
+		assert!(reports(&pool).contains(&report));
+
+		// cat results of encrypted content are decrypted off-chain only
+		assert_ok!(Ipfs::submit_cid(Origin::signed(reporter), alice(), cid.clone(), 6, None, true));
+		assert!(Ipfs::is_encrypted(&cid));
+		System::set_block_number(2);
+		assert_ok!(Ipfs::ipfs_cat_bytes(Origin::signed(alice()), cid.clone(), None, None));
+		Ipfs::offchain_worker(2);
+
+		// the raw bytes are also cached for the node's RPC
+		let cached = sp_io::offchain::local_storage_get(
+			StorageKind::PERSISTENT,
+			&Ipfs::cached_content_key(&cid),
+		);
+		assert_eq!(cached, Some(b"hello".to_vec()));
+		let request = BlakeTwo256::hash_of(&DataCommand::<AccountId>::CatBytes(cid, None));
+		let result = crate::Call::submit_cat_result { request, data: ciphertext };
+		assert!(submitted(&pool).contains(&(false, result)));
+		let mut key = b"ipfs::decrypted::".to_vec();
+		key.extend_from_slice(request.as_ref());
+		assert_eq!(
+			StorageValueRef::persistent(&key).get::<Vec<u8>>().ok().flatten(),
+			Some(b"secret".to_vec())
+		);
+	});
+}
+
+#[test]
+fn encrypted_adds_wait_for_a_key() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		assert_ok!(Ipfs::ipfs_add_bytes(
+			Origin::signed(alice()),
+			b"secret".to_vec(),
+			None,
+			None,
+			true
+		));
+
+		Ipfs::offchain_worker(1);
+
+		assert!(!reports(&pool).iter().any(|report| matches!(report, WorkerReport::Cid { .. })));
+		assert_eq!(Jobs::<DataJobs<Test>>::pending().len(), 1);
+	});
+}
+
+#[test]
+fn offchain_failures_are_reported_as_incidents() {
+	let (mut ext, _, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		assert_ok!(Ipfs::ipfs_add_bytes(
+			Origin::signed(alice()),
+			b"secret".to_vec(),
+			None,
+			None,
+			true
+		));
+
+		Ipfs::offchain_worker(1);
+
+		let cmd = DataCommand::AddEncrypted(alice(), b"secret".to_vec(), None);
+		let expected = OffchainIncident {
+			subsystem: Subsystem::Ipfs,
+			code: Error::<Test>::NoEncryptionKey.into(),
+			correlation_id: BlakeTwo256::hash_of(&cmd),
+			block: 1,
+		};
+		let reported = submitted(&pool).into_iter().find_map(|(signed, call)| match call {
+			crate::Call::report_incidents { incidents } if signed => Some(incidents),
+			_ => None,
+		});
+		assert_eq!(reported, Some(vec![expected]));
+	});
+}
+
+#[test]
+fn only_the_most_recent_incidents_are_kept() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let incident = |block| OffchainIncident {
+			subsystem: Subsystem::Ipfs,
+			code: Error::<Test>::RequestTimeout.into(),
+			correlation_id: Default::default(),
+			block,
+		};
+		let batch = |from: u64| {
+			(from..from + incidents::MAX_PER_REPORT as u64)
+				.map(incident)
+				.collect::<Vec<_>>()
+		};
+
+		assert_noop!(
+			Ipfs::report_incidents(Origin::signed(alice()), batch(0)),
+			Error::<Test>::NotAReporter
+		);
+		let mut too_many = batch(0);
+		too_many.push(incident(0));
+		assert_noop!(
+			Ipfs::report_incidents(Origin::signed(reporter), too_many),
+			Error::<Test>::TooManyIncidents
+		);
+
+		let mut from = 0;
+		while (from as usize) < incidents::MAX_RECENT + 1 {
+			assert_ok!(Ipfs::report_incidents(Origin::signed(reporter), batch(from)));
+			from += incidents::MAX_PER_REPORT as u64;
+		}
+		let recent = Ipfs::recent_incidents();
+		assert_eq!(recent.len(), incidents::MAX_RECENT);
+		assert_eq!(recent.last().unwrap().1.block, from - 1);
+		assert_eq!(recent.first().unwrap().1.block, from - incidents::MAX_RECENT as u64);
+
+		// the pruned incidents are archived to IPFS once they fill a batch
+		let pruned = from as usize - incidents::MAX_RECENT;
+		assert_eq!(Ipfs::pruned_incidents().len(), pruned);
+		assert_eq!(Ipfs::incident_archive_count(), 0);
+		while (from as usize) < incidents::MAX_RECENT + incidents::ARCHIVE_BATCH {
+			assert_ok!(Ipfs::report_incidents(Origin::signed(reporter), batch(from)));
+			from += incidents::MAX_PER_REPORT as u64;
+		}
+		let archived = (0..incidents::ARCHIVE_BATCH as u64)
+			.map(|block| (reporter, incident(block)))
+			.collect::<Vec<_>>();
+		let cid = cid::raw_v1(&archived.encode());
+		System::assert_has_event(Event::Ipfs(crate::Event::IncidentsArchived(0, cid.clone())));
+		assert_eq!(Ipfs::incident_archive_count(), 1);
+		assert_eq!(Ipfs::incident_archive(0), Some(cid));
+		assert_eq!(
+			Ipfs::pruned_incidents().first().map(|(_, incident)| incident.block),
+			Some(incidents::ARCHIVE_BATCH as u64)
+		);
+	});
+}
+
+#[test]
+fn pinners_are_assigned_deterministically() {
+	new_test_ext().execute_with(|| {
+		let reporters = (1..=5).map(|i| sr25519::Public::from_raw([i; 32])).collect::<Vec<_>>();
+		let cid = cid::raw_v1(b"content");
+
+		let assigned = Ipfs::assigned_pinners(&cid, &reporters);
+		assert_eq!(assigned.len(), 2);
+		assert_ne!(assigned[0], assigned[1]);
+		assert!(assigned.iter().all(|who| reporters.contains(who)));
+		// the order of the reporter set doesn't matter
+		let reversed = reporters.iter().rev().cloned().collect::<Vec<_>>();
+		assert_eq!(Ipfs::assigned_pinners(&cid, &reversed), assigned);
+		// every `Cid` is assigned to all reporters when there are too few of them
+		assert_eq!(Ipfs::assigned_pinners(&cid, &reporters[..1]), vec![reporters[0]]);
+	});
+}
+
+#[test]
+fn content_without_recent_attestations_is_under_replicated() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let cid = register(reporter, alice(), b"content");
+		assert_ok!(Ipfs::attest_pins(
+			Origin::signed(reporter),
+			vec![cid.clone(), cid::raw_v1(b"unknown")]
+		));
+		System::assert_last_event(Event::Ipfs(crate::Event::PinsAttested(reporter, 1)));
+
+		// the attestation covers two intervals
+		while System::block_number() < 21 {
+			next_block();
+		}
+		assert!(!Ipfs::is_under_replicated(&cid));
+		next_block();
+		assert!(Ipfs::is_under_replicated(&cid));
+		System::assert_has_event(Event::Ipfs(crate::Event::UnderReplicated(cid.clone())));
+
+		assert_ok!(Ipfs::attest_pins(Origin::signed(reporter), vec![cid.clone()]));
+		next_block();
+		assert!(!Ipfs::is_under_replicated(&cid));
+		System::assert_has_event(Event::Ipfs(crate::Event::ReplicationRestored(cid)));
+	});
+}
+
+#[test]
+fn offchain_worker_attests_assigned_pins() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"content");
+		Ipfs::offchain_worker(1);
+		let cid = register(reporter, alice(), b"content");
+
+		Ipfs::offchain_worker(10);
+
+		let attested = reports(&pool).into_iter().find_map(|report| match report {
+			WorkerReport::Attestations(cids) => Some(cids),
+			_ => None,
+		});
+		assert_eq!(attested, Some(vec![cid]));
+	});
+}
+
+#[test]
+fn offchain_workers_without_a_signing_key_skip_their_reports() {
+	let (mut ext, pool) = new_keyless_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"hello");
+
+		Ipfs::offchain_worker(1);
+		Ipfs::offchain_worker(2);
+
+		// the command is left to the nodes that can report its result
+		let cat = LocalStore::request(IpfsRequest::CatBytes(cid::raw_v1(b"hello")), None);
+		assert!(cat.is_err());
+		assert!(submitted(&pool).is_empty());
+		let missing = StorageValueRef::persistent(crate::SIGNING_KEY_MISSING).get::<bool>();
+		assert_eq!(missing, Ok(Some(true)));
+
+		// stats are still shared, as plain unsigned transactions
+		Ipfs::offchain_worker(5);
+		let stats = submitted(&pool)
+			.into_iter()
+			.find(|(_, call)| matches!(call, crate::Call::submit_node_stats { .. }));
+		assert!(matches!(stats, Some((false, _))));
+	});
+}
+
+#[test]
+fn reporters_that_stop_reporting_are_gauged() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		// apply the stats report the offchain worker submitted in `block_number`
+		let report_stats = |block_number: u64| {
+			Ipfs::offchain_worker(block_number);
+			let (payload, signature) = submitted(&pool)
+				.into_iter()
+				.find_map(|(_, call)| match call {
+					crate::Call::submit_report { payload, signature }
+						if payload.block_number == block_number =>
+					{
+						Some((payload, signature))
+					},
+					_ => None,
+				})
+				.unwrap();
+			assert_ok!(Ipfs::submit_report(Origin::none(), payload, signature));
+		};
+		assert_ok!(Ipfs::add_reporter(Origin::root(), bob()));
+
+		run_to_block(5);
+		report_stats(5);
+		run_to_block(20);
+		assert!(Ipfs::unreported_workers().is_empty());
+
+		// neither reported in the last window
+		run_to_block(40);
+		assert_eq!(Ipfs::unreported_workers(), vec![reporter, bob()]);
+		System::assert_has_event(Event::Ipfs(crate::Event::WorkersUnreported(vec![
+			reporter,
+			bob(),
+		])));
+
+		report_stats(40);
+		run_to_block(60);
+		assert_eq!(Ipfs::unreported_workers(), vec![bob()]);
+
+		assert_ok!(Ipfs::remove_reporter(Origin::root(), bob()));
+		assert!(Ipfs::unreported_workers().is_empty());
+	});
+}
+
+// a funded reporter with a storage bond
+fn bonded(reporter: AccountId) {
+	Balances::make_free_balance_be(&reporter, INITIAL_BALANCE);
+	assert_ok!(Ipfs::bond_storage(Origin::signed(reporter)));
+}
+
+fn run_to_block(block_number: u64) {
+	while System::block_number() < block_number {
+		next_block();
+	}
+}
+
+#[test]
+fn storage_bonds_are_reserved_until_unbonded() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		assert_noop!(Ipfs::bond_storage(Origin::signed(alice())), Error::<Test>::NotAReporter);
+		bonded(reporter);
+		assert_noop!(Ipfs::bond_storage(Origin::signed(reporter)), Error::<Test>::AlreadyBonded);
+		assert_eq!(Balances::reserved_balance(&reporter), 1_000);
+
+		crate::OpenChallenges::<Test>::insert(&reporter, 1);
+		assert_noop!(Ipfs::unbond_storage(Origin::signed(reporter)), Error::<Test>::ChallengesOpen);
+		crate::OpenChallenges::<Test>::remove(&reporter);
+
+		assert_ok!(Ipfs::unbond_storage(Origin::signed(reporter)));
+		assert_eq!(Balances::reserved_balance(&reporter), 0);
+		assert_noop!(Ipfs::unbond_storage(Origin::signed(reporter)), Error::<Test>::NotBonded);
+	});
+}
+
+#[test]
+fn offchain_worker_answers_storage_challenges() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		let data = b"challenged content".to_vec();
+		add_bytes(alice(), &data);
+		Ipfs::offchain_worker(1);
+		let cid = register(reporter, alice(), &data);
+		bonded(reporter);
+
+		// the first era starts in block 100
+		run_to_block(100);
+		let challenge = Ipfs::challenge(0).unwrap();
+		assert_eq!(
+			(challenge.cid.clone(), challenge.pinners.clone()),
+			(cid.clone(), vec![reporter])
+		);
+		System::assert_has_event(Event::Ipfs(crate::Event::ChallengeIssued(0, cid)));
+
+		Ipfs::offchain_worker(100);
+
+		let start = challenge.offset as usize;
+		let end = (start + challenge.len as usize).min(data.len());
+		let expected = Ipfs::storage_proof(&challenge.seed, &data[start..end]);
+		let response = submitted(&pool).into_iter().find_map(|(signed, call)| match call {
+			crate::Call::respond_challenge { challenge: 0, response } if signed => Some(response),
+			_ => None,
+		});
+		assert_eq!(response, Some(expected));
+
+		assert_ok!(Ipfs::respond_challenge(Origin::signed(reporter), 0, expected));
+		assert_noop!(
+			Ipfs::respond_challenge(Origin::signed(reporter), 0, expected),
+			Error::<Test>::AlreadyResponded
+		);
+		run_to_block(105);
+		System::assert_has_event(Event::Ipfs(crate::Event::ChallengePassed(0, reporter)));
+		assert_eq!(Ipfs::storage_bond(&reporter), Some(1_000));
+		assert_eq!(Ipfs::open_challenges(&reporter), 0);
+	});
+}
+
+#[test]
+fn missed_and_outvoted_challenge_responses_are_slashed() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let charlie = sr25519::Public::from_raw([3; 32]);
+		crate::Reporters::<Test>::put(vec![reporter, alice(), bob(), charlie]);
+		for who in [reporter, alice(), bob(), charlie] {
+			bonded(who);
+			crate::OpenChallenges::<Test>::insert(&who, 1);
+		}
+		let cid = register(reporter, alice(), b"content");
+		let challenge = StorageChallenge {
+			cid,
+			offset: 0,
+			len: 7,
+			seed: H256::repeat_byte(1),
+			deadline: 3,
+			pinners: vec![reporter, alice(), bob(), charlie],
+		};
+		crate::Challenges::<Test>::insert(0, challenge);
+		crate::ChallengeDeadlines::<Test>::append(3, 0);
+
+		let proof = Ipfs::storage_proof(&H256::repeat_byte(1), b"content");
+		assert_ok!(Ipfs::respond_challenge(Origin::signed(reporter), 0, proof));
+		assert_ok!(Ipfs::respond_challenge(Origin::signed(alice()), 0, proof));
+		assert_ok!(Ipfs::respond_challenge(Origin::signed(bob()), 0, H256::zero()));
+		assert_noop!(
+			Ipfs::respond_challenge(Origin::signed(alice()), 1, proof),
+			Error::<Test>::UnknownChallenge
+		);
+
+		run_to_block(3);
+		assert_eq!(Ipfs::storage_bond(&reporter), Some(1_000));
+		assert_eq!(Ipfs::storage_bond(&alice()), Some(1_000));
+		assert_eq!(Ipfs::storage_bond(&bob()), Some(900));
+		assert_eq!(Ipfs::storage_bond(&charlie), Some(900));
+		System::assert_has_event(Event::Ipfs(crate::Event::ChallengeFailed(0, charlie, 100)));
+		assert!(Ipfs::challenge(0).is_none());
+		assert_eq!(Ipfs::open_challenges(&bob()), 0);
+	});
+}
+
+#[test]
+fn owners_grant_temporary_access() {
+	new_test_ext().execute_with(|| {
+		let cid = cid::raw_v1(b"private");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), cid.clone(), true));
+		assert!(Ipfs::has_access(&cid, &alice()));
+		assert!(!Ipfs::has_access(&cid, &bob()));
+
+		assert_noop!(
+			Ipfs::grant_access(Origin::signed(bob()), cid.clone(), bob(), 10),
+			Error::<Test>::NotContentOwner
+		);
+		assert_noop!(
+			Ipfs::grant_access(Origin::signed(alice()), cid.clone(), bob(), 51),
+			Error::<Test>::InvalidAccessTtl
+		);
+		assert_ok!(Ipfs::grant_access(Origin::signed(alice()), cid.clone(), bob(), 10));
+		System::assert_last_event(Event::Ipfs(crate::Event::AccessGranted(cid.clone(), bob(), 11)));
+		assert!(Ipfs::has_access(&cid, &bob()));
+
+		// renewing the grant outlives the first expiry
+		next_block();
+		assert_ok!(Ipfs::grant_access(Origin::signed(alice()), cid.clone(), bob(), 20));
+		run_to_block(11);
+		assert!(Ipfs::has_access(&cid, &bob()));
+		run_to_block(22);
+		assert!(!Ipfs::has_access(&cid, &bob()));
+		assert_eq!(Ipfs::access_grant(&cid, &bob()), None);
+
+		assert_ok!(Ipfs::grant_access(Origin::signed(alice()), cid.clone(), bob(), 10));
+		assert_ok!(Ipfs::revoke_access(Origin::signed(alice()), cid.clone(), bob()));
+		assert!(!Ipfs::has_access(&cid, &bob()));
+		assert_noop!(
+			Ipfs::revoke_access(Origin::signed(alice()), cid, bob()),
+			Error::<Test>::NoAccessGrant
+		);
+	});
+}
+
+#[test]
+fn queues_and_peers_can_be_inspected() {
+	new_test_ext().execute_with(|| {
+		let bootstrap = OpaqueMultiaddr(b"/ip4/127.0.0.1/tcp/4002/p2p/QmBoot".to_vec());
+		assert_ok!(Ipfs::set_bootstrap_peers(Origin::root(), vec![bootstrap.clone()]));
+		assert_ok!(Ipfs::ipfs_connect(Origin::signed(alice()), ADDR.to_vec()));
+		add_bytes(alice(), b"hello");
+
+		let pending = Ipfs::pending_commands();
+		assert!(pending
+			.connections
+			.contains(&ConnectionCommand::ConnectTo(OpaqueMultiaddr(ADDR.to_vec()))));
+		assert_eq!(pending.data.len(), 1);
+		assert_eq!(
+			pending.data[0].2,
+			DataCommand::AddBytes(alice(), b"hello".to_vec(), None, None)
+		);
+		assert_eq!(Ipfs::peer_list(), vec![bootstrap, OpaqueMultiaddr(ADDR.to_vec())]);
+	});
+}
+
+#[test]
+fn full_repos_evict_the_oldest_unleased_content() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"old");
+		let old = register(reporter, alice(), b"old");
+		next_block();
+		let newer = register(reporter, alice(), b"newer");
+		let leased = cid::raw_v1(b"leased");
+		assert_ok!(Ipfs::submit_cid(
+			Origin::signed(reporter),
+			alice(),
+			leased.clone(),
+			6,
+			Some(100),
+			false
+		));
+
+		assert_eq!(Ipfs::eviction_candidates(3), vec![old.clone()]);
+		assert_eq!(Ipfs::eviction_candidates(4), vec![old.clone(), newer.clone()]);
+
+		assert_noop!(
+			Ipfs::evict_content(Origin::signed(alice()), vec![old.clone()]),
+			Error::<Test>::NotAReporter
+		);
+		assert_ok!(Ipfs::evict_content(
+			Origin::signed(reporter),
+			vec![old.clone(), leased.clone()]
+		));
+		System::assert_has_event(Event::Ipfs(crate::Event::EvictedContent(old.clone())));
+		assert!(Ipfs::content(&old).is_none());
+		assert!(Ipfs::content(&leased).is_some());
+		assert_eq!(Balances::reserved_balance(alice()), 0);
+		let queued = Ipfs::pending_commands()
+			.data
+			.into_iter()
+			.map(|(_, _, cmd)| cmd)
+			.collect::<Vec<_>>();
+		assert!(queued.contains(&DataCommand::RemoveBlock(old)));
+		assert!(queued.contains(&DataCommand::CollectGarbage));
+	});
+}
+
+#[test]
+fn only_root_can_trigger_garbage_collection() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(Ipfs::ipfs_run_gc(Origin::signed(alice())), DispatchError::BadOrigin);
+		assert_ok!(Ipfs::ipfs_run_gc(Origin::root()));
+		System::assert_last_event(Event::Ipfs(crate::Event::QueuedGarbageCollection(0)));
+		assert_eq!(Ipfs::pending_commands().data[0].2, DataCommand::CollectGarbage);
+	});
+}
+
+#[test]
+fn batches_queue_all_commands_or_none() {
+	new_test_ext().execute_with(|| {
+		let cid = cid::raw_v1(b"pinned");
+		let commands = vec![
+			IpfsCommand::Connect(ADDR.to_vec()),
+			IpfsCommand::AddBytes(b"hello".to_vec()),
+			IpfsCommand::InsertPin(cid.clone(), true),
+		];
+		let call = crate::Call::<Test>::ipfs_batch { commands: commands.clone() };
+		assert_eq!(blockspace::data_len_of::<Test>(&call), Some(5));
+		assert_eq!(
+			Ipfs::batch_weight(&commands),
+			<() as WeightInfo>::ipfs_connect(10)
+				+ <() as WeightInfo>::ipfs_add_bytes(5)
+				+ <() as WeightInfo>::ipfs_insert_pin()
+		);
+
+		assert_ok!(Ipfs::ipfs_batch(Origin::signed(alice()), commands));
+		let addr = OpaqueMultiaddr(ADDR.to_vec());
+		System::assert_has_event(Event::Ipfs(crate::Event::ConnectionRequested(alice(), addr)));
+		System::assert_has_event(Event::Ipfs(crate::Event::QueuedDataToPin(
+			alice(),
+			cid.clone(),
+			1,
+		)));
+		assert_eq!(Ipfs::pending_commands().data.len(), 2);
+
+		// the second command fails, so the first one isn't queued either
+		next_block();
+		let unknown = cid::raw_v1(b"unknown");
+		assert_noop!(
+			Ipfs::ipfs_batch(
+				Origin::signed(alice()),
+				vec![IpfsCommand::RemovePin(cid, true), IpfsCommand::RemoveBlock(unknown),]
+			),
+			Error::<Test>::UnknownContent
+		);
+		assert_noop!(
+			Ipfs::ipfs_batch(Origin::signed(alice()), vec![IpfsCommand::Connect(ADDR.to_vec()); 9]),
+			Error::<Test>::BatchTooLarge
+		);
+	});
+}
+
+#[test]
+fn snapshots_round_trip_through_car_files() {
+	let car = snapshot::to_car(b"snapshot");
+	assert_eq!(snapshot::from_car(&car), Some(b"snapshot".to_vec()));
+
+	let mut tampered = car.clone();
+	*tampered.last_mut().unwrap() ^= 1;
+	assert_eq!(snapshot::from_car(&tampered), None);
+	assert_eq!(snapshot::from_car(&car[..car.len() - 1]), None);
+}
+
+#[test]
+fn registry_is_exported_and_restored() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		let pinned = cid::raw_v1(b"pinned");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), pinned.clone(), true));
+		let leased = cid::raw_v1(b"leased");
+		assert_ok!(Ipfs::submit_cid(
+			Origin::signed(reporter),
+			alice(),
+			leased.clone(),
+			6,
+			Some(100),
+			false
+		));
+
+		assert_noop!(Ipfs::export_registry(Origin::signed(alice())), DispatchError::BadOrigin);
+		assert_ok!(Ipfs::export_registry(Origin::root()));
+		Ipfs::offchain_worker(1);
+		let (requested_at, export) = submitted(&pool)
+			.into_iter()
+			.find_map(|(signed, call)| match call {
+				crate::Call::submit_registry_export { requested_at, export } if signed => {
+					Some((requested_at, export))
+				},
+				_ => None,
+			})
+			.unwrap();
+		// both entries and the assignment of the pinned one
+		assert_eq!(export.entries, 3);
+		assert_ok!(Ipfs::submit_registry_export(
+			Origin::signed(reporter),
+			requested_at,
+			export.clone()
+		));
+		assert_eq!(Ipfs::last_export(), Some(export.clone()));
+		assert_eq!(Ipfs::pending_export(), None);
+
+		// the registry is lost
+		crate::ContentRegistry::<Test>::remove_all(None);
+		run_to_block(3);
+		assert_ok!(Ipfs::import_registry(Origin::root(), export.clone()));
+		assert_noop!(
+			Ipfs::import_registry(Origin::root(), export.clone()),
+			Error::<Test>::ImportInProgress
+		);
+		Ipfs::offchain_worker(3);
+		let (start, entries) = submitted(&pool)
+			.into_iter()
+			.find_map(|(signed, call)| match call {
+				crate::Call::restore_registry { start, entries } if signed => {
+					Some((start, entries))
+				},
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!((start, entries.len()), (0, 3));
+
+		assert_noop!(
+			Ipfs::restore_registry(Origin::signed(reporter), 1, entries.clone()),
+			Error::<Test>::UnexpectedRestorePage
+		);
+		assert_ok!(Ipfs::restore_registry(Origin::signed(reporter), start, entries));
+		System::assert_has_event(Event::Ipfs(crate::Event::RegistryImported(export.cid, 3)));
+		assert_eq!(Ipfs::pending_import(), None);
+		assert_eq!(Ipfs::content(&pinned).map(|c| (c.owner, c.deposit)), Some((alice(), 0)));
+		// the lease keeps the 100 blocks it had left
+		assert_eq!(Ipfs::content(&leased).and_then(|c| c.expires_at), Some(103));
+		assert_eq!(Ipfs::imported_pin(0), Some(pinned));
+	});
+}
+
+#[test]
+fn bucket_roles_control_shared_content() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Ipfs::create_bucket(Origin::signed(alice()), b"docs".to_vec()));
+		System::assert_last_event(Event::Ipfs(crate::Event::BucketCreated(alice(), 0)));
+		assert_noop!(
+			Ipfs::create_bucket(Origin::signed(alice()), b"docs".to_vec()),
+			Error::<Test>::BucketExists
+		);
+		assert_noop!(
+			Ipfs::create_bucket(Origin::signed(alice()), vec![b'a'; 65]),
+			Error::<Test>::BucketNameTooLong
+		);
+
+		let cid = cid::raw_v1(b"shared");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), cid.clone(), true));
+		assert_noop!(
+			Ipfs::add_to_bucket(Origin::signed(bob()), 0, cid.clone()),
+			Error::<Test>::NotContentOwner
+		);
+		assert_ok!(Ipfs::add_to_bucket(Origin::signed(alice()), 0, cid.clone()));
+		assert_noop!(
+			Ipfs::add_to_bucket(Origin::signed(alice()), 0, cid.clone()),
+			Error::<Test>::AlreadyInBucket
+		);
+
+		// others can neither retrieve nor modify the content without a role
+		assert!(!Ipfs::has_access(&cid, &bob()));
+		assert_noop!(
+			Ipfs::ipfs_remove_pin(Origin::signed(bob()), cid.clone(), true),
+			Error::<Test>::NotContentOwner
+		);
+		assert_noop!(
+			Ipfs::grant_bucket_role(Origin::signed(bob()), 0, bob(), BucketRole::Writer),
+			Error::<Test>::NotBucketOwner
+		);
+
+		assert_ok!(Ipfs::grant_bucket_role(Origin::signed(alice()), 0, bob(), BucketRole::Reader));
+		assert!(Ipfs::has_access(&cid, &bob()));
+		assert_noop!(
+			Ipfs::ipfs_insert_pin(Origin::signed(bob()), cid.clone(), false),
+			Error::<Test>::NotBucketWriter
+		);
+
+		assert_ok!(Ipfs::grant_bucket_role(Origin::signed(alice()), 0, bob(), BucketRole::Writer));
+		assert_ok!(Ipfs::ipfs_remove_pin(Origin::signed(bob()), cid.clone(), true));
+		let request = Ipfs::data_queue_tail() - 1;
+		System::assert_last_event(Event::Ipfs(crate::Event::QueuedDataToUnpin(
+			alice(),
+			cid.clone(),
+			request,
+		)));
+
+		assert_ok!(Ipfs::revoke_bucket_role(Origin::signed(alice()), 0, bob()));
+		assert_noop!(
+			Ipfs::revoke_bucket_role(Origin::signed(alice()), 0, bob()),
+			Error::<Test>::NoBucketRole
+		);
+		assert!(!Ipfs::has_access(&cid, &bob()));
+
+		assert_ok!(Ipfs::ipfs_remove_block(Origin::signed(alice()), cid.clone()));
+		assert_eq!(Ipfs::content_bucket(&cid), None);
+	});
+}
+
+#[test]
+fn namespaces_keep_services_names_and_quotas_apart() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let metadata = cid::raw_v1(b"metadata");
+		assert_ok!(Ipfs::register_service(
+			Origin::signed(alice()),
+			b"a".to_vec(),
+			metadata.clone(),
+			0
+		));
+		assert_ok!(Ipfs::register_service(Origin::signed(bob()), b"b".to_vec(), metadata, 0));
+		add_bytes(alice(), b"hello");
+		let hello = register(reporter, alice(), b"hello");
+		add_bytes(bob(), b"world");
+		let world = register(reporter, bob(), b"world");
+		let name = || b"logo".to_vec();
+
+		// binding names takes a role in the namespace and a member quota
+		assert_noop!(
+			Ipfs::bind_name(Origin::signed(bob()), 0, name(), world.clone(), vec![]),
+			Error::<Test>::NotNamespaceWriter
+		);
+		assert_noop!(
+			Ipfs::bind_name(Origin::signed(alice()), 0, name(), hello.clone(), vec![]),
+			Error::<Test>::MemberQuotaExceeded
+		);
+		assert_noop!(
+			Ipfs::set_member_quota(Origin::signed(bob()), 0, bob(), 5),
+			Error::<Test>::NotNamespaceAdmin
+		);
+		assert_ok!(Ipfs::set_member_quota(Origin::signed(alice()), 0, alice(), 5));
+		assert_ok!(Ipfs::bind_name(Origin::signed(alice()), 0, name(), hello.clone(), vec![]));
+		System::assert_last_event(Event::Ipfs(crate::Event::NameBound(0, name(), hello.clone())));
+		assert_eq!(Ipfs::member_quota(0, alice()).used, 5);
+		assert_noop!(
+			Ipfs::bind_name(Origin::signed(alice()), 0, name(), hello.clone(), vec![]),
+			Error::<Test>::NameTaken
+		);
+
+		// the same name is free in another namespace, whose quotas governance may set too
+		assert_ok!(Ipfs::set_member_quota(Origin::root(), 1, bob(), 5));
+		assert_ok!(Ipfs::bind_name(Origin::signed(bob()), 1, name(), world.clone(), vec![]));
+		assert_eq!(Ipfs::namespace_entry(1, name()).map(|entry| entry.cid), Some(world));
+
+		// writers may only retag and unbind the names they bound
+		assert_ok!(Ipfs::grant_namespace_role(
+			Origin::signed(alice()),
+			0,
+			bob(),
+			NamespaceRole::Writer
+		));
+		assert_noop!(
+			Ipfs::tag_name(Origin::signed(bob()), 0, name(), vec![b"image".to_vec()]),
+			Error::<Test>::NotNamespaceAdmin
+		);
+		assert_noop!(
+			Ipfs::tag_name(Origin::signed(alice()), 0, name(), vec![vec![b't'; 33]]),
+			Error::<Test>::InvalidTags
+		);
+		assert_ok!(Ipfs::tag_name(Origin::signed(alice()), 0, name(), vec![b"image".to_vec()]));
+		assert_eq!(
+			Ipfs::namespace_entry(0, name()).map(|entry| entry.tags),
+			Some(vec![b"image".to_vec()])
+		);
+		assert_noop!(
+			Ipfs::unbind_name(Origin::signed(bob()), 0, name()),
+			Error::<Test>::NotNamespaceAdmin
+		);
+		assert_ok!(Ipfs::unbind_name(Origin::signed(alice()), 0, name()));
+		assert_eq!(Ipfs::member_quota(0, alice()), MemberQuota { granted: 5, used: 0 });
+
+		assert_ok!(Ipfs::revoke_namespace_role(Origin::signed(alice()), 0, bob()));
+		assert_noop!(
+			Ipfs::revoke_namespace_role(Origin::signed(alice()), 0, bob()),
+			Error::<Test>::NoNamespaceRole
+		);
+
+		// a deregistered service takes its namespace with it
+		assert_ok!(Ipfs::deregister_service(Origin::signed(bob()), 1));
+		assert!(Ipfs::namespace_entry(1, name()).is_none());
+		assert_eq!(Ipfs::member_quota(1, bob()), MemberQuota::default());
+	});
+}
+
+#[test]
+fn scheduled_tasks_queue_their_command_every_period() {
+	new_test_ext().execute_with(|| {
+		let cid = cid::raw_v1(b"scheduled");
+		assert_ok!(Ipfs::ipfs_insert_pin(Origin::signed(alice()), cid.clone(), true));
+		assert_noop!(
+			Ipfs::schedule_task(Origin::signed(bob()), cid.clone(), ScheduledCommand::Pin(true), 5),
+			Error::<Test>::NotContentOwner
+		);
+		assert_noop!(
+			Ipfs::schedule_task(
+				Origin::signed(alice()),
+				cid.clone(),
+				ScheduledCommand::Pin(true),
+				0
+			),
+			Error::<Test>::InvalidSchedulePeriod
+		);
+		assert_ok!(Ipfs::schedule_task(
+			Origin::signed(alice()),
+			cid.clone(),
+			ScheduledCommand::Pin(true),
+			5
+		));
+		assert_ok!(Ipfs::schedule_task(
+			Origin::signed(alice()),
+			cid.clone(),
+			ScheduledCommand::Cat,
+			3
+		));
+		assert_noop!(
+			Ipfs::schedule_task(Origin::signed(alice()), cid.clone(), ScheduledCommand::Cat, 3),
+			Error::<Test>::TooManySchedules
+		);
+
+		let queued = || {
+			Ipfs::pending_commands()
+				.data
+				.into_iter()
+				.map(|(_, _, cmd)| cmd)
+				.collect::<Vec<_>>()
+		};
+		run_to_block(4);
+		assert_eq!(queued().last(), Some(&DataCommand::CatBytes(cid.clone(), None)));
+		run_to_block(6);
+		assert_eq!(queued().last(), Some(&DataCommand::InsertPin(cid.clone(), true)));
+		run_to_block(7);
+		assert_eq!(queued().len(), 4);
+
+		assert_noop!(
+			Ipfs::cancel_schedule(Origin::signed(bob()), 1),
+			Error::<Test>::NotScheduleOwner
+		);
+		assert_ok!(Ipfs::cancel_schedule(Origin::signed(alice()), 1));
+		run_to_block(10);
+		assert_eq!(queued().len(), 4);
+		assert_eq!(Ipfs::schedule_count(alice()), 1);
+
+		// the tasks of removed content end
+		assert_ok!(Ipfs::ipfs_remove_block(Origin::signed(alice()), cid));
+		run_to_block(11);
+		System::assert_has_event(Event::Ipfs(crate::Event::ScheduleCancelled(0)));
+		assert_eq!(Ipfs::schedule_count(alice()), 0);
+		assert_eq!(queued().len(), 5);
+	});
+}
+
+#[test]
+fn signed_reports_are_accepted_once_while_fresh() {
+	let (mut ext, reporter, pool) = new_offchain_ext();
+	ext.execute_with(|| {
+		add_bytes(alice(), b"reported");
+		Ipfs::offchain_worker(1);
+
+		let (payload, signature) = submitted(&pool)
+			.into_iter()
+			.find_map(|(signed, call)| match call {
+				crate::Call::submit_report { payload, signature } if !signed => {
+					Some((payload, signature))
+				},
+				_ => None,
+			})
+			.unwrap();
+		assert_eq!((payload.public, payload.block_number), (reporter, 1));
+		let validate = |payload: &ReportPayloadOf<Test>, signature: &sr25519::Signature| {
+			let call = crate::Call::submit_report {
+				payload: payload.clone(),
+				signature: signature.clone(),
+			};
+			Ipfs::validate_unsigned(TransactionSource::External, &call)
+		};
+		assert!(validate(&payload, &signature).is_ok());
+
+		// the signature covers the whole report
+		let mut forged = payload.clone();
+		let cid = cid::raw_v1(b"reported");
+		forged.report =
+			WorkerReport::Cid { owner: bob(), cid, size: 8, lease: None, encrypted: false };
+		assert_eq!(validate(&forged, &signature), InvalidTransaction::BadProof.into());
+
+		// a report is valid for `UnsignedLongevity` blocks, and only once
+		run_to_block(6);
+		assert!(validate(&payload, &signature).is_ok());
+		assert_ok!(Ipfs::submit_report(Origin::none(), payload.clone(), signature.clone()));
+		assert_eq!(Ipfs::content(cid::raw_v1(b"reported")).map(|info| info.owner), Some(alice()));
+		assert_eq!(validate(&payload, &signature), InvalidTransaction::Stale.into());
+		run_to_block(7);
+		assert_eq!(validate(&payload, &signature), InvalidTransaction::Stale.into());
+		assert!(!crate::AcceptedReports::<Test>::contains_key(1, BlakeTwo256::hash_of(&payload)));
+
+		// nor before the block it claims to be made in
+		let mut future = payload;
+		future.block_number = 8;
+		let signature = SignedPayload::<Test>::sign::<crate::crypto::OcwAuthId>(&future).unwrap();
+		assert_eq!(validate(&future, &signature), InvalidTransaction::Future.into());
+	});
+}
+
+#[test]
+fn genesis_registers_bootstrap_peers_and_pins() {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	crate::GenesisConfig::<Test> {
+		bootstrap_peers: vec![ADDR.to_vec()],
+		pins: vec![(alice(), cid::raw_v1(b"genesis")), (bob(), cid::raw_v1(b"genesis"))],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	sp_io::TestExternalities::new(t).execute_with(|| {
+		assert_eq!(Ipfs::bootstrap_peers(), vec![OpaqueMultiaddr(ADDR.to_vec())]);
+		// a CID listed twice keeps its first owner
+		assert_eq!(Ipfs::content(cid::raw_v1(b"genesis")).map(|info| info.owner), Some(alice()));
+		assert_eq!(Ipfs::imported_pin_count(), 1);
+		assert_eq!(Ipfs::imported_pin(0), Some(cid::raw_v1(b"genesis")));
+	});
+}
+
+#[test]
+fn other_pallets_queue_work_and_receive_results() {
+	let (mut ext, reporter, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		let added = <Ipfs as IpfsHandler<_>>::add_bytes(&alice(), b"hello".to_vec()).unwrap();
+		assert_eq!(
+			added,
+			BlakeTwo256::hash_of(&DataCommand::AddBytes(alice(), b"hello".to_vec(), None, None))
+		);
+		assert_eq!(Balances::reserved_balance(alice()), 5);
+		let cid = register(reporter, alice(), b"hello");
+
+		let request = <Ipfs as IpfsHandler<_>>::cat_bytes(&bob(), cid.clone()).unwrap();
+		assert!(Ipfs::is_result_pending(request));
+		assert_ok!(Ipfs::submit_cat_result(Origin::none(), request, b"hello".to_vec()));
+		let cmd = DataCommand::AddBytes(alice(), b"hello".to_vec(), None, None);
+		let reason = Error::<Test>::RequestTimeout.into();
+		assert_ok!(Ipfs::report_abandoned(Origin::signed(reporter), cmd, None, 3, reason));
+		assert_eq!(
+			RecordResults::take(),
+			vec![
+				IpfsResult::Added(alice(), cid),
+				IpfsResult::Cat(request, b"hello".to_vec()),
+				IpfsResult::Abandoned(added),
+			]
+		);
+
+		// pinning unknown content registers it to the given owner
+		let pinned = cid::raw_v1(b"pinned");
+		assert_ok!(<Ipfs as IpfsHandler<_>>::pin(&bob(), pinned.clone()));
+		let content = Ipfs::content(&pinned).unwrap();
+		assert_eq!((content.owner, content.pin_status), (bob(), PinStatus::Pinned));
+		assert_ok!(<Ipfs as IpfsHandler<_>>::unpin(pinned.clone()));
+		assert_eq!(
+			Ipfs::content(&pinned).map(|content| content.pin_status),
+			Some(PinStatus::Unpinned)
+		);
+		assert_noop!(
+			<Ipfs as IpfsHandler<_>>::unpin(cid::raw_v1(b"unknown")),
+			Error::<Test>::UnknownContent
+		);
+	});
+}
+
+#[test]
+fn receipts_are_stored_under_their_raw_cid_while_they_fit_a_block() {
+	use mpesa_primitives::IpfsStore;
+
+	new_test_ext().execute_with(|| {
+		let receipt = b"receipt".to_vec();
+		assert_eq!(
+			<Ipfs as IpfsStore<_>>::add_bytes(&alice(), receipt.clone()),
+			Ok(cid::raw_v1(&receipt))
+		);
+		assert_noop!(
+			<Ipfs as IpfsStore<_>>::add_bytes(&alice(), vec![0; cid::MAX_BLOCK_LEN + 1]),
+			Error::<Test>::ContentTooLarge
+		);
+	});
+}
+
+#[test]
+fn migration_re_encodes_the_legacy_queues() {
+	use crate::migrations::v1::{MigrateToV1, OldDataCommand, OLD_PREFIX};
+	use frame_support::{
+		storage::migration::{get_storage_value, put_storage_value},
+		traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+	};
+
+	frame_support::parameter_types! {
+		pub LegacyOwner: AccountId = bob();
+	}
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(7);
+		let connect = ConnectionCommand::ConnectTo(OpaqueMultiaddr(ADDR.to_vec()));
+		put_storage_value(OLD_PREFIX, b"ConnectionQueue", &[], vec![connect.clone()]);
+		put_storage_value(
+			OLD_PREFIX,
+			b"DhtQueue",
+			&[],
+			vec![DhtCommand::FindPeer(b"peer".to_vec())],
+		);
+		put_storage_value(
+			OLD_PREFIX,
+			b"DataQueue",
+			&[],
+			vec![
+				OldDataCommand::AddBytes(b"hello".to_vec()),
+				OldDataCommand::InsertPin(b"pin".to_vec()),
+				OldDataCommand::CatBytes(b"cat".to_vec()),
+			],
+		);
+		assert_eq!(Ipfs::on_chain_storage_version(), 0);
+
+		MigrateToV1::<Test, LegacyOwner>::on_runtime_upgrade();
+
+		assert_eq!(crate::ConnectionQueue::<Test>::get(), vec![connect]);
+		assert_eq!(crate::DhtQueue::<Test>::get(), vec![DhtCommand::FindPeer(b"peer".to_vec())]);
+		assert_eq!(
+			(0..Ipfs::data_queue_tail())
+				.filter_map(crate::DataQueue::<Test>::get)
+				.collect::<Vec<_>>(),
+			vec![
+				(Priority::Normal, DataCommand::AddBytes(bob(), b"hello".to_vec(), None, None)),
+				(Priority::High, DataCommand::InsertPin(b"pin".to_vec(), false)),
+				(Priority::Normal, DataCommand::CatBytes(b"cat".to_vec(), None)),
+			]
+		);
+		assert_eq!(Ipfs::request_status(2), Some(RequestStatus::Queued));
+		assert_eq!(Ipfs::data_queued_at(2), Some(7));
+		assert_eq!(Ipfs::maintenance_cursor(), 7);
+		assert_eq!(Ipfs::on_chain_storage_version(), StorageVersion::new(1));
+		assert!(get_storage_value::<Vec<OldDataCommand>>(OLD_PREFIX, b"DataQueue", &[]).is_none());
+
+		// running it again changes nothing
+		put_storage_value(OLD_PREFIX, b"DataQueue", &[], vec![OldDataCommand::RemoveBlock(vec![])]);
+		MigrateToV1::<Test, LegacyOwner>::on_runtime_upgrade();
+		assert_eq!(Ipfs::data_queue_tail(), 3);
+	});
+}
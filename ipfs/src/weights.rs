@@ -0,0 +1,541 @@
+// Weights for the IPFS pallet.
+//
+// The pallet has not been benchmarked yet, so there is no `SubstrateWeight`: the only
+// implementation is the one for `()`, whose values are the base weights the extrinsics were
+// charged before `WeightInfo` existed plus their database accesses. They are unmeasured and only
+// fit for tests. Generate the real weights with
+//
+//     node-template benchmark pallet --chain dev --pallet ipfs --extrinsic '*' --steps 50 \
+//         --repeat 20 --output ipfs/src/weights.rs
+//
+// before the pallet goes into a runtime.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+
+/// Weight functions needed for the IPFS pallet.
+pub trait WeightInfo {
+	fn ipfs_connect(q: u32) -> Weight;
+	fn ipfs_disconnect(q: u32) -> Weight;
+	fn ipfs_add_bytes(l: u32) -> Weight;
+	fn ipfs_add_bytes_with_lease(l: u32) -> Weight;
+	fn ipfs_begin_upload() -> Weight;
+	fn ipfs_upload_chunk(l: u32) -> Weight;
+	fn ipfs_finalize_upload(c: u32) -> Weight;
+	fn ipfs_cancel_upload(c: u32) -> Weight;
+	fn extend_lease() -> Weight;
+	fn ipfs_cat_bytes() -> Weight;
+	fn ipfs_remove_block() -> Weight;
+	fn ipfs_insert_pin() -> Weight;
+	fn ipfs_remove_pin() -> Weight;
+	fn ipfs_dht_find_peer(q: u32) -> Weight;
+	fn ipfs_dht_find_providers(q: u32) -> Weight;
+	fn ipfs_dht_provide(q: u32) -> Weight;
+	fn ipfs_pubsub_subscribe(q: u32) -> Weight;
+	fn ipfs_pubsub_publish(l: u32, q: u32) -> Weight;
+	fn ipfs_pubsub_unsubscribe(q: u32) -> Weight;
+	fn register_schema() -> Weight;
+	fn set_content_schema() -> Weight;
+	fn register_service() -> Weight;
+	fn update_service() -> Weight;
+	fn deregister_service() -> Weight;
+	fn grant_service_quota() -> Weight;
+	fn service_add_bytes(l: u32) -> Weight;
+	fn pause() -> Weight;
+	fn resume() -> Weight;
+	fn set_pin_policy() -> Weight;
+	fn set_feature_flags() -> Weight;
+	fn set_ingress_policy() -> Weight;
+	fn add_reporter(r: u32) -> Weight;
+	fn remove_reporter(r: u32) -> Weight;
+	fn force_connect(q: u32) -> Weight;
+	fn force_disconnect(p: u32) -> Weight;
+	fn set_bootstrap_peers(p: u32) -> Weight;
+	fn start_recovery() -> Weight;
+	fn submit_cid() -> Weight;
+	fn import_pinset(n: u32) -> Weight;
+	fn report_abandoned(l: u32) -> Weight;
+	fn replay_dead_letter() -> Weight;
+	fn purge_dead_letters(n: u32) -> Weight;
+	fn ack_data_commands() -> Weight;
+	fn update_request_statuses(n: u32) -> Weight;
+	fn submit_pubsub_message(l: u32) -> Weight;
+	fn submit_dht_result(l: u32) -> Weight;
+	fn submit_node_stats() -> Weight;
+	fn submit_cat_result(l: u32) -> Weight;
+	fn report_schema_mismatch() -> Weight;
+	fn report_recovery() -> Weight;
+	fn report_incidents(n: u32) -> Weight;
+	fn attest_pins(n: u32) -> Weight;
+	fn bond_storage() -> Weight;
+	fn unbond_storage() -> Weight;
+	fn respond_challenge() -> Weight;
+	fn grant_access() -> Weight;
+	fn revoke_access() -> Weight;
+	fn ipfs_run_gc() -> Weight;
+	fn evict_content(n: u32) -> Weight;
+	fn export_registry() -> Weight;
+	fn submit_registry_export() -> Weight;
+	fn import_registry() -> Weight;
+	fn restore_registry(n: u32) -> Weight;
+	fn create_bucket() -> Weight;
+	fn grant_bucket_role() -> Weight;
+	fn revoke_bucket_role() -> Weight;
+	fn add_to_bucket() -> Weight;
+	fn schedule_task() -> Weight;
+	fn cancel_schedule() -> Weight;
+	fn ipfs_ipns_publish() -> Weight;
+	fn ipfs_ipns_resolve() -> Weight;
+	fn deny_cid() -> Weight;
+	fn allow_cid() -> Weight;
+	fn bump_request() -> Weight;
+	fn appeal_denial(e: u32) -> Weight;
+	fn rule_appeal() -> Weight;
+	fn grant_namespace_role() -> Weight;
+	fn revoke_namespace_role() -> Weight;
+	fn set_member_quota() -> Weight;
+	fn bind_name() -> Weight;
+	fn unbind_name() -> Weight;
+	fn tag_name() -> Weight;
+}
+
+// Unmeasured placeholders, for tests only
+impl WeightInfo for () {
+	fn ipfs_connect(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn ipfs_disconnect(q: u32) -> Weight {
+		(500_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn ipfs_add_bytes(l: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn ipfs_add_bytes_with_lease(l: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn ipfs_begin_upload() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn ipfs_upload_chunk(l: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn ipfs_finalize_upload(c: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(8 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(c as Weight)))
+	}
+	fn ipfs_cancel_upload(c: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(c as Weight)))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(c as Weight)))
+	}
+	fn extend_lease() -> Weight {
+		(200_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn ipfs_cat_bytes() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(8 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn ipfs_remove_block() -> Weight {
+		(300_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn ipfs_insert_pin() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(8 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn ipfs_remove_pin() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(8 as Weight))
+	}
+	fn ipfs_dht_find_peer(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(7 as Weight))
+	}
+	fn ipfs_dht_find_providers(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(7 as Weight))
+	}
+	fn ipfs_dht_provide(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn ipfs_pubsub_subscribe(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn ipfs_pubsub_publish(l: u32, q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn ipfs_pubsub_unsubscribe(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn register_schema() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn set_content_schema() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn register_service() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn update_service() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn deregister_service() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn grant_service_quota() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn service_add_bytes(l: u32) -> Weight {
+		(200_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(10 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn pause() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn resume() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn set_pin_policy() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn set_feature_flags() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn set_ingress_policy() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn add_reporter(r: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(r as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn remove_reporter(r: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(r as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn force_connect(q: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(q as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn force_disconnect(p: u32) -> Weight {
+		(500_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(p as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn set_bootstrap_peers(p: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((10_000 as Weight).saturating_mul(p as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn start_recovery() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn submit_cid() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn import_pinset(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((50_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+	}
+	fn report_abandoned(l: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn replay_dead_letter() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn purge_dead_letters(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(n as Weight)))
+	}
+	fn ack_data_commands() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn update_request_statuses(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((20_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((5 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((7 as Weight).saturating_mul(n as Weight)))
+	}
+	fn submit_pubsub_message(l: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+	}
+	fn submit_dht_result(l: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
+	}
+	fn submit_node_stats() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn submit_cat_result(l: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(l as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn report_schema_mismatch() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().reads(1 as Weight))
+	}
+	fn report_recovery() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn report_incidents(n: u32) -> Weight {
+		(150_000 as Weight)
+			.saturating_add((20_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn attest_pins(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((40_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((1 as Weight).saturating_mul(n as Weight)))
+	}
+	fn bond_storage() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn unbond_storage() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn respond_challenge() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn grant_access() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn revoke_access() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn ipfs_run_gc() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
+	}
+	fn evict_content(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((150_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((9 as Weight).saturating_mul(n as Weight)))
+	}
+	fn export_registry() -> Weight {
+		(100_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn submit_registry_export() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn import_registry() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn restore_registry(n: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((60_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((2 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+	}
+	fn create_bucket() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn grant_bucket_role() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn revoke_bucket_role() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn add_to_bucket() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn schedule_task() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn cancel_schedule() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn ipfs_ipns_publish() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(7 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn ipfs_ipns_resolve() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(7 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+	}
+	fn deny_cid() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(7 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(15 as Weight))
+	}
+	fn allow_cid() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn bump_request() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn appeal_denial(e: u32) -> Weight {
+		(100_000 as Weight)
+			.saturating_add((1_000 as Weight).saturating_mul(e as Weight))
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn rule_appeal() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn grant_namespace_role() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn revoke_namespace_role() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn set_member_quota() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn bind_name() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn unbind_name() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn tag_name() -> Weight {
+		(100_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+}
@@ -142,6 +142,8 @@ pub fn create_benchmark_extrinsic(
 		frame_system::CheckNonce::<runtime::Runtime>::from(nonce),
 		frame_system::CheckWeight::<runtime::Runtime>::new(),
 		pallet_transaction_payment::ChargeTransactionPayment::<runtime::Runtime>::from(0),
+		runtime::pallet_ipfs::CheckFeatureFlags::<runtime::Runtime>::new(),
+		runtime::pallet_ipfs::CheckBlockData::<runtime::Runtime>::new(),
 	);
 
 	let raw_payload = runtime::SignedPayload::from_raw(
@@ -156,6 +158,8 @@ pub fn create_benchmark_extrinsic(
 			(),
 			(),
 			(),
+			(),
+			(),
 		),
 	);
 	let signature = raw_payload.using_encoded(|e| sender.sign(e));
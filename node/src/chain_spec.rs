@@ -1,8 +1,9 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	display::{TOKEN_DECIMALS, TOKEN_SYMBOL},
+	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, IpfsConfig,
+	MpesaUserConfig, SS58Prefix, Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
-use sc_service::ChainType;
+use sc_service::{ChainType, Properties};
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{sr25519, Pair, Public};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
@@ -11,6 +12,12 @@ use sp_runtime::traits::{IdentifyAccount, Verify};
 // The URL for the telemetry server.
 // const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
 
+/// The public libp2p bootstrap peers, which the IPFS nodes of the test chains dial on startup.
+const IPFS_BOOTSTRAP_PEERS: &[&str] = &[
+	"/dnsaddr/bootstrap.libp2p.io/p2p/QmNnooDu7bfjPFoTZYxMNLWUQJyrVwtbZg5gBMjTezGAJN",
+	"/dnsaddr/bootstrap.libp2p.io/p2p/QmQCU2EcMqAqQPR2i9bChDtGNJchTbq5TbXJJ16u19uLTa",
+];
+
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = sc_service::GenericChainSpec<GenesisConfig>;
 
@@ -31,6 +38,15 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
+/// Token properties used by wallets to render balances and addresses.
+pub fn chain_properties() -> Properties {
+	let mut properties = Properties::new();
+	properties.insert("tokenSymbol".into(), TOKEN_SYMBOL.into());
+	properties.insert("tokenDecimals".into(), TOKEN_DECIMALS.into());
+	properties.insert("ss58Format".into(), SS58Prefix::get().into());
+	properties
+}
+
 /// Generate an Aura authority key.
 pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
 	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
@@ -70,7 +86,7 @@ pub fn development_config() -> Result<ChainSpec, String> {
 		None,
 		None,
 		// Properties
-		None,
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -116,9 +132,9 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 		None,
 		// Protocol ID
 		None,
-		// Properties
-		None,
 		None,
+		// Properties
+		Some(chain_properties()),
 		// Extensions
 		None,
 	))
@@ -149,8 +165,20 @@ fn testnet_genesis(
 		},
 		sudo: SudoConfig {
 			// Assign network admin rights.
-			key: Some(root_key),
+			key: Some(root_key.clone()),
 		},
 		transaction_payment: Default::default(),
+		mpesa_user: MpesaUserConfig {
+			// Seed the pool with a contribution of 1 << 50 from the (endowed) root key.
+			providers: vec![(root_key, u8::MAX, 1 << 50)],
+			..Default::default()
+		},
+		ipfs: IpfsConfig {
+			bootstrap_peers: IPFS_BOOTSTRAP_PEERS
+				.iter()
+				.map(|addr| addr.as_bytes().to_vec())
+				.collect(),
+			pins: Vec::new(),
+		},
 	}
 }
@@ -0,0 +1,61 @@
+//! `substrate_ocw_signing_key_missing`: a Prometheus gauge per offchain worker key type, set to 1
+//! while the keystore holds no key of the type. The offchain workers skip the tasks that report
+//! to the chain without one and only warn about it once, so this is what alerts should watch.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use node_template_runtime::{opaque::Block, pallet_ipfs, pallet_mpesa_user, pallet_webhooks};
+use sc_client_api::BlockchainEvents;
+use sp_core::crypto::KeyTypeId;
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
+use substrate_prometheus_endpoint::{register, GaugeVec, Opts, PrometheusError, Registry, U64};
+
+// the key types the offchain workers sign with, and their labels
+const KEY_TYPES: [(KeyTypeId, &str); 3] = [
+	(pallet_ipfs::KEY_TYPE, "ipfs"),
+	(pallet_mpesa_user::KEY_TYPE, "mpsa"),
+	(pallet_webhooks::KEY_TYPE, "hook"),
+];
+
+/// Register the gauge and update it from the keystore on every imported block, since keys can be
+/// inserted over RPC at any time.
+pub async fn run_signing_key_gauge<C>(
+	client: Arc<C>,
+	keystore: SyncCryptoStorePtr,
+	registry: Registry,
+) where
+	C: BlockchainEvents<Block>,
+{
+	let gauge = match register_gauge(&registry) {
+		Ok(gauge) => gauge,
+		Err(e) => {
+			log::warn!("Failed to register the offchain worker key metrics: {}", e);
+			return;
+		},
+	};
+
+	let mut imported = client.import_notification_stream();
+	loop {
+		for (key_type, label) in KEY_TYPES {
+			let missing = SyncCryptoStore::sr25519_public_keys(&*keystore, key_type).is_empty();
+			gauge.with_label_values(&[label]).set(missing as u64);
+		}
+		if imported.next().await.is_none() {
+			return;
+		}
+	}
+}
+
+fn register_gauge(registry: &Registry) -> Result<GaugeVec<U64>, PrometheusError> {
+	register(
+		GaugeVec::new(
+			Opts::new(
+				"ocw_signing_key_missing",
+				"Whether the keystore lacks a key the offchain workers sign with",
+			),
+			&["key_type"],
+		)?,
+		registry,
+	)
+}
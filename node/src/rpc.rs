@@ -8,27 +8,37 @@
 use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
-use node_template_runtime::{opaque::Block, AccountId, Balance, Index};
+use node_template_runtime::{
+	opaque::Block, AccountId, Balance, BlockNumber, Call, Event, Hash, Index,
+};
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
+use sp_core::offchain::OffchainStorage;
 
 pub use sc_rpc_api::DenyUnsafe;
 
+pub mod dry_run;
+pub mod ipfs;
+pub mod ipfs_cache;
+pub mod mpesa;
+
 /// Full client dependencies.
-pub struct FullDeps<C, P> {
+pub struct FullDeps<C, P, S> {
 	/// The client instance to use.
 	pub client: Arc<C>,
 	/// Transaction pool instance.
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// The node's off-chain storage, if offchain workers are enabled.
+	pub offchain_storage: Option<S>,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P>(
-	deps: FullDeps<C, P>,
+pub fn create_full<C, P, S>(
+	deps: FullDeps<C, P, S>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
 	C: ProvideRuntimeApi<Block>,
@@ -37,16 +47,40 @@ where
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: BlockBuilder<Block>,
+	C::Api: node_template_runtime::dry_run::DryRunApi<Block, AccountId, Call, Event>,
+	C::Api: node_template_runtime::pallet_mpesa_user::runtime_api::MpesaUserApi<
+		Block,
+		AccountId,
+		BlockNumber,
+	>,
+	C::Api: node_template_runtime::pallet_ipfs::runtime_api::IpfsApi<
+		Block,
+		AccountId,
+		BlockNumber,
+		Balance,
+		Hash,
+	>,
 	P: TransactionPool + 'static,
+	S: OffchainStorage + 'static,
 {
+	use dry_run::{DryRun, DryRunApiServer};
+	use ipfs::{Ipfs, IpfsApiServer};
+	use ipfs_cache::{IpfsCache, IpfsCacheApiServer};
+	use mpesa::{Mpesa, MpesaApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, offchain_storage } = deps;
 
 	module.merge(System::new(client.clone(), pool.clone(), deny_unsafe).into_rpc())?;
-	module.merge(TransactionPayment::new(client).into_rpc())?;
+	module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	module.merge(DryRun::new(client.clone()).into_rpc())?;
+	module.merge(Mpesa::new(client.clone()).into_rpc())?;
+	module.merge(Ipfs::new(client).into_rpc())?;
+	if let Some(storage) = offchain_storage {
+		module.merge(IpfsCache::new(storage).into_rpc())?;
+	}
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
@@ -0,0 +1,131 @@
+//! `chain_dryRun`: execute a call against a throw-away overlay and describe the outcome.
+
+use std::sync::Arc;
+
+use codec::Decode;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use node_template_runtime::{
+	dry_run::DryRunApi as DryRunRuntimeApi, opaque::Block, AccountId, Call, Event,
+};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::Bytes;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// A human-readable description of what a call would do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+	/// Whether the call would be dispatched successfully.
+	pub success: bool,
+	/// Summary such as "would succeed" or "would fail: MinimumLPShareAmountNotMet".
+	pub outcome: String,
+	/// Debug representations of the events the call would emit.
+	pub events: Vec<String>,
+}
+
+/// Dry-run RPC methods.
+#[rpc(client, server)]
+pub trait DryRunApi<BlockHash> {
+	/// Apply the SCALE-encoded `call` as an extrinsic signed by `account`, nonce and fees included,
+	/// without committing it; the signature itself is not checked.
+	#[method(name = "chain_dryRun")]
+	fn dry_run(
+		&self,
+		call: Bytes,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<DryRunResult>;
+}
+
+/// The longest encoded call `chain_dryRun` accepts; the calls the app validates are far shorter,
+/// and the signed extensions already reject calls too heavy for a block.
+const MAX_CALL_LEN: usize = 64 * 1024;
+
+/// Error codes returned by `chain_dryRun`.
+pub enum Error {
+	/// The call could not be decoded.
+	DecodeError,
+	/// The runtime API call failed.
+	RuntimeError,
+	/// The call is longer than `MAX_CALL_LEN`.
+	CallTooLarge,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::DecodeError => 1,
+			Error::RuntimeError => 2,
+			Error::CallTooLarge => 3,
+		}
+	}
+}
+
+/// Implementation of the `chain_dryRun` RPC.
+pub struct DryRun<C> {
+	client: Arc<C>,
+}
+
+impl<C> DryRun<C> {
+	/// Create a new `DryRun` RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client }
+	}
+}
+
+impl<C> DryRunApiServer<<Block as BlockT>::Hash> for DryRun<C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: DryRunRuntimeApi<Block, AccountId, Call, Event>,
+{
+	fn dry_run(
+		&self,
+		call: Bytes,
+		account: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<DryRunResult> {
+		if call.len() > MAX_CALL_LEN {
+			return Err(CallError::Custom(ErrorObject::owned(
+				Error::CallTooLarge.into(),
+				"Call too large to dry-run.",
+				Some(format!("{} bytes, at most {} accepted", call.len(), MAX_CALL_LEN)),
+			))
+			.into());
+		}
+
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let call = Call::decode(&mut &*call).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::DecodeError.into(),
+				"Unable to decode call.",
+				Some(e.to_string()),
+			))
+		})?;
+
+		let outcome = api.dry_run(&at, account, call).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to dry-run call.",
+				Some(e.to_string()),
+			))
+		})?;
+
+		let events = outcome.events.iter().map(|event| format!("{:?}", event)).collect();
+		Ok(match outcome.error {
+			None => DryRunResult { success: true, outcome: "would succeed".into(), events },
+			Some(error) => DryRunResult {
+				success: false,
+				outcome: format!("would fail: {}", String::from_utf8_lossy(&error)),
+				events,
+			},
+		})
+	}
+}
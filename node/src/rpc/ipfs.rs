@@ -0,0 +1,275 @@
+//! `ipfs_pendingCommands`, `ipfs_contentInfo`, `ipfs_peerList` and `ipfs_requestStatus`: inspect
+//! the IPFS pallet's queues and content registry without decoding raw storage keys.
+
+use std::sync::Arc;
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use node_template_runtime::{
+	opaque::Block,
+	pallet_ipfs::{
+		runtime_api::IpfsApi as IpfsRuntimeApi, ConnectionCommand, ContentInfo, DataCommand,
+		DhtCommand, PinMode, PinStatus, Priority, PubsubCommand, RequestId, RequestStatus,
+	},
+	AccountId, Balance, BlockNumber, Hash,
+};
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// The commands waiting in the pallet's queues, each described in a line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingCommands {
+	/// Connections to establish or drop, such as "connect /ip4/1.2.3.4/tcp/4001/p2p/Qm...".
+	pub connections: Vec<String>,
+	/// DHT queries and announcements, such as "provide bafk...".
+	pub dht: Vec<String>,
+	/// Pubsub commands, such as "publish 12 bytes to receipts".
+	pub pubsub: Vec<String>,
+	/// Data commands not yet acknowledged by an offchain worker, in queue order.
+	pub data: Vec<QueuedCommand>,
+}
+
+/// A data command waiting in the data queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedCommand {
+	/// The request the command's status is tracked under.
+	pub request: RequestId,
+	/// "high", "normal" or "low".
+	pub priority: String,
+	/// The command, such as "add 1024 bytes for 5Grw...".
+	pub command: String,
+}
+
+/// The registry entry of a piece of content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+	/// The account that added or first pinned the content.
+	pub owner: AccountId,
+	/// The block the content was registered in.
+	pub added_at: BlockNumber,
+	/// Whether the content is meant to be pinned.
+	pub pinned: bool,
+	/// Whether pins cover everything the content links to.
+	pub recursive: bool,
+	/// The size of the content in bytes, if it was added through the pallet.
+	pub size: Option<u64>,
+	/// The block the content's lease expires in, if it is leased.
+	pub expires_at: Option<BlockNumber>,
+	/// The deposit reserved from the owner.
+	pub deposit: Balance,
+}
+
+/// How far the offchain workers have got with a queued data command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+	/// "queued", "processing", "done" or "failed".
+	pub state: String,
+	/// The hash of the result of a completed command.
+	pub result: Option<Hash>,
+	/// The error of the last attempt of a failed command.
+	pub error: Option<String>,
+}
+
+/// IPFS RPC methods.
+#[rpc(client, server)]
+pub trait IpfsApi<BlockHash> {
+	/// The commands waiting in the pallet's queues.
+	#[method(name = "ipfs_pendingCommands")]
+	fn pending_commands(&self, at: Option<BlockHash>) -> RpcResult<PendingCommands>;
+
+	/// The registry entry of `cid`, if it is registered.
+	#[method(name = "ipfs_contentInfo")]
+	fn content_info(&self, cid: String, at: Option<BlockHash>) -> RpcResult<Option<Content>>;
+
+	/// The multiaddrs the IPFS nodes are asked to stay connected to.
+	#[method(name = "ipfs_peerList")]
+	fn peer_list(&self, at: Option<BlockHash>) -> RpcResult<Vec<String>>;
+
+	/// The status of the queued data command `request`, until it expires.
+	#[method(name = "ipfs_requestStatus")]
+	fn request_status(
+		&self,
+		request: RequestId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Status>>;
+}
+
+/// Error code returned when the runtime API call fails.
+const RUNTIME_ERROR: i32 = 1;
+
+fn runtime_error(e: impl ToString) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		"Unable to query the IPFS pallet.",
+		Some(e.to_string()),
+	))
+	.into()
+}
+
+// CIDs, multiaddrs and topics are ASCII
+fn text(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn describe_data(cmd: &DataCommand<AccountId>) -> String {
+	match cmd {
+		DataCommand::AddBytes(owner, data, ..) => format!("add {} bytes for {}", data.len(), owner),
+		DataCommand::AddUpload(owner, upload, chunks, _) => {
+			format!("add upload {} ({} chunks) for {}", upload, chunks, owner)
+		},
+		DataCommand::CatBytes(cid, _) => format!("cat {}", text(cid)),
+		DataCommand::InsertPin(cid, recursive) => {
+			format!("pin {}{}", text(cid), if *recursive { " recursively" } else { "" })
+		},
+		DataCommand::RemoveBlock(cid) => format!("remove {}", text(cid)),
+		DataCommand::RemovePin(cid, recursive) => {
+			format!("unpin {}{}", text(cid), if *recursive { " recursively" } else { "" })
+		},
+		DataCommand::AddEncrypted(owner, data, _) => {
+			format!("add {} encrypted bytes for {}", data.len(), owner)
+		},
+		DataCommand::CollectGarbage => "collect garbage".into(),
+		DataCommand::IpnsPublish(owner, cid) => {
+			format!("point the IPNS name of {} at {}", owner, text(cid))
+		},
+		DataCommand::IpnsResolve(name) => format!("resolve {}", text(name)),
+	}
+}
+
+/// Implementation of the `ipfs_*` inspection RPCs.
+pub struct Ipfs<C> {
+	client: Arc<C>,
+}
+
+impl<C> Ipfs<C> {
+	/// Create a new `Ipfs` RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client }
+	}
+}
+
+impl<C> Ipfs<C>
+where
+	C: HeaderBackend<Block>,
+{
+	fn block(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId<Block> {
+		BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+	}
+}
+
+impl<C> IpfsApiServer<<Block as BlockT>::Hash> for Ipfs<C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: IpfsRuntimeApi<Block, AccountId, BlockNumber, Balance, Hash>,
+{
+	fn pending_commands(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<PendingCommands> {
+		let pending = self
+			.client
+			.runtime_api()
+			.pending_commands(&self.block(at))
+			.map_err(runtime_error)?;
+
+		let connections = pending
+			.connections
+			.iter()
+			.map(|cmd| match cmd {
+				ConnectionCommand::ConnectTo(addr) => format!("connect {}", text(&addr.0)),
+				ConnectionCommand::DisconnectFrom(addr) => format!("disconnect {}", text(&addr.0)),
+			})
+			.collect();
+		let dht = pending
+			.dht
+			.iter()
+			.map(|cmd| match cmd {
+				DhtCommand::FindPeer(peer_id) => format!("find peer {}", text(peer_id)),
+				DhtCommand::GetProviders(cid) => format!("find providers of {}", text(cid)),
+				DhtCommand::Provide(cid) => format!("provide {}", text(cid)),
+			})
+			.collect();
+		let pubsub = pending
+			.pubsub
+			.iter()
+			.map(|cmd| match cmd {
+				PubsubCommand::Subscribe(topic) => format!("subscribe to {}", text(topic)),
+				PubsubCommand::Publish(topic, message) => {
+					format!("publish {} bytes to {}", message.len(), text(topic))
+				},
+				PubsubCommand::Unsubscribe(topic) => format!("unsubscribe from {}", text(topic)),
+			})
+			.collect();
+		let data = pending
+			.data
+			.iter()
+			.map(|(request, priority, cmd)| QueuedCommand {
+				request: *request,
+				priority: match priority {
+					Priority::High => "high",
+					Priority::Normal => "normal",
+					Priority::Low => "low",
+				}
+				.into(),
+				command: describe_data(cmd),
+			})
+			.collect();
+
+		Ok(PendingCommands { connections, dht, pubsub, data })
+	}
+
+	fn content_info(
+		&self,
+		cid: String,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Content>> {
+		let info = self
+			.client
+			.runtime_api()
+			.content_info(&self.block(at), cid.into_bytes())
+			.map_err(runtime_error)?;
+
+		Ok(info.map(|info: ContentInfo<AccountId, BlockNumber, Balance>| Content {
+			owner: info.owner,
+			added_at: info.added_at,
+			pinned: info.pin_status == PinStatus::Pinned,
+			recursive: info.pin_mode == PinMode::Recursive,
+			size: info.size,
+			expires_at: info.expires_at,
+			deposit: info.deposit,
+		}))
+	}
+
+	fn peer_list(&self, at: Option<<Block as BlockT>::Hash>) -> RpcResult<Vec<String>> {
+		let peers = self.client.runtime_api().peer_list(&self.block(at)).map_err(runtime_error)?;
+		Ok(peers.iter().map(|addr| text(&addr.0)).collect())
+	}
+
+	fn request_status(
+		&self,
+		request: RequestId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Status>> {
+		let status = self
+			.client
+			.runtime_api()
+			.request_status(&self.block(at), request)
+			.map_err(runtime_error)?;
+
+		Ok(status.map(|status| {
+			let (state, result, error) = match status {
+				RequestStatus::Queued => ("queued", None, None),
+				RequestStatus::Processing => ("processing", None, None),
+				RequestStatus::Done(hash) => ("done", Some(hash), None),
+				RequestStatus::Failed(error) => ("failed", None, Some(format!("{:?}", error))),
+			};
+			Status { state: state.into(), result, error }
+		}))
+	}
+}
@@ -0,0 +1,37 @@
+//! `ipfs_getCachedContent`: serve content the IPFS offchain worker fetched from the node's
+//! off-chain storage, including results too large to be submitted on-chain.
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use node_template_runtime::pallet_ipfs::CAT_CACHE_PREFIX;
+use sp_core::{offchain::OffchainStorage, Bytes};
+
+/// IPFS cache RPC methods.
+#[rpc(client, server)]
+pub trait IpfsCacheApi {
+	/// The content with the given `Cid`, if this node's offchain worker has fetched it.
+	#[method(name = "ipfs_getCachedContent")]
+	fn get_cached_content(&self, cid: String) -> RpcResult<Option<Bytes>>;
+}
+
+/// Implementation of the `ipfs_getCachedContent` RPC.
+pub struct IpfsCache<S> {
+	storage: S,
+}
+
+impl<S> IpfsCache<S> {
+	/// Create a new `IpfsCache` RPC handler reading from the node's off-chain storage.
+	pub fn new(storage: S) -> Self {
+		Self { storage }
+	}
+}
+
+impl<S> IpfsCacheApiServer for IpfsCache<S>
+where
+	S: OffchainStorage + 'static,
+{
+	fn get_cached_content(&self, cid: String) -> RpcResult<Option<Bytes>> {
+		let mut key = CAT_CACHE_PREFIX.to_vec();
+		key.extend_from_slice(cid.as_bytes());
+		Ok(self.storage.get(sp_offchain::STORAGE_PREFIX, &key).map(Into::into))
+	}
+}
@@ -0,0 +1,68 @@
+//! `mpesa_accountHistory`: read the transactions the mpesa-user pallet recorded for an account.
+
+use std::sync::Arc;
+
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use node_template_runtime::{
+	opaque::Block,
+	pallet_mpesa_user::{runtime_api::MpesaUserApi as MpesaUserRuntimeApi, TransactionRecord},
+	AccountId, BlockNumber,
+};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// Mpesa-user RPC methods.
+#[rpc(client, server)]
+pub trait MpesaApi<BlockHash> {
+	/// The last transactions of `account`, oldest first.
+	#[method(name = "mpesa_accountHistory")]
+	fn account_history(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<TransactionRecord<AccountId, BlockNumber>>>;
+}
+
+/// Error code returned when the runtime API call fails.
+const RUNTIME_ERROR: i32 = 1;
+
+/// Implementation of the `mpesa_*` RPCs.
+pub struct Mpesa<C> {
+	client: Arc<C>,
+}
+
+impl<C> Mpesa<C> {
+	/// Create a new `Mpesa` RPC handler.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client }
+	}
+}
+
+impl<C> MpesaApiServer<<Block as BlockT>::Hash> for Mpesa<C>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: MpesaUserRuntimeApi<Block, AccountId, BlockNumber>,
+{
+	fn account_history(
+		&self,
+		account: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<TransactionRecord<AccountId, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.account_history(&at, account).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				RUNTIME_ERROR,
+				"Unable to query account history.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+}
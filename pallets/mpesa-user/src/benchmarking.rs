@@ -1,20 +1,414 @@
-//! Benchmarking setup for pallet-template
+//! Benchmarking setup for pallet-mpesa-user
 
 use super::*;
 
 #[allow(unused)]
-use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
+use crate::Pallet as MpesaUser;
+use codec::Decode;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::{
+	traits::{Currency, EnsureOrigin, Get},
+	BoundedVec,
+};
 use frame_system::RawOrigin;
+use sp_runtime::{
+	traits::{Bounded, SaturatedConversion, TrailingZeroInput},
+	FixedPointNumber, FixedU128,
+};
+use sp_std::{vec, vec::Vec};
+
+// a funded liquidity provider that contributed `amount` existential deposits
+fn provider<T: Config>(who: &T::AccountId, amount: u32) -> BalanceOf<T> {
+	T::Currency::make_free_balance_be(who, BalanceOf::<T>::max_value() / 2u32.into());
+	Attestations::<T>::insert(who, VerificationLevel::MAX);
+	MpesaUser::<T>::create_LProviderIdentity(RawOrigin::Signed(who.clone()).into()).unwrap();
+	let amount = T::Currency::minimum_balance() * amount.into();
+	if amount > 0u32.into() {
+		MpesaUser::<T>::contribute_LPShares(RawOrigin::Signed(who.clone()).into(), amount).unwrap();
+	}
+	amount
+}
+
+// a provider's shares locked in escrow for `to` as transfer 0
+fn escrowed<T: Config>(to: &T::AccountId) -> (T::AccountId, u128) {
+	let from: T::AccountId = account("from", 0, 0);
+	let shares: u128 = provider::<T>(&from, 1_000).saturated_into();
+	provider::<T>(to, 0);
+	MpesaUser::<T>::initiate_transfer(RawOrigin::Signed(from.clone()).into(), to.clone(), shares)
+		.unwrap();
+	(from, shares)
+}
+
+// an account holding the pool tokens for `shillings`
+fn token_holder<T: Config>(who: &T::AccountId, shillings: u128) {
+	let tokens = shillings * CENTS_PER_SHILLING;
+	Tokens::<T>::insert(who, tokens);
+	TokenIssuance::<T>::mutate(|issuance| *issuance += tokens);
+}
+
+// an account holding the pool tokens for twice a withdrawal of 1_000 shillings, with a worker to
+// pay the withdrawal out
+fn withdrawing_holder<T: Config>(who: &T::AccountId) {
+	let worker = T::Public::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+	Workers::<T>::put(vec![worker]);
+	Attestations::<T>::insert(who, VerificationLevel::MAX);
+	token_holder::<T>(who, 2_000);
+}
+
+// the key of a worker, and a signature the unsigned calls accept without checking it
+fn worker<T: Config>() -> (T::Public, T::Signature) {
+	let public = T::Public::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+	let signature = T::Signature::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+	(public, signature)
+}
+
+// a full history for `who`, with one transaction short of an archive dropped from it already, so
+// that the next transaction publishes an archive
+fn archiving_history<T: Config>(who: &T::AccountId) {
+	let block = frame_system::Pallet::<T>::block_number();
+	let record = TransactionRecord {
+		kind: TransactionKind::Deposit,
+		amount: 1_000,
+		counterparty: None,
+		block,
+		receipt: None,
+	};
+	let history = vec![record.clone(); T::MaxHistory::get() as usize];
+	History::<T>::insert(who, BoundedVec::try_from(history).unwrap());
+	let pruned = vec![record; T::HistoryArchiveBatch::get().saturating_sub(1) as usize];
+	PrunedHistory::<T>::insert(who, BoundedVec::try_from(pruned).unwrap());
+}
+
+// a pool holding `amount` existential deposits against as many pool tokens, contributed by a
+// provider, with the caller funded and holding pool tokens to swap
+fn swap_pool<T: Config>(caller: &T::AccountId, amount: u32) -> u128 {
+	let lp: T::AccountId = account("lp", 0, 0);
+	provider::<T>(&lp, 0);
+	let amount = T::Currency::minimum_balance() * amount.into();
+	let tokens: u128 = amount.saturated_into();
+	Tokens::<T>::insert(&lp, tokens);
+	MpesaUser::<T>::add_liquidity(RawOrigin::Signed(lp).into(), amount, tokens).unwrap();
+	T::Currency::make_free_balance_be(caller, BalanceOf::<T>::max_value() / 2u32.into());
+	Tokens::<T>::insert(caller, tokens);
+	tokens
+}
 
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+	create_LProviderIdentity {
+		let caller: T::AccountId = whitelisted_caller();
+		Attestations::<T>::insert(&caller, VerificationLevel::MAX);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(LiquidityProviders::<T>::contains_key(&caller));
+	}
+
+	contribute_LPShares {
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = provider::<T>(&caller, 1_000);
+	}: _(RawOrigin::Signed(caller.clone()), amount)
+	verify {
+		assert_eq!(PoolBalance::<T>::get(), amount + amount);
+	}
+
+	initiate_transfer {
+		let caller: T::AccountId = whitelisted_caller();
+		let to: T::AccountId = account("to", 0, 0);
+		let shares: u128 = provider::<T>(&caller, 1_000).saturated_into();
+		provider::<T>(&to, 0);
+	}: _(RawOrigin::Signed(caller.clone()), to, shares)
+	verify {
+		assert_eq!(LockedShares::<T>::get(&caller), shares);
+	}
+
+	accept_transfer {
+		let caller: T::AccountId = whitelisted_caller();
+		let (_, shares) = escrowed::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), 0)
+	verify {
+		assert_eq!(Shares::<T>::get(&caller), shares);
+	}
+
+	cancel_transfer {
+		let to: T::AccountId = account("to", 0, 0);
+		let (from, _) = escrowed::<T>(&to);
+		let expires_at = Escrows::<T>::get(0).unwrap().expires_at;
+		frame_system::Pallet::<T>::set_block_number(expires_at);
+	}: _(RawOrigin::Signed(from.clone()), 0)
+	verify {
+		assert_eq!(LockedShares::<T>::get(&from), 0);
+	}
+
+	resolve_transfer {
+		let caller: T::AccountId = whitelisted_caller();
+		let (_, shares) = escrowed::<T>(&caller);
+		let origin = T::DisputeOrigin::successful_origin();
+	}: _<T::Origin>(origin, 0, true)
+	verify {
+		assert_eq!(Shares::<T>::get(&caller), shares);
+	}
+
+	withdraw_LPShares {
+		let caller: T::AccountId = whitelisted_caller();
+		let shares: u128 = provider::<T>(&caller, 1_000).saturated_into();
+	}: _(RawOrigin::Signed(caller.clone()), shares)
+	verify {
+		assert_eq!(PoolBalance::<T>::get(), 0u32.into());
+	}
+
+	add_liquidity {
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = provider::<T>(&caller, 1_000);
+		let tokens: u128 = amount.saturated_into();
+		Tokens::<T>::insert(&caller, tokens);
+	}: _(RawOrigin::Signed(caller.clone()), amount, tokens)
+	verify {
+		assert_eq!(TokenReserve::<T>::get(), tokens);
+	}
+
+	delete_LProviderIdentity {
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+		provider::<T>(&caller, 0);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(!LiquidityProviders::<T>::contains_key(&caller));
+	}
+
+	request_deposit {
+		let caller: T::AccountId = whitelisted_caller();
+		let worker = T::Public::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+		Workers::<T>::put(vec![worker]);
+		// the queue is decoded and re-encoded whole, so fill it up to its last free slot
+		let pending = T::MaxPendingDeposits::get().saturating_sub(1) as u64;
+		let queue: Vec<DepositId> = (0..pending).collect();
+		PendingStkPushes::<T>::put(BoundedVec::try_from(queue).unwrap());
+		NextDepositId::<T>::put(pending);
+	}: _(RawOrigin::Signed(caller), [7; 32], 1_000)
+	verify {
+		assert!(Deposits::<T>::contains_key(pending));
+	}
+
+	confirm_payment {
+		let caller: T::AccountId = whitelisted_caller();
+		let worker = T::Public::decode(&mut TrailingZeroInput::zeroes()).unwrap();
+		Workers::<T>::put(vec![worker]);
+		archiving_history::<T>(&caller);
+		let deposit = NextDepositId::<T>::get();
+		MpesaUser::<T>::request_deposit(RawOrigin::Signed(caller).into(), [7; 32], 1_000).unwrap();
+		let checkout_request_id: CheckoutRequestId = vec![1; 64].try_into().unwrap();
+		Deposits::<T>::mutate(deposit, |deposit| {
+			deposit.as_mut().unwrap().checkout_request_id = Some(checkout_request_id.clone());
+		});
+		CheckoutRequests::<T>::insert(&checkout_request_id, deposit);
+		let receipt: MpesaReceipt = vec![2; 32].try_into().unwrap();
+		let origin = T::OracleOrigin::successful_origin();
+	}: _<T::Origin>(origin, checkout_request_id, receipt.clone(), 1_000)
+	verify {
+		assert_eq!(Receipts::<T>::get(&receipt), Some(deposit));
+	}
+
+	submit_checkout_request {
+		let caller: T::AccountId = whitelisted_caller();
+		let (worker, signature) = worker::<T>();
+		Workers::<T>::put(vec![worker.clone()]);
+		// the request is found and dropped in a full queue
+		let pending = T::MaxPendingDeposits::get().saturating_sub(1) as u64;
+		let queue: Vec<DepositId> = (0..pending).collect();
+		PendingStkPushes::<T>::put(BoundedVec::try_from(queue).unwrap());
+		NextDepositId::<T>::put(pending);
+		MpesaUser::<T>::request_deposit(RawOrigin::Signed(caller).into(), [7; 32], 1_000).unwrap();
+		let checkout_request_id: CheckoutRequestId = vec![1; 64].try_into().unwrap();
+		let payload = CheckoutPayload {
+			public: worker,
+			block_number: frame_system::Pallet::<T>::block_number(),
+			deposit: pending,
+			checkout_request_id: checkout_request_id.clone(),
+		};
+	}: _(RawOrigin::None, payload, signature)
+	verify {
+		assert_eq!(CheckoutRequests::<T>::get(&checkout_request_id), Some(pending));
+	}
+
+	set_parameter {
+		let origin = T::AdminOrigin::successful_origin();
+		let parameter = Parameter::ConfirmationTimeout(T::BlockNumber::from(50u32));
+	}: _<T::Origin>(origin, parameter)
+	verify {
+		assert_eq!(Parameters::<T>::get().confirmation_timeout, 50u32.into());
+	}
+
+	attest_identity {
+		let who: T::AccountId = account("who", 0, 0);
+		provider::<T>(&who, 0);
+		let origin = T::VerifierOrigin::successful_origin();
+	}: _<T::Origin>(origin, who.clone(), 1)
+	verify {
+		assert_eq!(LiquidityProviders::<T>::get(&who).unwrap().verification, 1);
+	}
+
+	revoke_attestation {
+		let who: T::AccountId = account("who", 0, 0);
+		provider::<T>(&who, 0);
+		let origin = T::VerifierOrigin::successful_origin();
+	}: _<T::Origin>(origin, who.clone())
+	verify {
+		assert!(!Attestations::<T>::contains_key(&who));
+	}
+
+	claim_rewards {
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = provider::<T>(&caller, 1_000);
+		let shares: u128 = amount.saturated_into();
+		// a whole unit of each asset earned per share, held by the pool's account
+		RewardPerShare::<T>::insert(Asset::Native, REWARD_PRECISION);
+		RewardPerShare::<T>::insert(Asset::Token, REWARD_PRECISION);
+		T::Currency::make_free_balance_be(&MpesaUser::<T>::account_id(), amount + amount);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(Tokens::<T>::get(&caller), shares);
+	}
+
+	swap_exact_in {
+		let caller: T::AccountId = whitelisted_caller();
+		let reserve = swap_pool::<T>(&caller, 1_000);
+	}: _(RawOrigin::Signed(caller.clone()), Asset::Native, reserve / 10, 0)
+	verify {
+		assert!(TokenReserve::<T>::get() < reserve);
+	}
+
+	swap_exact_out {
+		let caller: T::AccountId = whitelisted_caller();
+		let reserve = swap_pool::<T>(&caller, 1_000);
+	}: _(RawOrigin::Signed(caller.clone()), Asset::Token, reserve / 10, reserve)
+	verify {
+		assert!(TokenReserve::<T>::get() > reserve);
+	}
+
+	request_withdrawal {
+		let caller: T::AccountId = whitelisted_caller();
+		withdrawing_holder::<T>(&caller);
+		let pending = T::MaxPendingWithdrawals::get().saturating_sub(1) as u64;
+		let queue: Vec<WithdrawalId> = (0..pending).collect();
+		PendingB2cPayments::<T>::put(BoundedVec::try_from(queue).unwrap());
+		NextWithdrawalId::<T>::put(pending);
+	}: _(RawOrigin::Signed(caller), [7; 32], 1_000)
+	verify {
+		assert!(Withdrawals::<T>::contains_key(pending));
+	}
+
+	submit_conversation {
+		let caller: T::AccountId = whitelisted_caller();
+		withdrawing_holder::<T>(&caller);
+		let (worker, signature) = worker::<T>();
+		let pending = T::MaxPendingWithdrawals::get().saturating_sub(1) as u64;
+		let queue: Vec<WithdrawalId> = (0..pending).collect();
+		PendingB2cPayments::<T>::put(BoundedVec::try_from(queue).unwrap());
+		NextWithdrawalId::<T>::put(pending);
+		MpesaUser::<T>::request_withdrawal(RawOrigin::Signed(caller).into(), [7; 32], 1_000)
+			.unwrap();
+		let conversation_id: ConversationId = vec![1; 64].try_into().unwrap();
+		let payload = ConversationPayload {
+			public: worker,
+			block_number: frame_system::Pallet::<T>::block_number(),
+			withdrawal: pending,
+			conversation_id: conversation_id.clone(),
+		};
+	}: _(RawOrigin::None, payload, signature)
+	verify {
+		assert_eq!(Conversations::<T>::get(&conversation_id), Some(pending));
+	}
+
+	confirm_withdrawal {
+		let caller: T::AccountId = whitelisted_caller();
+		withdrawing_holder::<T>(&caller);
+		archiving_history::<T>(&caller);
+		let withdrawal = NextWithdrawalId::<T>::get();
+		MpesaUser::<T>::request_withdrawal(RawOrigin::Signed(caller).into(), [7; 32], 1_000)
+			.unwrap();
+		let conversation_id: ConversationId = vec![1; 64].try_into().unwrap();
+		Withdrawals::<T>::mutate(withdrawal, |withdrawal| {
+			withdrawal.as_mut().unwrap().conversation_id = Some(conversation_id.clone());
+		});
+		Conversations::<T>::insert(&conversation_id, withdrawal);
+		let receipt: MpesaReceipt = vec![2; 32].try_into().unwrap();
+		let origin = T::OracleOrigin::successful_origin();
+	}: _<T::Origin>(origin, conversation_id, receipt.clone(), 1_000)
+	verify {
+		assert_eq!(PayoutReceipts::<T>::get(&receipt), Some(withdrawal));
+	}
+
+	submit_receipt {
+		let who: T::AccountId = account("who", 0, 0);
+		let (worker, signature) = worker::<T>();
+		let block = frame_system::Pallet::<T>::block_number();
+		let receipt = Receipt {
+			kind: TransactionKind::Deposit,
+			id: 0,
+			who: who.clone(),
+			tokens: 1_000,
+			mpesa_receipt: vec![2; 32].try_into().unwrap(),
+			block,
+		};
+		PendingReceipts::<T>::insert(0, receipt.clone());
+		// a full history searched from its newest record down to the oldest, which is the deposit's
+		let history: Vec<_> = (0..T::MaxHistory::get())
+			.map(|i| TransactionRecord {
+				kind: TransactionKind::Deposit,
+				amount: if i == 0 { 1_000 } else { 0 },
+				counterparty: None,
+				block,
+				receipt: None,
+			})
+			.collect();
+		History::<T>::insert(&who, BoundedVec::try_from(history).unwrap());
+		let payload = ReceiptPayload {
+			public: worker,
+			block_number: block,
+			receipt: 0,
+			document: receipts::document(&receipt),
+		};
+	}: _(RawOrigin::None, payload, signature)
+	verify {
+		assert!(!PendingReceipts::<T>::contains_key(0));
+	}
+
+	submit_rate {
+		let (worker, signature) = worker::<T>();
+		let block = frame_system::Pallet::<T>::block_number();
+		let rate = FixedU128::saturating_from_integer(130u32);
+		let payload = RatePayload { public: worker, block_number: block, rate };
+	}: _(RawOrigin::None, payload, signature)
+	verify {
+		assert_eq!(KesRate::<T>::get(), Some((rate, block)));
+	}
+
+	set_workers {
+		let workers: Vec<T::Public> = (0..T::MaxWorkers::get())
+			.map(|i| T::Public::decode(&mut TrailingZeroInput::new(&i.to_le_bytes())).unwrap())
+			.collect();
+		let origin = T::AdminOrigin::successful_origin();
+	}: _<T::Origin>(origin, workers)
+	verify {
+		assert_eq!(Workers::<T>::get().len(), T::MaxWorkers::get() as usize);
+	}
+
+	repin_receipts {
+		let n in 1 .. MAX_REPINS;
+		let (worker, signature) = worker::<T>();
+		// receipts published a grace period ago that the store doesn't hold
+		let receipts: Vec<ReceiptCid> =
+			(0..n).map(|i| i.to_le_bytes().to_vec().try_into().unwrap()).collect();
+		for cid in &receipts {
+			PublishedReceipts::<T>::insert(cid, T::BlockNumber::from(0u32));
+		}
+		let block = T::ReceiptPinGrace::get();
+		frame_system::Pallet::<T>::set_block_number(block);
+		let payload = RepinPayload { public: worker, block_number: block, receipts };
+	}: _(RawOrigin::None, payload, signature)
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		frame_system::Pallet::<T>::assert_last_event(
+			<T as Config>::Event::from(Event::ReceiptRepinned(vec![n as u8 - 1, 0, 0, 0])).into(),
+		);
 	}
 
-	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
+	impl_benchmark_test_suite!(MpesaUser, crate::mock::new_test_ext(), crate::mock::Test);
 }
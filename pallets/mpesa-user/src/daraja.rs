@@ -0,0 +1,292 @@
+//! A minimal client for Safaricom's Daraja API, which the offchain worker uses to initiate M-Pesa
+//! payments into the pool (STK pushes) and out of it (B2C payments).
+//!
+//! The API credentials and the phone numbers behind the on-chain phone hashes never go on-chain:
+//! the node operator stores them in the node's persistent off-chain storage, e.g. with the
+//! `offchain_localStorageSet` RPC, under `CONFIG_KEY`, `B2C_CONFIG_KEY` and `PHONE_PREFIX`.
+
+use codec::{Decode, Encode};
+use sp_core::offchain::Duration;
+use sp_runtime::offchain::{http, storage::StorageValueRef};
+use sp_std::{str, vec, vec::Vec};
+
+/// The off-chain storage key of the SCALE-encoded `DarajaConfig`.
+pub const CONFIG_KEY: &[u8] = b"mpesa::daraja::config";
+/// The off-chain storage key of the SCALE-encoded `B2cConfig`.
+pub const B2C_CONFIG_KEY: &[u8] = b"mpesa::daraja::b2c";
+/// The prefix of the off-chain storage keys of phone numbers, followed by the phone's hash.
+pub const PHONE_PREFIX: &[u8] = b"mpesa::daraja::phone::";
+
+// how long a single Daraja request may take
+const TIMEOUT_MS: u64 = 10_000;
+// Daraja timestamps are in East Africa Time, UTC+3
+const EAT_OFFSET_SECS: u64 = 3 * 60 * 60;
+
+/// The node-local Daraja API credentials.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct DarajaConfig {
+	/// The API's base URL, e.g. `https://sandbox.safaricom.co.ke`.
+	pub base_url: Vec<u8>,
+	/// The consumer key of the Daraja app.
+	pub consumer_key: Vec<u8>,
+	/// The consumer secret of the Daraja app.
+	pub consumer_secret: Vec<u8>,
+	/// The paybill or till number payments are made to.
+	pub shortcode: Vec<u8>,
+	/// The Lipa na M-Pesa Online passkey of the shortcode.
+	pub passkey: Vec<u8>,
+	/// The URL Safaricom posts payment results to.
+	pub callback_url: Vec<u8>,
+}
+
+/// The node-local settings of B2C payments, which pay out of the pool to phones.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct B2cConfig {
+	/// The API operator initiating payments.
+	pub initiator_name: Vec<u8>,
+	/// The initiator's password, encrypted with Safaricom's public key.
+	pub security_credential: Vec<u8>,
+	/// The shortcode payments are made from.
+	pub shortcode: Vec<u8>,
+	/// The URL Safaricom posts payment results to.
+	pub result_url: Vec<u8>,
+	/// The URL Safaricom posts to when a payment timed out in its queue.
+	pub timeout_url: Vec<u8>,
+}
+
+/// Why a Daraja request failed.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DarajaError {
+	/// The node has no `DarajaConfig`.
+	NotConfigured,
+	/// The node doesn't know the phone number behind a phone hash.
+	UnknownPhone,
+	/// The request couldn't be sent or timed out.
+	Http,
+	/// Daraja rejected the request or answered with something unexpected.
+	UnexpectedResponse,
+}
+
+/// The node's Daraja credentials, if the operator configured them.
+pub fn config() -> Option<DarajaConfig> {
+	StorageValueRef::persistent(CONFIG_KEY).get::<DarajaConfig>().ok().flatten()
+}
+
+/// The node's B2C settings, if the operator configured them.
+pub fn b2c_config() -> Option<B2cConfig> {
+	StorageValueRef::persistent(B2C_CONFIG_KEY).get::<B2cConfig>().ok().flatten()
+}
+
+/// The phone number (in the `2547XXXXXXXX` form) whose blake2-256 hash is `phone_hash`, if the
+/// operator stored it.
+pub fn phone_number(phone_hash: &[u8; 32]) -> Option<Vec<u8>> {
+	let mut key = PHONE_PREFIX.to_vec();
+	key.extend_from_slice(phone_hash);
+	let phone = StorageValueRef::persistent(&key).get::<Vec<u8>>().ok().flatten()?;
+	if sp_io::hashing::blake2_256(&phone) != *phone_hash {
+		return None;
+	}
+	Some(phone)
+}
+
+/// Obtain an OAuth access token for the configured app.
+pub fn access_token(config: &DarajaConfig) -> Result<Vec<u8>, DarajaError> {
+	let url = [&config.base_url[..], b"/oauth/v1/generate?grant_type=client_credentials"].concat();
+	let credentials =
+		base64(&[&config.consumer_key[..], b":", &config.consumer_secret[..]].concat());
+	let authorization = [&b"Basic "[..], &credentials].concat();
+
+	let request =
+		http::Request::get(utf8(&url)?).add_header("Authorization", utf8(&authorization)?);
+	let body = send(request)?;
+	json_string(&body, b"access_token").ok_or(DarajaError::UnexpectedResponse)
+}
+
+/// Ask Daraja to prompt `phone` to pay `amount` shillings for a deposit to the configured shortcode
+/// (an STK push), returning the `CheckoutRequestID` the payment's result will refer to.
+pub fn stk_push(
+	config: &DarajaConfig,
+	token: &[u8],
+	phone: &[u8],
+	amount: u32,
+	deposit: u64,
+	unix_millis: u64,
+) -> Result<Vec<u8>, DarajaError> {
+	let timestamp = timestamp(unix_millis);
+	let password = base64(&[&config.shortcode[..], &config.passkey, &timestamp].concat());
+	let mut amount_digits = Vec::new();
+	write_decimal(&mut amount_digits, amount.into());
+	let mut reference = b"LP".to_vec();
+	write_decimal(&mut reference, deposit.into());
+
+	let body = json_object(&[
+		(b"BusinessShortCode", &config.shortcode),
+		(b"Password", &password),
+		(b"Timestamp", &timestamp),
+		(b"TransactionType", b"CustomerPayBillOnline"),
+		(b"Amount", &amount_digits),
+		(b"PartyA", phone),
+		(b"PartyB", &config.shortcode),
+		(b"PhoneNumber", phone),
+		(b"CallBackURL", &config.callback_url),
+		(b"AccountReference", &reference),
+		(b"TransactionDesc", b"Liquidity pool deposit"),
+	]);
+	let response = post(config, token, b"/mpesa/stkpush/v1/processrequest", body)?;
+	json_string(&response, b"CheckoutRequestID").ok_or(DarajaError::UnexpectedResponse)
+}
+
+/// Ask Daraja to pay `amount` shillings for a withdrawal from the B2C shortcode to `phone`,
+/// returning the `ConversationID` the payment's result will refer to.
+pub fn b2c_payment(
+	config: &DarajaConfig,
+	b2c: &B2cConfig,
+	token: &[u8],
+	phone: &[u8],
+	amount: u32,
+	withdrawal: u64,
+) -> Result<Vec<u8>, DarajaError> {
+	let mut amount_digits = Vec::new();
+	write_decimal(&mut amount_digits, amount.into());
+	let mut occasion = b"LP".to_vec();
+	write_decimal(&mut occasion, withdrawal.into());
+
+	let body = json_object(&[
+		(b"InitiatorName", &b2c.initiator_name),
+		(b"SecurityCredential", &b2c.security_credential),
+		(b"CommandID", b"BusinessPayment"),
+		(b"Amount", &amount_digits),
+		(b"PartyA", &b2c.shortcode),
+		(b"PartyB", phone),
+		(b"Remarks", b"Liquidity pool withdrawal"),
+		(b"QueueTimeOutURL", &b2c.timeout_url),
+		(b"ResultURL", &b2c.result_url),
+		(b"Occasion", &occasion),
+	]);
+	let response = post(config, token, b"/mpesa/b2c/v1/paymentrequest", body)?;
+	json_string(&response, b"ConversationID").ok_or(DarajaError::UnexpectedResponse)
+}
+
+// post a JSON request to an API endpoint and return the body of its accepted response
+fn post(
+	config: &DarajaConfig,
+	token: &[u8],
+	path: &[u8],
+	body: Vec<u8>,
+) -> Result<Vec<u8>, DarajaError> {
+	let url = [&config.base_url[..], path].concat();
+	let authorization = [&b"Bearer "[..], token].concat();
+	let request = http::Request::post(utf8(&url)?, vec![body])
+		.add_header("Authorization", utf8(&authorization)?)
+		.add_header("Content-Type", "application/json");
+	let response = send(request)?;
+	if json_string(&response, b"ResponseCode").as_deref() != Some(&b"0"[..]) {
+		return Err(DarajaError::UnexpectedResponse);
+	}
+	Ok(response)
+}
+
+// send a request and return the body of its successful response
+pub(crate) fn send(request: http::Request<Vec<Vec<u8>>>) -> Result<Vec<u8>, DarajaError> {
+	let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(TIMEOUT_MS));
+	let pending = request.deadline(deadline).send().map_err(|_| DarajaError::Http)?;
+	let response = pending
+		.try_wait(deadline)
+		.map_err(|_| DarajaError::Http)?
+		.map_err(|_| DarajaError::Http)?;
+	if response.code != 200 {
+		log::warn!("M-Pesa: Daraja responded with status {}", response.code);
+		return Err(DarajaError::UnexpectedResponse);
+	}
+	Ok(response.body().collect())
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, DarajaError> {
+	str::from_utf8(bytes).map_err(|_| DarajaError::NotConfigured)
+}
+
+/// A Daraja timestamp (`YYYYMMDDHHmmss` in East Africa Time) of a Unix time in milliseconds.
+pub fn timestamp(unix_millis: u64) -> Vec<u8> {
+	let secs = unix_millis / 1_000 + EAT_OFFSET_SECS;
+	let (days, secs) = (secs / 86_400, secs % 86_400);
+
+	// civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+	let z = days + 719_468;
+	let era = z / 146_097;
+	let doe = z % 146_097;
+	let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + (month <= 2) as u64;
+
+	let mut out = Vec::with_capacity(14);
+	for (value, width) in
+		[(year, 4u32), (month, 2), (day, 2), (secs / 3_600, 2), (secs / 60 % 60, 2), (secs % 60, 2)]
+	{
+		for i in (0..width).rev() {
+			out.push(b'0' + (value / 10u64.pow(i) % 10) as u8);
+		}
+	}
+	out
+}
+
+/// The standard, padded base64 encoding of `data`.
+pub fn base64(data: &[u8]) -> Vec<u8> {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	let mut out = Vec::with_capacity((data.len() + 2) / 3 * 4);
+	for chunk in data.chunks(3) {
+		let n = (chunk[0] as u32) << 16
+			| (*chunk.get(1).unwrap_or(&0) as u32) << 8
+			| *chunk.get(2).unwrap_or(&0) as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]);
+			} else {
+				out.push(b'=');
+			}
+		}
+	}
+	out
+}
+
+/// The value of the string member `key` of a flat JSON object; escape sequences are not
+/// supported, which Daraja's identifiers and tokens don't need.
+pub fn json_string(json: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+	let needle = [&b"\""[..], key, b"\""].concat();
+	let start = json.windows(needle.len()).position(|window| window == &needle[..])?;
+	let rest = skip_whitespace(&json[start + needle.len()..]).strip_prefix(b":")?;
+	let rest = skip_whitespace(rest).strip_prefix(b"\"")?;
+	let end = rest.iter().position(|b| *b == b'"')?;
+	Some(rest[..end].to_vec())
+}
+
+// a flat JSON object of string members; the values are ours or the operator's, none need escaping
+pub(crate) fn json_object(fields: &[(&[u8], &[u8])]) -> Vec<u8> {
+	let mut json = Vec::new();
+	for (i, &(key, value)) in fields.iter().enumerate() {
+		json.push(if i == 0 { b'{' } else { b',' });
+		json.extend([&b"\""[..], key, b"\":\"", value, b"\""].concat());
+	}
+	json.push(b'}');
+	json
+}
+
+pub(crate) fn skip_whitespace(json: &[u8]) -> &[u8] {
+	let start = json.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(json.len());
+	&json[start..]
+}
+
+pub(crate) fn write_decimal(out: &mut Vec<u8>, mut value: u128) {
+	let start = out.len();
+	loop {
+		out.push(b'0' + (value % 10) as u8);
+		value /= 10;
+		if value == 0 {
+			break;
+		}
+	}
+	out[start..].reverse();
+}
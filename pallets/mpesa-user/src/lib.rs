@@ -1,7 +1,121 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+// the LP extrinsics keep the names the pool's clients already use
+#![allow(non_snake_case)]
 
+/// A liquidity pool backing M-Pesa transfers: liquidity providers register an identity,
+/// contribute to the pool in exchange for shares, and transfer or redeem those shares. The pool's
+/// funds are held by an account derived from the pallet's `PalletId`.
 pub use pallet::*;
 
+use frame_support::traits::Currency;
+use sp_core::crypto::KeyTypeId;
+use sp_std::{marker::PhantomData, vec::Vec};
+
+pub mod daraja;
+pub mod math;
+pub mod rates;
+pub mod receipts;
+pub mod runtime_api;
+pub mod weights;
+
+use math::{mul_div, mul_div_ceil, REWARD_PRECISION};
+use mpesa_primitives::{collect_page, IndexFilter, Page, PageRequest};
+pub use mpesa_primitives::{AsyncOp, IpfsStore, PromiseRegistry, WebhookNotifier};
+use pallet_ocw_jobs::{claim, claim_until, release, JobHandler, Jobs, Outcome};
+use runtime_api::{PoolPosition, Settlement};
+pub use weights::WeightInfo;
+
+/// The key type of the keys the offchain worker signs its M-Pesa reports with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"mpsa");
+
+/// The crypto used by the offchain worker to sign its M-Pesa reports.
+pub mod crypto {
+	use super::KEY_TYPE;
+	use sp_core::sr25519::Signature as Sr25519Signature;
+	use sp_runtime::{
+		app_crypto::{app_crypto, sr25519},
+		traits::Verify,
+		MultiSignature, MultiSigner,
+	};
+
+	app_crypto!(sr25519, KEY_TYPE);
+
+	/// The identifier used to look up the worker's signing key in the keystore.
+	pub struct OcwAuthId;
+
+	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for OcwAuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+
+	impl frame_system::offchain::AppCrypto<<Sr25519Signature as Verify>::Signer, Sr25519Signature>
+		for OcwAuthId
+	{
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+}
+
+/// The ticker wallets show pool tokens under.
+pub const POOL_TOKEN_SYMBOL: &str = "mKES";
+
+/// Pool tokens have two decimals, as shillings do.
+pub const POOL_TOKEN_DECIMALS: u8 = 2;
+
+/// Each shilling paid into the pool's shortcode mints 100 pool tokens.
+pub const CENTS_PER_SHILLING: u128 = 10u128.pow(POOL_TOKEN_DECIMALS as u32);
+
+// The number of blocks the offchain workers' unsigned payloads stay valid in the pool.
+const PAYLOAD_LONGEVITY: u64 = 5;
+
+// Node-local flag set while a node configured for Daraja holds no worker key, so that its
+// offchain worker warns once rather than on every block.
+const SIGNING_KEY_MISSING: &[u8] = b"mpesa::signing_key_missing";
+
+// The most receipts an offchain worker asks to re-pin at once.
+const MAX_REPINS: u32 = 16;
+
+// The most published receipts an offchain worker checks the pins of per run, and the node-local
+// key of the last one it checked, so that every receipt is checked in turn.
+const REPIN_SCAN: usize = 100;
+const REPIN_CURSOR: &[u8] = b"mpesa::repin_cursor";
+
+// The node-local queues of the STK pushes and B2C payments Daraja failed, waiting to be retried.
+const STK_PUSH_RETRIES: &[u8] = b"mpesa::stk_push_retries";
+const B2C_RETRIES: &[u8] = b"mpesa::b2c_retries";
+
+// The number of times a failed STK push or B2C payment is retried before the worker gives up on
+// it, leaving the deposit or withdrawal to expire on-chain.
+const DARAJA_RETRIES: u32 = 3;
+
+// The most failed STK pushes, and B2C payments, an offchain worker retries per run.
+const DARAJA_RETRY_BATCH: usize = 4;
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+type CheckoutPayloadOf<T> = CheckoutPayload<
+	<T as frame_system::offchain::SigningTypes>::Public,
+	<T as frame_system::Config>::BlockNumber,
+>;
+type ConversationPayloadOf<T> = ConversationPayload<
+	<T as frame_system::offchain::SigningTypes>::Public,
+	<T as frame_system::Config>::BlockNumber,
+>;
+type ReceiptPayloadOf<T> = ReceiptPayload<
+	<T as frame_system::offchain::SigningTypes>::Public,
+	<T as frame_system::Config>::BlockNumber,
+>;
+type RepinPayloadOf<T> = RepinPayload<
+	<T as frame_system::offchain::SigningTypes>::Public,
+	<T as frame_system::Config>::BlockNumber,
+>;
+type RatePayloadOf<T> = RatePayload<
+	<T as frame_system::offchain::SigningTypes>::Public,
+	<T as frame_system::Config>::BlockNumber,
+>;
+
 #[cfg(test)]
 mod mock;
 
@@ -11,11 +125,319 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
-// specifying the parameters and types on which it depends.
 #[frame_support::pallet]
 pub mod pallet {
-	use frame_support::pallet_prelude::*;
-	use frame_system::pallet_prelude::*;
+	use super::*;
+	use crate::{
+		daraja::{self, DarajaError},
+		rates,
+	};
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{ExistenceRequirement, ReservableCurrency},
+		PalletId,
+	};
+	use frame_system::{
+		offchain::{
+			AppCrypto, SendTransactionTypes, SendUnsignedTransaction, SignedPayload, Signer,
+			SigningTypes,
+		},
+		pallet_prelude::*,
+	};
+	#[cfg(feature = "std")]
+	use serde::{Deserialize, Serialize};
+	use sp_runtime::{
+		offchain::storage::StorageValueRef,
+		traits::{
+			AccountIdConversion, CheckedAdd, CheckedSub, Hash, One, SaturatedConversion, Zero,
+		},
+		FixedU128, Perbill,
+	};
+	use sp_std::{vec, vec::Vec};
+
+	/// The identifier of a deposit request.
+	pub type DepositId = u64;
+
+	/// How thoroughly a verifier checked an account's identity; 0 means it wasn't.
+	pub type VerificationLevel = u8;
+
+	/// The `CheckoutRequestID` Daraja assigned to an STK push.
+	pub type CheckoutRequestId = BoundedVec<u8, ConstU32<64>>;
+
+	/// The identifier of a withdrawal request.
+	pub type WithdrawalId = u64;
+
+	/// The identifier of an escrowed share transfer.
+	pub type EscrowId = u64;
+
+	/// The `ConversationID` Daraja assigned to a B2C payment.
+	pub type ConversationId = BoundedVec<u8, ConstU32<64>>;
+
+	/// The receipt number M-Pesa issued for a payment, e.g. `NLJ7RT61SV`.
+	pub type MpesaReceipt = BoundedVec<u8, ConstU32<32>>;
+
+	/// The identifier of a receipt document waiting to be published.
+	pub type ReceiptId = u64;
+
+	/// The CID a receipt document was published to IPFS under.
+	pub type ReceiptCid = BoundedVec<u8, ConstU32<128>>;
+
+	/// An M-Pesa payment into the pool, from the phone to the shortcode.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct DepositRequest<AccountId, BlockNumber> {
+		/// The account the deposit is for.
+		pub who: AccountId,
+		/// The blake2-256 hash of the paying phone number.
+		pub phone_hash: [u8; 32],
+		/// The amount to pay, in Kenyan shillings.
+		pub amount: u32,
+		/// The block the deposit was requested in.
+		pub requested_at: BlockNumber,
+		/// The STK push's `CheckoutRequestID`, once the offchain worker initiated it.
+		pub checkout_request_id: Option<CheckoutRequestId>,
+	}
+
+	/// The `CheckoutRequestID` of the STK push an offchain worker initiated for a deposit, signed
+	/// with the worker's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct CheckoutPayload<Public, BlockNumber> {
+		/// The key the payload is signed with; it has to be the deposit's worker.
+		pub public: Public,
+		/// The block the STK push was initiated in.
+		pub block_number: BlockNumber,
+		/// The deposit the STK push is for.
+		pub deposit: DepositId,
+		/// The STK push's `CheckoutRequestID`.
+		pub checkout_request_id: CheckoutRequestId,
+	}
+
+	impl<T: SigningTypes> SignedPayload<T> for CheckoutPayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
+		}
+	}
+
+	/// An M-Pesa payment out of the pool, from the shortcode to the phone.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct WithdrawalRequest<AccountId, BlockNumber> {
+		/// The account withdrawing.
+		pub who: AccountId,
+		/// The blake2-256 hash of the phone number to pay.
+		pub phone_hash: [u8; 32],
+		/// The amount to pay, in Kenyan shillings.
+		pub amount: u32,
+		/// The account's pool tokens locked until the payment is confirmed or times out.
+		pub tokens: u128,
+		/// The pool tokens locked on top of `tokens` to pay the withdrawal fee.
+		pub fee: u128,
+		/// The block the withdrawal was requested in.
+		pub requested_at: BlockNumber,
+		/// The B2C payment's `ConversationID`, once the offchain worker initiated it.
+		pub conversation_id: Option<ConversationId>,
+	}
+
+	/// The `ConversationID` of the B2C payment an offchain worker initiated for a withdrawal,
+	/// signed with the worker's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct ConversationPayload<Public, BlockNumber> {
+		/// The key the payload is signed with; it has to be the withdrawal's worker.
+		pub public: Public,
+		/// The block the B2C payment was initiated in.
+		pub block_number: BlockNumber,
+		/// The withdrawal the B2C payment is for.
+		pub withdrawal: WithdrawalId,
+		/// The B2C payment's `ConversationID`.
+		pub conversation_id: ConversationId,
+	}
+
+	impl<T: SigningTypes> SignedPayload<T> for ConversationPayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
+		}
+	}
+
+	/// The receipt document an offchain worker generated for a confirmed payment, signed with the
+	/// worker's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct ReceiptPayload<Public, BlockNumber> {
+		/// The key the payload is signed with; it has to be the receipt's worker.
+		pub public: Public,
+		/// The block the document was generated in.
+		pub block_number: BlockNumber,
+		/// The receipt the document is for.
+		pub receipt: ReceiptId,
+		/// The document, as `receipts::document` renders it.
+		pub document: Vec<u8>,
+	}
+
+	impl<T: SigningTypes> SignedPayload<T> for ReceiptPayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
+		}
+	}
+
+	/// The published receipts an offchain worker found without an active pin, signed with the
+	/// worker's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct RepinPayload<Public, BlockNumber> {
+		/// The key the payload is signed with; it has to be a worker's.
+		pub public: Public,
+		/// The block the pins were checked in.
+		pub block_number: BlockNumber,
+		/// The CIDs of the receipt documents to pin again.
+		pub receipts: Vec<ReceiptCid>,
+	}
+
+	impl<T: SigningTypes> SignedPayload<T> for RepinPayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
+		}
+	}
+
+	/// The KES exchange rate an offchain worker fetched, signed with the worker's key.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct RatePayload<Public, BlockNumber> {
+		/// The key the payload is signed with; it has to be a worker's.
+		pub public: Public,
+		/// The block the rate was fetched in.
+		pub block_number: BlockNumber,
+		/// The price of a dollar in shillings.
+		pub rate: FixedU128,
+	}
+
+	impl<T: SigningTypes> SignedPayload<T> for RatePayload<T::Public, T::BlockNumber> {
+		fn public(&self) -> T::Public {
+			self.public.clone()
+		}
+	}
+
+	/// The assets the pool trades.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub enum Asset {
+		/// The chain's native currency.
+		Native,
+		/// The pool token, backed by the shillings held by the pool's M-Pesa shortcode.
+		Token,
+	}
+
+	/// What an entry of an account's transaction history records.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub enum TransactionKind {
+		/// Pool tokens minted for a confirmed M-Pesa deposit.
+		Deposit,
+		/// Pool tokens burnt for a completed M-Pesa payout.
+		Withdrawal,
+		/// Native currency contributed to the pool for shares.
+		Contribution,
+		/// Shares redeemed for native currency.
+		Redemption,
+		/// The asset sold to the pool in a swap.
+		Swap(Asset),
+		/// Shares sent to the counterparty.
+		TransferOut,
+		/// Shares received from the counterparty.
+		TransferIn,
+	}
+
+	/// An entry of an account's transaction history.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub struct TransactionRecord<AccountId, BlockNumber> {
+		/// What happened.
+		pub kind: TransactionKind,
+		/// The amount moved: pool tokens for deposits and withdrawals, shares for transfers,
+		/// native currency for contributions and redemptions, and the asset sold for swaps.
+		pub amount: u128,
+		/// The other account of a transfer.
+		pub counterparty: Option<AccountId>,
+		/// The block the transaction was made in.
+		pub block: BlockNumber,
+		/// The CID of the receipt document of a deposit or withdrawal, once it is published.
+		pub receipt: Option<ReceiptCid>,
+	}
+
+	/// A confirmed deposit or withdrawal whose receipt document is waiting to be published.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct Receipt<AccountId, BlockNumber> {
+		/// `Deposit` or `Withdrawal`.
+		pub kind: TransactionKind,
+		/// The deposit's or withdrawal's id.
+		pub id: u64,
+		/// The account the pool tokens were minted for or burnt from.
+		pub who: AccountId,
+		/// The pool tokens minted or burnt.
+		pub tokens: u128,
+		/// The receipt number M-Pesa issued for the payment.
+		pub mpesa_receipt: MpesaReceipt,
+		/// The block the payment was confirmed in.
+		pub block: BlockNumber,
+	}
+
+	/// Shares locked in escrow until their recipient accepts them or the transfer is cancelled.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct Escrow<AccountId, BlockNumber> {
+		/// The provider sending the shares.
+		pub from: AccountId,
+		/// The provider the shares are sent to.
+		pub to: AccountId,
+		/// The shares locked.
+		pub shares: u128,
+		/// The block from which the transfer can no longer be accepted, and can be cancelled.
+		pub expires_at: BlockNumber,
+	}
+
+	/// The pool's parameters, set at genesis and changed through the admin origin.
+	#[derive(
+		Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+	)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub struct PoolParameters<BlockNumber, Balance> {
+		/// The part of each swap's input charged as a fee.
+		pub swap_fee: Perbill,
+		/// The fee charged on each withdrawal, in pool tokens on top of the ones paid out.
+		pub withdrawal_fee: Perbill,
+		/// The part of every fee paid to the treasury; the rest is shared by the liquidity
+		/// providers in proportion to their shares.
+		pub protocol_fee: Perbill,
+		/// The smallest amount a provider can contribute at once.
+		pub min_contribution: Balance,
+		/// The most shillings an account can withdraw per day.
+		pub daily_withdrawal_limit: u32,
+		/// The number of blocks a deposit or withdrawal can wait for its payment to be confirmed.
+		pub confirmation_timeout: BlockNumber,
+	}
+
+	/// A change to one of the pool's parameters.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub enum Parameter<BlockNumber, Balance> {
+		/// Set `PoolParameters::swap_fee`; it must be less than 100%.
+		SwapFee(Perbill),
+		/// Set `PoolParameters::withdrawal_fee`.
+		WithdrawalFee(Perbill),
+		/// Set `PoolParameters::protocol_fee`.
+		ProtocolFee(Perbill),
+		/// Set `PoolParameters::min_contribution`.
+		MinContribution(Balance),
+		/// Set `PoolParameters::daily_withdrawal_limit`.
+		DailyWithdrawalLimit(u32),
+		/// Set `PoolParameters::confirmation_timeout`; it must be non-zero.
+		ConfirmationTimeout(BlockNumber),
+	}
+
+	/// What the pool knows about a liquidity provider.
+	#[derive(
+		Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+	)]
+	pub struct ProviderInfo<BlockNumber, Balance> {
+		/// The block the provider registered in.
+		pub joined_at: BlockNumber,
+		/// The total amount the provider contributed to the pool.
+		pub contributed: Balance,
+		/// The level the provider's identity is attested at.
+		pub verification: VerificationLevel,
+	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
@@ -23,46 +445,540 @@ pub mod pallet {
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
 	#[pallet::config]
-	pub trait Config: frame_system::Config {
+	pub trait Config:
+		SendTransactionTypes<Call<Self>> + SigningTypes + frame_system::Config
+	{
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The currency contributions are paid in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// The pool's id, which the account holding its funds is derived from.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+		/// The identifier type of the offchain worker's signing key.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+		/// The most deposits that can wait for their STK push at once.
+		#[pallet::constant]
+		type MaxPendingDeposits: Get<u32>;
+		/// The most withdrawals that can wait for their B2C payment at once.
+		#[pallet::constant]
+		type MaxPendingWithdrawals: Get<u32>;
+		/// The most offchain workers that can initiate M-Pesa payments.
+		#[pallet::constant]
+		type MaxWorkers: Get<u32>;
+		/// The priority of the offchain worker's unsigned transactions.
+		#[pallet::constant]
+		type UnsignedPriority: Get<TransactionPriority>;
+		/// The origin that confirms M-Pesa payments, e.g. a set of oracle accounts relaying
+		/// Safaricom's callbacks.
+		type OracleOrigin: EnsureOrigin<Self::Origin>;
+		/// The origin that attests accounts' identities, e.g. a KYC provider.
+		type VerifierOrigin: EnsureOrigin<Self::Origin>;
+		/// The verification level needed to contribute to the pool.
+		#[pallet::constant]
+		type MinContributionLevel: Get<VerificationLevel>;
+		/// The verification level needed to withdraw to a phone.
+		#[pallet::constant]
+		type MinWithdrawalLevel: Get<VerificationLevel>;
+		/// The origin that changes the pool's parameters, e.g. root or a governance collective.
+		type AdminOrigin: EnsureOrigin<Self::Origin>;
+		/// The number of blocks in a day.
+		#[pallet::constant]
+		type BlocksPerDay: Get<Self::BlockNumber>;
+		/// The number of blocks between the offchain workers' exchange rate updates.
+		#[pallet::constant]
+		type RateInterval: Get<Self::BlockNumber>;
+		/// The number of blocks after its update the exchange rate is no longer current.
+		#[pallet::constant]
+		type MaxRateAge: Get<Self::BlockNumber>;
+		/// The account owed the treasury's part of the fees.
+		#[pallet::constant]
+		type TreasuryAccount: Get<Self::AccountId>;
+		/// The origin that settles disputed share transfers.
+		type DisputeOrigin: EnsureOrigin<Self::Origin>;
+		/// The number of blocks a share transfer can be accepted in.
+		#[pallet::constant]
+		type EscrowTimeout: Get<Self::BlockNumber>;
+		/// The most share transfers that can be in escrow at once.
+		#[pallet::constant]
+		type MaxEscrows: Get<u32>;
+		/// The number of transactions kept in each account's history; older ones are dropped.
+		#[pallet::constant]
+		type MaxHistory: Get<u32>;
+		/// The number of transactions dropped from an account's history that are archived to the
+		/// receipt store together; with 0 they aren't archived.
+		#[pallet::constant]
+		type HistoryArchiveBatch: Get<u32>;
+		/// Where the receipt documents of confirmed payments are published, from
+		/// `receipts_account`; the store shouldn't charge that account deposits or quotas.
+		type ReceiptStore: IpfsStore<Self::AccountId>;
+		/// The number of blocks the receipt store has to pin a published receipt document in.
+		/// The offchain workers re-pin the receipts without an active pin by then, checking them
+		/// every `ReceiptPinGrace` blocks.
+		#[pallet::constant]
+		type ReceiptPinGrace: Get<Self::BlockNumber>;
+		/// Keeps promises of the deposits' and withdrawals' settlements, under their ids; they
+		/// resolve with the hash of the M-Pesa receipt.
+		type Promises: PromiseRegistry<Self::Hash>;
+		/// Pushes the deposits' and withdrawals' settlements and expiries to the accounts'
+		/// webhooks.
+		type Webhooks: WebhookNotifier<Self::AccountId, Self::Hash>;
+		/// Weight information for the pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+	}
+
+	// The pool's parameters.
+	#[pallet::storage]
+	#[pallet::getter(fn parameters)]
+	pub type Parameters<T: Config> =
+		StorageValue<_, PoolParameters<T::BlockNumber, BalanceOf<T>>, ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		pub parameters: PoolParameters<T::BlockNumber, BalanceOf<T>>,
+		/// The providers registered from the first block, each attested at a level and
+		/// contributing an amount of its genesis balance, which makes up the pool's balance.
+		pub providers: Vec<(T::AccountId, VerificationLevel, BalanceOf<T>)>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self {
+				parameters: PoolParameters {
+					swap_fee: Perbill::from_parts(3_000_000),
+					withdrawal_fee: Perbill::from_parts(5_000_000),
+					protocol_fee: Perbill::from_percent(20),
+					min_contribution: T::Currency::minimum_balance(),
+					daily_withdrawal_limit: 150_000,
+					confirmation_timeout: 100u32.into(),
+				},
+				providers: Vec::new(),
+			}
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			<Parameters<T>>::put(&self.parameters);
+			for (who, verification, amount) in &self.providers {
+				assert!(*verification > 0, "genesis providers must be attested");
+				<Attestations<T>>::insert(who, verification);
+				let info = ProviderInfo {
+					joined_at: Zero::zero(),
+					contributed: Zero::zero(),
+					verification: *verification,
+				};
+				<LiquidityProviders<T>>::insert(who, info);
+				if !amount.is_zero() {
+					Pallet::<T>::contribute(who.clone(), *amount, None)
+						.expect("genesis providers can afford their contributions");
+				}
+			}
+		}
 	}
 
-	// The pallet's runtime storage items.
-	// https://docs.substrate.io/main-docs/build/runtime-storage/
+	// The verification level attested for each account; accounts need one to become providers.
+	#[pallet::storage]
+	#[pallet::getter(fn attestation)]
+	pub type Attestations<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, VerificationLevel, ValueQuery>;
+
+	// The registered liquidity providers.
+	#[pallet::storage]
+	#[pallet::getter(fn liquidity_provider)]
+	pub type LiquidityProviders<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, ProviderInfo<T::BlockNumber, BalanceOf<T>>>;
+
+	// The amount held by the pool's account.
+	#[pallet::storage]
+	#[pallet::getter(fn pool_balance)]
+	pub type PoolBalance<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+	// The number of pool shares issued.
+	#[pallet::storage]
+	#[pallet::getter(fn total_shares)]
+	pub type TotalShares<T> = StorageValue<_, u128, ValueQuery>;
+
+	// The pool shares held by each liquidity provider.
+	#[pallet::storage]
+	#[pallet::getter(fn shares)]
+	pub type Shares<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u128, ValueQuery>;
+
+	// The pool tokens held by the pool.
 	#[pallet::storage]
-	#[pallet::getter(fn getLProviderIdentity)]
-	// Learn more about declaring storage items:
-	// https://docs.substrate.io/main-docs/build/runtime-storage/#declaring-storage-items
-	type TotalLiquidityPoolAmount<T> = StorageValue<_, u128, ValueQuery>;
-	type TotalLiquidtyProviders<T> = StorageValue<_, Vec<T::AccountId>, ValueQuery>;
-	pub(super) type LProviderIdentity<T>: map hasher(blake2_128_concat) Vec<u8> => StorageValue<Option<T::AccountId>>;
-	// ( identity, LPshare_key ) => LPshare_value
-	pub(super) type LPShareAmount<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Vec<u8>, ValueQuery>;
+	#[pallet::getter(fn token_reserve)]
+	pub type TokenReserve<T> = StorageValue<_, u128, ValueQuery>;
+
+	// The pool tokens held by each account.
+	#[pallet::storage]
+	#[pallet::getter(fn tokens)]
+	pub type Tokens<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u128, ValueQuery>;
+
+	// The number of pool tokens in existence, including the locked ones.
+	#[pallet::storage]
+	#[pallet::getter(fn token_issuance)]
+	pub type TokenIssuance<T> = StorageValue<_, u128, ValueQuery>;
+
+	// The fees accrued per share in each asset, scaled by `REWARD_PRECISION`.
+	#[pallet::storage]
+	#[pallet::getter(fn reward_per_share)]
+	pub type RewardPerShare<T> = StorageMap<_, Twox64Concat, Asset, u128, ValueQuery>;
+
+	// The part of each provider's accrued fees its current shares were already credited with.
+	#[pallet::storage]
+	#[pallet::getter(fn reward_debt)]
+	pub type RewardDebt<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, Asset, u128, ValueQuery>;
+
+	// The rewards set aside for each account when its shares changed, or owed to the treasury.
+	#[pallet::storage]
+	#[pallet::getter(fn owed_rewards)]
+	pub type OwedRewards<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, Asset, u128, ValueQuery>;
+
+	// The id of the next deposit request.
+	#[pallet::storage]
+	#[pallet::getter(fn next_deposit_id)]
+	pub type NextDepositId<T> = StorageValue<_, DepositId, ValueQuery>;
+
+	// The deposit requests.
+	#[pallet::storage]
+	#[pallet::getter(fn deposit)]
+	pub type Deposits<T: Config> =
+		StorageMap<_, Twox64Concat, DepositId, DepositRequest<T::AccountId, T::BlockNumber>>;
+
+	// The deposits whose STK push the offchain worker is yet to initiate, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_stk_pushes)]
+	pub type PendingStkPushes<T: Config> =
+		StorageValue<_, BoundedVec<DepositId, T::MaxPendingDeposits>, ValueQuery>;
+
+	// The deposit each initiated STK push is for, by `CheckoutRequestID`.
+	#[pallet::storage]
+	#[pallet::getter(fn checkout_request)]
+	pub type CheckoutRequests<T> = StorageMap<_, Blake2_128Concat, CheckoutRequestId, DepositId>;
+
+	// The keys of the offchain workers initiating STK pushes; each deposit is assigned to one of
+	// them, so that the payer is prompted once.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	#[pallet::getter(fn workers)]
+	pub type Workers<T: Config> = StorageValue<_, Vec<T::Public>, ValueQuery>;
+
+	// The deposit each confirmed M-Pesa receipt paid for, so that no payment is credited twice.
+	#[pallet::storage]
+	#[pallet::getter(fn receipt)]
+	pub type Receipts<T> = StorageMap<_, Blake2_128Concat, MpesaReceipt, DepositId>;
+
+	// The id of the next withdrawal request.
+	#[pallet::storage]
+	#[pallet::getter(fn next_withdrawal_id)]
+	pub type NextWithdrawalId<T> = StorageValue<_, WithdrawalId, ValueQuery>;
+
+	// The withdrawal requests.
+	#[pallet::storage]
+	#[pallet::getter(fn withdrawal)]
+	pub type Withdrawals<T: Config> =
+		StorageMap<_, Twox64Concat, WithdrawalId, WithdrawalRequest<T::AccountId, T::BlockNumber>>;
+
+	// The withdrawals whose B2C payment the offchain worker is yet to initiate, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_b2c_payments)]
+	pub type PendingB2cPayments<T: Config> =
+		StorageValue<_, BoundedVec<WithdrawalId, T::MaxPendingWithdrawals>, ValueQuery>;
+
+	// The withdrawal each initiated B2C payment is for, by `ConversationID`.
+	#[pallet::storage]
+	#[pallet::getter(fn conversation)]
+	pub type Conversations<T> = StorageMap<_, Blake2_128Concat, ConversationId, WithdrawalId>;
+
+	// The withdrawal each confirmed M-Pesa receipt paid out.
+	#[pallet::storage]
+	#[pallet::getter(fn payout_receipt)]
+	pub type PayoutReceipts<T> = StorageMap<_, Blake2_128Concat, MpesaReceipt, WithdrawalId>;
+
+	// The day each account last withdrew in and the shillings it withdrew that day.
+	#[pallet::storage]
+	#[pallet::getter(fn daily_withdrawals)]
+	pub type DailyWithdrawals<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::BlockNumber, u32), ValueQuery>;
+
+	// The oldest withdrawal that may still be waiting for its confirmation.
+	#[pallet::storage]
+	#[pallet::getter(fn withdrawal_expiry_cursor)]
+	pub type WithdrawalExpiryCursor<T> = StorageValue<_, WithdrawalId, ValueQuery>;
+
+	// The price of a dollar in shillings, and the block it was fetched in.
+	#[pallet::storage]
+	#[pallet::getter(fn kes_rate)]
+	pub type KesRate<T: Config> = StorageValue<_, (FixedU128, T::BlockNumber)>;
+
+	// The oldest deposit that may still be waiting for its confirmation.
+	#[pallet::storage]
+	#[pallet::getter(fn expiry_cursor)]
+	pub type ExpiryCursor<T> = StorageValue<_, DepositId, ValueQuery>;
+
+	// The id of the next share transfer.
+	#[pallet::storage]
+	#[pallet::getter(fn next_escrow_id)]
+	pub type NextEscrowId<T> = StorageValue<_, EscrowId, ValueQuery>;
+
+	// The share transfers in escrow, at most `MaxEscrows` of them.
+	#[pallet::storage]
+	#[pallet::getter(fn escrow)]
+	pub type Escrows<T: Config> =
+		CountedStorageMap<_, Twox64Concat, EscrowId, Escrow<T::AccountId, T::BlockNumber>>;
+
+	// The shares each provider has locked in escrow.
+	#[pallet::storage]
+	#[pallet::getter(fn locked_shares)]
+	pub type LockedShares<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u128, ValueQuery>;
+
+	// The oldest share transfer that may still be in escrow.
+	#[pallet::storage]
+	#[pallet::getter(fn escrow_expiry_cursor)]
+	pub type EscrowExpiryCursor<T> = StorageValue<_, EscrowId, ValueQuery>;
+
+	// The id of the next receipt document to publish.
+	#[pallet::storage]
+	#[pallet::getter(fn next_receipt_id)]
+	pub type NextReceiptId<T> = StorageValue<_, ReceiptId, ValueQuery>;
+
+	// The confirmed payments whose receipt documents the offchain workers have yet to publish.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_receipt)]
+	pub type PendingReceipts<T: Config> =
+		StorageMap<_, Twox64Concat, ReceiptId, Receipt<T::AccountId, T::BlockNumber>>;
+
+	// The block each published receipt document or history archive was published in, by CID;
+	// the documents have to stay pinned in the receipt store.
+	#[pallet::storage]
+	#[pallet::getter(fn receipt_published_at)]
+	pub type PublishedReceipts<T: Config> =
+		StorageMap<_, Blake2_128Concat, ReceiptCid, T::BlockNumber>;
+
+	// The last `MaxHistory` transactions of each account, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn history)]
+	pub type History<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<TransactionRecord<T::AccountId, T::BlockNumber>, T::MaxHistory>,
+		ValueQuery,
+	>;
+
+	// The transactions dropped from each account's full history, oldest first, until
+	// `HistoryArchiveBatch` of them are archived together.
+	#[pallet::storage]
+	#[pallet::getter(fn pruned_history)]
+	pub type PrunedHistory<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<TransactionRecord<T::AccountId, T::BlockNumber>, T::HistoryArchiveBatch>,
+		ValueQuery,
+	>;
+
+	// The CIDs of the archives of each account's dropped transactions, in archive order; each
+	// holds the SCALE encoding of a batch of `TransactionRecord`s, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn history_archive)]
+	pub type HistoryArchives<T: Config> =
+		StorageDoubleMap<_, Blake2_128Concat, T::AccountId, Twox64Concat, u32, ReceiptCid>;
+
+	// The number of each account's entries in `HistoryArchives`.
+	#[pallet::storage]
+	#[pallet::getter(fn history_archive_count)]
+	pub type HistoryArchiveCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
 
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/main-docs/build/events-errors/
 	#[pallet::event]
-	#[pallet::new_LProvider(pub(super) fn get_newLProvider)]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// Event documentation should end with an array that provides descriptive names for event
-		/// parameters. [something, who]
-		NewLiquidityProvider(u32, T::AccountId),
-		ContributedLPShares(u32, T::AccountId),
-		TransferLPTokens(u32, T::AccountId, T::AccountId),
-		ReceiveLPTokens(u32, T::AccountId, T::AccountId),
+		/// A liquidity provider registered. [who]
+		NewLiquidityProvider(T::AccountId),
+		/// A liquidity provider contributed to the pool. [amount, shares, who]
+		ContributedLPShares(BalanceOf<T>, u128, T::AccountId),
+		/// A liquidity provider locked shares in escrow for another one. [escrow, from, to, shares]
+		TransferInitiated(EscrowId, T::AccountId, T::AccountId, u128),
+		/// The recipient accepted a transfer and received its shares. [escrow]
+		TransferAccepted(EscrowId),
+		/// A transfer expired or was cancelled and its shares were unlocked. [escrow]
+		TransferCancelled(EscrowId),
+		/// The dispute origin settled a transfer, releasing its shares to the recipient or
+		/// unlocking them. [escrow, released]
+		TransferResolved(EscrowId, bool),
+		/// A liquidity provider redeemed shares for their part of the pool. [shares, amount, who]
+		WithdrewLPShares(u128, BalanceOf<T>, T::AccountId),
+		/// A liquidity provider without shares left the pool. [who]
+		LiquidityProviderRemoved(T::AccountId),
+		/// The admin origin changed one of the pool's parameters. [parameter]
+		ParameterSet(Parameter<T::BlockNumber, BalanceOf<T>>),
+		/// A verifier attested an account's identity. [who, level]
+		IdentityAttested(T::AccountId, VerificationLevel),
+		/// A verifier revoked an account's attestation. [who]
+		AttestationRevoked(T::AccountId),
+		/// An account asked to deposit shillings from a phone. [deposit, who, amount]
+		DepositRequested(DepositId, T::AccountId, u32),
+		/// The offchain worker prompted the payer's phone. [deposit, checkout_request_id]
+		DepositInitiated(DepositId, Vec<u8>),
+		/// The offchain workers were replaced. [count]
+		WorkersSet(u32),
+		/// An oracle confirmed a deposit's payment and pool tokens were minted for it.
+		/// [deposit, who, tokens, mpesa_receipt]
+		DepositConfirmed(DepositId, T::AccountId, u128, Vec<u8>),
+		/// A deposit's payment wasn't confirmed in time. [deposit, who]
+		DepositExpired(DepositId, T::AccountId),
+		/// An account locked pool tokens to be paid out to a phone. [withdrawal, who, amount]
+		WithdrawalRequested(WithdrawalId, T::AccountId, u32),
+		/// The offchain worker initiated a withdrawal's payment. [withdrawal, conversation_id]
+		WithdrawalInitiated(WithdrawalId, Vec<u8>),
+		/// An oracle confirmed a withdrawal's payment and its locked pool tokens were burnt.
+		/// [withdrawal, who, tokens, mpesa_receipt]
+		WithdrawalCompleted(WithdrawalId, T::AccountId, u128, Vec<u8>),
+		/// A withdrawal's payment wasn't confirmed in time and its pool tokens were unlocked.
+		/// [withdrawal, who, tokens]
+		WithdrawalRefunded(WithdrawalId, T::AccountId, u128),
+		/// An offchain worker updated the exchange rate. [rate]
+		KesRateUpdated(FixedU128),
+		/// A liquidity provider contributed pool tokens along with its balance. [tokens, who]
+		ContributedTokens(u128, T::AccountId),
+		/// A liquidity provider's redeemed shares paid out pool tokens too. [tokens, who]
+		WithdrewTokens(u128, T::AccountId),
+		/// An account swapped one asset for the other. [who, asset_in, amount_in, amount_out]
+		SwapExecuted(T::AccountId, Asset, u128, u128),
+		/// A fee was split between the treasury and the liquidity providers.
+		/// [asset, treasury, providers]
+		FeesAccrued(Asset, u128, u128),
+		/// An account was paid the fees it earned. [who, native, tokens]
+		RewardsClaimed(T::AccountId, u128, u128),
+		/// A payment's receipt document was published to IPFS. [receipt, who, cid]
+		ReceiptPublished(ReceiptId, T::AccountId, Vec<u8>),
+		/// A published receipt document that lost its pin was queued to be pinned again. [cid]
+		ReceiptRepinned(Vec<u8>),
+		/// Transactions dropped from an account's full history were archived. [who, index, cid]
+		HistoryArchived(T::AccountId, u32, Vec<u8>),
 	}
 
 	// Errors inform users that something went wrong.
 	#[pallet::error]
 	pub enum Error<T> {
-		NoneValue,
+		/// An amount would overflow the pool's or a provider's balance.
 		StorageOverflow,
+		/// The account is already a liquidity provider.
 		LProviderIdentityAlreadyExists,
+		/// The account is not a liquidity provider.
 		LProviderIdentityDoesNotExist,
+		/// The account's identity isn't attested.
+		NotAttested,
+		/// The account's identity isn't attested at the level the operation needs.
+		VerificationLevelTooLow,
+		/// Contributions, transfers and withdrawals must be of a non-zero amount.
 		MinimumLPShareAmountNotMet,
+		/// The pool doesn't hold enough to pay out the withdrawal.
 		NotEnoughLiquidity,
-		LProviderNotAuthorised,
+		/// The liquidity provider doesn't hold enough shares.
+		InsufficientShares,
+		/// A liquidity provider can only leave the pool once all its shares are withdrawn.
+		LProviderHasShares,
+		/// Deposits must be of a non-zero amount.
+		InvalidDepositAmount,
+		/// Too many deposits are waiting for their STK push.
+		TooManyPendingDeposits,
+		/// No offchain worker can initiate the deposit's STK push.
+		NoWorkers,
+		/// More workers than `MaxWorkers` were given.
+		TooManyWorkers,
+		/// The deposit doesn't exist or its STK push was already initiated.
+		DepositNotPending,
+		/// No deposit is waiting for a payment with the `CheckoutRequestID`.
+		UnknownCheckoutRequest,
+		/// The payment's amount isn't the deposit's.
+		AmountMismatch,
+		/// The M-Pesa receipt was already used to confirm a payment.
+		ReceiptAlreadyUsed,
+		/// Withdrawals must be of a non-zero amount.
+		InvalidWithdrawalAmount,
+		/// The withdrawal would exceed the account's daily limit.
+		DailyLimitExceeded,
+		/// Too many withdrawals are waiting for their B2C payment.
+		TooManyPendingWithdrawals,
+		/// The withdrawal doesn't exist or its B2C payment was already initiated.
+		WithdrawalNotPending,
+		/// No withdrawal is waiting for a payment with the `ConversationID`.
+		UnknownConversation,
+		/// Exchange rates must be non-zero.
+		InvalidRate,
+		/// The exchange rate was fetched before the current one.
+		StaleRate,
+		/// The account doesn't hold enough pool tokens.
+		InsufficientTokens,
+		/// Swaps must be of a non-zero amount and buy a non-zero amount.
+		InvalidSwapAmount,
+		/// The swap or contribution would be at a worse price than the caller accepts.
+		SlippageExceeded,
+		/// The account has no rewards to claim.
+		NoRewards,
+		/// The parameter's value is out of its range.
+		InvalidParameter,
+		/// Too many share transfers are in escrow.
+		TooManyEscrows,
+		/// There's no share transfer in escrow with this id.
+		UnknownEscrow,
+		/// Only the transfer's sender can cancel it, and only its recipient accept it.
+		NotEscrowParty,
+		/// The transfer can no longer be accepted.
+		EscrowExpired,
+		/// The transfer can't be cancelled before it expires.
+		EscrowNotExpired,
+		/// There's no receipt document waiting to be published with this id.
+		UnknownReceipt,
+		/// The receipt document doesn't match the confirmed payment.
+		ReceiptMismatch,
+		/// The IPFS store returned a CID too long to record.
+		InvalidReceiptCid,
+		/// The provider still has shares in escrow.
+		SharesInEscrow,
+		/// M-Pesa didn't confirm the payment within the confirmation timeout.
+		ConfirmationTimedOut,
+		/// More receipts than `MAX_REPINS` were given to re-pin.
+		TooManyRepins,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			Self::expire_deposits(now).saturating_add(Self::expire_withdrawals(now))
+		}
+
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			Self::expire_escrows(now, remaining_weight)
+		}
+
+		fn offchain_worker(block_number: T::BlockNumber) {
+			// every task ends in a payload signed with a worker key
+			if !Self::check_signing_key(block_number) {
+				return;
+			}
+			let mut run = DarajaRun { block_number, token: None };
+			if let Err(e) = Self::initiate_stk_pushes(&mut run) {
+				log::warn!("M-Pesa: can't initiate STK pushes: {:?}", e);
+			}
+			if let Err(e) = Self::initiate_b2c_payments(&mut run) {
+				log::warn!("M-Pesa: can't initiate B2C payments: {:?}", e);
+			}
+			if let Err(e) = Self::report_rate(block_number) {
+				log::warn!("M-Pesa: can't report the exchange rate: {:?}", e);
+			}
+			Self::publish_receipts(block_number);
+			Self::repair_receipt_pins(block_number);
+		}
 	}
 
 	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
@@ -70,83 +986,1872 @@ pub mod pallet {
 	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn create_LProviderIdentity(origin: OriginFor<T>, newmember: u32) -> DispatchResult {
-			// add LProvider in Vec<u8> storage and return the LProvider's identity
+		/// Register the caller as a liquidity provider; a verifier must have attested its identity.
+		#[pallet::weight(T::WeightInfo::create_LProviderIdentity())]
+		pub fn create_LProviderIdentity(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				!<LiquidityProviders<T>>::contains_key(&who),
+				Error::<T>::LProviderIdentityAlreadyExists
+			);
+			Self::register_provider(who)
+		}
+
+		/// Transfer `amount` of the caller's balance into the pool, receiving shares worth the same
+		/// part of the pool; the first contribution to an empty pool mints shares 1:1. Once the
+		/// pool holds pool tokens, the caller contributes the same part of them too.
+		#[pallet::weight(T::WeightInfo::contribute_LPShares())]
+		pub fn contribute_LPShares(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let identity = Self::get_LProviderIdentity(&who);
-			ensure!(identity.is_none(), Error::<T>::LProviderIdentityAlreadyExists);
+			Self::contribute(who, amount, None)
+		}
 
-			//update storage
-			TotalLiquidtyProviders::put(newmember)
+		/// Contribute `amount` of the caller's balance and the same part of the pool's tokens,
+		/// at most `max_tokens` of them, receiving shares. While the pool holds no tokens, all
+		/// `max_tokens` are contributed, setting the pool's price.
+		#[pallet::weight(T::WeightInfo::add_liquidity())]
+		pub fn add_liquidity(
+			origin: OriginFor<T>,
+			amount: BalanceOf<T>,
+			max_tokens: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::contribute(who, amount, Some(max_tokens))
+		}
 
-			// emit event of the created LProvider accountID
-			Self::get_newLProvider(Event::NewLiquidityProvider(newmember, who));
+		/// Lock `amount` of the caller's shares in escrow for another liquidity provider, who
+		/// has `EscrowTimeout` blocks to accept them. The locked shares keep earning fees for the
+		/// caller until then.
+		#[pallet::weight(T::WeightInfo::initiate_transfer())]
+		pub fn initiate_transfer(
+			origin: OriginFor<T>,
+			to: T::AccountId,
+			amount: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(amount > 0, Error::<T>::MinimumLPShareAmountNotMet);
+			ensure!(
+				<LiquidityProviders<T>>::contains_key(&who),
+				Error::<T>::LProviderIdentityDoesNotExist
+			);
+			ensure!(
+				<LiquidityProviders<T>>::contains_key(&to),
+				Error::<T>::LProviderIdentityDoesNotExist
+			);
+			ensure!(<Escrows<T>>::count() < T::MaxEscrows::get(), Error::<T>::TooManyEscrows);
+			let locked = Self::locked_shares(&who)
+				.checked_add(amount)
+				.filter(|locked| *locked <= Self::shares(&who))
+				.ok_or(Error::<T>::InsufficientShares)?;
 
+			let id = Self::next_escrow_id();
+			let expires_at =
+				<frame_system::Pallet<T>>::block_number().saturating_add(T::EscrowTimeout::get());
+			let escrow = Escrow { from: who.clone(), to: to.clone(), shares: amount, expires_at };
+			<Escrows<T>>::insert(id, escrow);
+			<NextEscrowId<T>>::put(id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?);
+			<LockedShares<T>>::insert(&who, locked);
+			Self::deposit_event(Event::TransferInitiated(id, who, to, amount));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn contribute_LPShares(origin: OriginFor<T>, amount: u128) -> DispatchResult {
+		/// Accept the shares of the transfer `id` sent to the caller, before it expires.
+		#[pallet::weight(T::WeightInfo::accept_transfer())]
+		pub fn accept_transfer(origin: OriginFor<T>, id: EscrowId) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let identity = Self::get_LProviderIdentity(&who);
-			ensure!(identity.is_some(), Error::<T>::LProviderIdentityDoesNotExist);
+			let escrow = Self::escrow(id).ok_or(Error::<T>::UnknownEscrow)?;
+			ensure!(escrow.to == who, Error::<T>::NotEscrowParty);
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(now < escrow.expires_at, Error::<T>::EscrowExpired);
 
-			//update storage
-			TotalLiquidityPoolAmount::put(amount)
+			Self::release_escrow(id, escrow)?;
+			Self::deposit_event(Event::TransferAccepted(id));
+			Ok(())
+		}
+
+		/// Unlock the shares of the caller's transfer `id` once it expired unaccepted.
+		#[pallet::weight(T::WeightInfo::cancel_transfer())]
+		pub fn cancel_transfer(origin: OriginFor<T>, id: EscrowId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let escrow = Self::escrow(id).ok_or(Error::<T>::UnknownEscrow)?;
+			ensure!(escrow.from == who, Error::<T>::NotEscrowParty);
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(now >= escrow.expires_at, Error::<T>::EscrowNotExpired);
+
+			Self::refund_escrow(id, escrow);
+			Self::deposit_event(Event::TransferCancelled(id));
+			Ok(())
+		}
+
+		/// Settle the disputed transfer `id`, releasing its shares to the recipient or unlocking
+		/// them for the sender.
+		#[pallet::weight(T::WeightInfo::resolve_transfer())]
+		pub fn resolve_transfer(
+			origin: OriginFor<T>,
+			id: EscrowId,
+			release: bool,
+		) -> DispatchResult {
+			T::DisputeOrigin::ensure_origin(origin)?;
+			let escrow = Self::escrow(id).ok_or(Error::<T>::UnknownEscrow)?;
+
+			if release {
+				Self::release_escrow(id, escrow)?;
+			} else {
+				Self::refund_escrow(id, escrow);
+			}
+			Self::deposit_event(Event::TransferResolved(id, release));
+			Ok(())
+		}
+
+		/// Redeem `shares` of the caller's shares for the same part of the pool's funds.
+		#[pallet::weight(T::WeightInfo::withdraw_LPShares())]
+		pub fn withdraw_LPShares(origin: OriginFor<T>, shares: u128) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(shares > 0, Error::<T>::MinimumLPShareAmountNotMet);
+			ensure!(
+				<LiquidityProviders<T>>::contains_key(&who),
+				Error::<T>::LProviderIdentityDoesNotExist
+			);
 
-			// emit event of the created LProvider accountID
-			Self::get_newLProvider(Event::ContributedLPShares(amount, who));
+			let held = Self::shares(&who)
+				.checked_sub(shares)
+				.filter(|held| *held >= Self::locked_shares(&who))
+				.ok_or(Error::<T>::InsufficientShares)?;
+			let amount = Self::redemption_value(shares)?;
+			let balance = Self::pool_balance()
+				.checked_sub(&amount)
+				.ok_or(Error::<T>::NotEnoughLiquidity)?;
+			let tokens = mul_div(shares, Self::token_reserve(), Self::total_shares()).unwrap_or(0);
 
+			T::Currency::transfer(
+				&Self::account_id(),
+				&who,
+				amount,
+				ExistenceRequirement::AllowDeath,
+			)?;
+			Self::set_shares(&who, held);
+			<TotalShares<T>>::mutate(|total| *total = total.saturating_sub(shares));
+			<PoolBalance<T>>::put(balance);
+			if tokens > 0 {
+				<TokenReserve<T>>::mutate(|reserve| *reserve = reserve.saturating_sub(tokens));
+				<Tokens<T>>::mutate(&who, |held| *held = held.saturating_add(tokens));
+				Self::deposit_event(Event::WithdrewTokens(tokens, who.clone()));
+			}
+			Self::record(&who, TransactionKind::Redemption, amount.saturated_into(), None);
+			Self::deposit_event(Event::WithdrewLPShares(shares, amount, who));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn transfer_LPShares(origin: OriginFor<T>, to: T::AccountId, amount: u128) -> DispatchResult {
-			// add LProvider in Vec<u8> storage and return the LProvider's identity
+		/// Leave the pool, paying out the rewards the caller's shares earned; the caller must have
+		/// withdrawn all its shares and have none in escrow.
+		#[pallet::weight(T::WeightInfo::delete_LProviderIdentity())]
+		pub fn delete_LProviderIdentity(origin: OriginFor<T>) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let identity = Self::get_LProviderIdentity(&who);
-			ensure!(identity.is_some(), Error::<T>::LProviderIdentityDoesNotExist);
+			ensure!(
+				<LiquidityProviders<T>>::contains_key(&who),
+				Error::<T>::LProviderIdentityDoesNotExist
+			);
+			ensure!(Self::shares(&who) == 0, Error::<T>::LProviderHasShares);
+			ensure!(Self::locked_shares(&who) == 0, Error::<T>::SharesInEscrow);
+
+			let (native, tokens) = Self::pay_rewards(&who)?;
+			if native > 0 || tokens > 0 {
+				Self::deposit_event(Event::RewardsClaimed(who.clone(), native, tokens));
+			}
+			<LiquidityProviders<T>>::remove(&who);
+			<Shares<T>>::remove(&who);
+			<LockedShares<T>>::remove(&who);
+			Self::set_reward_debt(&who, 0);
+			Self::deposit_event(Event::LiquidityProviderRemoved(who));
+			Ok(())
+		}
+
+		/// Change one of the pool's parameters.
+		#[pallet::weight(T::WeightInfo::set_parameter())]
+		pub fn set_parameter(
+			origin: OriginFor<T>,
+			parameter: Parameter<T::BlockNumber, BalanceOf<T>>,
+		) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+
+			<Parameters<T>>::try_mutate(|parameters| -> DispatchResult {
+				match parameter.clone() {
+					Parameter::SwapFee(fee) => {
+						ensure!(fee < Perbill::one(), Error::<T>::InvalidParameter);
+						parameters.swap_fee = fee;
+					},
+					Parameter::WithdrawalFee(fee) => parameters.withdrawal_fee = fee,
+					Parameter::ProtocolFee(fee) => parameters.protocol_fee = fee,
+					Parameter::MinContribution(amount) => parameters.min_contribution = amount,
+					Parameter::DailyWithdrawalLimit(limit) => {
+						parameters.daily_withdrawal_limit = limit
+					},
+					Parameter::ConfirmationTimeout(timeout) => {
+						ensure!(!timeout.is_zero(), Error::<T>::InvalidParameter);
+						parameters.confirmation_timeout = timeout;
+					},
+				}
+				Ok(())
+			})?;
+			Self::deposit_event(Event::ParameterSet(parameter));
+			Ok(())
+		}
+
+		/// Attest `who`'s identity at `level`, updating its provider identity if it has one.
+		#[pallet::weight(T::WeightInfo::attest_identity())]
+		pub fn attest_identity(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			level: VerificationLevel,
+		) -> DispatchResult {
+			T::VerifierOrigin::ensure_origin(origin)?;
+			ensure!(level > 0, Error::<T>::VerificationLevelTooLow);
 
-			//update storage
-			TotalLiquidityPoolAmount::put(amount)
+			<Attestations<T>>::insert(&who, level);
+			<LiquidityProviders<T>>::mutate(&who, |info| {
+				if let Some(info) = info {
+					info.verification = level;
+				}
+			});
+			Self::deposit_event(Event::IdentityAttested(who, level));
+			Ok(())
+		}
 
-			// emit event of the created LProvider accountID
-			Self::get_newLProvider(Event::TransferLPTokens(amount, who, to));
+		/// Revoke `who`'s attestation; a provider keeps its identity and shares but can't
+		/// contribute until attested again.
+		#[pallet::weight(T::WeightInfo::revoke_attestation())]
+		pub fn revoke_attestation(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::VerifierOrigin::ensure_origin(origin)?;
+			ensure!(<Attestations<T>>::contains_key(&who), Error::<T>::NotAttested);
 
+			<Attestations<T>>::remove(&who);
+			<LiquidityProviders<T>>::mutate(&who, |info| {
+				if let Some(info) = info {
+					info.verification = 0;
+				}
+			});
+			Self::deposit_event(Event::AttestationRevoked(who));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn receive_LPShares(origin: OriginFor<T>, from: T::AccountId, amount: u128) -> DispatchResult {
-			// add LProvider in Vec<u8> storage and return the LProvider's identity
+		/// Pay the caller the swap and withdrawal fees its shares earned, or the treasury's part
+		/// of them when called by the treasury.
+		#[pallet::weight(T::WeightInfo::claim_rewards())]
+		pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let identity = Self::get_LProviderIdentity(&who);
-			ensure!(identity.is_some(), Error::<T>::LProviderIdentityDoesNotExist);
+			let (native, tokens) = Self::pay_rewards(&who)?;
+			ensure!(native > 0 || tokens > 0, Error::<T>::NoRewards);
+			Self::set_reward_debt(&who, Self::shares(&who));
+			Self::deposit_event(Event::RewardsClaimed(who, native, tokens));
+			Ok(())
+		}
 
-			//update storage
-			TotalLiquidityPoolAmount::put(amount)
+		/// Sell `amount_in` of `asset_in` to the pool for at least `min_out` of the other asset,
+		/// priced so that the product of the pool's reserves doesn't shrink.
+		#[pallet::weight(T::WeightInfo::swap_exact_in())]
+		pub fn swap_exact_in(
+			origin: OriginFor<T>,
+			asset_in: Asset,
+			amount_in: u128,
+			min_out: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let amount_out =
+				Self::amount_out(asset_in, amount_in).ok_or(Error::<T>::NotEnoughLiquidity)?;
+			ensure!(amount_out >= min_out, Error::<T>::SlippageExceeded);
+			Self::swap(who, asset_in, amount_in, amount_out)
+		}
 
-			// emit event of the created LProvider accountID
-			Self::get_newLProvider(Event::ReceiveLPTokens(amount, who, from));
+		/// Buy `amount_out` of the asset other than `asset_in` from the pool for at most `max_in`
+		/// of `asset_in`, priced so that the product of the pool's reserves doesn't shrink.
+		#[pallet::weight(T::WeightInfo::swap_exact_out())]
+		pub fn swap_exact_out(
+			origin: OriginFor<T>,
+			asset_in: Asset,
+			amount_out: u128,
+			max_in: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let amount_in =
+				Self::amount_in(asset_in, amount_out).ok_or(Error::<T>::NotEnoughLiquidity)?;
+			ensure!(amount_in <= max_in, Error::<T>::SlippageExceeded);
+			Self::swap(who, asset_in, amount_in, amount_out)
+		}
+
+		/// Ask to pay `amount` shillings into the pool from the phone whose blake2-256 hash is
+		/// `phone_hash`; an offchain worker prompts the phone to pay (an STK push).
+		#[pallet::weight(T::WeightInfo::request_deposit())]
+		pub fn request_deposit(
+			origin: OriginFor<T>,
+			phone_hash: [u8; 32],
+			amount: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(amount > 0, Error::<T>::InvalidDepositAmount);
+			ensure!(!Self::workers().is_empty(), Error::<T>::NoWorkers);
 
+			let id = Self::next_deposit_id();
+			<PendingStkPushes<T>>::try_append(id)
+				.map_err(|_| Error::<T>::TooManyPendingDeposits)?;
+			let requested_at = <frame_system::Pallet<T>>::block_number();
+			let deposit = DepositRequest {
+				who: who.clone(),
+				phone_hash,
+				amount,
+				requested_at,
+				checkout_request_id: None,
+			};
+			<Deposits<T>>::insert(id, deposit);
+			<NextDepositId<T>>::put(id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?);
+			T::Promises::create(AsyncOp::MpesaDeposit(id));
+			Self::deposit_event(Event::DepositRequested(id, who, amount));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1))]
-		pub fn delete_LProviderIdentity(origin: OriginFor<T>) -> DispatchResult {
-			// add LProvider in Vec<u8> storage and return the LProvider's identity
+		/// Record the `CheckoutRequestID` of the STK push an offchain worker initiated.
+		#[pallet::weight((T::WeightInfo::submit_checkout_request(), DispatchClass::Operational))]
+		pub fn submit_checkout_request(
+			origin: OriginFor<T>,
+			payload: CheckoutPayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let id = payload.deposit;
+			<Deposits<T>>::try_mutate(id, |deposit| -> DispatchResult {
+				let deposit = deposit.as_mut().ok_or(Error::<T>::DepositNotPending)?;
+				ensure!(deposit.checkout_request_id.is_none(), Error::<T>::DepositNotPending);
+				deposit.checkout_request_id = Some(payload.checkout_request_id.clone());
+				Ok(())
+			})?;
+			<PendingStkPushes<T>>::mutate(|pending| pending.retain(|pending| *pending != id));
+			<CheckoutRequests<T>>::insert(&payload.checkout_request_id, id);
+			Self::deposit_event(Event::DepositInitiated(id, payload.checkout_request_id.into()));
+			Ok(())
+		}
+
+		/// Confirm that M-Pesa settled the payment of the STK push `checkout_request_id` with
+		/// receipt `mpesa_receipt`, minting the pool tokens for `amount` shillings to the
+		/// deposit's account.
+		#[pallet::weight(T::WeightInfo::confirm_payment())]
+		pub fn confirm_payment(
+			origin: OriginFor<T>,
+			checkout_request_id: CheckoutRequestId,
+			mpesa_receipt: MpesaReceipt,
+			amount: u32,
+		) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(!<Receipts<T>>::contains_key(&mpesa_receipt), Error::<T>::ReceiptAlreadyUsed);
+			let id = Self::checkout_request(&checkout_request_id)
+				.ok_or(Error::<T>::UnknownCheckoutRequest)?;
+			let deposit = Self::deposit(id).ok_or(Error::<T>::UnknownCheckoutRequest)?;
+			ensure!(deposit.amount == amount, Error::<T>::AmountMismatch);
+			let tokens = amount as u128 * CENTS_PER_SHILLING;
+			let issuance =
+				Self::token_issuance().checked_add(tokens).ok_or(Error::<T>::StorageOverflow)?;
+			let held = Self::tokens(&deposit.who)
+				.checked_add(tokens)
+				.ok_or(Error::<T>::StorageOverflow)?;
+
+			<TokenIssuance<T>>::put(issuance);
+			<Tokens<T>>::insert(&deposit.who, held);
+			<Deposits<T>>::remove(id);
+			<CheckoutRequests<T>>::remove(&checkout_request_id);
+			<Receipts<T>>::insert(&mpesa_receipt, id);
+			let receipt_hash = T::Hashing::hash_of(&mpesa_receipt);
+			T::Promises::resolve(&AsyncOp::MpesaDeposit(id), receipt_hash);
+			T::Webhooks::notify(&deposit.who, AsyncOp::MpesaDeposit(id), Ok(receipt_hash));
+			Self::record(&deposit.who, TransactionKind::Deposit, tokens, None);
+			Self::queue_receipt(TransactionKind::Deposit, id, &deposit.who, tokens, &mpesa_receipt);
+			Self::deposit_event(Event::DepositConfirmed(
+				id,
+				deposit.who,
+				tokens,
+				mpesa_receipt.into(),
+			));
+			Ok(())
+		}
+
+		/// Lock the caller's pool tokens for `amount` shillings to pay them out of the pool to the
+		/// phone whose blake2-256 hash is `phone_hash`; an offchain worker initiates the payment
+		/// (a B2C payment).
+		#[pallet::weight(T::WeightInfo::request_withdrawal())]
+		pub fn request_withdrawal(
+			origin: OriginFor<T>,
+			phone_hash: [u8; 32],
+			amount: u32,
+		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			let identity = Self::get_LProviderIdentity(&who);
-			ensure!(identity.is_some(), Error::<T>::LProviderIdentityDoesNotExist);
+			ensure!(amount > 0, Error::<T>::InvalidWithdrawalAmount);
+			ensure!(
+				Self::attestation(&who) >= T::MinWithdrawalLevel::get(),
+				Error::<T>::VerificationLevelTooLow
+			);
+			ensure!(!Self::workers().is_empty(), Error::<T>::NoWorkers);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			let day = now / T::BlocksPerDay::get().max(One::one());
+			let (last_day, withdrawn) = Self::daily_withdrawals(&who);
+			let withdrawn = if last_day == day { withdrawn } else { 0 };
+			let withdrawn = withdrawn
+				.checked_add(amount)
+				.filter(|withdrawn| *withdrawn <= Self::parameters().daily_withdrawal_limit)
+				.ok_or(Error::<T>::DailyLimitExceeded)?;
+
+			let tokens = amount as u128 * CENTS_PER_SHILLING;
+			let fee = Self::parameters().withdrawal_fee * tokens;
+			let held = Self::tokens(&who)
+				.checked_sub(tokens + fee)
+				.ok_or(Error::<T>::InsufficientTokens)?;
+
+			let id = Self::next_withdrawal_id();
+			<PendingB2cPayments<T>>::try_append(id)
+				.map_err(|_| Error::<T>::TooManyPendingWithdrawals)?;
+			let withdrawal = WithdrawalRequest {
+				who: who.clone(),
+				phone_hash,
+				amount,
+				tokens,
+				fee,
+				requested_at: now,
+				conversation_id: None,
+			};
+			<Withdrawals<T>>::insert(id, withdrawal);
+			<NextWithdrawalId<T>>::put(id.checked_add(1).ok_or(Error::<T>::StorageOverflow)?);
+			T::Promises::create(AsyncOp::MpesaWithdrawal(id));
+			<Tokens<T>>::insert(&who, held);
+			<DailyWithdrawals<T>>::insert(&who, (day, withdrawn));
+			Self::deposit_event(Event::WithdrawalRequested(id, who, amount));
+			Ok(())
+		}
+
+		/// Record the `ConversationID` of the B2C payment an offchain worker initiated.
+		#[pallet::weight((T::WeightInfo::submit_conversation(), DispatchClass::Operational))]
+		pub fn submit_conversation(
+			origin: OriginFor<T>,
+			payload: ConversationPayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let id = payload.withdrawal;
+			<Withdrawals<T>>::try_mutate(id, |withdrawal| -> DispatchResult {
+				let withdrawal = withdrawal.as_mut().ok_or(Error::<T>::WithdrawalNotPending)?;
+				ensure!(withdrawal.conversation_id.is_none(), Error::<T>::WithdrawalNotPending);
+				withdrawal.conversation_id = Some(payload.conversation_id.clone());
+				Ok(())
+			})?;
+			<PendingB2cPayments<T>>::mutate(|pending| pending.retain(|pending| *pending != id));
+			<Conversations<T>>::insert(&payload.conversation_id, id);
+			Self::deposit_event(Event::WithdrawalInitiated(id, payload.conversation_id.into()));
+			Ok(())
+		}
+
+		/// Confirm that M-Pesa paid out the B2C payment `conversation_id` with receipt
+		/// `mpesa_receipt`, burning the withdrawal's locked pool tokens and accruing its fee.
+		#[pallet::weight(T::WeightInfo::confirm_withdrawal())]
+		pub fn confirm_withdrawal(
+			origin: OriginFor<T>,
+			conversation_id: ConversationId,
+			mpesa_receipt: MpesaReceipt,
+			amount: u32,
+		) -> DispatchResult {
+			T::OracleOrigin::ensure_origin(origin)?;
+			ensure!(
+				!<PayoutReceipts<T>>::contains_key(&mpesa_receipt),
+				Error::<T>::ReceiptAlreadyUsed
+			);
+			let id = Self::conversation(&conversation_id).ok_or(Error::<T>::UnknownConversation)?;
+			let withdrawal = Self::withdrawal(id).ok_or(Error::<T>::UnknownConversation)?;
+			ensure!(withdrawal.amount == amount, Error::<T>::AmountMismatch);
+
+			// the shillings left the pool's shortcode, so the tokens they backed go too
+			<TokenIssuance<T>>::mutate(|issuance| {
+				*issuance = issuance.saturating_sub(withdrawal.tokens)
+			});
+			Self::accrue_fee(Asset::Token, withdrawal.fee);
+			<Withdrawals<T>>::remove(id);
+			<Conversations<T>>::remove(&conversation_id);
+			<PayoutReceipts<T>>::insert(&mpesa_receipt, id);
+			let receipt_hash = T::Hashing::hash_of(&mpesa_receipt);
+			T::Promises::resolve(&AsyncOp::MpesaWithdrawal(id), receipt_hash);
+			T::Webhooks::notify(&withdrawal.who, AsyncOp::MpesaWithdrawal(id), Ok(receipt_hash));
+			Self::record(&withdrawal.who, TransactionKind::Withdrawal, withdrawal.tokens, None);
+			Self::queue_receipt(
+				TransactionKind::Withdrawal,
+				id,
+				&withdrawal.who,
+				withdrawal.tokens,
+				&mpesa_receipt,
+			);
+			Self::deposit_event(Event::WithdrawalCompleted(
+				id,
+				withdrawal.who,
+				withdrawal.tokens,
+				mpesa_receipt.into(),
+			));
+			Ok(())
+		}
+
+		/// Publish the receipt document an offchain worker generated for a confirmed payment to
+		/// IPFS, recording its CID in the account's history.
+		#[pallet::weight((T::WeightInfo::submit_receipt(), DispatchClass::Operational))]
+		pub fn submit_receipt(
+			origin: OriginFor<T>,
+			payload: ReceiptPayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			let receipt =
+				Self::pending_receipt(payload.receipt).ok_or(Error::<T>::UnknownReceipt)?;
+			ensure!(payload.document == receipts::document(&receipt), Error::<T>::ReceiptMismatch);
+
+			let cid = T::ReceiptStore::add_bytes(&Self::receipts_account(), payload.document)?;
+			let cid: ReceiptCid = cid.try_into().map_err(|_| Error::<T>::InvalidReceiptCid)?;
+			<PendingReceipts<T>>::remove(payload.receipt);
+			<PublishedReceipts<T>>::insert(&cid, <frame_system::Pallet<T>>::block_number());
+			<History<T>>::mutate(&receipt.who, |history| {
+				// the record may have been dropped from a full history already
+				let record = history.iter_mut().rev().find(|record| {
+					record.kind == receipt.kind
+						&& record.block == receipt.block
+						&& record.amount == receipt.tokens
+						&& record.receipt.is_none()
+				});
+				if let Some(record) = record {
+					record.receipt = Some(cid.clone());
+				}
+			});
+			Self::deposit_event(Event::ReceiptPublished(
+				payload.receipt,
+				receipt.who,
+				cid.into_inner(),
+			));
+			Ok(())
+		}
+
+		/// Record the exchange rate an offchain worker fetched.
+		#[pallet::weight((T::WeightInfo::submit_rate(), DispatchClass::Operational))]
+		pub fn submit_rate(
+			origin: OriginFor<T>,
+			payload: RatePayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			ensure!(!payload.rate.is_zero(), Error::<T>::InvalidRate);
+			ensure!(
+				Self::kes_rate().map_or(true, |(_, at)| at < payload.block_number),
+				Error::<T>::StaleRate
+			);
+
+			<KesRate<T>>::put((payload.rate, payload.block_number));
+			Self::deposit_event(Event::KesRateUpdated(payload.rate));
+			Ok(())
+		}
+
+		/// Replace the keys of the offchain workers initiating M-Pesa payments.
+		#[pallet::weight(T::WeightInfo::set_workers())]
+		pub fn set_workers(origin: OriginFor<T>, workers: Vec<T::Public>) -> DispatchResult {
+			T::AdminOrigin::ensure_origin(origin)?;
+			ensure!(workers.len() <= T::MaxWorkers::get() as usize, Error::<T>::TooManyWorkers);
+
+			let count = workers.len() as u32;
+			<Workers<T>>::put(workers);
+			Self::deposit_event(Event::WorkersSet(count));
+			Ok(())
+		}
+
+		/// Pin the published receipt documents an offchain worker found without an active pin
+		/// `ReceiptPinGrace` blocks after they were published, up to `MAX_REPINS` at once.
+		/// Receipts pinned again in the meantime are skipped.
+		#[pallet::weight((
+			T::WeightInfo::repin_receipts(payload.receipts.len() as u32),
+			DispatchClass::Operational
+		))]
+		pub fn repin_receipts(
+			origin: OriginFor<T>,
+			payload: RepinPayloadOf<T>,
+			_signature: T::Signature,
+		) -> DispatchResult {
+			ensure_none(origin)?;
+			ensure!(payload.receipts.len() as u32 <= MAX_REPINS, Error::<T>::TooManyRepins);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			for cid in payload.receipts {
+				if Self::needs_repin(&cid, now) {
+					T::ReceiptStore::pin(&Self::receipts_account(), cid.to_vec())?;
+					Self::deposit_event(Event::ReceiptRepinned(cid.into_inner()));
+				}
+			}
+			Ok(())
+		}
+	}
+
+	#[pallet::validate_unsigned]
+	impl<T: Config> ValidateUnsigned for Pallet<T> {
+		type Call = Call<T>;
+
+		// only accept a worker's payment id signed by the request's worker, once per request
+		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
+			match call {
+				Call::submit_checkout_request { payload, signature } => {
+					let pending = Self::deposit(payload.deposit)
+						.map_or(false, |deposit| deposit.checkout_request_id.is_none());
+					Self::validate_worker_payload(
+						payload,
+						signature,
+						payload.block_number,
+						payload.deposit,
+						pending,
+						"MpesaCheckout",
+					)
+				},
+				Call::submit_conversation { payload, signature } => {
+					let pending = Self::withdrawal(payload.withdrawal)
+						.map_or(false, |withdrawal| withdrawal.conversation_id.is_none());
+					Self::validate_worker_payload(
+						payload,
+						signature,
+						payload.block_number,
+						payload.withdrawal,
+						pending,
+						"MpesaConversation",
+					)
+				},
+				Call::submit_receipt { payload, signature } => Self::validate_worker_payload(
+					payload,
+					signature,
+					payload.block_number,
+					payload.receipt,
+					<PendingReceipts<T>>::contains_key(payload.receipt),
+					"MpesaReceipt",
+				),
+				Call::submit_rate { payload, signature } => Self::validate_rate(payload, signature),
+				Call::repin_receipts { payload, signature } => {
+					Self::validate_repin(payload, signature)
+				},
+				_ => InvalidTransaction::Call.into(),
+			}
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The account holding the pool's funds.
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account_truncating()
+		}
+
+		/// The account receipt documents are published from, holding no funds.
+		pub fn receipts_account() -> T::AccountId {
+			T::PalletId::get().into_sub_account_truncating(b"receipts")
+		}
+
+		/// The shares a contribution of `amount` mints: `amount * total_shares / pool_balance`,
+		/// rounded down so that existing shares never lose value, or `amount` itself while the
+		/// pool is empty.
+		pub fn shares_for(amount: BalanceOf<T>) -> Option<u128> {
+			let balance = Self::pool_balance().saturated_into();
+			math::shares_for(amount.saturated_into(), Self::total_shares(), balance)
+		}
+
+		/// The part of the pool's funds `shares` are worth: `shares * pool_balance /
+		/// total_shares`, rounded down so that the remaining shares never lose value.
+		pub fn redemption_value(shares: u128) -> Result<BalanceOf<T>, DispatchError> {
+			let balance = Self::pool_balance().saturated_into();
+			let value = math::redemption_value(shares, Self::total_shares(), balance)
+				.ok_or(Error::<T>::StorageOverflow)?;
+			Ok(value.saturated_into())
+		}
+
+		/// The part of the pool owned by `who`.
+		pub fn pool_share(who: &T::AccountId) -> Perbill {
+			let total = Self::total_shares();
+			if total == 0 {
+				return Perbill::zero();
+			}
+			Perbill::from_rational(Self::shares(who), total)
+		}
+
+		/// A page of the settlements matching `filter`, for indexers: the deposits and
+		/// withdrawals in the accounts' histories, each account's oldest first.
+		///
+		/// The accounts are paged in storage order, or only the filtered one, and the cursor is
+		/// the SCALE encoding of the last account examined and the position in its history to
+		/// resume at.
+		pub fn settlements_page(
+			filter: &IndexFilter<T::AccountId, T::BlockNumber>,
+			request: &PageRequest,
+		) -> Page<Settlement<T::AccountId, T::BlockNumber>> {
+			let resumed = request
+				.cursor
+				.as_ref()
+				.and_then(|cursor| <(T::AccountId, u32)>::decode(&mut &cursor[..]).ok());
+			let (first, rest) = match (&filter.account, resumed) {
+				(_, Some((who, position))) => {
+					let rest = filter
+						.account
+						.is_none()
+						.then(|| <History<T>>::iter_from(<History<T>>::hashed_key_for(&who)));
+					(Some((who, position)), rest)
+				},
+				(Some(who), None) => (Some((who.clone(), 0)), None),
+				(None, None) => (None, Some(<History<T>>::iter())),
+			};
+			let first = first.map(|(who, position)| {
+				let history = Self::history(&who);
+				(who, history, position)
+			});
+			let rest = rest.into_iter().flatten().map(|(who, history)| (who, history, 0));
+			let records = first.into_iter().chain(rest).flat_map(|(who, history, position)| {
+				let records = history.into_inner().into_iter().enumerate();
+				records.skip(position as usize).map(move |(i, record)| (who.clone(), i, record))
+			});
+			collect_page(
+				records,
+				request,
+				|(who, _, record)| {
+					let settled = matches!(
+						record.kind,
+						TransactionKind::Deposit | TransactionKind::Withdrawal
+					);
+					let cid = record.receipt.as_ref().map(|cid| &cid[..]);
+					(settled && filter.matches(&who, cid, &record.block)).then(|| Settlement {
+						who,
+						kind: record.kind,
+						amount: record.amount,
+						block: record.block,
+						receipt: record.receipt,
+					})
+				},
+				|(who, i, _)| (who, *i as u32 + 1).encode(),
+			)
+		}
+
+		/// A page of the liquidity providers' positions matching `filter`, for indexers, dated
+		/// by the block the provider joined in; positions refer to no `Cid`.
+		///
+		/// The providers are paged in storage order, or only the filtered one, and the cursor is
+		/// the SCALE encoding of the last provider examined.
+		pub fn positions_page(
+			filter: &IndexFilter<T::AccountId, T::BlockNumber>,
+			request: &PageRequest,
+		) -> Page<PoolPosition<T::AccountId, T::BlockNumber, BalanceOf<T>>> {
+			let position = |(who, provider): (T::AccountId, ProviderInfo<_, _>)| {
+				filter.matches(&who, None, &provider.joined_at).then(|| PoolPosition {
+					shares: Self::shares(&who),
+					tokens: Self::tokens(&who),
+					joined_at: provider.joined_at,
+					contributed: provider.contributed,
+					who,
+				})
+			};
+			if let Some(who) = &filter.account {
+				let provider = match request.cursor {
+					Some(_) => None,
+					None => Self::liquidity_provider(who).map(|provider| (who.clone(), provider)),
+				};
+				return collect_page(provider, request, position, |(who, _)| who.encode());
+			}
+			let resumed = request
+				.cursor
+				.as_ref()
+				.and_then(|cursor| T::AccountId::decode(&mut &cursor[..]).ok());
+			let providers = match resumed {
+				Some(who) => <LiquidityProviders<T>>::iter_from(
+					<LiquidityProviders<T>>::hashed_key_for(&who),
+				),
+				None => <LiquidityProviders<T>>::iter(),
+			};
+			collect_page(providers, request, position, |(who, _)| who.encode())
+		}
+
+		// expire the deposits left unconfirmed for `confirmation_timeout` blocks, in the order they
+		// were requested, looking at no more than `MaxPendingDeposits` of them per block
+		fn expire_deposits(now: T::BlockNumber) -> Weight {
+			let start = Self::expiry_cursor();
+			let end = Self::next_deposit_id()
+				.min(start.saturating_add(T::MaxPendingDeposits::get().into()));
+			let mut cursor = start;
+			let timeout = Self::parameters().confirmation_timeout;
+			while cursor < end {
+				if let Some(deposit) = Self::deposit(cursor) {
+					if deposit.requested_at.saturating_add(timeout) > now {
+						break;
+					}
+					<Deposits<T>>::remove(cursor);
+					<PendingStkPushes<T>>::mutate(|pending| pending.retain(|id| *id != cursor));
+					if let Some(checkout_request_id) = deposit.checkout_request_id {
+						<CheckoutRequests<T>>::remove(checkout_request_id);
+					}
+					let timed_out: DispatchError = Error::<T>::ConfirmationTimedOut.into();
+					T::Promises::reject(&AsyncOp::MpesaDeposit(cursor), timed_out);
+					T::Webhooks::notify(
+						&deposit.who,
+						AsyncOp::MpesaDeposit(cursor),
+						Err(timed_out),
+					);
+					Self::deposit_event(Event::DepositExpired(cursor, deposit.who));
+				}
+				cursor += 1;
+			}
+
+			let looked_at = cursor - start + 1;
+			if cursor == start {
+				return T::DbWeight::get().reads(looked_at + 2);
+			}
+			<ExpiryCursor<T>>::put(cursor);
+			T::DbWeight::get()
+				.reads_writes(looked_at + 2 + (cursor - start) * 4, (cursor - start) * 7 + 1)
+		}
+
+		// move the shares of the transfer `id` from its sender to its recipient
+		fn release_escrow(
+			id: EscrowId,
+			escrow: Escrow<T::AccountId, T::BlockNumber>,
+		) -> DispatchResult {
+			let shares = Self::shares(&escrow.from)
+				.checked_sub(escrow.shares)
+				.ok_or(Error::<T>::InsufficientShares)?;
+			Self::set_shares(&escrow.from, shares);
+			let shares = Self::shares(&escrow.to)
+				.checked_add(escrow.shares)
+				.ok_or(Error::<T>::StorageOverflow)?;
+			Self::set_shares(&escrow.to, shares);
+			let (from, to) = (escrow.from.clone(), escrow.to.clone());
+			Self::record(&from, TransactionKind::TransferOut, escrow.shares, Some(to.clone()));
+			Self::record(&to, TransactionKind::TransferIn, escrow.shares, Some(from));
+			Self::refund_escrow(id, escrow);
+			Ok(())
+		}
+
+		// queue the receipt document of the confirmed deposit or withdrawal `id` for the offchain
+		// workers to publish, if there's a store to publish it to
+		fn queue_receipt(
+			kind: TransactionKind,
+			id: u64,
+			who: &T::AccountId,
+			tokens: u128,
+			mpesa_receipt: &MpesaReceipt,
+		) {
+			if !T::ReceiptStore::is_available() {
+				return;
+			}
+			let receipt = Receipt {
+				kind,
+				id,
+				who: who.clone(),
+				tokens,
+				mpesa_receipt: mpesa_receipt.clone(),
+				block: <frame_system::Pallet<T>>::block_number(),
+			};
+			let receipt_id = Self::next_receipt_id();
+			<PendingReceipts<T>>::insert(receipt_id, receipt);
+			<NextReceiptId<T>>::put(receipt_id.wrapping_add(1));
+		}
+
+		// append a transaction to `who`'s history, moving its oldest one to be archived once it
+		// is full
+		fn record(
+			who: &T::AccountId,
+			kind: TransactionKind,
+			amount: u128,
+			counterparty: Option<T::AccountId>,
+		) {
+			let block = <frame_system::Pallet<T>>::block_number();
+			let record = TransactionRecord { kind, amount, counterparty, block, receipt: None };
+			let dropped = <History<T>>::mutate(who, |history| {
+				let mut dropped = None;
+				if !history.is_empty() && history.len() as u32 >= T::MaxHistory::get() {
+					dropped = Some(history.remove(0));
+				}
+				// only fails when `MaxHistory` is 0
+				let _ = history.try_push(record);
+				dropped
+			});
+			if let Some(dropped) = dropped {
+				Self::archive_history(who, dropped);
+			}
+		}
+
+		// keep a transaction dropped from `who`'s history for the next archive, publishing the
+		// archive once it holds `HistoryArchiveBatch` transactions; a transaction is only lost if
+		// the receipt store refused the full archive before
+		fn archive_history(
+			who: &T::AccountId,
+			dropped: TransactionRecord<T::AccountId, T::BlockNumber>,
+		) {
+			if T::HistoryArchiveBatch::get() == 0 || !T::ReceiptStore::is_available() {
+				return;
+			}
+			let mut pruned = Self::pruned_history(who);
+			if pruned.try_push(dropped).is_err() {
+				log::warn!("M-Pesa: a transaction of {:?} was dropped unarchived", who);
+			}
+			if pruned.len() as u32 != T::HistoryArchiveBatch::get() {
+				<PrunedHistory<T>>::insert(who, pruned);
+				return;
+			}
+
+			let cid = T::ReceiptStore::add_bytes(&Self::receipts_account(), pruned.encode())
+				.and_then(|cid| {
+					ReceiptCid::try_from(cid).map_err(|_| Error::<T>::InvalidReceiptCid.into())
+				});
+			match cid {
+				Ok(cid) => {
+					let index = Self::history_archive_count(who);
+					<PublishedReceipts<T>>::insert(&cid, <frame_system::Pallet<T>>::block_number());
+					<HistoryArchives<T>>::insert(who, index, &cid);
+					<HistoryArchiveCount<T>>::insert(who, index + 1);
+					<PrunedHistory<T>>::remove(who);
+					Self::deposit_event(Event::HistoryArchived(
+						who.clone(),
+						index,
+						cid.into_inner(),
+					));
+				},
+				Err(e) => {
+					log::warn!("M-Pesa: can't archive the history of {:?}: {:?}", who, e);
+					<PrunedHistory<T>>::insert(who, pruned);
+				},
+			}
+		}
+
+		// close the transfer `id`, unlocking whatever shares its sender still holds
+		fn refund_escrow(id: EscrowId, escrow: Escrow<T::AccountId, T::BlockNumber>) {
+			<LockedShares<T>>::mutate_exists(&escrow.from, |locked| {
+				*locked =
+					locked.map(|locked| locked.saturating_sub(escrow.shares)).filter(|l| *l > 0)
+			});
+			<Escrows<T>>::remove(id);
+		}
+
+		// cancel the transfers left unaccepted past their expiry, in the order they were
+		// initiated, as far as `remaining_weight` allows
+		fn expire_escrows(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let per_escrow = 10_000 + T::DbWeight::get().reads_writes(2, 3);
+			let mut used = T::DbWeight::get().reads_writes(2, 1);
+			if used.saturating_add(per_escrow) > remaining_weight {
+				return 0;
+			}
+			let start = Self::escrow_expiry_cursor();
+			let end = Self::next_escrow_id();
+			let mut cursor = start;
+			while cursor < end && used.saturating_add(per_escrow) <= remaining_weight {
+				used = used.saturating_add(per_escrow);
+				if let Some(escrow) = Self::escrow(cursor) {
+					if escrow.expires_at > now {
+						break;
+					}
+					Self::refund_escrow(cursor, escrow);
+					Self::deposit_event(Event::TransferCancelled(cursor));
+				}
+				cursor += 1;
+			}
+
+			if cursor != start {
+				<EscrowExpiryCursor<T>>::put(cursor);
+			}
+			used
+		}
+
+		/// The price of a dollar in shillings, unless the offchain workers haven't updated it in
+		/// `MaxRateAge` blocks.
+		pub fn current_rate() -> Option<FixedU128> {
+			let (rate, at) = Self::kes_rate()?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			if now.saturating_sub(at) > T::MaxRateAge::get() {
+				return None;
+			}
+			Some(rate)
+		}
+
+		// only accept a rate signed by a worker that is newer than the current one, fetched in
+		// the last `RateInterval` blocks
+		fn validate_rate(
+			payload: &RatePayloadOf<T>,
+			signature: &T::Signature,
+		) -> TransactionValidity {
+			if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+				return InvalidTransaction::BadProof.into();
+			}
+			let now = <frame_system::Pallet<T>>::block_number();
+			if payload.block_number > now {
+				return InvalidTransaction::Future.into();
+			}
+			let interval = T::RateInterval::get();
+			if now - payload.block_number > interval
+				|| Self::kes_rate().map_or(false, |(_, at)| at >= payload.block_number)
+			{
+				return InvalidTransaction::Stale.into();
+			}
+			if !Self::workers().contains(&payload.public) {
+				return InvalidTransaction::BadSigner.into();
+			}
+
+			ValidTransaction::with_tag_prefix("MpesaRate")
+				.priority(T::UnsignedPriority::get())
+				.and_provides(payload.block_number)
+				.longevity(interval.saturated_into::<u64>().max(1))
+				.propagate(true)
+				.build()
+		}
+
+		// submit the median of the node's rate sources every `RateInterval` blocks, if it has a
+		// worker key
+		fn report_rate(block_number: T::BlockNumber) -> Result<(), DarajaError> {
+			let interval = T::RateInterval::get();
+			if interval.is_zero() || !(block_number % interval).is_zero() {
+				return Ok(());
+			}
+			let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(Self::workers());
+			if !signer.can_sign() {
+				return Ok(());
+			}
+
+			let fetched = rates::sources()
+				.iter()
+				.filter_map(|source| match rates::fetch(source) {
+					Ok(rate) => Some(rate),
+					Err(e) => {
+						log::warn!("M-Pesa: can't fetch the exchange rate: {:?}", e);
+						None
+					},
+				})
+				.collect();
+			let rate = rates::median(fetched).ok_or(DarajaError::NotConfigured)?;
+
+			let result = signer.send_unsigned_transaction(
+				|account| RatePayload { public: account.public.clone(), block_number, rate },
+				|payload, signature| Call::submit_rate { payload, signature },
+			);
+			if let Some((account, Err(()))) = result {
+				log::error!(
+					"M-Pesa: failed to submit the exchange rate with account {:?}",
+					account.id
+				);
+			}
+			Ok(())
+		}
+
+		// whether the receipt document with `cid` was published `ReceiptPinGrace` blocks ago or
+		// more and has no active pin
+		fn needs_repin(cid: &ReceiptCid, now: T::BlockNumber) -> bool {
+			Self::receipt_published_at(cid).map_or(false, |published_at| {
+				published_at.saturating_add(T::ReceiptPinGrace::get()) <= now
+					&& !T::ReceiptStore::is_pinned(cid)
+			})
+		}
+
+		fn validate_repin(
+			payload: &RepinPayloadOf<T>,
+			signature: &T::Signature,
+		) -> TransactionValidity {
+			if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+				return InvalidTransaction::BadProof.into();
+			}
+			let now = <frame_system::Pallet<T>>::block_number();
+			if payload.block_number > now {
+				return InvalidTransaction::Future.into();
+			}
+			let grace = T::ReceiptPinGrace::get();
+			if now - payload.block_number > grace
+				|| !payload.receipts.iter().any(|cid| Self::needs_repin(cid, now))
+			{
+				return InvalidTransaction::Stale.into();
+			}
+			if !Self::workers().contains(&payload.public) {
+				return InvalidTransaction::BadSigner.into();
+			}
+
+			ValidTransaction::with_tag_prefix("MpesaRepin")
+				.priority(T::UnsignedPriority::get())
+				.and_provides(payload.block_number)
+				.longevity(grace.saturated_into::<u64>().max(1))
+				.propagate(true)
+				.build()
+		}
+
+		// every `ReceiptPinGrace` blocks, check the pins of the next `REPIN_SCAN` published
+		// receipts and ask to re-pin those that lost theirs, if the node has a worker key
+		fn repair_receipt_pins(block_number: T::BlockNumber) {
+			let grace = T::ReceiptPinGrace::get();
+			if grace.is_zero() || !(block_number % grace).is_zero() {
+				return;
+			}
+			let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(Self::workers());
+			if !signer.can_sign() {
+				return;
+			}
+
+			let mut cursor = StorageValueRef::persistent(REPIN_CURSOR);
+			let mut scanned = match cursor.get::<Vec<u8>>() {
+				Ok(Some(key)) => <PublishedReceipts<T>>::iter_keys_from(key),
+				_ => <PublishedReceipts<T>>::iter_keys(),
+			};
+			let mut receipts = Vec::new();
+			let mut checked = 0;
+			let mut last_key = None;
+			while checked < REPIN_SCAN && (receipts.len() as u32) < MAX_REPINS {
+				let cid = match scanned.next() {
+					Some(cid) => cid,
+					None => break,
+				};
+				checked += 1;
+				last_key = Some(scanned.last_raw_key().to_vec());
+				if Self::needs_repin(&cid, block_number) {
+					receipts.push(cid);
+				}
+			}
+			// start over once every receipt was checked
+			match last_key {
+				Some(key) if checked == REPIN_SCAN || receipts.len() as u32 == MAX_REPINS => {
+					cursor.set(&key)
+				},
+				_ => cursor.clear(),
+			}
+			if receipts.is_empty() {
+				return;
+			}
 
-			//update storage
-			TotalLiquidtyProviders::put(who)
+			let result = signer.send_unsigned_transaction(
+				|account| RepinPayload {
+					public: account.public.clone(),
+					block_number,
+					receipts: receipts.clone(),
+				},
+				|payload, signature| Call::repin_receipts { payload, signature },
+			);
+			if let Some((account, Err(()))) = result {
+				log::error!(
+					"M-Pesa: failed to submit {} receipts to re-pin with account {:?}",
+					receipts.len(),
+					account.id
+				);
+			}
+		}
+
+		/// Check that every receipt document recorded in an account's history was published,
+		/// and that every receipt published `ReceiptPinGrace` blocks ago or more is pinned in the
+		/// receipt store.
+		#[cfg(any(feature = "try-runtime", test))]
+		pub fn do_try_state() -> Result<(), &'static str> {
+			let now = <frame_system::Pallet<T>>::block_number();
+			for (_, history) in <History<T>>::iter() {
+				for cid in history.iter().filter_map(|record| record.receipt.as_ref()) {
+					if !<PublishedReceipts<T>>::contains_key(cid) {
+						return Err("a settlement's receipt wasn't published");
+					}
+				}
+			}
+			if <PublishedReceipts<T>>::iter_keys().any(|cid| Self::needs_repin(&cid, now)) {
+				return Err("a settlement's receipt has no active pin");
+			}
+			Ok(())
+		}
+
+		/// The pool's reserve of `asset_in` and of the other asset.
+		pub fn reserves(asset_in: Asset) -> (u128, u128) {
+			let native = Self::pool_balance().saturated_into();
+			let tokens = Self::token_reserve();
+			match asset_in {
+				Asset::Native => (native, tokens),
+				Asset::Token => (tokens, native),
+			}
+		}
+
+		/// What selling `amount_in` of `asset_in` to the pool buys once the swap fee is taken:
+		/// `reserve_out * in / (reserve_in + in)`, rounded down.
+		pub fn amount_out(asset_in: Asset, amount_in: u128) -> Option<u128> {
+			let (reserve_in, reserve_out) = Self::reserves(asset_in);
+			math::amount_out(reserve_in, reserve_out, Self::parameters().swap_fee, amount_in)
+		}
+
+		/// What buying `amount_out` from the pool costs in `asset_in`, the swap fee included:
+		/// `reserve_in * out / (reserve_out - out)`, rounded up.
+		pub fn amount_in(asset_in: Asset, amount_out: u128) -> Option<u128> {
+			let (reserve_in, reserve_out) = Self::reserves(asset_in);
+			math::amount_in(reserve_in, reserve_out, Self::parameters().swap_fee, amount_out)
+		}
+
+		// exchange `amount_in` of the caller's `asset_in` for `amount_out` of the pool's other
+		// asset; the swap fee is taken out of `amount_in` before it joins the reserves
+		fn swap(
+			who: T::AccountId,
+			asset_in: Asset,
+			amount_in: u128,
+			amount_out: u128,
+		) -> DispatchResult {
+			ensure!(amount_in > 0 && amount_out > 0, Error::<T>::InvalidSwapAmount);
+			let fee = Self::parameters().swap_fee * amount_in;
+
+			match asset_in {
+				Asset::Native => {
+					let balance = Self::pool_balance()
+						.checked_add(&Self::balance(amount_in - fee)?)
+						.ok_or(Error::<T>::StorageOverflow)?;
+					let reserve = Self::token_reserve()
+						.checked_sub(amount_out)
+						.ok_or(Error::<T>::NotEnoughLiquidity)?;
+					let held = Self::tokens(&who)
+						.checked_add(amount_out)
+						.ok_or(Error::<T>::StorageOverflow)?;
+					T::Currency::transfer(
+						&who,
+						&Self::account_id(),
+						Self::balance(amount_in)?,
+						ExistenceRequirement::KeepAlive,
+					)?;
+					<PoolBalance<T>>::put(balance);
+					<TokenReserve<T>>::put(reserve);
+					<Tokens<T>>::insert(&who, held);
+				},
+				Asset::Token => {
+					let held = Self::tokens(&who)
+						.checked_sub(amount_in)
+						.ok_or(Error::<T>::InsufficientTokens)?;
+					let reserve = Self::token_reserve()
+						.checked_add(amount_in - fee)
+						.ok_or(Error::<T>::StorageOverflow)?;
+					let balance = Self::pool_balance()
+						.checked_sub(&Self::balance(amount_out)?)
+						.ok_or(Error::<T>::NotEnoughLiquidity)?;
+					T::Currency::transfer(
+						&Self::account_id(),
+						&who,
+						Self::balance(amount_out)?,
+						ExistenceRequirement::AllowDeath,
+					)?;
+					<Tokens<T>>::insert(&who, held);
+					<TokenReserve<T>>::put(reserve);
+					<PoolBalance<T>>::put(balance);
+				},
+			}
+			Self::accrue_fee(asset_in, fee);
+			Self::record(&who, TransactionKind::Swap(asset_in), amount_in, None);
+			Self::deposit_event(Event::SwapExecuted(who, asset_in, amount_in, amount_out));
+			Ok(())
+		}
+
+		// `amount` of the native currency
+		fn balance(amount: u128) -> Result<BalanceOf<T>, Error<T>> {
+			BalanceOf::<T>::try_from(amount).map_err(|_| Error::<T>::StorageOverflow)
+		}
+
+		/// The rewards in `asset` `who` can claim: the ones set aside for it and the fees its
+		/// current shares earned since they last changed.
+		pub fn rewards(who: &T::AccountId, asset: Asset) -> u128 {
+			let earned = math::earned(
+				Self::shares(who),
+				Self::reward_per_share(asset),
+				Self::reward_debt(who, asset),
+			);
+			Self::owed_rewards(who, asset).saturating_add(earned)
+		}
+
+		// split `fee` of `asset` between the treasury and the providers' shares; it all goes to
+		// the treasury while there are no shares to accrue it to
+		fn accrue_fee(asset: Asset, fee: u128) {
+			if fee == 0 {
+				return;
+			}
+			let (treasury, per_share) =
+				math::split_fee(fee, Self::parameters().protocol_fee, Self::total_shares());
+			if per_share > 0 {
+				<RewardPerShare<T>>::mutate(asset, |acc| *acc = acc.saturating_add(per_share));
+			}
+			<OwedRewards<T>>::mutate(T::TreasuryAccount::get(), asset, |owed| {
+				*owed = owed.saturating_add(treasury)
+			});
+			Self::deposit_event(Event::FeesAccrued(asset, treasury, fee - treasury));
+		}
+
+		// pay `who` the rewards its shares earned and return them; the caller resets its debt
+		fn pay_rewards(who: &T::AccountId) -> Result<(u128, u128), DispatchError> {
+			let native = Self::rewards(who, Asset::Native);
+			let tokens = Self::rewards(who, Asset::Token);
+			let held = Self::tokens(who).checked_add(tokens).ok_or(Error::<T>::StorageOverflow)?;
+
+			if native > 0 {
+				T::Currency::transfer(
+					&Self::account_id(),
+					who,
+					Self::balance(native)?,
+					ExistenceRequirement::AllowDeath,
+				)?;
+			}
+			if tokens > 0 {
+				<Tokens<T>>::insert(who, held);
+			}
+			<OwedRewards<T>>::remove(who, Asset::Native);
+			<OwedRewards<T>>::remove(who, Asset::Token);
+			Ok((native, tokens))
+		}
+
+		// set `who`'s shares, first setting aside the rewards its old shares earned
+		fn set_shares(who: &T::AccountId, shares: u128) {
+			for asset in [Asset::Native, Asset::Token] {
+				let owed = Self::rewards(who, asset);
+				if owed > 0 {
+					<OwedRewards<T>>::insert(who, asset, owed);
+				}
+			}
+			<Shares<T>>::insert(who, shares);
+			Self::set_reward_debt(who, shares);
+		}
+
+		// credit `shares` of `who` with the fees accrued so far, so they only earn later ones
+		fn set_reward_debt(who: &T::AccountId, shares: u128) {
+			for asset in [Asset::Native, Asset::Token] {
+				match mul_div(shares, Self::reward_per_share(asset), REWARD_PRECISION) {
+					Some(debt) if debt > 0 => <RewardDebt<T>>::insert(who, asset, debt),
+					_ => <RewardDebt<T>>::remove(who, asset),
+				}
+			}
+		}
+
+		// register the attested `who` as a liquidity provider
+		fn register_provider(who: T::AccountId) -> DispatchResult {
+			let verification = Self::attestation(&who);
+			ensure!(verification > 0, Error::<T>::NotAttested);
+
+			let joined_at = <frame_system::Pallet<T>>::block_number();
+			let info = ProviderInfo { joined_at, contributed: Zero::zero(), verification };
+			<LiquidityProviders<T>>::insert(&who, info);
+			Self::deposit_event(Event::NewLiquidityProvider(who));
+			Ok(())
+		}
+
+		// contribute `amount` of the provider's balance and, once the pool holds tokens, the same
+		// part of them, up to `max_tokens` if given; while it holds none, `max_tokens` are added
+		fn contribute(
+			who: T::AccountId,
+			amount: BalanceOf<T>,
+			max_tokens: Option<u128>,
+		) -> DispatchResult {
+			ensure!(
+				!amount.is_zero() && amount >= Self::parameters().min_contribution,
+				Error::<T>::MinimumLPShareAmountNotMet
+			);
+			let mut info =
+				Self::liquidity_provider(&who).ok_or(Error::<T>::LProviderIdentityDoesNotExist)?;
+			ensure!(
+				info.verification >= T::MinContributionLevel::get(),
+				Error::<T>::VerificationLevelTooLow
+			);
+
+			let minted = Self::shares_for(amount).ok_or(Error::<T>::StorageOverflow)?;
+			ensure!(minted > 0, Error::<T>::MinimumLPShareAmountNotMet);
+			let reserve = Self::token_reserve();
+			let tokens = if reserve == 0 {
+				max_tokens.unwrap_or(0)
+			} else {
+				let tokens = mul_div_ceil(
+					amount.saturated_into(),
+					reserve,
+					Self::pool_balance().saturated_into(),
+				)
+				.ok_or(Error::<T>::NotEnoughLiquidity)?;
+				ensure!(max_tokens.map_or(true, |max| tokens <= max), Error::<T>::SlippageExceeded);
+				tokens
+			};
+			let balance =
+				Self::pool_balance().checked_add(&amount).ok_or(Error::<T>::StorageOverflow)?;
+			let total =
+				Self::total_shares().checked_add(minted).ok_or(Error::<T>::StorageOverflow)?;
+			let shares =
+				Self::shares(&who).checked_add(minted).ok_or(Error::<T>::StorageOverflow)?;
+			let held =
+				Self::tokens(&who).checked_sub(tokens).ok_or(Error::<T>::InsufficientTokens)?;
+			let reserve = reserve.checked_add(tokens).ok_or(Error::<T>::StorageOverflow)?;
+			info.contributed =
+				info.contributed.checked_add(&amount).ok_or(Error::<T>::StorageOverflow)?;
+
+			T::Currency::transfer(
+				&who,
+				&Self::account_id(),
+				amount,
+				ExistenceRequirement::KeepAlive,
+			)?;
+			<PoolBalance<T>>::put(balance);
+			<TotalShares<T>>::put(total);
+			Self::set_shares(&who, shares);
+			<LiquidityProviders<T>>::insert(&who, info);
+			if tokens > 0 {
+				<Tokens<T>>::insert(&who, held);
+				<TokenReserve<T>>::put(reserve);
+				Self::deposit_event(Event::ContributedTokens(tokens, who.clone()));
+			}
+			Self::record(&who, TransactionKind::Contribution, amount.saturated_into(), None);
+			Self::deposit_event(Event::ContributedLPShares(amount, minted, who));
+			Ok(())
+		}
+
+		// expire the withdrawals left unconfirmed for `confirmation_timeout` blocks, unlocking
+		// their pool tokens, in the order they were requested
+		fn expire_withdrawals(now: T::BlockNumber) -> Weight {
+			let start = Self::withdrawal_expiry_cursor();
+			let end = Self::next_withdrawal_id()
+				.min(start.saturating_add(T::MaxPendingWithdrawals::get().into()));
+			let mut cursor = start;
+			let timeout = Self::parameters().confirmation_timeout;
+			while cursor < end {
+				if let Some(withdrawal) = Self::withdrawal(cursor) {
+					if withdrawal.requested_at.saturating_add(timeout) > now {
+						break;
+					}
+					<Withdrawals<T>>::remove(cursor);
+					<PendingB2cPayments<T>>::mutate(|pending| pending.retain(|id| *id != cursor));
+					if let Some(conversation_id) = withdrawal.conversation_id {
+						<Conversations<T>>::remove(conversation_id);
+					}
+					let refunded = withdrawal.tokens.saturating_add(withdrawal.fee);
+					<Tokens<T>>::mutate(&withdrawal.who, |tokens| {
+						*tokens = tokens.saturating_add(refunded)
+					});
+					<DailyWithdrawals<T>>::mutate(&withdrawal.who, |(day, withdrawn)| {
+						let requested_on =
+							withdrawal.requested_at / T::BlocksPerDay::get().max(One::one());
+						if *day == requested_on {
+							*withdrawn = withdrawn.saturating_sub(withdrawal.amount);
+						}
+					});
+					let timed_out: DispatchError = Error::<T>::ConfirmationTimedOut.into();
+					T::Promises::reject(&AsyncOp::MpesaWithdrawal(cursor), timed_out);
+					T::Webhooks::notify(
+						&withdrawal.who,
+						AsyncOp::MpesaWithdrawal(cursor),
+						Err(timed_out),
+					);
+					Self::deposit_event(Event::WithdrawalRefunded(
+						cursor,
+						withdrawal.who,
+						refunded,
+					));
+				}
+				cursor += 1;
+			}
+
+			let looked_at = cursor - start + 1;
+			if cursor == start {
+				return T::DbWeight::get().reads(looked_at + 2);
+			}
+			<WithdrawalExpiryCursor<T>>::put(cursor);
+			T::DbWeight::get()
+				.reads_writes(looked_at * 3 + 2 + (cursor - start) * 4, (cursor - start) * 9 + 1)
+		}
+
+		/// The key of the offchain worker initiating the M-Pesa payment of the deposit or
+		/// withdrawal `id`.
+		pub fn worker_for(id: u64) -> Option<T::Public> {
+			let workers = Self::workers();
+			if workers.is_empty() {
+				return None;
+			}
+			workers.get((id % workers.len() as u64) as usize).cloned()
+		}
+
+		fn validate_worker_payload<P: SignedPayload<T>>(
+			payload: &P,
+			signature: &T::Signature,
+			block_number: T::BlockNumber,
+			id: u64,
+			pending: bool,
+			tag: &'static str,
+		) -> TransactionValidity {
+			if !payload.verify::<T::AuthorityId>(signature.clone()) {
+				return InvalidTransaction::BadProof.into();
+			}
+			if block_number > <frame_system::Pallet<T>>::block_number() {
+				return InvalidTransaction::Future.into();
+			}
+			if !pending {
+				return InvalidTransaction::Stale.into();
+			}
+			if Self::worker_for(id) != Some(payload.public()) {
+				return InvalidTransaction::BadSigner.into();
+			}
+
+			ValidTransaction::with_tag_prefix(tag)
+				.priority(T::UnsignedPriority::get())
+				.and_provides(id)
+				.longevity(PAYLOAD_LONGEVITY)
+				.propagate(true)
+				.build()
+		}
+
+		// generate and submit the receipt documents assigned to this node's worker key
+		fn publish_receipts(block_number: T::BlockNumber) {
+			for (id, receipt) in <PendingReceipts<T>>::iter() {
+				let worker = match Self::worker_for(id) {
+					Some(worker) => worker,
+					None => return,
+				};
+				let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![worker]);
+				if !signer.can_sign() {
+					continue;
+				}
+				let mut key = b"mpesa::receipt::".to_vec();
+				key.extend(id.encode());
+				let until = block_number.saturating_add(PAYLOAD_LONGEVITY.saturated_into());
+				if !claim_until(&key, block_number, until) {
+					continue;
+				}
+
+				let document = receipts::document(&receipt);
+				let result = signer.send_unsigned_transaction(
+					|account| ReceiptPayload {
+						public: account.public.clone(),
+						block_number,
+						receipt: id,
+						document: document.clone(),
+					},
+					|payload, signature| Call::submit_receipt { payload, signature },
+				);
+				if let Some((account, Err(()))) = result {
+					release(&key);
+					log::error!(
+						"M-Pesa: failed to submit receipt {} with account {:?}",
+						id,
+						account.id
+					);
+				}
+			}
+		}
+
+		// whether the keystore holds a worker's key; a node configured for Daraja without one
+		// warns on its first run, and says so once it finds one again
+		fn check_signing_key(block_number: T::BlockNumber) -> bool {
+			let can_sign = Signer::<T, T::AuthorityId>::any_account()
+				.with_filter(Self::workers())
+				.can_sign();
+			let missing = StorageValueRef::persistent(SIGNING_KEY_MISSING);
+			let flagged = missing.get::<bool>().ok().flatten().unwrap_or(false);
+			if !can_sign && !flagged && daraja::config().is_some() {
+				log::warn!(
+					"M-Pesa: no worker key in the keystore (key_type=mpsa, block={:?}); skipping \
+					 the STK pushes, B2C payments, rates and receipts until one is inserted",
+					block_number
+				);
+				missing.set(&true);
+			} else if can_sign && flagged {
+				log::info!("M-Pesa: found a worker key, resuming the offchain worker's tasks");
+				missing.clear();
+			}
+			can_sign
+		}
 
-			// emit event of the created LProvider accountID
-			Self::get_newLProvider(Event::NewLiquidityProvider(who));
+		// initiate the STK pushes of the pending deposits assigned to this node's worker key, after
+		// retrying those Daraja failed earlier
+		fn initiate_stk_pushes(run: &mut DarajaRun<T::BlockNumber>) -> Result<(), DarajaError> {
+			let now = run.block_number;
+			Jobs::<StkPushes<T>>::run_retries(run, now, DARAJA_RETRY_BATCH);
 
+			let mut jobs = Vec::new();
+			for id in Self::pending_stk_pushes() {
+				let worker = match Self::worker_for(id) {
+					Some(worker) => worker,
+					None => break,
+				};
+				let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![worker]);
+				if !signer.can_sign() {
+					continue;
+				}
+				let deposit = match Self::deposit(id) {
+					Some(deposit) => deposit,
+					None => continue,
+				};
+				daraja::config().ok_or(DarajaError::NotConfigured)?;
+
+				// claimed for good: a failed push is retried from the retry queue
+				let mut key = b"mpesa::stk_push::".to_vec();
+				key.extend(id.encode());
+				if claim(&key) {
+					jobs.push((id, deposit));
+				}
+			}
+			Jobs::<StkPushes<T>>::process(run, jobs, now);
 			Ok(())
 		}
+
+		// initiate the B2C payments of the pending withdrawals assigned to this node's worker key,
+		// after retrying those Daraja failed earlier
+		fn initiate_b2c_payments(run: &mut DarajaRun<T::BlockNumber>) -> Result<(), DarajaError> {
+			let now = run.block_number;
+			Jobs::<B2cPayments<T>>::run_retries(run, now, DARAJA_RETRY_BATCH);
+
+			let mut jobs = Vec::new();
+			for id in Self::pending_b2c_payments() {
+				let worker = match Self::worker_for(id) {
+					Some(worker) => worker,
+					None => break,
+				};
+				let signer = Signer::<T, T::AuthorityId>::any_account().with_filter(vec![worker]);
+				if !signer.can_sign() {
+					continue;
+				}
+				let withdrawal = match Self::withdrawal(id) {
+					Some(withdrawal) => withdrawal,
+					None => continue,
+				};
+				daraja::config().ok_or(DarajaError::NotConfigured)?;
+				daraja::b2c_config().ok_or(DarajaError::NotConfigured)?;
+
+				let mut key = b"mpesa::b2c::".to_vec();
+				key.extend(id.encode());
+				if claim(&key) {
+					jobs.push((id, withdrawal));
+				}
+			}
+			Jobs::<B2cPayments<T>>::process(run, jobs, now);
+			Ok(())
+		}
+
+		// submit the checkout request Daraja assigned to a deposit's STK push
+		fn send_checkout_request(
+			block_number: T::BlockNumber,
+			id: DepositId,
+			checkout_request_id: CheckoutRequestId,
+		) {
+			let worker = match Self::worker_for(id) {
+				Some(worker) => worker,
+				None => return,
+			};
+			let result = Signer::<T, T::AuthorityId>::any_account()
+				.with_filter(vec![worker])
+				.send_unsigned_transaction(
+					|account| CheckoutPayload {
+						public: account.public.clone(),
+						block_number,
+						deposit: id,
+						checkout_request_id: checkout_request_id.clone(),
+					},
+					|payload, signature| Call::submit_checkout_request { payload, signature },
+				);
+			if let Some((account, Err(()))) = result {
+				log::error!(
+					"M-Pesa: failed to submit the checkout request of deposit {} with account {:?}",
+					id,
+					account.id
+				);
+			}
+		}
+
+		// submit the conversation Daraja assigned to a withdrawal's B2C payment
+		fn send_conversation(
+			block_number: T::BlockNumber,
+			id: WithdrawalId,
+			conversation_id: ConversationId,
+		) {
+			let worker = match Self::worker_for(id) {
+				Some(worker) => worker,
+				None => return,
+			};
+			let result = Signer::<T, T::AuthorityId>::any_account()
+				.with_filter(vec![worker])
+				.send_unsigned_transaction(
+					|account| ConversationPayload {
+						public: account.public.clone(),
+						block_number,
+						withdrawal: id,
+						conversation_id: conversation_id.clone(),
+					},
+					|payload, signature| Call::submit_conversation { payload, signature },
+				);
+			if let Some((account, Err(()))) = result {
+				log::error!(
+					"M-Pesa: failed to submit the conversation of withdrawal {} with account {:?}",
+					id,
+					account.id
+				);
+			}
+		}
+
+		// pay the withdrawal out to its phone, fetching an access token on first use
+		fn b2c_payment(
+			config: &daraja::DarajaConfig,
+			b2c: &daraja::B2cConfig,
+			token: &mut Option<Vec<u8>>,
+			id: WithdrawalId,
+			withdrawal: &WithdrawalRequest<T::AccountId, T::BlockNumber>,
+		) -> Result<ConversationId, DarajaError> {
+			let phone =
+				daraja::phone_number(&withdrawal.phone_hash).ok_or(DarajaError::UnknownPhone)?;
+			if token.is_none() {
+				*token = Some(daraja::access_token(config)?);
+			}
+			let token = token.as_deref().unwrap_or_default();
+			let conversation_id =
+				daraja::b2c_payment(config, b2c, token, &phone, withdrawal.amount, id)?;
+			conversation_id.try_into().map_err(|_| DarajaError::UnexpectedResponse)
+		}
+
+		// prompt the deposit's phone to pay, fetching an access token on first use
+		fn stk_push(
+			config: &daraja::DarajaConfig,
+			token: &mut Option<Vec<u8>>,
+			id: DepositId,
+			deposit: &DepositRequest<T::AccountId, T::BlockNumber>,
+		) -> Result<CheckoutRequestId, DarajaError> {
+			let phone =
+				daraja::phone_number(&deposit.phone_hash).ok_or(DarajaError::UnknownPhone)?;
+			if token.is_none() {
+				*token = Some(daraja::access_token(config)?);
+			}
+			let token = token.as_deref().unwrap_or_default();
+			let now = sp_io::offchain::timestamp().unix_millis();
+			let checkout_request_id =
+				daraja::stk_push(config, token, &phone, deposit.amount, id, now)?;
+			checkout_request_id.try_into().map_err(|_| DarajaError::UnexpectedResponse)
+		}
+	}
+
+	// What an offchain worker run shares between its Daraja requests: the block its payloads are
+	// signed for, and the access token, fetched on first use.
+	struct DarajaRun<BlockNumber> {
+		block_number: BlockNumber,
+		token: Option<Vec<u8>>,
+	}
+
+	// Runs the STK pushes of the deposits assigned to this node's worker key: prompts the phones
+	// and submits the checkout requests, retrying the pushes Daraja failed.
+	struct StkPushes<T>(PhantomData<T>);
+
+	impl<T: Config> JobHandler for StkPushes<T> {
+		type BlockNumber = T::BlockNumber;
+		type Job = (DepositId, DepositRequest<T::AccountId, T::BlockNumber>);
+		type Output = CheckoutRequestId;
+		type Error = DarajaError;
+		type Context = DarajaRun<T::BlockNumber>;
+
+		const QUEUE: &'static [u8] = STK_PUSH_RETRIES;
+
+		fn max_retries() -> u32 {
+			DARAJA_RETRIES
+		}
+
+		// the deposit expired, or a run of another node pushed it after all
+		fn is_stale((id, _): &Self::Job) -> bool {
+			!Pallet::<T>::pending_stk_pushes().contains(id)
+		}
+
+		fn process(
+			run: &mut Self::Context,
+			(id, deposit): &Self::Job,
+			_: Option<&DarajaError>,
+		) -> Result<CheckoutRequestId, DarajaError> {
+			let config = daraja::config().ok_or(DarajaError::NotConfigured)?;
+			Pallet::<T>::stk_push(&config, &mut run.token, *id, deposit)
+		}
+
+		fn on_complete(
+			run: &mut Self::Context,
+			outcomes: Vec<Outcome<Self::Job, CheckoutRequestId, DarajaError>>,
+		) {
+			for outcome in outcomes {
+				match outcome {
+					Outcome::Done { job: (id, _), output, .. } => {
+						Pallet::<T>::send_checkout_request(run.block_number, id, output)
+					},
+					Outcome::Failed { job: (id, _), error: DarajaError::UnknownPhone, .. } => {
+						log::warn!("M-Pesa: deposit {} is for an unknown phone", id)
+					},
+					Outcome::Failed { job: (id, _), attempts, error, .. } => log::warn!(
+						"M-Pesa: attempt {} at the STK push of deposit {} failed: {:?}",
+						attempts,
+						id,
+						error
+					),
+				}
+			}
+		}
+
+		// the deposit stays claimed, so it expires on-chain
+		fn on_dead_letter((id, _): Self::Job, attempts: u32, error: DarajaError) {
+			log::error!(
+				"M-Pesa: gave up on the STK push of deposit {} after {} attempts: {:?}",
+				id,
+				attempts,
+				error
+			);
+		}
+	}
+
+	// Runs the B2C payments of the withdrawals assigned to this node's worker key: pays the phones
+	// and submits the conversations, retrying the payments Daraja failed.
+	struct B2cPayments<T>(PhantomData<T>);
+
+	impl<T: Config> JobHandler for B2cPayments<T> {
+		type BlockNumber = T::BlockNumber;
+		type Job = (WithdrawalId, WithdrawalRequest<T::AccountId, T::BlockNumber>);
+		type Output = ConversationId;
+		type Error = DarajaError;
+		type Context = DarajaRun<T::BlockNumber>;
+
+		const QUEUE: &'static [u8] = B2C_RETRIES;
+
+		fn max_retries() -> u32 {
+			DARAJA_RETRIES
+		}
+
+		// the withdrawal expired, or a run of another node paid it after all
+		fn is_stale((id, _): &Self::Job) -> bool {
+			!Pallet::<T>::pending_b2c_payments().contains(id)
+		}
+
+		fn process(
+			run: &mut Self::Context,
+			(id, withdrawal): &Self::Job,
+			_: Option<&DarajaError>,
+		) -> Result<ConversationId, DarajaError> {
+			let config = daraja::config().ok_or(DarajaError::NotConfigured)?;
+			let b2c = daraja::b2c_config().ok_or(DarajaError::NotConfigured)?;
+			Pallet::<T>::b2c_payment(&config, &b2c, &mut run.token, *id, withdrawal)
+		}
+
+		fn on_complete(
+			run: &mut Self::Context,
+			outcomes: Vec<Outcome<Self::Job, ConversationId, DarajaError>>,
+		) {
+			for outcome in outcomes {
+				match outcome {
+					Outcome::Done { job: (id, _), output, .. } => {
+						Pallet::<T>::send_conversation(run.block_number, id, output)
+					},
+					Outcome::Failed { job: (id, _), error: DarajaError::UnknownPhone, .. } => {
+						log::warn!("M-Pesa: withdrawal {} is to an unknown phone", id)
+					},
+					Outcome::Failed { job: (id, _), attempts, error, .. } => log::warn!(
+						"M-Pesa: attempt {} at the B2C payment of withdrawal {} failed: {:?}",
+						attempts,
+						id,
+						error
+					),
+				}
+			}
+		}
+
+		// the withdrawal stays claimed, so it expires on-chain and is refunded
+		fn on_dead_letter((id, _): Self::Job, attempts: u32, error: DarajaError) {
+			log::error!(
+				"M-Pesa: gave up on the B2C payment of withdrawal {} after {} attempts: {:?}",
+				id,
+				attempts,
+				error
+			);
+		}
+	}
+
+	/// Phones contribute through their USSD gateway like any provider does, becoming one with their
+	/// first contribution once a verifier attested their account.
+	impl<T: Config> mpesa_primitives::LiquidityPool<T::AccountId, BalanceOf<T>> for Pallet<T> {
+		fn contribute(who: &T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+			if !<LiquidityProviders<T>>::contains_key(who) {
+				Self::register_provider(who.clone())?;
+			}
+			Pallet::<T>::contribute(who.clone(), amount, None)
+		}
+
+		#[cfg(feature = "runtime-benchmarks")]
+		fn prepare_contributor(who: &T::AccountId) {
+			<Attestations<T>>::insert(who, VerificationLevel::MAX);
+		}
 	}
-}
\ No newline at end of file
+}
@@ -0,0 +1,96 @@
+//! The pool's arithmetic, kept apart from its storage so that the golden vectors in `vectors/`
+//! run exactly the code the pallet does.
+
+use sp_core::U256;
+use sp_runtime::Perbill;
+
+/// The scale of the fees accrued per share, so that small fees over many shares aren't lost.
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+/// `a * b / c` rounded down, without overflowing in the intermediate product.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+	if c == 0 {
+		return None;
+	}
+	let result = U256::from(a) * U256::from(b) / U256::from(c);
+	u128::try_from(result).ok()
+}
+
+/// `a * b / c` rounded up.
+pub fn mul_div_ceil(a: u128, b: u128, c: u128) -> Option<u128> {
+	if c == 0 {
+		return None;
+	}
+	let product = U256::from(a) * U256::from(b);
+	let result = (product + U256::from(c) - 1) / U256::from(c);
+	u128::try_from(result).ok()
+}
+
+/// The shares a contribution of `amount` mints: `amount * total_shares / pool_balance`, rounded
+/// down so that existing shares never lose value, or `amount` itself while the pool is empty.
+pub fn shares_for(amount: u128, total_shares: u128, pool_balance: u128) -> Option<u128> {
+	if total_shares == 0 || pool_balance == 0 {
+		return Some(amount);
+	}
+	mul_div(amount, total_shares, pool_balance)
+}
+
+/// The part of `pool_balance` that `shares` are worth: `shares * pool_balance / total_shares`,
+/// rounded down so that the remaining shares never lose value.
+pub fn redemption_value(shares: u128, total_shares: u128, pool_balance: u128) -> Option<u128> {
+	if total_shares == 0 {
+		return Some(0);
+	}
+	mul_div(shares, pool_balance, total_shares)
+}
+
+/// What selling `amount_in` to a pool with the given reserves buys once `swap_fee` is taken:
+/// `reserve_out * in / (reserve_in + in)`, rounded down.
+pub fn amount_out(
+	reserve_in: u128,
+	reserve_out: u128,
+	swap_fee: Perbill,
+	amount_in: u128,
+) -> Option<u128> {
+	if reserve_in == 0 || reserve_out == 0 {
+		return None;
+	}
+	let in_after_fee = amount_in - swap_fee * amount_in;
+	mul_div(reserve_out, in_after_fee, reserve_in.checked_add(in_after_fee)?)
+}
+
+/// What buying `amount_out` from a pool with the given reserves costs, `swap_fee` included:
+/// `reserve_in * out / (reserve_out - out)`, rounded up.
+pub fn amount_in(
+	reserve_in: u128,
+	reserve_out: u128,
+	swap_fee: Perbill,
+	amount_out: u128,
+) -> Option<u128> {
+	if reserve_in == 0 || amount_out >= reserve_out {
+		return None;
+	}
+	let in_after_fee = mul_div_ceil(reserve_in, amount_out, reserve_out - amount_out)?;
+	let kept = Perbill::one().saturating_sub(swap_fee).deconstruct();
+	mul_div_ceil(in_after_fee, Perbill::one().deconstruct() as u128, kept as u128)
+}
+
+/// How `fee` is split between the treasury and `total_shares`: the treasury's part, and what the
+/// fees accrued per share grow by. It all goes to the treasury while there are no shares to
+/// accrue it to, or too few of it to add to the fees accrued per share.
+pub fn split_fee(fee: u128, protocol_fee: Perbill, total_shares: u128) -> (u128, u128) {
+	let treasury = protocol_fee * fee;
+	match mul_div(fee - treasury, REWARD_PRECISION, total_shares).filter(|per_share| *per_share > 0)
+	{
+		Some(per_share) => (treasury, per_share),
+		None => (fee, 0),
+	}
+}
+
+/// The fees `shares` earned at `reward_per_share`, less the `reward_debt` they were already
+/// credited with.
+pub fn earned(shares: u128, reward_per_share: u128, reward_debt: u128) -> u128 {
+	mul_div(shares, reward_per_share, REWARD_PRECISION)
+		.unwrap_or(0)
+		.saturating_sub(reward_debt)
+}
@@ -1,15 +1,29 @@
-use crate as pallet_template;
-use frame_support::traits::{ConstU16, ConstU64};
+use crate as pallet_mpesa_user;
+use crate::{IpfsStore, PoolParameters};
+use frame_support::{
+	ord_parameter_types, parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, ConstU8},
+	PalletId,
+};
 use frame_system as system;
-use sp_core::H256;
+use frame_system::{EnsureRoot, EnsureSignedBy};
+use parking_lot::RwLock;
+use sp_core::{
+	offchain::{testing, OffchainDbExt, OffchainWorkerExt, TransactionPoolExt},
+	H256,
+};
 use sp_runtime::{
-	testing::Header,
+	testing::{Header, TestSignature, TestXt, UintAuthorityId},
 	traits::{BlakeTwo256, IdentityLookup},
+	DispatchError, DispatchResult, Perbill,
 };
+use std::{cell::RefCell, sync::Arc};
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 
+pub type Extrinsic = TestXt<Call, ()>;
+
 // Configure a mock runtime to test the pallet.
 frame_support::construct_runtime!(
 	pub enum Test where
@@ -18,7 +32,8 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system,
-		TemplateModule: pallet_template,
+		Balances: pallet_balances,
+		MpesaUser: pallet_mpesa_user,
 	}
 );
 
@@ -40,20 +55,167 @@ impl system::Config for Test {
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
 	type SS58Prefix = ConstU16<42>;
 	type OnSetCode = ();
-	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MpesaPalletId: PalletId = PalletId(*b"py/mpesa");
+	pub const Treasury: u64 = 5;
+}
+
+ord_parameter_types! {
+	pub const Oracle: u64 = 4;
+	pub const Verifier: u64 = 6;
+	pub const Arbiter: u64 = 7;
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = UintAuthorityId;
+	type Signature = TestSignature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+/// Signs with the `UintAuthorityId` keys set with `UintAuthorityId::set_all_keys`.
+pub struct TestAuthId;
+
+impl frame_system::offchain::AppCrypto<UintAuthorityId, TestSignature> for TestAuthId {
+	type RuntimeAppPublic = UintAuthorityId;
+	type GenericSignature = TestSignature;
+	type GenericPublic = UintAuthorityId;
+}
+
+thread_local! {
+	// the documents added to `TestIpfs`, with their owners
+	pub static IPFS: RefCell<Vec<(u64, Vec<u8>)>> = RefCell::new(vec![]);
+	// the CIDs of the documents in `IPFS` that lost their pin
+	pub static UNPINNED: RefCell<Vec<Vec<u8>>> = RefCell::new(vec![]);
+}
+
+/// Keeps the documents it is given in `IPFS`, naming the `n`th one `cid<n>`. They stay pinned
+/// unless their CID is put in `UNPINNED`.
+pub struct TestIpfs;
+
+impl IpfsStore<u64> for TestIpfs {
+	fn add_bytes(owner: &u64, data: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+		IPFS.with(|ipfs| {
+			let mut ipfs = ipfs.borrow_mut();
+			ipfs.push((*owner, data));
+			Ok(format!("cid{}", ipfs.len()).into_bytes())
+		})
+	}
+
+	fn is_pinned(cid: &[u8]) -> bool {
+		let added = IPFS
+			.with(|ipfs| (1..=ipfs.borrow().len()).any(|n| format!("cid{}", n).as_bytes() == cid));
+		added && !UNPINNED.with(|unpinned| unpinned.borrow().iter().any(|c| c == cid))
+	}
+
+	fn pin(_owner: &u64, cid: Vec<u8>) -> DispatchResult {
+		UNPINNED.with(|unpinned| unpinned.borrow_mut().retain(|c| *c != cid));
+		Ok(())
+	}
 }
 
-impl pallet_template::Config for Test {
+impl pallet_mpesa_user::Config for Test {
 	type Event = Event;
+	type Currency = Balances;
+	type PalletId = MpesaPalletId;
+	type AuthorityId = TestAuthId;
+	type MaxPendingDeposits = ConstU32<2>;
+	type MaxPendingWithdrawals = ConstU32<2>;
+	type MaxWorkers = ConstU32<2>;
+	type UnsignedPriority = ConstU64<100>;
+	type OracleOrigin = EnsureSignedBy<Oracle, u64>;
+	type VerifierOrigin = EnsureSignedBy<Verifier, u64>;
+	type AdminOrigin = EnsureRoot<u64>;
+	type MinContributionLevel = ConstU8<1>;
+	type MinWithdrawalLevel = ConstU8<2>;
+	type BlocksPerDay = ConstU64<100>;
+	type RateInterval = ConstU64<5>;
+	type MaxRateAge = ConstU64<20>;
+	type TreasuryAccount = Treasury;
+	type DisputeOrigin = EnsureSignedBy<Arbiter, u64>;
+	type EscrowTimeout = ConstU64<10>;
+	type MaxEscrows = ConstU32<2>;
+	type MaxHistory = ConstU32<3>;
+	type HistoryArchiveBatch = ConstU32<2>;
+	type ReceiptStore = TestIpfs;
+	type ReceiptPinGrace = ConstU64<5>;
+	type Promises = ();
+	type Webhooks = ();
+	type WeightInfo = ();
 }
 
+pub const INITIAL_BALANCE: u64 = 1_000_000;
+
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+	new_test_ext_with_providers(vec![])
+}
+
+// Build genesis storage with `providers` registered, each attested at a level and contributing.
+pub fn new_test_ext_with_providers(providers: Vec<(u64, u8, u64)>) -> sp_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: (1..=3).map(|who| (who, INITIAL_BALANCE)).collect(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	pallet_mpesa_user::GenesisConfig::<Test> {
+		parameters: PoolParameters {
+			swap_fee: Perbill::from_percent(1),
+			withdrawal_fee: Perbill::from_percent(1),
+			protocol_fee: Perbill::from_percent(20),
+			min_contribution: 1,
+			daily_withdrawal_limit: 1_000,
+			confirmation_timeout: 10,
+		},
+		providers,
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	t.into()
+}
+
+/// The account of the offchain worker key the offchain tests sign with.
+pub const WORKER: u64 = 10;
+
+// Build genesis storage with offchain worker extensions, signing with `WORKER`'s key.
+pub fn new_offchain_ext(
+) -> (sp_io::TestExternalities, Arc<RwLock<testing::OffchainState>>, Arc<RwLock<testing::PoolState>>)
+{
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+	UintAuthorityId::set_all_keys(vec![WORKER]);
+
+	let mut ext = new_test_ext();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext.register_extension(TransactionPoolExt::new(pool));
+	(ext, offchain_state, pool_state)
 }
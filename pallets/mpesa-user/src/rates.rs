@@ -0,0 +1,97 @@
+//! Fetching the KES exchange rate for the offchain worker.
+//!
+//! Like the Daraja credentials, the rate's sources are node-local: the node operator stores the
+//! SCALE-encoded `Vec<RateSource>` under `SOURCES_KEY` in the node's persistent off-chain storage.
+//! The worker queries each source and submits the median of the rates it got.
+
+use crate::daraja::{self, DarajaError};
+use codec::{Decode, Encode};
+use sp_runtime::{
+	offchain::{http, storage::StorageValueRef},
+	FixedPointNumber, FixedU128,
+};
+use sp_std::{str, vec::Vec};
+
+/// The off-chain storage key of the SCALE-encoded `Vec<RateSource>`.
+pub const SOURCES_KEY: &[u8] = b"mpesa::rate::sources";
+
+/// An HTTP endpoint returning the price of a dollar (or a dollar stablecoin) in shillings.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct RateSource {
+	/// The URL to `GET`, e.g. `https://open.er-api.com/v6/latest/USD`.
+	pub url: Vec<u8>,
+	/// The key of the rate's member in the JSON response, e.g. `KES`.
+	pub key: Vec<u8>,
+}
+
+/// The node's rate sources, if the operator configured any.
+pub fn sources() -> Vec<RateSource> {
+	StorageValueRef::persistent(SOURCES_KEY)
+		.get::<Vec<RateSource>>()
+		.ok()
+		.flatten()
+		.unwrap_or_default()
+}
+
+/// Query a source for the rate.
+pub fn fetch(source: &RateSource) -> Result<FixedU128, DarajaError> {
+	let url = str::from_utf8(&source.url).map_err(|_| DarajaError::NotConfigured)?;
+	let body = daraja::send(http::Request::get(url))?;
+	rate(&body, &source.key).ok_or(DarajaError::UnexpectedResponse)
+}
+
+/// The rate in a source's response, unless it is missing or zero.
+pub fn rate(body: &[u8], key: &[u8]) -> Option<FixedU128> {
+	json_number(body, key).filter(|rate| !rate.is_zero())
+}
+
+/// The median of `rates`, averaging the middle two of an even number of them.
+pub fn median(mut rates: Vec<FixedU128>) -> Option<FixedU128> {
+	rates.sort();
+	let middle = rates.len() / 2;
+	match rates.len() {
+		0 => None,
+		len if len % 2 == 1 => Some(rates[middle]),
+		_ => {
+			let sum = rates[middle - 1].saturating_add(rates[middle]);
+			Some(FixedU128::from_inner(sum.into_inner() / 2))
+		},
+	}
+}
+
+/// The value of the number member `key` of a JSON object, which can be nested, e.g.
+/// `{"rates":{"KES":129.25}}`; exponents are not supported.
+pub fn json_number(json: &[u8], key: &[u8]) -> Option<FixedU128> {
+	let needle = [&b"\""[..], key, b"\""].concat();
+	let start = json.windows(needle.len()).position(|window| window == &needle[..])?;
+	let rest = daraja::skip_whitespace(&json[start + needle.len()..]).strip_prefix(b":")?;
+	let rest = daraja::skip_whitespace(rest);
+	let end = rest
+		.iter()
+		.position(|b| !b.is_ascii_digit() && *b != b'.')
+		.unwrap_or(rest.len());
+	parse_decimal(&rest[..end])
+}
+
+// a non-negative decimal number, e.g. `129.25`, rounded down to the precision of `FixedU128`
+fn parse_decimal(number: &[u8]) -> Option<FixedU128> {
+	let (integer, fraction) = match number.iter().position(|b| *b == b'.') {
+		Some(dot) => (&number[..dot], &number[dot + 1..]),
+		None => (number, &[][..]),
+	};
+	if integer.is_empty() || fraction.contains(&b'.') {
+		return None;
+	}
+
+	let mut inner: u128 = 0;
+	for digit in integer {
+		inner = inner.checked_mul(10)?.checked_add((digit - b'0') as u128)?;
+	}
+	inner = inner.checked_mul(FixedU128::DIV)?;
+	let mut scale = FixedU128::DIV;
+	for digit in fraction {
+		scale /= 10;
+		inner = inner.checked_add((digit - b'0') as u128 * scale)?;
+	}
+	Some(FixedU128::from_inner(inner))
+}
@@ -0,0 +1,51 @@
+//! Receipt documents for confirmed M-Pesa payments.
+//!
+//! Once a deposit or withdrawal is confirmed, the offchain worker renders its receipt as a flat
+//! JSON object and submits it; the pallet checks it against the chain's own record before
+//! publishing it to IPFS, so the CID kept in the account's history commits to a document anyone
+//! can fetch and check against the chain.
+
+use codec::Encode;
+use sp_runtime::traits::UniqueSaturatedInto;
+use sp_std::vec::Vec;
+
+use crate::{daraja, Receipt, TransactionKind};
+
+/// The JSON document of `receipt`, e.g.
+/// `{"kind":"deposit","id":"0","account":"0x01…","tokens":"50000","mpesaReceipt":"NLJ7RT61SV",
+/// "block":"3"}`; the account is hex of its SCALE encoding.
+pub fn document<AccountId, BlockNumber>(receipt: &Receipt<AccountId, BlockNumber>) -> Vec<u8>
+where
+	AccountId: Encode,
+	BlockNumber: Copy + UniqueSaturatedInto<u128>,
+{
+	let kind: &[u8] = match receipt.kind {
+		TransactionKind::Withdrawal => b"withdrawal",
+		_ => b"deposit",
+	};
+	let decimal = |value: u128| {
+		let mut digits = Vec::new();
+		daraja::write_decimal(&mut digits, value);
+		digits
+	};
+
+	daraja::json_object(&[
+		(b"kind", kind),
+		(b"id", &decimal(receipt.id.into())),
+		(b"account", &hex(&receipt.who.encode())),
+		(b"tokens", &decimal(receipt.tokens)),
+		(b"mpesaReceipt", &receipt.mpesa_receipt),
+		(b"block", &decimal(receipt.block.unique_saturated_into())),
+	])
+}
+
+// `0x`-prefixed lowercase hex
+fn hex(bytes: &[u8]) -> Vec<u8> {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+	let mut hex = b"0x".to_vec();
+	for byte in bytes {
+		hex.push(DIGITS[(byte >> 4) as usize]);
+		hex.push(DIGITS[(byte & 0xf) as usize]);
+	}
+	hex
+}
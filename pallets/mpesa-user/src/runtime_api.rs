@@ -0,0 +1,56 @@
+// Runtime API for reading the pool's records of an account.
+//
+// Auditors would otherwise have to index every block's events to reconstruct an account's
+// deposits, withdrawals, swaps and transfers. A runtime implements the API by forwarding to
+// `Pallet::history`, and the node exposes it over RPC as `mpesa_accountHistory`.
+//
+// `Settlement` and `PoolPosition` are the items of the pages `Pallet::settlements_page` and
+// `Pallet::positions_page` return for indexers.
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+use crate::{ReceiptCid, TransactionKind, TransactionRecord};
+
+/// A confirmed deposit or completed withdrawal of an account.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Settlement<AccountId, BlockNumber> {
+	/// The account the pool tokens were minted for or burnt from.
+	pub who: AccountId,
+	/// `Deposit` or `Withdrawal`.
+	pub kind: TransactionKind,
+	/// The pool tokens minted or burnt.
+	pub amount: u128,
+	/// The block the payment was confirmed in.
+	pub block: BlockNumber,
+	/// The CID of the receipt document, once it is published.
+	pub receipt: Option<ReceiptCid>,
+}
+
+/// A liquidity provider's stake in the pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PoolPosition<AccountId, BlockNumber, Balance> {
+	/// The provider.
+	pub who: AccountId,
+	/// The block the provider registered in.
+	pub joined_at: BlockNumber,
+	/// The total amount the provider contributed to the pool.
+	pub contributed: Balance,
+	/// The provider's shares of the pool.
+	pub shares: u128,
+	/// The provider's pool tokens.
+	pub tokens: u128,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to read the mpesa-user pallet's per-account records.
+	pub trait MpesaUserApi<AccountId, BlockNumber> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// The last transactions of `account`, oldest first.
+		fn account_history(account: AccountId) -> Vec<TransactionRecord<AccountId, BlockNumber>>;
+	}
+}
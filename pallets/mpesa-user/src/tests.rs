@@ -1,20 +1,1618 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+	daraja::{self, B2cConfig, DarajaConfig, DarajaError},
+	mock::*,
+	rates::{self, RateSource},
+	runtime_api::{PoolPosition, Settlement},
+	Asset, CheckoutPayload, ConversationPayload, DepositId, DepositRequest, Error,
+	Event as MpesaEvent, Parameter, PoolBalance, RatePayload, TotalShares, TransactionKind,
+	TransactionRecord, WithdrawalId,
+};
+use codec::{Decode, Encode};
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::DispatchError,
+	traits::{Currency, Hooks},
+	weights::Weight,
+};
+use frame_system::offchain::SignedPayload;
+use mpesa_primitives::{IndexFilter, LiquidityPool, Page, PageRequest};
+use pallet_balances::Error as BalancesError;
+use pallet_ocw_jobs::Retry;
+use sp_core::offchain::testing::PendingRequest;
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	testing::UintAuthorityId,
+	traits::ValidateUnsigned,
+	transaction_validity::{InvalidTransaction, TransactionSource},
+	FixedU128, Perbill,
+};
+
+// attest `who`'s identity at `level`
+fn attest(who: u64, level: u8) {
+	assert_ok!(MpesaUser::attest_identity(Origin::signed(Verifier::get()), who, level));
+}
+
+// register `who` as a liquidity provider that contributed `amount`
+fn provider(who: u64, amount: u64) {
+	attest(who, 2);
+	assert_ok!(MpesaUser::create_LProviderIdentity(Origin::signed(who)));
+	if amount > 0 {
+		assert_ok!(MpesaUser::contribute_LPShares(Origin::signed(who), amount));
+	}
+}
+
+#[test]
+fn providers_register_once() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 0);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::NewLiquidityProvider(1)));
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.joined_at), Some(1));
+		assert_noop!(
+			MpesaUser::create_LProviderIdentity(Origin::signed(1)),
+			Error::<Test>::LProviderIdentityAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn genesis_providers_fund_the_pool() {
+	new_test_ext_with_providers(vec![(1, 2, 300), (2, 1, 0)]).execute_with(|| {
+		assert_eq!(MpesaUser::attestation(1), 2);
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.contributed), Some(300));
+		assert_eq!((MpesaUser::pool_balance(), MpesaUser::total_shares()), (300, 300));
+		assert_eq!(MpesaUser::shares(1), 300);
+		assert_eq!(Balances::free_balance(1), INITIAL_BALANCE - 300);
+		assert_eq!(MpesaUser::liquidity_provider(2).map(|info| info.verification), Some(1));
+		assert_eq!(MpesaUser::shares(2), 0);
+	});
+}
+
+#[test]
+fn providers_need_an_attested_identity() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			MpesaUser::create_LProviderIdentity(Origin::signed(1)),
+			Error::<Test>::NotAttested
+		);
+		assert_noop!(MpesaUser::attest_identity(Origin::signed(1), 1, 1), DispatchError::BadOrigin);
+		assert_noop!(
+			MpesaUser::attest_identity(Origin::signed(Verifier::get()), 1, 0),
+			Error::<Test>::VerificationLevelTooLow
+		);
+
+		attest(1, 1);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::IdentityAttested(1, 1)));
+		assert_ok!(MpesaUser::create_LProviderIdentity(Origin::signed(1)));
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.verification), Some(1));
+		assert_ok!(MpesaUser::contribute_LPShares(Origin::signed(1), 100));
+
+		// revoked providers keep their shares but can't add to them
+		assert_ok!(MpesaUser::revoke_attestation(Origin::signed(Verifier::get()), 1));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::AttestationRevoked(1)));
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.verification), Some(0));
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 100),
+			Error::<Test>::VerificationLevelTooLow
+		);
+		assert_noop!(
+			MpesaUser::revoke_attestation(Origin::signed(Verifier::get()), 1),
+			Error::<Test>::NotAttested
+		);
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(1), 100));
+
+		attest(1, 2);
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.verification), Some(2));
+		assert_ok!(MpesaUser::contribute_LPShares(Origin::signed(1), 100));
+	});
+}
+
+#[test]
+fn parameters_are_set_by_the_admin_origin() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_eq!(MpesaUser::parameters().confirmation_timeout, 10);
+		assert_noop!(
+			MpesaUser::set_parameter(Origin::signed(1), Parameter::MinContribution(50)),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MpesaUser::set_parameter(Origin::root(), Parameter::SwapFee(Perbill::one())),
+			Error::<Test>::InvalidParameter
+		);
+		assert_noop!(
+			MpesaUser::set_parameter(Origin::root(), Parameter::ConfirmationTimeout(0)),
+			Error::<Test>::InvalidParameter
+		);
+
+		assert_ok!(MpesaUser::set_parameter(Origin::root(), Parameter::MinContribution(50)));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::ParameterSet(
+			Parameter::MinContribution(50),
+		)));
+		assert_ok!(MpesaUser::set_parameter(Origin::root(), Parameter::DailyWithdrawalLimit(5)));
+		assert_eq!(
+			(
+				MpesaUser::parameters().min_contribution,
+				MpesaUser::parameters().daily_withdrawal_limit
+			),
+			(50, 5)
+		);
+
+		provider(1, 0);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 49),
+			Error::<Test>::MinimumLPShareAmountNotMet
+		);
+		assert_ok!(MpesaUser::contribute_LPShares(Origin::signed(1), 50));
+		buy_tokens(1, 10);
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 6),
+			Error::<Test>::DailyLimitExceeded
+		);
+	});
+}
+
+#[test]
+fn contributions_add_up() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 100),
+			Error::<Test>::LProviderIdentityDoesNotExist
+		);
+		provider(1, 100);
+		provider(2, 50);
+		assert_ok!(MpesaUser::contribute_LPShares(Origin::signed(1), 20));
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 0),
+			Error::<Test>::MinimumLPShareAmountNotMet
+		);
+
+		assert_eq!(MpesaUser::pool_balance(), 170);
+		assert_eq!((MpesaUser::shares(1), MpesaUser::total_shares()), (120, 170));
+		assert_eq!(MpesaUser::liquidity_provider(1).map(|info| info.contributed), Some(120));
+
+		// the funds are moved into the pool's account
+		assert_eq!(Balances::free_balance(MpesaUser::account_id()), 170);
+		assert_eq!(Balances::free_balance(1), INITIAL_BALANCE - 120);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(2), INITIAL_BALANCE),
+			BalancesError::<Test>::KeepAlive
+		);
+	});
+}
+
+#[test]
+fn contributions_that_overflow_are_rejected() {
+	new_test_ext().execute_with(|| {
+		provider(1, 100);
+		PoolBalance::<Test>::put(u64::MAX);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 1),
+			Error::<Test>::StorageOverflow
+		);
+
+		PoolBalance::<Test>::put(100);
+		TotalShares::<Test>::put(u128::MAX);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(1), 1),
+			Error::<Test>::StorageOverflow
+		);
+		assert_eq!(Balances::free_balance(MpesaUser::account_id()), 100);
+	});
+}
+
+#[test]
+fn phones_become_providers_with_their_first_gateway_contribution() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			<MpesaUser as LiquidityPool<u64, u64>>::contribute(&3, 100),
+			Error::<Test>::NotAttested
+		);
+
+		attest(3, 1);
+		assert_ok!(<MpesaUser as LiquidityPool<u64, u64>>::contribute(&3, 100));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::NewLiquidityProvider(3)));
+		assert_ok!(<MpesaUser as LiquidityPool<u64, u64>>::contribute(&3, 50));
+		assert_eq!(MpesaUser::liquidity_provider(3).map(|info| info.contributed), Some(150));
+		assert_eq!(MpesaUser::shares(3), 150);
+	});
+}
+
+#[test]
+fn leaving_the_pool_pays_out_rewards_once_no_shares_are_in_escrow() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		seeded_pool();
+		buy_tokens(2, 500);
+		provider(2, 0);
+		assert_ok!(MpesaUser::add_liquidity(Origin::signed(2), 50_000, 50_000));
+		assert_ok!(MpesaUser::swap_exact_in(Origin::signed(3), Asset::Native, 15_000, 0));
+		assert_eq!(MpesaUser::rewards(&2, Asset::Native), 40);
+
+		// shares in escrow may still come back to 2
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(2), 1, MpesaUser::shares(2)));
+		assert_noop!(
+			MpesaUser::delete_LProviderIdentity(Origin::signed(2)),
+			Error::<Test>::SharesInEscrow
+		);
+
+		assert_ok!(MpesaUser::accept_transfer(Origin::signed(1), 0));
+		let balance = Balances::free_balance(2);
+		assert_ok!(MpesaUser::delete_LProviderIdentity(Origin::signed(2)));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::RewardsClaimed(2, 40, 0)));
+		assert_eq!(Balances::free_balance(2), balance + 40);
+		assert_eq!(MpesaUser::liquidity_provider(2), None);
+		for asset in [Asset::Native, Asset::Token] {
+			assert_eq!(MpesaUser::owed_rewards(2, asset), 0);
+			assert_eq!(MpesaUser::reward_debt(2, asset), 0);
+		}
+	});
+}
+
+#[test]
+fn accepted_transfers_move_shares_between_providers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 100);
+		assert_noop!(
+			MpesaUser::initiate_transfer(Origin::signed(1), 2, 10),
+			Error::<Test>::LProviderIdentityDoesNotExist
+		);
+		provider(2, 0);
+		assert_noop!(
+			MpesaUser::initiate_transfer(Origin::signed(1), 2, 101),
+			Error::<Test>::InsufficientShares
+		);
+
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 40));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferInitiated(0, 1, 2, 40)));
+		// the locked shares can't be sent or redeemed twice
+		assert_noop!(
+			MpesaUser::initiate_transfer(Origin::signed(1), 2, 61),
+			Error::<Test>::InsufficientShares
+		);
+		assert_noop!(
+			MpesaUser::withdraw_LPShares(Origin::signed(1), 61),
+			Error::<Test>::InsufficientShares
+		);
+		assert_noop!(
+			MpesaUser::accept_transfer(Origin::signed(1), 0),
+			Error::<Test>::NotEscrowParty
+		);
+		assert_noop!(
+			MpesaUser::cancel_transfer(Origin::signed(1), 0),
+			Error::<Test>::EscrowNotExpired
+		);
+
+		assert_ok!(MpesaUser::accept_transfer(Origin::signed(2), 0));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferAccepted(0)));
+		assert_eq!((MpesaUser::shares(1), MpesaUser::shares(2)), (60, 40));
+		assert_eq!(MpesaUser::locked_shares(1), 0);
+		assert_eq!(MpesaUser::escrow(0), None);
+		assert_eq!(MpesaUser::pool_balance(), 100);
+		assert_noop!(
+			MpesaUser::accept_transfer(Origin::signed(2), 0),
+			Error::<Test>::UnknownEscrow
+		);
+	});
+}
+
+#[test]
+fn history_keeps_the_last_transactions_of_each_account() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 1_000);
+		provider(1, 100);
+		provider(2, 0);
+		System::set_block_number(2);
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 40));
+		assert_ok!(MpesaUser::accept_transfer(Origin::signed(2), 0));
+		let record = |kind, amount, counterparty, block| TransactionRecord {
+			kind,
+			amount,
+			counterparty,
+			block,
+			receipt: None,
+		};
+		assert_eq!(
+			MpesaUser::history(1).into_inner(),
+			vec![
+				record(TransactionKind::Deposit, 100_000, None, 1),
+				record(TransactionKind::Contribution, 100, None, 1),
+				record(TransactionKind::TransferOut, 40, Some(2), 2),
+			]
+		);
+		assert_eq!(
+			MpesaUser::history(2).into_inner(),
+			vec![record(TransactionKind::TransferIn, 40, Some(1), 2)]
+		);
+
+		// the oldest transaction makes room for the newest
+		System::set_block_number(3);
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(1), 10));
+		assert_eq!(
+			MpesaUser::history(1).into_inner(),
+			vec![
+				record(TransactionKind::Contribution, 100, None, 1),
+				record(TransactionKind::TransferOut, 40, Some(2), 2),
+				record(TransactionKind::Redemption, 10, None, 3),
+			]
+		);
+		// and is kept until a full batch of dropped transactions is archived
+		let deposit = record(TransactionKind::Deposit, 100_000, None, 1);
+		assert_eq!(MpesaUser::pruned_history(1).into_inner(), vec![deposit.clone()]);
+		assert_eq!(MpesaUser::history_archive_count(1), 0);
+		System::set_block_number(4);
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(1), 10));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::HistoryArchived(
+			1,
+			0,
+			b"cid1".to_vec(),
+		)));
+		let archived = vec![deposit, record(TransactionKind::Contribution, 100, None, 1)];
+		IPFS.with(|ipfs| {
+			assert_eq!(*ipfs.borrow(), vec![(MpesaUser::receipts_account(), archived.encode())])
+		});
+		assert_eq!(
+			MpesaUser::history_archive(1, 0).map(|cid| cid.into_inner()),
+			Some(b"cid1".to_vec())
+		);
+		assert_eq!(MpesaUser::history_archive_count(1), 1);
+		assert!(MpesaUser::pruned_history(1).is_empty());
+		assert_ok!(MpesaUser::do_try_state());
+	});
+}
+
+// the items of every page of a view, requested `limit` at a time
+fn all_pages<Item>(limit: u32, mut page: impl FnMut(&PageRequest) -> Page<Item>) -> Vec<Item> {
+	let mut request = PageRequest { cursor: None, limit };
+	let mut items = Vec::new();
+	loop {
+		let page = page(&request);
+		items.extend(page.items);
+		match page.next {
+			Some(next) => request.cursor = Some(next),
+			None => return items,
+		}
+	}
+}
+
+#[test]
+fn indexers_page_through_settlements_and_positions() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 1_000);
+		provider(1, 100);
+		System::set_block_number(2);
+		buy_tokens(2, 500);
+		provider(2, 0);
+		System::set_block_number(3);
+		buy_tokens(1, 200);
+
+		let settlement = |who, amount, block| Settlement {
+			who,
+			kind: TransactionKind::Deposit,
+			amount,
+			block,
+			receipt: None,
+		};
+		let mut settlements =
+			all_pages(1, |request| MpesaUser::settlements_page(&IndexFilter::default(), request));
+		settlements.sort_by_key(|settlement| (settlement.who, settlement.block));
+		assert_eq!(
+			settlements,
+			vec![settlement(1, 100_000, 1), settlement(1, 20_000, 3), settlement(2, 50_000, 2)]
+		);
+
+		let filter = IndexFilter { account: Some(1), from: Some(2), ..Default::default() };
+		assert_eq!(
+			all_pages(10, |request| MpesaUser::settlements_page(&filter, request)),
+			vec![settlement(1, 20_000, 3)]
+		);
+		let filter = IndexFilter { to: Some(2), ..Default::default() };
+		assert_eq!(all_pages(10, |request| MpesaUser::settlements_page(&filter, request)).len(), 2);
+		let filter = IndexFilter { cid: Some(b"cid1".to_vec()), ..Default::default() };
+		assert!(all_pages(10, |request| MpesaUser::settlements_page(&filter, request)).is_empty());
+
+		let mut positions =
+			all_pages(1, |request| MpesaUser::positions_page(&IndexFilter::default(), request));
+		positions.sort_by_key(|position| position.who);
+		assert_eq!(
+			positions,
+			vec![
+				PoolPosition {
+					who: 1,
+					joined_at: 1,
+					contributed: 100,
+					shares: MpesaUser::shares(1),
+					tokens: MpesaUser::tokens(1),
+				},
+				PoolPosition {
+					who: 2,
+					joined_at: 2,
+					contributed: 0,
+					shares: 0,
+					tokens: MpesaUser::tokens(2),
+				},
+			]
+		);
+		let filter = IndexFilter { account: Some(2), ..Default::default() };
+		assert_eq!(
+			all_pages(10, |request| MpesaUser::positions_page(&filter, request)),
+			positions[1..].to_vec()
+		);
+		// positions refer to no `Cid`
+		let filter = IndexFilter { cid: Some(b"cid1".to_vec()), ..Default::default() };
+		assert!(all_pages(10, |request| MpesaUser::positions_page(&filter, request)).is_empty());
+	});
+}
+
+#[test]
+fn unaccepted_transfers_are_refunded() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 100);
+		provider(2, 0);
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 10));
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 20));
+		assert_noop!(
+			MpesaUser::initiate_transfer(Origin::signed(1), 2, 30),
+			Error::<Test>::TooManyEscrows
+		);
+		assert_eq!(MpesaUser::locked_shares(1), 30);
+
+		System::set_block_number(11);
+		assert_noop!(
+			MpesaUser::accept_transfer(Origin::signed(2), 0),
+			Error::<Test>::EscrowExpired
+		);
+		assert_noop!(
+			MpesaUser::cancel_transfer(Origin::signed(2), 0),
+			Error::<Test>::NotEscrowParty
+		);
+		assert_ok!(MpesaUser::cancel_transfer(Origin::signed(1), 0));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferCancelled(0)));
+		assert_eq!(MpesaUser::locked_shares(1), 20);
+
+		// idle blocks clean up the rest, as far as their weight allows
+		assert_eq!(MpesaUser::on_idle(11, 0), 0);
+		assert!(MpesaUser::escrow(1).is_some());
+		MpesaUser::on_idle(11, Weight::max_value());
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferCancelled(1)));
+		assert_eq!(MpesaUser::escrow(1), None);
+		assert_eq!(MpesaUser::locked_shares(1), 0);
+		assert_eq!(MpesaUser::escrow_expiry_cursor(), 2);
+		assert_eq!(MpesaUser::shares(1), 100);
+	});
+}
+
+#[test]
+fn disputed_transfers_are_resolved_by_the_dispute_origin() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 100);
+		provider(2, 0);
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 10));
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(1), 2, 20));
+
+		assert_noop!(
+			MpesaUser::resolve_transfer(Origin::signed(2), 0, true),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(MpesaUser::resolve_transfer(Origin::signed(Arbiter::get()), 0, true));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferResolved(0, true)));
+		assert_ok!(MpesaUser::resolve_transfer(Origin::signed(Arbiter::get()), 1, false));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::TransferResolved(1, false)));
+		assert_eq!((MpesaUser::shares(1), MpesaUser::shares(2)), (90, 10));
+		assert_eq!(MpesaUser::locked_shares(1), 0);
+	});
+}
+
+#[test]
+fn withdrawals_are_limited_to_the_shares_held() {
+	new_test_ext().execute_with(|| {
+		provider(1, 100);
+		provider(2, 50);
+		assert_noop!(
+			MpesaUser::withdraw_LPShares(Origin::signed(2), 51),
+			Error::<Test>::InsufficientShares
+		);
+		assert_noop!(
+			MpesaUser::delete_LProviderIdentity(Origin::signed(2)),
+			Error::<Test>::LProviderHasShares
+		);
+
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(2), 50));
+		assert_eq!(MpesaUser::pool_balance(), 100);
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE);
+		assert_ok!(MpesaUser::delete_LProviderIdentity(Origin::signed(2)));
+		assert_eq!(MpesaUser::liquidity_provider(2), None);
+	});
+}
+
+#[test]
+fn withdrawals_pay_out_the_part_of_the_pool_the_shares_are_worth() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 100);
+		provider(2, 300);
+
+		// the pool grows without issuing shares
+		let _ = Balances::deposit_creating(&MpesaUser::account_id(), 400);
+		PoolBalance::<Test>::put(800);
+
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(1), 100));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::WithdrewLPShares(100, 200, 1)));
+		assert_eq!(Balances::free_balance(1), INITIAL_BALANCE + 100);
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(2), 300));
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE + 300);
+		assert_eq!((MpesaUser::pool_balance(), MpesaUser::total_shares()), (0, 0));
+	});
+}
+
+// let the pool grow by `amount` without issuing shares, the way fees will
+fn grow_pool(amount: u64) {
+	let _ = Balances::deposit_creating(&MpesaUser::account_id(), amount);
+	PoolBalance::<Test>::mutate(|balance| *balance += amount);
+}
+
+#[test]
+fn later_contributions_mint_shares_at_the_pool_price() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		provider(1, 100);
+		grow_pool(100);
+
+		provider(2, 100);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::ContributedLPShares(100, 50, 2)));
+		assert_eq!(MpesaUser::total_shares(), 150);
+		assert_eq!(MpesaUser::pool_share(&2), Perbill::from_rational(1u32, 3u32));
+		assert_eq!(MpesaUser::redemption_value(50), Ok(100));
+
+		// contributions worth less than a share mint nothing
+		PoolBalance::<Test>::put(1_000_000);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(2), 1),
+			Error::<Test>::MinimumLPShareAmountNotMet
+		);
+	});
+}
+
+#[test]
+fn share_math_does_not_overflow() {
+	new_test_ext().execute_with(|| {
+		TotalShares::<Test>::put(u128::MAX / 2);
+		PoolBalance::<Test>::put(u64::MAX / 2);
+		assert_eq!(MpesaUser::shares_for(u64::MAX / 2), Some(u128::MAX / 2));
+		assert_eq!(MpesaUser::redemption_value(u128::MAX / 4), Ok(u64::MAX / 4));
+	});
+}
+
+#[test]
+fn share_invariants_hold_over_random_operations() {
+	new_test_ext().execute_with(|| {
+		for who in 1..=3 {
+			provider(who, 0);
+		}
+
+		// a linear congruential generator, so that failures are reproducible
+		let mut seed = 0x2545_f491_4f6c_dd1du64;
+		let mut next = |bound: u64| {
+			seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+			(seed >> 33) % bound
+		};
+
+		for _ in 0..500 {
+			let who = next(3) + 1;
+			match next(4) {
+				0 | 1 => {
+					let amount = next(10_000) + 1;
+					let before = MpesaUser::shares(who);
+					if MpesaUser::contribute_LPShares(Origin::signed(who), amount).is_ok() {
+						// contributors never get shares worth more than they paid
+						let minted = MpesaUser::shares(who) - before;
+						assert!(MpesaUser::redemption_value(minted).unwrap() <= amount);
+					}
+				},
+				2 => {
+					let shares = next(MpesaUser::shares(who) as u64 + 1) as u128;
+					let value = MpesaUser::redemption_value(shares).unwrap();
+					let balance = Balances::free_balance(who);
+					if MpesaUser::withdraw_LPShares(Origin::signed(who), shares).is_ok() {
+						assert_eq!(Balances::free_balance(who), balance + value);
+					}
+				},
+				// fees only accrue while there are shares to accrue to
+				_ if MpesaUser::total_shares() > 0 => grow_pool(next(1_000)),
+				_ => {},
+			}
+
+			let shares: u128 = (1..=3).map(MpesaUser::shares).sum();
+			assert_eq!(shares, MpesaUser::total_shares());
+			assert_eq!(Balances::free_balance(MpesaUser::account_id()), MpesaUser::pool_balance());
+			// rounding in the pool's favour never leaves shares backed by nothing
+			assert!(MpesaUser::redemption_value(shares).unwrap() <= MpesaUser::pool_balance());
+			if shares == 0 {
+				assert_eq!(MpesaUser::pool_share(&who), Perbill::zero());
+			}
+		}
+	});
+}
+
+const PHONE: &[u8] = b"254708374149";
+
+// store the Daraja sandbox credentials and `PHONE` in the node's off-chain storage
+fn configure_daraja() {
+	let config = DarajaConfig {
+		base_url: b"https://sandbox.safaricom.co.ke".to_vec(),
+		consumer_key: b"key".to_vec(),
+		consumer_secret: b"secret".to_vec(),
+		shortcode: b"174379".to_vec(),
+		passkey: b"passkey".to_vec(),
+		callback_url: b"https://pool.example/mpesa/callback".to_vec(),
+	};
+	StorageValueRef::persistent(daraja::CONFIG_KEY).set(&config);
+	let mut key = daraja::PHONE_PREFIX.to_vec();
+	key.extend(sp_io::hashing::blake2_256(PHONE));
+	StorageValueRef::persistent(&key).set(&PHONE.to_vec());
+}
+
+#[test]
+fn deposits_are_queued_for_an_stk_push() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_noop!(
+			MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500),
+			Error::<Test>::NoWorkers
+		);
+		assert_noop!(
+			MpesaUser::set_workers(Origin::signed(1), vec![UintAuthorityId(WORKER)]),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MpesaUser::set_workers(Origin::root(), (1..=3).map(UintAuthorityId).collect()),
+			Error::<Test>::TooManyWorkers
+		);
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+
+		assert_noop!(
+			MpesaUser::request_deposit(Origin::signed(1), phone_hash, 0),
+			Error::<Test>::InvalidDepositAmount
+		);
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::DepositRequested(0, 1, 500)));
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(2), phone_hash, 20));
+		assert_noop!(
+			MpesaUser::request_deposit(Origin::signed(3), phone_hash, 20),
+			Error::<Test>::TooManyPendingDeposits
+		);
+
+		assert_eq!(MpesaUser::pending_stk_pushes().into_inner(), vec![0, 1]);
+		let deposit = MpesaUser::deposit(0).unwrap();
+		assert_eq!((deposit.who, deposit.amount, deposit.requested_at), (1, 500, 1));
+		assert_eq!(deposit.checkout_request_id, None);
+	});
+}
+
+#[test]
+fn offchain_worker_initiates_stk_pushes_once() {
+	let (mut ext, offchain_state, pool_state) = new_offchain_ext();
+	offchain_state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: "https://sandbox.safaricom.co.ke/oauth/v1/generate?grant_type=client_credentials"
+			.into(),
+		headers: vec![("Authorization".into(), "Basic a2V5OnNlY3JldA==".into())],
+		response: Some(br#"{"access_token":"tok","expires_in":"3599"}"#.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+	offchain_state.write().expect_request(PendingRequest {
+		method: "POST".into(),
+		uri: "https://sandbox.safaricom.co.ke/mpesa/stkpush/v1/processrequest".into(),
+		headers: vec![
+			("Authorization".into(), "Bearer tok".into()),
+			("Content-Type".into(), "application/json".into()),
+		],
+		body: br#"{"BusinessShortCode":"174379","Password":"MTc0Mzc5cGFzc2tleTE5NzAwMTAxMDMwMDAw","Timestamp":"19700101030000","TransactionType":"CustomerPayBillOnline","Amount":"500","PartyA":"254708374149","PartyB":"174379","PhoneNumber":"254708374149","CallBackURL":"https://pool.example/mpesa/callback","AccountReference":"LP0","TransactionDesc":"Liquidity pool deposit"}"#.to_vec(),
+		response: Some(
+			br#"{"MerchantRequestID":"29115-34620561-1","CheckoutRequestID":"ws_CO_191220191020363925","ResponseCode": "0","ResponseDescription":"Success. Request accepted for processing"}"#
+				.to_vec(),
+		),
+		sent: true,
+		..Default::default()
+	});
+
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		configure_daraja();
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+
+		MpesaUser::offchain_worker(1);
+		// the deposit is claimed, so a run before the checkout request lands doesn't prompt again
+		MpesaUser::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		assert_eq!(tx.signature, None);
+		let call = match tx.call {
+			Call::MpesaUser(call) => call,
+			call => panic!("unexpected call {:?}", call),
+		};
+		let (payload, signature) = match call.clone() {
+			crate::Call::submit_checkout_request { payload, signature } => (payload, signature),
+			call => panic!("unexpected call {:?}", call),
+		};
+		assert_eq!(payload.deposit, 0);
+		assert_eq!(payload.public, UintAuthorityId(WORKER));
+		assert_eq!(&payload.checkout_request_id[..], b"ws_CO_191220191020363925");
+
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::Local, &call));
+		assert_ok!(MpesaUser::submit_checkout_request(Origin::none(), payload, signature));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::DepositInitiated(
+			0,
+			b"ws_CO_191220191020363925".to_vec(),
+		)));
+		assert!(MpesaUser::pending_stk_pushes().is_empty());
+		let checkout_request_id = MpesaUser::deposit(0).unwrap().checkout_request_id.unwrap();
+		assert_eq!(MpesaUser::checkout_request(checkout_request_id), Some(0));
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+// the STK pushes waiting in the node's off-chain storage to be retried
+fn stk_push_retries() -> Vec<Retry<(DepositId, DepositRequest<u64, u64>), DarajaError, u64>> {
+	StorageValueRef::persistent(crate::STK_PUSH_RETRIES)
+		.get()
+		.unwrap()
+		.unwrap_or_default()
+}
 
 #[test]
-fn it_works_for_default_value() {
+fn offchain_worker_retries_failed_stk_pushes_then_gives_up() {
+	let (mut ext, _, pool_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		configure_daraja();
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+		// the node doesn't know the phone, so no request reaches Daraja
+		let phone_hash = sp_io::hashing::blake2_256(b"254700000000");
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+
+		MpesaUser::offchain_worker(1);
+		let retries = stk_push_retries();
+		assert_eq!(retries.len(), 1);
+		assert_eq!((retries[0].job.0, retries[0].attempts, retries[0].retry_at), (0, 1, 3));
+		assert_eq!(retries[0].error, DarajaError::UnknownPhone);
+
+		// nothing is retried before the backoff elapses, then the wait doubles
+		MpesaUser::offchain_worker(2);
+		assert_eq!(stk_push_retries()[0].attempts, 1);
+		MpesaUser::offchain_worker(3);
+		assert_eq!((stk_push_retries()[0].attempts, stk_push_retries()[0].retry_at), (2, 7));
+		MpesaUser::offchain_worker(7);
+		assert_eq!(stk_push_retries()[0].retry_at, 15);
+
+		// out of retries, the push is given up on and the deposit left to expire on-chain
+		MpesaUser::offchain_worker(15);
+		assert!(stk_push_retries().is_empty());
+		MpesaUser::offchain_worker(16);
+		assert!(stk_push_retries().is_empty());
+		assert!(pool_state.read().transactions.is_empty());
+		assert_eq!(MpesaUser::pending_stk_pushes().into_inner(), vec![0]);
+	});
+}
+
+#[test]
+fn offchain_workers_without_a_worker_key_stay_idle() {
+	let (mut ext, _, pool_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		configure_daraja();
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER + 1)]));
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+
+		// no request is sent to Daraja, which the test offchain externalities would reject
+		MpesaUser::offchain_worker(1);
+		MpesaUser::offchain_worker(2);
+		assert!(pool_state.read().transactions.is_empty());
+		let missing = StorageValueRef::persistent(crate::SIGNING_KEY_MISSING).get::<bool>();
+		assert_eq!(missing, Ok(Some(true)));
+	});
+}
+
+#[test]
+fn checkout_requests_must_come_from_the_deposits_worker() {
+	let (mut ext, _, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		let workers = vec![UintAuthorityId(WORKER), UintAuthorityId(WORKER + 1)];
+		assert_ok!(MpesaUser::set_workers(Origin::root(), workers));
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(1), phone_hash, 500));
+
+		let checkout = |deposit| {
+			let payload = CheckoutPayload {
+				public: UintAuthorityId(WORKER),
+				block_number: 1,
+				deposit,
+				checkout_request_id: b"ws_CO_1".to_vec().try_into().unwrap(),
+			};
+			let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+			crate::Call::<Test>::submit_checkout_request { payload, signature }
+		};
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::External, &checkout(0)));
+		// deposit 1 is assigned to the other worker
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &checkout(1)),
+			InvalidTransaction::BadSigner.into()
+		);
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &checkout(2)),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+#[test]
+fn daraja_encodings_match_the_api() {
+	assert_eq!(daraja::base64(b""), b"");
+	assert_eq!(daraja::base64(b"a"), b"YQ==");
+	assert_eq!(daraja::base64(b"ab"), b"YWI=");
+	assert_eq!(daraja::base64(b"abc"), b"YWJj");
+	assert_eq!(daraja::base64(b"key:secret"), b"a2V5OnNlY3JldA==");
+
+	assert_eq!(daraja::timestamp(0), b"19700101030000");
+	// 2024-02-29 23:59:59 UTC, a leap day, is already March in Nairobi
+	assert_eq!(daraja::timestamp(1_709_251_199_000), b"20240301025959");
+
+	let json = br#"{ "ResponseCode" : "0", "CheckoutRequestID":"ws_CO_1" }"#;
+	assert_eq!(daraja::json_string(json, b"ResponseCode"), Some(b"0".to_vec()));
+	assert_eq!(daraja::json_string(json, b"CheckoutRequestID"), Some(b"ws_CO_1".to_vec()));
+	assert_eq!(daraja::json_string(json, b"MerchantRequestID"), None);
+}
+
+// request a deposit of `amount` shillings for `who` and record its STK push as
+// `checkout_request_id`
+fn initiated_deposit(who: u64, amount: u32, checkout_request_id: &[u8]) -> DepositId {
+	if MpesaUser::workers().is_empty() {
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+	}
+	let deposit = MpesaUser::next_deposit_id();
+	let phone_hash = sp_io::hashing::blake2_256(PHONE);
+	assert_ok!(MpesaUser::request_deposit(Origin::signed(who), phone_hash, amount));
+	let payload = CheckoutPayload {
+		public: UintAuthorityId(WORKER),
+		block_number: System::block_number(),
+		deposit,
+		checkout_request_id: checkout_request_id.to_vec().try_into().unwrap(),
+	};
+	let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+	assert_ok!(MpesaUser::submit_checkout_request(Origin::none(), payload, signature));
+	deposit
+}
+
+// pay `amount` shillings in for `who`, minting their pool tokens
+fn buy_tokens(who: u64, amount: u32) {
+	let id = format!("ws_CO_{}", MpesaUser::next_deposit_id()).into_bytes();
+	initiated_deposit(who, amount, &id);
+	let receipt = format!("R{}", MpesaUser::next_deposit_id()).into_bytes();
+	assert_ok!(MpesaUser::confirm_payment(
+		Origin::signed(Oracle::get()),
+		id.try_into().unwrap(),
+		receipt.try_into().unwrap(),
+		amount
+	));
+}
+
+#[test]
+fn payments_are_confirmed_once() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let deposit = initiated_deposit(1, 500, b"ws_CO_1");
+		let checkout = || b"ws_CO_1".to_vec().try_into().unwrap();
+		let receipt = |receipt: &[u8]| receipt.to_vec().try_into().unwrap();
+
+		assert_noop!(
+			MpesaUser::confirm_payment(Origin::signed(1), checkout(), receipt(b"NLJ7RT61SV"), 500),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MpesaUser::confirm_payment(
+				Origin::signed(Oracle::get()),
+				checkout(),
+				receipt(b"NLJ7RT61SV"),
+				50
+			),
+			Error::<Test>::AmountMismatch
+		);
+		let unknown = b"ws_CO_2".to_vec().try_into().unwrap();
+		assert_noop!(
+			MpesaUser::confirm_payment(
+				Origin::signed(Oracle::get()),
+				unknown,
+				receipt(b"NLJ7RT61SV"),
+				500
+			),
+			Error::<Test>::UnknownCheckoutRequest
+		);
+
+		assert_ok!(MpesaUser::confirm_payment(
+			Origin::signed(Oracle::get()),
+			checkout(),
+			receipt(b"NLJ7RT61SV"),
+			500
+		));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::DepositConfirmed(
+			deposit,
+			1,
+			50_000,
+			b"NLJ7RT61SV".to_vec(),
+		)));
+		assert_eq!((MpesaUser::tokens(1), MpesaUser::token_issuance()), (50_000, 50_000));
+		assert_eq!(MpesaUser::deposit(deposit), None);
+		assert_eq!(MpesaUser::receipt(receipt(b"NLJ7RT61SV")), Some(deposit));
+
+		assert_noop!(
+			MpesaUser::confirm_payment(
+				Origin::signed(Oracle::get()),
+				checkout(),
+				receipt(b"NLJ7RT61SV"),
+				500
+			),
+			Error::<Test>::ReceiptAlreadyUsed
+		);
+		assert_noop!(
+			MpesaUser::confirm_payment(
+				Origin::signed(Oracle::get()),
+				checkout(),
+				receipt(b"NLJ7RT62SV"),
+				500
+			),
+			Error::<Test>::UnknownCheckoutRequest
+		);
+	});
+}
+
+#[test]
+fn unconfirmed_deposits_expire_in_order() {
 	new_test_ext().execute_with(|| {
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
+		System::set_block_number(1);
+		let first = initiated_deposit(1, 500, b"ws_CO_1");
+		let confirmed = initiated_deposit(1, 20, b"ws_CO_2");
+		assert_ok!(MpesaUser::confirm_payment(
+			Origin::signed(Oracle::get()),
+			b"ws_CO_2".to_vec().try_into().unwrap(),
+			b"NLJ7RT61SV".to_vec().try_into().unwrap(),
+			20
+		));
+		System::set_block_number(2);
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_ok!(MpesaUser::request_deposit(Origin::signed(2), phone_hash, 100));
+		let last = confirmed + 1;
+
+		MpesaUser::on_initialize(10);
+		assert_eq!(MpesaUser::expiry_cursor(), first);
+
+		System::set_block_number(11);
+		MpesaUser::on_initialize(11);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::DepositExpired(first, 1)));
+		assert_eq!(MpesaUser::deposit(first), None);
+		assert_eq!(MpesaUser::checkout_request(b"ws_CO_1".to_vec().try_into().unwrap()), None);
+		assert_eq!(MpesaUser::expiry_cursor(), last);
+		assert_eq!(MpesaUser::pending_stk_pushes().into_inner(), vec![last]);
+
+		MpesaUser::on_initialize(12);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::DepositExpired(last, 2)));
+		assert!(MpesaUser::pending_stk_pushes().is_empty());
+		assert_eq!(MpesaUser::expiry_cursor(), last + 1);
+		assert_eq!(MpesaUser::tokens(2), 0);
+	});
+}
+
+#[test]
+fn offchain_workers_publish_receipts_of_confirmed_payments() {
+	let (mut ext, _, pool_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 500);
+		assert_eq!(MpesaUser::pending_receipt(0).map(|receipt| receipt.tokens), Some(50_000));
+
+		MpesaUser::offchain_worker(1);
+		// the receipt is claimed, so a later run doesn't submit it again
+		MpesaUser::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let call = match Extrinsic::decode(&mut &*tx).unwrap().call {
+			Call::MpesaUser(call) => call,
+			call => panic!("unexpected call {:?}", call),
+		};
+		let (payload, signature) = match call.clone() {
+			crate::Call::submit_receipt { payload, signature } => (payload, signature),
+			call => panic!("unexpected call {:?}", call),
+		};
+		let document = br#"{"kind":"deposit","id":"0","account":"0x0100000000000000","tokens":"50000","mpesaReceipt":"R1","block":"1"}"#;
+		assert_eq!(payload.document, document.to_vec());
+
+		// the document has to match the chain's record of the payment
+		let mut forged = payload.clone();
+		forged.document.push(b' ');
+		assert_noop!(
+			MpesaUser::submit_receipt(Origin::none(), forged, signature.clone()),
+			Error::<Test>::ReceiptMismatch
+		);
+
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::Local, &call));
+		assert_ok!(MpesaUser::submit_receipt(Origin::none(), payload, signature));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::ReceiptPublished(
+			0,
+			1,
+			b"cid1".to_vec(),
+		)));
+		IPFS.with(|ipfs| {
+			assert_eq!(*ipfs.borrow(), vec![(MpesaUser::receipts_account(), document.to_vec())])
+		});
+		let receipt = MpesaUser::history(1)[0].receipt.clone().map(|cid| cid.into_inner());
+		assert_eq!(receipt, Some(b"cid1".to_vec()));
+		assert_eq!(MpesaUser::pending_receipt(0), None);
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
+fn receipts_are_submitted_again_once_their_claim_expires() {
+	let (mut ext, _, pool_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 500);
+		let submitted_receipts = || {
+			pool_state
+				.read()
+				.transactions
+				.iter()
+				.filter(|tx| {
+					matches!(
+						Extrinsic::decode(&mut &***tx).unwrap().call,
+						Call::MpesaUser(crate::Call::submit_receipt { .. })
+					)
+				})
+				.count()
+		};
+
+		MpesaUser::offchain_worker(1);
+		assert_eq!(submitted_receipts(), 1);
+		// the submission never made it into a block, e.g. because it went stale in the pool
+		pool_state.write().transactions.clear();
+		MpesaUser::offchain_worker(5);
+		assert_eq!(submitted_receipts(), 0);
+		MpesaUser::offchain_worker(6);
+		assert_eq!(submitted_receipts(), 1);
+	});
+}
+
+#[test]
+fn offchain_workers_repin_receipts_that_lost_their_pin() {
+	let (mut ext, _, pool_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 500);
+		MpesaUser::offchain_worker(1);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		match Extrinsic::decode(&mut &*tx).unwrap().call {
+			Call::MpesaUser(crate::Call::submit_receipt { payload, signature }) => {
+				assert_ok!(MpesaUser::submit_receipt(Origin::none(), payload, signature))
+			},
+			call => panic!("unexpected call {:?}", call),
+		}
+		assert_ok!(MpesaUser::do_try_state());
+
+		// the store has the grace period to pin the receipt
+		UNPINNED.with(|unpinned| unpinned.borrow_mut().push(b"cid1".to_vec()));
+		System::set_block_number(5);
+		assert_ok!(MpesaUser::do_try_state());
+		MpesaUser::offchain_worker(5);
+		assert!(pool_state.read().transactions.is_empty());
+
+		System::set_block_number(10);
+		assert_eq!(MpesaUser::do_try_state(), Err("a settlement's receipt has no active pin"));
+		MpesaUser::offchain_worker(10);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let call = match Extrinsic::decode(&mut &*tx).unwrap().call {
+			Call::MpesaUser(call) => call,
+			call => panic!("unexpected call {:?}", call),
+		};
+		let (payload, signature) = match call.clone() {
+			crate::Call::repin_receipts { payload, signature } => (payload, signature),
+			call => panic!("unexpected call {:?}", call),
+		};
+		assert_eq!(payload.receipts, vec![b"cid1".to_vec().try_into().unwrap()]);
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::Local, &call));
+		assert_ok!(MpesaUser::repin_receipts(Origin::none(), payload, signature));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::ReceiptRepinned(b"cid1".to_vec())));
+		assert_ok!(MpesaUser::do_try_state());
+		// nothing is left to re-pin
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+	});
+}
+
+// lock `who`'s pool tokens for a withdrawal of `amount` shillings and record its B2C payment as
+// `conversation_id`
+fn initiated_withdrawal(who: u64, amount: u32, conversation_id: &[u8]) -> WithdrawalId {
+	if MpesaUser::workers().is_empty() {
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+	}
+	let withdrawal = MpesaUser::next_withdrawal_id();
+	let phone_hash = sp_io::hashing::blake2_256(PHONE);
+	attest(who, 2);
+	assert_ok!(MpesaUser::request_withdrawal(Origin::signed(who), phone_hash, amount));
+	let payload = ConversationPayload {
+		public: UintAuthorityId(WORKER),
+		block_number: System::block_number(),
+		withdrawal,
+		conversation_id: conversation_id.to_vec().try_into().unwrap(),
+	};
+	let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+	assert_ok!(MpesaUser::submit_conversation(Origin::none(), payload, signature));
+	withdrawal
+}
+
+#[test]
+fn withdrawals_lock_tokens_within_the_daily_limit() {
 	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
-		assert_noop!(TemplateModule::cause_error(Origin::signed(1)), Error::<Test>::NoneValue);
+		System::set_block_number(1);
+		buy_tokens(1, 2_500);
+		buy_tokens(2, 10);
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		attest(1, 2);
+		attest(2, 1);
+
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 0),
+			Error::<Test>::InvalidWithdrawalAmount
+		);
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(2), phone_hash, 5),
+			Error::<Test>::VerificationLevelTooLow
+		);
+		attest(2, 2);
+		// 20 shillings take 2_000 tokens, more than 2 holds
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(2), phone_hash, 20),
+			Error::<Test>::InsufficientTokens
+		);
+
+		assert_ok!(MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 600));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::WithdrawalRequested(0, 1, 600)));
+		// the 1% fee is locked on top of the 60_000 tokens paid out
+		assert_eq!(MpesaUser::tokens(1), 189_400);
+		assert_eq!(MpesaUser::token_issuance(), 251_000);
+		assert_eq!(MpesaUser::pending_b2c_payments().into_inner(), vec![0]);
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 401),
+			Error::<Test>::DailyLimitExceeded
+		);
+		assert_ok!(MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 400));
+
+		// the limit resets the next day
+		System::set_block_number(100);
+		assert_noop!(
+			MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 1),
+			Error::<Test>::TooManyPendingWithdrawals
+		);
+		assert_eq!(MpesaUser::daily_withdrawals(1), (0, 1_000));
+		let conversation_id = b"AG_1".to_vec().try_into().unwrap();
+		let payload = ConversationPayload {
+			public: UintAuthorityId(WORKER),
+			block_number: 100,
+			withdrawal: 0,
+			conversation_id,
+		};
+		let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+		assert_ok!(MpesaUser::submit_conversation(Origin::none(), payload, signature));
+		assert_ok!(MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 1_000));
+		assert_eq!(MpesaUser::daily_withdrawals(1), (1, 1_000));
+	});
+}
+
+#[test]
+fn confirmed_withdrawals_burn_the_locked_tokens() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 2_000);
+		let withdrawal = initiated_withdrawal(1, 600, b"AG_1");
+		let conversation = || b"AG_1".to_vec().try_into().unwrap();
+		let receipt = |receipt: &[u8]| receipt.to_vec().try_into().unwrap();
+		let oracle = || Origin::signed(Oracle::get());
+
+		assert_noop!(
+			MpesaUser::confirm_withdrawal(Origin::signed(1), conversation(), receipt(b"R1"), 600),
+			DispatchError::BadOrigin
+		);
+		assert_noop!(
+			MpesaUser::confirm_withdrawal(oracle(), conversation(), receipt(b"R1"), 60),
+			Error::<Test>::AmountMismatch
+		);
+		let unknown = b"AG_2".to_vec().try_into().unwrap();
+		assert_noop!(
+			MpesaUser::confirm_withdrawal(oracle(), unknown, receipt(b"R1"), 600),
+			Error::<Test>::UnknownConversation
+		);
+
+		assert_ok!(MpesaUser::confirm_withdrawal(oracle(), conversation(), receipt(b"R1"), 600));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::WithdrawalCompleted(
+			withdrawal,
+			1,
+			60_000,
+			b"R1".to_vec(),
+		)));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::FeesAccrued(Asset::Token, 600, 0)));
+		// with no shares to share it, the fee is all the treasury's
+		assert_eq!((MpesaUser::tokens(1), MpesaUser::token_issuance()), (139_400, 140_000));
+		assert_eq!(MpesaUser::rewards(&Treasury::get(), Asset::Token), 600);
+
+		assert_noop!(
+			MpesaUser::confirm_withdrawal(oracle(), conversation(), receipt(b"R1"), 600),
+			Error::<Test>::ReceiptAlreadyUsed
+		);
+		assert_noop!(
+			MpesaUser::confirm_withdrawal(oracle(), conversation(), receipt(b"R2"), 600),
+			Error::<Test>::UnknownConversation
+		);
+	});
+}
+
+#[test]
+fn unconfirmed_withdrawals_are_refunded() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		buy_tokens(1, 2_000);
+		let withdrawal = initiated_withdrawal(1, 600, b"AG_1");
+		assert_eq!(MpesaUser::tokens(1), 139_400);
+
+		MpesaUser::on_initialize(10);
+		assert_eq!(MpesaUser::withdrawal_expiry_cursor(), withdrawal);
+
+		System::set_block_number(11);
+		MpesaUser::on_initialize(11);
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::WithdrawalRefunded(
+			withdrawal, 1, 60_600,
+		)));
+		assert_eq!((MpesaUser::tokens(1), MpesaUser::token_issuance()), (200_000, 200_000));
+		assert_eq!(MpesaUser::daily_withdrawals(1), (0, 0));
+		assert_eq!(MpesaUser::withdrawal(withdrawal), None);
+		assert_eq!(MpesaUser::conversation(b"AG_1".to_vec().try_into().unwrap()), None);
+		assert_eq!(MpesaUser::withdrawal_expiry_cursor(), withdrawal + 1);
+	});
+}
+
+#[test]
+fn offchain_worker_initiates_b2c_payments() {
+	let (mut ext, offchain_state, pool_state) = new_offchain_ext();
+	offchain_state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: "https://sandbox.safaricom.co.ke/oauth/v1/generate?grant_type=client_credentials"
+			.into(),
+		headers: vec![("Authorization".into(), "Basic a2V5OnNlY3JldA==".into())],
+		response: Some(br#"{"access_token":"tok","expires_in":"3599"}"#.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+	offchain_state.write().expect_request(PendingRequest {
+		method: "POST".into(),
+		uri: "https://sandbox.safaricom.co.ke/mpesa/b2c/v1/paymentrequest".into(),
+		headers: vec![
+			("Authorization".into(), "Bearer tok".into()),
+			("Content-Type".into(), "application/json".into()),
+		],
+		body: br#"{"InitiatorName":"testapi","SecurityCredential":"Y3JlZA==","CommandID":"BusinessPayment","Amount":"600","PartyA":"600998","PartyB":"254708374149","Remarks":"Liquidity pool withdrawal","QueueTimeOutURL":"https://pool.example/mpesa/b2c/timeout","ResultURL":"https://pool.example/mpesa/b2c/result","Occasion":"LP0"}"#.to_vec(),
+		response: Some(
+			br#"{"ConversationID":"AG_20191219_00005797af5d7d75f652","OriginatorConversationID":"16740-34861180-1","ResponseCode":"0","ResponseDescription":"Accept the service request successfully."}"#
+				.to_vec(),
+		),
+		sent: true,
+		..Default::default()
+	});
+
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		configure_daraja();
+		let b2c = B2cConfig {
+			initiator_name: b"testapi".to_vec(),
+			security_credential: b"Y3JlZA==".to_vec(),
+			shortcode: b"600998".to_vec(),
+			result_url: b"https://pool.example/mpesa/b2c/result".to_vec(),
+			timeout_url: b"https://pool.example/mpesa/b2c/timeout".to_vec(),
+		};
+		StorageValueRef::persistent(daraja::B2C_CONFIG_KEY).set(&b2c);
+		buy_tokens(1, 2_000);
+		attest(1, 2);
+		let phone_hash = sp_io::hashing::blake2_256(PHONE);
+		assert_ok!(MpesaUser::request_withdrawal(Origin::signed(1), phone_hash, 600));
+
+		MpesaUser::offchain_worker(1);
+		MpesaUser::offchain_worker(1);
+		assert_eq!(pool_state.read().transactions.len(), 1);
+
+		let tx = pool_state.write().transactions.pop().unwrap();
+		let tx = Extrinsic::decode(&mut &*tx).unwrap();
+		let call = match tx.call {
+			Call::MpesaUser(call) => call,
+			call => panic!("unexpected call {:?}", call),
+		};
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::Local, &call));
+		let payload = match call {
+			crate::Call::submit_conversation { payload, .. } => payload,
+			call => panic!("unexpected call {:?}", call),
+		};
+		assert_eq!(payload.withdrawal, 0);
+		assert_eq!(&payload.conversation_id[..], b"AG_20191219_00005797af5d7d75f652");
+	});
+}
+
+#[test]
+fn offchain_worker_reports_the_median_rate() {
+	let (mut ext, offchain_state, pool_state) = new_offchain_ext();
+	let responses: [(&str, &[u8]); 3] = [
+		(
+			"https://open.er-api.com/v6/latest/USD",
+			br#"{"result":"success","rates":{"KES":129.5,"UGX":3700.1}}"#,
+		),
+		("https://rates.example/usdt-kes", br#"{"pair":"USDT/KES","KES": 130}"#),
+		("https://rates.example/down", br#"{"error":"unavailable"}"#),
+	];
+	for (url, response) in responses {
+		offchain_state.write().expect_request(PendingRequest {
+			method: "GET".into(),
+			uri: url.into(),
+			response: Some(response.to_vec()),
+			sent: true,
+			..Default::default()
+		});
+	}
+
+	ext.execute_with(|| {
+		System::set_block_number(5);
+		let sources: Vec<_> = [
+			&b"https://open.er-api.com/v6/latest/USD"[..],
+			b"https://rates.example/usdt-kes",
+			b"https://rates.example/down",
+		]
+		.into_iter()
+		.map(|url| RateSource { url: url.to_vec(), key: b"KES".to_vec() })
+		.collect();
+		StorageValueRef::persistent(rates::SOURCES_KEY).set(&sources);
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+
+		// rates are only fetched every `RateInterval` blocks
+		MpesaUser::offchain_worker(4);
+		MpesaUser::offchain_worker(5);
+		let tx = pool_state.write().transactions.pop().unwrap();
+		assert!(pool_state.read().transactions.is_empty());
+		let call = match Extrinsic::decode(&mut &*tx).unwrap().call {
+			Call::MpesaUser(call) => call,
+			call => panic!("unexpected call {:?}", call),
+		};
+		let (payload, signature) = match call.clone() {
+			crate::Call::submit_rate { payload, signature } => (payload, signature),
+			call => panic!("unexpected call {:?}", call),
+		};
+		assert_eq!(payload.rate, FixedU128::from_rational(25_95, 20));
+
+		assert_ok!(MpesaUser::validate_unsigned(TransactionSource::Local, &call));
+		assert_ok!(MpesaUser::submit_rate(Origin::none(), payload.clone(), signature.clone()));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::KesRateUpdated(payload.rate)));
+		assert_eq!(MpesaUser::current_rate(), Some(payload.rate));
+		assert_eq!(
+			MpesaUser::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Stale.into()
+		);
+		assert_noop!(
+			MpesaUser::submit_rate(Origin::none(), payload, signature),
+			Error::<Test>::StaleRate
+		);
+
+		System::set_block_number(25);
+		assert_eq!(MpesaUser::current_rate(), Some(FixedU128::from_rational(25_95, 20)));
+		System::set_block_number(26);
+		assert_eq!(MpesaUser::current_rate(), None);
+	});
+}
+
+#[test]
+fn rates_only_come_from_workers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(10);
+		assert_ok!(MpesaUser::set_workers(Origin::root(), vec![UintAuthorityId(WORKER)]));
+		let rate = |public, block_number| {
+			let payload = RatePayload { public, block_number, rate: FixedU128::from(130) };
+			let signature = SignedPayload::<Test>::sign::<TestAuthId>(&payload).unwrap();
+			crate::Call::<Test>::submit_rate { payload, signature }
+		};
+
+		assert_ok!(MpesaUser::validate_unsigned(
+			TransactionSource::External,
+			&rate(UintAuthorityId(WORKER), 5)
+		));
+		assert_eq!(
+			MpesaUser::validate_unsigned(
+				TransactionSource::External,
+				&rate(UintAuthorityId(WORKER + 1), 5)
+			),
+			InvalidTransaction::BadSigner.into()
+		);
+		assert_eq!(
+			MpesaUser::validate_unsigned(
+				TransactionSource::External,
+				&rate(UintAuthorityId(WORKER), 4)
+			),
+			InvalidTransaction::Stale.into()
+		);
+		assert_eq!(
+			MpesaUser::validate_unsigned(
+				TransactionSource::External,
+				&rate(UintAuthorityId(WORKER), 11)
+			),
+			InvalidTransaction::Future.into()
+		);
+	});
+}
+
+#[test]
+fn rates_are_parsed_and_medianised() {
+	let json = br#"{"rates": {"KES": 129.257, "USD":1}}"#;
+	assert_eq!(rates::json_number(json, b"KES"), Some(FixedU128::from_rational(129_257, 1_000)));
+	assert_eq!(rates::json_number(json, b"USD"), Some(FixedU128::from(1)));
+	assert_eq!(rates::json_number(json, b"UGX"), None);
+	assert_eq!(rates::json_number(br#"{"KES":"129"}"#, b"KES"), None);
+	assert_eq!(rates::json_number(br#"{"KES":1.2.3}"#, b"KES"), None);
+
+	let rate = |rate: u128| FixedU128::from(rate);
+	assert_eq!(rates::median(vec![]), None);
+	assert_eq!(rates::median(vec![rate(131), rate(129), rate(500)]), Some(rate(131)));
+	assert_eq!(rates::median(vec![rate(131), rate(129)]), Some(rate(130)));
+}
+
+// a pool holding 100_000 of the native currency against 100_000 pool tokens, all provided by 1
+fn seeded_pool() {
+	buy_tokens(1, 1_000);
+	provider(1, 0);
+	assert_ok!(MpesaUser::add_liquidity(Origin::signed(1), 100_000, 100_000));
+	assert_eq!(MpesaUser::reserves(Asset::Native), (100_000, 100_000));
+}
+
+#[test]
+fn liquidity_is_added_at_the_pool_price() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		seeded_pool();
+		assert_eq!((MpesaUser::tokens(1), MpesaUser::shares(1)), (0, 100_000));
+		buy_tokens(2, 500);
+		provider(2, 0);
+		provider(3, 0);
+
+		assert_noop!(
+			MpesaUser::add_liquidity(Origin::signed(2), 50_000, 49_999),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_noop!(
+			MpesaUser::contribute_LPShares(Origin::signed(3), 50_000),
+			Error::<Test>::InsufficientTokens
+		);
+		assert_ok!(MpesaUser::add_liquidity(Origin::signed(2), 50_000, 50_000));
+		assert_eq!(MpesaUser::reserves(Asset::Token), (150_000, 150_000));
+		assert_eq!((MpesaUser::tokens(2), MpesaUser::shares(2)), (0, 50_000));
+
+		assert_ok!(MpesaUser::withdraw_LPShares(Origin::signed(2), 50_000));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::WithdrewTokens(50_000, 2)));
+		assert_eq!(MpesaUser::tokens(2), 50_000);
+		assert_eq!(MpesaUser::reserves(Asset::Native), (100_000, 100_000));
+	});
+}
+
+#[test]
+fn swaps_keep_the_reserves_product() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			MpesaUser::swap_exact_in(Origin::signed(2), Asset::Native, 10_000, 0),
+			Error::<Test>::NotEnoughLiquidity
+		);
+		seeded_pool();
+
+		// 10_000 less the 1% fee buys 100_000 * 9_900 / 109_900 tokens
+		assert_noop!(
+			MpesaUser::swap_exact_in(Origin::signed(2), Asset::Native, 10_000, 9_009),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_noop!(
+			MpesaUser::swap_exact_in(Origin::signed(2), Asset::Native, 0, 0),
+			Error::<Test>::InvalidSwapAmount
+		);
+		assert_ok!(MpesaUser::swap_exact_in(Origin::signed(2), Asset::Native, 10_000, 9_008));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::SwapExecuted(
+			2,
+			Asset::Native,
+			10_000,
+			9_008,
+		)));
+		assert_eq!(MpesaUser::tokens(2), 9_008);
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE - 10_000);
+		assert_eq!(MpesaUser::reserves(Asset::Native), (109_900, 90_992));
+
+		// 1_000 out takes 90_992 * 1_000 / 108_900 tokens after the fee, rounded up
+		assert_noop!(
+			MpesaUser::swap_exact_out(Origin::signed(2), Asset::Token, 1_000, 844),
+			Error::<Test>::SlippageExceeded
+		);
+		assert_noop!(
+			MpesaUser::swap_exact_out(Origin::signed(3), Asset::Token, 1_000, 845),
+			Error::<Test>::InsufficientTokens
+		);
+		assert_noop!(
+			MpesaUser::swap_exact_out(Origin::signed(2), Asset::Token, 109_900, u128::MAX),
+			Error::<Test>::NotEnoughLiquidity
+		);
+		assert_ok!(MpesaUser::swap_exact_out(Origin::signed(2), Asset::Token, 1_000, 845));
+		assert_eq!(MpesaUser::tokens(2), 9_008 - 845);
+		assert_eq!(Balances::free_balance(2), INITIAL_BALANCE - 9_000);
+		assert_eq!(MpesaUser::reserves(Asset::Native), (108_900, 91_829));
+	});
+}
+
+#[test]
+fn fees_are_shared_by_the_providers_and_the_treasury() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		seeded_pool();
+		buy_tokens(2, 500);
+		provider(2, 0);
+		assert_ok!(MpesaUser::add_liquidity(Origin::signed(2), 50_000, 50_000));
+		assert_noop!(MpesaUser::claim_rewards(Origin::signed(1)), Error::<Test>::NoRewards);
+
+		// a 150 fee: 20% to the treasury, the rest split 2:1 between 1's and 2's shares
+		assert_ok!(MpesaUser::swap_exact_in(Origin::signed(3), Asset::Native, 15_000, 0));
+		System::assert_has_event(Event::MpesaUser(MpesaEvent::FeesAccrued(Asset::Native, 30, 120)));
+		assert_eq!(MpesaUser::rewards(&1, Asset::Native), 80);
+		assert_eq!(MpesaUser::rewards(&2, Asset::Native), 40);
+
+		// shares changing hands keep what they earned so far
+		assert_ok!(MpesaUser::initiate_transfer(Origin::signed(2), 1, 25_000));
+		assert_ok!(MpesaUser::accept_transfer(Origin::signed(1), 0));
+		assert_ok!(MpesaUser::swap_exact_in(Origin::signed(3), Asset::Native, 15_000, 0));
+		assert_eq!(MpesaUser::rewards(&1, Asset::Native), 80 + 100);
+		assert_eq!(MpesaUser::rewards(&2, Asset::Native), 40 + 20);
+		assert_eq!(MpesaUser::rewards(&Treasury::get(), Asset::Native), 60);
+
+		let balance = Balances::free_balance(2);
+		assert_ok!(MpesaUser::claim_rewards(Origin::signed(2)));
+		System::assert_last_event(Event::MpesaUser(MpesaEvent::RewardsClaimed(2, 60, 0)));
+		assert_eq!(Balances::free_balance(2), balance + 60);
+		assert_noop!(MpesaUser::claim_rewards(Origin::signed(2)), Error::<Test>::NoRewards);
+
+		assert_ok!(MpesaUser::claim_rewards(Origin::signed(Treasury::get())));
+		assert_eq!(Balances::free_balance(Treasury::get()), 60);
+		assert_eq!(MpesaUser::rewards(&1, Asset::Native), 180);
+		// the fees were held by the pool's account apart from its balance
+		assert_eq!(
+			Balances::free_balance(MpesaUser::account_id()),
+			MpesaUser::pool_balance() + 180
+		);
 	});
 }
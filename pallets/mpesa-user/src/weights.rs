@@ -0,0 +1,305 @@
+// Weights for the mpesa-user pallet.
+//
+// The pallet has not been benchmarked on reference hardware yet. The values below are the weights
+// the extrinsics were charged before `WeightInfo` existed: a 10_000 base plus their database
+// accesses. Replace them with the output of
+//
+//     node-template benchmark pallet --chain dev --pallet pallet_mpesa_user --extrinsic '*' \
+//         --steps 50 --repeat 20 --output pallets/mpesa-user/src/weights.rs
+//
+// from a node built with `runtime-benchmarks`.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_mpesa_user.
+pub trait WeightInfo {
+	fn create_LProviderIdentity() -> Weight;
+	fn contribute_LPShares() -> Weight;
+	fn add_liquidity() -> Weight;
+	fn initiate_transfer() -> Weight;
+	fn accept_transfer() -> Weight;
+	fn cancel_transfer() -> Weight;
+	fn resolve_transfer() -> Weight;
+	fn withdraw_LPShares() -> Weight;
+	fn delete_LProviderIdentity() -> Weight;
+	fn set_parameter() -> Weight;
+	fn attest_identity() -> Weight;
+	fn revoke_attestation() -> Weight;
+	fn claim_rewards() -> Weight;
+	fn swap_exact_in() -> Weight;
+	fn swap_exact_out() -> Weight;
+	fn request_deposit() -> Weight;
+	fn submit_checkout_request() -> Weight;
+	fn confirm_payment() -> Weight;
+	fn request_withdrawal() -> Weight;
+	fn submit_conversation() -> Weight;
+	fn confirm_withdrawal() -> Weight;
+	fn submit_receipt() -> Weight;
+	fn submit_rate() -> Weight;
+	fn set_workers() -> Weight;
+	fn repin_receipts(n: u32) -> Weight;
+}
+
+/// Weights for pallet_mpesa_user using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn create_LProviderIdentity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn contribute_LPShares() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(18 as Weight))
+			.saturating_add(T::DbWeight::get().writes(21 as Weight))
+	}
+	fn add_liquidity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(18 as Weight))
+			.saturating_add(T::DbWeight::get().writes(21 as Weight))
+	}
+	fn initiate_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(6 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	fn accept_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(25 as Weight))
+			.saturating_add(T::DbWeight::get().writes(30 as Weight))
+	}
+	fn cancel_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn resolve_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(25 as Weight))
+			.saturating_add(T::DbWeight::get().writes(30 as Weight))
+	}
+	fn withdraw_LPShares() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(16 as Weight))
+			.saturating_add(T::DbWeight::get().writes(18 as Weight))
+	}
+	fn delete_LProviderIdentity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(12 as Weight))
+			.saturating_add(T::DbWeight::get().writes(10 as Weight))
+	}
+	fn set_parameter() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn attest_identity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn revoke_attestation() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn claim_rewards() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(9 as Weight))
+			.saturating_add(T::DbWeight::get().writes(8 as Weight))
+	}
+	fn swap_exact_in() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(15 as Weight))
+			.saturating_add(T::DbWeight::get().writes(17 as Weight))
+	}
+	fn swap_exact_out() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(15 as Weight))
+			.saturating_add(T::DbWeight::get().writes(17 as Weight))
+	}
+	fn request_deposit() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(5 as Weight))
+			.saturating_add(T::DbWeight::get().writes(6 as Weight))
+	}
+	fn submit_checkout_request() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	fn confirm_payment() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(15 as Weight))
+			.saturating_add(T::DbWeight::get().writes(19 as Weight))
+	}
+	fn request_withdrawal() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(9 as Weight))
+			.saturating_add(T::DbWeight::get().writes(8 as Weight))
+	}
+	fn submit_conversation() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(3 as Weight))
+	}
+	fn confirm_withdrawal() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(18 as Weight))
+			.saturating_add(T::DbWeight::get().writes(21 as Weight))
+	}
+	fn submit_receipt() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	fn submit_rate() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn set_workers() -> Weight {
+		(10_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn repin_receipts(n: u32) -> Weight {
+		(10_000 as Weight)
+			.saturating_add((10_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn create_LProviderIdentity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn contribute_LPShares() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(18 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(21 as Weight))
+	}
+	fn add_liquidity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(18 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(21 as Weight))
+	}
+	fn initiate_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(6 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn accept_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(25 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(30 as Weight))
+	}
+	fn cancel_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn resolve_transfer() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(25 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(30 as Weight))
+	}
+	fn withdraw_LPShares() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(16 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(18 as Weight))
+	}
+	fn delete_LProviderIdentity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(12 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(10 as Weight))
+	}
+	fn set_parameter() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn attest_identity() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn revoke_attestation() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn claim_rewards() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(8 as Weight))
+	}
+	fn swap_exact_in() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(15 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(17 as Weight))
+	}
+	fn swap_exact_out() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(15 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(17 as Weight))
+	}
+	fn request_deposit() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(5 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
+	}
+	fn submit_checkout_request() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn confirm_payment() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(15 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(19 as Weight))
+	}
+	fn request_withdrawal() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(8 as Weight))
+	}
+	fn submit_conversation() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(3 as Weight))
+	}
+	fn confirm_withdrawal() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(18 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(21 as Weight))
+	}
+	fn submit_receipt() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn submit_rate() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn set_workers() -> Weight {
+		(10_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn repin_receipts(n: u32) -> Weight {
+		(10_000 as Weight)
+			.saturating_add((10_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+	}
+}
@@ -0,0 +1,173 @@
+//! Golden vectors for the arithmetic of the M-Pesa liquidity pool: share minting and redemption,
+//! fee accrual, swap pricing and FX rate parsing. Each file in `golden/` records inputs and the
+//! outputs the pallet computes for them, so a refactor of `pallet_mpesa_user::math` or
+//! `pallet_mpesa_user::rates` can be checked against them byte for byte.
+
+use pallet_mpesa_user::{math, rates};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use sp_runtime::{FixedPointNumber, FixedU128, Perbill};
+
+/// A `u128`, written as a decimal string since most JSON parsers lose precision above 2^53.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Amount(pub u128);
+
+impl Serialize for Amount {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(&self.0)
+	}
+}
+
+impl<'de> Deserialize<'de> for Amount {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let amount = String::deserialize(deserializer)?;
+		amount.parse().map(Amount).map_err(serde::de::Error::custom)
+	}
+}
+
+/// A vector: the inputs and the outputs recorded for them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Case<I, O> {
+	pub input: I,
+	pub output: O,
+}
+
+/// Minting shares for a contribution and redeeming shares, in `golden/shares.json`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharesInput {
+	pub pool_balance: Amount,
+	pub total_shares: Amount,
+	/// The contribution to mint shares for.
+	pub amount: Amount,
+	/// The shares to redeem.
+	pub shares: Amount,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharesOutput {
+	/// `None` where the result overflows.
+	pub minted: Option<Amount>,
+	pub redeemed: Option<Amount>,
+}
+
+pub fn shares(input: &SharesInput) -> SharesOutput {
+	let (balance, total) = (input.pool_balance.0, input.total_shares.0);
+	SharesOutput {
+		minted: math::shares_for(input.amount.0, total, balance).map(Amount),
+		redeemed: math::redemption_value(input.shares.0, total, balance).map(Amount),
+	}
+}
+
+/// Accruing a fee to the pool and what a position earned after it, in `golden/fees.json`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeesInput {
+	pub fee: Amount,
+	/// The treasury's part of the fee, in parts per billion.
+	pub protocol_fee: u32,
+	pub total_shares: Amount,
+	/// The fees accrued per share before the fee, scaled by `REWARD_PRECISION`.
+	pub reward_per_share: Amount,
+	/// The position's shares and the fees it was already credited with.
+	pub shares: Amount,
+	pub reward_debt: Amount,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeesOutput {
+	pub treasury: Amount,
+	/// The fees accrued per share after the fee.
+	pub reward_per_share: Amount,
+	pub earned: Amount,
+}
+
+pub fn fees(input: &FeesInput) -> FeesOutput {
+	let (treasury, per_share) =
+		math::split_fee(input.fee.0, Perbill::from_parts(input.protocol_fee), input.total_shares.0);
+	let reward_per_share = input.reward_per_share.0.saturating_add(per_share);
+	FeesOutput {
+		treasury: Amount(treasury),
+		reward_per_share: Amount(reward_per_share),
+		earned: Amount(math::earned(input.shares.0, reward_per_share, input.reward_debt.0)),
+	}
+}
+
+/// Pricing a swap both ways, in `golden/swaps.json`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapsInput {
+	pub reserve_in: Amount,
+	pub reserve_out: Amount,
+	/// In parts per billion.
+	pub swap_fee: u32,
+	/// Sold for `amountOut`, and bought for `amountIn`.
+	pub amount: Amount,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapsOutput {
+	/// `None` where the pool can't price the swap.
+	pub amount_out: Option<Amount>,
+	pub amount_in: Option<Amount>,
+}
+
+pub fn swaps(input: &SwapsInput) -> SwapsOutput {
+	let (reserve_in, reserve_out) = (input.reserve_in.0, input.reserve_out.0);
+	let fee = Perbill::from_parts(input.swap_fee);
+	SwapsOutput {
+		amount_out: math::amount_out(reserve_in, reserve_out, fee, input.amount.0).map(Amount),
+		amount_in: math::amount_in(reserve_in, reserve_out, fee, input.amount.0).map(Amount),
+	}
+}
+
+/// Reading a rate out of each source's response and taking their median, in `golden/fx.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxInput {
+	/// The member holding the rate.
+	pub key: String,
+	/// The response bodies of the sources.
+	pub responses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FxOutput {
+	/// The rate read from each response, as the inner value of a `FixedU128` (1 is 10^18), or
+	/// `None` where the response is rejected.
+	pub rates: Vec<Option<Amount>>,
+	pub median: Option<Amount>,
+}
+
+pub fn fx(input: &FxInput) -> FxOutput {
+	let rates: Vec<Option<FixedU128>> = input
+		.responses
+		.iter()
+		.map(|response| rates::rate(response.as_bytes(), input.key.as_bytes()))
+		.collect();
+	let median = rates::median(rates.iter().flatten().copied().collect());
+	FxOutput {
+		rates: rates.iter().map(|rate| rate.map(|rate| Amount(rate.into_inner()))).collect(),
+		median: median.map(|rate| Amount(rate.into_inner())),
+	}
+}
+
+/// Recompute the outputs of the vectors in `golden` with `evaluate`, returning what the file
+/// should hold.
+pub fn regenerate<I, O>(golden: &str, evaluate: impl Fn(&I) -> O) -> serde_json::Result<String>
+where
+	I: Serialize + DeserializeOwned,
+	O: Serialize + DeserializeOwned,
+{
+	let cases: Vec<Case<I, O>> = serde_json::from_str(golden)?;
+	let cases: Vec<Case<I, O>> = cases
+		.into_iter()
+		.map(|case| {
+			let output = evaluate(&case.input);
+			Case { input: case.input, output }
+		})
+		.collect();
+	let mut document = serde_json::to_string_pretty(&cases)?;
+	document.push('\n');
+	Ok(document)
+}
@@ -0,0 +1,41 @@
+use std::{env, fs, path::Path};
+
+use mpesa_user_vectors::{fees, fx, regenerate, shares, swaps};
+use serde::{de::DeserializeOwned, Serialize};
+
+// compare `golden/<name>` with the outputs recomputed for its inputs, or rewrite it with them
+// when `UPDATE_GOLDEN` is set
+fn check<I, O>(name: &str, evaluate: impl Fn(&I) -> O)
+where
+	I: Serialize + DeserializeOwned,
+	O: Serialize + DeserializeOwned,
+{
+	let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden").join(name);
+	let recorded = fs::read_to_string(&path).unwrap();
+	let computed = regenerate(&recorded, evaluate).unwrap();
+	if env::var_os("UPDATE_GOLDEN").is_some() {
+		fs::write(&path, computed).unwrap();
+		return;
+	}
+	assert_eq!(computed, recorded, "{} no longer matches the pallet's arithmetic", name);
+}
+
+#[test]
+fn share_minting_matches_the_golden_vectors() {
+	check("shares.json", shares);
+}
+
+#[test]
+fn fee_accrual_matches_the_golden_vectors() {
+	check("fees.json", fees);
+}
+
+#[test]
+fn swap_pricing_matches_the_golden_vectors() {
+	check("swaps.json", swaps);
+}
+
+#[test]
+fn fx_conversion_matches_the_golden_vectors() {
+	check("fx.json", fx);
+}
@@ -0,0 +1,248 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Jobs the offchain workers run against external services.
+//!
+//! A job is a request to something outside the chain: a command to the IPFS node, an STK push or
+//! B2C payment through Daraja. Every worker follows the same cycle with its jobs: process them,
+//! hand the outcomes back to the chain through a callback, retry the failed ones with exponential
+//! backoff and dead-letter those that used up their retries. `Jobs` runs that cycle for any
+//! `JobHandler`, keeping the jobs waiting to be retried in the node's persistent off-chain
+//! storage, so the workers only implement what is particular to their service.
+//!
+//! The jobs themselves come from the worker's own on-chain queue; nothing here touches the chain's
+//! state. `claim` and friends let overlapping worker runs agree on who processes a job.
+
+use codec::{Decode, Encode};
+use sp_runtime::{
+	offchain::{
+		storage::StorageValueRef,
+		storage_lock::{StorageLock, Time},
+	},
+	traits::AtLeast32BitUnsigned,
+};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+#[cfg(test)]
+mod tests;
+
+/// The number of blocks a job waits after its first failed attempt; the wait doubles with every
+/// further failure.
+pub const RETRY_BASE_DELAY: u32 = 2;
+/// The most times the wait doubles.
+pub const MAX_BACKOFF_EXPONENT: u32 = 10;
+
+/// A kind of job, and how to process it and hand its outcome back to the chain.
+pub trait JobHandler {
+	/// The block number of the chain the jobs are for.
+	type BlockNumber: AtLeast32BitUnsigned + Copy + Encode + Decode;
+	/// A job, kept in the node's off-chain storage while it waits to be retried.
+	type Job: Encode + Decode + Clone;
+	/// What a successful attempt yields.
+	type Output;
+	/// Why an attempt failed, kept with the job while it waits to be retried.
+	type Error: Encode + Decode + Clone;
+	/// What a worker run shares between its attempts and callbacks, e.g. an access token.
+	type Context;
+
+	/// The key of the node-local queue of the jobs waiting to be retried.
+	const QUEUE: &'static [u8];
+
+	/// The number of times a failed job is retried before it is dead-lettered.
+	fn max_retries() -> u32;
+
+	/// The number of blocks to wait before the next attempt, after `attempts` failed ones.
+	fn backoff(attempts: u32) -> u32 {
+		RETRY_BASE_DELAY << attempts.saturating_sub(1).min(MAX_BACKOFF_EXPONENT)
+	}
+
+	/// Whether a job waiting out its backoff should be retried right away, e.g. because its
+	/// submitter paid to bump it.
+	fn is_urgent(_job: &Self::Job, _now: Self::BlockNumber) -> bool {
+		false
+	}
+
+	/// Whether a job waiting to be retried no longer has to run, e.g. because the chain settled
+	/// it another way; such jobs are dropped.
+	fn is_stale(_job: &Self::Job) -> bool {
+		false
+	}
+
+	/// Attempt `job`; `retry` is the error of its last failed attempt when it is retried.
+	fn process(
+		ctx: &mut Self::Context,
+		job: &Self::Job,
+		retry: Option<&Self::Error>,
+	) -> Result<Self::Output, Self::Error>;
+
+	/// Hand the outcomes of a run's attempts back to the chain.
+	fn on_complete(
+		ctx: &mut Self::Context,
+		outcomes: Vec<Outcome<Self::Job, Self::Output, Self::Error>>,
+	);
+
+	/// Give up on a job that failed `attempts` times, the last one with `error`.
+	fn on_dead_letter(job: Self::Job, attempts: u32, error: Self::Error);
+}
+
+/// The outcome of an attempt at a job.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Outcome<Job, Output, Error> {
+	/// The job is done, after `attempts` attempts.
+	Done { job: Job, attempts: u32, output: Output },
+	/// The job's `attempts`th attempt failed with `error`; it is retried unless it is
+	/// dead-lettered.
+	Failed { job: Job, attempts: u32, error: Error, retrying: bool },
+}
+
+/// A job waiting in the node-local queue to be retried.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct Retry<Job, Error, BlockNumber> {
+	/// The job to retry.
+	pub job: Job,
+	/// The number of failed attempts so far.
+	pub attempts: u32,
+	/// The block from which on the job may be retried.
+	pub retry_at: BlockNumber,
+	/// The error of the last failed attempt.
+	pub error: Error,
+}
+
+type RetryOf<H> =
+	Retry<<H as JobHandler>::Job, <H as JobHandler>::Error, <H as JobHandler>::BlockNumber>;
+
+/// Runs the jobs of `H`.
+pub struct Jobs<H>(PhantomData<H>);
+
+impl<H: JobHandler> Jobs<H> {
+	/// Attempt `jobs` for the first time, handing their outcomes to `H::on_complete` and queueing
+	/// the failed ones to be retried.
+	pub fn process(ctx: &mut H::Context, jobs: Vec<H::Job>, now: H::BlockNumber) {
+		Self::attempt(ctx, jobs.into_iter().map(|job| (job, 0, None)).collect(), now);
+	}
+
+	/// Queue jobs whose attempts failed elsewhere to be retried, as `(job, attempts, error)`;
+	/// those that used up their retries are dead-lettered.
+	pub fn retry(failures: Vec<(H::Job, u32, H::Error)>, now: H::BlockNumber) {
+		let mut retries = Vec::new();
+		for (job, attempts, error) in failures {
+			if attempts > H::max_retries() {
+				H::on_dead_letter(job, attempts, error);
+				continue;
+			}
+			let retry_at = now.saturating_add(H::backoff(attempts).into());
+			retries.push(Retry { job, attempts, retry_at, error });
+		}
+		if retries.is_empty() {
+			return;
+		}
+
+		let mut lock = StorageLock::<Time>::new(&Self::lock_key());
+		let _guard = lock.lock();
+		let queue = StorageValueRef::persistent(H::QUEUE);
+		let mut pending = Self::queued(&queue);
+		pending.extend(retries);
+		queue.set(&pending);
+	}
+
+	/// Retry up to `limit` of the queued jobs whose backoff elapsed, or that are urgent.
+	pub fn run_retries(ctx: &mut H::Context, now: H::BlockNumber, limit: usize) {
+		let due = {
+			let mut lock = StorageLock::<Time>::new(&Self::lock_key());
+			let _guard = lock.lock();
+
+			let queue = StorageValueRef::persistent(H::QUEUE);
+			let (mut due, mut pending): (Vec<_>, Vec<_>) = Self::queued(&queue)
+				.into_iter()
+				.filter(|retry| !H::is_stale(&retry.job))
+				.partition(|retry| retry.retry_at <= now || H::is_urgent(&retry.job, now));
+			if due.len() > limit {
+				pending.extend(due.split_off(limit));
+			}
+			queue.set(&pending);
+			due
+		};
+		if due.is_empty() {
+			return;
+		}
+
+		let jobs = due
+			.into_iter()
+			.map(|retry| (retry.job, retry.attempts, Some(retry.error)))
+			.collect();
+		Self::attempt(ctx, jobs, now);
+	}
+
+	/// The jobs waiting to be retried.
+	pub fn pending() -> Vec<RetryOf<H>> {
+		Self::queued(&StorageValueRef::persistent(H::QUEUE))
+	}
+
+	// attempt each `(job, attempts, error)`, retry or dead-letter the failed ones and hand the
+	// outcomes to the callback
+	fn attempt(
+		ctx: &mut H::Context,
+		jobs: Vec<(H::Job, u32, Option<H::Error>)>,
+		now: H::BlockNumber,
+	) {
+		if jobs.is_empty() {
+			return;
+		}
+
+		let mut outcomes = Vec::new();
+		let mut failures = Vec::new();
+		for (job, attempts, last_error) in jobs {
+			let attempts = attempts.saturating_add(1);
+			match H::process(ctx, &job, last_error.as_ref()) {
+				Ok(output) => outcomes.push(Outcome::Done { job, attempts, output }),
+				Err(error) => {
+					let retrying = attempts <= H::max_retries();
+					failures.push((job.clone(), attempts, error.clone()));
+					outcomes.push(Outcome::Failed { job, attempts, error, retrying });
+				},
+			}
+		}
+		Self::retry(failures, now);
+		H::on_complete(ctx, outcomes);
+	}
+
+	fn queued(queue: &StorageValueRef) -> Vec<RetryOf<H>> {
+		queue.get::<Vec<RetryOf<H>>>().ok().flatten().unwrap_or_default()
+	}
+
+	fn lock_key() -> Vec<u8> {
+		let mut key = H::QUEUE.to_vec();
+		key.extend_from_slice(b"::lock");
+		key
+	}
+}
+
+/// Claim the job under `key` for good, so that an overlapping run doesn't process it again;
+/// `false` if it is claimed already.
+pub fn claim(key: &[u8]) -> bool {
+	StorageValueRef::persistent(key)
+		.mutate(|claimed: Result<Option<bool>, _>| match claimed {
+			Ok(Some(_)) => Err(()),
+			_ => Ok(true),
+		})
+		.is_ok()
+}
+
+/// Claim the job under `key` until the block `until`, by which whatever was submitted for it has
+/// been included or dropped from the pool, so that a later run tries again if it is still
+/// pending; `false` if it is claimed already.
+pub fn claim_until<BlockNumber>(key: &[u8], now: BlockNumber, until: BlockNumber) -> bool
+where
+	BlockNumber: Encode + Decode + PartialOrd,
+{
+	StorageValueRef::persistent(key)
+		.mutate(|claimed: Result<Option<BlockNumber>, _>| match claimed {
+			Ok(Some(expires_at)) if expires_at > now => Err(()),
+			_ => Ok(until),
+		})
+		.is_ok()
+}
+
+/// Release the claim on the job under `key`, so that a later run processes it again.
+pub fn release(key: &[u8]) {
+	StorageValueRef::persistent(key).clear();
+}
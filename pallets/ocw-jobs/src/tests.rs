@@ -0,0 +1,135 @@
+use crate::{claim, claim_until, release, JobHandler, Jobs, Outcome};
+use sp_core::offchain::{testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt};
+use std::cell::RefCell;
+
+thread_local! {
+	// the jobs `TestJobs` fails, with the error to fail them with
+	static FAILING: RefCell<Vec<(u32, u8)>> = RefCell::new(vec![]);
+	// the outcomes and dead letters handed back, in order
+	static COMPLETED: RefCell<Vec<Outcome<u32, u32, u8>>> = RefCell::new(vec![]);
+	static DEAD_LETTERS: RefCell<Vec<(u32, u32, u8)>> = RefCell::new(vec![]);
+	// the jobs that don't have to run anymore
+	static STALE: RefCell<Vec<u32>> = RefCell::new(vec![]);
+}
+
+/// Doubles its jobs, or fails those in `FAILING`; counts its attempts in the context.
+struct TestJobs;
+
+impl JobHandler for TestJobs {
+	type BlockNumber = u64;
+	type Job = u32;
+	type Output = u32;
+	type Error = u8;
+	type Context = u32;
+
+	const QUEUE: &'static [u8] = b"test::jobs";
+
+	fn max_retries() -> u32 {
+		2
+	}
+
+	fn is_stale(job: &u32) -> bool {
+		STALE.with(|stale| stale.borrow().contains(job))
+	}
+
+	fn process(attempts: &mut u32, job: &u32, _retry: Option<&u8>) -> Result<u32, u8> {
+		*attempts += 1;
+		let failing = FAILING.with(|failing| {
+			failing.borrow().iter().find(|(failing, _)| failing == job).map(|(_, e)| *e)
+		});
+		failing.map_or(Ok(job * 2), Err)
+	}
+
+	fn on_complete(_: &mut u32, outcomes: Vec<Outcome<u32, u32, u8>>) {
+		COMPLETED.with(|completed| completed.borrow_mut().extend(outcomes));
+	}
+
+	fn on_dead_letter(job: u32, attempts: u32, error: u8) {
+		DEAD_LETTERS.with(|dead| dead.borrow_mut().push((job, attempts, error)));
+	}
+}
+
+fn new_offchain_ext() -> sp_io::TestExternalities {
+	let (offchain, _) = TestOffchainExt::new();
+	let mut ext = sp_io::TestExternalities::default();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	ext
+}
+
+fn completed() -> Vec<Outcome<u32, u32, u8>> {
+	COMPLETED.with(|completed| completed.borrow_mut().drain(..).collect())
+}
+
+#[test]
+fn failed_jobs_are_retried_with_backoff_then_dead_lettered() {
+	new_offchain_ext().execute_with(|| {
+		FAILING.with(|failing| failing.borrow_mut().push((2, 7)));
+		let mut attempts = 0;
+		Jobs::<TestJobs>::process(&mut attempts, vec![1, 2], 10);
+		assert_eq!(
+			completed(),
+			vec![
+				Outcome::Done { job: 1, attempts: 1, output: 2 },
+				Outcome::Failed { job: 2, attempts: 1, error: 7, retrying: true },
+			]
+		);
+		let pending = Jobs::<TestJobs>::pending();
+		assert_eq!((pending[0].job, pending[0].retry_at), (2, 12));
+
+		// nothing is retried before the backoff elapses
+		Jobs::<TestJobs>::run_retries(&mut attempts, 11, 8);
+		assert_eq!(attempts, 2);
+		Jobs::<TestJobs>::run_retries(&mut attempts, 12, 8);
+		assert_eq!(
+			completed(),
+			vec![Outcome::Failed { job: 2, attempts: 2, error: 7, retrying: true }]
+		);
+		// the wait doubles
+		assert_eq!(Jobs::<TestJobs>::pending()[0].retry_at, 16);
+
+		Jobs::<TestJobs>::run_retries(&mut attempts, 16, 8);
+		assert_eq!(
+			completed(),
+			vec![Outcome::Failed { job: 2, attempts: 3, error: 7, retrying: false }]
+		);
+		assert_eq!(DEAD_LETTERS.with(|dead| dead.borrow().clone()), vec![(2, 3, 7)]);
+		assert!(Jobs::<TestJobs>::pending().is_empty());
+		assert_eq!(attempts, 4);
+	});
+}
+
+#[test]
+fn retries_run_in_batches_and_stale_jobs_are_dropped() {
+	new_offchain_ext().execute_with(|| {
+		Jobs::<TestJobs>::retry(vec![(1, 1, 0), (2, 1, 0), (3, 1, 0), (4, 3, 0)], 0);
+		// the job out of retries is dead-lettered right away
+		assert_eq!(DEAD_LETTERS.with(|dead| dead.borrow().clone()), vec![(4, 3, 0)]);
+
+		STALE.with(|stale| stale.borrow_mut().push(1));
+		let mut attempts = 0;
+		Jobs::<TestJobs>::run_retries(&mut attempts, 2, 1);
+		assert_eq!(completed(), vec![Outcome::Done { job: 2, attempts: 2, output: 4 }]);
+		assert_eq!(
+			Jobs::<TestJobs>::pending()
+				.into_iter()
+				.map(|retry| retry.job)
+				.collect::<Vec<_>>(),
+			vec![3]
+		);
+	});
+}
+
+#[test]
+fn claims_keep_overlapping_runs_apart() {
+	new_offchain_ext().execute_with(|| {
+		assert!(claim(b"job"));
+		assert!(!claim(b"job"));
+		release(b"job");
+		assert!(claim(b"job"));
+
+		assert!(claim_until(b"until", 1u64, 5));
+		assert!(!claim_until(b"until", 4u64, 8));
+		assert!(claim_until(b"until", 5u64, 9));
+	});
+}
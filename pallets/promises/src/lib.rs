@@ -0,0 +1,164 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Promises of the outcomes of the chain's asynchronous operations: IPFS data commands, DHT
+/// queries, M-Pesa deposits and withdrawals. The pallets running the operations create a promise
+/// when they start one and resolve or reject it when it completes, through the
+/// `PromiseRegistry` trait, so that clients track every kind of operation by an id the same way,
+/// through the `PromiseResolved` and `PromiseRejected` events or the runtime API. Settled promises
+/// are kept for `SettledTtl` blocks.
+pub use pallet::*;
+
+pub mod runtime_api;
+
+pub use mpesa_primitives::{AsyncOp, PromiseId, PromiseRegistry};
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::Saturating;
+	use sp_std::vec::Vec;
+
+	/// The state of a promise.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub enum PromiseState<Hash> {
+		/// The operation hasn't completed yet.
+		Pending,
+		/// The operation completed, with the hash of its result.
+		Resolved(Hash),
+		/// The operation failed.
+		Rejected(DispatchError),
+	}
+
+	/// A promise of the outcome of an asynchronous operation.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct Promise<Hash, BlockNumber> {
+		/// The operation the promise is for.
+		pub op: AsyncOp<Hash>,
+		/// Whether the operation completed, and how.
+		pub state: PromiseState<Hash>,
+		/// The block the promise was created in.
+		pub created_at: BlockNumber,
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The number of blocks settled promises are kept for.
+		#[pallet::constant]
+		type SettledTtl: Get<Self::BlockNumber>;
+	}
+
+	// The id of the next promise.
+	#[pallet::storage]
+	#[pallet::getter(fn next_promise_id)]
+	pub type NextPromiseId<T> = StorageValue<_, PromiseId, ValueQuery>;
+
+	// The promises, until `SettledTtl` blocks after they settled.
+	#[pallet::storage]
+	#[pallet::getter(fn promise)]
+	pub type Promises<T: Config> =
+		StorageMap<_, Twox64Concat, PromiseId, Promise<T::Hash, T::BlockNumber>>;
+
+	// The pending promise of each operation.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_promise)]
+	pub type PendingPromises<T: Config> =
+		StorageMap<_, Blake2_128Concat, AsyncOp<T::Hash>, PromiseId>;
+
+	// The settled promises to drop in each block.
+	#[pallet::storage]
+	#[pallet::getter(fn promise_expiry)]
+	pub type PromiseExpiry<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<PromiseId>, ValueQuery>;
+
+	// Pallets use events to inform users when important changes are made.
+	// https://docs.substrate.io/main-docs/build/events-errors/
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An operation was started. [promise, op]
+		PromiseCreated(PromiseId, AsyncOp<T::Hash>),
+		/// An operation completed. [promise, result]
+		PromiseResolved(PromiseId, T::Hash),
+		/// An operation failed. [promise, error]
+		PromiseRejected(PromiseId, DispatchError),
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(now: T::BlockNumber) -> Weight {
+			let expired = <PromiseExpiry<T>>::take(now);
+			for id in &expired {
+				<Promises<T>>::remove(id);
+			}
+			T::DbWeight::get().reads_writes(1, 1 + expired.len() as Weight)
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The promises with `ids`, for the runtime API: `None` for those that don't exist or
+		/// were dropped.
+		pub fn promises(ids: Vec<PromiseId>) -> Vec<Option<Promise<T::Hash, T::BlockNumber>>> {
+			ids.into_iter().map(Self::promise).collect()
+		}
+
+		// settle the pending promise of `op`, if any, and drop it once `SettledTtl` passed
+		fn settle(op: &AsyncOp<T::Hash>, state: PromiseState<T::Hash>) -> Option<PromiseId> {
+			let id = <PendingPromises<T>>::take(op)?;
+			<Promises<T>>::mutate(id, |promise| {
+				if let Some(promise) = promise {
+					promise.state = state;
+				}
+			});
+			let expires_at =
+				<frame_system::Pallet<T>>::block_number().saturating_add(T::SettledTtl::get());
+			<PromiseExpiry<T>>::append(expires_at, id);
+			Some(id)
+		}
+	}
+
+	impl<T: Config> PromiseRegistry<T::Hash> for Pallet<T> {
+		fn create(op: AsyncOp<T::Hash>) -> Option<PromiseId> {
+			if let Some(id) = Self::pending_promise(&op) {
+				return Some(id);
+			}
+			let id = Self::next_promise_id();
+			let created_at = <frame_system::Pallet<T>>::block_number();
+			<Promises<T>>::insert(
+				id,
+				Promise { op: op.clone(), state: PromiseState::Pending, created_at },
+			);
+			<PendingPromises<T>>::insert(&op, id);
+			<NextPromiseId<T>>::put(id + 1);
+			Self::deposit_event(Event::PromiseCreated(id, op));
+			Some(id)
+		}
+
+		fn resolve(op: &AsyncOp<T::Hash>, result: T::Hash) {
+			if let Some(id) = Self::settle(op, PromiseState::Resolved(result)) {
+				Self::deposit_event(Event::PromiseResolved(id, result));
+			}
+		}
+
+		fn reject(op: &AsyncOp<T::Hash>, error: DispatchError) {
+			if let Some(id) = Self::settle(op, PromiseState::Rejected(error)) {
+				Self::deposit_event(Event::PromiseRejected(id, error));
+			}
+		}
+	}
+}
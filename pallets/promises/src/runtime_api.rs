@@ -0,0 +1,22 @@
+// Runtime API for resolving promise ids.
+//
+// Clients that started an asynchronous operation get its promise's id from the
+// `PromiseCreated` event, and poll the promise here instead of following every block's events.
+// A runtime implements the API by forwarding to `Pallet::promises`.
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+use crate::{Promise, PromiseId};
+
+sp_api::decl_runtime_apis! {
+	/// API to read the promises pallet's promises.
+	pub trait PromisesApi<Hash, BlockNumber> where
+		Hash: Codec,
+		BlockNumber: Codec,
+	{
+		/// The promises with `ids`, in the same order: `None` for those that don't exist, or
+		/// settled long enough ago to have been dropped.
+		fn promises(ids: Vec<PromiseId>) -> Vec<Option<Promise<Hash, BlockNumber>>>;
+	}
+}
@@ -0,0 +1,50 @@
+use crate::{mock::*, AsyncOp, Event as PromisesEvent, PromiseRegistry, PromiseState};
+use frame_support::traits::Hooks;
+use sp_core::H256;
+use sp_runtime::DispatchError;
+
+#[test]
+fn promises_settle_once_and_expire() {
+	new_test_ext().execute_with(|| {
+		let add = AsyncOp::IpfsRequest(3);
+		assert_eq!(<Promises as PromiseRegistry<H256>>::create(add.clone()), Some(0));
+		System::assert_last_event(Event::Promises(PromisesEvent::PromiseCreated(0, add.clone())));
+		// the pending promise is reused
+		assert_eq!(<Promises as PromiseRegistry<H256>>::create(add.clone()), Some(0));
+		let deposit = AsyncOp::MpesaDeposit(3);
+		assert_eq!(<Promises as PromiseRegistry<H256>>::create(deposit.clone()), Some(1));
+
+		let result = H256::repeat_byte(1);
+		Promises::resolve(&add, result);
+		System::assert_last_event(Event::Promises(PromisesEvent::PromiseResolved(0, result)));
+		assert_eq!(Promises::promise(0).unwrap().state, PromiseState::Resolved(result));
+		// a settled promise can't be settled again, but the operation can be promised anew
+		Promises::reject(&add, DispatchError::Other("late"));
+		assert_eq!(Promises::promise(0).unwrap().state, PromiseState::Resolved(result));
+		assert_eq!(Promises::pending_promise(&add), None);
+
+		System::set_block_number(5);
+		Promises::reject(&deposit, DispatchError::Other("timeout"));
+		System::assert_last_event(Event::Promises(PromisesEvent::PromiseRejected(
+			1,
+			DispatchError::Other("timeout"),
+		)));
+		assert_eq!(
+			Promises::promises(vec![1, 0, 7])
+				.into_iter()
+				.map(|promise| promise.map(|promise| promise.state))
+				.collect::<Vec<_>>(),
+			vec![
+				Some(PromiseState::Rejected(DispatchError::Other("timeout"))),
+				Some(PromiseState::Resolved(result)),
+				None,
+			]
+		);
+
+		Promises::on_initialize(11);
+		assert_eq!(Promises::promise(0), None);
+		assert!(Promises::promise(1).is_some());
+		Promises::on_initialize(15);
+		assert_eq!(Promises::promise(1), None);
+	});
+}
@@ -0,0 +1,94 @@
+//! Benchmarking setup for pallet-ussd-gateway
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as UssdGateway;
+use codec::Encode;
+use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_support::traits::{EnsureOrigin, Get};
+use frame_system::RawOrigin;
+use sp_runtime::traits::Bounded;
+
+// a funded, whitelisted gateway
+fn gateway<T: Config>(who: &T::AccountId) {
+	T::Currency::make_free_balance_be(who, BalanceOf::<T>::max_value() / 2u32.into());
+	let origin = T::GatewayOrigin::successful_origin();
+	UssdGateway::<T>::register_gateway(origin, who.clone()).unwrap();
+}
+
+benchmarks! {
+	register_gateway {
+		let who: T::AccountId = account("gateway", 0, 0);
+		T::Currency::make_free_balance_be(&who, BalanceOf::<T>::max_value() / 2u32.into());
+		let origin = T::GatewayOrigin::successful_origin();
+	}: _<T::Origin>(origin, who.clone())
+	verify {
+		assert!(Gateways::<T>::contains_key(&who));
+	}
+
+	remove_gateway {
+		let who: T::AccountId = account("gateway", 0, 0);
+		gateway::<T>(&who);
+		let origin = T::GatewayOrigin::successful_origin();
+	}: _<T::Origin>(origin, who.clone())
+	verify {
+		assert!(Gateways::<T>::get(&who).unwrap().unbonding_at.is_some());
+	}
+
+	withdraw_bond {
+		let caller: T::AccountId = whitelisted_caller();
+		gateway::<T>(&caller);
+		let origin = T::GatewayOrigin::successful_origin();
+		UssdGateway::<T>::remove_gateway(origin, caller.clone()).unwrap();
+		let unbonding_at = Gateways::<T>::get(&caller).unwrap().unbonding_at.unwrap();
+		frame_system::Pallet::<T>::set_block_number(unbonding_at);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(!Gateways::<T>::contains_key(&caller));
+	}
+
+	proxy_contribute {
+		let caller: T::AccountId = whitelisted_caller();
+		gateway::<T>(&caller);
+		// the phone's first contribution, which also creates its account
+		let phone_hash = [7; 32];
+		let phone = UssdGateway::<T>::phone_account(&phone_hash);
+		T::Currency::make_free_balance_be(&phone, BalanceOf::<T>::max_value() / 2u32.into());
+		T::Pool::prepare_contributor(&phone);
+		let amount = T::Currency::minimum_balance() * 1_000u32.into();
+		let (key, sig) = T::BenchmarkHelper::sign(&(phone_hash, amount, 0u64).encode());
+		PhoneKeys::<T>::insert(phone_hash, key);
+	}: _(RawOrigin::Signed(caller.clone()), phone_hash, amount, 0, sig)
+	verify {
+		assert_eq!(PhoneNonces::<T>::get(phone_hash), 1);
+	}
+
+	report_fraud {
+		let who: T::AccountId = account("gateway", 0, 0);
+		gateway::<T>(&who);
+		let intent = Intent {
+			gateway: who.clone(),
+			phone_hash: [0; 32],
+			nonce: 0,
+			amount: T::Currency::minimum_balance(),
+			submitted_at: frame_system::Pallet::<T>::block_number(),
+		};
+		Intents::<T>::insert(0, intent);
+		let origin = T::FraudOrigin::successful_origin();
+	}: _<T::Origin>(origin, 0)
+	verify {
+		let phone = UssdGateway::<T>::phone_account(&[0; 32]);
+		assert_eq!(T::Currency::free_balance(&phone), T::GatewayBond::get());
+	}
+
+	register_phone_key {
+		let key: T::AccountId = account("key", 0, 0);
+		let origin = T::GatewayOrigin::successful_origin();
+	}: _<T::Origin>(origin, [7; 32], key.clone())
+	verify {
+		assert_eq!(PhoneKeys::<T>::get([7; 32]), Some(key));
+	}
+
+	impl_benchmark_test_suite!(UssdGateway, crate::mock::new_test_ext(), crate::mock::Test);
+}
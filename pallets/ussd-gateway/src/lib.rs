@@ -0,0 +1,376 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Gateways bridging feature phones to the chain: a whitelisted gateway runs the USSD or SMS
+/// session with a phone and submits the intents it collects on the phone's behalf, signed with
+/// the key registered for the phone. Each phone number is represented by an account derived from
+/// its hash, and its intents are ordered by a per-phone nonce. Gateways bond a deposit when they're
+/// whitelisted, which is paid to the phone's account if an intent they submitted is found to be
+/// fraudulent.
+pub use pallet::*;
+
+use frame_support::traits::Currency;
+
+pub mod weights;
+
+pub use mpesa_primitives::LiquidityPool;
+pub use weights::WeightInfo;
+
+/// Signs intents in benchmarks, where the runtime's signature scheme isn't known.
+#[cfg(feature = "runtime-benchmarks")]
+pub trait BenchmarkHelper<AccountId, Signature> {
+	/// Sign `message` with a fresh key, returning the key's account and the signature.
+	fn sign(message: &[u8]) -> (AccountId, Signature);
+}
+
+type BalanceOf<T> =
+	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{BalanceStatus, ReservableCurrency},
+		PalletId,
+	};
+	use frame_system::pallet_prelude::*;
+	use sp_runtime::traits::{IdentifyAccount, Saturating, TrailingZeroInput, Verify, Zero};
+
+	/// The identifier of a proxied intent.
+	pub type IntentId = u64;
+
+	/// A whitelisted gateway's bond.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct GatewayInfo<Balance, BlockNumber> {
+		/// The gateway's balance reserved as its bond.
+		pub bond: Balance,
+		/// The block the gateway can withdraw its bond in, once it was removed from the whitelist.
+		pub unbonding_at: Option<BlockNumber>,
+	}
+
+	/// An intent a gateway submitted on a phone's behalf, kept while it can be disputed.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+	pub struct Intent<AccountId, Balance, BlockNumber> {
+		/// The gateway that submitted the intent.
+		pub gateway: AccountId,
+		/// The blake2-256 hash of the phone number the intent is for.
+		pub phone_hash: [u8; 32],
+		/// The phone's nonce the intent used.
+		pub nonce: u64,
+		/// The amount contributed to the pool.
+		pub amount: Balance,
+		/// The block the intent was submitted in.
+		pub submitted_at: BlockNumber,
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The currency gateways bond and contributions are paid in.
+		type Currency: ReservableCurrency<Self::AccountId>;
+		/// The pallet's id, which the phones' accounts are derived from.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+		/// The key phones' intents are signed with.
+		type Signer: IdentifyAccount<AccountId = Self::AccountId>;
+		/// The signature of a phone's intent.
+		type Signature: Verify<Signer = Self::Signer> + Parameter;
+		/// The pool proxied contributions are paid into.
+		type Pool: LiquidityPool<Self::AccountId, BalanceOf<Self>>;
+		/// The origin that whitelists gateways, e.g. root or a governance collective.
+		type GatewayOrigin: EnsureOrigin<Self::Origin>;
+		/// The origin that rules intents fraudulent and slashes their gateways.
+		type FraudOrigin: EnsureOrigin<Self::Origin>;
+		/// The balance a gateway has to bond to be whitelisted.
+		#[pallet::constant]
+		type GatewayBond: Get<BalanceOf<Self>>;
+		/// The number of blocks an intent can be reported as fraudulent in, and a removed
+		/// gateway's bond stays reserved for.
+		#[pallet::constant]
+		type DisputePeriod: Get<Self::BlockNumber>;
+		/// Weight information for the pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+		/// Signs the intents of the phones the benchmarks register.
+		#[cfg(feature = "runtime-benchmarks")]
+		type BenchmarkHelper: BenchmarkHelper<Self::AccountId, Self::Signature>;
+	}
+
+	// The whitelisted gateways and those still unbonding, with their bonds.
+	#[pallet::storage]
+	#[pallet::getter(fn gateway)]
+	pub type Gateways<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, GatewayInfo<BalanceOf<T>, T::BlockNumber>>;
+
+	// The nonce each phone's next intent has to use.
+	#[pallet::storage]
+	#[pallet::getter(fn phone_nonce)]
+	pub type PhoneNonces<T> = StorageMap<_, Identity, [u8; 32], u64, ValueQuery>;
+
+	// The key each phone's intents have to be signed with, as an account.
+	#[pallet::storage]
+	#[pallet::getter(fn phone_key)]
+	pub type PhoneKeys<T: Config> = StorageMap<_, Identity, [u8; 32], T::AccountId>;
+
+	// The account of each phone a gateway submitted an intent for.
+	#[pallet::storage]
+	#[pallet::getter(fn phone_account_of)]
+	pub type PhoneAccounts<T: Config> = StorageMap<_, Identity, [u8; 32], T::AccountId>;
+
+	// The id of the next intent.
+	#[pallet::storage]
+	#[pallet::getter(fn next_intent_id)]
+	pub type NextIntentId<T> = StorageValue<_, IntentId, ValueQuery>;
+
+	// The intents that can still be reported as fraudulent.
+	#[pallet::storage]
+	#[pallet::getter(fn intent)]
+	pub type Intents<T: Config> =
+		StorageMap<_, Twox64Concat, IntentId, Intent<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+
+	// The oldest intent that may still be disputed.
+	#[pallet::storage]
+	#[pallet::getter(fn intent_expiry_cursor)]
+	pub type IntentExpiryCursor<T> = StorageValue<_, IntentId, ValueQuery>;
+
+	// Pallets use events to inform users when important changes are made.
+	// https://docs.substrate.io/main-docs/build/events-errors/
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A gateway was whitelisted and bonded. [gateway, bond]
+		GatewayRegistered(T::AccountId, BalanceOf<T>),
+		/// A gateway was removed from the whitelist; its bond unlocks in a block. [gateway, block]
+		GatewayRemoved(T::AccountId, T::BlockNumber),
+		/// A removed gateway withdrew its bond. [gateway, bond]
+		BondWithdrawn(T::AccountId, BalanceOf<T>),
+		/// A phone got its account with its first intent. [phone_hash, account]
+		PhoneAccountCreated([u8; 32], T::AccountId),
+		/// A gateway contributed a phone's balance to the pool.
+		/// [intent, gateway, phone_hash, amount]
+		ProxiedContribution(IntentId, T::AccountId, [u8; 32], BalanceOf<T>),
+		/// An intent was ruled fraudulent and its gateway's bond paid to the phone's account.
+		/// [intent, gateway, slashed]
+		GatewaySlashed(IntentId, T::AccountId, BalanceOf<T>),
+		/// A phone's intents have to be signed with a new key. [phone_hash, key]
+		PhoneKeyRegistered([u8; 32], T::AccountId),
+	}
+
+	// Errors inform users that something went wrong.
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already a gateway, or still unbonding.
+		AlreadyGateway,
+		/// The account isn't a whitelisted gateway.
+		NotGateway,
+		/// The gateway's bond can't be withdrawn yet.
+		StillBonded,
+		/// The intent doesn't use the phone's next nonce.
+		InvalidNonce,
+		/// No key is registered for the phone.
+		NoPhoneKey,
+		/// The intent isn't signed with the phone's key.
+		InvalidSignature,
+		/// There's no intent that can still be disputed with this id.
+		UnknownIntent,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			Self::expire_intents(now, remaining_weight)
+		}
+	}
+
+	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
+	// These functions materialize as "extrinsics", which are often compared to transactions.
+	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Whitelist `gateway`, reserving `GatewayBond` of its balance.
+		#[pallet::weight(T::WeightInfo::register_gateway())]
+		pub fn register_gateway(origin: OriginFor<T>, gateway: T::AccountId) -> DispatchResult {
+			T::GatewayOrigin::ensure_origin(origin)?;
+			ensure!(!<Gateways<T>>::contains_key(&gateway), Error::<T>::AlreadyGateway);
+
+			let bond = T::GatewayBond::get();
+			T::Currency::reserve(&gateway, bond)?;
+			<Gateways<T>>::insert(&gateway, GatewayInfo { bond, unbonding_at: None });
+			Self::deposit_event(Event::GatewayRegistered(gateway, bond));
+			Ok(())
+		}
+
+		/// Remove `gateway` from the whitelist. Its bond stays reserved for `DisputePeriod`
+		/// blocks, so that the intents it submitted can still be disputed.
+		#[pallet::weight(T::WeightInfo::remove_gateway())]
+		pub fn remove_gateway(origin: OriginFor<T>, gateway: T::AccountId) -> DispatchResult {
+			T::GatewayOrigin::ensure_origin(origin)?;
+			let mut info = Self::active_gateway(&gateway)?;
+
+			let unbonding_at =
+				<frame_system::Pallet<T>>::block_number().saturating_add(T::DisputePeriod::get());
+			info.unbonding_at = Some(unbonding_at);
+			<Gateways<T>>::insert(&gateway, info);
+			Self::deposit_event(Event::GatewayRemoved(gateway, unbonding_at));
+			Ok(())
+		}
+
+		/// Unreserve the caller's bond, once it was removed from the whitelist and its
+		/// `DisputePeriod` passed.
+		#[pallet::weight(T::WeightInfo::withdraw_bond())]
+		pub fn withdraw_bond(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let info = Self::gateway(&who).ok_or(Error::<T>::NotGateway)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(info.unbonding_at.map_or(false, |at| at <= now), Error::<T>::StillBonded);
+
+			T::Currency::unreserve(&who, info.bond);
+			<Gateways<T>>::remove(&who);
+			Self::deposit_event(Event::BondWithdrawn(who, info.bond));
+			Ok(())
+		}
+
+		/// Contribute `amount` of the balance of the phone with `phone_hash` to the pool, as the
+		/// phone's user asked the calling gateway to. `nonce` has to be the phone's next one, and
+		/// `gateway_sig` the signature of `(phone_hash, amount, nonce)` by the phone's key.
+		#[pallet::weight(T::WeightInfo::proxy_contribute())]
+		pub fn proxy_contribute(
+			origin: OriginFor<T>,
+			phone_hash: [u8; 32],
+			amount: BalanceOf<T>,
+			nonce: u64,
+			gateway_sig: T::Signature,
+		) -> DispatchResult {
+			let gateway = ensure_signed(origin)?;
+			Self::active_gateway(&gateway)?;
+			ensure!(nonce == Self::phone_nonce(phone_hash), Error::<T>::InvalidNonce);
+			let key = Self::phone_key(phone_hash).ok_or(Error::<T>::NoPhoneKey)?;
+			let signed = (phone_hash, amount, nonce).encode();
+			ensure!(gateway_sig.verify(&signed[..], &key), Error::<T>::InvalidSignature);
+
+			let who = Self::phone_account(&phone_hash);
+			if !<PhoneAccounts<T>>::contains_key(phone_hash) {
+				<PhoneAccounts<T>>::insert(phone_hash, &who);
+				Self::deposit_event(Event::PhoneAccountCreated(phone_hash, who.clone()));
+			}
+			T::Pool::contribute(&who, amount)?;
+			<PhoneNonces<T>>::insert(phone_hash, nonce + 1);
+
+			let id = Self::next_intent_id();
+			let submitted_at = <frame_system::Pallet<T>>::block_number();
+			<Intents<T>>::insert(
+				id,
+				Intent { gateway: gateway.clone(), phone_hash, nonce, amount, submitted_at },
+			);
+			<NextIntentId<T>>::put(id + 1);
+			Self::deposit_event(Event::ProxiedContribution(id, gateway, phone_hash, amount));
+			Ok(())
+		}
+
+		/// Rule an intent fraudulent: its gateway's bond is paid to the phone's account and the
+		/// gateway is dropped from the whitelist.
+		#[pallet::weight(T::WeightInfo::report_fraud())]
+		pub fn report_fraud(origin: OriginFor<T>, intent: IntentId) -> DispatchResult {
+			T::FraudOrigin::ensure_origin(origin)?;
+			let Intent { gateway, phone_hash, .. } =
+				Self::intent(intent).ok_or(Error::<T>::UnknownIntent)?;
+			<Intents<T>>::remove(intent);
+
+			// the gateway may have been slashed for another intent already
+			let slashed = match <Gateways<T>>::take(&gateway) {
+				Some(info) => {
+					let who = Self::phone_account(&phone_hash);
+					let unpaid = T::Currency::repatriate_reserved(
+						&gateway,
+						&who,
+						info.bond,
+						BalanceStatus::Free,
+					)?;
+					T::Currency::unreserve(&gateway, unpaid);
+					info.bond.saturating_sub(unpaid)
+				},
+				None => Zero::zero(),
+			};
+			Self::deposit_event(Event::GatewaySlashed(intent, gateway, slashed));
+			Ok(())
+		}
+
+		/// Require the intents of the phone with `phone_hash` to be signed with `key` from now
+		/// on, e.g. once its user enrolled the key with a gateway's operator. Replaces the
+		/// phone's previous key, if any.
+		#[pallet::weight(T::WeightInfo::register_phone_key())]
+		pub fn register_phone_key(
+			origin: OriginFor<T>,
+			phone_hash: [u8; 32],
+			key: T::AccountId,
+		) -> DispatchResult {
+			T::GatewayOrigin::ensure_origin(origin)?;
+			<PhoneKeys<T>>::insert(phone_hash, &key);
+			Self::deposit_event(Event::PhoneKeyRegistered(phone_hash, key));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The account of the phone with `phone_hash`, the same on every chain using the pallet's
+		/// `PalletId`.
+		pub fn phone_account(phone_hash: &[u8; 32]) -> T::AccountId {
+			let entropy =
+				(T::PalletId::get(), phone_hash).using_encoded(sp_io::hashing::blake2_256);
+			Decode::decode(&mut TrailingZeroInput::new(entropy.as_ref()))
+				.expect("infinite length input; no invalid inputs for type; qed")
+		}
+
+		// the bond of `who`, if it's a whitelisted gateway
+		fn active_gateway(
+			who: &T::AccountId,
+		) -> Result<GatewayInfo<BalanceOf<T>, T::BlockNumber>, DispatchError> {
+			match Self::gateway(who) {
+				Some(info) if info.unbonding_at.is_none() => Ok(info),
+				_ => Err(Error::<T>::NotGateway.into()),
+			}
+		}
+
+		// drop the intents past their dispute period, in the order they were submitted, as far
+		// as `remaining_weight` allows
+		fn expire_intents(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let per_intent = 10_000 + T::DbWeight::get().reads_writes(1, 1);
+			let mut used = T::DbWeight::get().reads_writes(2, 1);
+			if used.saturating_add(per_intent) > remaining_weight {
+				return 0;
+			}
+			let start = Self::intent_expiry_cursor();
+			let end = Self::next_intent_id();
+			let mut cursor = start;
+			while cursor < end && used.saturating_add(per_intent) <= remaining_weight {
+				used = used.saturating_add(per_intent);
+				if let Some(intent) = Self::intent(cursor) {
+					if intent.submitted_at.saturating_add(T::DisputePeriod::get()) > now {
+						break;
+					}
+					<Intents<T>>::remove(cursor);
+				}
+				cursor += 1;
+			}
+
+			if cursor != start {
+				<IntentExpiryCursor<T>>::put(cursor);
+			}
+			used
+		}
+	}
+}
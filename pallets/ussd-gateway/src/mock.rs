@@ -0,0 +1,137 @@
+use crate as pallet_ussd_gateway;
+use crate::LiquidityPool;
+use frame_support::{
+	dispatch::DispatchResult,
+	ord_parameter_types, parameter_types,
+	traits::{ConstU16, ConstU32, ConstU64, Currency, ExistenceRequirement},
+	PalletId,
+};
+use frame_system as system;
+use frame_system::{EnsureRoot, EnsureSignedBy};
+use sp_core::H256;
+use sp_runtime::{
+	testing::{Header, TestSignature, UintAuthorityId},
+	traits::{BlakeTwo256, IdentityLookup},
+};
+use std::cell::RefCell;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		UssdGateway: pallet_ussd_gateway,
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const UssdPalletId: PalletId = PalletId(*b"py/ussdg");
+}
+
+ord_parameter_types! {
+	pub const Arbiter: u64 = 7;
+	pub const PoolAccount: u64 = 9;
+}
+
+thread_local! {
+	// the contributions paid into `TestPool`, with their contributors
+	pub static CONTRIBUTIONS: RefCell<Vec<(u64, u64)>> = RefCell::new(vec![]);
+}
+
+/// Pays contributions to `PoolAccount`, keeping them in `CONTRIBUTIONS`.
+pub struct TestPool;
+
+impl LiquidityPool<u64, u64> for TestPool {
+	fn contribute(who: &u64, amount: u64) -> DispatchResult {
+		let pool = PoolAccount::get();
+		<Balances as Currency<u64>>::transfer(who, &pool, amount, ExistenceRequirement::KeepAlive)?;
+		CONTRIBUTIONS.with(|contributions| contributions.borrow_mut().push((*who, amount)));
+		Ok(())
+	}
+}
+
+/// Signs benchmarked intents with the test key `42`.
+#[cfg(feature = "runtime-benchmarks")]
+pub struct TestSigner;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_ussd_gateway::BenchmarkHelper<u64, TestSignature> for TestSigner {
+	fn sign(message: &[u8]) -> (u64, TestSignature) {
+		(42, TestSignature(42, message.to_vec()))
+	}
+}
+
+impl pallet_ussd_gateway::Config for Test {
+	type Event = Event;
+	type Currency = Balances;
+	type PalletId = UssdPalletId;
+	type Signer = UintAuthorityId;
+	type Signature = TestSignature;
+	type Pool = TestPool;
+	type GatewayOrigin = EnsureRoot<u64>;
+	type FraudOrigin = EnsureSignedBy<Arbiter, u64>;
+	type GatewayBond = ConstU64<500>;
+	type DisputePeriod = ConstU64<10>;
+	type WeightInfo = ();
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = TestSigner;
+}
+
+pub const INITIAL_BALANCE: u64 = 1_000_000;
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: (1..=3).map(|who| (who, INITIAL_BALANCE)).collect(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	t.into()
+}
@@ -0,0 +1,181 @@
+use crate::{mock::*, Error, Event as GatewayEvent};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, Hooks, ReservableCurrency},
+	weights::Weight,
+};
+use sp_runtime::{testing::TestSignature, DispatchError};
+
+const PHONE: [u8; 32] = [7; 32];
+
+// the key registered for `PHONE`
+const PHONE_KEY: u64 = 42;
+
+// `key`'s signature of an intent
+fn sign(key: u64, phone_hash: [u8; 32], amount: u64, nonce: u64) -> TestSignature {
+	TestSignature(key, (phone_hash, amount, nonce).encode())
+}
+
+// contribute `amount` of `PHONE`'s balance through `gateway`
+fn proxy_contribute(gateway: u64, amount: u64, nonce: u64) -> Result<(), DispatchError> {
+	let sig = sign(PHONE_KEY, PHONE, amount, nonce);
+	UssdGateway::proxy_contribute(Origin::signed(gateway), PHONE, amount, nonce, sig)
+}
+
+// whitelist gateway 1, register `PHONE`'s key and fund its account
+fn setup() -> u64 {
+	System::set_block_number(1);
+	assert_ok!(UssdGateway::register_gateway(Origin::root(), 1));
+	assert_ok!(UssdGateway::register_phone_key(Origin::root(), PHONE, PHONE_KEY));
+	let phone = UssdGateway::phone_account(&PHONE);
+	Balances::make_free_balance_be(&phone, 1_000);
+	phone
+}
+
+#[test]
+fn gateways_bond_until_their_dispute_period_passes() {
+	new_test_ext().execute_with(|| {
+		setup();
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::GatewayRegistered(1, 500)));
+		assert_eq!(Balances::reserved_balance(1), 500);
+		assert_noop!(
+			UssdGateway::register_gateway(Origin::root(), 1),
+			Error::<Test>::AlreadyGateway
+		);
+		assert_noop!(UssdGateway::register_gateway(Origin::signed(2), 2), DispatchError::BadOrigin);
+		assert_noop!(UssdGateway::withdraw_bond(Origin::signed(1)), Error::<Test>::StillBonded);
+
+		assert_ok!(UssdGateway::remove_gateway(Origin::root(), 1));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::GatewayRemoved(1, 11)));
+		assert_noop!(proxy_contribute(1, 100, 0), Error::<Test>::NotGateway);
+		assert_noop!(UssdGateway::remove_gateway(Origin::root(), 1), Error::<Test>::NotGateway);
+		assert_noop!(UssdGateway::withdraw_bond(Origin::signed(1)), Error::<Test>::StillBonded);
+
+		System::set_block_number(11);
+		assert_ok!(UssdGateway::withdraw_bond(Origin::signed(1)));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::BondWithdrawn(1, 500)));
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_eq!(UssdGateway::gateway(1), None);
+	});
+}
+
+#[test]
+fn gateways_contribute_for_phones_in_nonce_order() {
+	new_test_ext().execute_with(|| {
+		let phone = setup();
+		assert_ne!(phone, UssdGateway::phone_account(&[8; 32]));
+
+		assert_ok!(proxy_contribute(1, 100, 0));
+		System::assert_has_event(Event::UssdGateway(GatewayEvent::PhoneAccountCreated(
+			PHONE, phone,
+		)));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::ProxiedContribution(
+			0, 1, PHONE, 100,
+		)));
+		assert_eq!(UssdGateway::phone_account_of(PHONE), Some(phone));
+		assert_eq!(UssdGateway::phone_nonce(PHONE), 1);
+		assert_eq!(UssdGateway::intent(0).map(|intent| intent.gateway), Some(1));
+		assert_eq!(CONTRIBUTIONS.with(|c| c.borrow().clone()), vec![(phone, 100)]);
+
+		// replayed, signed with another key, not signed for this amount, or submitted by an
+		// account that isn't a gateway
+		assert_noop!(proxy_contribute(1, 100, 0), Error::<Test>::InvalidNonce);
+		let sig = sign(1, PHONE, 100, 1);
+		assert_noop!(
+			UssdGateway::proxy_contribute(Origin::signed(1), PHONE, 100, 1, sig),
+			Error::<Test>::InvalidSignature
+		);
+		let sig = sign(PHONE_KEY, PHONE, 100, 1);
+		assert_noop!(
+			UssdGateway::proxy_contribute(Origin::signed(1), PHONE, 200, 1, sig),
+			Error::<Test>::InvalidSignature
+		);
+		assert_noop!(proxy_contribute(2, 100, 1), Error::<Test>::NotGateway);
+
+		// a failed contribution doesn't use up the nonce
+		assert!(proxy_contribute(1, 10_000, 1).is_err());
+		assert_eq!(UssdGateway::phone_nonce(PHONE), 1);
+		assert_ok!(proxy_contribute(1, 200, 1));
+		assert_eq!(Balances::free_balance(PoolAccount::get()), 300);
+		assert_eq!(UssdGateway::next_intent_id(), 2);
+	});
+}
+
+#[test]
+fn fraudulent_intents_slash_their_gateway() {
+	new_test_ext().execute_with(|| {
+		let phone = setup();
+		assert_ok!(proxy_contribute(1, 100, 0));
+		assert_ok!(proxy_contribute(1, 100, 1));
+
+		assert_noop!(UssdGateway::report_fraud(Origin::signed(1), 0), DispatchError::BadOrigin);
+		assert_ok!(UssdGateway::report_fraud(Origin::signed(Arbiter::get()), 0));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::GatewaySlashed(0, 1, 500)));
+		assert_eq!(Balances::free_balance(phone), 1_300);
+		assert_eq!(Balances::total_balance(&1), INITIAL_BALANCE - 500);
+		assert_eq!(UssdGateway::gateway(1), None);
+		assert_noop!(proxy_contribute(1, 100, 2), Error::<Test>::NotGateway);
+		assert_noop!(
+			UssdGateway::report_fraud(Origin::signed(Arbiter::get()), 0),
+			Error::<Test>::UnknownIntent
+		);
+
+		// the gateway's bond was already paid out
+		assert_ok!(UssdGateway::report_fraud(Origin::signed(Arbiter::get()), 1));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::GatewaySlashed(1, 1, 0)));
+		assert_eq!(Balances::free_balance(phone), 1_300);
+	});
+}
+
+#[test]
+fn intents_can_only_be_disputed_in_their_dispute_period() {
+	new_test_ext().execute_with(|| {
+		setup();
+		assert_ok!(proxy_contribute(1, 100, 0));
+		System::set_block_number(5);
+		assert_ok!(proxy_contribute(1, 100, 1));
+
+		UssdGateway::on_idle(10, Weight::MAX);
+		assert_eq!(UssdGateway::intent_expiry_cursor(), 0);
+		assert_eq!(UssdGateway::on_idle(11, 0), 0);
+		assert!(UssdGateway::intent(0).is_some());
+
+		UssdGateway::on_idle(11, Weight::MAX);
+		assert_eq!(UssdGateway::intent(0), None);
+		assert!(UssdGateway::intent(1).is_some());
+		assert_eq!(UssdGateway::intent_expiry_cursor(), 1);
+		assert_noop!(
+			UssdGateway::report_fraud(Origin::signed(Arbiter::get()), 0),
+			Error::<Test>::UnknownIntent
+		);
+		assert!(Balances::reserved_balance(1) > 0);
+	});
+}
+
+#[test]
+fn phones_sign_their_intents_with_their_registered_key() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(UssdGateway::register_gateway(Origin::root(), 1));
+		let phone = UssdGateway::phone_account(&PHONE);
+		Balances::make_free_balance_be(&phone, 1_000);
+		assert_noop!(proxy_contribute(1, 100, 0), Error::<Test>::NoPhoneKey);
+
+		assert_noop!(
+			UssdGateway::register_phone_key(Origin::signed(1), PHONE, PHONE_KEY),
+			DispatchError::BadOrigin
+		);
+		assert_ok!(UssdGateway::register_phone_key(Origin::root(), PHONE, PHONE_KEY));
+		System::assert_last_event(Event::UssdGateway(GatewayEvent::PhoneKeyRegistered(
+			PHONE, PHONE_KEY,
+		)));
+		assert_ok!(proxy_contribute(1, 100, 0));
+
+		// a replaced key can't sign for the phone any more
+		assert_ok!(UssdGateway::register_phone_key(Origin::root(), PHONE, 43));
+		assert_noop!(proxy_contribute(1, 100, 1), Error::<Test>::InvalidSignature);
+		let sig = sign(43, PHONE, 100, 1);
+		assert_ok!(UssdGateway::proxy_contribute(Origin::signed(1), PHONE, 100, 1, sig));
+	});
+}
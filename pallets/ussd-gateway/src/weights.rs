@@ -0,0 +1,96 @@
+// Weights for the USSD gateway pallet.
+//
+// The pallet has not been benchmarked on reference hardware yet. The values below are the weights
+// the extrinsics were charged before `WeightInfo` existed, plus their database accesses;
+// `proxy_contribute`'s include the pool's contribution and the phone's key, and
+// `register_phone_key` is charged like the other whitelisting calls. Replace them with the
+// output of
+//
+//     node-template benchmark pallet --chain dev --pallet pallet_ussd_gateway --extrinsic '*' \
+//         --steps 50 --repeat 20 --output pallets/ussd-gateway/src/weights.rs
+//
+// from a node built with `runtime-benchmarks`.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_ussd_gateway.
+pub trait WeightInfo {
+	fn register_gateway() -> Weight;
+	fn remove_gateway() -> Weight;
+	fn withdraw_bond() -> Weight;
+	fn proxy_contribute() -> Weight;
+	fn report_fraud() -> Weight;
+	fn register_phone_key() -> Weight;
+}
+
+/// Weights for pallet_ussd_gateway using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn register_gateway() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn remove_gateway() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn withdraw_bond() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+	}
+	fn proxy_contribute() -> Weight {
+		(20_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(18 as Weight))
+			.saturating_add(T::DbWeight::get().writes(17 as Weight))
+	}
+	fn report_fraud() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+	}
+	fn register_phone_key() -> Weight {
+		(10_000 as Weight).saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn register_gateway() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn remove_gateway() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn withdraw_bond() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+	}
+	fn proxy_contribute() -> Weight {
+		(20_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(18 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(17 as Weight))
+	}
+	fn report_fraud() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+	}
+	fn register_phone_key() -> Weight {
+		(10_000 as Weight).saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+}
@@ -0,0 +1,32 @@
+//! Benchmarking setup for pallet-webhooks
+
+use super::*;
+
+#[allow(unused)]
+use crate::Pallet as Webhooks;
+use frame_benchmarking::{benchmarks, whitelisted_caller};
+use frame_support::traits::Get;
+use frame_system::RawOrigin;
+
+benchmarks! {
+	register_webhook {
+		let n in 9 .. T::MaxUrlLength::get();
+		let caller: T::AccountId = whitelisted_caller();
+		let mut url = b"https://".to_vec();
+		url.resize(n as usize, b'a');
+	}: _(RawOrigin::Signed(caller.clone()), url.clone())
+	verify {
+		assert_eq!(Webhooks::<T>::webhook(&caller), Some(sp_io::hashing::blake2_256(&url)));
+	}
+
+	remove_webhook {
+		let caller: T::AccountId = whitelisted_caller();
+		let url = b"https://integrator.example/hooks".to_vec();
+		Webhooks::<T>::register_webhook(RawOrigin::Signed(caller.clone()).into(), url).unwrap();
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(Webhooks::<T>::webhook(&caller).is_none());
+	}
+
+	impl_benchmark_test_suite!(Webhooks, crate::mock::new_test_ext(), crate::mock::Test);
+}
@@ -0,0 +1,430 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Webhooks pushing the outcomes of accounts' asynchronous operations to the accounts' servers,
+/// so that integrators learn of their settlements and IPFS completions without indexing the
+/// chain. An account registers an HTTPS callback URL; only its hash goes on-chain, while the URL
+/// itself reaches the nodes' off-chain storage through offchain indexing, so delivering nodes have
+/// to run with `--enable-offchain-indexing true`. The pallets running the operations notify this
+/// one through the `WebhookNotifier` trait, and every node holding a webhook key POSTs the block's
+/// notifications to the accounts' URLs from its offchain worker, signed with that key, retrying
+/// failed deliveries with backoff. Receivers may get a notification from several nodes, and
+/// dedupe them by their block and index.
+pub use pallet::*;
+
+use codec::{Decode, Encode};
+use sp_core::crypto::KeyTypeId;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, SaturatedConversion},
+	RuntimeDebug,
+};
+use sp_std::{marker::PhantomData, vec::Vec};
+
+pub mod weights;
+
+pub use mpesa_primitives::{AsyncOp, WebhookNotifier};
+use pallet_ocw_jobs::{JobHandler, Jobs, Outcome};
+pub use weights::WeightInfo;
+
+/// The key type of the keys the offchain worker signs its notifications with.
+pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"hook");
+
+/// The crypto used by the offchain worker to sign its notifications.
+pub mod crypto {
+	use super::KEY_TYPE;
+	use sp_core::sr25519::Signature as Sr25519Signature;
+	use sp_runtime::{
+		app_crypto::{app_crypto, sr25519},
+		traits::Verify,
+		MultiSignature, MultiSigner,
+	};
+
+	app_crypto!(sr25519, KEY_TYPE);
+
+	/// The identifier used to look up the worker's signing key in the keystore.
+	pub struct OcwAuthId;
+
+	impl frame_system::offchain::AppCrypto<MultiSigner, MultiSignature> for OcwAuthId {
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+
+	impl frame_system::offchain::AppCrypto<<Sr25519Signature as Verify>::Signer, Sr25519Signature>
+		for OcwAuthId
+	{
+		type RuntimeAppPublic = Public;
+		type GenericSignature = sp_core::sr25519::Signature;
+		type GenericPublic = sp_core::sr25519::Public;
+	}
+}
+
+/// The prefix of the off-chain storage keys of the registered URLs, followed by the
+/// SCALE-encoded account.
+pub const URL_PREFIX: &[u8] = b"webhooks::url::";
+
+// The node-local queue of the deliveries that failed, waiting to be retried.
+const RETRY_QUEUE: &[u8] = b"webhooks::retries";
+
+// The number of times a failed delivery is retried before it's given up on.
+const MAX_RETRIES: u32 = 5;
+
+// The most failed deliveries an offchain worker retries per run.
+const RETRY_BATCH: usize = 8;
+
+// How long a single delivery may take.
+const TIMEOUT_MS: u64 = 5_000;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::{
+		offchain::{AppCrypto, SignMessage, Signer, SigningTypes},
+		pallet_prelude::*,
+	};
+	use sp_core::offchain::Duration;
+	use sp_runtime::offchain::{http, storage::StorageValueRef};
+	use sp_std::{str, vec};
+
+	/// The outcome of an account's asynchronous operation, to push to the account's webhook.
+	#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+	pub struct Notification<AccountId, Hash> {
+		/// The account the operation is for.
+		pub who: AccountId,
+		/// The operation.
+		pub op: AsyncOp<Hash>,
+		/// The hash of the operation's result, or the error it failed with.
+		pub outcome: Result<Hash, DispatchError>,
+	}
+
+	/// Why a delivery failed.
+	#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+	pub enum DeliveryError {
+		/// The node's off-chain storage lacks the account's URL, or holds an outdated one.
+		UnknownUrl,
+		/// The keystore holds no webhook key to sign the notification with.
+		NoKey,
+		/// The request couldn't be sent or timed out.
+		Http,
+		/// The webhook answered with a status other than 2xx.
+		Rejected(u16),
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// Configure the pallet by specifying the parameters and types on which it depends.
+	#[pallet::config]
+	pub trait Config: frame_system::Config + SigningTypes {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+		/// The identifier type for the offchain worker's signing key.
+		type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+		/// The longest callback URL an account can register.
+		#[pallet::constant]
+		type MaxUrlLength: Get<u32>;
+		/// The most notifications pushed per block; the rest are dropped.
+		#[pallet::constant]
+		type MaxNotifications: Get<u32>;
+		/// Weight information for the pallet's extrinsics.
+		type WeightInfo: WeightInfo;
+	}
+
+	// The blake2-256 hash of each account's callback URL.
+	#[pallet::storage]
+	#[pallet::getter(fn webhook)]
+	pub type Webhooks<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, [u8; 32]>;
+
+	// The notifications of the current block, which the offchain workers deliver; cleared at the
+	// start of the next one.
+	#[pallet::storage]
+	#[pallet::getter(fn notifications)]
+	pub type Notifications<T: Config> = StorageValue<
+		_,
+		BoundedVec<Notification<T::AccountId, T::Hash>, T::MaxNotifications>,
+		ValueQuery,
+	>;
+
+	// Pallets use events to inform users when important changes are made.
+	// https://docs.substrate.io/main-docs/build/events-errors/
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An account registered a callback URL. [who, url_hash]
+		WebhookRegistered(T::AccountId, [u8; 32]),
+		/// An account removed its callback URL. [who]
+		WebhookRemoved(T::AccountId),
+		/// A block had more notifications than are pushed per block. [who, op]
+		NotificationDropped(T::AccountId, AsyncOp<T::Hash>),
+	}
+
+	// Errors inform users that something went wrong.
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The URL isn't an `https://` one.
+		InsecureUrl,
+		/// The URL is longer than `MaxUrlLength`.
+		UrlTooLong,
+		/// The account has no callback URL.
+		NoWebhook,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(_: T::BlockNumber) -> Weight {
+			<Notifications<T>>::kill();
+			T::DbWeight::get().writes(1)
+		}
+
+		fn offchain_worker(block_number: T::BlockNumber) {
+			if !Signer::<T, T::AuthorityId>::any_account().can_sign() {
+				return;
+			}
+			Jobs::<Deliveries<T>>::run_retries(&mut (), block_number, RETRY_BATCH);
+			let deliveries = Self::notifications()
+				.into_iter()
+				.enumerate()
+				.map(|(index, notification)| Delivery {
+					block_number,
+					index: index as u32,
+					notification,
+				})
+				.collect();
+			Jobs::<Deliveries<T>>::process(&mut (), deliveries, block_number);
+		}
+	}
+
+	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
+	// These functions materialize as "extrinsics", which are often compared to transactions.
+	// Dispatchable functions must be annotated with a weight and must return a DispatchResult.
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Push the outcomes of the caller's operations to `url`, replacing its previous URL, if
+		/// any. Only the URL's hash is stored on-chain; the URL is indexed off-chain.
+		#[pallet::weight(T::WeightInfo::register_webhook(url.len() as u32))]
+		pub fn register_webhook(origin: OriginFor<T>, url: Vec<u8>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(url.len() as u32 <= T::MaxUrlLength::get(), Error::<T>::UrlTooLong);
+			ensure!(url.starts_with(b"https://"), Error::<T>::InsecureUrl);
+
+			let url_hash = sp_io::hashing::blake2_256(&url);
+			sp_io::offchain_index::set(&url_key(&who), &url.encode());
+			<Webhooks<T>>::insert(&who, url_hash);
+			Self::deposit_event(Event::WebhookRegistered(who, url_hash));
+			Ok(())
+		}
+
+		/// Stop pushing the outcomes of the caller's operations.
+		#[pallet::weight(T::WeightInfo::remove_webhook())]
+		pub fn remove_webhook(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			<Webhooks<T>>::take(&who).ok_or(Error::<T>::NoWebhook)?;
+
+			sp_io::offchain_index::clear(&url_key(&who));
+			Self::deposit_event(Event::WebhookRemoved(who));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		// the URL `who` registered, from the node's off-chain storage, if it is the one whose
+		// hash is on-chain
+		fn url_of(who: &T::AccountId) -> Option<Vec<u8>> {
+			let url_hash = Self::webhook(who)?;
+			let url = StorageValueRef::persistent(&url_key(who)).get::<Vec<u8>>().ok().flatten()?;
+			(sp_io::hashing::blake2_256(&url) == url_hash).then(|| url)
+		}
+
+		// POST a notification to its account's webhook, signed with the node's webhook key
+		fn deliver(
+			delivery: &Delivery<T::AccountId, T::Hash, T::BlockNumber>,
+		) -> Result<(), DeliveryError> {
+			let url = Self::url_of(&delivery.notification.who).ok_or(DeliveryError::UnknownUrl)?;
+			let url = str::from_utf8(&url).map_err(|_| DeliveryError::UnknownUrl)?;
+			let body = body(delivery);
+			let (account, signature) = Signer::<T, T::AuthorityId>::any_account()
+				.sign_message(&body)
+				.ok_or(DeliveryError::NoKey)?;
+			let signer = hex(&account.public.encode());
+			let signature = hex(&signature.encode());
+
+			let deadline = sp_io::offchain::timestamp().add(Duration::from_millis(TIMEOUT_MS));
+			let pending = http::Request::post(url, vec![body])
+				.add_header("Content-Type", "application/json")
+				.add_header("X-Webhook-Signer", str::from_utf8(&signer).unwrap_or_default())
+				.add_header("X-Webhook-Signature", str::from_utf8(&signature).unwrap_or_default())
+				.deadline(deadline)
+				.send()
+				.map_err(|_| DeliveryError::Http)?;
+			let response = pending
+				.try_wait(deadline)
+				.map_err(|_| DeliveryError::Http)?
+				.map_err(|_| DeliveryError::Http)?;
+			if !(200..300).contains(&response.code) {
+				return Err(DeliveryError::Rejected(response.code));
+			}
+			Ok(())
+		}
+	}
+
+	impl<T: Config> WebhookNotifier<T::AccountId, T::Hash> for Pallet<T> {
+		fn notify(
+			who: &T::AccountId,
+			op: AsyncOp<T::Hash>,
+			outcome: Result<T::Hash, DispatchError>,
+		) {
+			if !<Webhooks<T>>::contains_key(who) {
+				return;
+			}
+			let notification = Notification { who: who.clone(), op: op.clone(), outcome };
+			if <Notifications<T>>::try_append(notification).is_err() {
+				Self::deposit_event(Event::NotificationDropped(who.clone(), op));
+			}
+		}
+	}
+
+	// Delivers the notifications to the accounts' webhooks, retrying the failed deliveries.
+	struct Deliveries<T>(PhantomData<T>);
+
+	impl<T: Config> JobHandler for Deliveries<T> {
+		type BlockNumber = T::BlockNumber;
+		type Job = Delivery<T::AccountId, T::Hash, T::BlockNumber>;
+		type Output = ();
+		type Error = DeliveryError;
+		type Context = ();
+
+		const QUEUE: &'static [u8] = RETRY_QUEUE;
+
+		fn max_retries() -> u32 {
+			MAX_RETRIES
+		}
+
+		// the account removed its webhook
+		fn is_stale(delivery: &Self::Job) -> bool {
+			!<Webhooks<T>>::contains_key(&delivery.notification.who)
+		}
+
+		fn process(
+			_: &mut (),
+			delivery: &Self::Job,
+			_: Option<&DeliveryError>,
+		) -> Result<(), DeliveryError> {
+			Pallet::<T>::deliver(delivery)
+		}
+
+		fn on_complete(_: &mut (), outcomes: Vec<Outcome<Self::Job, (), DeliveryError>>) {
+			for outcome in outcomes {
+				if let Outcome::Failed { job, attempts, error, .. } = outcome {
+					log::warn!(
+						"Webhooks: attempt {} at notification {} of block {:?} failed: {:?}",
+						attempts,
+						job.index,
+						job.block_number,
+						error
+					);
+				}
+			}
+		}
+
+		fn on_dead_letter(delivery: Self::Job, attempts: u32, error: DeliveryError) {
+			log::error!(
+				"Webhooks: gave up on notification {} of block {:?} after {} attempts: {:?}",
+				delivery.index,
+				delivery.block_number,
+				attempts,
+				error
+			);
+		}
+	}
+}
+
+/// A notification on its way to its account's webhook.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct Delivery<AccountId, Hash, BlockNumber> {
+	/// The block the notification was made in.
+	pub block_number: BlockNumber,
+	/// The notification's index in the block, which with the block identifies it.
+	pub index: u32,
+	/// The notification.
+	pub notification: Notification<AccountId, Hash>,
+}
+
+// the off-chain storage key of `who`'s URL
+fn url_key<AccountId: Encode>(who: &AccountId) -> Vec<u8> {
+	let mut key = URL_PREFIX.to_vec();
+	key.extend(who.encode());
+	key
+}
+
+// the JSON body of a notification: the account, the notification's block and index, the kind and
+// id of the operation, and its `result` hash or SCALE-encoded `error`, in hex
+fn body<AccountId: Encode, Hash: Encode, BlockNumber: AtLeast32BitUnsigned + Copy>(
+	delivery: &Delivery<AccountId, Hash, BlockNumber>,
+) -> Vec<u8> {
+	let Notification { who, op, outcome } = &delivery.notification;
+	let (kind, id) = match op {
+		AsyncOp::IpfsRequest(id) => (&b"ipfs_request"[..], decimal(*id)),
+		AsyncOp::DhtQuery(hash) => (&b"dht_query"[..], hex(&hash.encode())),
+		AsyncOp::MpesaDeposit(id) => (&b"mpesa_deposit"[..], decimal(*id)),
+		AsyncOp::MpesaWithdrawal(id) => (&b"mpesa_withdrawal"[..], decimal(*id)),
+	};
+	let (status, key, value) = match outcome {
+		Ok(result) => (&b"completed"[..], &b"result"[..], hex(&result.encode())),
+		Err(error) => (&b"failed"[..], &b"error"[..], hex(&error.encode())),
+	};
+	let members: [(&[u8], &[u8]); 4] =
+		[(b"op", kind), (b"id", &id), (b"status", status), (key, &value)];
+
+	let mut body = b"{\"account\":\"".to_vec();
+	body.extend(hex(&who.encode()));
+	body.extend(b"\",\"block\":");
+	body.extend(decimal(delivery.block_number.saturated_into()));
+	body.extend(b",\"index\":");
+	body.extend(decimal(delivery.index.into()));
+	for (name, value) in members {
+		body.extend(b",\"");
+		body.extend(name);
+		body.extend(b"\":\"");
+		body.extend(value);
+		body.push(b'"');
+	}
+	body.push(b'}');
+	body
+}
+
+// `0x` followed by the lowercase hex digits of `bytes`
+fn hex(bytes: &[u8]) -> Vec<u8> {
+	const DIGITS: &[u8; 16] = b"0123456789abcdef";
+	let mut out = b"0x".to_vec();
+	for byte in bytes {
+		out.push(DIGITS[(byte >> 4) as usize]);
+		out.push(DIGITS[(byte & 0xf) as usize]);
+	}
+	out
+}
+
+// the decimal digits of `n`
+fn decimal(n: u64) -> Vec<u8> {
+	let mut digits = Vec::new();
+	let mut n = n;
+	loop {
+		digits.push(b'0' + (n % 10) as u8);
+		n /= 10;
+		if n == 0 {
+			break;
+		}
+	}
+	digits.reverse();
+	digits
+}
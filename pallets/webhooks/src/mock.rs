@@ -0,0 +1,96 @@
+use crate as pallet_webhooks;
+use frame_support::traits::{ConstU16, ConstU32, ConstU64};
+use frame_system as system;
+use parking_lot::RwLock;
+use sp_core::{
+	offchain::{testing, OffchainDbExt, OffchainWorkerExt},
+	H256,
+};
+use sp_runtime::{
+	testing::{Header, TestSignature, UintAuthorityId},
+	traits::{BlakeTwo256, IdentityLookup},
+};
+use std::sync::Arc;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Webhooks: pallet_webhooks,
+	}
+);
+
+impl system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = UintAuthorityId;
+	type Signature = TestSignature;
+}
+
+/// Signs with the `UintAuthorityId` keys set with `UintAuthorityId::set_all_keys`.
+pub struct TestAuthId;
+
+impl frame_system::offchain::AppCrypto<UintAuthorityId, TestSignature> for TestAuthId {
+	type RuntimeAppPublic = UintAuthorityId;
+	type GenericSignature = TestSignature;
+	type GenericPublic = UintAuthorityId;
+}
+
+impl pallet_webhooks::Config for Test {
+	type Event = Event;
+	type AuthorityId = TestAuthId;
+	type MaxUrlLength = ConstU32<64>;
+	type MaxNotifications = ConstU32<2>;
+	type WeightInfo = ();
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
+
+/// The key the offchain worker signs its notifications with.
+pub const NODE_KEY: u64 = 10;
+
+// Build genesis storage with offchain worker extensions sharing the externalities' off-chain
+// database, which offchain indexing writes to, signing with `NODE_KEY`.
+pub fn new_offchain_ext() -> (sp_io::TestExternalities, Arc<RwLock<testing::OffchainState>>) {
+	let mut ext = new_test_ext();
+	let (offchain, offchain_state) = testing::TestOffchainExt::with_offchain_db(ext.offchain_db());
+	UintAuthorityId::set_all_keys(vec![NODE_KEY]);
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+	(ext, offchain_state)
+}
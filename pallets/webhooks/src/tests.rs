@@ -0,0 +1,136 @@
+use crate::{
+	body, hex, mock::*, AsyncOp, Delivery, DeliveryError, Error, Event as WebhooksEvent,
+	Notification, WebhookNotifier,
+};
+use codec::Encode;
+use frame_support::{assert_noop, assert_ok, traits::Hooks};
+use pallet_ocw_jobs::Retry;
+use sp_core::{offchain::testing::PendingRequest, H256};
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	testing::{TestSignature, UintAuthorityId},
+	DispatchError,
+};
+
+const URL: &[u8] = b"https://integrator.example/hooks";
+
+// the deliveries waiting in the node's off-chain storage to be retried
+fn retries() -> Vec<Retry<Delivery<u64, H256, u64>, DeliveryError, u64>> {
+	StorageValueRef::persistent(crate::RETRY_QUEUE)
+		.get()
+		.unwrap()
+		.unwrap_or_default()
+}
+
+#[test]
+fn accounts_register_https_webhooks() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_noop!(
+			Webhooks::register_webhook(Origin::signed(1), b"http://integrator.example".to_vec()),
+			Error::<Test>::InsecureUrl
+		);
+		let mut long = URL.to_vec();
+		long.resize(65, b'a');
+		assert_noop!(
+			Webhooks::register_webhook(Origin::signed(1), long),
+			Error::<Test>::UrlTooLong
+		);
+
+		assert_ok!(Webhooks::register_webhook(Origin::signed(1), URL.to_vec()));
+		let url_hash = sp_io::hashing::blake2_256(URL);
+		assert_eq!(Webhooks::webhook(1), Some(url_hash));
+		System::assert_last_event(Event::Webhooks(WebhooksEvent::WebhookRegistered(1, url_hash)));
+
+		assert_noop!(Webhooks::remove_webhook(Origin::signed(2)), Error::<Test>::NoWebhook);
+		assert_ok!(Webhooks::remove_webhook(Origin::signed(1)));
+		assert_eq!(Webhooks::webhook(1), None);
+		System::assert_last_event(Event::Webhooks(WebhooksEvent::WebhookRemoved(1)));
+	});
+}
+
+#[test]
+fn notifications_are_kept_for_their_block() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Webhooks::register_webhook(Origin::signed(1), URL.to_vec()));
+		// accounts without a webhook aren't notified
+		Webhooks::notify(&2, AsyncOp::MpesaDeposit(0), Ok(H256::zero()));
+		assert!(Webhooks::notifications().is_empty());
+
+		for id in 0..3 {
+			Webhooks::notify(&1, AsyncOp::IpfsRequest(id), Err(DispatchError::Other("")));
+		}
+		assert_eq!(Webhooks::notifications().len(), 2);
+		System::assert_last_event(Event::Webhooks(WebhooksEvent::NotificationDropped(
+			1,
+			AsyncOp::IpfsRequest(2),
+		)));
+
+		Webhooks::on_initialize(2);
+		assert!(Webhooks::notifications().is_empty());
+	});
+}
+
+#[test]
+fn offchain_workers_push_signed_notifications() {
+	let (mut ext, offchain_state) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Webhooks::register_webhook(Origin::signed(1), URL.to_vec()));
+		Webhooks::notify(&1, AsyncOp::MpesaDeposit(0), Ok(H256::repeat_byte(1)));
+	});
+	// the URL reaches the node's off-chain storage once the block is imported
+	ext.persist_offchain_overlay();
+
+	let notification =
+		Notification { who: 1, op: AsyncOp::MpesaDeposit(0), outcome: Ok(H256::repeat_byte(1)) };
+	let body = body(&Delivery { block_number: 1u64, index: 0, notification });
+	let expected = format!(
+		"{{\"account\":\"0x0100000000000000\",\"block\":1,\"index\":0,\"op\":\"mpesa_deposit\",\
+		 \"id\":\"0\",\"status\":\"completed\",\"result\":\"0x{}\"}}",
+		"01".repeat(32)
+	);
+	assert_eq!(String::from_utf8(body.clone()).unwrap(), expected);
+	let signer = hex(&UintAuthorityId(NODE_KEY).encode());
+	let signature = hex(&TestSignature(NODE_KEY, body.clone()).encode());
+	offchain_state.write().expect_request(PendingRequest {
+		method: "POST".into(),
+		uri: String::from_utf8(URL.to_vec()).unwrap(),
+		headers: vec![
+			("Content-Type".into(), "application/json".into()),
+			("X-Webhook-Signer".into(), String::from_utf8(signer).unwrap()),
+			("X-Webhook-Signature".into(), String::from_utf8(signature).unwrap()),
+		],
+		body,
+		response: Some(b"ok".to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	ext.execute_with(|| {
+		Webhooks::offchain_worker(1);
+		assert!(retries().is_empty());
+	});
+}
+
+#[test]
+fn failed_deliveries_are_retried_until_the_webhook_is_removed() {
+	let (mut ext, _) = new_offchain_ext();
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(Webhooks::register_webhook(Origin::signed(1), URL.to_vec()));
+		Webhooks::notify(&1, AsyncOp::IpfsRequest(4), Ok(H256::zero()));
+
+		// the node didn't index the URL, so no request is sent
+		Webhooks::offchain_worker(1);
+		let pending = retries();
+		assert_eq!(pending.len(), 1);
+		assert_eq!((pending[0].job.index, pending[0].attempts, pending[0].retry_at), (0, 1, 3));
+		assert_eq!(pending[0].error, DeliveryError::UnknownUrl);
+
+		assert_ok!(Webhooks::remove_webhook(Origin::signed(1)));
+		Webhooks::offchain_worker(3);
+		assert!(retries().is_empty());
+	});
+}
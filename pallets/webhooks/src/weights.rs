@@ -0,0 +1,54 @@
+// Weights for the webhooks pallet.
+//
+// The pallet has not been benchmarked on reference hardware yet. The values below are estimates
+// from the extrinsics' database accesses; `register_webhook` also hashes and indexes the URL.
+// Replace them with the output of
+//
+//     node-template benchmark pallet --chain dev --pallet pallet_webhooks --extrinsic '*' \
+//         --steps 50 --repeat 20 --output pallets/webhooks/src/weights.rs
+//
+// from a node built with `runtime-benchmarks`.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_webhooks.
+pub trait WeightInfo {
+	fn register_webhook(n: u32) -> Weight;
+	fn remove_webhook() -> Weight;
+}
+
+/// Weights for pallet_webhooks using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn register_webhook(n: u32) -> Weight {
+		(10_000 as Weight)
+			.saturating_add((10 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn remove_webhook() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn register_webhook(n: u32) -> Weight {
+		(10_000 as Weight)
+			.saturating_add((10 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn remove_webhook() -> Weight {
+		(10_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+}
@@ -0,0 +1,220 @@
+//! Traits and types shared by the mpesa liquidity pool and the pallets that plug into it, so that
+//! the pallets implementing them don't depend on the pallets using them, or the other way around.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{DispatchError, DispatchResult, RuntimeDebug};
+use sp_std::vec::Vec;
+
+/// Content-addressed storage the pool publishes its receipt documents to; the IPFS pallet
+/// implements it.
+pub trait IpfsStore<AccountId> {
+	/// Queue `data` to be added to IPFS on behalf of `owner`, returning the CID it is stored
+	/// under.
+	fn add_bytes(owner: &AccountId, data: Vec<u8>) -> Result<Vec<u8>, DispatchError>;
+
+	/// Whether the document with `cid` is registered with an active pin.
+	fn is_pinned(cid: &[u8]) -> bool;
+
+	/// Queue the document with `cid` to be pinned again on behalf of `owner`, e.g. once its pin
+	/// was lost.
+	fn pin(owner: &AccountId, cid: Vec<u8>) -> DispatchResult;
+
+	/// Whether documents can be stored at all; receipts are only generated if they can.
+	fn is_available() -> bool {
+		true
+	}
+}
+
+/// No IPFS store: receipts aren't generated.
+impl<AccountId> IpfsStore<AccountId> for () {
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	fn add_bytes(_: &AccountId, _: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+		Err(DispatchError::Other("no IPFS store"))
+	}
+
+	// lets `submit_receipt` be benchmarked in runtimes without a store, with the document's hash
+	// standing in for its CID
+	#[cfg(feature = "runtime-benchmarks")]
+	fn add_bytes(_: &AccountId, data: Vec<u8>) -> Result<Vec<u8>, DispatchError> {
+		Ok(sp_io::hashing::blake2_256(&data).to_vec())
+	}
+
+	fn is_pinned(_: &[u8]) -> bool {
+		false
+	}
+
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	fn pin(_: &AccountId, _: Vec<u8>) -> DispatchResult {
+		Err(DispatchError::Other("no IPFS store"))
+	}
+
+	// lets the receipts' repair be benchmarked in runtimes without a store
+	#[cfg(feature = "runtime-benchmarks")]
+	fn pin(_: &AccountId, _: Vec<u8>) -> DispatchResult {
+		Ok(())
+	}
+
+	fn is_available() -> bool {
+		false
+	}
+}
+
+/// The liquidity pool the USSD gateway's proxied contributions are paid into; the M-Pesa pool
+/// implements it.
+pub trait LiquidityPool<AccountId, Balance> {
+	/// Contribute `amount` of `who`'s balance to the pool.
+	fn contribute(who: &AccountId, amount: Balance) -> DispatchResult;
+
+	/// Let the fresh account `who` contribute, e.g. by attesting its identity, so that
+	/// contributions can be benchmarked.
+	#[cfg(feature = "runtime-benchmarks")]
+	fn prepare_contributor(_who: &AccountId) {}
+}
+
+/// No liquidity pool: every contribution fails.
+impl<AccountId, Balance> LiquidityPool<AccountId, Balance> for () {
+	fn contribute(_: &AccountId, _: Balance) -> DispatchResult {
+		Err(DispatchError::Other("no liquidity pool"))
+	}
+}
+
+/// The identifier of a promise.
+pub type PromiseId = u64;
+
+/// An asynchronous operation: one the chain starts and learns the outcome of in a later block,
+/// identified the way the pallet running it does.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum AsyncOp<Hash> {
+	/// An IPFS data command, such as an add, by its request id.
+	IpfsRequest(u64),
+	/// An IPFS DHT query, by the hash of the query.
+	DhtQuery(Hash),
+	/// An M-Pesa deposit, by its id.
+	MpesaDeposit(u64),
+	/// An M-Pesa withdrawal, by its id.
+	MpesaWithdrawal(u64),
+}
+
+/// Promises of the outcomes of asynchronous operations, so that clients track all of them the
+/// same way; the promises pallet implements it.
+pub trait PromiseRegistry<Hash> {
+	/// Promise the outcome of `op`, returning the promise's id: the pending one's, if `op`
+	/// already has one.
+	fn create(op: AsyncOp<Hash>) -> Option<PromiseId>;
+
+	/// Resolve the pending promise of `op` with the hash of the operation's result.
+	fn resolve(op: &AsyncOp<Hash>, result: Hash);
+
+	/// Reject the pending promise of `op` with the error the operation failed with.
+	fn reject(op: &AsyncOp<Hash>, error: DispatchError);
+}
+
+/// No promises are kept.
+impl<Hash> PromiseRegistry<Hash> for () {
+	fn create(_: AsyncOp<Hash>) -> Option<PromiseId> {
+		None
+	}
+
+	fn resolve(_: &AsyncOp<Hash>, _: Hash) {}
+
+	fn reject(_: &AsyncOp<Hash>, _: DispatchError) {}
+}
+
+/// Webhooks pushing the outcomes of accounts' asynchronous operations to the accounts' servers;
+/// the webhooks pallet implements it.
+pub trait WebhookNotifier<AccountId, Hash> {
+	/// Notify `who` that `op` completed with the hash of its result, or failed with an error.
+	fn notify(who: &AccountId, op: AsyncOp<Hash>, outcome: Result<Hash, DispatchError>);
+}
+
+/// No webhooks are notified.
+impl<AccountId, Hash> WebhookNotifier<AccountId, Hash> for () {
+	fn notify(_: &AccountId, _: AsyncOp<Hash>, _: Result<Hash, DispatchError>) {}
+}
+
+/// The most items an indexer page holds.
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// The most entries a page examines, so that a runtime API call stays cheap however few of the
+/// entries a filter keeps.
+pub const MAX_PAGE_SCAN: u32 = 1_000;
+
+/// A request for a page of one of the views indexers read the chain's records through.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PageRequest {
+	/// The `next` cursor of the previous page, unchanged, or `None` for the first page.
+	pub cursor: Option<Vec<u8>>,
+	/// The most items to return, between 1 and `MAX_PAGE_SIZE`.
+	pub limit: u32,
+}
+
+/// A page of a view: the items, and the cursor of the next page.
+///
+/// A page may hold fewer than `limit` items, even none, while entries are left to examine; the
+/// view is exhausted only once `next` is `None`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Page<Item> {
+	/// The items matching the filter, in the view's order.
+	pub items: Vec<Item>,
+	/// The cursor to request the next page with.
+	pub next: Option<Vec<u8>>,
+}
+
+/// Narrows a view to the entries matching every field that is set.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct IndexFilter<AccountId, BlockNumber> {
+	/// The account the entries belong to.
+	pub account: Option<AccountId>,
+	/// The `Cid` the entries refer to; entries without one never match.
+	pub cid: Option<Vec<u8>>,
+	/// The first block the entries may date from.
+	pub from: Option<BlockNumber>,
+	/// The last block the entries may date from.
+	pub to: Option<BlockNumber>,
+}
+
+impl<AccountId, BlockNumber> Default for IndexFilter<AccountId, BlockNumber> {
+	fn default() -> Self {
+		IndexFilter { account: None, cid: None, from: None, to: None }
+	}
+}
+
+impl<AccountId: PartialEq, BlockNumber: PartialOrd> IndexFilter<AccountId, BlockNumber> {
+	/// Whether an entry of `account`, referring to `cid` and dating from `block` matches.
+	pub fn matches(&self, account: &AccountId, cid: Option<&[u8]>, block: &BlockNumber) -> bool {
+		self.account.as_ref().map_or(true, |a| a == account)
+			&& self.cid.as_ref().map_or(true, |c| cid == Some(&c[..]))
+			&& self.from.as_ref().map_or(true, |from| block >= from)
+			&& self.to.as_ref().map_or(true, |to| block <= to)
+	}
+}
+
+/// Page through `entries`, keeping the items `item` maps them to, and resuming after the last
+/// entry examined with the cursor `cursor` makes of it.
+pub fn collect_page<Entry, Item>(
+	entries: impl IntoIterator<Item = Entry>,
+	request: &PageRequest,
+	mut item: impl FnMut(Entry) -> Option<Item>,
+	cursor: impl Fn(&Entry) -> Vec<u8>,
+) -> Page<Item> {
+	let limit = request.limit.clamp(1, MAX_PAGE_SIZE) as usize;
+	let mut entries = entries.into_iter();
+	let mut items = Vec::new();
+	let mut next = None;
+	for _ in 0..MAX_PAGE_SCAN {
+		if items.len() == limit {
+			break;
+		}
+		match entries.next() {
+			Some(entry) => {
+				next = Some(cursor(&entry));
+				items.extend(item(entry));
+			},
+			None => return Page { items, next: None },
+		}
+	}
+	Page { items, next }
+}
@@ -0,0 +1,45 @@
+//! Display hints for wallets.
+//!
+//! Wallets need to know how to render raw balances. The native token's values are published in
+//! the chain spec properties (which polkadot-js reads), and those of every asset, including the
+//! M-Pesa pool's token, through [`DisplayApi`] for clients that prefer to ask the runtime.
+
+use codec::{Decode, Encode};
+use pallet_mpesa_user::{POOL_TOKEN_DECIMALS, POOL_TOKEN_SYMBOL};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::{vec, vec::Vec};
+
+/// Ticker of the native token.
+pub const TOKEN_SYMBOL: &str = "UNIT";
+
+/// Number of decimal places of the native token.
+pub const TOKEN_DECIMALS: u8 = 12;
+
+/// How an asset's balances should be presented to users.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct AssetDisplay {
+	/// The asset's ticker, e.g. `UNIT`.
+	pub symbol: Vec<u8>,
+	/// The number of decimal places a raw balance has to be shifted by.
+	pub decimals: u8,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API exposing display hints for the assets known to the runtime.
+	pub trait DisplayApi {
+		/// Display hints for every asset, native token first and the M-Pesa pool's token second.
+		fn assets() -> Vec<AssetDisplay>;
+	}
+}
+
+/// The display hints for every asset known to the runtime.
+pub fn assets() -> Vec<AssetDisplay> {
+	vec![
+		AssetDisplay { symbol: TOKEN_SYMBOL.as_bytes().to_vec(), decimals: TOKEN_DECIMALS },
+		AssetDisplay {
+			symbol: POOL_TOKEN_SYMBOL.as_bytes().to_vec(),
+			decimals: POOL_TOKEN_DECIMALS,
+		},
+	]
+}
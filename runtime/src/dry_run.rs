@@ -0,0 +1,94 @@
+//! Dry-run support for extrinsics.
+//!
+//! Runtime API calls execute on a temporary storage overlay that is thrown away afterwards, so a
+//! call can be applied for real and its events and errors collected without touching chain
+//! state. The node exposes this through the `chain_dryRun` RPC.
+//!
+//! The call is applied like a signed extrinsic of `origin` with its current nonce and no tip, so
+//! the signed extensions reject it, or charge its fee, as they would the real one. Only the
+//! signature isn't checked, and the length fee is charged for an estimated signature size.
+
+use codec::{Codec, Decode, Encode};
+use frame_support::weights::GetDispatchInfo;
+use scale_info::TypeInfo;
+use sp_runtime::{
+	generic::{CheckedExtrinsic, Era},
+	traits::Applyable,
+	transaction_validity::TransactionValidityError,
+	DispatchError, ModuleError, RuntimeDebug,
+};
+use sp_std::vec::Vec;
+
+use crate::{AccountId, Call, Event, Runtime, SignedExtra, System};
+
+/// The bytes a signature adds to an extrinsic at most: the version, the address, the sr25519
+/// signature and the immortal era, nonce and tip of the signed extensions.
+const SIGNATURE_LEN: usize = 1 + 33 + 65 + 1 + 5 + 1;
+
+/// The outcome of dispatching a call against a throw-away overlay.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct DryRunOutcome<Event> {
+	/// A readable description of the dispatch error, if the call would fail, e.g.
+	/// `MinimumLPShareAmountNotMet`.
+	pub error: Option<Vec<u8>>,
+	/// The events the call would emit, in order.
+	pub events: Vec<Event>,
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to dispatch a call without committing any of its effects.
+	pub trait DryRunApi<AccountId, Call, Event> where
+		AccountId: Codec,
+		Call: Codec,
+		Event: Codec,
+	{
+		/// Apply `call` as an extrinsic signed by `origin`, through the signed extensions but
+		/// without a signature check, and report what would happen.
+		fn dry_run(origin: AccountId, call: Call) -> DryRunOutcome<Event>;
+	}
+}
+
+/// Apply `call` as an extrinsic of `origin` and collect the resulting events and error.
+///
+/// Must only be called from a runtime API context, where all storage changes are discarded.
+pub fn dry_run(origin: AccountId, call: Call) -> DryRunOutcome<Event> {
+	System::reset_events();
+
+	let info = call.get_dispatch_info();
+	let len = call.encoded_size() + SIGNATURE_LEN;
+	let nonce = System::account_nonce(&origin);
+	let extra: SignedExtra = (
+		frame_system::CheckNonZeroSender::new(),
+		frame_system::CheckSpecVersion::new(),
+		frame_system::CheckTxVersion::new(),
+		frame_system::CheckGenesis::new(),
+		frame_system::CheckEra::from(Era::Immortal),
+		frame_system::CheckNonce::from(nonce),
+		frame_system::CheckWeight::new(),
+		pallet_transaction_payment::ChargeTransactionPayment::from(0),
+		pallet_ipfs::CheckFeatureFlags::new(),
+		pallet_ipfs::CheckBlockData::new(),
+	);
+	let xt = CheckedExtrinsic { signed: Some((origin, extra)), function: call };
+
+	let error = match xt.apply::<Runtime>(&info, len) {
+		Ok(Ok(_)) => None,
+		Ok(Err(e)) => Some(describe_error(e.error)),
+		Err(e) => Some(describe_invalid(e)),
+	};
+	let events = System::read_events_no_consensus().into_iter().map(|r| r.event).collect();
+
+	DryRunOutcome { error, events }
+}
+
+fn describe_error(error: DispatchError) -> Vec<u8> {
+	match error {
+		DispatchError::Module(ModuleError { message: Some(message), .. }) => message.into(),
+		other => <&'static str>::from(other).into(),
+	}
+}
+
+// e.g. `Inability to pay some fees (e.g. account balance too low)`
+fn describe_invalid(error: TransactionValidityError) -> Vec<u8> {
+	<&'static str>::from(error).into()
+}
@@ -0,0 +1,39 @@
+//! Paginated views for indexers.
+//!
+//! SubQuery and Subsquid projects would otherwise have to decode the pallets' hashed storage keys
+//! to ingest the registry, the pool's settlements and its providers' positions. [`IndexerApi`]
+//! pages through each of them instead, filtered by account, `Cid` and block range, returning
+//! typed items and an opaque cursor for the next page.
+
+use codec::Codec;
+use pallet_ipfs::ContentInfo;
+use sp_std::vec::Vec;
+
+pub use mpesa_primitives::{IndexFilter, Page, PageRequest};
+pub use pallet_mpesa_user::runtime_api::{PoolPosition, Settlement};
+
+sp_api::decl_runtime_apis! {
+	/// API to page through the chain's records for indexers.
+	pub trait IndexerApi<AccountId, BlockNumber, Balance> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+		Balance: Codec,
+	{
+		/// A page of the IPFS registry's entries, keyed by `Cid` and dated by the block they
+		/// were registered in.
+		fn registry_entries(
+			filter: IndexFilter<AccountId, BlockNumber>,
+			request: PageRequest,
+		) -> Page<(Vec<u8>, ContentInfo<AccountId, BlockNumber, Balance>)>;
+		/// A page of the M-Pesa deposits and withdrawals, referring to their receipt's `Cid`.
+		fn settlements(
+			filter: IndexFilter<AccountId, BlockNumber>,
+			request: PageRequest,
+		) -> Page<Settlement<AccountId, BlockNumber>>;
+		/// A page of the liquidity providers' positions, dated by the block they joined in.
+		fn pool_positions(
+			filter: IndexFilter<AccountId, BlockNumber>,
+			request: PageRequest,
+		) -> Page<PoolPosition<AccountId, BlockNumber, Balance>>;
+	}
+}
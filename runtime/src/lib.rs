@@ -6,6 +6,7 @@
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
+use codec::Encode;
 use pallet_grandpa::{
 	fg_primitives, AuthorityId as GrandpaId, AuthorityList as GrandpaAuthorityList,
 };
@@ -14,8 +15,11 @@ use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	traits::{
+		self, AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount,
+		NumberFor, SaturatedConversion, Verify,
+	},
+	transaction_validity::{TransactionPriority, TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
 use sp_std::prelude::*;
@@ -27,15 +31,17 @@ use sp_version::RuntimeVersion;
 pub use frame_support::{
 	construct_runtime, parameter_types,
 	traits::{
-		ConstU128, ConstU32, ConstU64, ConstU8, KeyOwnerProofSystem, Randomness, StorageInfo,
+		AsEnsureOriginWithArg, ConstBool, ConstU128, ConstU32, ConstU64, ConstU8, Contains,
+		KeyOwnerProofSystem, Randomness, StorageInfo,
 	},
 	weights::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 pub use frame_system::Call as SystemCall;
+use frame_system::EnsureRoot;
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::CurrencyAdapter;
@@ -43,8 +49,18 @@ use pallet_transaction_payment::CurrencyAdapter;
 pub use sp_runtime::BuildStorage;
 pub use sp_runtime::{Perbill, Permill};
 
+/// Import the IPFS pallet, which mpesa-user publishes its receipts through.
+pub use pallet_ipfs;
+/// Import the mpesa-user pallet, whose runtime API the node calls.
+pub use pallet_mpesa_user;
 /// Import the template pallet.
 pub use pallet_template;
+/// Import the webhooks pallet, whose key type the node watches.
+pub use pallet_webhooks;
+
+pub mod display;
+pub mod dry_run;
+pub mod indexer;
 
 /// An index to a block.
 pub type BlockNumber = u32;
@@ -101,10 +117,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 142,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 22,
 	state_version: 1,
 };
 
@@ -270,9 +286,212 @@ impl pallet_template::Config for Runtime {
 	type Event = Event;
 }
 
+impl frame_system::offchain::SigningTypes for Runtime {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	Call: From<C>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = UncheckedExtrinsic;
+}
+
+// Lets the IPFS offchain workers sign the transactions reporting their results.
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		public: <Signature as Verify>::Signer,
+		account: AccountId,
+		nonce: Index,
+	) -> Option<(Call, <UncheckedExtrinsic as traits::Extrinsic>::SignaturePayload)> {
+		let period =
+			BlockHashCount::get().checked_next_power_of_two().map(|c| c / 2).unwrap_or(2) as u64;
+		// offchain workers see `System::block_number` initialized to `n + 1` for block `n`
+		let current_block = System::block_number().saturated_into::<u64>().saturating_sub(1);
+		let extra: SignedExtra = (
+			frame_system::CheckNonZeroSender::<Runtime>::new(),
+			frame_system::CheckSpecVersion::<Runtime>::new(),
+			frame_system::CheckTxVersion::<Runtime>::new(),
+			frame_system::CheckGenesis::<Runtime>::new(),
+			frame_system::CheckEra::<Runtime>::from(generic::Era::mortal(period, current_block)),
+			frame_system::CheckNonce::<Runtime>::from(nonce),
+			frame_system::CheckWeight::<Runtime>::new(),
+			pallet_transaction_payment::ChargeTransactionPayment::<Runtime>::from(0),
+			pallet_ipfs::CheckFeatureFlags::<Runtime>::new(),
+			pallet_ipfs::CheckBlockData::<Runtime>::new(),
+		);
+		let raw_payload = SignedPayload::new(call, extra).ok()?;
+		let signature = raw_payload.using_encoded(|payload| C::sign(payload, public))?;
+		let (call, extra, _) = raw_payload.deconstruct();
+		Some((call, (Address::Id(account), signature, extra)))
+	}
+}
+
+parameter_types! {
+	pub const MpesaPalletId: PalletId = PalletId(*b"py/mpesa");
+	pub TreasuryAccount: AccountId = PalletId(*b"py/trsry").into_account_truncating();
+}
+
 /// impl mpesa_user_pallet
 impl pallet_mpesa_user::Config for Runtime {
 	type Event = Event;
+	type Currency = Balances;
+	type PalletId = MpesaPalletId;
+	type AuthorityId = pallet_mpesa_user::crypto::OcwAuthId;
+	type MaxPendingDeposits = ConstU32<64>;
+	type MaxPendingWithdrawals = ConstU32<64>;
+	type MaxWorkers = ConstU32<8>;
+	type UnsignedPriority = ConstU64<{ TransactionPriority::max_value() / 2 }>;
+	type OracleOrigin = EnsureRoot<AccountId>;
+	type VerifierOrigin = EnsureRoot<AccountId>;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type MinContributionLevel = ConstU8<1>;
+	type MinWithdrawalLevel = ConstU8<2>;
+	type BlocksPerDay = ConstU32<DAYS>;
+	type RateInterval = ConstU32<{ 10 * MINUTES }>;
+	type MaxRateAge = ConstU32<HOURS>;
+	type TreasuryAccount = TreasuryAccount;
+	type DisputeOrigin = EnsureRoot<AccountId>;
+	type EscrowTimeout = ConstU32<DAYS>;
+	type MaxEscrows = ConstU32<1_024>;
+	type MaxHistory = ConstU32<100>;
+	type HistoryArchiveBatch = ConstU32<50>;
+	type ReceiptStore = Ipfs;
+	type ReceiptPinGrace = ConstU32<{ 10 * MINUTES }>;
+	type Promises = Promises;
+	type Webhooks = Webhooks;
+	type WeightInfo = pallet_mpesa_user::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	pub const UssdPalletId: PalletId = PalletId(*b"py/ussdg");
+}
+
+/// Signs benchmarked intents with a fresh sr25519 key from the benchmark's keystore.
+#[cfg(feature = "runtime-benchmarks")]
+pub struct UssdBenchmarkHelper;
+
+#[cfg(feature = "runtime-benchmarks")]
+impl pallet_ussd_gateway::BenchmarkHelper<AccountId, Signature> for UssdBenchmarkHelper {
+	fn sign(message: &[u8]) -> (AccountId, Signature) {
+		const KEY_TYPE: KeyTypeId = KeyTypeId(*b"ussd");
+		let key = sp_io::crypto::sr25519_generate(KEY_TYPE, None);
+		let signature = sp_io::crypto::sr25519_sign(KEY_TYPE, &key, message)
+			.expect("the key was just generated; qed");
+		(sp_runtime::MultiSigner::from(key).into_account(), signature.into())
+	}
+}
+
+/// Configure the USSD gateway pallet, whose phones contribute to the M-Pesa pool.
+impl pallet_ussd_gateway::Config for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type PalletId = UssdPalletId;
+	type Signer = <Signature as Verify>::Signer;
+	type Signature = Signature;
+	type Pool = MpesaUser;
+	type GatewayOrigin = EnsureRoot<AccountId>;
+	type FraudOrigin = EnsureRoot<AccountId>;
+	type GatewayBond = ConstU128<{ 1_000 * EXISTENTIAL_DEPOSIT }>;
+	type DisputePeriod = ConstU32<{ 7 * DAYS }>;
+	type WeightInfo = pallet_ussd_gateway::weights::SubstrateWeight<Runtime>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type BenchmarkHelper = UssdBenchmarkHelper;
+}
+
+parameter_types! {
+	/// The HTTP RPC API of the Kubo daemon each validator runs next to its node.
+	pub const IpfsApiEndpoint: &'static str = "http://127.0.0.1:5001";
+	pub const IpfsPinPolicy: pallet_ipfs::PinMode = pallet_ipfs::PinMode::Recursive;
+	/// Owns the content of the adds queued before the pallet recorded who queued them.
+	pub IpfsLegacyOwner: AccountId = PalletId(*b"py/ipfs_").into_account_truncating();
+	/// Publishes the archives of the incidents pruned from the recent ones.
+	pub IpfsArchiveAccount: AccountId = PalletId(*b"py/ipfsa").into_account_truncating();
+}
+
+/// The accounts publishing protocol documents to IPFS without deposits or quotas.
+pub struct IpfsProtocolAccounts;
+
+impl Contains<AccountId> for IpfsProtocolAccounts {
+	fn contains(who: &AccountId) -> bool {
+		*who == MpesaUser::receipts_account() || *who == IpfsArchiveAccount::get()
+	}
+}
+
+/// Configure the IPFS pallet, driving the Kubo daemons of the validators.
+impl pallet_ipfs::Config for Runtime {
+	type AuthorityId = pallet_ipfs::crypto::OcwAuthId;
+	type Event = Event;
+	type Currency = Balances;
+	type ByteDeposit = ConstU128<{ EXISTENTIAL_DEPOSIT / 100 }>;
+	type ProtocolAccounts = IpfsProtocolAccounts;
+	type ArchiveAccount = IpfsArchiveAccount;
+	type ContentStore = pallet_ipfs::Kubo<IpfsApiEndpoint>;
+	type MaxQueueLen = ConstU32<256>;
+	type MaxBatchLen = ConstU32<16>;
+	type RequestTimeout = ConstU64<5_000>;
+	type MaxRequestTimeout = ConstU64<60_000>;
+	type FastDeadline = ConstU64<2_000>;
+	type BackgroundDeadline = ConstU64<30_000>;
+	type MaxRetries = ConstU32<3>;
+	type ReprovideInterval = ConstU32<{ 12 * HOURS }>;
+	type MaxRepoSize = ConstU64<{ 10 * 1024 * 1024 * 1024 }>;
+	type MaxAccessTtl = ConstU32<{ 30 * DAYS }>;
+	type ReplicationFactor = ConstU32<3>;
+	type AttestationInterval = ConstU32<HOURS>;
+	type DefaultPinPolicy = IpfsPinPolicy;
+	type ResultTtl = ConstU32<{ 10 * MINUTES }>;
+	type UnsignedPriority = ConstU64<{ TransactionPriority::max_value() / 2 }>;
+	type UnsignedLongevity = ConstU32<5>;
+	type ValidateSchemas = ConstBool<false>;
+	type EraLength = ConstU32<DAYS>;
+	type MaxBytesPerAccountPerEra = ConstU64<{ 100 * 1024 * 1024 }>;
+	type MaxCommandsPerBlock = ConstU32<10>;
+	type MaxSchedulesPerAccount = ConstU32<8>;
+	type MaxBlockDataLen = ConstU32<{ 1024 * 1024 }>;
+	type LeaseDepositPerBlock = ConstU128<1>;
+	type BumpFee = ConstU128<{ 10 * EXISTENTIAL_DEPOSIT }>;
+	type DataBudget = ConstU32<16>;
+	type WorkersPerCommand = ConstU32<1>;
+	type ClaimGracePeriod = ConstU32<5>;
+	type AdminOrigin = EnsureRoot<AccountId>;
+	type ModerationOrigin = EnsureRoot<AccountId>;
+	type AppealOrigin = EnsureRoot<AccountId>;
+	type NamespaceAdminOrigin = AsEnsureOriginWithArg<EnsureRoot<AccountId>>;
+	type AppealDeposit = ConstU128<{ 100 * EXISTENTIAL_DEPOSIT }>;
+	type AppealPeriod = ConstU32<{ 7 * DAYS }>;
+	type Randomness = RandomnessCollectiveFlip;
+	type StorageBond = ConstU128<{ 1_000 * EXISTENTIAL_DEPOSIT }>;
+	type ChallengeSlash = ConstU128<{ 100 * EXISTENTIAL_DEPOSIT }>;
+	type ChallengesPerEra = ConstU32<4>;
+	type ChallengePeriod = ConstU32<{ 10 * MINUTES }>;
+	// not benchmarked yet; see ipfs/src/weights.rs
+	type WeightInfo = ();
+	type OnResult = ();
+	type Promises = Promises;
+	type Webhooks = Webhooks;
+}
+
+/// Configure the promises pallet, tracking the IPFS and M-Pesa operations' outcomes.
+impl pallet_promises::Config for Runtime {
+	type Event = Event;
+	type SettledTtl = ConstU32<{ 10 * MINUTES }>;
+}
+
+/// Configure the webhooks pallet, pushing the IPFS and M-Pesa operations' outcomes to the
+/// accounts' servers.
+impl pallet_webhooks::Config for Runtime {
+	type Event = Event;
+	type AuthorityId = pallet_webhooks::crypto::OcwAuthId;
+	type MaxUrlLength = ConstU32<256>;
+	type MaxNotifications = ConstU32<256>;
+	type WeightInfo = pallet_webhooks::weights::SubstrateWeight<Runtime>;
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -293,7 +512,11 @@ construct_runtime!(
 		Sudo: pallet_sudo,
 		// Include the custom logic from the pallet-template in the runtime.
 		TemplateModule: pallet_template,
-		MpesaUser: pallet_mpesa_user::{Pallet, Call, Storage, Event<T>},
+		MpesaUser: pallet_mpesa_user::{Pallet, Call, Storage, Config<T>, Event<T>, ValidateUnsigned},
+		UssdGateway: pallet_ussd_gateway,
+		Ipfs: pallet_ipfs,
+		Promises: pallet_promises,
+		Webhooks: pallet_webhooks,
 	}
 );
 
@@ -313,6 +536,8 @@ pub type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_ipfs::CheckFeatureFlags<Runtime>,
+	pallet_ipfs::CheckBlockData<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -325,6 +550,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	pallet_ipfs::migrations::v1::MigrateToV1<Runtime, IpfsLegacyOwner>,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -340,6 +566,9 @@ mod benches {
 		[pallet_timestamp, Timestamp]
 		[pallet_template, TemplateModule]
 		[pallet_mpesa_user, MpesaUser]
+		[pallet_ussd_gateway, UssdGateway]
+		[pallet_ipfs, Ipfs]
+		[pallet_webhooks, Webhooks]
 	);
 }
 
@@ -474,6 +703,79 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl display::DisplayApi<Block> for Runtime {
+		fn assets() -> Vec<display::AssetDisplay> {
+			display::assets()
+		}
+	}
+
+	impl dry_run::DryRunApi<Block, AccountId, Call, Event> for Runtime {
+		fn dry_run(origin: AccountId, call: Call) -> dry_run::DryRunOutcome<Event> {
+			dry_run::dry_run(origin, call)
+		}
+	}
+
+	impl indexer::IndexerApi<Block, AccountId, BlockNumber, Balance> for Runtime {
+		fn registry_entries(
+			filter: indexer::IndexFilter<AccountId, BlockNumber>,
+			request: indexer::PageRequest,
+		) -> indexer::Page<(Vec<u8>, pallet_ipfs::ContentInfo<AccountId, BlockNumber, Balance>)> {
+			Ipfs::registry_page(&filter, &request)
+		}
+
+		fn settlements(
+			filter: indexer::IndexFilter<AccountId, BlockNumber>,
+			request: indexer::PageRequest,
+		) -> indexer::Page<indexer::Settlement<AccountId, BlockNumber>> {
+			MpesaUser::settlements_page(&filter, &request)
+		}
+
+		fn pool_positions(
+			filter: indexer::IndexFilter<AccountId, BlockNumber>,
+			request: indexer::PageRequest,
+		) -> indexer::Page<indexer::PoolPosition<AccountId, BlockNumber, Balance>> {
+			MpesaUser::positions_page(&filter, &request)
+		}
+	}
+
+	impl pallet_mpesa_user::runtime_api::MpesaUserApi<Block, AccountId, BlockNumber> for Runtime {
+		fn account_history(
+			account: AccountId,
+		) -> Vec<pallet_mpesa_user::TransactionRecord<AccountId, BlockNumber>> {
+			MpesaUser::history(account).into_inner()
+		}
+	}
+
+	impl pallet_ipfs::runtime_api::IpfsApi<Block, AccountId, BlockNumber, Balance, Hash> for Runtime {
+		fn pending_commands() -> pallet_ipfs::PendingCommands<AccountId> {
+			Ipfs::pending_commands()
+		}
+
+		fn content_info(
+			cid: Vec<u8>,
+		) -> Option<pallet_ipfs::ContentInfo<AccountId, BlockNumber, Balance>> {
+			Ipfs::content(cid)
+		}
+
+		fn peer_list() -> Vec<sp_core::offchain::OpaqueMultiaddr> {
+			Ipfs::peer_list()
+		}
+
+		fn request_status(
+			request: pallet_ipfs::RequestId,
+		) -> Option<pallet_ipfs::RequestStatus<Hash>> {
+			Ipfs::request_status(request)
+		}
+	}
+
+	impl pallet_promises::runtime_api::PromisesApi<Block, Hash, BlockNumber> for Runtime {
+		fn promises(
+			ids: Vec<pallet_promises::PromiseId>,
+		) -> Vec<Option<pallet_promises::Promise<Hash, BlockNumber>>> {
+			Promises::promises(ids)
+		}
+	}
+
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentCallApi<Block, Balance, Call>
 		for Runtime
 	{
@@ -549,11 +851,14 @@ impl_runtime_apis! {
 			// have a backtrace here. If any of the pre/post migration checks fail, we shall stop
 			// right here and right now.
 			let weight = Executive::try_runtime_upgrade().unwrap();
+			MpesaUser::do_try_state().expect("M-Pesa receipts are published and pinned");
 			(weight, BlockWeights::get().max_block)
 		}
 
 		fn execute_block_no_check(block: Block) -> Weight {
-			Executive::execute_block_no_check(block)
+			let weight = Executive::execute_block_no_check(block);
+			MpesaUser::do_try_state().expect("M-Pesa receipts are published and pinned");
+			weight
 		}
 	}
 }